@@ -1,4 +1,4 @@
-#import bevy_ecs_tilemap::common::process_fragment
+#import bevy_ecs_tilemap::common::{process_fragment, tile_position}
 #import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
 #import bevy_sprite::mesh2d_view_bindings::globals
 
@@ -20,7 +20,9 @@ fn hsv2rgb(c: vec3<f32>) -> vec3<f32>
 @fragment
 fn fragment(in: MeshVertexOutput) -> @location(0) vec4<f32> {
     let color = process_fragment(in);
-    
-    let hsv = vec3(abs(sin(globals.time)), 1.0, 1.0);
+
+    // The hue cycles over time, offset for each tile so that waves of color cross the map.
+    let position = vec2<f32>(tile_position(in));
+    let hsv = vec3(abs(sin(globals.time - (position.x + position.y) * 0.1)), 1.0, 1.0);
     return vec4((color.rgb + hsv2rgb(hsv)) * material.brightness, color.a);
-}
\ No newline at end of file
+}
@@ -0,0 +1,112 @@
+//! Compares the ways of spawning the tiles of a large tilemap, and of restoring them from a
+//! snapshot.
+//!
+//! Run it with `cargo bench --bench fill`. The parallel fill spreads the spawning over the
+//! `ComputeTaskPool`, so its lead over the single-threaded fill grows with the number of cores.
+
+use std::time::{Duration, Instant};
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::ecs::world::CommandQueue;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::chunk_snapshot::{restore_chunk, snapshot_chunk};
+use bevy_ecs_tilemap::helpers::filling::{fill_tilemap, par_fill_tilemap};
+use bevy_ecs_tilemap::prelude::*;
+
+const MAP_SIZE: TilemapSize = TilemapSize { x: 1024, y: 1024 };
+const RUNS: usize = 5;
+
+/// Returns the fastest of a few runs of `run`, each on a new world with an empty tilemap which
+/// `setup` prepared first.
+fn measure<T>(
+    setup: impl Fn(&mut World, Entity) -> T,
+    run: impl Fn(&mut World, Entity, T),
+) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut world = World::new();
+            let tilemap = world.spawn(TileStorage::empty(MAP_SIZE)).id();
+            let input = setup(&mut world, tilemap);
+            let start = Instant::now();
+            run(&mut world, tilemap, input);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn fill(world: &mut World, tilemap: Entity) {
+    let mut tile_storage = TileStorage::empty(MAP_SIZE);
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    fill_tilemap(
+        TileTextureIndex(0),
+        MAP_SIZE,
+        TilemapId(tilemap),
+        &mut commands,
+        &mut tile_storage,
+    );
+    queue.apply(world);
+    world.entity_mut(tilemap).insert(tile_storage);
+}
+
+fn par_fill(world: &mut World, tilemap: Entity) {
+    world
+        .run_system_once(
+            move |par_commands: ParallelCommands, mut storages: Query<&mut TileStorage>| {
+                par_fill_tilemap(
+                    TileTextureIndex(0),
+                    TilemapId(tilemap),
+                    &par_commands,
+                    &mut storages.get_mut(tilemap).unwrap(),
+                );
+            },
+        )
+        .unwrap();
+}
+
+fn batch_fill(world: &mut World, tilemap: Entity) {
+    let tile_storage = TileStorage::filled(MAP_SIZE, TilemapId(tilemap), world, |_| {
+        Some(TileBundle::default())
+    });
+    world.entity_mut(tilemap).insert(tile_storage);
+}
+
+fn main() {
+    let tiles = MAP_SIZE.count();
+    println!("Spawning {tiles} tiles, fastest of {RUNS} runs:");
+    let results = [
+        (
+            "fill_tilemap",
+            measure(|_, _| (), |world, tilemap, _| fill(world, tilemap)),
+        ),
+        (
+            "par_fill_tilemap",
+            measure(|_, _| (), |world, tilemap, _| par_fill(world, tilemap)),
+        ),
+        (
+            "TileStorage::filled",
+            measure(|_, _| (), |world, tilemap, _| batch_fill(world, tilemap)),
+        ),
+        (
+            "restore_chunk",
+            measure(
+                |world, tilemap| {
+                    batch_fill(world, tilemap);
+                    let chunk_size = UVec2::new(MAP_SIZE.x, MAP_SIZE.y);
+                    snapshot_chunk(world, tilemap, UVec2::ZERO, chunk_size).unwrap()
+                },
+                |world, tilemap, snapshot| restore_chunk(world, tilemap, &snapshot),
+            ),
+        ),
+    ];
+
+    let serial = results[0].1;
+    for (name, duration) in results {
+        println!(
+            "{name:<20} {:>8.1} ms {:>6.2}x",
+            duration.as_secs_f64() * 1000.0,
+            serial.as_secs_f64() / duration.as_secs_f64()
+        );
+    }
+}
@@ -6,18 +6,15 @@ mod helpers;
 fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
 
-    let map_handle = helpers::tiled::TiledMapHandle(asset_server.load("iso_map.tmx"));
-
-    commands.spawn(helpers::tiled::TiledMapBundle {
-        tiled_map: map_handle,
-        render_settings: TilemapRenderSettings {
+    commands.spawn((
+        TiledMapHandle(asset_server.load("iso_map.tmx")),
+        TilemapRenderSettings {
             // Map size is 12x12 so we'll have render chunks that are:
             // 12 tiles wide and 1 tile tall.
             render_chunk_size: UVec2::new(3, 1),
             y_sort: true,
         },
-        ..Default::default()
-    });
+    ));
 }
 
 fn main() {
@@ -33,7 +30,7 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_plugins((TilemapPlugin, helpers::tiled::TiledMapPlugin))
+        .add_plugins((TilemapPlugin, TiledMapPlugin))
         .add_systems(Startup, startup)
         .add_systems(Update, helpers::camera::movement)
         .run();
@@ -15,19 +15,23 @@ fn startup(
     let texture_handle: Handle<Image> = asset_server.load("tiles.png");
 
     let map_size = TilemapSize { x: 32, y: 32 };
+    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
 
-    // Create a tilemap entity a little early.
+    // Spawn the tilemap entity first.
     // We want this entity early because we need to tell each tile which tilemap entity
     // it is associated with. This is done with the TilemapId component on each tile.
-    // Eventually, we will insert the `TilemapBundle` bundle on the entity, which
-    // will contain various necessary components, such as `TileStorage`.
-    let tilemap_entity = commands.spawn_empty().id();
-
-    // To begin creating the map we will need a `TileStorage` component.
+    //
+    // The builder also hands us an empty `TileStorage`.
     // This component is a grid of tile entities and is used to help keep track of individual
     // tiles in the world. If you have multiple layers of tiles you would have a tilemap entity
     // per layer, each with their own `TileStorage` component.
-    let mut tile_storage = TileStorage::empty(map_size);
+    let (tilemap_entity, mut tile_storage) = TilemapBuilder::new()
+        .size(map_size)
+        .tile_size(tile_size)
+        .map_type(TilemapType::default())
+        .texture(TilemapTexture::Single(texture_handle))
+        .anchor(TilemapAnchor::Center)
+        .spawn(&mut commands);
 
     // Spawn the elements of the tilemap.
     // Alternatively, you can use helpers::filling::fill_tilemap.
@@ -45,20 +49,8 @@ fn startup(
         }
     }
 
-    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
-    let grid_size = tile_size.into();
-    let map_type = TilemapType::default();
-
-    commands.entity(tilemap_entity).insert(TilemapBundle {
-        grid_size,
-        map_type,
-        size: map_size,
-        storage: tile_storage,
-        texture: TilemapTexture::Single(texture_handle),
-        tile_size,
-        anchor: TilemapAnchor::Center,
-        ..Default::default()
-    });
+    // Once it is filled, the storage replaces the empty one the tilemap was spawned with.
+    commands.entity(tilemap_entity).insert(tile_storage);
 
     // Add atlas to array texture loader so it's preprocessed before we need to use it.
     // Only used when the atlas feature is off and we are using array textures.
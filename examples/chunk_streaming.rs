@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::chunked::{
+    ChunkLoader, ChunkStreamingPlugin, ChunkTiles, ChunkedTilemap,
+};
+use bevy_ecs_tilemap::helpers::streaming::{ChunkSpawnQueue, heading_priority};
+use bevy_ecs_tilemap::prelude::*;
+mod helpers;
+
+// Press WASD to move the camera around. The chunks around the camera are generated on demand,
+// and the ones left behind are despawned.
+
+const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 16.0, y: 16.0 };
+const CHUNK_SIZE: UVec2 = UVec2 { x: 8, y: 8 };
+
+// Gives each chunk a texture picked from its index, so the chunk boundaries are visible.
+fn generate_chunk(chunk: IVec2, tiles: &mut ChunkTiles) {
+    let texture_index = (chunk.x + chunk.y).rem_euclid(6) as u32;
+    tiles.fill(TileBundle {
+        texture_index: TileTextureIndex(texture_index),
+        ..Default::default()
+    });
+}
+
+fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((Camera2d, ChunkLoader { radius: 2 }));
+
+    let mut chunked_tilemap = ChunkedTilemap::new(
+        generate_chunk,
+        CHUNK_SIZE,
+        TILE_SIZE,
+        TilemapTexture::Single(asset_server.load("tiles.png")),
+    );
+    // Spawn up to 2 chunks per frame, favoring the ones the camera moves towards.
+    chunked_tilemap.spawn_queue = ChunkSpawnQueue::new(heading_priority(0.5), 2);
+    commands.spawn(chunked_tilemap);
+}
+
+fn main() {
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: String::from("Chunk Streaming Example"),
+                        ..Default::default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .add_plugins((TilemapPlugin, ChunkStreamingPlugin))
+        .add_systems(Startup, startup)
+        .add_systems(Update, helpers::camera::movement)
+        .run();
+}
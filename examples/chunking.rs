@@ -1,8 +1,10 @@
 use bevy::{math::Vec3Swizzles, platform::collections::HashSet, prelude::*};
+use bevy_ecs_tilemap::helpers::streaming::{ChunkFocus, ChunkSpawnQueue, heading_priority};
 use bevy_ecs_tilemap::prelude::*;
 mod helpers;
 
-// Press WASD to move the camera around, and watch as chunks spawn/despawn in response.
+// Press WASD to move the camera around, and watch as chunks spawn/despawn in response. Chunks
+// ahead of the camera are spawned first.
 
 const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 16.0, y: 16.0 };
 // For this example, don't choose too large a chunk size.
@@ -67,19 +69,46 @@ fn camera_pos_to_chunk_pos(camera_pos: &Vec2) -> IVec2 {
 fn spawn_chunks_around_camera(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    time: Res<Time>,
     camera_query: Query<&Transform, With<Camera>>,
     mut chunk_manager: ResMut<ChunkManager>,
 ) {
     for transform in camera_query.iter() {
-        let camera_chunk_pos = camera_pos_to_chunk_pos(&transform.translation.xy());
+        let camera_pos = transform.translation.xy();
+        let camera_chunk_pos = camera_pos_to_chunk_pos(&camera_pos);
+
+        // Queue the missing chunks in range, and forget the ones that went out of range before
+        // they were spawned.
+        let in_range = |chunk_pos: &IVec2| {
+            (camera_chunk_pos.x - 2..camera_chunk_pos.x + 2).contains(&chunk_pos.x)
+                && (camera_chunk_pos.y - 2..camera_chunk_pos.y + 2).contains(&chunk_pos.y)
+        };
+        chunk_manager.spawn_queue.retain(in_range);
         for y in (camera_chunk_pos.y - 2)..(camera_chunk_pos.y + 2) {
             for x in (camera_chunk_pos.x - 2)..(camera_chunk_pos.x + 2) {
                 if !chunk_manager.spawned_chunks.contains(&IVec2::new(x, y)) {
-                    chunk_manager.spawned_chunks.insert(IVec2::new(x, y));
-                    spawn_chunk(&mut commands, &asset_server, IVec2::new(x, y));
+                    chunk_manager.spawn_queue.push(IVec2::new(x, y));
                 }
             }
         }
+
+        let chunk_world_size = CHUNK_SIZE.as_vec2() * Vec2::new(TILE_SIZE.x, TILE_SIZE.y);
+        let velocity = match chunk_manager.last_camera_pos {
+            Some(last_camera_pos) if time.delta_secs() > 0.0 => {
+                (camera_pos - last_camera_pos) / chunk_world_size / time.delta_secs()
+            }
+            _ => Vec2::ZERO,
+        };
+        chunk_manager.last_camera_pos = Some(camera_pos);
+
+        let focus = ChunkFocus {
+            chunk: camera_chunk_pos,
+            velocity,
+        };
+        for chunk_pos in chunk_manager.spawn_queue.pop_budgeted(&focus) {
+            chunk_manager.spawned_chunks.insert(chunk_pos);
+            spawn_chunk(&mut commands, &asset_server, chunk_pos);
+        }
     }
 }
 
@@ -103,9 +132,22 @@ fn despawn_outofrange_chunks(
     }
 }
 
-#[derive(Default, Debug, Resource)]
+#[derive(Resource)]
 struct ChunkManager {
     pub spawned_chunks: HashSet<IVec2>,
+    pub spawn_queue: ChunkSpawnQueue,
+    pub last_camera_pos: Option<Vec2>,
+}
+
+impl Default for ChunkManager {
+    fn default() -> Self {
+        Self {
+            spawned_chunks: HashSet::default(),
+            // Spawn up to 2 chunks per frame, favoring the ones the camera moves towards.
+            spawn_queue: ChunkSpawnQueue::new(heading_priority(0.5), 2),
+            last_camera_pos: None,
+        }
+    }
 }
 
 fn main() {
@@ -1,4 +1,2 @@
 pub mod anchor;
 pub mod camera;
-pub mod ldtk;
-pub mod tiled;
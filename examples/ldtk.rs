@@ -1,28 +1,16 @@
-//! This example is capable of spawning tilemaps from [LDtk](https://ldtk.io) files.
-//!
-//! It can load the AutoTile and Tile layers of simple LDtk levels.
-//! However, it does have limitations.
-//! Some edge cases around tileset definitions and layer definitions haven't been considered here.
-//! Furthermore, since this example is primarily concerned with the tilemap functionality,
-//! there's no solution built in for Entity or Intgrid layers.
+//! This example spawns tilemaps from [LDtk](https://ldtk.io) files with the `ldtk` feature.
 //!
 //! For a more comprehensive LDtk solution, consider [bevy_ecs_ldtk](https://github.com/Trouv/bevy_ecs_ldtk), which uses bevy_ecs_tilemap internally.
 
 use bevy::prelude::*;
-use bevy_ecs_tilemap::*;
+use bevy_ecs_tilemap::prelude::*;
 
 mod helpers;
 
 fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
 
-    let handle = helpers::ldtk::LdtkMapHandle(asset_server.load("map.ldtk"));
-
-    commands.spawn(helpers::ldtk::LdtkMapBundle {
-        ldtk_map: handle,
-        transform: Transform::from_xyz(0.0, 0.0, 0.0),
-        ..Default::default()
-    });
+    commands.spawn(LdtkMapHandle(asset_server.load("map.ldtk")));
 }
 
 fn main() {
@@ -39,7 +27,7 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugins(TilemapPlugin)
-        .add_plugins(helpers::ldtk::LdtkPlugin)
+        .add_plugins(LdtkPlugin)
         .add_systems(Startup, startup)
         .add_systems(Update, helpers::camera::movement)
         .run();
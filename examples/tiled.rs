@@ -6,12 +6,7 @@ mod helpers;
 fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
 
-    let map_handle = helpers::tiled::TiledMapHandle(asset_server.load("map.tmx"));
-
-    commands.spawn(helpers::tiled::TiledMapBundle {
-        tiled_map: map_handle,
-        ..Default::default()
-    });
+    commands.spawn(TiledMapHandle(asset_server.load("map.tmx")));
 }
 
 fn main() {
@@ -28,7 +23,7 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugins(TilemapPlugin)
-        .add_plugins(helpers::tiled::TiledMapPlugin)
+        .add_plugins(TiledMapPlugin)
         .add_systems(Startup, startup)
         .add_systems(Update, helpers::camera::movement)
         .run();
@@ -6,6 +6,7 @@ use bevy::prelude::*;
 /// `BottomLeft` refers to the bottom-left of the tilemap--not that tile's center.
 #[derive(Debug, Clone, Copy, Component, Default, Reflect, PartialEq)]
 #[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TilemapAnchor {
     /// The center of the bottom-left tile
     #[default]
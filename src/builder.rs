@@ -0,0 +1,122 @@
+use bevy::prelude::{Commands, Entity, Transform};
+
+use crate::anchor::TilemapAnchor;
+use crate::map::{
+    TilemapGridSize, TilemapRenderSettings, TilemapSize, TilemapSpacing, TilemapTexture,
+    TilemapTileSize, TilemapType,
+};
+use crate::tiles::TileStorage;
+use crate::{FrustumCulling, TilemapBundle};
+
+/// Assembles a [`TilemapBundle`] with chained calls instead of a struct literal.
+///
+/// Unless [`grid_size`](Self::grid_size) is called, the grid size follows the tile size.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let (tilemap_entity, mut storage) = TilemapBuilder::new()
+///         .size(TilemapSize::new(32, 32))
+///         .tile_size(TilemapTileSize::new(16.0, 16.0))
+///         .texture(TilemapTexture::Single(asset_server.load("tiles.png")))
+///         .anchor(TilemapAnchor::Center)
+///         .spawn(&mut commands);
+///
+///     let tile_pos = TilePos::new(0, 0);
+///     let tile_entity = commands
+///         .spawn(TileBundle {
+///             position: tile_pos,
+///             tilemap_id: TilemapId(tilemap_entity),
+///             ..Default::default()
+///         })
+///         .id();
+///     storage.set(&tile_pos, tile_entity);
+///     commands.entity(tilemap_entity).insert(storage);
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TilemapBuilder {
+    bundle: TilemapBundle,
+    grid_size: Option<TilemapGridSize>,
+}
+
+impl TilemapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(mut self, size: TilemapSize) -> Self {
+        self.bundle.size = size;
+        self
+    }
+
+    pub fn tile_size(mut self, tile_size: TilemapTileSize) -> Self {
+        self.bundle.tile_size = tile_size;
+        self
+    }
+
+    /// Sets a grid size which differs from the tile size, e.g. for hexagonal or isometric maps.
+    pub fn grid_size(mut self, grid_size: TilemapGridSize) -> Self {
+        self.grid_size = Some(grid_size);
+        self
+    }
+
+    pub fn spacing(mut self, spacing: TilemapSpacing) -> Self {
+        self.bundle.spacing = spacing;
+        self
+    }
+
+    pub fn map_type(mut self, map_type: TilemapType) -> Self {
+        self.bundle.map_type = map_type;
+        self
+    }
+
+    pub fn texture(mut self, texture: TilemapTexture) -> Self {
+        self.bundle.texture = texture;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: TilemapAnchor) -> Self {
+        self.bundle.anchor = anchor;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.bundle.transform = transform;
+        self
+    }
+
+    pub fn render_settings(mut self, render_settings: TilemapRenderSettings) -> Self {
+        self.bundle.render_settings = render_settings;
+        self
+    }
+
+    pub fn frustum_culling(mut self, frustum_culling: bool) -> Self {
+        self.bundle.frustum_culling = FrustumCulling(frustum_culling);
+        self
+    }
+
+    /// Returns the assembled bundle, with the given tile storage.
+    pub fn build(self, storage: TileStorage) -> TilemapBundle {
+        let grid_size = self
+            .grid_size
+            .unwrap_or_else(|| self.bundle.tile_size.into());
+        TilemapBundle {
+            grid_size,
+            storage,
+            ..self.bundle
+        }
+    }
+
+    /// Spawns the tilemap, and returns its entity together with an empty [`TileStorage`] of the
+    /// right size.
+    ///
+    /// The tilemap is spawned with an empty storage of its own. Once the tiles are spawned and
+    /// set in the returned storage, insert it on the tilemap entity.
+    pub fn spawn(self, commands: &mut Commands) -> (Entity, TileStorage) {
+        let storage = TileStorage::empty(self.bundle.size);
+        let entity = commands.spawn(self.build(storage.clone())).id();
+        (entity, storage)
+    }
+}
@@ -0,0 +1,292 @@
+//! Extends [`Commands`] and [`EntityCommands`] with methods for spawning tilemaps and changing
+//! their tiles.
+//!
+//! Changing a tile by hand means spawning its entity, parenting it to the tilemap, despawning the
+//! tile it replaces and updating the [`TileStorage`]. The methods here do all of that, on
+//! tilemaps with a [`SparseTileStorage`] or a [`LayeredTileStorage`] too, where they change the
+//! bottom layer. The other layers are changed through
+//! [`LayeredTileStorage::layer_store`](crate::tiles::LayeredTileStorage::layer_store):
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! fn dig(mut commands: Commands, tilemap: Single<Entity, With<TileStorage>>) {
+//!     commands.set_tile(*tilemap, TilePos::new(3, 4), TileTextureIndex(7));
+//!     commands.entity(*tilemap).remove_tile(TilePos::new(3, 5));
+//! }
+//! ```
+//!
+//! [`SparseTileStorage`]: crate::tiles::SparseTileStorage
+//! [`LayeredTileStorage`]: crate::tiles::LayeredTileStorage
+
+use bevy::prelude::*;
+
+use crate::map::{TilemapId, TilemapSize};
+use crate::tiles::{TileBundle, TilePos, TileStorage, tile_store_mut};
+
+/// Methods on [`Commands`] for spawning tilemaps and changing their tiles.
+pub trait TilemapCommands {
+    /// Spawns a tilemap with the components of `bundle`, and a tile for each position of a map of
+    /// the given `size` for which `f` returns a bundle.
+    ///
+    /// Unlike spawning each tile inside of `with_children`, the tiles are spawned in a single
+    /// batch by [`TileStorage::filled`], which is much faster for maps with millions of tiles.
+    /// The tilemap's [`TileStorage`] is inserted when the commands are applied, replacing any
+    /// storage in `bundle`.
+    fn spawn_tilemap_filled<F>(&mut self, bundle: impl Bundle, size: TilemapSize, f: F) -> Entity
+    where
+        F: FnMut(TilePos) -> Option<TileBundle> + Send + 'static;
+
+    /// Spawns a tile with the components of `bundle` at `tile_pos` on `tilemap`, despawning the
+    /// tile which was there.
+    ///
+    /// The tile gets its [`TilePos`], [`TilemapId`] and [`ChildOf`] from the tilemap, replacing
+    /// any in `bundle`, and is set in the tilemap's storage, as found by [`tile_store_mut`], when
+    /// the commands are applied. If the position lies outside of the tilemap, the tile is
+    /// despawned again.
+    fn set_tile(&mut self, tilemap: Entity, tile_pos: TilePos, bundle: impl Bundle) -> Entity;
+
+    /// Removes the tile at `tile_pos` from the storage of `tilemap` and despawns it.
+    fn remove_tile(&mut self, tilemap: Entity, tile_pos: TilePos);
+
+    /// Swaps the tiles at `a` and `b` on `tilemap`, in its storage and in their
+    /// [`TilePos`]. One of the positions may be empty, which moves the other tile there.
+    fn swap_tiles(&mut self, tilemap: Entity, a: TilePos, b: TilePos);
+}
+
+/// Methods on the [`EntityCommands`] of a tilemap for changing its tiles, which work like the
+/// ones of [`TilemapCommands`].
+pub trait TilemapEntityCommands {
+    /// See [`TilemapCommands::set_tile`].
+    fn set_tile(&mut self, tile_pos: TilePos, bundle: impl Bundle) -> Entity;
+
+    /// See [`TilemapCommands::remove_tile`].
+    fn remove_tile(&mut self, tile_pos: TilePos) -> &mut Self;
+
+    /// See [`TilemapCommands::swap_tiles`].
+    fn swap_tiles(&mut self, a: TilePos, b: TilePos) -> &mut Self;
+}
+
+impl TilemapCommands for Commands<'_, '_> {
+    fn spawn_tilemap_filled<F>(&mut self, bundle: impl Bundle, size: TilemapSize, f: F) -> Entity
+    where
+        F: FnMut(TilePos) -> Option<TileBundle> + Send + 'static,
+    {
+        let tilemap = self.spawn(bundle).id();
+        self.queue(move |world: &mut World| {
+            if world.get_entity(tilemap).is_err() {
+                return;
+            }
+            let storage = TileStorage::filled(size, TilemapId(tilemap), world, f);
+            world.entity_mut(tilemap).insert(storage);
+        });
+        tilemap
+    }
+
+    fn set_tile(&mut self, tilemap: Entity, tile_pos: TilePos, bundle: impl Bundle) -> Entity {
+        let tile_entity = self
+            .spawn(bundle)
+            .insert((tile_pos, TilemapId(tilemap), ChildOf(tilemap)))
+            .id();
+        self.queue(move |world: &mut World| {
+            let previous = tile_store_mut(world, tilemap)
+                .filter(|storage| tile_pos.within_map_bounds(&storage.size()))
+                .map(|mut storage| storage.remove(&tile_pos));
+            match previous {
+                Some(previous) => {
+                    if let Some(previous) = previous.filter(|previous| *previous != tile_entity) {
+                        world.despawn(previous);
+                    }
+                    if let Some(mut storage) = tile_store_mut(world, tilemap) {
+                        storage.set(&tile_pos, tile_entity);
+                    }
+                }
+                None => {
+                    world.despawn(tile_entity);
+                }
+            }
+        });
+        tile_entity
+    }
+
+    fn remove_tile(&mut self, tilemap: Entity, tile_pos: TilePos) {
+        self.queue(move |world: &mut World| {
+            let tile_entity = tile_store_mut(world, tilemap)
+                .and_then(|mut storage| storage.checked_remove(&tile_pos));
+            if let Some(tile_entity) = tile_entity {
+                world.despawn(tile_entity);
+            }
+        });
+    }
+
+    fn swap_tiles(&mut self, tilemap: Entity, a: TilePos, b: TilePos) {
+        self.queue(move |world: &mut World| {
+            let Some(mut storage) = tile_store_mut(world, tilemap) else {
+                return;
+            };
+            let size = storage.size();
+            if !a.within_map_bounds(&size) || !b.within_map_bounds(&size) {
+                return;
+            }
+            let tile_a = storage.remove(&a);
+            let tile_b = storage.remove(&b);
+            drop(storage);
+            for (tile_entity, tile_pos) in [(tile_a, b), (tile_b, a)] {
+                let Some(tile_entity) = tile_entity else {
+                    continue;
+                };
+                if let Some(mut storage) = tile_store_mut(world, tilemap) {
+                    storage.set(&tile_pos, tile_entity);
+                }
+                if let Some(mut position) = world.get_mut::<TilePos>(tile_entity) {
+                    *position = tile_pos;
+                }
+            }
+        });
+    }
+}
+
+impl TilemapEntityCommands for EntityCommands<'_> {
+    fn set_tile(&mut self, tile_pos: TilePos, bundle: impl Bundle) -> Entity {
+        let tilemap = self.id();
+        self.commands().set_tile(tilemap, tile_pos, bundle)
+    }
+
+    fn remove_tile(&mut self, tile_pos: TilePos) -> &mut Self {
+        let tilemap = self.id();
+        self.commands().remove_tile(tilemap, tile_pos);
+        self
+    }
+
+    fn swap_tiles(&mut self, a: TilePos, b: TilePos) -> &mut Self {
+        let tilemap = self.id();
+        self.commands().swap_tiles(tilemap, a, b);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{LayeredTileStorage, SparseTileStorage, TileTextureIndex};
+
+    #[test]
+    fn tilemaps_are_spawned_filled() {
+        let mut world = World::new();
+        let tilemap = world.commands().spawn_tilemap_filled(
+            (Name::new("checkers"), TileStorage::default()),
+            TilemapSize::new(4, 4),
+            |tile_pos| {
+                ((tile_pos.x + tile_pos.y) % 2 == 0).then(|| TileBundle {
+                    texture_index: TileTextureIndex(tile_pos.x),
+                    ..Default::default()
+                })
+            },
+        );
+        world.flush();
+
+        let storage = world.get::<TileStorage>(tilemap).unwrap();
+        assert_eq!(storage.size, TilemapSize::new(4, 4));
+        assert_eq!(storage.iter().flatten().count(), 8);
+        assert_eq!(storage.get(&TilePos::new(1, 0)), None);
+
+        let tile = storage.get(&TilePos::new(3, 1)).unwrap();
+        assert_eq!(world.get::<TilePos>(tile), Some(&TilePos::new(3, 1)));
+        assert_eq!(world.get::<TilemapId>(tile), Some(&TilemapId(tilemap)));
+        assert_eq!(
+            world.get::<TileTextureIndex>(tile),
+            Some(&TileTextureIndex(3))
+        );
+        assert_eq!(world.get::<ChildOf>(tile), Some(&ChildOf(tilemap)));
+        assert_eq!(world.get::<Children>(tilemap).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn tiles_are_set_removed_and_swapped() {
+        let mut world = World::new();
+        let tilemap = world.spawn(TileStorage::empty(TilemapSize::new(4, 4))).id();
+        let first = world
+            .commands()
+            .set_tile(tilemap, TilePos::new(1, 1), TileTextureIndex(1));
+        world.flush();
+        let second = world
+            .commands()
+            .entity(tilemap)
+            .set_tile(TilePos::new(1, 1), TileTextureIndex(2));
+        let outside = world
+            .commands()
+            .set_tile(tilemap, TilePos::new(4, 0), TileBundle::default());
+        world.flush();
+
+        // The replaced tile and the one outside of the tilemap are despawned.
+        assert!(world.get_entity(first).is_err());
+        assert!(world.get_entity(outside).is_err());
+        let storage = world.get::<TileStorage>(tilemap).unwrap();
+        assert_eq!(storage.get(&TilePos::new(1, 1)), Some(second));
+        assert_eq!(world.get::<TilePos>(second), Some(&TilePos::new(1, 1)));
+        assert_eq!(world.get::<ChildOf>(second), Some(&ChildOf(tilemap)));
+
+        world
+            .commands()
+            .swap_tiles(tilemap, TilePos::new(1, 1), TilePos::new(2, 3));
+        world.flush();
+        let storage = world.get::<TileStorage>(tilemap).unwrap();
+        assert_eq!(storage.get(&TilePos::new(1, 1)), None);
+        assert_eq!(storage.get(&TilePos::new(2, 3)), Some(second));
+        assert_eq!(world.get::<TilePos>(second), Some(&TilePos::new(2, 3)));
+
+        world
+            .commands()
+            .entity(tilemap)
+            .remove_tile(TilePos::new(2, 3));
+        world.flush();
+        assert!(world.get_entity(second).is_err());
+        assert_eq!(
+            world
+                .get::<TileStorage>(tilemap)
+                .unwrap()
+                .iter()
+                .flatten()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn tiles_are_set_in_sparse_and_layered_storages() {
+        let mut world = World::new();
+        let size = TilemapSize::new(1 << 20, 1 << 20);
+        // The default dense storage is left in place, as with the tilemap bundles.
+        let sparse = world
+            .spawn((TileStorage::default(), SparseTileStorage::empty(size)))
+            .id();
+        let layered = world
+            .spawn((
+                TileStorage::default(),
+                LayeredTileStorage::empty(TilemapSize::new(4, 4), 2),
+            ))
+            .id();
+        let far = TilePos::new(900_000, 3);
+        let sparse_tile = world.commands().set_tile(sparse, far, TileTextureIndex(1));
+        let layered_tile =
+            world
+                .commands()
+                .set_tile(layered, TilePos::new(1, 1), TileTextureIndex(2));
+        world.flush();
+
+        assert!(world.get_entity(sparse_tile).is_ok());
+        let storage = world.get::<SparseTileStorage>(sparse).unwrap();
+        assert_eq!(storage.get(&far), Some(sparse_tile));
+        let storage = world.get::<LayeredTileStorage>(layered).unwrap();
+        assert_eq!(storage.get(&TilePos::new(1, 1), 0), Some(layered_tile));
+
+        world.commands().swap_tiles(sparse, far, TilePos::new(0, 0));
+        world.commands().remove_tile(layered, TilePos::new(1, 1));
+        world.flush();
+        let storage = world.get::<SparseTileStorage>(sparse).unwrap();
+        assert_eq!(storage.get(&far), None);
+        assert_eq!(storage.get(&TilePos::new(0, 0)), Some(sparse_tile));
+        assert_eq!(world.get::<TilePos>(sparse_tile), Some(&TilePos::new(0, 0)));
+        assert!(world.get_entity(layered_tile).is_err());
+    }
+}
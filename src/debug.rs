@@ -0,0 +1,137 @@
+//! Gizmos which show where tilemaps are anchored.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::debug::{TilemapDebugPlugin, TilemapGizmos};
+//! fn build(app: &mut App) {
+//!     app.add_plugins(TilemapDebugPlugin)
+//!         .add_systems(Update, hide_bounds);
+//! }
+//!
+//! fn hide_bounds(mut config_store: ResMut<GizmoConfigStore>) {
+//!     let (_, tilemap_gizmos) = config_store.config_mut::<TilemapGizmos>();
+//!     tilemap_gizmos.bounds = false;
+//! }
+//! ```
+
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    color::palettes::css,
+    gizmos::{AppGizmoBuilder, config::GizmoConfigGroup, gizmos::Gizmos},
+    prelude::*,
+};
+
+use crate::{
+    TilemapAnchor,
+    map::{
+        TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType, TilemapWorldBounds,
+        update_tilemap_world_bounds,
+    },
+    tiles::TilePos,
+};
+
+/// Draws the [`TilemapGizmos`] of every tilemap.
+///
+/// Gizmos can be turned off as a whole through bevy's `GizmoConfigStore`, or one at a time with
+/// the fields of [`TilemapGizmos`].
+#[derive(Default)]
+pub struct TilemapDebugPlugin;
+
+impl Plugin for TilemapDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<TilemapGizmos>().add_systems(
+            PostUpdate,
+            draw_tilemap_gizmos.after(update_tilemap_world_bounds),
+        );
+    }
+}
+
+/// The gizmos drawn by the [`TilemapDebugPlugin`].
+///
+/// The anchor point of a tilemap always lies on the origin of its [`Transform`]; the anchor only
+/// decides which part of the map is moved there. Drawing the tile `(0, 0)` marker next to it
+/// shows how far the [`TilemapAnchor`] shifted the map, which is where most "my map is off by half
+/// a tile" mistakes come from.
+#[derive(Reflect, GizmoConfigGroup)]
+pub struct TilemapGizmos {
+    /// Draws a cross on the origin of the tilemap's transform.
+    pub origin: bool,
+    /// Draws the [`TilemapWorldBounds`] of the tilemap.
+    pub bounds: bool,
+    /// Draws a circle on the anchor point, and a line from it to the center of tile `(0, 0)`.
+    pub anchor: bool,
+    pub origin_color: Color,
+    pub bounds_color: Color,
+    pub anchor_color: Color,
+}
+
+impl Default for TilemapGizmos {
+    fn default() -> Self {
+        Self {
+            origin: true,
+            bounds: true,
+            anchor: true,
+            origin_color: css::RED.into(),
+            bounds_color: css::YELLOW.into(),
+            anchor_color: css::LIME.into(),
+        }
+    }
+}
+
+fn draw_tilemap_gizmos(
+    mut gizmos: Gizmos<TilemapGizmos>,
+    tilemaps: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &GlobalTransform,
+        Option<&TilemapWorldBounds>,
+    )>,
+) {
+    let config = gizmos.config_ext;
+    for (
+        map_size,
+        grid_size,
+        tile_size,
+        map_type,
+        tilemap_anchor,
+        global_transform,
+        world_bounds,
+    ) in tilemaps.iter()
+    {
+        let translation = global_transform.translation().truncate();
+        let marker_size = Vec2::new(grid_size.x, grid_size.y).min_element() * 0.25;
+
+        if config.origin {
+            gizmos.cross_2d(translation, marker_size, config.origin_color);
+        }
+
+        if config.bounds
+            && let Some(world_bounds) = world_bounds
+        {
+            gizmos.rect_2d(
+                world_bounds.0.center(),
+                world_bounds.0.size(),
+                config.bounds_color,
+            );
+        }
+
+        if config.anchor {
+            gizmos.circle_2d(translation, marker_size, config.anchor_color);
+            let first_tile = TilePos::new(0, 0).center_in_world(
+                map_size,
+                grid_size,
+                tile_size,
+                map_type,
+                tilemap_anchor,
+            );
+            let first_tile = global_transform
+                .transform_point(first_tile.extend(0.0))
+                .truncate();
+            gizmos.line_2d(translation, first_tile, config.anchor_color);
+            gizmos.rect_2d(first_tile, Vec2::splat(marker_size), config.anchor_color);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Diagnostics for the tilemap renderer.
+
+use bevy::{
+    app::{App, Plugin, Update},
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::Res,
+};
+
+pub use crate::render::{ChunkBufferPoolStats, RenderChunkStats};
+
+/// Records [`ChunkBufferPoolStats`] and [`RenderChunkStats`] as diagnostics, so they can be
+/// inspected with e.g. bevy's `LogDiagnosticsPlugin`.
+#[derive(Default)]
+pub struct TilemapDiagnosticsPlugin;
+
+impl TilemapDiagnosticsPlugin {
+    /// Number of chunk buffers reused from the pool in the last frame.
+    pub const CHUNK_BUFFERS_REUSED: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/chunk_buffers/reused");
+    /// Number of chunk buffers allocated in the last frame.
+    pub const CHUNK_BUFFERS_ALLOCATED: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/chunk_buffers/allocated");
+    /// Number of pooled chunk buffers freed in the last frame.
+    pub const CHUNK_BUFFERS_EVICTED: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/chunk_buffers/evicted");
+    /// Number of chunk buffers waiting in the pool.
+    pub const CHUNK_BUFFERS_POOLED: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/chunk_buffers/pooled");
+    /// Total size of the chunk buffers waiting in the pool, in bytes.
+    pub const CHUNK_BUFFERS_POOLED_BYTES: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/chunk_buffers/pooled_bytes");
+    /// Number of chunks of all tilemaps.
+    pub const CHUNKS: DiagnosticPath = DiagnosticPath::const_new("tilemap/chunks/count");
+    /// Number of chunks drawn in the last frame.
+    pub const CHUNKS_VISIBLE: DiagnosticPath = DiagnosticPath::const_new("tilemap/chunks/visible");
+    /// Number of chunk meshes rebuilt in the last frame.
+    pub const CHUNKS_REBUILT: DiagnosticPath = DiagnosticPath::const_new("tilemap/chunks/rebuilt");
+    /// Number of vertices of the meshes of all chunks.
+    pub const CHUNK_VERTICES: DiagnosticPath = DiagnosticPath::const_new("tilemap/chunks/vertices");
+    /// Number of layers of the texture arrays made from tilemap textures.
+    pub const TEXTURE_ARRAY_LAYERS: DiagnosticPath =
+        DiagnosticPath::const_new("tilemap/texture_arrays/layers");
+}
+
+impl Plugin for TilemapDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::CHUNK_BUFFERS_REUSED))
+            .register_diagnostic(Diagnostic::new(Self::CHUNK_BUFFERS_ALLOCATED))
+            .register_diagnostic(Diagnostic::new(Self::CHUNK_BUFFERS_EVICTED))
+            .register_diagnostic(Diagnostic::new(Self::CHUNK_BUFFERS_POOLED))
+            .register_diagnostic(
+                Diagnostic::new(Self::CHUNK_BUFFERS_POOLED_BYTES).with_suffix(" B"),
+            )
+            .register_diagnostic(Diagnostic::new(Self::CHUNKS))
+            .register_diagnostic(Diagnostic::new(Self::CHUNKS_VISIBLE))
+            .register_diagnostic(Diagnostic::new(Self::CHUNKS_REBUILT))
+            .register_diagnostic(Diagnostic::new(Self::CHUNK_VERTICES))
+            .register_diagnostic(Diagnostic::new(Self::TEXTURE_ARRAY_LAYERS))
+            .add_systems(
+                Update,
+                (record_chunk_buffer_pool_stats, record_render_chunk_stats),
+            );
+    }
+}
+
+fn record_chunk_buffer_pool_stats(
+    stats: Option<Res<ChunkBufferPoolStats>>,
+    mut diagnostics: Diagnostics,
+) {
+    let Some(stats) = stats else {
+        return;
+    };
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNK_BUFFERS_REUSED, || {
+        stats.reused as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNK_BUFFERS_ALLOCATED, || {
+        stats.allocated as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNK_BUFFERS_EVICTED, || {
+        stats.evicted as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNK_BUFFERS_POOLED, || {
+        stats.pooled_buffers as f64
+    });
+    diagnostics.add_measurement(
+        &TilemapDiagnosticsPlugin::CHUNK_BUFFERS_POOLED_BYTES,
+        || stats.pooled_bytes as f64,
+    );
+}
+
+fn record_render_chunk_stats(stats: Option<Res<RenderChunkStats>>, mut diagnostics: Diagnostics) {
+    let Some(stats) = stats else {
+        return;
+    };
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNKS, || stats.chunks as f64);
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNKS_VISIBLE, || {
+        stats.visible_chunks as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNKS_REBUILT, || {
+        stats.rebuilt_chunks as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::CHUNK_VERTICES, || {
+        stats.vertices as f64
+    });
+    diagnostics.add_measurement(&TilemapDiagnosticsPlugin::TEXTURE_ARRAY_LAYERS, || {
+        stats.texture_array_layers as f64
+    });
+}
@@ -0,0 +1,109 @@
+//! Pauses and resumes every tile animation at once, e.g. while a pause menu is open.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::animation_control::*;
+//! fn toggle_pause(keys: Res<ButtonInput<KeyCode>>, mut control: ResMut<TileAnimationControl>) {
+//!     if keys.just_pressed(KeyCode::Escape) {
+//!         control.paused = !control.paused;
+//!     }
+//! }
+//!
+//! App::new()
+//!     .add_plugins(TilemapAnimationControlPlugin)
+//!     .add_systems(Update, toggle_pause);
+//! ```
+
+use bevy::prelude::*;
+
+use crate::tiles::{AnimatedTile, AnimationPaused, TileFrameAnimation};
+
+/// Adds the [`TileAnimationControl`] resource and the system which applies it.
+pub struct TilemapAnimationControlPlugin;
+
+impl Plugin for TilemapAnimationControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileAnimationControl>()
+            .add_systems(PostUpdate, apply_tile_animation_control);
+    }
+}
+
+/// Controls the animations of all tiles.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileAnimationControl {
+    /// Halts every animated tile on its current frame with an [`AnimationPaused`]. Tiles spawned
+    /// while paused are halted too.
+    ///
+    /// Once unpaused, tiles continue in step with the shared animation clock. Tiles which were
+    /// paused on their own before stay paused.
+    pub paused: bool,
+}
+
+/// Marks the tiles paused by the [`TileAnimationControl`], so they are the only ones resumed.
+#[derive(Component)]
+struct PausedByControl;
+
+#[allow(clippy::type_complexity)]
+fn apply_tile_animation_control(
+    mut commands: Commands,
+    control: Res<TileAnimationControl>,
+    running: Query<
+        Entity,
+        (
+            Or<(With<AnimatedTile>, With<TileFrameAnimation>)>,
+            Without<AnimationPaused>,
+        ),
+    >,
+    paused: Query<Entity, With<PausedByControl>>,
+) {
+    if control.paused {
+        for tile_entity in running.iter() {
+            commands
+                .entity(tile_entity)
+                .insert((AnimationPaused::default(), PausedByControl));
+        }
+    } else if control.is_changed() {
+        for tile_entity in paused.iter() {
+            commands
+                .entity(tile_entity)
+                .remove::<(AnimationPaused, PausedByControl)>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_only_resumes_what_it_paused() {
+        let mut world = World::new();
+        world.init_resource::<TileAnimationControl>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_tile_animation_control);
+
+        let animated = AnimatedTile {
+            start: 0,
+            end: 4,
+            speed: 1.0,
+        };
+        let running = world.spawn(animated).id();
+        let halted = world.spawn((animated, AnimationPaused::on_frame(2))).id();
+
+        world.resource_mut::<TileAnimationControl>().paused = true;
+        schedule.run(&mut world);
+        let spawned = world.spawn(animated).id();
+        schedule.run(&mut world);
+        assert!(world.get::<AnimationPaused>(running).is_some());
+        assert!(world.get::<AnimationPaused>(spawned).is_some());
+
+        world.resource_mut::<TileAnimationControl>().paused = false;
+        schedule.run(&mut world);
+        assert!(world.get::<AnimationPaused>(running).is_none());
+        assert!(world.get::<AnimationPaused>(spawned).is_none());
+        assert_eq!(
+            world.get::<AnimationPaused>(halted),
+            Some(&AnimationPaused::on_frame(2))
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Builds tile layers from ASCII art, for tests and quick prototypes.
+//!
+//! ```
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::tilemap;
+//! let layer = tilemap!(
+//!     "
+//!     WWWWW
+//!     W..~W
+//!     WWWWW
+//!     ",
+//!     { 'W' => 1, '~' => 2 }
+//! );
+//! assert_eq!(layer.size, TilemapSize::new(5, 3));
+//! // The first line of the art is the top row of the layer.
+//! assert_eq!(*layer.get(&TilePos::new(3, 1)), Some(TileTextureIndex(2)));
+//! assert_eq!(*layer.get(&TilePos::new(1, 1)), None);
+//! ```
+//!
+//! The layer can then be spawned with
+//! [`fill_tilemap_from_layer`](crate::helpers::filling::fill_tilemap_from_layer).
+
+use std::fmt;
+
+use crate::map::TilemapSize;
+use crate::tiles::{TileDataLayer, TilePos, TileTextureIndex};
+
+/// The character which stands for a position without a tile, unless the legend maps it to a
+/// texture.
+pub const EMPTY_TILE_CHAR: char = '.';
+
+/// An error in the ASCII art given to [`parse_ascii_layer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiLayerError {
+    /// The art has no rows.
+    Empty,
+    /// A row is not as wide as the first row. Rows are counted from the top, starting at 0.
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    /// A character is neither in the legend nor [`EMPTY_TILE_CHAR`].
+    UnknownChar { ch: char, row: usize, column: usize },
+}
+
+impl fmt::Display for AsciiLayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the art has no rows"),
+            Self::RaggedRow { row, len, expected } => {
+                write!(f, "row {row} is {len} tiles wide instead of {expected}")
+            }
+            Self::UnknownChar { ch, row, column } => {
+                write!(
+                    f,
+                    "'{ch}' at row {row}, column {column} is not in the legend"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsciiLayerError {}
+
+/// Parses ASCII art into a layer of texture indices, looking each character up in `legend`.
+///
+/// Every non-blank line of `art` is a row of tiles, with the first line at the top of the layer.
+/// Leading and trailing whitespace is ignored, so the art can be indented along with the code.
+/// [`EMPTY_TILE_CHAR`] leaves the position without a tile.
+pub fn parse_ascii_layer(
+    art: &str,
+    legend: &[(char, u32)],
+) -> Result<TileDataLayer<Option<TileTextureIndex>>, AsciiLayerError> {
+    let rows = art
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let expected = rows.first().ok_or(AsciiLayerError::Empty)?.len();
+    if let Some((row, chars)) = rows
+        .iter()
+        .enumerate()
+        .find(|(_, chars)| chars.len() != expected)
+    {
+        return Err(AsciiLayerError::RaggedRow {
+            row,
+            len: chars.len(),
+            expected,
+        });
+    }
+
+    let size = TilemapSize::new(expected as u32, rows.len() as u32);
+    let mut layer = TileDataLayer::filled(size, None);
+    for (row, chars) in rows.iter().enumerate() {
+        for (column, ch) in chars.iter().enumerate() {
+            let texture_index = match legend.iter().find(|(key, _)| key == ch) {
+                Some((_, index)) => Some(TileTextureIndex(*index)),
+                None if *ch == EMPTY_TILE_CHAR => None,
+                None => {
+                    return Err(AsciiLayerError::UnknownChar {
+                        ch: *ch,
+                        row,
+                        column,
+                    });
+                }
+            };
+            let tile_pos = TilePos::new(column as u32, size.y - 1 - row as u32);
+            layer.set(&tile_pos, texture_index);
+        }
+    }
+    Ok(layer)
+}
+
+/// Builds a layer of texture indices from ASCII art and a legend, panicking if the art is
+/// malformed.
+///
+/// See [`parse_ascii_layer`] for the format of the art.
+#[macro_export]
+macro_rules! tilemap {
+    ($art:expr, { $($ch:literal => $index:expr),* $(,)? } $(,)?) => {
+        $crate::helpers::ascii::parse_ascii_layer($art, &[$(($ch, $index)),*])
+            .unwrap_or_else(|err| panic!("invalid tilemap! art: {err}"))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_art_is_rejected() {
+        assert_eq!(parse_ascii_layer("  \n ", &[]), Err(AsciiLayerError::Empty));
+        assert_eq!(
+            parse_ascii_layer("##\n#", &[('#', 0)]),
+            Err(AsciiLayerError::RaggedRow {
+                row: 1,
+                len: 1,
+                expected: 2
+            })
+        );
+        assert_eq!(
+            parse_ascii_layer("#.\n.x", &[('#', 0)]),
+            Err(AsciiLayerError::UnknownChar {
+                ch: 'x',
+                row: 1,
+                column: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,548 @@
+//! Helpers for autotiling, i.e. picking the texture of a tile based on its neighborhood.
+//!
+//! Add the [`AutotilePlugin`], give a tilemap an [`AutoTileRules`] pointing at a [`RuleTileSet`],
+//! and tag its tiles with an [`AutoTile`] terrain. Whenever tiles are tagged, retagged, moved,
+//! untagged or despawned, the texture and flip of the tiles around them are picked again from the
+//! rules of their terrain, a limited number of tiles per frame through the tilemap's
+//! [`AutotileQueue`].
+
+use crate::helpers::square_grid::neighbors::{Neighbors, SQUARE_DIRECTIONS, SquareDirection};
+use crate::map::{TilemapId, TilemapSize, TilemapTopology};
+use crate::tiles::{TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex};
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Adds the [`RuleTileSet`] asset and the systems which apply it to [`AutoTile`]s.
+pub struct AutotilePlugin;
+
+impl Plugin for AutotilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RuleTileSet>()
+            .register_type::<AutoTile>()
+            .add_systems(PostUpdate, (queue_auto_tiles, apply_auto_tiles).chain());
+    }
+}
+
+/// The terrain of a tile whose texture is picked by the [`RuleTileSet`] of its tilemap.
+///
+/// Tiles are connected to neighbors of the same terrain.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoTile(pub u32);
+
+/// The [`RuleTileSet`] used for the [`AutoTile`]s of a tilemap.
+///
+/// Changing the handle, or the rule set it points at, picks the texture of every tile again.
+#[derive(Component, Clone, Debug)]
+#[require(AutotileQueue)]
+pub struct AutoTileRules(pub Handle<RuleTileSet>);
+
+/// Which of the eight neighbors of a tile are connected to it, i.e. have the same terrain.
+///
+/// The bits follow the usual blob tileset convention, going clockwise from north.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct NeighborMask(pub u8);
+
+impl NeighborMask {
+    pub const NORTH: Self = Self(1);
+    pub const NORTH_EAST: Self = Self(2);
+    pub const EAST: Self = Self(4);
+    pub const SOUTH_EAST: Self = Self(8);
+    pub const SOUTH: Self = Self(16);
+    pub const SOUTH_WEST: Self = Self(32);
+    pub const WEST: Self = Self(64);
+    pub const NORTH_WEST: Self = Self(128);
+    pub const CARDINALS: Self = Self(1 | 4 | 16 | 64);
+
+    pub fn from_direction(direction: SquareDirection) -> Self {
+        use SquareDirection::*;
+        match direction {
+            North => Self::NORTH,
+            NorthEast => Self::NORTH_EAST,
+            East => Self::EAST,
+            SouthEast => Self::SOUTH_EAST,
+            South => Self::SOUTH,
+            SouthWest => Self::SOUTH_WEST,
+            West => Self::WEST,
+            NorthWest => Self::NORTH_WEST,
+        }
+    }
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the mask without the diagonal neighbors which are not next to two connected
+    /// cardinal neighbors, as those do not change the look of a blob tile.
+    pub fn blob(self) -> Self {
+        let mut mask = self.0 & Self::CARDINALS.0;
+        for (diagonal, a, b) in [
+            (Self::NORTH_EAST, Self::NORTH, Self::EAST),
+            (Self::SOUTH_EAST, Self::SOUTH, Self::EAST),
+            (Self::SOUTH_WEST, Self::SOUTH, Self::WEST),
+            (Self::NORTH_WEST, Self::NORTH, Self::WEST),
+        ] {
+            if self.contains(diagonal) && self.contains(a) && self.contains(b) {
+                mask |= diagonal.0;
+            }
+        }
+        Self(mask)
+    }
+}
+
+/// A rule of a [`RuleTileSet`]: tiles whose [`NeighborMask`], limited to the neighbors in `mask`,
+/// equals `pattern` get the given texture and flip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRule {
+    /// The neighbors the rule looks at.
+    pub mask: u8,
+    /// Which of the neighbors in `mask` must be connected. The others must not be.
+    pub pattern: u8,
+    pub texture_index: TileTextureIndex,
+    pub flip: TileFlip,
+}
+
+impl TileRule {
+    pub fn matches(&self, neighbors: NeighborMask) -> bool {
+        neighbors.0 & self.mask == self.pattern
+    }
+}
+
+/// The rules which pick the textures of [`AutoTile`]s, for each terrain.
+///
+/// The rules of a terrain are tried in the order they were added, and the first one which
+/// matches wins. Tiles no rule matches keep their texture.
+#[derive(Asset, TypePath, Clone, Debug, Default)]
+pub struct RuleTileSet {
+    terrains: HashMap<u32, Vec<TileRule>>,
+    /// Whether the edges of the map count as connected, so terrain running off the map is drawn
+    /// without a border.
+    pub edges_connect: bool,
+}
+
+impl RuleTileSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule for the given terrain, after its existing rules.
+    pub fn add_rule(&mut self, terrain: u32, rule: TileRule) -> &mut Self {
+        self.terrains.entry(terrain).or_default().push(rule);
+        self
+    }
+
+    /// Adds the rules of a 47 tile blob set for the given terrain.
+    ///
+    /// The tiles are expected in the order of their [`NeighborMask::blob`] values, starting at
+    /// `first_index`, as in most blob tilesets.
+    pub fn add_blob_47(&mut self, terrain: u32, first_index: u32) -> &mut Self {
+        let mut blobs = (0..=u8::MAX)
+            .map(|mask| NeighborMask(mask).blob().0)
+            .collect::<Vec<_>>();
+        blobs.sort_unstable();
+        blobs.dedup();
+        for (index, pattern) in blobs.into_iter().enumerate() {
+            // Only the diagonals between two connected cardinals matter.
+            let mask = NeighborMask(pattern | !NeighborMask::CARDINALS.0).blob().0
+                | NeighborMask::CARDINALS.0;
+            self.add_rule(
+                terrain,
+                TileRule {
+                    mask,
+                    pattern,
+                    texture_index: TileTextureIndex(first_index + index as u32),
+                    flip: TileFlip::default(),
+                },
+            );
+        }
+        self
+    }
+
+    /// Adds the rules of a 16 tile two-corner Wang set for the given terrain.
+    ///
+    /// A corner of a tile is connected if the three tiles around it are. The tiles are expected
+    /// in the order of their corners, with north-east, south-east, south-west and north-west
+    /// adding 1, 2, 4 and 8, starting at `first_index`.
+    pub fn add_wang_2_corner(&mut self, terrain: u32, first_index: u32) -> &mut Self {
+        let corners = [
+            NeighborMask::NORTH.0 | NeighborMask::NORTH_EAST.0 | NeighborMask::EAST.0,
+            NeighborMask::SOUTH.0 | NeighborMask::SOUTH_EAST.0 | NeighborMask::EAST.0,
+            NeighborMask::SOUTH.0 | NeighborMask::SOUTH_WEST.0 | NeighborMask::WEST.0,
+            NeighborMask::NORTH.0 | NeighborMask::NORTH_WEST.0 | NeighborMask::WEST.0,
+        ];
+        // Tiles with more connected corners go first, so they are not hidden by the ones with
+        // fewer.
+        let mut indices = (0..16u32).collect::<Vec<_>>();
+        indices.sort_by_key(|index| std::cmp::Reverse(index.count_ones()));
+        for index in indices {
+            let pattern = (0..4)
+                .filter(|corner| index & (1 << corner) != 0)
+                .fold(0, |pattern, corner| pattern | corners[corner]);
+            self.add_rule(
+                terrain,
+                TileRule {
+                    mask: pattern,
+                    pattern,
+                    texture_index: TileTextureIndex(first_index + index),
+                    flip: TileFlip::default(),
+                },
+            );
+        }
+        self
+    }
+
+    /// Returns the rules of a terrain.
+    pub fn rules(&self, terrain: u32) -> &[TileRule] {
+        self.terrains.get(&terrain).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the first rule of the terrain which matches the neighbors.
+    pub fn pick(&self, terrain: u32, neighbors: NeighborMask) -> Option<&TileRule> {
+        self.rules(terrain)
+            .iter()
+            .find(|rule| rule.matches(neighbors))
+    }
+}
+
+/// A queue of tiles whose neighborhood needs to be re-evaluated, processed a limited number of
+/// tiles per frame.
+///
+/// Each tile is only queued once until it is drained, so large edits (e.g. terraforming
+/// thousands of tiles at once) whose neighborhoods overlap do not evaluate the same tile
+/// repeatedly. It is usually added as a component to the tilemap entity.
+#[derive(Component, Debug, Clone)]
+pub struct AutotileQueue {
+    queue: VecDeque<TilePos>,
+    queued: HashSet<TilePos>,
+    /// The maximum number of tiles returned by [`drain_budgeted`](Self::drain_budgeted).
+    pub budget: usize,
+}
+
+impl Default for AutotileQueue {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl AutotileQueue {
+    /// Creates an empty queue that processes at most `budget` tiles per frame.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            queued: HashSet::default(),
+            budget,
+        }
+    }
+
+    /// Queues a single tile, returning `false` if it was already queued.
+    pub fn push(&mut self, tile_pos: TilePos) -> bool {
+        if self.queued.insert(tile_pos) {
+            self.queue.push_back(tile_pos);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Queues a tile that was changed, together with its eight neighbors, since their appearance
+    /// may depend on it.
+    pub fn push_neighborhood(&mut self, tile_pos: &TilePos, map_size: &TilemapSize) {
+        self.push(*tile_pos);
+        for neighbor in Neighbors::get_square_neighboring_positions(tile_pos, map_size, true).iter()
+        {
+            self.push(*neighbor);
+        }
+    }
+
+    /// Queues every tile in a changed region, together with the ring of tiles surrounding it.
+    pub fn push_region(&mut self, origin: TilePos, size: TilemapSize, map_size: &TilemapSize) {
+        if map_size.x == 0 || map_size.y == 0 {
+            return;
+        }
+        let min_x = origin.x.saturating_sub(1);
+        let min_y = origin.y.saturating_sub(1);
+        let max_x = origin.x.saturating_add(size.x).min(map_size.x - 1);
+        let max_y = origin.y.saturating_add(size.y).min(map_size.y - 1);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                self.push(TilePos { x, y });
+            }
+        }
+    }
+
+    /// Returns the number of queued tiles.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` if no tiles are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Removes all queued tiles without processing them.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.queued.clear();
+    }
+
+    /// Removes and returns up to [`budget`](Self::budget) tiles, in the order they were queued.
+    ///
+    /// Tiles which are not consumed from the iterator are still removed from the queue.
+    pub fn drain_budgeted(&mut self) -> impl Iterator<Item = TilePos> + '_ {
+        let count = self.budget.min(self.queue.len());
+        for tile_pos in self.queue.range(..count) {
+            self.queued.remove(tile_pos);
+        }
+        self.queue.drain(..count)
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn queue_auto_tiles(
+    mut rule_set_events: MessageReader<AssetEvent<RuleTileSet>>,
+    mut tilemaps: Query<(Ref<AutoTileRules>, &TileStorage, &mut AutotileQueue)>,
+    changed_tiles: Query<
+        (Entity, &TilemapId, Ref<TilePos>, &TilePosOld),
+        (
+            With<AutoTile>,
+            Or<(Changed<AutoTile>, Changed<TilePos>, Changed<TilemapId>)>,
+        ),
+    >,
+    mut removed: RemovedComponents<AutoTile>,
+    untagged_tiles: Query<(&TilemapId, &TilePos), Without<AutoTile>>,
+    // Where each autotile was last seen, as despawned tiles leave nothing to look up.
+    mut positions: Local<HashMap<Entity, (TilemapId, TilePos)>>,
+) {
+    let changed_rule_sets = rule_set_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    for (rules, tile_storage, mut queue) in tilemaps.iter_mut() {
+        if rules.is_changed() || changed_rule_sets.contains(&rules.0.id()) {
+            queue.push_region(TilePos::new(0, 0), tile_storage.size, &tile_storage.size);
+        }
+    }
+
+    // Removals go first, so tiles which are untagged and tagged again in the same frame are
+    // still known afterwards.
+    for tile_entity in removed.read() {
+        let Some(last_seen) = positions.remove(&tile_entity) else {
+            continue;
+        };
+        let (tilemap_id, tile_pos) = untagged_tiles
+            .get(tile_entity)
+            .map_or(last_seen, |(tilemap_id, tile_pos)| (*tilemap_id, *tile_pos));
+        if let Ok((_, tile_storage, mut queue)) = tilemaps.get_mut(tilemap_id.0) {
+            queue.push_neighborhood(&tile_pos, &tile_storage.size);
+        }
+    }
+
+    for (tile_entity, tilemap_id, tile_pos, tile_pos_old) in changed_tiles.iter() {
+        positions.insert(tile_entity, (*tilemap_id, *tile_pos));
+        if let Ok((_, tile_storage, mut queue)) = tilemaps.get_mut(tilemap_id.0) {
+            queue.push_neighborhood(&tile_pos, &tile_storage.size);
+            // A moved tile also leaves a gap behind.
+            if tile_pos.is_changed() && !tile_pos.is_added() {
+                queue.push_neighborhood(&tile_pos_old.0, &tile_storage.size);
+            }
+        }
+    }
+}
+
+fn apply_auto_tiles(
+    rule_sets: Res<Assets<RuleTileSet>>,
+    mut tilemaps: Query<(
+        &AutoTileRules,
+        &TileStorage,
+        &mut AutotileQueue,
+        Option<&TilemapTopology>,
+    )>,
+    terrains: Query<&AutoTile>,
+    mut tiles: Query<(&mut TileTextureIndex, &mut TileFlip)>,
+) {
+    for (rules, tile_storage, mut queue, topology) in tilemaps.iter_mut() {
+        if queue.is_empty() {
+            continue;
+        }
+        // Tiles stay queued until the rules are loaded.
+        let Some(rule_set) = rule_sets.get(&rules.0) else {
+            continue;
+        };
+        let topology = topology.copied().unwrap_or_default();
+        let terrain_at = |tile_pos: &TilePos| {
+            tile_storage
+                .checked_get(tile_pos)
+                .and_then(|tile_entity| terrains.get(tile_entity).ok())
+        };
+
+        for tile_pos in queue.drain_budgeted() {
+            let Some(tile_entity) = tile_storage.checked_get(&tile_pos) else {
+                continue;
+            };
+            let Ok(terrain) = terrains.get(tile_entity) else {
+                continue;
+            };
+            let neighbors = Neighbors::get_square_neighboring_positions_wrapped(
+                &tile_pos,
+                &tile_storage.size,
+                true,
+                &topology,
+            );
+            let mask = SQUARE_DIRECTIONS
+                .into_iter()
+                .filter(|direction| match neighbors.get(*direction) {
+                    Some(neighbor) => terrain_at(neighbor) == Some(terrain),
+                    None => rule_set.edges_connect,
+                })
+                .fold(NeighborMask::default(), |mask, direction| {
+                    NeighborMask(mask.0 | NeighborMask::from_direction(direction).0)
+                });
+
+            let Some(rule) = rule_set.pick(terrain.0, mask) else {
+                continue;
+            };
+            if let Ok((mut texture_index, mut flip)) = tiles.get_mut(tile_entity) {
+                texture_index.set_if_neq(rule.texture_index);
+                flip.set_if_neq(rule.flip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileBundle;
+
+    #[test]
+    fn rule_sets_cover_every_neighborhood() {
+        let mut rule_set = RuleTileSet::new();
+        rule_set.add_blob_47(0, 0).add_wang_2_corner(1, 100);
+        assert_eq!(rule_set.rules(0).len(), 47);
+        for mask in 0..=u8::MAX {
+            let blob = rule_set.pick(0, NeighborMask(mask)).unwrap();
+            assert_eq!(blob.pattern, NeighborMask(mask).blob().0);
+            assert!(rule_set.pick(1, NeighborMask(mask)).is_some());
+        }
+        // A lone tile, and a tile in the middle of its terrain.
+        assert_eq!(
+            rule_set.pick(0, NeighborMask(0)).unwrap().texture_index,
+            TileTextureIndex(0)
+        );
+        assert_eq!(
+            rule_set
+                .pick(0, NeighborMask(u8::MAX))
+                .unwrap()
+                .texture_index,
+            TileTextureIndex(46)
+        );
+        assert_eq!(
+            rule_set
+                .pick(1, NeighborMask(u8::MAX))
+                .unwrap()
+                .texture_index,
+            TileTextureIndex(115)
+        );
+        // Only the north-east corner is connected.
+        assert_eq!(
+            rule_set
+                .pick(1, NeighborMask(0b0001_0111))
+                .unwrap()
+                .texture_index,
+            TileTextureIndex(101)
+        );
+    }
+
+    #[test]
+    fn tagged_tiles_follow_their_neighbors() {
+        let mut world = World::new();
+        world.init_resource::<Assets<RuleTileSet>>();
+        world.init_resource::<Messages<AssetEvent<RuleTileSet>>>();
+        let mut rule_set = RuleTileSet::new();
+        rule_set.add_blob_47(0, 0);
+        let rules = world.resource_mut::<Assets<RuleTileSet>>().add(rule_set);
+        let mut schedule = Schedule::default();
+        schedule.add_systems((queue_auto_tiles, apply_auto_tiles).chain());
+
+        let map_size = TilemapSize::new(3, 3);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        for index in 0..map_size.count() as u32 {
+            let tile_pos = TilePos::new(index % map_size.x, index / map_size.x);
+            let tile_entity = world
+                .spawn((
+                    TileBundle {
+                        position: tile_pos,
+                        tilemap_id: TilemapId(tilemap),
+                        ..Default::default()
+                    },
+                    AutoTile(0),
+                ))
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        let center = tile_storage.get(&TilePos::new(1, 1)).unwrap();
+        let corner = tile_storage.get(&TilePos::new(0, 0)).unwrap();
+        world
+            .entity_mut(tilemap)
+            .insert((tile_storage, AutoTileRules(rules)));
+        let texture = |world: &World, tile: Entity| world.get::<TileTextureIndex>(tile).unwrap().0;
+
+        schedule.run(&mut world);
+        assert_eq!(texture(&world, center), 46);
+        // North, north-east and east are connected, which is the fifth blob.
+        assert_eq!(texture(&world, corner), 4);
+
+        // Untagging the center cuts the corner off from its diagonal.
+        world.entity_mut(center).remove::<AutoTile>();
+        schedule.run(&mut world);
+        assert_eq!(texture(&world, corner), 3);
+
+        // Despawning its eastern neighbor leaves only the north connected.
+        let east = TilePos::new(1, 0);
+        let mut tile_storage = world.get_mut::<TileStorage>(tilemap).unwrap();
+        let east_tile = tile_storage.remove(&east).unwrap();
+        world.despawn(east_tile);
+        schedule.run(&mut world);
+        assert_eq!(texture(&world, corner), 1);
+    }
+
+    #[test]
+    fn queues_hold_each_tile_once_and_drain_within_their_budget() {
+        let map_size = TilemapSize::new(4, 4);
+        let mut queue = AutotileQueue::new(4);
+        assert!(queue.push(TilePos::new(1, 1)));
+        assert!(!queue.push(TilePos::new(1, 1)));
+
+        // The neighborhood of a corner stops at the edge of the map, and overlaps the tile
+        // already queued.
+        queue.push_neighborhood(&TilePos::new(0, 0), &map_size);
+        assert_eq!(queue.len(), 4);
+        // A region reaching past the map is clipped to it, ring included.
+        queue.push_region(TilePos::new(2, 2), TilemapSize::new(5, 5), &map_size);
+        assert_eq!(queue.len(), 12);
+
+        let drained = queue.drain_budgeted().collect::<Vec<_>>();
+        assert_eq!(
+            drained,
+            [(1, 1), (0, 0), (1, 0), (0, 1)].map(|(x, y)| TilePos::new(x, y))
+        );
+        assert_eq!(queue.len(), 8);
+        // Drained tiles can be queued again.
+        assert!(queue.push(TilePos::new(1, 1)));
+        // Tiles skipped by the caller are still drained.
+        queue.drain_budgeted().next();
+        assert_eq!(queue.len(), 5);
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert!(queue.push(TilePos::new(3, 3)));
+    }
+}
@@ -0,0 +1,212 @@
+//! Saves and restores the tiles of a tilemap one chunk at a time, so autosaves of huge maps only
+//! write the chunks which changed since the last save.
+//!
+//! Add a [`TilemapDirtyChunks`] in the [`UntilTaken`] mode to the tilemaps to save. Changes to
+//! their tiles mark the chunks they are in as dirty; an autosave system then takes the dirty
+//! chunks with [`TilemapDirtyChunks::take`] and stores a [`snapshot_chunk`] of each, which is
+//! brought back later with [`restore_chunk`].
+//!
+//! With the `serde` feature, [`ChunkSnapshot`]s can be serialized in any format.
+//!
+//! [`TilemapDirtyChunks`]: crate::map::TilemapDirtyChunks
+//! [`TilemapDirtyChunks::take`]: crate::map::TilemapDirtyChunks::take
+//! [`UntilTaken`]: crate::map::DirtyChunksMode::UntilTaken
+
+use bevy::math::UVec2;
+use bevy::prelude::*;
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+
+use crate::map::TilemapId;
+use crate::tiles::{
+    TileBundle, TileColor, TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex,
+    TileVisible,
+};
+
+/// The saved state of a tile, as stored in a [`ChunkSnapshot`].
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileSnapshot {
+    pub position: TilePos,
+    pub texture_index: TileTextureIndex,
+    pub visible: TileVisible,
+    pub flip: TileFlip,
+    pub color: TileColor,
+}
+
+/// The tiles of one chunk of a tilemap, as saved by [`snapshot_chunk`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkSnapshot {
+    /// The chunk, in units of `chunk_size`.
+    pub chunk: UVec2,
+    pub chunk_size: UVec2,
+    /// The tiles of the chunk, ordered by row. Positions without a tile are left out.
+    pub tiles: Vec<TileSnapshot>,
+}
+
+impl ChunkSnapshot {
+    /// Returns the positions of the chunk which lie on a map of the given storage.
+    fn positions(&self, tile_storage: &TileStorage) -> Vec<TilePos> {
+        let min = self.chunk * self.chunk_size;
+        let max = (min + self.chunk_size).min(UVec2::new(tile_storage.size.x, tile_storage.size.y));
+        (min.y..max.y)
+            .flat_map(|y| (min.x..max.x).map(move |x| TilePos::new(x, y)))
+            .collect()
+    }
+}
+
+/// Saves the tiles of the given chunk of a tilemap.
+///
+/// Returns `None` if `tilemap` has no [`TileStorage`].
+pub fn snapshot_chunk(
+    world: &World,
+    tilemap: Entity,
+    chunk: UVec2,
+    chunk_size: UVec2,
+) -> Option<ChunkSnapshot> {
+    let tile_storage = world.get::<TileStorage>(tilemap)?;
+    let mut snapshot = ChunkSnapshot {
+        chunk,
+        chunk_size,
+        tiles: Vec::new(),
+    };
+    for tile_pos in snapshot.positions(tile_storage) {
+        let Some(tile) = tile_storage
+            .get(&tile_pos)
+            .and_then(|tile_entity| world.get_entity(tile_entity).ok())
+        else {
+            continue;
+        };
+        snapshot.tiles.push(TileSnapshot {
+            position: tile_pos,
+            texture_index: tile.get().copied().unwrap_or_default(),
+            visible: tile.get().copied().unwrap_or_default(),
+            flip: tile.get().copied().unwrap_or_default(),
+            color: tile.get().copied().unwrap_or_default(),
+        });
+    }
+    Some(snapshot)
+}
+
+/// Replaces the tiles of a chunk of a tilemap with the ones saved in `snapshot`.
+///
+/// The tiles in the chunk are despawned, and the saved tiles are spawned as children of the
+/// tilemap and put into its [`TileStorage`]. Does nothing if `tilemap` has no [`TileStorage`].
+///
+/// The saved tiles are decoded in parallel on the [`ComputeTaskPool`], and spawned in a single
+/// batch.
+pub fn restore_chunk(world: &mut World, tilemap: Entity, snapshot: &ChunkSnapshot) {
+    let Some(mut tile_storage) = world.get_mut::<TileStorage>(tilemap) else {
+        return;
+    };
+    let old_tiles = snapshot
+        .positions(&tile_storage)
+        .into_iter()
+        .filter_map(|tile_pos| tile_storage.remove(&tile_pos))
+        .collect::<Vec<_>>();
+    for tile_entity in old_tiles {
+        world.despawn(tile_entity);
+    }
+
+    let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let batch_size = snapshot.tiles.len().div_ceil(task_pool.thread_num()).max(1);
+    let tiles = snapshot
+        .tiles
+        .par_chunk_map(task_pool, batch_size, |_, tiles| {
+            tiles
+                .iter()
+                .map(|tile| {
+                    (
+                        TileBundle {
+                            position: tile.position,
+                            texture_index: tile.texture_index,
+                            tilemap_id: TilemapId(tilemap),
+                            visible: tile.visible,
+                            flip: tile.flip,
+                            color: tile.color,
+                            old_position: TilePosOld(tile.position),
+                            ..Default::default()
+                        },
+                        ChildOf(tilemap),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten();
+    let tile_entities = world.spawn_batch(tiles).collect::<Vec<_>>();
+
+    let mut tile_storage = world.get_mut::<TileStorage>(tilemap).unwrap();
+    for (tile, tile_entity) in snapshot.tiles.iter().zip(tile_entities) {
+        tile_storage.checked_set(&tile.position, tile_entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{DirtyChunksMode, TilemapDirtyChunks, TilemapSize};
+
+    #[test]
+    fn dirty_chunks_are_saved_and_restored() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(crate::map::update_dirty_chunks);
+        let map_size = TilemapSize::new(8, 8);
+        let tilemap = world
+            .spawn((
+                map_size,
+                TileStorage::empty(map_size),
+                TilemapDirtyChunks::new(DirtyChunksMode::UntilTaken)
+                    .with_chunk_size(UVec2::new(4, 4)),
+            ))
+            .id();
+        for index in 0..map_size.count() as u32 {
+            let tile_pos = TilePos::new(index % map_size.x, index / map_size.x);
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    ..Default::default()
+                })
+                .id();
+            let mut tile_storage = world.get_mut::<TileStorage>(tilemap).unwrap();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        schedule.run(&mut world);
+        assert_eq!(world.get::<TilemapDirtyChunks>(tilemap).unwrap().len(), 4);
+        world.get_mut::<TilemapDirtyChunks>(tilemap).unwrap().take();
+
+        // Painting a tile dirties its chunk only.
+        let painted = world
+            .get::<TileStorage>(tilemap)
+            .unwrap()
+            .get(&TilePos::new(5, 1));
+        world
+            .get_mut::<TileTextureIndex>(painted.unwrap())
+            .unwrap()
+            .0 = 7;
+        schedule.run(&mut world);
+        let dirty = world.get_mut::<TilemapDirtyChunks>(tilemap).unwrap().take();
+        assert_eq!(dirty, vec![UVec2::new(1, 0)]);
+
+        let snapshot = snapshot_chunk(&world, tilemap, dirty[0], UVec2::new(4, 4)).unwrap();
+        assert_eq!(snapshot.tiles.len(), 16);
+
+        // Restoring the chunk after it was painted over brings the saved tile back.
+        world
+            .get_mut::<TileTextureIndex>(painted.unwrap())
+            .unwrap()
+            .0 = 2;
+        restore_chunk(&mut world, tilemap, &snapshot);
+        let tile_storage = world.get::<TileStorage>(tilemap).unwrap();
+        let restored = tile_storage.get(&TilePos::new(5, 1)).unwrap();
+        assert_ne!(Some(restored), painted);
+        assert_eq!(
+            world.get::<TileTextureIndex>(restored),
+            Some(&TileTextureIndex(7))
+        );
+        assert!(world.get_entity(painted.unwrap()).is_err());
+        assert_eq!(world.query::<&TilePos>().iter(&world).count(), 64);
+    }
+}
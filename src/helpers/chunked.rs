@@ -0,0 +1,718 @@
+//! Streams maps which are too large to keep in memory in and out as separate tilemap chunks
+//! around one or more [`ChunkLoader`]s.
+//!
+//! Add the [`ChunkStreamingPlugin`], spawn an entity with a [`ChunkedTilemap`] and give the
+//! camera or player a [`ChunkLoader`]. Missing chunks in range of a loader are generated by the
+//! tilemap's [`ChunkProvider`] and spawned a few per frame, in the order of its
+//! [`spawn_queue`](ChunkedTilemap::spawn_queue). Chunks out of range of every loader are
+//! despawned.
+//!
+//! By default, changes made to the tiles of a chunk are lost when it is despawned, and the
+//! provider generates it again when it comes back into range. With a [`DormantChunkCodec`], the
+//! tiles of modified chunks are kept in memory in its encoding instead, and respawned from there.
+
+use std::fmt;
+use std::sync::Arc;
+
+use bevy::log::warn;
+use bevy::math::{IVec2, UVec2, Vec2};
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use crate::builder::TilemapBuilder;
+use crate::helpers::selection::unclamped_tile_coords;
+use crate::helpers::streaming::{ChunkFocus, ChunkSpawnQueue};
+use crate::map::{
+    TilemapGridSize, TilemapId, TilemapRenderSettings, TilemapTexture, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{
+    TileBundle, TileColor, TileFlip, TilePos, TileStorage, TileTextureIndex, TileVisible,
+};
+
+/// Adds the system which streams the chunks of every [`ChunkedTilemap`].
+pub struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ChunkLoader>().add_systems(
+            PostUpdate,
+            (track_modified_chunks, stream_chunked_tilemaps)
+                .chain()
+                .before(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// The tiles of a chunk, filled in by a [`ChunkProvider`].
+///
+/// The [`position`](TileBundle::position) and [`tilemap_id`](TileBundle::tilemap_id) of the
+/// tiles are set when the chunk is spawned.
+#[derive(Clone, Debug)]
+pub struct ChunkTiles {
+    size: UVec2,
+    tiles: Vec<Option<TileBundle>>,
+}
+
+impl ChunkTiles {
+    /// Creates an empty chunk of the given size, in tiles.
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            tiles: vec![None; (size.x * size.y) as usize],
+        }
+    }
+
+    /// The size of the chunk, in tiles.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Returns the tile at a position within the chunk, if there is one.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<&TileBundle> {
+        self.index(tile_pos)
+            .and_then(|index| self.tiles[index].as_ref())
+    }
+
+    /// Sets or clears the tile at a position within the chunk. Positions outside of the chunk
+    /// are ignored.
+    pub fn set(&mut self, tile_pos: &TilePos, tile: Option<TileBundle>) {
+        if let Some(index) = self.index(tile_pos) {
+            self.tiles[index] = tile;
+        }
+    }
+
+    /// Sets every tile of the chunk to a copy of `tile`.
+    pub fn fill(&mut self, tile: TileBundle) {
+        self.tiles.fill(Some(tile));
+    }
+
+    fn index(&self, tile_pos: &TilePos) -> Option<usize> {
+        (tile_pos.x < self.size.x && tile_pos.y < self.size.y)
+            .then(|| (tile_pos.y * self.size.x + tile_pos.x) as usize)
+    }
+}
+
+/// Generates or loads the tiles of chunks on demand.
+///
+/// It is implemented for closures taking the index of a chunk and the [`ChunkTiles`] to fill.
+pub trait ChunkProvider: Send + Sync + 'static {
+    /// Fills the tiles of the chunk with the given index, which is about to be spawned.
+    fn load(&self, chunk: IVec2, tiles: &mut ChunkTiles);
+
+    /// Called when the chunk with the given index is despawned.
+    fn unload(&self, _chunk: IVec2) {}
+}
+
+impl<F> ChunkProvider for F
+where
+    F: Fn(IVec2, &mut ChunkTiles) + Send + Sync + 'static,
+{
+    fn load(&self, chunk: IVec2, tiles: &mut ChunkTiles) {
+        self(chunk, tiles)
+    }
+}
+
+/// Encodes the tiles of modified chunks which went out of range, so they can be kept in memory
+/// until they are spawned again.
+///
+/// Codecs trade the memory taken by dormant chunks against the time spent encoding and decoding
+/// them: [`RawChunkCodec`] stores every tile as is, while [`RunLengthChunkCodec`] is much smaller
+/// for chunks made of large areas of the same tile.
+pub trait DormantChunkCodec: Send + Sync + 'static {
+    fn encode(&self, tiles: &ChunkTiles) -> Vec<u8>;
+
+    /// Fills `tiles`, which is empty and of the size of the encoded chunk, from the `bytes`
+    /// returned by [`encode`](Self::encode).
+    ///
+    /// Returns an error if the bytes are truncated or do not match the size of `tiles`, in which
+    /// case the chunk is generated by the provider instead.
+    fn decode(&self, bytes: &[u8], tiles: &mut ChunkTiles) -> Result<(), DormantChunkError>;
+}
+
+/// An error while decoding a dormant chunk with [`DormantChunkCodec::decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DormantChunkError;
+
+impl fmt::Display for DormantChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the dormant chunk is damaged")
+    }
+}
+
+impl std::error::Error for DormantChunkError {}
+
+/// The bytes a tile, or the lack of one, is stored in by the codecs of this module: a byte of
+/// flags, followed by the texture index and the linear RGBA color of the tile.
+const TILE_RECORD_SIZE: usize = 21;
+
+fn encode_tile(tile: Option<&TileBundle>, bytes: &mut Vec<u8>) {
+    let Some(tile) = tile else {
+        bytes.extend_from_slice(&[0; TILE_RECORD_SIZE]);
+        return;
+    };
+    let flags = 1
+        | (tile.visible.0 as u8) << 1
+        | (tile.flip.x as u8) << 2
+        | (tile.flip.y as u8) << 3
+        | (tile.flip.d as u8) << 4;
+    bytes.push(flags);
+    bytes.extend_from_slice(&tile.texture_index.0.to_le_bytes());
+    for channel in tile.color.0.to_linear().to_f32_array() {
+        bytes.extend_from_slice(&channel.to_le_bytes());
+    }
+}
+
+fn decode_tile(record: &[u8]) -> Result<Option<TileBundle>, DormantChunkError> {
+    let record: &[u8; TILE_RECORD_SIZE] = record.try_into().map_err(|_| DormantChunkError)?;
+    let flags = record[0];
+    if flags & 1 == 0 {
+        return Ok(None);
+    }
+    let read = |offset: usize| {
+        [
+            record[offset],
+            record[offset + 1],
+            record[offset + 2],
+            record[offset + 3],
+        ]
+    };
+    let channel = |index: usize| f32::from_le_bytes(read(5 + index * 4));
+    Ok(Some(TileBundle {
+        texture_index: TileTextureIndex(u32::from_le_bytes(read(1))),
+        visible: TileVisible(flags & 2 != 0),
+        flip: TileFlip {
+            x: flags & 4 != 0,
+            y: flags & 8 != 0,
+            d: flags & 16 != 0,
+        },
+        color: TileColor(Color::linear_rgba(
+            channel(0),
+            channel(1),
+            channel(2),
+            channel(3),
+        )),
+        ..Default::default()
+    }))
+}
+
+/// Stores every tile of a dormant chunk, empty or not, in a fixed number of bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawChunkCodec;
+
+impl DormantChunkCodec for RawChunkCodec {
+    fn encode(&self, tiles: &ChunkTiles) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(tiles.tiles.len() * TILE_RECORD_SIZE);
+        for tile in &tiles.tiles {
+            encode_tile(tile.as_ref(), &mut bytes);
+        }
+        bytes
+    }
+
+    fn decode(&self, bytes: &[u8], tiles: &mut ChunkTiles) -> Result<(), DormantChunkError> {
+        if bytes.len() != tiles.tiles.len() * TILE_RECORD_SIZE {
+            return Err(DormantChunkError);
+        }
+        for (tile, record) in tiles.tiles.iter_mut().zip(bytes.chunks(TILE_RECORD_SIZE)) {
+            *tile = decode_tile(record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stores a dormant chunk as runs of identical tiles, in row order, each a 4 byte length
+/// followed by the tile.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunLengthChunkCodec;
+
+impl DormantChunkCodec for RunLengthChunkCodec {
+    fn encode(&self, tiles: &ChunkTiles) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut record = Vec::with_capacity(TILE_RECORD_SIZE);
+        let mut run: Option<(u32, Vec<u8>)> = None;
+        for tile in &tiles.tiles {
+            record.clear();
+            encode_tile(tile.as_ref(), &mut record);
+            match &mut run {
+                Some((length, run_record)) if *run_record == record => *length += 1,
+                _ => {
+                    if let Some((length, run_record)) = run.replace((1, record.clone())) {
+                        bytes.extend_from_slice(&length.to_le_bytes());
+                        bytes.extend_from_slice(&run_record);
+                    }
+                }
+            }
+        }
+        if let Some((length, run_record)) = run {
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&run_record);
+        }
+        bytes
+    }
+
+    fn decode(&self, bytes: &[u8], tiles: &mut ChunkTiles) -> Result<(), DormantChunkError> {
+        let runs = bytes.chunks_exact(4 + TILE_RECORD_SIZE);
+        if !runs.remainder().is_empty() {
+            return Err(DormantChunkError);
+        }
+        let mut slots = tiles.tiles.iter_mut();
+        for run in runs {
+            let length = u32::from_le_bytes([run[0], run[1], run[2], run[3]]) as usize;
+            let tile = decode_tile(&run[4..])?;
+            // The runs cover every tile of the chunk, and nothing beyond it.
+            if length == 0 || slots.len() < length {
+                return Err(DormantChunkError);
+            }
+            for slot in slots.by_ref().take(length) {
+                *slot = tile;
+            }
+        }
+        if slots.len() > 0 {
+            return Err(DormantChunkError);
+        }
+        Ok(())
+    }
+}
+
+/// An unbounded map made of tilemap chunks, which are spawned around [`ChunkLoader`]s.
+///
+/// Each chunk is a regular tilemap spawned as a child of this entity, with a
+/// [`StreamedChunk`] component. Changing the settings only affects chunks loaded afterwards.
+///
+/// Chunks are laid out by repeating the offset between tiles, so hexagonal maps with offset
+/// coordinates and staggered isometric maps need an even chunk size along their staggered axis.
+#[derive(Component, Clone)]
+#[require(Transform, Visibility)]
+pub struct ChunkedTilemap {
+    /// The size of each chunk, in tiles.
+    pub chunk_size: UVec2,
+    pub tile_size: TilemapTileSize,
+    pub grid_size: TilemapGridSize,
+    pub map_type: TilemapType,
+    pub texture: TilemapTexture,
+    pub render_settings: TilemapRenderSettings,
+    /// How many chunks beyond the radius of a loader are kept before they are despawned, so
+    /// chunks at the edge are not respawned whenever a loader moves back and forth.
+    pub unload_margin: u32,
+    /// The chunks waiting to be spawned.
+    pub spawn_queue: ChunkSpawnQueue,
+    /// How modified chunks are kept while they are out of range. Without a codec, their changes
+    /// are lost.
+    pub dormant_codec: Option<Arc<dyn DormantChunkCodec>>,
+    provider: Arc<dyn ChunkProvider>,
+    loaded: HashMap<IVec2, Entity>,
+    modified: HashSet<IVec2>,
+    dormant: HashMap<IVec2, Vec<u8>>,
+    last_focus: Option<Vec2>,
+}
+
+impl ChunkedTilemap {
+    /// Creates a square chunked tilemap whose tiles are provided by `provider`.
+    pub fn new(
+        provider: impl ChunkProvider,
+        chunk_size: UVec2,
+        tile_size: TilemapTileSize,
+        texture: TilemapTexture,
+    ) -> Self {
+        Self {
+            chunk_size,
+            tile_size,
+            grid_size: tile_size.into(),
+            map_type: TilemapType::Square,
+            texture,
+            render_settings: TilemapRenderSettings::default(),
+            unload_margin: 1,
+            spawn_queue: ChunkSpawnQueue::default(),
+            dormant_codec: None,
+            provider: Arc::new(provider),
+            loaded: HashMap::default(),
+            modified: HashSet::default(),
+            dormant: HashMap::default(),
+            last_focus: None,
+        }
+    }
+
+    /// Keeps modified chunks which go out of range in memory, encoded by `codec`.
+    pub fn with_dormant_codec(mut self, codec: impl DormantChunkCodec) -> Self {
+        self.dormant_codec = Some(Arc::new(codec));
+        self
+    }
+
+    /// Marks a loaded chunk as modified, so it is kept when it goes out of range.
+    ///
+    /// Chunks are marked automatically when the texture, visibility, flip or color of one of
+    /// their tiles changes. Code which despawns or spawns tiles should mark their chunk itself.
+    pub fn mark_modified(&mut self, chunk: IVec2) {
+        if self.loaded.contains_key(&chunk) {
+            self.modified.insert(chunk);
+        }
+    }
+
+    /// Returns `true` if the chunk is kept in memory while it is out of range.
+    pub fn is_dormant(&self, chunk: &IVec2) -> bool {
+        self.dormant.contains_key(chunk)
+    }
+
+    /// Returns the number of dormant chunks, and the number of bytes they take up.
+    pub fn dormant_usage(&self) -> (usize, usize) {
+        let bytes = self.dormant.values().map(Vec::len).sum();
+        (self.dormant.len(), bytes)
+    }
+
+    /// Forgets a dormant chunk, so it is generated by the provider the next time it is loaded.
+    pub fn discard_dormant(&mut self, chunk: &IVec2) -> bool {
+        self.dormant.remove(chunk).is_some()
+    }
+
+    /// Returns the tilemap entity of a loaded chunk.
+    pub fn loaded_chunk(&self, chunk: &IVec2) -> Option<Entity> {
+        self.loaded.get(chunk).copied()
+    }
+
+    /// Returns the index and tilemap entity of every loaded chunk.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+        self.loaded.iter().map(|(chunk, entity)| (*chunk, *entity))
+    }
+
+    /// Returns the position of the chunk's tilemap, relative to this entity.
+    pub fn chunk_origin(&self, chunk: &IVec2) -> Vec2 {
+        let step_x = TilePos::new(self.chunk_size.x, 0)
+            .center_in_world_unanchored(&self.grid_size, &self.map_type);
+        let step_y = TilePos::new(0, self.chunk_size.y)
+            .center_in_world_unanchored(&self.grid_size, &self.map_type);
+        step_x * chunk.x as f32 + step_y * chunk.y as f32
+    }
+
+    /// Returns the index of the chunk containing a position relative to this entity.
+    pub fn chunk_at(&self, local_pos: &Vec2) -> IVec2 {
+        let chunk_size = self.chunk_size.max(UVec2::ONE).as_ivec2();
+        unclamped_tile_coords(local_pos, &self.grid_size, &self.map_type).div_euclid(chunk_size)
+    }
+}
+
+/// Makes the [`ChunkedTilemap`]s load the chunks around this entity, up to `radius` chunks away
+/// from the chunk it is in.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+#[require(Transform)]
+pub struct ChunkLoader {
+    pub radius: u32,
+}
+
+impl Default for ChunkLoader {
+    fn default() -> Self {
+        Self { radius: 2 }
+    }
+}
+
+/// Marks the tilemap of a chunk spawned by a [`ChunkedTilemap`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamedChunk {
+    /// The index of the chunk.
+    pub index: IVec2,
+    /// The entity of the [`ChunkedTilemap`].
+    pub chunked_tilemap: Entity,
+}
+
+fn chunk_distance(a: IVec2, b: IVec2) -> u32 {
+    let d = (a - b).abs();
+    d.x.max(d.y) as u32
+}
+
+/// Marks the chunks whose tiles changed since they were spawned.
+#[allow(clippy::type_complexity)]
+fn track_modified_chunks(
+    tiles: Query<
+        (&TilemapId, Ref<TileTextureIndex>),
+        Or<(
+            Changed<TileTextureIndex>,
+            Changed<TileVisible>,
+            Changed<TileFlip>,
+            Changed<TileColor>,
+        )>,
+    >,
+    chunks: Query<&StreamedChunk>,
+    mut tilemaps: Query<&mut ChunkedTilemap>,
+) {
+    for (tilemap_id, texture_index) in tiles.iter() {
+        if texture_index.is_added() {
+            continue;
+        }
+        if let Ok(chunk) = chunks.get(tilemap_id.0)
+            && let Ok(mut chunked) = tilemaps.get_mut(chunk.chunked_tilemap)
+            && chunked.dormant_codec.is_some()
+            && !chunked.modified.contains(&chunk.index)
+        {
+            chunked.modified.insert(chunk.index);
+        }
+    }
+}
+
+/// Reads the tiles of a spawned chunk back into [`ChunkTiles`].
+fn chunk_tiles(
+    chunk_size: UVec2,
+    tile_storage: &TileStorage,
+    tiles: &Query<(&TileTextureIndex, &TileVisible, &TileFlip, &TileColor)>,
+) -> ChunkTiles {
+    let mut chunk_tiles = ChunkTiles::new(chunk_size);
+    for (tile_pos, tile_entity) in tile_storage.iter_some() {
+        if let Ok((texture_index, visible, flip, color)) = tiles.get(tile_entity) {
+            chunk_tiles.set(
+                &tile_pos,
+                Some(TileBundle {
+                    texture_index: *texture_index,
+                    visible: *visible,
+                    flip: *flip,
+                    color: *color,
+                    ..Default::default()
+                }),
+            );
+        }
+    }
+    chunk_tiles
+}
+
+/// Despawns the chunks which are out of range, and spawns the queued chunks in range of the
+/// [`ChunkLoader`]s.
+#[allow(clippy::type_complexity)]
+fn stream_chunked_tilemaps(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tilemaps: Query<(Entity, &mut ChunkedTilemap, &GlobalTransform)>,
+    loaders: Query<(&ChunkLoader, &GlobalTransform)>,
+    tile_storages: Query<&TileStorage>,
+    tiles: Query<(&TileTextureIndex, &TileVisible, &TileFlip, &TileColor)>,
+) {
+    for (tilemap_entity, mut chunked, global_transform) in tilemaps.iter_mut() {
+        let chunked = &mut *chunked;
+        let to_local = global_transform.affine().inverse();
+        let loader_positions = loaders
+            .iter()
+            .map(|(loader, transform)| {
+                let local = to_local
+                    .transform_point3(transform.translation())
+                    .truncate();
+                (local, chunked.chunk_at(&local), loader.radius)
+            })
+            .collect::<Vec<_>>();
+
+        let unload_margin = chunked.unload_margin;
+        let out_of_range = chunked
+            .loaded
+            .keys()
+            .filter(|chunk| {
+                !loader_positions.iter().any(|(_, loader_chunk, radius)| {
+                    chunk_distance(**chunk, *loader_chunk) <= radius + unload_margin
+                })
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        for chunk in out_of_range {
+            if let Some(entity) = chunked.loaded.remove(&chunk) {
+                if chunked.modified.remove(&chunk)
+                    && let Some(codec) = &chunked.dormant_codec
+                    && let Ok(tile_storage) = tile_storages.get(entity)
+                {
+                    let bytes =
+                        codec.encode(&chunk_tiles(chunked.chunk_size, tile_storage, &tiles));
+                    chunked.dormant.insert(chunk, bytes);
+                }
+                chunked.provider.unload(chunk);
+                commands.entity(entity).despawn();
+            }
+        }
+
+        chunked.spawn_queue.retain(|chunk| {
+            loader_positions
+                .iter()
+                .any(|(_, loader_chunk, radius)| chunk_distance(*chunk, *loader_chunk) <= *radius)
+        });
+        for (_, loader_chunk, radius) in &loader_positions {
+            let radius = *radius as i32;
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let chunk = *loader_chunk + IVec2::new(x, y);
+                    if !chunked.loaded.contains_key(&chunk) {
+                        chunked.spawn_queue.push(chunk);
+                    }
+                }
+            }
+        }
+
+        // The first loader is the focus of the queue, with its velocity measured in chunks.
+        let Some((focus_pos, focus_chunk, _)) = loader_positions.first().copied() else {
+            chunked.last_focus = None;
+            continue;
+        };
+        let chunk_extent = (chunked.chunk_origin(&IVec2::ONE) - chunked.chunk_origin(&IVec2::ZERO))
+            .abs()
+            .max(Vec2::splat(f32::EPSILON));
+        let velocity = match chunked.last_focus {
+            Some(last_focus) if time.delta_secs() > 0.0 => {
+                (focus_pos - last_focus) / chunk_extent / time.delta_secs()
+            }
+            _ => Vec2::ZERO,
+        };
+        chunked.last_focus = Some(focus_pos);
+
+        let focus = ChunkFocus {
+            chunk: focus_chunk,
+            velocity,
+        };
+        for chunk in chunked.spawn_queue.pop_budgeted(&focus) {
+            let entity = spawn_chunk(&mut commands, tilemap_entity, chunked, chunk);
+            chunked.loaded.insert(chunk, entity);
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    chunked_tilemap: Entity,
+    chunked: &mut ChunkedTilemap,
+    chunk: IVec2,
+) -> Entity {
+    let mut tiles = ChunkTiles::new(chunked.chunk_size);
+    let decoded = match (chunked.dormant.remove(&chunk), &chunked.dormant_codec) {
+        (Some(bytes), Some(codec)) => codec
+            .decode(&bytes, &mut tiles)
+            .inspect_err(|err| warn!("Generating chunk {chunk} again: {err}"))
+            .is_ok(),
+        _ => false,
+    };
+    if decoded {
+        // It still differs from what the provider generates.
+        chunked.modified.insert(chunk);
+    } else {
+        tiles = ChunkTiles::new(chunked.chunk_size);
+        chunked.provider.load(chunk, &mut tiles);
+    }
+
+    let tilemap_entity = commands
+        .spawn((
+            StreamedChunk {
+                index: chunk,
+                chunked_tilemap,
+            },
+            ChildOf(chunked_tilemap),
+        ))
+        .id();
+
+    let mut storage = TileStorage::empty(chunked.chunk_size.into());
+    for (index, tile) in tiles.tiles.into_iter().enumerate() {
+        let Some(tile) = tile else {
+            continue;
+        };
+        let tile_pos = TilePos::new(
+            index as u32 % chunked.chunk_size.x,
+            index as u32 / chunked.chunk_size.x,
+        );
+        let tile_entity = commands
+            .spawn((
+                TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    ..tile
+                },
+                ChildOf(tilemap_entity),
+            ))
+            .id();
+        storage.set(&tile_pos, tile_entity);
+    }
+
+    commands.entity(tilemap_entity).insert(
+        TilemapBuilder::new()
+            .size(chunked.chunk_size.into())
+            .tile_size(chunked.tile_size)
+            .grid_size(chunked.grid_size)
+            .map_type(chunked.map_type)
+            .texture(chunked.texture.clone())
+            .render_settings(chunked.render_settings)
+            .transform(Transform::from_translation(
+                chunked.chunk_origin(&chunk).extend(0.0),
+            ))
+            .build(storage),
+    );
+    tilemap_entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_at_finds_the_chunk_of_its_origin() {
+        let chunked = ChunkedTilemap::new(
+            |_: IVec2, _: &mut ChunkTiles| {},
+            UVec2::new(8, 4),
+            TilemapTileSize::new(16.0, 16.0),
+            TilemapTexture::default(),
+        );
+        for chunk in [IVec2::ZERO, IVec2::new(3, -2), IVec2::new(-1, -1)] {
+            let origin = chunked.chunk_origin(&chunk);
+            assert_eq!(chunked.chunk_at(&origin), chunk);
+        }
+        assert_eq!(chunked.chunk_at(&Vec2::new(-9.0, 70.0)), IVec2::new(-1, 1));
+    }
+
+    #[test]
+    fn dormant_chunks_survive_encoding() {
+        let mut tiles = ChunkTiles::new(UVec2::new(8, 8));
+        tiles.fill(TileBundle {
+            texture_index: TileTextureIndex(3),
+            ..Default::default()
+        });
+        tiles.set(&TilePos::new(2, 1), None);
+        tiles.set(
+            &TilePos::new(5, 6),
+            Some(TileBundle {
+                texture_index: TileTextureIndex(70_000),
+                visible: TileVisible(false),
+                flip: TileFlip {
+                    x: true,
+                    y: false,
+                    d: true,
+                },
+                color: TileColor(Color::srgba(0.25, 0.5, 1.0, 0.75)),
+                ..Default::default()
+            }),
+        );
+
+        let raw = RawChunkCodec.encode(&tiles);
+        let run_length = RunLengthChunkCodec.encode(&tiles);
+        assert_eq!(raw.len(), 64 * TILE_RECORD_SIZE);
+        // The filled area before, between and after the two odd tiles, and the tiles themselves.
+        assert_eq!(run_length.len(), 5 * (4 + TILE_RECORD_SIZE));
+
+        for (codec, bytes) in [
+            (&RawChunkCodec as &dyn DormantChunkCodec, raw),
+            (&RunLengthChunkCodec, run_length),
+        ] {
+            let mut decoded = ChunkTiles::new(tiles.size());
+            codec.decode(&bytes, &mut decoded).unwrap();
+            for (original, decoded) in tiles.tiles.iter().zip(&decoded.tiles) {
+                let key = |tile: &Option<TileBundle>| {
+                    tile.map(|tile| {
+                        (
+                            tile.texture_index,
+                            tile.visible,
+                            tile.flip,
+                            tile.color.0.to_linear(),
+                        )
+                    })
+                };
+                assert_eq!(key(original), key(decoded));
+            }
+
+            // Truncated chunks, or chunks of another size, are rejected.
+            let mut decoded = ChunkTiles::new(tiles.size());
+            for truncated in [&bytes[..bytes.len() - 1], &bytes[..bytes.len() - 25], &[]] {
+                assert_eq!(
+                    codec.decode(truncated, &mut decoded),
+                    Err(DormantChunkError)
+                );
+            }
+            let mut larger = ChunkTiles::new(UVec2::new(8, 9));
+            assert_eq!(codec.decode(&bytes, &mut larger), Err(DormantChunkError));
+        }
+    }
+}
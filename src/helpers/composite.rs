@@ -0,0 +1,106 @@
+//! Helpers for compositing one tilemap onto another, e.g. to stamp prefab maps (puzzle pieces,
+//! districts) into a world map at runtime.
+
+use crate::map::TilemapId;
+use crate::tiles::{
+    AnimatedTile, TileBundle, TileColor, TileFlip, TilePos, TileStorage, TileTextureIndex,
+    TileVisible,
+};
+use bevy::prelude::{Commands, Query};
+
+/// Decides how the tiles of the source map are combined with the tiles of the destination map by
+/// [`composite`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CompositeMode {
+    /// Source tiles overwrite destination tiles. Where the source is empty, the destination is
+    /// left untouched.
+    #[default]
+    SkipEmpty,
+    /// Source tiles overwrite destination tiles. Where the source is empty, the destination tile
+    /// is despawned, so the whole area covered by the source map is replaced.
+    ClearEmpty,
+    /// Source tiles are only placed where the destination is empty; existing destination tiles are
+    /// never overwritten.
+    KeepDestination,
+}
+
+/// Copies the tiles of a source map into a destination map, placing the source's `(0, 0)` at
+/// `origin` in the destination.
+///
+/// Overwritten destination tiles keep their entities; only their [`TileTextureIndex`],
+/// [`TileColor`], [`TileFlip`], [`TileVisible`] and [`AnimatedTile`] components are replaced with
+/// those of the source tile. Source tiles that fall outside of the destination map are ignored.
+///
+/// `src_tiles` must contain the tiles of `src_storage`. The source map itself is not modified.
+#[allow(clippy::too_many_arguments)]
+pub fn composite(
+    dst_tilemap_id: TilemapId,
+    dst_storage: &mut TileStorage,
+    src_storage: &TileStorage,
+    src_tiles: &Query<(
+        &TileTextureIndex,
+        &TileColor,
+        &TileFlip,
+        &TileVisible,
+        Option<&AnimatedTile>,
+    )>,
+    origin: TilePos,
+    mode: CompositeMode,
+    commands: &mut Commands,
+) {
+    for y in 0..src_storage.size.y {
+        for x in 0..src_storage.size.x {
+            let (Some(dst_x), Some(dst_y)) = (origin.x.checked_add(x), origin.y.checked_add(y))
+            else {
+                continue;
+            };
+            let dst_pos = TilePos { x: dst_x, y: dst_y };
+            if !dst_pos.within_map_bounds(&dst_storage.size) {
+                continue;
+            }
+
+            let src_tile = src_storage
+                .get(&TilePos { x, y })
+                .and_then(|entity| src_tiles.get(entity).ok());
+            let dst_entity = dst_storage.get(&dst_pos);
+
+            let Some((texture_index, color, flip, visible, animated)) = src_tile else {
+                if mode == CompositeMode::ClearEmpty
+                    && let Some(dst_entity) = dst_storage.remove(&dst_pos)
+                {
+                    commands.entity(dst_entity).despawn();
+                }
+                continue;
+            };
+
+            match dst_entity {
+                Some(_) if mode == CompositeMode::KeepDestination => {}
+                Some(dst_entity) => {
+                    let mut entity_commands = commands.entity(dst_entity);
+                    entity_commands.insert((*texture_index, *color, *flip, *visible));
+                    match animated {
+                        Some(animated) => entity_commands.insert(*animated),
+                        None => entity_commands.remove::<AnimatedTile>(),
+                    };
+                }
+                None => {
+                    let mut entity_commands = commands.spawn(TileBundle {
+                        position: dst_pos,
+                        tilemap_id: dst_tilemap_id,
+                        texture_index: *texture_index,
+                        color: *color,
+                        flip: *flip,
+                        visible: *visible,
+                        ..Default::default()
+                    });
+                    if let Some(animated) = animated {
+                        entity_commands.insert(*animated);
+                    }
+                    let dst_entity = entity_commands.id();
+                    commands.entity(dst_tilemap_id.0).add_child(dst_entity);
+                    dst_storage.set(&dst_pos, dst_entity);
+                }
+            }
+        }
+    }
+}
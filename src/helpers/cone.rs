@@ -0,0 +1,289 @@
+//! Cones and arcs of tiles spreading out from a tile in a direction, e.g. for breath attacks and
+//! vision cones, on hex and square grids.
+//!
+//! A tile lies in a cone if the direction from the origin to its center is at most half of the
+//! cone's `angle` (in radians) away from the `facing` direction. Cones are measured on the grid,
+//! not in world space, so a cone covers the same tiles whatever the grid size. Tiles right on the
+//! border of a cone are part of it. The origin itself is never part of a cone.
+//!
+//! The facing can point anywhere, e.g. from a unit towards the cursor. [`hex_facing`] and
+//! [`square_facing`] give the facings of the neighbor directions.
+//!
+//! Cones contain the tiles up to `radius` steps away, and arcs only the ones exactly `radius`
+//! steps away. Steps on square grids include the diagonal ones. The `_tiles` variants take and
+//! return [`TilePos`]es, leaving out the ones outside of the map.
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::helpers::hex_grid::axial::{AxialPos, COL_BASIS, ROW_BASIS};
+use crate::helpers::hex_grid::neighbors::HexDirection;
+use crate::helpers::square_grid::SquarePos;
+use crate::helpers::square_grid::neighbors::SquareDirection;
+use crate::map::{HexCoordSystem, TilemapSize};
+use crate::tiles::TilePos;
+
+/// Tiles whose direction is this close to the border of the cone, as a cosine, are inside it, so
+/// a cone of `90` degrees includes the diagonals on either side.
+const EPSILON: f32 = 1e-4;
+
+/// Returns whether `offset` lies within half of `angle` of `facing`.
+fn in_cone(offset: Vec2, facing: Vec2, angle: f32) -> bool {
+    if offset == Vec2::ZERO {
+        return false;
+    }
+    if angle >= std::f32::consts::TAU {
+        return true;
+    }
+    offset.normalize().dot(facing.normalize()) >= (angle / 2.0).cos() - EPSILON
+}
+
+/// Maps an axial offset onto the plane of a hex grid with the given basis, so that the hex
+/// directions are `60` degrees apart.
+fn hex_plane_offset(offset: AxialPos, basis: Mat2) -> Vec2 {
+    basis * Vec2::new(offset.q as f32, offset.r as f32)
+}
+
+/// Returns the basis of the plane the tiles of a hex grid lie in, in world space.
+fn hex_basis(hex_coord_sys: HexCoordSystem) -> Mat2 {
+    match hex_coord_sys {
+        HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => ROW_BASIS,
+        HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+            COL_BASIS
+        }
+    }
+}
+
+/// Returns the facing of a cone pointing at the neighbor of a hex in `direction`, for
+/// [`hex_cone`] and [`hex_arc`].
+pub fn hex_facing(direction: HexDirection) -> Vec2 {
+    hex_plane_offset(AxialPos::from(direction), ROW_BASIS)
+}
+
+/// Returns the hex positions within `radius` of `origin`, in a cone of `angle` radians around
+/// `facing`, nearest rings first.
+///
+/// `facing` is a direction in the plane of a row oriented ("pointy top") grid, i.e. of
+/// [`ROW_BASIS`].
+pub fn hex_cone(
+    origin: AxialPos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+) -> impl Iterator<Item = AxialPos> {
+    origin.spiral(radius).filter(move |axial_pos| {
+        in_cone(
+            hex_plane_offset(*axial_pos - origin, ROW_BASIS),
+            facing,
+            angle,
+        )
+    })
+}
+
+/// Returns the hex positions exactly `radius` away from `origin`, in a cone of `angle` radians
+/// around `facing`, in the order of [`AxialPos::ring`].
+///
+/// `facing` is a direction in the plane of a row oriented ("pointy top") grid, i.e. of
+/// [`ROW_BASIS`].
+pub fn hex_arc(
+    origin: AxialPos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+) -> impl Iterator<Item = AxialPos> {
+    origin.ring(radius).filter(move |axial_pos| {
+        in_cone(
+            hex_plane_offset(*axial_pos - origin, ROW_BASIS),
+            facing,
+            angle,
+        )
+    })
+}
+
+/// Returns the tiles of a hex map within `radius` of `origin`, in a cone of `angle` radians
+/// around `facing`, nearest rings first. Tiles outside of the map are left out.
+///
+/// `facing` is a direction in world space, ignoring the grid size, so on column oriented grids
+/// it is measured in the plane of [`COL_BASIS`].
+pub fn hex_cone_tiles(
+    origin: &TilePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+    hex_coord_sys: HexCoordSystem,
+    map_size: &TilemapSize,
+) -> impl Iterator<Item = TilePos> {
+    let origin = AxialPos::from_tile_pos_given_coord_system(origin, hex_coord_sys);
+    let basis = hex_basis(hex_coord_sys);
+    let map_size = *map_size;
+    origin
+        .spiral(radius)
+        .filter(move |axial_pos| {
+            in_cone(hex_plane_offset(*axial_pos - origin, basis), facing, angle)
+        })
+        .filter_map(move |axial_pos| {
+            axial_pos.as_tile_pos_given_coord_system_and_map_size(hex_coord_sys, &map_size)
+        })
+}
+
+/// Returns the tiles of a hex map exactly `radius` away from `origin`, in a cone of `angle`
+/// radians around `facing`, in the order of [`AxialPos::ring`]. Tiles outside of the map are
+/// left out.
+///
+/// `facing` is a direction in world space, as for [`hex_cone_tiles`].
+pub fn hex_arc_tiles(
+    origin: &TilePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+    hex_coord_sys: HexCoordSystem,
+    map_size: &TilemapSize,
+) -> impl Iterator<Item = TilePos> {
+    let origin = AxialPos::from_tile_pos_given_coord_system(origin, hex_coord_sys);
+    let basis = hex_basis(hex_coord_sys);
+    let map_size = *map_size;
+    origin
+        .ring(radius)
+        .filter(move |axial_pos| {
+            in_cone(hex_plane_offset(*axial_pos - origin, basis), facing, angle)
+        })
+        .filter_map(move |axial_pos| {
+            axial_pos.as_tile_pos_given_coord_system_and_map_size(hex_coord_sys, &map_size)
+        })
+}
+
+fn square_plane_offset(offset: SquarePos) -> Vec2 {
+    Vec2::new(offset.x as f32, offset.y as f32)
+}
+
+/// Returns the facing of a cone pointing at the neighbor of a square in `direction`, for
+/// [`square_cone`] and [`square_arc`].
+pub fn square_facing(direction: SquareDirection) -> Vec2 {
+    square_plane_offset(SquarePos::from(direction))
+}
+
+/// Returns the square positions within `radius` steps of `origin`, in a cone of `angle` radians
+/// around `facing`, nearest rings first.
+pub fn square_cone(
+    origin: SquarePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+) -> impl Iterator<Item = SquarePos> {
+    origin
+        .spiral(radius)
+        .filter(move |square_pos| in_cone(square_plane_offset(*square_pos - origin), facing, angle))
+}
+
+/// Returns the square positions exactly `radius` steps away from `origin`, in a cone of `angle`
+/// radians around `facing`, in the order of [`SquarePos::ring`].
+pub fn square_arc(
+    origin: SquarePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+) -> impl Iterator<Item = SquarePos> {
+    origin
+        .ring(radius)
+        .filter(move |square_pos| in_cone(square_plane_offset(*square_pos - origin), facing, angle))
+}
+
+/// Returns the tiles of a square map within `radius` steps of `origin`, in a cone of `angle`
+/// radians around `facing`, nearest rings first. Tiles outside of the map are left out.
+pub fn square_cone_tiles(
+    origin: &TilePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+    map_size: &TilemapSize,
+) -> impl Iterator<Item = TilePos> {
+    let map_size = *map_size;
+    square_cone(SquarePos::from(origin), facing, angle, radius)
+        .filter_map(move |square_pos| square_pos.as_tile_pos(&map_size))
+}
+
+/// Returns the tiles of a square map exactly `radius` steps away from `origin`, in a cone of
+/// `angle` radians around `facing`, in the order of [`SquarePos::ring`]. Tiles outside of the
+/// map are left out.
+pub fn square_arc_tiles(
+    origin: &TilePos,
+    facing: Vec2,
+    angle: f32,
+    radius: u32,
+    map_size: &TilemapSize,
+) -> impl Iterator<Item = TilePos> {
+    let map_size = *map_size;
+    square_arc(SquarePos::from(origin), facing, angle, radius)
+        .filter_map(move |square_pos| square_pos.as_tile_pos(&map_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+
+    #[test]
+    fn cones_cover_their_share_of_the_area() {
+        let origin = AxialPos::new(4, -2);
+        // The borders of a cone of 60 degrees pass through the positions halfway between two
+        // corners of the even rings, which are included.
+        let cone =
+            hex_cone(origin, hex_facing(HexDirection::Two), FRAC_PI_3, 3).collect::<Vec<_>>();
+        assert_eq!(cone.len(), 1 + 3 + 3);
+        assert!(cone.contains(&(origin + 3 * AxialPos::from(HexDirection::Two))));
+        assert_eq!(
+            hex_arc(origin, hex_facing(HexDirection::Two), 2.0 * FRAC_PI_3, 3).count(),
+            7
+        );
+
+        let origin = SquarePos::new(1, 1);
+        let cone = square_cone(origin, square_facing(SquareDirection::North), FRAC_PI_2, 2)
+            .collect::<Vec<_>>();
+        assert_eq!(cone.len(), 3 + 5);
+        assert!(cone.iter().all(|square_pos| square_pos.y > origin.y));
+        assert_eq!(
+            square_arc(
+                origin,
+                square_facing(SquareDirection::East),
+                std::f32::consts::TAU,
+                2
+            )
+            .count(),
+            16
+        );
+    }
+
+    #[test]
+    fn cones_aim_anywhere_and_stay_on_the_map() {
+        let map_size = TilemapSize::new(4, 4);
+        // A narrow cone towards the north-north-east only reaches the tile straight ahead of
+        // it on the map, which a neighbor direction cannot aim at.
+        let facing = Vec2::new(1.0, 2.0);
+        let cone =
+            square_cone_tiles(&TilePos::new(0, 0), facing, 0.1, 3, &map_size).collect::<Vec<_>>();
+        assert_eq!(cone, [TilePos::new(1, 2)]);
+        // The tiles of a wide cone which lie to the left of the map are left out.
+        assert_eq!(
+            square_arc_tiles(&TilePos::new(0, 1), Vec2::Y, FRAC_PI_2, 1, &map_size)
+                .collect::<Vec<_>>()
+                .len(),
+            2
+        );
+
+        // On a column oriented grid, north is a neighbor direction.
+        let origin = TilePos::new(1, 1);
+        let north = hex_arc_tiles(&origin, Vec2::Y, 0.1, 1, HexCoordSystem::Column, &map_size)
+            .collect::<Vec<_>>();
+        assert_eq!(north, [TilePos::new(1, 2)]);
+        let cone = hex_cone_tiles(
+            &origin,
+            Vec2::NEG_Y,
+            FRAC_PI_3,
+            2,
+            HexCoordSystem::Column,
+            &map_size,
+        )
+        .collect::<Vec<_>>();
+        assert!(cone.contains(&TilePos::new(1, 0)));
+        assert!(cone.iter().all(|tile_pos| tile_pos.y < origin.y));
+    }
+}
@@ -0,0 +1,266 @@
+//! A ready-made grid cursor, which follows the mouse over a tilemap and snaps to the hovered
+//! tile.
+//!
+//! Add the [`TileCursorPlugin`] and spawn an entity with a [`TileCursor`] (usually together with
+//! a [`Sprite`] to display it). Systems in [`TileCursorSystems::Validate`] can decide whether the
+//! hovered tile is a valid target, and clicks on valid tiles are sent as
+//! [`TileCursorConfirmed`] messages.
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::projection::snap_world_pos_to_tile_center;
+use crate::map::{TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::TilePos;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// Adds the systems which drive [`TileCursor`]s.
+pub struct TileCursorPlugin;
+
+impl Plugin for TileCursorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<TileCursorConfirmed>()
+            .configure_sets(
+                Update,
+                (
+                    TileCursorSystems::Track,
+                    TileCursorSystems::Validate,
+                    TileCursorSystems::Apply,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_tile_cursors.in_set(TileCursorSystems::Track),
+                    apply_tile_cursors.in_set(TileCursorSystems::Apply),
+                ),
+            );
+    }
+}
+
+/// The system sets used by the [`TileCursorPlugin`], in the order they run.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileCursorSystems {
+    /// Updates [`TileCursor::tile_pos`] and snaps the cursor to the hovered tile.
+    Track,
+    /// Runs nothing by default. Add systems which set [`TileCursor::valid`] here.
+    Validate,
+    /// Colors the cursor and sends [`TileCursorConfirmed`] messages.
+    Apply,
+}
+
+/// A cursor which follows the mouse over a tilemap, snapping to the center of the hovered tile.
+///
+/// If the entity has a [`Sprite`], it is tinted with [`valid_color`](Self::valid_color) or
+/// [`invalid_color`](Self::invalid_color). If it has a [`Visibility`], it is hidden while the
+/// mouse is not over the tilemap.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct TileCursor {
+    /// The tilemap the cursor moves on.
+    pub tilemap: Entity,
+    /// The camera through which the tilemap is seen.
+    pub camera: Entity,
+    /// The tile currently under the mouse, if any.
+    pub tile_pos: Option<TilePos>,
+    /// Whether the hovered tile is a valid target. It is reset to `true` whenever the mouse is
+    /// over a tile, before the systems in [`TileCursorSystems::Validate`] run.
+    pub valid: bool,
+    pub valid_color: Color,
+    pub invalid_color: Color,
+    /// The mouse button which confirms the hovered tile.
+    pub confirm_button: MouseButton,
+}
+
+impl TileCursor {
+    /// Creates a cursor for the given tilemap, as seen through the given camera.
+    pub fn new(tilemap: Entity, camera: Entity) -> Self {
+        Self {
+            tilemap,
+            camera,
+            tile_pos: None,
+            valid: false,
+            valid_color: Color::srgba(0.2, 0.9, 0.3, 0.6),
+            invalid_color: Color::srgba(0.9, 0.2, 0.2, 0.6),
+            confirm_button: MouseButton::Left,
+        }
+    }
+}
+
+/// Sent when the confirm button of a [`TileCursor`] is pressed over a valid tile.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileCursorConfirmed {
+    /// The cursor entity.
+    pub cursor: Entity,
+    /// The tilemap the cursor moves on.
+    pub tilemap: Entity,
+    /// The confirmed tile.
+    pub tile_pos: TilePos,
+}
+
+#[allow(clippy::type_complexity)]
+fn track_tile_cursors(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tilemaps: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &GlobalTransform,
+    )>,
+    mut cursors: Query<(&mut TileCursor, &mut Transform)>,
+) {
+    let cursor_position = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+
+    for (mut cursor, mut transform) in cursors.iter_mut() {
+        let hovered = cursor_position.and_then(|cursor_position| {
+            let (camera, camera_transform) = cameras.get(cursor.camera).ok()?;
+            let world_pos = camera
+                .viewport_to_world_2d(camera_transform, cursor_position)
+                .ok()?;
+            let (map_size, grid_size, tile_size, map_type, anchor, map_transform) =
+                tilemaps.get(cursor.tilemap).ok()?;
+
+            // Tile helpers work in the local space of the tilemap.
+            let local_pos = map_transform
+                .affine()
+                .inverse()
+                .transform_point3(world_pos.extend(0.0))
+                .truncate();
+            let tile_pos = TilePos::from_world_pos(
+                &local_pos, map_size, grid_size, tile_size, map_type, anchor,
+            )?;
+            let center = snap_world_pos_to_tile_center(
+                &local_pos, map_size, grid_size, tile_size, map_type, anchor,
+            )?;
+            Some((tile_pos, map_transform.transform_point(center.extend(0.0))))
+        });
+
+        match hovered {
+            Some((tile_pos, center)) => {
+                cursor.tile_pos = Some(tile_pos);
+                cursor.valid = true;
+                transform.translation.x = center.x;
+                transform.translation.y = center.y;
+            }
+            None => {
+                cursor.tile_pos = None;
+                cursor.valid = false;
+            }
+        }
+    }
+}
+
+fn apply_tile_cursors(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut confirmed: MessageWriter<TileCursorConfirmed>,
+    mut cursors: Query<(
+        Entity,
+        &TileCursor,
+        Option<&mut Sprite>,
+        Option<&mut Visibility>,
+    )>,
+) {
+    for (entity, cursor, sprite, visibility) in cursors.iter_mut() {
+        if let Some(mut visibility) = visibility {
+            visibility.set_if_neq(if cursor.tile_pos.is_some() {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+
+        if let Some(mut sprite) = sprite {
+            let color = if cursor.valid {
+                cursor.valid_color
+            } else {
+                cursor.invalid_color
+            };
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+
+        if let Some(tile_pos) = cursor.tile_pos
+            && cursor.valid
+            && mouse_buttons.just_pressed(cursor.confirm_button)
+        {
+            confirmed.write(TileCursorConfirmed {
+                cursor: entity,
+                tilemap: cursor.tilemap,
+                tile_pos,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::message::Messages;
+
+    #[test]
+    fn cursors_are_tinted_and_confirm_valid_tiles() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.init_resource::<Messages<TileCursorConfirmed>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems((track_tile_cursors, apply_tile_cursors).chain());
+        let tilemap = world.spawn_empty().id();
+        let camera = world.spawn_empty().id();
+        let cursor = TileCursor::new(tilemap, camera);
+        let (valid_color, invalid_color) = (cursor.valid_color, cursor.invalid_color);
+        let cursor = world
+            .spawn((cursor, Sprite::default(), Visibility::Inherited))
+            .id();
+        let confirmed = |world: &mut World| {
+            world
+                .resource_mut::<Messages<TileCursorConfirmed>>()
+                .drain()
+                .collect::<Vec<_>>()
+        };
+
+        // Without a window there is nothing under the mouse, so the cursor is hidden.
+        schedule.run(&mut world);
+        assert_eq!(world.get::<TileCursor>(cursor).unwrap().tile_pos, None);
+        assert_eq!(world.get::<Visibility>(cursor), Some(&Visibility::Hidden));
+        assert_eq!(world.get::<Sprite>(cursor).unwrap().color, invalid_color);
+
+        // Clicks confirm the hovered tile, but only while it is valid.
+        let hovered = |world: &mut World, valid| {
+            let mut tile_cursor = world.get_mut::<TileCursor>(cursor).unwrap();
+            tile_cursor.tile_pos = Some(TilePos::new(2, 3));
+            tile_cursor.valid = valid;
+            world
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+        };
+        let mut apply = Schedule::default();
+        apply.add_systems(apply_tile_cursors);
+        hovered(&mut world, false);
+        apply.run(&mut world);
+        assert!(confirmed(&mut world).is_empty());
+        assert_eq!(
+            world.get::<Visibility>(cursor),
+            Some(&Visibility::Inherited)
+        );
+
+        world.resource_mut::<ButtonInput<MouseButton>>().reset_all();
+        hovered(&mut world, true);
+        apply.run(&mut world);
+        assert_eq!(world.get::<Sprite>(cursor).unwrap().color, valid_color);
+        assert_eq!(
+            confirmed(&mut world),
+            [TileCursorConfirmed {
+                cursor,
+                tilemap,
+                tile_pos: TilePos::new(2, 3),
+            }]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Distances between tiles, and the tiles within a distance of a tile, e.g. for ability ranges
+//! and AI queries.
+//!
+//! On hexagonal maps, use [`TilePos::hex_distance_to`] and [`TilePos::within_range`].
+
+use crate::helpers::square_grid::SquarePos;
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::IsoCoordSystem;
+use crate::tiles::TilePos;
+use crate::{TilemapGridSize, TilemapSize, TilemapType};
+
+/// The shape of the tiles within a distance of a tile on a square grid.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum RangeShape {
+    /// The tiles within the [Manhattan distance](TilePos::manhattan_distance), which is the
+    /// number of steps when only moving to the four cardinal neighbors.
+    #[default]
+    Diamond,
+    /// The tiles within the [Chebyshev distance](TilePos::chebyshev_distance), which is the number
+    /// of steps when also moving to the diagonal neighbors.
+    Square,
+}
+
+impl RangeShape {
+    /// Returns whether `offset` lies within `radius` of the origin.
+    #[inline]
+    pub fn contains(&self, offset: SquarePos, radius: u32) -> bool {
+        let (x, y) = (offset.x.unsigned_abs(), offset.y.unsigned_abs());
+        match self {
+            RangeShape::Diamond => x + y <= radius,
+            RangeShape::Square => x.max(y) <= radius,
+        }
+    }
+}
+
+impl TilePos {
+    /// Returns the offset from `self` to `other` on the square grid underlying `map_type`.
+    fn square_offset_to(&self, other: &TilePos, map_type: &TilemapType) -> SquarePos {
+        match map_type {
+            TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+                SquarePos::from(StaggeredPos::from(other))
+                    - SquarePos::from(StaggeredPos::from(self))
+            }
+            _ => SquarePos::from(other) - SquarePos::from(self),
+        }
+    }
+
+    /// Returns the number of steps between `self` and `other` when only moving to the four
+    /// cardinal neighbors.
+    ///
+    /// On hexagonal maps, every neighbor is a step, and this is the same as
+    /// [`hex_distance_to`](Self::hex_distance_to).
+    pub fn manhattan_distance(&self, other: &TilePos, map_type: &TilemapType) -> u32 {
+        if let TilemapType::Hexagon(coord_sys) = map_type {
+            return self.hex_distance_to(other, *coord_sys);
+        }
+        let offset = self.square_offset_to(other, map_type);
+        offset.x.unsigned_abs() + offset.y.unsigned_abs()
+    }
+
+    /// Returns the number of steps between `self` and `other` when also moving to the diagonal
+    /// neighbors.
+    ///
+    /// On hexagonal maps, this is the same as [`hex_distance_to`](Self::hex_distance_to).
+    pub fn chebyshev_distance(&self, other: &TilePos, map_type: &TilemapType) -> u32 {
+        if let TilemapType::Hexagon(coord_sys) = map_type {
+            return self.hex_distance_to(other, *coord_sys);
+        }
+        let offset = self.square_offset_to(other, map_type);
+        offset.x.unsigned_abs().max(offset.y.unsigned_abs())
+    }
+
+    /// Returns the distance between the centers of `self` and `other` in world space.
+    pub fn euclidean_distance_in_world(
+        &self,
+        other: &TilePos,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> f32 {
+        self.center_in_world_unanchored(grid_size, map_type)
+            .distance(other.center_in_world_unanchored(grid_size, map_type))
+    }
+
+    /// Returns the positions on a square or diamond isometric map within `radius` of `self`, in
+    /// the given `shape`, nearest rings first and starting with `self`.
+    ///
+    /// Positions which do not fit in `map_size` are skipped.
+    pub fn range_iter(
+        &self,
+        radius: u32,
+        shape: RangeShape,
+        map_size: &TilemapSize,
+    ) -> impl Iterator<Item = TilePos> + use<> {
+        let origin = SquarePos::from(self);
+        let map_size = *map_size;
+        origin.spiral(radius).filter_map(move |square_pos| {
+            if !shape.contains(square_pos - origin, radius) {
+                return None;
+            }
+            square_pos.as_tile_pos(&map_size)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::HexCoordSystem;
+
+    #[test]
+    fn distances_and_ranges() {
+        let a = TilePos::new(2, 3);
+        let b = TilePos::new(5, 1);
+        assert_eq!(a.manhattan_distance(&b, &TilemapType::Square), 5);
+        assert_eq!(a.chebyshev_distance(&b, &TilemapType::Square), 3);
+        let hex = TilemapType::Hexagon(HexCoordSystem::Row);
+        assert_eq!(a.manhattan_distance(&b, &hex), 3);
+        let grid_size = TilemapGridSize::new(2.0, 2.0);
+        let distance = a.euclidean_distance_in_world(&b, &grid_size, &TilemapType::Square);
+        assert!((distance - 2.0 * 13f32.sqrt()).abs() < 1e-5);
+
+        let map_size = TilemapSize::new(10, 10);
+        let center = TilePos::new(5, 5);
+        assert_eq!(
+            center.range_iter(2, RangeShape::Diamond, &map_size).count(),
+            13
+        );
+        assert_eq!(
+            center.range_iter(2, RangeShape::Square, &map_size).count(),
+            25
+        );
+        let corner = TilePos::new(0, 0);
+        assert_eq!(
+            corner.range_iter(1, RangeShape::Square, &map_size).count(),
+            4
+        );
+    }
+}
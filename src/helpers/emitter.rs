@@ -0,0 +1,246 @@
+//! Keeps effect entities, such as particle emitters, on the tiles they belong to.
+//!
+//! Add the [`TileEmitterPlugin`] and a [`TileEmitters`] to a tilemap. Every tile matching its
+//! predicate gets an emitter entity, which is a child of the tilemap placed on the center of the
+//! tile, and which the spawn function fills with the components of the effect. When a tile is
+//! moved, changes its texture or is despawned, its emitter is despawned, and spawned again if the
+//! tile still matches. Burning or sparkling tiles thus follow edits to the map without any
+//! bookkeeping.
+
+use bevy::ecs::system::EntityCommands;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::anchor::TilemapAnchor;
+use crate::map::{TilemapGridSize, TilemapId, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::{TilePos, TileStorage, TileTextureIndex};
+
+/// Adds the system which spawns and despawns the emitters of [`TileEmitters`].
+pub struct TileEmitterPlugin;
+
+impl Plugin for TileEmitterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, sync_tile_emitters);
+    }
+}
+
+/// Decides which tiles get an emitter.
+pub type TileEmitterPredicate = Arc<dyn Fn(&TilePos, &TileTextureIndex) -> bool + Send + Sync>;
+
+/// Adds the components of an effect to a newly spawned emitter.
+pub type TileEmitterSpawner =
+    Arc<dyn Fn(&mut EntityCommands, &TilePos, &TileTextureIndex) + Send + Sync>;
+
+/// Gives the tiles of a tilemap which match a predicate an emitter entity each.
+///
+/// Replacing the predicate or the spawner respawns every emitter of the tilemap.
+#[derive(Component, Clone)]
+pub struct TileEmitters {
+    pub predicate: TileEmitterPredicate,
+    pub spawner: TileEmitterSpawner,
+    /// The Z offset of the emitters from the tilemap, to draw effects above the tiles.
+    pub z_offset: f32,
+    emitters: HashMap<Entity, Entity>,
+}
+
+impl TileEmitters {
+    pub fn new(predicate: TileEmitterPredicate, spawner: TileEmitterSpawner) -> Self {
+        Self {
+            predicate,
+            spawner,
+            z_offset: 1.0,
+            emitters: HashMap::new(),
+        }
+    }
+
+    /// Gives the tiles with the given texture an emitter.
+    pub fn for_texture(texture_index: TileTextureIndex, spawner: TileEmitterSpawner) -> Self {
+        Self::new(
+            Arc::new(move |_, tile_texture_index| *tile_texture_index == texture_index),
+            spawner,
+        )
+    }
+
+    /// Returns the emitter of a tile, if it has one.
+    pub fn emitter(&self, tile_entity: Entity) -> Option<Entity> {
+        self.emitters.get(&tile_entity).copied()
+    }
+
+    /// Returns the tiles with an emitter, and their emitters.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.emitters
+            .iter()
+            .map(|(tile, emitter)| (*tile, *emitter))
+    }
+
+    pub fn len(&self) -> usize {
+        self.emitters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+}
+
+/// Added to the emitters spawned for [`TileEmitters`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TileEmitter {
+    /// The tile the emitter belongs to.
+    pub tile: Entity,
+}
+
+#[allow(clippy::type_complexity)]
+fn sync_tile_emitters(
+    mut commands: Commands,
+    mut tilemaps: Query<(
+        Entity,
+        &mut TileEmitters,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+    )>,
+    changed_tiles: Query<(Entity, &TilemapId), Or<(Changed<TilePos>, Changed<TileTextureIndex>)>>,
+    tiles: Query<(&TilePos, &TileTextureIndex)>,
+    mut removed: RemovedComponents<TilePos>,
+) {
+    if tilemaps.is_empty() {
+        return;
+    }
+    let removed = removed.read().collect::<Vec<_>>();
+    let mut updates: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for (tile_entity, tilemap_id) in changed_tiles.iter() {
+        updates.entry(tilemap_id.0).or_default().push(tile_entity);
+    }
+
+    for (
+        tilemap_entity,
+        mut tile_emitters,
+        tile_storage,
+        map_size,
+        grid_size,
+        tile_size,
+        map_type,
+        anchor,
+    ) in tilemaps.iter_mut()
+    {
+        let resync = tile_emitters.is_changed();
+        let updated = updates.remove(&tilemap_entity).unwrap_or_default();
+        if !resync && updated.is_empty() && removed.is_empty() {
+            continue;
+        }
+        let tile_emitters = tile_emitters.bypass_change_detection();
+
+        let updated = if resync {
+            for (_, emitter) in tile_emitters.emitters.drain() {
+                commands.entity(emitter).try_despawn();
+            }
+            tile_storage
+                .iter_some()
+                .map(|(_, tile_entity)| tile_entity)
+                .collect()
+        } else {
+            for tile_entity in removed.iter().chain(&updated) {
+                if let Some(emitter) = tile_emitters.emitters.remove(tile_entity) {
+                    commands.entity(emitter).try_despawn();
+                }
+            }
+            updated
+        };
+
+        for tile_entity in updated {
+            let Ok((tile_pos, texture_index)) = tiles.get(tile_entity) else {
+                continue;
+            };
+            if !(tile_emitters.predicate)(tile_pos, texture_index) {
+                continue;
+            }
+            let center = tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+            let mut emitter = commands.spawn((
+                TileEmitter { tile: tile_entity },
+                Transform::from_translation(center.extend(tile_emitters.z_offset)),
+                ChildOf(tilemap_entity),
+            ));
+            (tile_emitters.spawner)(&mut emitter, tile_pos, texture_index);
+            tile_emitters.emitters.insert(tile_entity, emitter.id());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileBundle;
+
+    #[derive(Component)]
+    struct Sparkles;
+
+    #[test]
+    fn emitters_follow_tile_edits() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_tile_emitters);
+
+        let map_size = TilemapSize::new(4, 1);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        for x in 0..map_size.x {
+            let tile_pos = TilePos::new(x, 0);
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    texture_index: TileTextureIndex(x % 2),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        let tiles = (0..map_size.x)
+            .map(|x| tile_storage.get(&TilePos::new(x, 0)).unwrap())
+            .collect::<Vec<_>>();
+        world.entity_mut(tilemap).insert((
+            tile_storage,
+            map_size,
+            TilemapGridSize::new(16.0, 16.0),
+            TilemapTileSize::new(16.0, 16.0),
+            TilemapType::Square,
+            TilemapAnchor::None,
+            TileEmitters::for_texture(
+                TileTextureIndex(1),
+                Arc::new(|emitter, _, _| {
+                    emitter.insert(Sparkles);
+                }),
+            ),
+        ));
+        let emitter =
+            |world: &World, tile: Entity| world.get::<TileEmitters>(tilemap).unwrap().emitter(tile);
+
+        schedule.run(&mut world);
+        assert_eq!(world.query::<&Sparkles>().iter(&world).count(), 2);
+        let first = emitter(&world, tiles[1]).unwrap();
+        assert_eq!(
+            world.get::<Transform>(first).unwrap().translation,
+            Vec3::new(16.0, 0.0, 1.0)
+        );
+        assert_eq!(world.get::<ChildOf>(first).unwrap().parent(), tilemap);
+
+        // Painting a tile over removes its emitter, and painting another one adds one.
+        world.get_mut::<TileTextureIndex>(tiles[1]).unwrap().0 = 0;
+        world.get_mut::<TileTextureIndex>(tiles[2]).unwrap().0 = 1;
+        schedule.run(&mut world);
+        assert!(world.get_entity(first).is_err());
+        assert_eq!(emitter(&world, tiles[1]), None);
+        assert!(emitter(&world, tiles[2]).is_some());
+
+        // Despawning a tile removes its emitter.
+        let last = emitter(&world, tiles[3]).unwrap();
+        world.despawn(tiles[3]);
+        schedule.run(&mut world);
+        assert!(world.get_entity(last).is_err());
+        assert_eq!(world.query::<&Sparkles>().iter(&world).count(), 1);
+    }
+}
@@ -1,11 +1,13 @@
 use crate::helpers::hex_grid::axial::AxialPos;
-use crate::helpers::hex_grid::neighbors::{HEX_DIRECTIONS, HexDirection};
+use crate::helpers::square_grid::neighbors::SquareDirection;
+use crate::helpers::square_grid::{SquarePos, SquareRingIter, SquareSpiralIter};
 use crate::map::TilemapId;
 use crate::prelude::HexCoordSystem;
-use crate::tiles::{TileBundle, TileColor, TilePos, TileTextureIndex};
+use crate::tiles::{TileBundle, TileColor, TileDataLayer, TilePos, TileStore, TileTextureIndex};
 use crate::{TileStorage, TilemapSize};
 
-use bevy::prelude::{Color, Commands};
+use bevy::log::warn;
+use bevy::prelude::{ChildOf, Color, Commands, Entity, ParallelCommands, Resource, World};
 
 /// Fills an entire tile storage with the given tile.
 pub fn fill_tilemap(
@@ -13,7 +15,7 @@ pub fn fill_tilemap(
     size: TilemapSize,
     tilemap_id: TilemapId,
     commands: &mut Commands,
-    tile_storage: &mut TileStorage,
+    tile_storage: &mut impl TileStore,
 ) {
     commands.entity(tilemap_id.0).with_children(|parent| {
         for x in 0..size.x {
@@ -33,6 +35,62 @@ pub fn fill_tilemap(
     });
 }
 
+/// Fills every position of the tile storage for which `f` returns a texture index, spawning the
+/// tiles in parallel on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool).
+///
+/// This is meant for spawning maps of millions of tiles at startup, where spawning the tiles one
+/// by one from a single thread dominates the time to the first frame. Each thread spawns a batch
+/// of tiles through `par_commands`, and the tile storage is filled right away.
+pub fn par_fill_tilemap_with<F>(
+    f: F,
+    tilemap_id: TilemapId,
+    par_commands: &ParallelCommands,
+    tile_storage: &mut TileStorage,
+) where
+    F: Fn(TilePos) -> Option<TileTextureIndex> + Send + Sync,
+{
+    let size = tile_storage.size;
+    tile_storage.par_batches_mut(|first, slots| {
+        par_commands.command_scope(|mut commands| {
+            for (index, slot) in (first..).zip(slots.iter_mut()) {
+                let tile_pos = TilePos::new(index as u32 % size.x, index as u32 / size.x);
+                let Some(texture_index) = f(tile_pos) else {
+                    continue;
+                };
+                let tile_entity = commands
+                    .spawn((
+                        TileBundle {
+                            position: tile_pos,
+                            tilemap_id,
+                            texture_index,
+                            ..Default::default()
+                        },
+                        ChildOf(tilemap_id.0),
+                    ))
+                    .id();
+                *slot = Some(tile_entity);
+            }
+        });
+    });
+}
+
+/// Fills an entire tile storage with the given tile, spawning the tiles in parallel.
+///
+/// See [`par_fill_tilemap_with`].
+pub fn par_fill_tilemap(
+    texture_index: TileTextureIndex,
+    tilemap_id: TilemapId,
+    par_commands: &ParallelCommands,
+    tile_storage: &mut TileStorage,
+) {
+    par_fill_tilemap_with(
+        |_| Some(texture_index),
+        tilemap_id,
+        par_commands,
+        tile_storage,
+    );
+}
+
 /// Fills a rectangular region with the given tile.
 ///
 /// The rectangular region is defined by an `origin` in [`TilePos`], and a
@@ -43,7 +101,7 @@ pub fn fill_tilemap_rect(
     size: TilemapSize,
     tilemap_id: TilemapId,
     commands: &mut Commands,
-    tile_storage: &mut TileStorage,
+    tile_storage: &mut impl TileStore,
 ) {
     commands.entity(tilemap_id.0).with_children(|parent| {
         for x in 0..size.x {
@@ -67,6 +125,118 @@ pub fn fill_tilemap_rect(
     });
 }
 
+/// Spawns a tile for every texture index of `layer`, with the layer's origin at `origin`.
+///
+/// Positions without a texture index are left empty. Tiles that do not fit in the tilemap will
+/// not be created. Layers can be built from ASCII art with the [`tilemap!`](crate::tilemap)
+/// macro.
+pub fn fill_tilemap_from_layer(
+    layer: &TileDataLayer<Option<TileTextureIndex>>,
+    origin: TilePos,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) {
+    commands.entity(tilemap_id.0).with_children(|parent| {
+        for (pos, texture_index) in layer.iter() {
+            let Some(texture_index) = texture_index else {
+                continue;
+            };
+            let (Some(x), Some(y)) = (origin.x.checked_add(pos.x), origin.y.checked_add(pos.y))
+            else {
+                continue;
+            };
+            let tile_pos = TilePos { x, y };
+            if !tile_pos.within_map_bounds(&tile_storage.size()) {
+                continue;
+            }
+
+            let tile_entity = parent
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id,
+                    texture_index: *texture_index,
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+    });
+}
+
+/// Flood fills the region connected to `origin` with the given tile, like the paint bucket of an
+/// image editor, and returns the positions which were filled.
+///
+/// The region grows from `origin` in the given `directions`: pass
+/// [`CARDINAL_SQUARE_DIRECTIONS`](crate::helpers::square_grid::neighbors::CARDINAL_SQUARE_DIRECTIONS)
+/// for a 4-connected fill, or
+/// [`SQUARE_DIRECTIONS`](crate::helpers::square_grid::neighbors::SQUARE_DIRECTIONS) for an
+/// 8-connected one. It only spreads to positions for which `predicate`, given the entity
+/// currently stored there, returns `true`. Nothing is filled if `origin` does not lie within the
+/// tile storage or is rejected by the `predicate`.
+///
+/// Empty positions get a new tile, and existing tiles get the new texture index.
+pub fn fill_tilemap_flood<F>(
+    texture_index: TileTextureIndex,
+    origin: TilePos,
+    directions: &[SquareDirection],
+    mut predicate: F,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) -> Vec<TilePos>
+where
+    F: FnMut(&TilePos, Option<Entity>) -> bool,
+{
+    let size = tile_storage.size();
+    let mut filled = Vec::new();
+    if !origin.within_map_bounds(&size) || !predicate(&origin, tile_storage.get(&origin)) {
+        return filled;
+    }
+
+    let mut visited = vec![false; size.count()];
+    visited[origin.to_index(&size)] = true;
+    let mut stack = vec![origin];
+    while let Some(tile_pos) = stack.pop() {
+        filled.push(tile_pos);
+        for direction in directions {
+            let Some(neighbor) = SquarePos::from(&tile_pos)
+                .offset(direction)
+                .as_tile_pos(&size)
+            else {
+                continue;
+            };
+            let index = neighbor.to_index(&size);
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+            if predicate(&neighbor, tile_storage.get(&neighbor)) {
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    commands.entity(tilemap_id.0).with_children(|parent| {
+        for tile_pos in &filled {
+            if let Some(tile_entity) = tile_storage.get(tile_pos) {
+                parent.commands().entity(tile_entity).insert(texture_index);
+                continue;
+            }
+            let tile_entity = parent
+                .spawn(TileBundle {
+                    position: *tile_pos,
+                    tilemap_id,
+                    texture_index,
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(tile_pos, tile_entity);
+        }
+    });
+    filled
+}
+
 /// Fills a rectangular region with colored versions of the given tile.
 ///
 /// The rectangular region is defined by an `origin` in [`TilePos`], and a
@@ -78,7 +248,7 @@ pub fn fill_tilemap_rect_color(
     color: Color,
     tilemap_id: TilemapId,
     commands: &mut Commands,
-    tile_storage: &mut TileStorage,
+    tile_storage: &mut impl TileStore,
 ) {
     commands.entity(tilemap_id.0).with_children(|parent| {
         for x in 0..size.x {
@@ -103,42 +273,186 @@ pub fn fill_tilemap_rect_color(
     });
 }
 
-/// Generates a vector of hex positions that form a ring of given `radius` around the specified
-/// `origin`.
+/// Despawns all tiles in a rectangular region, and clears their slots in the tile storage.
 ///
-/// If `radius` is zero, `origin` is the only position in the returned vector.
-pub fn generate_hex_ring(origin: AxialPos, radius: u32) -> Vec<AxialPos> {
-    if radius == 0 {
-        vec![origin]
-    } else {
-        let mut ring = Vec::with_capacity((radius * 6) as usize);
-        let corners = HEX_DIRECTIONS
-            .iter()
-            .map(|direction| origin + radius * AxialPos::from(direction))
-            .collect::<Vec<AxialPos>>();
-        // The "tangent" is the direction we must travel in to reach the next corner
-        let tangents = (0..6)
-            .map(|ix| HexDirection::from(ix + 2).into())
-            .collect::<Vec<AxialPos>>();
-
-        for (&corner, &tangent) in corners.iter().zip(tangents.iter()) {
-            for k in 0..radius {
-                ring.push(corner + k * tangent);
+/// The rectangular region is defined by an `origin` in [`TilePos`], and a
+/// `size` in tiles ([`TilemapSize`]). Positions which do not lie within the tile storage are
+/// ignored.
+///
+/// Tiles whose entities were already despawned are removed from the storage, and a warning is
+/// logged.
+pub fn despawn_region(
+    origin: TilePos,
+    size: TilemapSize,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) {
+    despawn_region_where(origin, size, |_, _| true, commands, tile_storage);
+}
+
+/// Despawns the tiles in a rectangular region for which `predicate` returns `true`, and clears
+/// their slots in the tile storage.
+///
+/// The rectangular region is defined by an `origin` in [`TilePos`], and a
+/// `size` in tiles ([`TilemapSize`]). Positions which do not lie within the tile storage are
+/// ignored.
+///
+/// Tiles whose entities were already despawned are removed from the storage, and a warning is
+/// logged.
+pub fn despawn_region_where<F>(
+    origin: TilePos,
+    size: TilemapSize,
+    mut predicate: F,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) where
+    F: FnMut(&TilePos, Entity) -> bool,
+{
+    // The region is clipped to the storage, so the positions in it can not overflow.
+    let storage_size = tile_storage.size();
+    let width = size.x.min(storage_size.x.saturating_sub(origin.x));
+    let height = size.y.min(storage_size.y.saturating_sub(origin.y));
+    for x in 0..width {
+        for y in 0..height {
+            let tile_pos = TilePos {
+                x: origin.x + x,
+                y: origin.y + y,
+            };
+
+            let Some(tile_entity) = tile_storage.get(&tile_pos) else {
+                continue;
+            };
+            if !predicate(&tile_pos, tile_entity) {
+                continue;
+            }
+
+            tile_storage.remove(&tile_pos);
+            match commands.get_entity(tile_entity) {
+                Ok(mut entity_commands) => entity_commands.despawn(),
+                Err(_) => warn!(
+                    "Tile entity {tile_entity} at {tile_pos:?} was already despawned, \
+                    clearing it from the tile storage."
+                ),
             }
         }
+    }
+}
+
+/// What [`despawn_where`] does with the entities of the tiles it removes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DespawnMode {
+    /// The entities are despawned.
+    #[default]
+    Despawn,
+    /// The entities are stripped of all of their components and added to the
+    /// [`TileEntityPool`], to be reused for new tiles.
+    Recycle,
+}
 
-        ring
+/// Blank entities left behind by [`despawn_where`] with [`DespawnMode::Recycle`], which are reused
+/// for new tiles before spawning new entities.
+///
+/// Maps which repeatedly clear and refill large areas reuse the same entities this way, instead of
+/// despawning and spawning thousands of them.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct TileEntityPool {
+    entities: Vec<Entity>,
+}
+
+impl TileEntityPool {
+    /// The number of entities waiting to be reused.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Spawns a tile as a child of its tilemap, reusing a pooled entity if there is one.
+    ///
+    /// Like [`Commands::spawn`], the tile still has to be added to the tile storage.
+    pub fn spawn(&mut self, commands: &mut Commands, tile: TileBundle) -> Entity {
+        let tilemap_entity = tile.tilemap_id.0;
+        let tile_entity = match self.entities.pop() {
+            Some(tile_entity) => commands.entity(tile_entity).insert(tile).id(),
+            None => commands.spawn(tile).id(),
+        };
+        commands.entity(tilemap_entity).add_child(tile_entity);
+        tile_entity
     }
 }
 
+/// Removes every tile of the tile storage for which `predicate` returns `true`, e.g. to destroy all
+/// ice tiles, and returns their positions.
+///
+/// The tiles are cleared from the storage immediately, and their entities are despawned or
+/// recycled by a single command, according to `mode`. Entities which were already despawned are
+/// skipped.
+pub fn despawn_where<F>(
+    mut predicate: F,
+    mode: DespawnMode,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) -> Vec<TilePos>
+where
+    F: FnMut(&TilePos, Entity) -> bool,
+{
+    let (positions, entities): (Vec<_>, Vec<_>) = tile_storage
+        .iter_some()
+        .filter(|(tile_pos, tile_entity)| predicate(tile_pos, *tile_entity))
+        .unzip();
+    for tile_pos in &positions {
+        tile_storage.remove(tile_pos);
+    }
+
+    commands.queue(move |world: &mut World| {
+        for tile_entity in entities {
+            let Ok(mut entity) = world.get_entity_mut(tile_entity) else {
+                continue;
+            };
+            match mode {
+                DespawnMode::Despawn => entity.despawn(),
+                DespawnMode::Recycle => {
+                    entity.clear();
+                    world
+                        .get_resource_or_init::<TileEntityPool>()
+                        .entities
+                        .push(tile_entity);
+                }
+            }
+        }
+    });
+    positions
+}
+
+/// Generates a vector of hex positions that form a ring of given `radius` around the specified
+/// `origin`.
+///
+/// If `radius` is zero, `origin` is the only position in the returned vector. Use
+/// [`AxialPos::ring`] to go through the positions without collecting them.
+pub fn generate_hex_ring(origin: AxialPos, radius: u32) -> Vec<AxialPos> {
+    origin.ring(radius).collect()
+}
+
 /// Generates a vector of hex positions that form a hexagon of given `radius` around the specified
 /// `origin`.
+///
+/// Use [`AxialPos::spiral`] to go through the positions without collecting them.
 pub fn generate_hexagon(origin: AxialPos, radius: u32) -> Vec<AxialPos> {
-    let mut hexagon = Vec::with_capacity(1 + (6 * radius * (radius + 1) / 2) as usize);
-    for r in 0..(radius + 1) {
-        hexagon.extend(generate_hex_ring(origin, r));
-    }
-    hexagon
+    origin.spiral(radius).collect()
+}
+
+/// Returns the square positions that form a ring of given `radius` around the specified
+/// `origin`, lazily. See [`SquarePos::ring`].
+pub fn square_ring(origin: SquarePos, radius: u32) -> SquareRingIter {
+    origin.ring(radius)
+}
+
+/// Returns the square positions that form a filled square of given `radius` around the specified
+/// `origin`, ring by ring outward, lazily. See [`SquarePos::spiral`].
+pub fn square_spiral(origin: SquarePos, radius: u32) -> SquareSpiralIter {
+    origin.spiral(radius)
 }
 
 /// Fills a hexagonal region with the given `tile_texture`.
@@ -154,7 +468,7 @@ pub fn fill_tilemap_hexagon(
     hex_coord_system: HexCoordSystem,
     tilemap_id: TilemapId,
     commands: &mut Commands,
-    tile_storage: &mut TileStorage,
+    tile_storage: &mut impl TileStore,
 ) {
     let tile_positions = generate_hexagon(
         AxialPos::from_tile_pos_given_coord_system(&origin, hex_coord_system),
@@ -178,3 +492,225 @@ pub fn fill_tilemap_hexagon(
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::square_grid::neighbors::{CARDINAL_SQUARE_DIRECTIONS, SQUARE_DIRECTIONS};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::ecs::world::CommandQueue;
+    use bevy::prelude::{Query, World};
+
+    #[test]
+    fn flood_fill_stops_at_walls() {
+        let walls = crate::tilemap!(
+            "
+            WWWW.
+            W..W.
+            W..W.
+            WWW..
+            .....
+            ",
+            { 'W' => 1 }
+        );
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut storage = TileStorage::empty(TilemapSize::new(5, 5));
+        fill_tilemap_from_layer(
+            &walls,
+            TilePos::new(0, 0),
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+
+        let filled = fill_tilemap_flood(
+            TileTextureIndex(2),
+            TilePos::new(1, 2),
+            &CARDINAL_SQUARE_DIRECTIONS,
+            |_, entity| entity.is_none(),
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        assert_eq!(filled.len(), 4);
+        assert!(
+            filled
+                .iter()
+                .all(|tile_pos| storage.get(tile_pos).is_some())
+        );
+
+        // The fill leaks out through the gap between the diagonal walls.
+        let filled = fill_tilemap_flood(
+            TileTextureIndex(3),
+            TilePos::new(1, 2),
+            &SQUARE_DIRECTIONS,
+            |tile_pos, _| walls.get(tile_pos).is_none(),
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        assert_eq!(filled.len(), 14);
+        assert_eq!(storage.iter().flatten().count(), 25);
+    }
+
+    #[test]
+    fn despawn_region_where_only_despawns_matching_tiles() {
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut storage = TileStorage::empty(TilemapSize::new(4, 4));
+        fill_tilemap(
+            TileTextureIndex(0),
+            TilemapSize::new(4, 4),
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        queue.apply(&mut world);
+        let tiles = storage.iter().flatten().copied().collect::<Vec<_>>();
+
+        let mut commands = Commands::new(&mut queue, &world);
+        // Only the tiles on the diagonal of the lower right corner are despawned.
+        despawn_region_where(
+            TilePos::new(2, 2),
+            TilemapSize::new(u32::MAX, u32::MAX),
+            |tile_pos, _| tile_pos.x == tile_pos.y,
+            &mut commands,
+            &mut storage,
+        );
+        // A region past the end of the positions is left alone instead of overflowing.
+        despawn_region_where(
+            TilePos::new(u32::MAX, u32::MAX),
+            TilemapSize::new(2, 2),
+            |_, _| true,
+            &mut commands,
+            &mut storage,
+        );
+        queue.apply(&mut world);
+
+        let despawned = [TilePos::new(2, 2), TilePos::new(3, 3)];
+        for tile_pos in despawned {
+            assert_eq!(storage.get(&tile_pos), None);
+        }
+        assert_eq!(storage.iter().flatten().count(), 14);
+        assert_eq!(
+            tiles
+                .iter()
+                .filter(|tile| world.get_entity(**tile).is_err())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn recycled_tiles_are_reused() {
+        let ice = crate::tilemap!(
+            "
+            #.#
+            .#.
+            ",
+            { '#' => 1 }
+        );
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut storage = TileStorage::empty(TilemapSize::new(3, 2));
+        fill_tilemap(
+            TileTextureIndex(0),
+            storage.size,
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        let melted = despawn_where(
+            |tile_pos, _| ice.get(tile_pos).is_some(),
+            DespawnMode::Recycle,
+            &mut commands,
+            &mut storage,
+        );
+        queue.apply(&mut world);
+
+        assert_eq!(melted.len(), 3);
+        assert_eq!(storage.iter().flatten().count(), 3);
+        let mut pool = world.remove_resource::<TileEntityPool>().unwrap();
+        assert_eq!(pool.len(), 3);
+        assert_eq!(world.query::<&TilePos>().iter(&world).count(), 3);
+
+        let mut commands = Commands::new(&mut queue, &world);
+        let tile_entity = pool.spawn(
+            &mut commands,
+            TileBundle {
+                position: melted[0],
+                tilemap_id,
+                ..Default::default()
+            },
+        );
+        queue.apply(&mut world);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(world.get::<TilePos>(tile_entity), Some(&melted[0]));
+    }
+
+    #[test]
+    fn parallel_fill_spawns_every_tile() {
+        let mut world = World::new();
+        let size = TilemapSize::new(37, 29);
+        let tilemap = world.spawn(TileStorage::empty(size)).id();
+        world
+            .run_system_once(
+                move |par_commands: ParallelCommands, mut storages: Query<&mut TileStorage>| {
+                    par_fill_tilemap_with(
+                        |tile_pos| (tile_pos.x != tile_pos.y).then_some(TileTextureIndex(1)),
+                        TilemapId(tilemap),
+                        &par_commands,
+                        &mut storages.get_mut(tilemap).unwrap(),
+                    );
+                },
+            )
+            .unwrap();
+
+        let storage = world.get::<TileStorage>(tilemap).unwrap().clone();
+        assert_eq!(storage.iter().flatten().count(), 37 * 29 - 29);
+        for (tile_pos, tile_entity) in storage.iter_some() {
+            assert_eq!(world.get::<TilePos>(tile_entity), Some(&tile_pos));
+            assert_eq!(world.get::<ChildOf>(tile_entity).unwrap().parent(), tilemap);
+        }
+    }
+
+    #[test]
+    fn rings_and_spirals_cover_each_position_once() {
+        let origin = AxialPos::new(2, -1);
+        let hexagon = generate_hexagon(origin, 4);
+        assert_eq!(hexagon.len(), 1 + 6 * (4 * 5) / 2);
+        assert_eq!(origin.spiral(4).len(), hexagon.len());
+        assert_eq!(hexagon[0], origin);
+        let ring = origin.ring(3).collect::<Vec<_>>();
+        assert_eq!(ring.len(), 18);
+        assert!(
+            ring.iter()
+                .all(|axial_pos| axial_pos.distance_from(&origin) == 3)
+        );
+        // Consecutive positions of a ring are neighbors.
+        assert!(
+            ring.windows(2)
+                .all(|pair| pair[0].distance_from(&pair[1]) == 1)
+        );
+
+        let origin = SquarePos::new(-3, 5);
+        let mut spiral = square_spiral(origin, 3);
+        assert_eq!(spiral.len(), 49);
+        assert_eq!(spiral.next(), Some(origin));
+        let square = spiral.collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(square.len(), 48);
+        let ring = square_ring(origin, 2).collect::<Vec<_>>();
+        assert_eq!(ring.len(), 16);
+        assert!(ring.iter().all(|square_pos| {
+            let offset = *square_pos - origin;
+            offset.x.abs().max(offset.y.abs()) == 2
+        }));
+    }
+}
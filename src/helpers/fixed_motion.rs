@@ -0,0 +1,201 @@
+//! Moves entities across a tilemap from a fixed-timestep simulation, while drawing them smoothly
+//! in between the ticks.
+//!
+//! Add the [`FixedTileMotionPlugin`] and give the moving entities a [`TileMotion`]. Systems in
+//! [`FixedUpdate`] move an entity by setting [`TileMotion::current`], its authoritative position,
+//! and the entity's [`Transform`] is placed between the previous and the current tile according to
+//! how far the frame is into the next tick. Tilemaps with a [`TileOccupancy`] track which entity is
+//! on which tile, as of the last tick.
+//!
+//! Tile animations are driven by the render clock and need no special handling: they keep
+//! playing smoothly between ticks. When simulation code needs to know the frame of an
+//! [`AnimatedTile`](crate::tiles::AnimatedTile), it gets the frame of the current tick by passing
+//! the elapsed time of `Time<Fixed>` to
+//! [`frame_at`](crate::tiles::AnimatedTile::frame_at).
+
+use crate::anchor::TilemapAnchor;
+use crate::map::{TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::TilePos;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+/// Adds the systems which drive [`TileMotion`] and [`TileOccupancy`].
+pub struct FixedTileMotionPlugin;
+
+impl Plugin for FixedTileMotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TileMotion>()
+            .add_systems(FixedFirst, start_tile_motion_ticks)
+            .add_systems(FixedPostUpdate, update_tile_occupancy)
+            .add_systems(
+                PostUpdate,
+                interpolate_tile_motion.before(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// The position of an entity on a tilemap, as simulated in [`FixedUpdate`].
+///
+/// The entity's [`Transform`] is only used for drawing: its `x` and `y` are set to the center of
+/// the tile, in the local space of the tilemap, so the entity should be a child of the tilemap.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+#[require(Transform)]
+pub struct TileMotion {
+    /// The tilemap the entity moves on.
+    pub tilemap: Entity,
+    /// The position at the start of the current tick.
+    pub previous: TilePos,
+    /// The authoritative position, which the simulation moves the entity to.
+    pub current: TilePos,
+}
+
+impl TileMotion {
+    /// Places an entity on the given tile, without moving it in from elsewhere.
+    pub fn new(tilemap: Entity, tile_pos: TilePos) -> Self {
+        Self {
+            tilemap,
+            previous: tile_pos,
+            current: tile_pos,
+        }
+    }
+
+    /// The position the entity is drawn at, `overstep_fraction` of the way from the center of
+    /// the previous tile to the center of the current one.
+    pub fn presentation_position(
+        &self,
+        overstep_fraction: f32,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+    ) -> Vec2 {
+        let center = |tile_pos: TilePos| {
+            tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor)
+        };
+        center(self.previous).lerp(center(self.current), overstep_fraction.clamp(0.0, 1.0))
+    }
+}
+
+/// The entities with a [`TileMotion`] on each tile of a tilemap, as of the end of the last tick.
+///
+/// Add it to the tilemap entity to have it kept up to date. Unlike the drawn positions, it only
+/// changes once per tick, so simulation code sees the same occupancy for the whole tick.
+#[derive(Component, Default, Clone, Debug)]
+pub struct TileOccupancy {
+    occupants: HashMap<TilePos, Entity>,
+}
+
+impl TileOccupancy {
+    /// The entity on the given tile. If several entities share the tile, one of them is returned.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        self.occupants.get(tile_pos).copied()
+    }
+
+    pub fn is_occupied(&self, tile_pos: &TilePos) -> bool {
+        self.occupants.contains_key(tile_pos)
+    }
+
+    /// Iterates over the occupied tiles and their occupants.
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, Entity)> + '_ {
+        self.occupants
+            .iter()
+            .map(|(tile_pos, entity)| (*tile_pos, *entity))
+    }
+}
+
+/// Remembers where every entity was at the start of the tick, so it is drawn moving from there.
+fn start_tile_motion_ticks(mut motions: Query<&mut TileMotion>) {
+    for mut motion in motions.iter_mut() {
+        if motion.previous != motion.current {
+            motion.previous = motion.current;
+        }
+    }
+}
+
+fn update_tile_occupancy(
+    mut tilemaps: Query<(Entity, &mut TileOccupancy)>,
+    motions: Query<(Entity, &TileMotion)>,
+) {
+    for (_, mut occupancy) in tilemaps.iter_mut() {
+        occupancy.occupants.clear();
+    }
+    for (entity, motion) in motions.iter() {
+        if let Ok((_, mut occupancy)) = tilemaps.get_mut(motion.tilemap) {
+            occupancy.occupants.insert(motion.current, entity);
+        }
+    }
+}
+
+fn interpolate_tile_motion(
+    fixed_time: Res<Time<Fixed>>,
+    tilemaps: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+    )>,
+    mut motions: Query<(&TileMotion, &mut Transform)>,
+) {
+    let overstep_fraction = fixed_time.overstep_fraction();
+    for (motion, mut transform) in motions.iter_mut() {
+        let Ok((map_size, grid_size, tile_size, map_type, anchor)) = tilemaps.get(motion.tilemap)
+        else {
+            continue;
+        };
+        let position = motion.presentation_position(
+            overstep_fraction,
+            map_size,
+            grid_size,
+            tile_size,
+            map_type,
+            anchor,
+        );
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn ticks_move_the_authoritative_position_only() {
+        let mut world = World::new();
+        let tilemap = world.spawn(TileOccupancy::default()).id();
+        let unit = world
+            .spawn(TileMotion::new(tilemap, TilePos::new(1, 1)))
+            .id();
+
+        // A tick moves the unit one tile to the right.
+        world.run_system_once(start_tile_motion_ticks).unwrap();
+        world.get_mut::<TileMotion>(unit).unwrap().current = TilePos::new(2, 1);
+        world.run_system_once(update_tile_occupancy).unwrap();
+
+        let occupancy = world.get::<TileOccupancy>(tilemap).unwrap();
+        assert_eq!(occupancy.get(&TilePos::new(2, 1)), Some(unit));
+        assert!(!occupancy.is_occupied(&TilePos::new(1, 1)));
+
+        let motion = *world.get::<TileMotion>(unit).unwrap();
+        let position = |fraction| {
+            motion.presentation_position(
+                fraction,
+                &TilemapSize::new(4, 4),
+                &TilemapGridSize::new(16.0, 16.0),
+                &TilemapTileSize::new(16.0, 16.0),
+                &TilemapType::Square,
+                &TilemapAnchor::None,
+            )
+        };
+        assert_eq!(position(0.0), Vec2::new(16.0, 16.0));
+        assert_eq!(position(0.25), Vec2::new(20.0, 16.0));
+
+        // Without another move, the next tick settles the unit on its tile.
+        world.run_system_once(start_tile_motion_ticks).unwrap();
+        let motion = world.get::<TileMotion>(unit).unwrap();
+        assert_eq!(motion.previous, motion.current);
+    }
+}
@@ -10,6 +10,7 @@ use crate::map::HexCoordSystem;
 use crate::tiles::TilePos;
 use crate::{TilemapGridSize, TilemapSize};
 use bevy::math::{Mat2, Vec2};
+use std::iter::FusedIterator;
 use std::ops::{Add, Mul, Sub};
 
 /// A position in a hex grid labelled according to [`HexCoordSystem::Row`] or
@@ -103,7 +104,7 @@ impl Mul<AxialPos> for u32 {
 }
 
 #[inline]
-fn ceiled_division_by_2(x: i32) -> i32 {
+pub(crate) fn ceiled_division_by_2(x: i32) -> i32 {
     if x < 0 { (x - 1) / 2 } else { (x + 1) / 2 }
 }
 
@@ -234,6 +235,46 @@ impl AxialPos {
         (*self - *other).magnitude()
     }
 
+    /// Rotates `self` by `60` degrees counter-clockwise around `(0, 0)`. See
+    /// [`CubePos::rotate_left`].
+    #[inline]
+    pub fn rotate_left(&self) -> AxialPos {
+        CubePos::from(*self).rotate_left().into()
+    }
+
+    /// Rotates `self` by `60` degrees clockwise around `(0, 0)`. See [`CubePos::rotate_right`].
+    #[inline]
+    pub fn rotate_right(&self) -> AxialPos {
+        CubePos::from(*self).rotate_right().into()
+    }
+
+    /// Rotates `self` around `center` by `steps` multiples of `60` degrees. Positive steps rotate
+    /// counter-clockwise, negative ones clockwise.
+    #[inline]
+    pub fn rotate_around(&self, center: &AxialPos, steps: i32) -> AxialPos {
+        CubePos::from(*self)
+            .rotate_around(&CubePos::from(*center), steps)
+            .into()
+    }
+
+    /// Reflects `self` across the `q` axis through `(0, 0)`. See [`CubePos::reflect_q`].
+    #[inline]
+    pub fn reflect_q(&self) -> AxialPos {
+        CubePos::from(*self).reflect_q().into()
+    }
+
+    /// Reflects `self` across the `r` axis through `(0, 0)`. See [`CubePos::reflect_r`].
+    #[inline]
+    pub fn reflect_r(&self) -> AxialPos {
+        CubePos::from(*self).reflect_r().into()
+    }
+
+    /// Reflects `self` across the `s` axis through `(0, 0)`. See [`CubePos::reflect_s`].
+    #[inline]
+    pub fn reflect_s(&self) -> AxialPos {
+        CubePos::from(*self).reflect_s().into()
+    }
+
     /// Project a vector representing a fractional axial position (i.e. the components can be `f32`)
     /// into world space.
     #[inline]
@@ -467,6 +508,136 @@ impl AxialPos {
     pub fn offset_compass_col(&self, direction: HexColDirection) -> AxialPos {
         *self + HEX_OFFSETS[direction as usize]
     }
+
+    /// Returns the positions at distance `radius` from `self`, starting from the corner in the
+    /// first of the [`HEX_DIRECTIONS`](crate::helpers::hex_grid::neighbors::HEX_DIRECTIONS) and
+    /// going around the ring.
+    ///
+    /// If `radius` is zero, `self` is the only position.
+    #[inline]
+    pub fn ring(&self, radius: u32) -> HexRingIter {
+        HexRingIter {
+            origin: *self,
+            radius,
+            index: 0,
+        }
+    }
+
+    /// Returns the positions at distance at most `radius` from `self`, ring by ring outward,
+    /// starting with `self`.
+    #[inline]
+    pub fn spiral(&self, radius: u32) -> HexSpiralIter {
+        HexSpiralIter {
+            ring: self.ring(0),
+            radius,
+        }
+    }
+}
+
+/// Iterator over the positions of a hex ring, created by [`AxialPos::ring`].
+#[derive(Clone, Debug)]
+pub struct HexRingIter {
+    origin: AxialPos,
+    radius: u32,
+    index: u32,
+}
+
+impl HexRingIter {
+    fn len_of(radius: u32) -> usize {
+        (radius as usize * 6).max(1)
+    }
+}
+
+impl Iterator for HexRingIter {
+    type Item = AxialPos;
+
+    fn next(&mut self) -> Option<AxialPos> {
+        if self.index as usize >= Self::len_of(self.radius) {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        if self.radius == 0 {
+            return Some(self.origin);
+        }
+        let side = index / self.radius;
+        let corner = self.origin + self.radius * AxialPos::from(HexDirection::from(side));
+        // The "tangent" is the direction we must travel in to reach the next corner
+        let tangent = AxialPos::from(HexDirection::from(side + 2));
+        Some(corner + (index % self.radius) * tangent)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = Self::len_of(self.radius) - self.index as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for HexRingIter {}
+
+impl FusedIterator for HexRingIter {}
+
+/// Iterator over the positions of a filled hexagon, created by [`AxialPos::spiral`].
+#[derive(Clone, Debug)]
+pub struct HexSpiralIter {
+    ring: HexRingIter,
+    radius: u32,
+}
+
+impl Iterator for HexSpiralIter {
+    type Item = AxialPos;
+
+    fn next(&mut self) -> Option<AxialPos> {
+        loop {
+            if let Some(axial_pos) = self.ring.next() {
+                return Some(axial_pos);
+            }
+            if self.ring.radius >= self.radius {
+                return None;
+            }
+            self.ring = self.ring.origin.ring(self.ring.radius + 1);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let ring_radius = self.ring.radius as usize;
+        let radius = (self.radius as usize).max(ring_radius);
+        let outer_rings = 3 * (radius * (radius + 1) - ring_radius * (ring_radius + 1));
+        let len = self.ring.len() + outer_rings;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for HexSpiralIter {}
+
+impl FusedIterator for HexSpiralIter {}
+
+impl TilePos {
+    /// Returns the number of steps between `self` and `other` on a hex map using `coord_sys`.
+    #[inline]
+    pub fn hex_distance_to(&self, other: &TilePos, coord_sys: HexCoordSystem) -> u32 {
+        let from = AxialPos::from_tile_pos_given_coord_system(self, coord_sys);
+        let to = AxialPos::from_tile_pos_given_coord_system(other, coord_sys);
+        from.distance_from(&to) as u32
+    }
+
+    /// Returns the positions on the map at most `radius` steps away from `self` on a hex map using
+    /// `coord_sys`, ring by ring outward, starting with `self`.
+    ///
+    /// Positions which do not fit in `map_size` are skipped.
+    pub fn within_range(
+        &self,
+        radius: u32,
+        coord_sys: HexCoordSystem,
+        map_size: &TilemapSize,
+    ) -> impl Iterator<Item = TilePos> + use<> {
+        let map_size = *map_size;
+        AxialPos::from_tile_pos_given_coord_system(self, coord_sys)
+            .spiral(radius)
+            .filter_map(move |axial_pos| {
+                axial_pos.as_tile_pos_given_coord_system_and_map_size(coord_sys, &map_size)
+            })
+    }
 }
 
 /// A fractional axial position can represent a point that lies inside a hexagon. It is typically
@@ -509,3 +680,42 @@ impl From<AxialPos> for FractionalAxialPos {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::hex_grid::neighbors::HEX_DIRECTIONS;
+
+    #[test]
+    fn range_queries_agree_with_neighbors() {
+        let map_size = TilemapSize::new(10, 10);
+        let center = TilePos::new(5, 4);
+        for coord_sys in [
+            HexCoordSystem::Row,
+            HexCoordSystem::RowEven,
+            HexCoordSystem::RowOdd,
+            HexCoordSystem::Column,
+            HexCoordSystem::ColumnEven,
+            HexCoordSystem::ColumnOdd,
+        ] {
+            for direction in HEX_DIRECTIONS {
+                let neighbor = center
+                    .hex_neighbor(direction, coord_sys, &map_size)
+                    .unwrap();
+                assert_eq!(center.hex_distance_to(&neighbor, coord_sys), 1);
+            }
+            let in_range = center
+                .within_range(2, coord_sys, &map_size)
+                .collect::<Vec<_>>();
+            assert_eq!(in_range.len(), 19);
+            assert!(
+                in_range
+                    .iter()
+                    .all(|tile_pos| center.hex_distance_to(tile_pos, coord_sys) <= 2)
+            );
+            // Positions off the map are skipped.
+            let corner = TilePos::new(0, 0);
+            assert!(corner.within_range(3, coord_sys, &map_size).count() < 37);
+        }
+    }
+}
@@ -121,6 +121,57 @@ impl CubePos {
         let cube_pos: CubePos = *self - *other;
         cube_pos.magnitude()
     }
+
+    /// Rotates `self` by `60` degrees counter-clockwise around `[0, 0, 0]`, i.e. from one
+    /// [`HexDirection`](crate::helpers::hex_grid::neighbors::HexDirection) to the next.
+    ///
+    /// See the Red Blob Games article on [rotation](https://www.redblobgames.com/grids/hexagons/#rotation).
+    #[inline]
+    pub fn rotate_left(&self) -> CubePos {
+        CubePos::new(-self.r, -self.s, -self.q)
+    }
+
+    /// Rotates `self` by `60` degrees clockwise around `[0, 0, 0]`, i.e. from one
+    /// [`HexDirection`](crate::helpers::hex_grid::neighbors::HexDirection) to the previous one.
+    #[inline]
+    pub fn rotate_right(&self) -> CubePos {
+        CubePos::new(-self.s, -self.q, -self.r)
+    }
+
+    /// Rotates `self` around `center` by `steps` multiples of `60` degrees. Positive steps rotate
+    /// counter-clockwise, negative ones clockwise.
+    #[inline]
+    pub fn rotate_around(&self, center: &CubePos, steps: i32) -> CubePos {
+        let mut offset = *self - *center;
+        for _ in 0..steps.rem_euclid(6) {
+            offset = offset.rotate_left();
+        }
+        *center + offset
+    }
+
+    /// Reflects `self` across the `q` axis through `[0, 0, 0]`, keeping `q` and swapping `r` and
+    /// `s`.
+    ///
+    /// To reflect across an axis through another position, subtract that position first and add
+    /// it back afterwards.
+    #[inline]
+    pub fn reflect_q(&self) -> CubePos {
+        CubePos::new(self.q, self.s, self.r)
+    }
+
+    /// Reflects `self` across the `r` axis through `[0, 0, 0]`, keeping `r` and swapping `q` and
+    /// `s`.
+    #[inline]
+    pub fn reflect_r(&self) -> CubePos {
+        CubePos::new(self.s, self.r, self.q)
+    }
+
+    /// Reflects `self` across the `s` axis through `[0, 0, 0]`, keeping `s` and swapping `q` and
+    /// `r`.
+    #[inline]
+    pub fn reflect_s(&self) -> CubePos {
+        CubePos::new(self.r, self.q, self.s)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialOrd, PartialEq)]
@@ -145,11 +196,14 @@ impl FractionalCubePos {
 
     /// Returns `self` rounded to a [`CubePos`] that contains `self`. This is particularly useful
     /// for determining the hex tile that this fractional position is in.
+    ///
+    /// Halves are rounded up, like the square and isometric projections do, so a position on the
+    /// border between two tiles is resolved the same way on either side of the origin.
     #[inline]
     pub fn round(&self) -> CubePos {
-        let q_round = self.q.round();
-        let r_round = self.r.round();
-        let s_round = self.s.round();
+        let q_round = (self.q + 0.5).floor();
+        let r_round = (self.r + 0.5).floor();
+        let s_round = (self.s + 0.5).floor();
 
         let q_diff = (q_round - self.q).abs();
         let r_diff = (r_round - self.r).abs();
@@ -175,3 +229,32 @@ impl FractionalCubePos {
         CubePos { q, r, s }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::hex_grid::neighbors::{HEX_DIRECTIONS, HexDirection};
+
+    #[test]
+    fn rotations_step_through_directions() {
+        for direction in HEX_DIRECTIONS {
+            let cube_pos = CubePos::from(AxialPos::from(direction));
+            let next = CubePos::from(AxialPos::from(direction + 1usize));
+            assert_eq!(cube_pos.rotate_left(), next);
+            assert_eq!(next.rotate_right(), cube_pos);
+        }
+
+        let center = AxialPos::new(3, -2);
+        let axial_pos = center + 2 * AxialPos::from(HexDirection::Zero);
+        assert_eq!(
+            axial_pos.rotate_around(&center, -2),
+            center + 2 * AxialPos::from(HexDirection::Four)
+        );
+        assert_eq!(axial_pos.rotate_around(&center, 6), axial_pos);
+
+        let cube_pos = CubePos::new(2, -3, 1);
+        assert_eq!(cube_pos.reflect_q(), CubePos::new(2, 1, -3));
+        assert_eq!(cube_pos.reflect_r().reflect_r(), cube_pos);
+        assert_eq!(cube_pos.reflect_s().s, cube_pos.s);
+    }
+}
@@ -0,0 +1,570 @@
+//! Edges and vertices of hex grids, e.g. for the roads and settlements of a board game.
+//!
+//! Both are identified by a canonical tile and direction, so the same edge or vertex compares
+//! equal no matter which of the tiles touching it it was constructed from.
+
+use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::hex_grid::neighbors::{HEX_OFFSETS, HexDirection};
+use crate::map::HexCoordSystem;
+use crate::tiles::TilePos;
+use crate::{TilemapAnchor, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use bevy::math::{IVec2, Vec2};
+
+/// Projects a fractional axial position into the local space of a hex map, ignoring its anchor.
+fn project(axial_pos: Vec2, grid_size: &TilemapGridSize, hex_coord_sys: HexCoordSystem) -> Vec2 {
+    match hex_coord_sys {
+        HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => {
+            AxialPos::project_row(axial_pos, grid_size)
+        }
+        HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+            AxialPos::project_col(axial_pos, grid_size)
+        }
+    }
+}
+
+/// Returns the axial position of the tile containing the given position in the local space of a
+/// hex map, ignoring its anchor and the extents of the map.
+fn axial_from_world_pos(
+    pos: &Vec2,
+    grid_size: &TilemapGridSize,
+    hex_coord_sys: HexCoordSystem,
+) -> AxialPos {
+    match hex_coord_sys {
+        HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => {
+            AxialPos::from_world_pos_row(pos, grid_size)
+        }
+        HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+            AxialPos::from_world_pos_col(pos, grid_size)
+        }
+    }
+}
+
+fn anchor_offset(
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    hex_coord_sys: HexCoordSystem,
+    anchor: &TilemapAnchor,
+) -> Vec2 {
+    anchor.as_offset(
+        map_size,
+        grid_size,
+        tile_size,
+        &TilemapType::Hexagon(hex_coord_sys),
+    )
+}
+
+fn is_on_map(axial_pos: &AxialPos, hex_coord_sys: HexCoordSystem, map_size: &TilemapSize) -> bool {
+    axial_pos
+        .as_tile_pos_given_coord_system_and_map_size(hex_coord_sys, map_size)
+        .is_some()
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = if segment.length_squared() > 0.0 {
+        ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(start + t * segment)
+}
+
+/// An edge of a hex grid, i.e. the side shared by two neighboring tiles.
+///
+/// It is stored as a tile and the [`HexDirection`] of the neighbor on the other side of the edge,
+/// normalized so that the direction is [`Zero`](HexDirection::Zero), [`One`](HexDirection::One) or
+/// [`Two`](HexDirection::Two).
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexEdge {
+    tile: AxialPos,
+    direction: HexDirection,
+}
+
+impl HexEdge {
+    /// Returns the edge of `tile` which faces its neighbor in `direction`.
+    pub fn new(tile: AxialPos, direction: HexDirection) -> Self {
+        if (direction as usize) < 3 {
+            Self { tile, direction }
+        } else {
+            Self {
+                tile: tile.offset(direction),
+                direction: direction - 3usize,
+            }
+        }
+    }
+
+    /// Returns the edge of the tile at `tile_pos` which faces its neighbor in `direction`.
+    pub fn from_tile_pos(
+        tile_pos: &TilePos,
+        direction: HexDirection,
+        hex_coord_sys: HexCoordSystem,
+    ) -> Self {
+        Self::new(
+            AxialPos::from_tile_pos_given_coord_system(tile_pos, hex_coord_sys),
+            direction,
+        )
+    }
+
+    /// Returns the six edges of a tile, starting with the one in [`HexDirection::Zero`].
+    pub fn of_tile(tile: AxialPos) -> [Self; 6] {
+        std::array::from_fn(|ix| Self::new(tile, ix.into()))
+    }
+
+    /// The canonical tile of the edge.
+    pub fn tile(&self) -> AxialPos {
+        self.tile
+    }
+
+    /// The direction of the edge as seen from [`tile`](Self::tile). It is always
+    /// [`Zero`](HexDirection::Zero), [`One`](HexDirection::One) or [`Two`](HexDirection::Two),
+    /// which can be used to pick a differently rotated texture for each orientation of edge.
+    pub fn direction(&self) -> HexDirection {
+        self.direction
+    }
+
+    /// The two tiles sharing this edge.
+    pub fn tiles(&self) -> [AxialPos; 2] {
+        [self.tile, self.tile.offset(self.direction)]
+    }
+
+    /// The two vertices at the ends of this edge.
+    pub fn vertices(&self) -> [HexVertex; 2] {
+        [
+            HexVertex::new(self.tile, self.direction + 5usize),
+            HexVertex::new(self.tile, self.direction),
+        ]
+    }
+
+    /// The position of the edge on a grid with half the spacing of the tiles, in axial
+    /// coordinates. Every edge has a unique key.
+    fn key(&self) -> IVec2 {
+        let offset = HEX_OFFSETS[self.direction as usize];
+        IVec2::new(2 * self.tile.q + offset.q, 2 * self.tile.r + offset.r)
+    }
+
+    /// Returns `true` if at least one of the tiles sharing the edge lies on the map.
+    pub fn is_on_map(&self, hex_coord_sys: HexCoordSystem, map_size: &TilemapSize) -> bool {
+        self.tiles()
+            .iter()
+            .any(|tile| is_on_map(tile, hex_coord_sys, map_size))
+    }
+
+    /// Returns the midpoint of the edge in world space.
+    pub fn center_in_world(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> Vec2 {
+        anchor_offset(map_size, grid_size, tile_size, hex_coord_sys, anchor)
+            + project(self.key().as_vec2() / 2.0, grid_size, hex_coord_sys)
+    }
+
+    /// Returns the ends of the edge in world space.
+    pub fn endpoints_in_world(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> [Vec2; 2] {
+        self.vertices().map(|vertex| {
+            vertex.center_in_world(map_size, grid_size, tile_size, hex_coord_sys, anchor)
+        })
+    }
+
+    /// Returns the edge closest to `world_pos`, if it is at most `tolerance` away from it and
+    /// touches a tile on the map.
+    pub fn from_world_pos(
+        world_pos: &Vec2,
+        tolerance: f32,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> Option<Self> {
+        let pos = world_pos - anchor_offset(map_size, grid_size, tile_size, hex_coord_sys, anchor);
+        // The closest edge is always one of the edges of the tile containing the position.
+        let tile = axial_from_world_pos(&pos, grid_size, hex_coord_sys);
+        Self::of_tile(tile)
+            .into_iter()
+            .map(|edge| {
+                let [start, end] = edge
+                    .vertices()
+                    .map(|vertex| project(vertex.key().as_vec2() / 3.0, grid_size, hex_coord_sys));
+                (distance_to_segment(pos, start, end), edge)
+            })
+            .filter(|(distance, edge)| {
+                *distance <= tolerance && edge.is_on_map(hex_coord_sys, map_size)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, edge)| edge)
+    }
+}
+
+/// A vertex of a hex grid, i.e. the corner shared by three tiles.
+///
+/// It is stored as a tile and a [`HexDirection`], naming the corner between the neighbors in that
+/// direction and the next one. It is normalized so that the direction is
+/// [`Zero`](HexDirection::Zero) or [`One`](HexDirection::One).
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexVertex {
+    tile: AxialPos,
+    direction: HexDirection,
+}
+
+/// The keys of the corners in direction `Zero` and `One` of the tile at `(0, 0)`.
+const VERTEX_KEY_OFFSETS: [IVec2; 2] = [IVec2::new(1, 1), IVec2::new(-1, 2)];
+
+impl HexVertex {
+    /// Returns the corner of `tile` between its neighbors in `direction` and `direction + 1`.
+    pub fn new(tile: AxialPos, direction: HexDirection) -> Self {
+        let a = HEX_OFFSETS[direction as usize];
+        let b = HEX_OFFSETS[(direction + 1usize) as usize];
+        Self::from_key(IVec2::new(3 * tile.q + a.q + b.q, 3 * tile.r + a.r + b.r))
+    }
+
+    /// Returns the corner of the tile at `tile_pos` between its neighbors in `direction` and
+    /// `direction + 1`.
+    pub fn from_tile_pos(
+        tile_pos: &TilePos,
+        direction: HexDirection,
+        hex_coord_sys: HexCoordSystem,
+    ) -> Self {
+        Self::new(
+            AxialPos::from_tile_pos_given_coord_system(tile_pos, hex_coord_sys),
+            direction,
+        )
+    }
+
+    /// Returns the six corners of a tile, starting with the one between the neighbors in
+    /// [`HexDirection::Zero`] and [`HexDirection::One`].
+    pub fn of_tile(tile: AxialPos) -> [Self; 6] {
+        std::array::from_fn(|ix| Self::new(tile, ix.into()))
+    }
+
+    /// The position of the vertex on a grid with a third of the spacing of the tiles, in axial
+    /// coordinates. Every vertex has a unique key.
+    fn key(&self) -> IVec2 {
+        IVec2::new(3 * self.tile.q, 3 * self.tile.r) + VERTEX_KEY_OFFSETS[self.direction as usize]
+    }
+
+    fn from_key(key: IVec2) -> Self {
+        // Each vertex is the corner `Zero` or `One` of exactly one tile.
+        let (ix, tile) = VERTEX_KEY_OFFSETS
+            .iter()
+            .enumerate()
+            .map(|(ix, offset)| (ix, key - *offset))
+            .find(|(_, tile)| tile.x.rem_euclid(3) == 0 && tile.y.rem_euclid(3) == 0)
+            .expect("every vertex key is the corner of a tile");
+        Self {
+            tile: AxialPos::new(tile.x / 3, tile.y / 3),
+            direction: ix.into(),
+        }
+    }
+
+    /// The canonical tile of the vertex.
+    pub fn tile(&self) -> AxialPos {
+        self.tile
+    }
+
+    /// The direction of the vertex as seen from [`tile`](Self::tile). It is always
+    /// [`Zero`](HexDirection::Zero) or [`One`](HexDirection::One).
+    pub fn direction(&self) -> HexDirection {
+        self.direction
+    }
+
+    /// The three tiles sharing this vertex.
+    pub fn tiles(&self) -> [AxialPos; 3] {
+        [
+            self.tile,
+            self.tile.offset(self.direction),
+            self.tile.offset(self.direction + 1usize),
+        ]
+    }
+
+    /// The three edges meeting at this vertex.
+    pub fn edges(&self) -> [HexEdge; 3] {
+        let [tile, a, _] = self.tiles();
+        [
+            HexEdge::new(tile, self.direction),
+            HexEdge::new(tile, self.direction + 1usize),
+            HexEdge::new(a, self.direction + 2usize),
+        ]
+    }
+
+    /// Returns `true` if at least one of the tiles sharing the vertex lies on the map.
+    pub fn is_on_map(&self, hex_coord_sys: HexCoordSystem, map_size: &TilemapSize) -> bool {
+        self.tiles()
+            .iter()
+            .any(|tile| is_on_map(tile, hex_coord_sys, map_size))
+    }
+
+    /// Returns the position of the vertex in world space.
+    pub fn center_in_world(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> Vec2 {
+        anchor_offset(map_size, grid_size, tile_size, hex_coord_sys, anchor)
+            + project(self.key().as_vec2() / 3.0, grid_size, hex_coord_sys)
+    }
+
+    /// Returns the vertex closest to `world_pos`, if it is at most `tolerance` away from it and
+    /// touches a tile on the map.
+    pub fn from_world_pos(
+        world_pos: &Vec2,
+        tolerance: f32,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> Option<Self> {
+        let pos = world_pos - anchor_offset(map_size, grid_size, tile_size, hex_coord_sys, anchor);
+        // The closest vertex is always one of the corners of the tile containing the position.
+        let tile = axial_from_world_pos(&pos, grid_size, hex_coord_sys);
+        Self::of_tile(tile)
+            .into_iter()
+            .map(|vertex| {
+                let center = project(vertex.key().as_vec2() / 3.0, grid_size, hex_coord_sys);
+                (pos.distance(center), vertex)
+            })
+            .filter(|(distance, vertex)| {
+                *distance <= tolerance && vertex.is_on_map(hex_coord_sys, map_size)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, vertex)| vertex)
+    }
+}
+
+/// Which features of a hex map a [`HexFeatureLayer`] draws.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum HexFeature {
+    Edges,
+    Vertices,
+}
+
+/// The layout of a tilemap which draws items on the edges or vertices of a hex map, such as roads
+/// or settlements.
+///
+/// The midpoints of the edges of a hex grid form a hex grid with half the spacing, and its
+/// vertices form one with a third of the spacing. A feature layer is a regular tilemap on that
+/// finer grid, so its items are drawn in chunks like any other tile, and each edge or vertex maps
+/// to a [`TilePos`] of the layer. Cells of the finer grid which are not edges or vertices are
+/// simply left empty.
+///
+/// Spawn the layer with [`map_size`](Self::map_size), [`grid_size`](Self::grid_size),
+/// [`map_type`](Self::map_type) and [`TilemapAnchor::None`], and translate it by
+/// [`offset`](Self::offset) relative to the hex map. Since tiles can't be rotated by 60 degrees,
+/// use [`HexEdge::direction`] to pick a texture for each orientation of edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HexFeatureLayer {
+    pub feature: HexFeature,
+    pub map_size: TilemapSize,
+    pub grid_size: TilemapGridSize,
+    pub map_type: TilemapType,
+    /// The translation of the layer relative to the hex map.
+    pub offset: Vec2,
+    /// The key of the feature at `TilePos(0, 0)` of the layer.
+    origin: IVec2,
+}
+
+impl HexFeatureLayer {
+    /// Computes the layout of a feature layer covering every edge or vertex of the given hex map.
+    pub fn new(
+        feature: HexFeature,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        hex_coord_sys: HexCoordSystem,
+        anchor: &TilemapAnchor,
+    ) -> Self {
+        let (scale, margin) = match feature {
+            HexFeature::Edges => (2, 1),
+            HexFeature::Vertices => (3, 2),
+        };
+        let layer_grid_size =
+            TilemapGridSize::new(grid_size.x / scale as f32, grid_size.y / scale as f32);
+        let layer_coord_sys = match hex_coord_sys {
+            HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => {
+                HexCoordSystem::Row
+            }
+            HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+                HexCoordSystem::Column
+            }
+        };
+
+        // An empty map has no edges or vertices, and no bounds to anchor a layer to.
+        if map_size.x == 0 || map_size.y == 0 {
+            return Self {
+                feature,
+                map_size: TilemapSize::new(0, 0),
+                grid_size: layer_grid_size,
+                map_type: TilemapType::Hexagon(layer_coord_sys),
+                offset: Vec2::ZERO,
+                origin: IVec2::ZERO,
+            };
+        }
+
+        let mut min = IVec2::MAX;
+        let mut max = IVec2::MIN;
+        for x in 0..map_size.x {
+            for y in 0..map_size.y {
+                let axial_pos =
+                    AxialPos::from_tile_pos_given_coord_system(&TilePos::new(x, y), hex_coord_sys);
+                let pos = IVec2::new(axial_pos.q, axial_pos.r);
+                min = min.min(pos);
+                max = max.max(pos);
+            }
+        }
+        let origin = min * scale - margin;
+        let size = (max * scale + margin - origin + 1)
+            .max(IVec2::ZERO)
+            .as_uvec2();
+        let offset = anchor_offset(map_size, grid_size, tile_size, hex_coord_sys, anchor)
+            + project(origin.as_vec2(), &layer_grid_size, layer_coord_sys);
+
+        Self {
+            feature,
+            map_size: size.into(),
+            grid_size: layer_grid_size,
+            map_type: TilemapType::Hexagon(layer_coord_sys),
+            offset,
+            origin,
+        }
+    }
+
+    fn tile_pos(&self, key: IVec2) -> Option<TilePos> {
+        let pos = key - self.origin;
+        TilePos::from_i32_pair(pos.x, pos.y, &self.map_size)
+    }
+
+    /// Returns the position of the tile drawing `edge`, or `None` if this is not an edge layer or
+    /// the edge lies outside of the map.
+    pub fn edge_tile_pos(&self, edge: &HexEdge) -> Option<TilePos> {
+        match self.feature {
+            HexFeature::Edges => self.tile_pos(edge.key()),
+            HexFeature::Vertices => None,
+        }
+    }
+
+    /// Returns the position of the tile drawing `vertex`, or `None` if this is not a vertex layer
+    /// or the vertex lies outside of the map.
+    pub fn vertex_tile_pos(&self, vertex: &HexVertex) -> Option<TilePos> {
+        match self.feature {
+            HexFeature::Edges => None,
+            HexFeature::Vertices => self.tile_pos(vertex.key()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_features_are_equal() {
+        let tile = AxialPos::new(2, 3);
+        for direction in 0..6usize {
+            let direction = HexDirection::from(direction);
+            let neighbor = tile.offset(direction);
+            assert_eq!(
+                HexEdge::new(tile, direction),
+                HexEdge::new(neighbor, direction + 3usize)
+            );
+
+            let vertex = HexVertex::new(tile, direction);
+            assert!(HexVertex::of_tile(neighbor).contains(&vertex));
+            assert!(vertex.tiles().contains(&neighbor));
+            for edge in vertex.edges() {
+                assert!(edge.vertices().contains(&vertex));
+            }
+        }
+    }
+
+    #[test]
+    fn picking_and_layers_agree() {
+        let map_size = TilemapSize::new(4, 4);
+        let grid_size = TilemapGridSize::new(32.0, 36.0);
+        let tile_size = TilemapTileSize::new(32.0, 36.0);
+        let anchor = TilemapAnchor::Center;
+
+        for hex_coord_sys in [HexCoordSystem::RowOdd, HexCoordSystem::Column] {
+            let edge =
+                HexEdge::from_tile_pos(&TilePos::new(1, 2), HexDirection::Four, hex_coord_sys);
+            let edge_center =
+                edge.center_in_world(&map_size, &grid_size, &tile_size, hex_coord_sys, &anchor);
+            let picked = HexEdge::from_world_pos(
+                &(edge_center + Vec2::splat(1.0)),
+                4.0,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                hex_coord_sys,
+                &anchor,
+            );
+            assert_eq!(picked, Some(edge));
+
+            let layer = HexFeatureLayer::new(
+                HexFeature::Edges,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                hex_coord_sys,
+                &anchor,
+            );
+            let layer_pos = layer.edge_tile_pos(&edge).unwrap().center_in_world(
+                &layer.map_size,
+                &layer.grid_size,
+                &tile_size,
+                &layer.map_type,
+                &TilemapAnchor::None,
+            ) + layer.offset;
+            assert!(layer_pos.distance(edge_center) < 1e-3);
+
+            let vertex = edge.vertices()[0];
+            let center =
+                vertex.center_in_world(&map_size, &grid_size, &tile_size, hex_coord_sys, &anchor);
+            let picked = HexVertex::from_world_pos(
+                &center,
+                4.0,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                hex_coord_sys,
+                &anchor,
+            );
+            assert_eq!(picked, Some(vertex));
+        }
+    }
+
+    #[test]
+    fn empty_maps_have_empty_layers() {
+        for map_size in [TilemapSize::new(0, 0), TilemapSize::new(0, 5)] {
+            for feature in [HexFeature::Edges, HexFeature::Vertices] {
+                let layer = HexFeatureLayer::new(
+                    feature,
+                    &map_size,
+                    &TilemapGridSize::new(32.0, 36.0),
+                    &TilemapTileSize::new(32.0, 36.0),
+                    HexCoordSystem::RowOdd,
+                    &TilemapAnchor::Center,
+                );
+                assert_eq!(layer.map_size, TilemapSize::new(0, 0));
+                let edge = HexEdge::new(AxialPos::new(0, 0), HexDirection::Zero);
+                assert_eq!(layer.edge_tile_pos(&edge), None);
+            }
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::TilePos;
 use crate::helpers::hex_grid::axial::AxialPos;
 use crate::helpers::hex_grid::offset::{ColEvenPos, ColOddPos, RowEvenPos, RowOddPos};
-use crate::map::{HexCoordSystem, TilemapSize};
-use crate::prelude::TileStorage;
+use crate::map::{HexCoordSystem, TilemapSize, TilemapTopology};
+use crate::prelude::TileStore;
+use bevy::math::IVec2;
 use bevy::prelude::Entity;
 use std::ops::{Add, Sub};
 
@@ -18,6 +19,7 @@ use std::ops::{Add, Sub};
 ///
 /// [`HexDirection`]s can be converted from/into `usize`, `u32`, `isize`, `i32`.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HexDirection {
     Zero,
     One,
@@ -527,9 +529,48 @@ impl HexNeighbors<TilePos> {
         HexNeighbors::from_directional_closure(f)
     }
 
+    /// Returns neighboring tile positions on a map with the given [`TilemapTopology`], so tiles on
+    /// a wrapping edge have neighbors across it.
+    ///
+    /// A tile position will be `None` for a particular direction, if that neighbor would lie past
+    /// a bounded edge of the map.
+    pub fn get_neighboring_positions_wrapped(
+        tile_pos: &TilePos,
+        map_size: &TilemapSize,
+        hex_coord_sys: &HexCoordSystem,
+        topology: &TilemapTopology,
+    ) -> HexNeighbors<TilePos> {
+        let f = |direction| {
+            let coords = match hex_coord_sys {
+                HexCoordSystem::RowEven => {
+                    let p = RowEvenPos::from(tile_pos).offset(direction);
+                    IVec2::new(p.q, p.r)
+                }
+                HexCoordSystem::RowOdd => {
+                    let p = RowOddPos::from(tile_pos).offset(direction);
+                    IVec2::new(p.q, p.r)
+                }
+                HexCoordSystem::ColumnEven => {
+                    let p = ColEvenPos::from(tile_pos).offset(direction);
+                    IVec2::new(p.q, p.r)
+                }
+                HexCoordSystem::ColumnOdd => {
+                    let p = ColOddPos::from(tile_pos).offset(direction);
+                    IVec2::new(p.q, p.r)
+                }
+                HexCoordSystem::Row | HexCoordSystem::Column => {
+                    let p = AxialPos::from(tile_pos).offset(direction);
+                    IVec2::new(p.q, p.r)
+                }
+            };
+            topology.wrap(coords, map_size)
+        };
+        HexNeighbors::from_directional_closure(f)
+    }
+
     /// Returns the entities associated with each tile position.
     #[inline]
-    pub fn entities(&self, tile_storage: &TileStorage) -> HexNeighbors<Entity> {
+    pub fn entities(&self, tile_storage: &impl TileStore) -> HexNeighbors<Entity> {
         let f = |tile_pos| tile_storage.get(tile_pos);
         self.and_then_ref(f)
     }
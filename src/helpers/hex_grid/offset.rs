@@ -1,7 +1,10 @@
 //! Code for the offset coordinate system.
 
-use crate::helpers::hex_grid::axial::AxialPos;
-use crate::helpers::hex_grid::neighbors::{HexColDirection, HexDirection, HexRowDirection};
+use crate::helpers::hex_grid::axial::{AxialPos, ceiled_division_by_2};
+use crate::helpers::hex_grid::neighbors::{
+    HEX_OFFSETS, HexColDirection, HexDirection, HexRowDirection,
+};
+use crate::map::HexCoordSystem;
 use crate::tiles::TilePos;
 use crate::{TilemapGridSize, TilemapSize};
 use bevy::math::Vec2;
@@ -369,3 +372,65 @@ impl From<&TilePos> for ColEvenPos {
         }
     }
 }
+
+/// Returns `true` if the row of `tile_pos` is shoved half a tile to the right (towards `+x`),
+/// relative to the rows above and below it.
+///
+/// Only [`HexCoordSystem::RowOdd`] (odd rows are shoved) and [`HexCoordSystem::RowEven`] (even
+/// rows are shoved) have offset rows; this is `false` for every other coordinate system.
+#[inline]
+pub fn is_offset_row(tile_pos: &TilePos, coord_sys: HexCoordSystem) -> bool {
+    match coord_sys {
+        HexCoordSystem::RowOdd => !tile_pos.y.is_multiple_of(2),
+        HexCoordSystem::RowEven => tile_pos.y.is_multiple_of(2),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the column of `tile_pos` is shoved half a tile upwards (towards `+y`),
+/// relative to the columns to its left and right.
+///
+/// Only [`HexCoordSystem::ColumnOdd`] (odd columns are shoved) and
+/// [`HexCoordSystem::ColumnEven`] (even columns are shoved) have offset columns; this is `false`
+/// for every other coordinate system.
+#[inline]
+pub fn is_offset_col(tile_pos: &TilePos, coord_sys: HexCoordSystem) -> bool {
+    match coord_sys {
+        HexCoordSystem::ColumnOdd => !tile_pos.x.is_multiple_of(2),
+        HexCoordSystem::ColumnEven => tile_pos.x.is_multiple_of(2),
+        _ => false,
+    }
+}
+
+impl TilePos {
+    /// Returns the tile directly adjacent to `self` in the given [`HexDirection`], working on the
+    /// offset coordinates of `coord_sys` directly.
+    ///
+    /// Whether a neighbor in a diagonal direction lies in the same or in the adjacent
+    /// column (row) depends on the parity of `self`'s row (column); this takes care of it.
+    ///
+    /// Returns `None` if the neighbor lies outside of `map_size`.
+    pub fn hex_neighbor(
+        &self,
+        direction: HexDirection,
+        coord_sys: HexCoordSystem,
+        map_size: &TilemapSize,
+    ) -> Option<TilePos> {
+        let AxialPos { q: dq, r: dr } = HEX_OFFSETS[direction as usize];
+        let (x, y) = (self.x as i32, self.y as i32);
+        let (x, y) = match coord_sys {
+            HexCoordSystem::RowOdd => (x + dq + (y + dr) / 2 - y / 2, y + dr),
+            HexCoordSystem::RowEven => (
+                x + dq + ceiled_division_by_2(y + dr) - ceiled_division_by_2(y),
+                y + dr,
+            ),
+            HexCoordSystem::ColumnOdd => (x + dq, y + dr + (x + dq) / 2 - x / 2),
+            HexCoordSystem::ColumnEven => (
+                x + dq,
+                y + dr + ceiled_division_by_2(x + dq) - ceiled_division_by_2(x),
+            ),
+            HexCoordSystem::Row | HexCoordSystem::Column => (x + dq, y + dr),
+        };
+        TilePos::from_i32_pair(x, y, map_size)
+    }
+}
@@ -0,0 +1,287 @@
+//! Highlights the hovered and selected tiles of tilemaps by recoloring them.
+//!
+//! Add the [`TileHighlightPlugin`] and fill the [`HoveredTile`] and [`SelectedTiles`] resources,
+//! or let the plugin fill them from a [`TileCursor`]: the hovered tile follows the cursor, and
+//! confirming a tile selects it (holding shift adds it to the selection or removes it again).
+//!
+//! Highlighted tiles have their [`TileColor`] changed, and get it back once they are no longer
+//! highlighted. Colors set on a highlighted tile by other code are kept, and highlighted again.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::cursor::{TileCursor, TileCursorPlugin};
+//! # use bevy_ecs_tilemap::helpers::highlight::*;
+//! fn build(app: &mut App) {
+//!     app.add_plugins((TileCursorPlugin, TileHighlightPlugin))
+//!         .insert_resource(TileHighlightSettings {
+//!             selected_color: Color::srgb(1.0, 0.3, 0.3),
+//!             ..Default::default()
+//!         })
+//!         .add_systems(Update, report_selection);
+//! }
+//!
+//! fn report_selection(selected: Res<SelectedTiles>) {
+//!     if selected.is_changed() {
+//!         info!("{} tiles selected", selected.len());
+//!     }
+//! }
+//!
+//! // The cursor drives the hovered tile and the selection.
+//! fn spawn_cursor(mut commands: Commands, tilemap: Entity, camera: Entity) {
+//!     commands.spawn(TileCursor::new(tilemap, camera));
+//! }
+//! ```
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use crate::helpers::cursor::{TileCursor, TileCursorConfirmed, TileCursorSystems};
+use crate::tiles::{TileColor, TilePos, TileStorage};
+
+/// Adds the resources and systems which highlight tiles.
+pub struct TileHighlightPlugin;
+
+impl Plugin for TileHighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HoveredTile>()
+            .init_resource::<SelectedTiles>()
+            .init_resource::<TileHighlightSettings>()
+            .add_message::<TileCursorConfirmed>()
+            .add_systems(
+                Update,
+                (hover_cursor_tiles, select_confirmed_tiles).after(TileCursorSystems::Apply),
+            )
+            .add_systems(PostUpdate, apply_tile_highlights);
+    }
+}
+
+/// The tile under the mouse, as a tilemap and a position on it.
+///
+/// Set from the first [`TileCursor`] which is over a tile, if there is one.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HoveredTile(pub Option<(Entity, TilePos)>);
+
+/// The selected tiles, as tilemaps and positions on them.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SelectedTiles {
+    tiles: HashSet<(Entity, TilePos)>,
+}
+
+impl SelectedTiles {
+    pub fn select(&mut self, tilemap: Entity, tile_pos: TilePos) {
+        self.tiles.insert((tilemap, tile_pos));
+    }
+
+    pub fn deselect(&mut self, tilemap: Entity, tile_pos: TilePos) {
+        self.tiles.remove(&(tilemap, tile_pos));
+    }
+
+    /// Selects the tile if it is not selected, and deselects it otherwise.
+    pub fn toggle(&mut self, tilemap: Entity, tile_pos: TilePos) {
+        if !self.tiles.remove(&(tilemap, tile_pos)) {
+            self.tiles.insert((tilemap, tile_pos));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn contains(&self, tilemap: Entity, tile_pos: &TilePos) -> bool {
+        self.tiles.contains(&(tilemap, *tile_pos))
+    }
+
+    /// Returns the selected tiles, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, TilePos)> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+/// How highlighted tiles are colored.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct TileHighlightSettings {
+    pub hovered_color: Color,
+    pub selected_color: Color,
+    /// How much of the highlight color is mixed into the color of a tile, from `0.0` (none) to
+    /// `1.0` (the tile takes the highlight color).
+    pub strength: f32,
+}
+
+impl Default for TileHighlightSettings {
+    fn default() -> Self {
+        Self {
+            hovered_color: Color::srgb(1.0, 1.0, 0.6),
+            selected_color: Color::srgb(0.4, 0.8, 1.0),
+            strength: 0.5,
+        }
+    }
+}
+
+/// Added to highlighted tiles, to restore their color once they are no longer highlighted.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TileHighlighted {
+    /// The color of the tile without the highlight.
+    pub original: TileColor,
+    /// The color the highlight gave the tile.
+    highlighted: Color,
+}
+
+fn hover_cursor_tiles(cursors: Query<&TileCursor>, mut hovered: ResMut<HoveredTile>) {
+    if cursors.is_empty() {
+        return;
+    }
+    let tile = cursors
+        .iter()
+        .find_map(|cursor| Some((cursor.tilemap, cursor.tile_pos?)));
+    hovered.set_if_neq(HoveredTile(tile));
+}
+
+fn select_confirmed_tiles(
+    mut confirmed: MessageReader<TileCursorConfirmed>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mut selected: ResMut<SelectedTiles>,
+) {
+    let adding =
+        keys.is_some_and(|keys| keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]));
+    for confirmed in confirmed.read() {
+        if adding {
+            selected.toggle(confirmed.tilemap, confirmed.tile_pos);
+        } else {
+            selected.clear();
+            selected.select(confirmed.tilemap, confirmed.tile_pos);
+        }
+    }
+}
+
+fn apply_tile_highlights(
+    mut commands: Commands,
+    hovered: Res<HoveredTile>,
+    selected: Res<SelectedTiles>,
+    settings: Res<TileHighlightSettings>,
+    tilemaps: Query<&TileStorage>,
+    mut tiles: Query<(&mut TileColor, Option<&mut TileHighlighted>)>,
+    highlighted: Query<Entity, With<TileHighlighted>>,
+) {
+    let tile_entity =
+        |(tilemap, tile_pos): (Entity, TilePos)| tilemaps.get(tilemap).ok()?.checked_get(&tile_pos);
+    let mut highlights = selected
+        .iter()
+        .filter_map(|tile| Some((tile_entity(tile)?, settings.selected_color)))
+        .collect::<HashMap<_, _>>();
+    // Hovering a selected tile shows the hover.
+    if let Some(tile_entity) = hovered.0.and_then(tile_entity) {
+        highlights.insert(tile_entity, settings.hovered_color);
+    }
+
+    for tile_entity in highlighted.iter() {
+        if highlights.contains_key(&tile_entity) {
+            continue;
+        }
+        if let Ok((mut color, Some(highlight))) = tiles.get_mut(tile_entity)
+            && color.0 == highlight.highlighted
+        {
+            *color = highlight.original;
+        }
+        commands.entity(tile_entity).remove::<TileHighlighted>();
+    }
+
+    for (tile_entity, highlight_color) in highlights {
+        let Ok((mut color, highlight)) = tiles.get_mut(tile_entity) else {
+            continue;
+        };
+        // A color which differs from the highlight was set by someone else, and is the one to
+        // restore later.
+        let original = match &highlight {
+            Some(highlight) if color.0 == highlight.highlighted => highlight.original,
+            _ => *color,
+        };
+        let highlighted = original.0.mix(&highlight_color, settings.strength);
+        if color.0 != highlighted {
+            color.0 = highlighted;
+        }
+        match highlight {
+            Some(mut highlight) => {
+                highlight.original = original;
+                highlight.highlighted = highlighted;
+            }
+            None => {
+                commands.entity(tile_entity).insert(TileHighlighted {
+                    original,
+                    highlighted,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapId, TilemapSize};
+    use crate::tiles::TileBundle;
+
+    #[test]
+    fn highlights_are_applied_and_restored() {
+        let mut world = World::new();
+        world.init_resource::<HoveredTile>();
+        world.init_resource::<SelectedTiles>();
+        world.insert_resource(TileHighlightSettings {
+            strength: 1.0,
+            ..Default::default()
+        });
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_tile_highlights);
+
+        let map_size = TilemapSize::new(2, 1);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        let red = Color::srgb(1.0, 0.0, 0.0);
+        let tiles = [TilePos::new(0, 0), TilePos::new(1, 0)].map(|tile_pos| {
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    color: TileColor(red),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+            tile_entity
+        });
+        world.entity_mut(tilemap).insert(tile_storage);
+        let color = |world: &World, tile: Entity| world.get::<TileColor>(tile).unwrap().0;
+        let settings = *world.resource::<TileHighlightSettings>();
+
+        world
+            .resource_mut::<SelectedTiles>()
+            .select(tilemap, TilePos::new(0, 0));
+        world.resource_mut::<HoveredTile>().0 = Some((tilemap, TilePos::new(0, 0)));
+        schedule.run(&mut world);
+        assert_eq!(color(&world, tiles[0]), settings.hovered_color);
+        assert_eq!(color(&world, tiles[1]), red);
+
+        // Moving the mouse away shows the selection again.
+        world.resource_mut::<HoveredTile>().0 = Some((tilemap, TilePos::new(1, 0)));
+        schedule.run(&mut world);
+        assert_eq!(color(&world, tiles[0]), settings.selected_color);
+        assert_eq!(color(&world, tiles[1]), settings.hovered_color);
+
+        // A color set while highlighted is the one restored.
+        let blue = Color::srgb(0.0, 0.0, 1.0);
+        world.get_mut::<TileColor>(tiles[1]).unwrap().0 = blue;
+        world.resource_mut::<SelectedTiles>().clear();
+        world.resource_mut::<HoveredTile>().0 = None;
+        schedule.run(&mut world);
+        assert_eq!(color(&world, tiles[0]), red);
+        assert_eq!(color(&world, tiles[1]), blue);
+        assert!(world.get::<TileHighlighted>(tiles[0]).is_none());
+    }
+}
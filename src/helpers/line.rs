@@ -0,0 +1,182 @@
+//! Draws lines of tiles between two tile positions, e.g. for walls, roads or simple line of
+//! sight checks.
+
+use bevy::math::IVec2;
+
+use crate::helpers::hex_grid::axial::{AxialPos, FractionalAxialPos};
+use crate::helpers::hex_grid::cube::FractionalCubePos;
+use crate::helpers::hex_grid::offset::{ColEvenPos, ColOddPos, RowEvenPos, RowOddPos};
+use crate::helpers::square_grid::diamond::DiamondPos;
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapSize, TilemapType};
+use crate::tiles::TilePos;
+
+/// A map size which every non-negative position lies within.
+const UNBOUNDED: TilemapSize = TilemapSize {
+    x: u32::MAX,
+    y: u32::MAX,
+};
+
+/// Returns the tiles on the line from `a` to `b`, both included, in order.
+///
+/// Square and isometric maps use Bresenham's algorithm, so consecutive tiles share an edge or a
+/// corner. Hexagonal maps interpolate between the tile centers, so consecutive tiles are always
+/// neighbors and the line has one more tile than the hex distance between `a` and `b`.
+pub fn tile_line(a: TilePos, b: TilePos, map_type: &TilemapType) -> Vec<TilePos> {
+    tile_line_coords(
+        IVec2::new(a.x as i32, a.y as i32),
+        IVec2::new(b.x as i32, b.y as i32),
+        map_type,
+    )
+    .into_iter()
+    .filter_map(|coords| TilePos::from_i32_pair(coords.x, coords.y, &UNBOUNDED))
+    .collect()
+}
+
+/// Like [`tile_line`], for tile coordinates which may lie outside of the tilemap, e.g. the ends
+/// of lines drawn in world space.
+pub(crate) fn tile_line_coords(a: IVec2, b: IVec2, map_type: &TilemapType) -> Vec<IVec2> {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            bresenham((a.x, a.y), (b.x, b.y))
+                .map(|(x, y)| IVec2::new(x, y))
+                .collect()
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            // Staggered positions are not laid out along straight lines, so the line is drawn
+            // between the equivalent diamond positions instead.
+            let a = DiamondPos::from(StaggeredPos { x: a.x, y: a.y });
+            let b = DiamondPos::from(StaggeredPos { x: b.x, y: b.y });
+            bresenham((a.x, a.y), (b.x, b.y))
+                .map(|(x, y)| {
+                    let staggered = StaggeredPos::from(DiamondPos { x, y });
+                    IVec2::new(staggered.x, staggered.y)
+                })
+                .collect()
+        }
+        TilemapType::Hexagon(hex_coord_sys) => hex_line(a, b, *hex_coord_sys),
+    }
+}
+
+/// Iterates over the points of the line from `a` to `b`, in any of the eight octants.
+fn bresenham(a: (i32, i32), b: (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+    let (dx, dy) = ((b.0 - a.0).abs(), -(b.1 - a.1).abs());
+    let (step_x, step_y) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+    let mut error = dx + dy;
+    let mut point = a;
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let current = point;
+        if point == b {
+            done = true;
+            return Some(current);
+        }
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            point.0 += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            point.1 += step_y;
+        }
+        Some(current)
+    })
+}
+
+fn hex_line(a: IVec2, b: IVec2, hex_coord_sys: HexCoordSystem) -> Vec<IVec2> {
+    let a = coords_to_axial(a, hex_coord_sys);
+    let b = coords_to_axial(b, hex_coord_sys);
+    let distance = a.distance_from(&b);
+    if distance == 0 {
+        return vec![axial_to_coords(a, hex_coord_sys)];
+    }
+
+    // The start is nudged off the line, so samples which fall exactly on the border between two
+    // tiles are always rounded to the same side.
+    let start = (a.q as f32 + 1e-6, a.r as f32 + 2e-6);
+    let delta = ((b.q - a.q) as f32, (b.r - a.r) as f32);
+    (0..=distance)
+        .map(|step| {
+            let t = step as f32 / distance as f32;
+            let sample = FractionalAxialPos::new(start.0 + delta.0 * t, start.1 + delta.1 * t);
+            axial_to_coords(
+                AxialPos::from(FractionalCubePos::from(sample).round()),
+                hex_coord_sys,
+            )
+        })
+        .collect()
+}
+
+fn coords_to_axial(coords: IVec2, hex_coord_sys: HexCoordSystem) -> AxialPos {
+    let (q, r) = (coords.x, coords.y);
+    match hex_coord_sys {
+        HexCoordSystem::RowEven => RowEvenPos { q, r }.into(),
+        HexCoordSystem::RowOdd => RowOddPos { q, r }.into(),
+        HexCoordSystem::ColumnEven => ColEvenPos { q, r }.into(),
+        HexCoordSystem::ColumnOdd => ColOddPos { q, r }.into(),
+        HexCoordSystem::Row | HexCoordSystem::Column => AxialPos { q, r },
+    }
+}
+
+fn axial_to_coords(axial: AxialPos, hex_coord_sys: HexCoordSystem) -> IVec2 {
+    let (q, r) = match hex_coord_sys {
+        HexCoordSystem::RowEven => {
+            let pos = RowEvenPos::from(axial);
+            (pos.q, pos.r)
+        }
+        HexCoordSystem::RowOdd => {
+            let pos = RowOddPos::from(axial);
+            (pos.q, pos.r)
+        }
+        HexCoordSystem::ColumnEven => {
+            let pos = ColEvenPos::from(axial);
+            (pos.q, pos.r)
+        }
+        HexCoordSystem::ColumnOdd => {
+            let pos = ColOddPos::from(axial);
+            (pos.q, pos.r)
+        }
+        HexCoordSystem::Row | HexCoordSystem::Column => (axial.q, axial.r),
+    };
+    IVec2::new(q, r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_connected() {
+        let line = tile_line(TilePos::new(0, 0), TilePos::new(5, 2), &TilemapType::Square);
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.first(), Some(&TilePos::new(0, 0)));
+        assert_eq!(line.last(), Some(&TilePos::new(5, 2)));
+        assert_eq!(
+            tile_line(TilePos::new(3, 7), TilePos::new(3, 2), &TilemapType::Square).len(),
+            6
+        );
+
+        let hex_coord_sys = HexCoordSystem::RowOdd;
+        let (a, b) = (TilePos::new(1, 6), TilePos::new(7, 1));
+        let line = tile_line(a, b, &TilemapType::Hexagon(hex_coord_sys));
+        let axial = line
+            .iter()
+            .map(|tile_pos| AxialPos::from_tile_pos_given_coord_system(tile_pos, hex_coord_sys))
+            .collect::<Vec<_>>();
+        assert_eq!(line.first(), Some(&a));
+        assert_eq!(line.last(), Some(&b));
+        assert_eq!(
+            axial.len() as i32,
+            axial[0].distance_from(&axial[axial.len() - 1]) + 1
+        );
+        assert!(
+            axial
+                .windows(2)
+                .all(|pair| pair[0].distance_from(&pair[1]) == 1)
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Helpers for converting a region of tiles into a standalone [`Mesh`].
+//!
+//! This is useful when a part of the map should move independently of the tilemap, for example
+//! to break off a chunk of wall into a falling physics piece:
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::mesh::{MeshTile, tile_region_material, tiles_to_mesh};
+//! # #[allow(clippy::too_many_arguments)]
+//! # fn example(
+//! #     mut commands: Commands,
+//! #     mut meshes: ResMut<Assets<Mesh>>,
+//! #     mut materials: ResMut<Assets<ColorMaterial>>,
+//! #     images: Res<Assets<Image>>,
+//! #     tilemap: Single<(&TilemapSize, &TilemapGridSize, &TilemapTileSize, &TilemapSpacing, &TilemapType, &TilemapAnchor, &TilemapTexture, &GlobalTransform)>,
+//! #     tiles: Query<(&TilePos, &TileTextureIndex, &TileFlip, &TileColor)>,
+//! # ) {
+//! let (map_size, grid_size, tile_size, spacing, map_type, anchor, texture, transform) = *tilemap;
+//! let TilemapTexture::Single(image) = texture else { return };
+//! let Some(image) = images.get(image) else { return };
+//!
+//! let mesh = tiles_to_mesh(
+//!     tiles.iter().map(|(position, texture_index, flip, color)| MeshTile {
+//!         position: *position,
+//!         texture_index: *texture_index,
+//!         flip: *flip,
+//!         color: *color,
+//!     }),
+//!     map_size,
+//!     grid_size,
+//!     tile_size,
+//!     spacing,
+//!     &image.size_f32().into(),
+//!     map_type,
+//!     anchor,
+//! );
+//!
+//! if let Some(material) = tile_region_material(texture) {
+//!     commands.spawn((
+//!         Mesh2d(meshes.add(mesh)),
+//!         MeshMaterial2d(materials.add(material)),
+//!         transform.compute_transform(),
+//!     ));
+//! }
+//! # }
+//! ```
+
+use crate::anchor::TilemapAnchor;
+use crate::map::{
+    TilemapGridSize, TilemapSize, TilemapSpacing, TilemapTextureSize, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{TileColor, TileFlip, TilePos, TileTextureIndex};
+use bevy::asset::RenderAssetUsages;
+use bevy::color::ColorToComponents;
+use bevy::math::Vec2;
+use bevy::mesh::{Indices, Mesh, PrimitiveTopology};
+
+#[cfg(feature = "render")]
+use crate::map::TilemapTexture;
+#[cfg(feature = "render")]
+use bevy::sprite_render::ColorMaterial;
+
+/// The data of a single tile that is baked into a mesh by [`tiles_to_mesh`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeshTile {
+    pub position: TilePos,
+    pub texture_index: TileTextureIndex,
+    pub flip: TileFlip,
+    pub color: TileColor,
+}
+
+/// For every combination of flip bits, whether the bottom-left, top-left, top-right and
+/// bottom-right vertices use the end (rather than the start) of the tile's `u` and `v` range.
+///
+/// This mirrors the tables used by the tilemap vertex shader.
+const FLIPPED_UV_ENDS: [[(bool, bool); 4]; 8] = [
+    [(false, true), (false, false), (true, false), (true, true)],
+    [(true, true), (true, false), (false, false), (false, true)],
+    [(false, false), (false, true), (true, true), (true, false)],
+    [(true, false), (true, true), (false, true), (false, false)],
+    [(true, false), (false, false), (false, true), (true, true)],
+    [(true, true), (false, true), (false, false), (true, false)],
+    [(false, false), (true, false), (true, true), (false, true)],
+    [(false, true), (true, true), (true, false), (false, false)],
+];
+
+/// Builds a [`Mesh`] containing a textured quad for each of the given tiles.
+///
+/// The vertices are placed in the tilemap's local space (taking its `anchor` into account), so the
+/// mesh lines up with the original tiles when it is given the tilemap's transform.
+///
+/// The UVs address a single atlas image of the given `texture_size`, laid out the same way as a
+/// [`TilemapTexture::Single`](crate::map::TilemapTexture::Single) texture. Animated tiles are
+/// baked using their current texture index.
+#[allow(clippy::too_many_arguments)]
+pub fn tiles_to_mesh(
+    tiles: impl IntoIterator<Item = MeshTile>,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    spacing: &TilemapSpacing,
+    texture_size: &TilemapTextureSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    let columns = ((texture_size.x - spacing.x) / (tile_size.x + spacing.x))
+        .round()
+        .max(1.0) as u32;
+    let half_tile = Vec2::from(tile_size) / 2.0;
+
+    for tile in tiles {
+        let center = tile
+            .position
+            .center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+        let bottom_left = center - half_tile;
+        let top_right = center + half_tile;
+
+        let index = tile.texture_index.0;
+        let start = Vec2::new(
+            spacing.x + (index % columns) as f32 * (tile_size.x + spacing.x),
+            spacing.y + (index / columns) as f32 * (tile_size.y + spacing.y),
+        );
+        let end = start + Vec2::from(tile_size);
+        let start = start / Vec2::from(*texture_size);
+        let end = end / Vec2::from(*texture_size);
+
+        let flip_bits =
+            tile.flip.x as usize | ((tile.flip.y as usize) << 1) | ((tile.flip.d as usize) << 2);
+
+        let i = positions.len() as u32;
+        positions.extend([
+            [bottom_left.x, bottom_left.y, 0.0],
+            [bottom_left.x, top_right.y, 0.0],
+            [top_right.x, top_right.y, 0.0],
+            [top_right.x, bottom_left.y, 0.0],
+        ]);
+        uvs.extend(FLIPPED_UV_ENDS[flip_bits].map(|(u_end, v_end)| {
+            [
+                if u_end { end.x } else { start.x },
+                if v_end { end.y } else { start.y },
+            ]
+        }));
+        colors.extend(std::iter::repeat_n(
+            tile.color.0.to_linear().to_f32_array(),
+            4,
+        ));
+        indices.extend_from_slice(&[i, i + 2, i + 1, i, i + 3, i + 2]);
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Creates a [`ColorMaterial`] which can be used to render a mesh built by [`tiles_to_mesh`].
+///
+/// Returns `None` if the texture is not a [`TilemapTexture::Single`] atlas, since the other kinds
+/// of textures can not be sampled by a regular 2d material.
+#[cfg(feature = "render")]
+pub fn tile_region_material(texture: &TilemapTexture) -> Option<ColorMaterial> {
+    match texture {
+        TilemapTexture::Single(handle) => Some(ColorMaterial {
+            texture: Some(handle.clone()),
+            ..Default::default()
+        }),
+        #[cfg(not(feature = "atlas"))]
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::mesh::VertexAttributeValues;
+
+    #[test]
+    fn tiles_become_quads_over_their_atlas_texture() {
+        let tiles = [
+            MeshTile {
+                position: TilePos::new(0, 0),
+                texture_index: TileTextureIndex(5),
+                ..Default::default()
+            },
+            MeshTile {
+                position: TilePos::new(2, 1),
+                texture_index: TileTextureIndex(5),
+                flip: TileFlip {
+                    x: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        let mesh = tiles_to_mesh(
+            tiles,
+            &TilemapSize::new(4, 4),
+            &TilemapGridSize::new(16.0, 16.0),
+            &TilemapTileSize::new(16.0, 16.0),
+            &TilemapSpacing::zero(),
+            &TilemapTextureSize::new(64.0, 32.0),
+            &TilemapType::Square,
+            &TilemapAnchor::None,
+        );
+
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("the mesh has no positions");
+        };
+        assert_eq!(positions.len(), 8);
+        assert_eq!(positions[0], [-8.0, -8.0, 0.0]);
+        assert_eq!(positions[6], [40.0, 24.0, 0.0]);
+
+        // Texture 5 is the second tile of the second row of a 4 by 2 atlas, and flipping a tile
+        // swaps the ends of its `u` range.
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("the mesh has no UVs");
+        };
+        assert_eq!(uvs[..4], [[0.25, 1.0], [0.25, 0.5], [0.5, 0.5], [0.5, 1.0]]);
+        assert_eq!(uvs[4..], [[0.5, 1.0], [0.5, 0.5], [0.25, 0.5], [0.25, 1.0]]);
+        assert_eq!(mesh.indices().unwrap().len(), 12);
+    }
+}
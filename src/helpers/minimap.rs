@@ -0,0 +1,499 @@
+//! Keeps a low-resolution image of a tilemap, with a pixel per tile, to show as a minimap.
+//!
+//! Add the [`TilemapMinimapPlugin`] and a [`TilemapMinimap`] to the tilemap, then show its image
+//! in the UI or on a sprite. Each pixel takes the color of its tile's texture, tinted by the
+//! [`TileColor`] of the tile. Only the pixels of tiles which changed are written, so editing a few
+//! tiles does not redraw the whole minimap. A [`MinimapFog`] on the tilemap shades the minimap by
+//! the fog of war of the tilemap.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::minimap::TilemapMinimap;
+//! fn add_minimap(
+//!     mut commands: Commands,
+//!     mut images: ResMut<Assets<Image>>,
+//!     tilemaps: Query<(Entity, &TilemapSize), Added<TileStorage>>,
+//! ) {
+//!     for (tilemap_entity, map_size) in tilemaps.iter() {
+//!         let image = images.add(TilemapMinimap::new_image(map_size));
+//!         commands.spawn((
+//!             Sprite::from_image(image.clone()),
+//!             Transform::from_xyz(400.0, 200.0, 10.0).with_scale(Vec3::splat(4.0)),
+//!         ));
+//!         commands
+//!             .entity(tilemap_entity)
+//!             .insert(TilemapMinimap::new(image));
+//!     }
+//! }
+//! ```
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::map::{TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTileSize};
+use crate::tiles::{TileColor, TilePos, TileStorage, TileTextureIndex, TileVisible};
+
+/// Adds the system which draws the images of [`TilemapMinimap`]s and their [`MinimapFog`].
+pub struct TilemapMinimapPlugin;
+
+impl Plugin for TilemapMinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, sync_tilemap_minimaps);
+    }
+}
+
+/// Draws a pixel of `image` for each tile of the tilemap.
+///
+/// The color of a texture is taken from [`texture_colors`](Self::texture_colors). Textures
+/// missing there get the average color of their opaque pixels in a
+/// [`TilemapTexture::Single`] atlas (or of their image, for a `TilemapTexture::Vector`) once it
+/// is loaded, or white otherwise.
+///
+/// Tile `(0, 0)` is the bottom-left pixel of the image, which should be at least as large as the
+/// tilemap and use an uncompressed format. [`TilemapMinimap::new_image`] makes such an image.
+/// Changing the [`TileStorage`] of the tilemap, e.g. by spawning or despawning tiles, redraws the
+/// whole image.
+#[derive(Component, Clone, Debug)]
+pub struct TilemapMinimap {
+    pub image: Handle<Image>,
+    /// The colors of textures by texture index, filled with the colors read from the tilemap
+    /// texture. Textures set by the game are kept as they are.
+    pub texture_colors: HashMap<u32, Color>,
+    /// The color of positions without a tile, or with a hidden one.
+    pub empty_color: Color,
+    /// Set when a texture color could not be read yet, to redraw once the texture is loaded.
+    missing_texture_colors: bool,
+}
+
+impl TilemapMinimap {
+    pub fn new(image: Handle<Image>) -> Self {
+        Self {
+            image,
+            texture_colors: HashMap::default(),
+            empty_color: Color::NONE,
+            missing_texture_colors: false,
+        }
+    }
+
+    /// Returns a transparent image with a pixel per tile, to draw the minimap of a tilemap of
+    /// `map_size` into.
+    pub fn new_image(map_size: &TilemapSize) -> Image {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: map_size.x.max(1),
+                height: map_size.y.max(1),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        // A pixel per tile would be blurred when the minimap is scaled up.
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+
+    /// Returns the color of a texture, reading it from the tilemap texture the first time.
+    fn texture_color(
+        &mut self,
+        texture_index: u32,
+        texture: Option<(&TilemapTexture, &TilemapTileSize, &TilemapSpacing)>,
+        images: &Assets<Image>,
+    ) -> Color {
+        if let Some(color) = self.texture_colors.get(&texture_index) {
+            return *color;
+        }
+        let color = match texture {
+            Some((TilemapTexture::Single(handle), tile_size, spacing)) => {
+                images.get(handle).map(|atlas| {
+                    let columns = ((atlas.width() as f32 - spacing.x) / (tile_size.x + spacing.x))
+                        .round()
+                        .max(1.0) as u32;
+                    let start = Vec2::new(
+                        spacing.x + (texture_index % columns) as f32 * (tile_size.x + spacing.x),
+                        spacing.y + (texture_index / columns) as f32 * (tile_size.y + spacing.y),
+                    );
+                    average_color(atlas, start.as_uvec2(), Vec2::from(tile_size).as_uvec2())
+                })
+            }
+            #[cfg(not(feature = "atlas"))]
+            Some((TilemapTexture::Vector(handles), ..)) => handles
+                .get(texture_index as usize)
+                .and_then(|handle| images.get(handle))
+                .map(|image| average_color(image, UVec2::ZERO, image.size())),
+            _ => Some(Color::WHITE),
+        };
+        match color {
+            Some(color) => {
+                self.texture_colors.insert(texture_index, color);
+                color
+            }
+            None => {
+                self.missing_texture_colors = true;
+                Color::WHITE
+            }
+        }
+    }
+
+    /// Returns the color of a tile, shaded by the fog if there is one. `fog` holds the index of
+    /// the tile.
+    fn tile_color(
+        &mut self,
+        tile: Option<(&TileTextureIndex, &TileColor, &TileVisible)>,
+        texture: Option<(&TilemapTexture, &TilemapTileSize, &TilemapSpacing)>,
+        images: &Assets<Image>,
+        fog: Option<(&mut MinimapFog, usize)>,
+    ) -> Color {
+        let visible = tile.is_some_and(|(.., visible)| visible.0);
+        let mut fog_tint = None;
+        if let Some((fog, index)) = fog {
+            if !fog.explore(index, visible) {
+                return fog.hidden_color;
+            }
+            // Tiles which were explored are still drawn once they are hidden again.
+            if !visible {
+                fog_tint = Some(fog.explored_color);
+            }
+        }
+        match tile {
+            Some((texture_index, color, _)) if visible || fog_tint.is_some() => {
+                let color = tint(
+                    self.texture_color(texture_index.0, texture, images),
+                    color.0,
+                );
+                fog_tint.map_or(color, |fog_tint| tint(color, fog_tint))
+            }
+            _ => self.empty_color,
+        }
+    }
+}
+
+/// Shades the [`TilemapMinimap`] of a tilemap by its fog of war, which is the [`TileVisible`] of
+/// its tiles.
+///
+/// Visible tiles are drawn as they are, tiles which were visible once but are hidden again are
+/// tinted by the [`explored_color`](Self::explored_color), and positions which were never visible
+/// take the [`hidden_color`](Self::hidden_color). It must be added to a tilemap with a
+/// `TilemapMinimap`.
+#[derive(Component, Clone, Debug)]
+pub struct MinimapFog {
+    /// The tint of tiles which were visible once, but are hidden again.
+    pub explored_color: Color,
+    /// The color of positions which were never visible.
+    pub hidden_color: Color,
+    explored: Vec<bool>,
+}
+
+impl Default for MinimapFog {
+    fn default() -> Self {
+        Self {
+            explored_color: Color::srgb(0.4, 0.4, 0.4),
+            hidden_color: Color::BLACK,
+            explored: Vec::new(),
+        }
+    }
+}
+
+impl MinimapFog {
+    /// Returns `true` if the tile at `tile_pos` was visible at some point since the fog was
+    /// added.
+    pub fn is_explored(&self, tile_storage: &TileStorage, tile_pos: &TilePos) -> bool {
+        tile_pos.within_map_bounds(&tile_storage.size)
+            && self
+                .explored
+                .get(tile_pos.to_index(&tile_storage.size))
+                .is_some_and(|explored| *explored)
+    }
+
+    /// Records the visibility of a tile, and returns whether it was ever visible.
+    fn explore(&mut self, index: usize, visible: bool) -> bool {
+        self.explored[index] |= visible;
+        self.explored[index]
+    }
+}
+
+/// Multiplies `color` by `tint`, the way a [`TileColor`] tints a tile.
+fn tint(color: Color, tint: Color) -> Color {
+    LinearRgba::from_vec4(color.to_linear().to_vec4() * tint.to_linear().to_vec4()).into()
+}
+
+/// Averages the pixels of the `size` pixels large area of `image` starting at `start`, weighted
+/// by their alpha. Areas without any opaque pixel are transparent.
+fn average_color(image: &Image, start: UVec2, size: UVec2) -> Color {
+    let mut sum = Vec3::ZERO;
+    let mut weight = 0.0;
+    for y in start.y..start.y + size.y {
+        for x in start.x..start.x + size.x {
+            if let Ok(color) = image.get_color_at(x, y) {
+                let color = color.to_linear();
+                sum += Vec3::new(color.red, color.green, color.blue) * color.alpha;
+                weight += color.alpha;
+            }
+        }
+    }
+    if weight == 0.0 {
+        return Color::NONE;
+    }
+    let average = sum / weight;
+    Color::linear_rgb(average.x, average.y, average.z)
+}
+
+fn write_pixel(image: &mut Image, tile_storage: &TileStorage, tile_pos: &TilePos, color: Color) {
+    let y = tile_storage.size.y - 1 - tile_pos.y;
+    // Pixels outside of a smaller image are left out.
+    let _ = image.set_color_at(tile_pos.x, y, color);
+}
+
+#[allow(clippy::type_complexity)]
+fn sync_tilemap_minimaps(
+    mut tilemaps: Query<(
+        Entity,
+        &mut TilemapMinimap,
+        Ref<TileStorage>,
+        Option<(&TilemapTexture, &TilemapTileSize, &TilemapSpacing)>,
+        Option<&mut MinimapFog>,
+    )>,
+    changed_tiles: Query<
+        (
+            &TilemapId,
+            &TilePos,
+            &TileTextureIndex,
+            &TileColor,
+            &TileVisible,
+        ),
+        Or<(
+            Changed<TileTextureIndex>,
+            Changed<TileColor>,
+            Changed<TileVisible>,
+        )>,
+    >,
+    tiles: Query<(&TileTextureIndex, &TileColor, &TileVisible)>,
+    mut removed_fogs: RemovedComponents<MinimapFog>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let removed_fogs = removed_fogs.read().collect::<HashSet<_>>();
+    for (tilemap_entity, mut minimap, tile_storage, texture, mut fog) in tilemaps.iter_mut() {
+        let map_size = tile_storage.size;
+        let resync = minimap.is_changed()
+            || tile_storage.is_changed()
+            || minimap.missing_texture_colors
+            || removed_fogs.contains(&tilemap_entity)
+            || fog
+                .as_ref()
+                .is_some_and(|fog| fog.is_changed() || fog.explored.len() != map_size.count());
+        let minimap = minimap.bypass_change_detection();
+        let mut fog = fog.as_mut().map(|fog| fog.bypass_change_detection());
+
+        // The colors are read before the image is borrowed mutably, as they may come from another
+        // image.
+        let colors = if resync {
+            if !images.contains(&minimap.image) {
+                continue;
+            }
+            minimap.missing_texture_colors = false;
+            if let Some(fog) = &mut fog {
+                fog.explored.resize(map_size.count(), false);
+            }
+            (0..map_size.y)
+                .flat_map(|y| (0..map_size.x).map(move |x| TilePos::new(x, y)))
+                .map(|tile_pos| {
+                    let tile = tile_storage
+                        .get(&tile_pos)
+                        .and_then(|tile_entity| tiles.get(tile_entity).ok());
+                    let fog = fog
+                        .as_deref_mut()
+                        .map(|fog| (fog, tile_pos.to_index(&map_size)));
+                    (tile_pos, minimap.tile_color(tile, texture, &images, fog))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            changed_tiles
+                .iter()
+                .filter(|(tilemap_id, tile_pos, ..)| {
+                    tilemap_id.0 == tilemap_entity && tile_pos.within_map_bounds(&map_size)
+                })
+                .map(|(_, tile_pos, texture_index, color, visible)| {
+                    let tile = Some((texture_index, color, visible));
+                    let fog = fog
+                        .as_deref_mut()
+                        .map(|fog| (fog, tile_pos.to_index(&map_size)));
+                    (*tile_pos, minimap.tile_color(tile, texture, &images, fog))
+                })
+                .collect::<Vec<_>>()
+        };
+        // The image is only borrowed mutably when there is something to write, as that uploads
+        // it again.
+        if colors.is_empty() {
+            continue;
+        }
+        let Some(image) = images.get_mut(&minimap.image) else {
+            continue;
+        };
+        for (tile_pos, color) in colors {
+            write_pixel(image, &tile_storage, &tile_pos, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileBundle;
+
+    fn minimap_schedule(world: &mut World) -> Schedule {
+        world.init_resource::<Assets<Image>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_tilemap_minimaps);
+        schedule
+    }
+
+    /// Spawns a tile at each of `positions` of `tilemap`, and returns its tile storage.
+    fn spawn_tiles(
+        world: &mut World,
+        tilemap: Entity,
+        map_size: TilemapSize,
+        positions: impl IntoIterator<Item = TilePos>,
+        tile: impl Fn(TilePos) -> TileBundle,
+    ) -> TileStorage {
+        let mut tile_storage = TileStorage::empty(map_size);
+        for tile_pos in positions {
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    ..tile(tile_pos)
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        tile_storage
+    }
+
+    #[track_caller]
+    fn assert_pixel(world: &World, image: &Handle<Image>, x: u32, y: u32, expected: Color) {
+        let images = world.resource::<Assets<Image>>();
+        let pixel = images.get(image).unwrap().get_color_at(x, y).unwrap();
+        let pixel = pixel.to_srgba().to_u8_array();
+        let expected = expected.to_srgba().to_u8_array();
+        // Colors go through sRGB encoding, which may round them by a step.
+        assert!(
+            pixel.iter().zip(expected).all(|(a, b)| a.abs_diff(b) <= 1),
+            "{pixel:?} != {expected:?} at ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn tile_changes_are_drawn_on_the_minimap() {
+        let mut world = World::new();
+        let mut schedule = minimap_schedule(&mut world);
+
+        // An atlas of two 2x2 tiles, a red one and a blue one.
+        let mut atlas = Image::new_fill(
+            Extent3d {
+                width: 4,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[255, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        for (x, y) in [(2, 0), (3, 0), (2, 1), (3, 1)] {
+            atlas
+                .set_color_at(x, y, Color::srgb(0.0, 0.0, 1.0))
+                .unwrap();
+        }
+        let mut images = world.resource_mut::<Assets<Image>>();
+        let atlas = images.add(atlas);
+        let map_size = TilemapSize::new(2, 2);
+        let image = images.add(TilemapMinimap::new_image(&map_size));
+
+        let tilemap = world.spawn_empty().id();
+        let positions = [TilePos::new(0, 0), TilePos::new(1, 0), TilePos::new(0, 1)];
+        let tile_storage = spawn_tiles(&mut world, tilemap, map_size, positions, |tile_pos| {
+            TileBundle {
+                texture_index: TileTextureIndex(tile_pos.x),
+                ..Default::default()
+            }
+        });
+        let recolored = tile_storage.get(&TilePos::new(0, 1)).unwrap();
+        let mut minimap = TilemapMinimap::new(image.clone());
+        minimap.texture_colors.insert(2, Color::srgb(0.0, 1.0, 0.0));
+        world.entity_mut(tilemap).insert((
+            tile_storage,
+            TilemapTexture::Single(atlas),
+            TilemapTileSize::new(2.0, 2.0),
+            TilemapSpacing::zero(),
+            minimap,
+        ));
+
+        schedule.run(&mut world);
+        // Tile (0, 0) is the bottom-left pixel.
+        assert_pixel(&world, &image, 0, 1, Color::srgb(1.0, 0.0, 0.0));
+        assert_pixel(&world, &image, 1, 1, Color::srgb(0.0, 0.0, 1.0));
+        assert_pixel(&world, &image, 1, 0, Color::NONE);
+
+        let mut tile = world.entity_mut(recolored);
+        tile.get_mut::<TileColor>().unwrap().0 = Color::srgb(0.0, 0.0, 0.0);
+        tile.get_mut::<TileTextureIndex>().unwrap().0 = 2;
+        schedule.run(&mut world);
+        assert_pixel(&world, &image, 0, 0, Color::BLACK);
+
+        world.get_mut::<TileColor>(recolored).unwrap().0 = Color::WHITE;
+        schedule.run(&mut world);
+        assert_pixel(&world, &image, 0, 0, Color::srgb(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn fog_shades_the_minimap() {
+        let mut world = World::new();
+        let mut schedule = minimap_schedule(&mut world);
+        let map_size = TilemapSize::new(4, 4);
+        let image = world
+            .resource_mut::<Assets<Image>>()
+            .add(TilemapMinimap::new_image(&map_size));
+        let tilemap = world.spawn_empty().id();
+        let positions = (0..map_size.count() as u32)
+            .map(|index| TilePos::new(index % map_size.x, index / map_size.x))
+            // One position is left empty.
+            .filter(|tile_pos| *tile_pos != TilePos::new(3, 3));
+        let tile_storage = spawn_tiles(&mut world, tilemap, map_size, positions, |tile_pos| {
+            TileBundle {
+                visible: TileVisible(tile_pos.x == 0),
+                ..Default::default()
+            }
+        });
+        let revealed = tile_storage.get(&TilePos::new(3, 0)).unwrap();
+        let hidden = tile_storage.get(&TilePos::new(0, 3)).unwrap();
+        world.entity_mut(tilemap).insert((
+            tile_storage,
+            TilemapMinimap::new(image.clone()),
+            MinimapFog::default(),
+        ));
+        let explored = Color::srgb(0.4, 0.4, 0.4);
+
+        schedule.run(&mut world);
+        assert_pixel(&world, &image, 0, 3, Color::WHITE);
+        assert_pixel(&world, &image, 3, 3, Color::BLACK);
+        assert_pixel(&world, &image, 3, 0, Color::BLACK);
+
+        world.get_mut::<TileVisible>(revealed).unwrap().0 = true;
+        world.get_mut::<TileVisible>(hidden).unwrap().0 = false;
+        schedule.run(&mut world);
+        assert_pixel(&world, &image, 3, 3, Color::WHITE);
+        assert_pixel(&world, &image, 0, 0, explored);
+        assert_pixel(&world, &image, 1, 0, Color::BLACK);
+
+        // Without the fog, hidden tiles are left out again.
+        world.entity_mut(tilemap).remove::<MinimapFog>();
+        schedule.run(&mut world);
+        assert_pixel(&world, &image, 0, 0, Color::NONE);
+        assert_pixel(&world, &image, 3, 3, Color::WHITE);
+    }
+}
@@ -1,7 +1,94 @@
+//! Optional helpers built on top of tilemaps.
+//!
+//! Most helpers are plain functions and components. The ones driven by systems come with a plugin
+//! each, and [`TilemapHelperPlugins`] adds all of those at once.
+
+pub mod animation_control;
+pub mod ascii;
+pub mod autotile;
+pub mod chunk_snapshot;
+#[cfg(feature = "render")]
+pub mod chunked;
+pub mod composite;
+pub mod cone;
+pub mod cursor;
+pub mod distance;
+pub mod emitter;
 pub mod filling;
+pub mod fixed_motion;
 pub mod geometry;
 pub mod hex_grid;
+pub mod highlight;
+pub mod line;
+pub mod mesh;
+pub mod minimap;
+pub mod nav;
+pub mod orientation;
+pub mod palette;
+pub mod pathfinding;
+pub mod placement;
 pub mod projection;
+pub mod rasterize;
+pub mod raycast;
+pub mod region;
+pub mod roof_reveal;
 pub mod selection;
 pub mod square_grid;
+pub mod stamp;
+pub mod streaming;
+pub mod terrain_brush;
 pub mod transform;
+
+use bevy::app::{PluginGroup, PluginGroupBuilder};
+
+/// The plugins of the helpers which are driven by systems.
+///
+/// None of them do any work until their components are added to tilemaps or tiles, so the group
+/// can be added as a whole, with unwanted plugins disabled:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// use bevy_ecs_tilemap::helpers::{TilemapHelperPlugins, minimap::TilemapMinimapPlugin};
+///
+/// fn build(app: &mut App) {
+///     app.add_plugins((
+///         TilemapPlugin,
+///         TilemapHelperPlugins.build().disable::<TilemapMinimapPlugin>(),
+///     ));
+/// }
+/// ```
+///
+/// The group holds:
+/// - [`TilemapAnimationControlPlugin`](animation_control::TilemapAnimationControlPlugin)
+/// - [`AutotilePlugin`](autotile::AutotilePlugin)
+/// - [`TileCursorPlugin`](cursor::TileCursorPlugin)
+/// - [`TileEmitterPlugin`](emitter::TileEmitterPlugin)
+/// - [`FixedTileMotionPlugin`](fixed_motion::FixedTileMotionPlugin)
+/// - [`TileHighlightPlugin`](highlight::TileHighlightPlugin)
+/// - [`TilemapMinimapPlugin`](minimap::TilemapMinimapPlugin)
+/// - [`NavGridPlugin`](nav::NavGridPlugin)
+/// - [`RoofRevealPlugin`](roof_reveal::RoofRevealPlugin)
+/// - [`ChunkStreamingPlugin`](chunked::ChunkStreamingPlugin), with the `render` feature
+/// - [`TilemapDebugPlugin`](crate::debug::TilemapDebugPlugin), with the `debug` feature
+pub struct TilemapHelperPlugins;
+
+impl PluginGroup for TilemapHelperPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(animation_control::TilemapAnimationControlPlugin)
+            .add(autotile::AutotilePlugin)
+            .add(cursor::TileCursorPlugin)
+            .add(emitter::TileEmitterPlugin)
+            .add(fixed_motion::FixedTileMotionPlugin)
+            .add(highlight::TileHighlightPlugin)
+            .add(minimap::TilemapMinimapPlugin)
+            .add(nav::NavGridPlugin)
+            .add(roof_reveal::RoofRevealPlugin);
+        #[cfg(feature = "render")]
+        let group = group.add(chunked::ChunkStreamingPlugin);
+        #[cfg(feature = "debug")]
+        let group = group.add(crate::debug::TilemapDebugPlugin);
+        group
+    }
+}
@@ -0,0 +1,260 @@
+//! Grids of the walkable tiles of tilemaps, for pathfinding and AI code which should not query
+//! every tile entity each frame.
+//!
+//! [`walkability_grid`] builds a grid once. To keep one up to date, add the [`NavGridPlugin`] and
+//! a [`TilemapNavGrid`] to a tilemap: only the tiles which were moved, changed their texture or
+//! were despawned are looked at again, and a [`NavGridChanged`] message lists the tiles whose
+//! walkability changed, so pathfinding caches can be updated incrementally.
+//!
+//! The grid is a [`TileDataLayer`], whose [`values`](TileDataLayer::values) are laid out row by
+//! row like most pathfinding crates expect.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::nav::*;
+//! # use std::sync::Arc;
+//! const WALL: TileTextureIndex = TileTextureIndex(3);
+//!
+//! fn track_walls(mut commands: Commands, tilemap: Entity) {
+//!     commands
+//!         .entity(tilemap)
+//!         .insert(TilemapNavGrid::new(Arc::new(|_, texture_index| {
+//!             texture_index.is_some_and(|texture_index| *texture_index != WALL)
+//!         })));
+//! }
+//!
+//! fn update_paths(mut changes: MessageReader<NavGridChanged>) {
+//!     for change in changes.read() {
+//!         info!("{} tiles changed their walkability", change.tiles.len());
+//!     }
+//! }
+//! ```
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::map::TilemapId;
+use crate::tiles::{TileDataLayer, TilePos, TileStorage, TileTextureIndex};
+
+/// Adds the system which keeps [`TilemapNavGrid`]s up to date.
+pub struct NavGridPlugin;
+
+impl Plugin for NavGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<NavGridChanged>()
+            .add_systems(PostUpdate, update_nav_grids);
+    }
+}
+
+/// Decides whether a tile is walkable, from its position and texture. The texture is `None` for
+/// positions without a tile.
+pub type WalkablePredicate = Arc<dyn Fn(&TilePos, Option<&TileTextureIndex>) -> bool + Send + Sync>;
+
+/// Returns a grid of the size of `tile_storage` telling which of its positions are walkable.
+pub fn walkability_grid(
+    tile_storage: &TileStorage,
+    tiles: &Query<&TileTextureIndex>,
+    walkable: impl Fn(&TilePos, Option<&TileTextureIndex>) -> bool,
+) -> TileDataLayer<bool> {
+    TileDataLayer::from_fn(tile_storage.size, |tile_pos| {
+        let texture_index = tile_storage
+            .get(&tile_pos)
+            .and_then(|tile_entity| tiles.get(tile_entity).ok());
+        walkable(&tile_pos, texture_index)
+    })
+}
+
+/// Keeps a grid of the walkable tiles of a tilemap.
+///
+/// It must be added as a component to the tilemap entity. Replacing the predicate rebuilds the
+/// whole grid.
+#[derive(Component, Clone)]
+pub struct TilemapNavGrid {
+    pub walkable: WalkablePredicate,
+    grid: TileDataLayer<bool>,
+    /// The positions of the tiles, to find where despawned tiles were.
+    positions: HashMap<Entity, TilePos>,
+}
+
+impl TilemapNavGrid {
+    pub fn new(walkable: WalkablePredicate) -> Self {
+        Self {
+            walkable,
+            grid: TileDataLayer::default(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Returns the grid, which is empty until the tilemap was first updated.
+    pub fn grid(&self) -> &TileDataLayer<bool> {
+        &self.grid
+    }
+
+    /// Returns whether the tile at `tile_pos` is walkable. Positions off the map are not.
+    pub fn is_walkable(&self, tile_pos: &TilePos) -> bool {
+        self.grid.checked_get(tile_pos).copied().unwrap_or(false)
+    }
+}
+
+/// Sent when tiles of a [`TilemapNavGrid`] changed their walkability.
+#[derive(Message, Clone, Debug)]
+pub struct NavGridChanged {
+    pub tilemap: Entity,
+    /// The tiles whose walkability changed. Empty if the whole grid was `rebuilt`.
+    pub tiles: Vec<TilePos>,
+    /// Whether the whole grid was rebuilt, because the nav grid was added or its predicate or the
+    /// size of the tilemap changed.
+    pub rebuilt: bool,
+}
+
+#[allow(clippy::type_complexity)]
+fn update_nav_grids(
+    mut tilemaps: Query<(Entity, &mut TilemapNavGrid, &TileStorage)>,
+    changed_tiles: Query<
+        (Entity, &TilemapId, &TilePos),
+        Or<(Changed<TilePos>, Changed<TileTextureIndex>)>,
+    >,
+    tiles: Query<&TileTextureIndex>,
+    mut removed: RemovedComponents<TilePos>,
+    mut changes: MessageWriter<NavGridChanged>,
+) {
+    if tilemaps.is_empty() {
+        return;
+    }
+    let removed = removed.read().collect::<Vec<_>>();
+    let mut updates: HashMap<Entity, Vec<(Entity, TilePos)>> = HashMap::new();
+    for (tile_entity, tilemap_id, tile_pos) in changed_tiles.iter() {
+        updates
+            .entry(tilemap_id.0)
+            .or_default()
+            .push((tile_entity, *tile_pos));
+    }
+
+    for (tilemap_entity, mut nav_grid, tile_storage) in tilemaps.iter_mut() {
+        let rebuild = nav_grid.is_changed() || nav_grid.grid.size != tile_storage.size;
+        let updated = updates.remove(&tilemap_entity).unwrap_or_default();
+        if !rebuild && updated.is_empty() && removed.is_empty() {
+            continue;
+        }
+        let nav_grid = nav_grid.bypass_change_detection();
+
+        if rebuild {
+            nav_grid.grid = walkability_grid(tile_storage, &tiles, &*nav_grid.walkable);
+            nav_grid.positions = tile_storage
+                .iter_some()
+                .map(|(tile_pos, tile_entity)| (tile_entity, tile_pos))
+                .collect();
+            changes.write(NavGridChanged {
+                tilemap: tilemap_entity,
+                tiles: Vec::new(),
+                rebuilt: true,
+            });
+            continue;
+        }
+
+        // Both the old and the new position of a moved tile may have changed.
+        let mut dirty = HashSet::new();
+        for tile_entity in &removed {
+            dirty.extend(nav_grid.positions.remove(tile_entity));
+        }
+        for (tile_entity, tile_pos) in updated {
+            dirty.extend(nav_grid.positions.insert(tile_entity, tile_pos));
+            dirty.insert(tile_pos);
+        }
+
+        let mut changed = Vec::new();
+        for tile_pos in dirty {
+            let Some(was_walkable) = nav_grid.grid.checked_get(&tile_pos).copied() else {
+                continue;
+            };
+            let texture_index = tile_storage
+                .get(&tile_pos)
+                .and_then(|tile_entity| tiles.get(tile_entity).ok());
+            let walkable = (nav_grid.walkable)(&tile_pos, texture_index);
+            if walkable != was_walkable {
+                nav_grid.grid.set(&tile_pos, walkable);
+                changed.push(tile_pos);
+            }
+        }
+        if !changed.is_empty() {
+            changes.write(NavGridChanged {
+                tilemap: tilemap_entity,
+                tiles: changed,
+                rebuilt: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TilemapSize;
+    use crate::tiles::TileBundle;
+
+    #[test]
+    fn walls_update_the_grid() {
+        let mut world = World::new();
+        world.init_resource::<Messages<NavGridChanged>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_nav_grids);
+
+        let map_size = TilemapSize::new(3, 1);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        let tiles = [0, 1, 2].map(|x| {
+            let tile_pos = TilePos::new(x, 0);
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    texture_index: TileTextureIndex(u32::from(x == 1)),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+            tile_entity
+        });
+        world.entity_mut(tilemap).insert((
+            tile_storage,
+            TilemapNavGrid::new(Arc::new(|_, texture_index| {
+                texture_index.is_some_and(|texture_index| texture_index.0 == 0)
+            })),
+        ));
+        let walkable = |world: &World| {
+            world
+                .get::<TilemapNavGrid>(tilemap)
+                .unwrap()
+                .grid()
+                .values()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let changed_tiles = |world: &mut World| {
+            world
+                .resource_mut::<Messages<NavGridChanged>>()
+                .drain()
+                .flat_map(|change| change.tiles)
+                .collect::<Vec<_>>()
+        };
+
+        schedule.run(&mut world);
+        assert_eq!(walkable(&world), vec![true, false, true]);
+        assert!(changed_tiles(&mut world).is_empty());
+
+        // Building a wall, and despawning a tile, which leaves a hole.
+        world.get_mut::<TileTextureIndex>(tiles[0]).unwrap().0 = 1;
+        world
+            .get_mut::<TileStorage>(tilemap)
+            .unwrap()
+            .remove(&TilePos::new(2, 0));
+        world.despawn(tiles[2]);
+        schedule.run(&mut world);
+        assert_eq!(walkable(&world), vec![false, false, false]);
+        let mut changed = changed_tiles(&mut world);
+        changed.sort();
+        assert_eq!(changed, vec![TilePos::new(0, 0), TilePos::new(2, 0)]);
+    }
+}
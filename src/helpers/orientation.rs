@@ -0,0 +1,184 @@
+//! Gives tiles random but repeatable flips and rotations, so large areas of the same floor or
+//! ceiling tile do not look tiled.
+
+use bevy::prelude::{DetectChangesMut, Query};
+
+use crate::tiles::{TileFlip, TilePos, TileStorage, TileTextureIndex, scatter_hash};
+
+/// A set of the eight orientations a [`TileFlip`] can give a tile.
+///
+/// Rotations are flips too: a quarter turn is a diagonal flip followed by a flip along one axis.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct OrientationSet(u8);
+
+impl OrientationSet {
+    /// No orientations at all.
+    pub const NONE: Self = Self(0);
+    /// Only the unchanged orientation.
+    pub const IDENTITY: Self = Self(1);
+    /// Flipping along the X and Y axes, which includes the half turn.
+    pub const FLIPS: Self = Self(0b0000_1111);
+    /// The four quarter turns.
+    pub const ROTATIONS: Self = Self(0b0110_1001);
+    /// Every flip and rotation. Only suited to tiles which look right any way around.
+    pub const ALL: Self = Self(0b1111_1111);
+
+    fn bit(flip: &TileFlip) -> u8 {
+        1 << (flip.x as u8 | (flip.y as u8) << 1 | (flip.d as u8) << 2)
+    }
+
+    /// Returns the set containing only `flip`.
+    pub fn only(flip: TileFlip) -> Self {
+        Self(Self::bit(&flip))
+    }
+
+    /// Returns this set with `flip` added.
+    pub fn with(self, flip: TileFlip) -> Self {
+        Self(self.0 | Self::bit(&flip))
+    }
+
+    pub fn contains(&self, flip: &TileFlip) -> bool {
+        self.0 & Self::bit(flip) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the orientations in the set.
+    pub fn iter(&self) -> impl Iterator<Item = TileFlip> + '_ {
+        (0..8u8)
+            .filter(|index| self.0 & (1 << index) != 0)
+            .map(|index| TileFlip {
+                x: index & 1 != 0,
+                y: index & 2 != 0,
+                d: index & 4 != 0,
+            })
+    }
+
+    /// Picks one of the orientations in the set for the tile at `tile_pos`, which is the same
+    /// every time it is picked for the same position and `seed`.
+    ///
+    /// Returns `None` if the set is empty.
+    pub fn pick(&self, tile_pos: &TilePos, seed: u32) -> Option<TileFlip> {
+        if self.is_empty() {
+            return None;
+        }
+        let index = scatter_hash(tile_pos, seed) as usize % self.len();
+        self.iter().nth(index)
+    }
+}
+
+/// Gives every tile for which `predicate` returns `true` an orientation picked from `allowed`
+/// with [`OrientationSet::pick`].
+///
+/// The same `seed` always gives a tile at the same position the same orientation, so the result
+/// survives saving and reloading the map without storing the flips. Only tiles whose flip
+/// actually changes are marked as changed.
+pub fn randomize_orientation<F>(
+    tile_storage: &TileStorage,
+    tiles: &mut Query<(&TileTextureIndex, &mut TileFlip)>,
+    mut predicate: F,
+    allowed: OrientationSet,
+    seed: u32,
+) where
+    F: FnMut(&TilePos, &TileTextureIndex) -> bool,
+{
+    for (tile_pos, tile_entity) in tile_storage.iter_some() {
+        let Ok((texture_index, mut flip)) = tiles.get_mut(tile_entity) else {
+            continue;
+        };
+        if !predicate(&tile_pos, texture_index) {
+            continue;
+        }
+        if let Some(new_flip) = allowed.pick(&tile_pos, seed) {
+            flip.set_if_neq(new_flip);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapId, TilemapSize};
+    use crate::tiles::TileBundle;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::*;
+
+    #[test]
+    fn orientations_are_repeatable_and_allowed() {
+        assert_eq!(OrientationSet::ROTATIONS.len(), 4);
+        assert!(OrientationSet::ROTATIONS.contains(&TileFlip {
+            x: true,
+            y: false,
+            d: true,
+        }));
+        assert!(!OrientationSet::ROTATIONS.contains(&TileFlip {
+            x: true,
+            y: false,
+            d: false,
+        }));
+        assert_eq!(OrientationSet::NONE.pick(&TilePos::new(1, 1), 0), None);
+
+        let mut world = World::new();
+        let map_size = TilemapSize::new(8, 8);
+        let mut tile_storage = TileStorage::empty(map_size);
+        let tilemap = world.spawn_empty().id();
+        for index in 0..map_size.count() as u32 {
+            let tile_pos = TilePos::new(index % map_size.x, index / map_size.x);
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    texture_index: TileTextureIndex(tile_pos.x % 2),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        world.entity_mut(tilemap).insert(tile_storage);
+
+        let randomize =
+            |storage: Query<&TileStorage>, mut tiles: Query<(&TileTextureIndex, &mut TileFlip)>| {
+                randomize_orientation(
+                    storage.single().unwrap(),
+                    &mut tiles,
+                    |_, texture_index| texture_index.0 == 0,
+                    OrientationSet::ROTATIONS,
+                    7,
+                );
+            };
+        world.run_system_once(randomize).unwrap();
+        let first = world
+            .query::<(&TilePos, &TileTextureIndex, &TileFlip)>()
+            .iter(&world)
+            .map(|(tile_pos, texture_index, flip)| (*tile_pos, *texture_index, *flip))
+            .collect::<Vec<_>>();
+        for (tile_pos, texture_index, flip) in &first {
+            if texture_index.0 == 0 {
+                assert!(OrientationSet::ROTATIONS.contains(flip));
+                assert_eq!(OrientationSet::ROTATIONS.pick(tile_pos, 7), Some(*flip));
+            } else {
+                assert_eq!(*flip, TileFlip::default());
+            }
+        }
+        // Floors this size should not all end up the same way around.
+        let distinct = first
+            .iter()
+            .map(|(_, _, flip)| *flip)
+            .collect::<bevy::platform::collections::HashSet<_>>();
+        assert!(distinct.len() > 1);
+
+        world.run_system_once(randomize).unwrap();
+        let second = world
+            .query::<(&TilePos, &TileTextureIndex, &TileFlip)>()
+            .iter(&world)
+            .map(|(tile_pos, texture_index, flip)| (*tile_pos, *texture_index, *flip))
+            .collect::<Vec<_>>();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,303 @@
+//! Color-blind-safe palettes for visualizing per-tile data (regions, distance fields, occupancy,
+//! ...) directly on a tilemap.
+
+use crate::map::TilemapSize;
+use crate::tiles::{TileColor, TileDataLayer, TilePos, TileStorage};
+use bevy::color::{Color, Mix};
+use bevy::prelude::Query;
+
+/// The palette by Okabe & Ito, which remains distinguishable under the common forms of color
+/// blindness.
+pub const OKABE_ITO: [Color; 8] = [
+    Color::srgb_u8(0xE6, 0x9F, 0x00),
+    Color::srgb_u8(0x56, 0xB4, 0xE9),
+    Color::srgb_u8(0x00, 0x9E, 0x73),
+    Color::srgb_u8(0xF0, 0xE4, 0x42),
+    Color::srgb_u8(0x00, 0x72, 0xB2),
+    Color::srgb_u8(0xD5, 0x5E, 0x00),
+    Color::srgb_u8(0xCC, 0x79, 0xA7),
+    Color::srgb_u8(0x00, 0x00, 0x00),
+];
+
+/// Paul Tol's "bright" qualitative palette.
+pub const TOL_BRIGHT: [Color; 7] = [
+    Color::srgb_u8(0x44, 0x77, 0xAA),
+    Color::srgb_u8(0xEE, 0x66, 0x77),
+    Color::srgb_u8(0x22, 0x88, 0x33),
+    Color::srgb_u8(0xCC, 0xBB, 0x44),
+    Color::srgb_u8(0x66, 0xCC, 0xEE),
+    Color::srgb_u8(0xAA, 0x33, 0x77),
+    Color::srgb_u8(0xBB, 0xBB, 0xBB),
+];
+
+/// Evenly spaced stops of the perceptually uniform "viridis" color map.
+pub const VIRIDIS: [Color; 10] = [
+    Color::srgb_u8(0x44, 0x01, 0x54),
+    Color::srgb_u8(0x48, 0x28, 0x78),
+    Color::srgb_u8(0x3E, 0x4A, 0x89),
+    Color::srgb_u8(0x31, 0x68, 0x8E),
+    Color::srgb_u8(0x26, 0x82, 0x8E),
+    Color::srgb_u8(0x1F, 0x9E, 0x89),
+    Color::srgb_u8(0x35, 0xB7, 0x79),
+    Color::srgb_u8(0x6D, 0xCD, 0x59),
+    Color::srgb_u8(0xB4, 0xDE, 0x2C),
+    Color::srgb_u8(0xFD, 0xE7, 0x25),
+];
+
+/// Evenly spaced stops of the "cividis" color map, which was designed to look nearly identical
+/// to people with and without color vision deficiency.
+pub const CIVIDIS: [Color; 10] = [
+    Color::srgb_u8(0x00, 0x22, 0x4E),
+    Color::srgb_u8(0x12, 0x35, 0x70),
+    Color::srgb_u8(0x3B, 0x49, 0x6C),
+    Color::srgb_u8(0x57, 0x5D, 0x6D),
+    Color::srgb_u8(0x70, 0x71, 0x73),
+    Color::srgb_u8(0x8A, 0x87, 0x79),
+    Color::srgb_u8(0xA6, 0x9D, 0x75),
+    Color::srgb_u8(0xC4, 0xB5, 0x6C),
+    Color::srgb_u8(0xE4, 0xCF, 0x5B),
+    Color::srgb_u8(0xFE, 0xE8, 0x38),
+];
+
+/// A palette of distinct colors, for visualizing integer categories (e.g. region ids).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CategoricalPalette {
+    /// See [`OKABE_ITO`].
+    #[default]
+    OkabeIto,
+    /// See [`TOL_BRIGHT`].
+    TolBright,
+}
+
+impl CategoricalPalette {
+    /// The colors of this palette.
+    pub fn colors(&self) -> &'static [Color] {
+        match self {
+            CategoricalPalette::OkabeIto => &OKABE_ITO,
+            CategoricalPalette::TolBright => &TOL_BRIGHT,
+        }
+    }
+
+    /// Returns the color of the given category. Categories wrap around once the palette runs
+    /// out of colors.
+    pub fn color(&self, category: u32) -> TileColor {
+        let colors = self.colors();
+        TileColor(colors[category as usize % colors.len()])
+    }
+}
+
+/// A palette which smoothly varies in lightness, for visualizing scalar values (e.g. distances,
+/// costs or light levels).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SequentialPalette {
+    /// See [`VIRIDIS`].
+    #[default]
+    Viridis,
+    /// See [`CIVIDIS`].
+    Cividis,
+}
+
+impl SequentialPalette {
+    /// The color stops of this palette.
+    pub fn stops(&self) -> &'static [Color] {
+        match self {
+            SequentialPalette::Viridis => &VIRIDIS,
+            SequentialPalette::Cividis => &CIVIDIS,
+        }
+    }
+
+    /// Returns the color at `t`, where `0.0` is the start and `1.0` is the end of the palette.
+    ///
+    /// `t` is clamped to `[0.0, 1.0]`.
+    pub fn color(&self, t: f32) -> TileColor {
+        let stops = self.stops();
+        let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let ix = (scaled.floor() as usize).min(stops.len() - 2);
+        TileColor(stops[ix].mix(&stops[ix + 1], scaled - ix as f32))
+    }
+
+    /// Returns the color of `value`, where `min` maps to the start and `max` maps to the end of
+    /// the palette.
+    pub fn color_in_range(&self, value: f32, min: f32, max: f32) -> TileColor {
+        if max > min {
+            self.color((value - min) / (max - min))
+        } else {
+            self.color(0.0)
+        }
+    }
+}
+
+/// Colors every tile in `tile_storage` according to the category returned by `category`.
+///
+/// Tiles for which `category` returns `None` are left untouched.
+pub fn color_tiles_by_category<F>(
+    palette: CategoricalPalette,
+    mut category: F,
+    tile_storage: &TileStorage,
+    tile_colors: &mut Query<&mut TileColor>,
+) where
+    F: FnMut(&TilePos) -> Option<u32>,
+{
+    for_each_tile(&tile_storage.size, |tile_pos| {
+        if let Some(category) = category(&tile_pos)
+            && let Some(entity) = tile_storage.get(&tile_pos)
+            && let Ok(mut tile_color) = tile_colors.get_mut(entity)
+        {
+            *tile_color = palette.color(category);
+        }
+    });
+}
+
+/// Colors every tile in `tile_storage` according to the value returned by `value`, where `min`
+/// maps to the start and `max` maps to the end of the palette.
+///
+/// Tiles for which `value` returns `None` are left untouched.
+pub fn color_tiles_by_value<F>(
+    palette: SequentialPalette,
+    min: f32,
+    max: f32,
+    mut value: F,
+    tile_storage: &TileStorage,
+    tile_colors: &mut Query<&mut TileColor>,
+) where
+    F: FnMut(&TilePos) -> Option<f32>,
+{
+    for_each_tile(&tile_storage.size, |tile_pos| {
+        if let Some(value) = value(&tile_pos)
+            && let Some(entity) = tile_storage.get(&tile_pos)
+            && let Ok(mut tile_color) = tile_colors.get_mut(entity)
+        {
+            *tile_color = palette.color_in_range(value, min, max);
+        }
+    });
+}
+
+/// Colors every tile in `tile_storage` according to its value in `layer`, stretching `gradient`
+/// over the smallest and largest value in the layer.
+///
+/// Non-finite values (such as the `f32::INFINITY` distance of an unreachable tile) are ignored
+/// and leave their tiles untouched.
+pub fn visualize_scalar_layer(
+    layer: &TileDataLayer<f32>,
+    gradient: SequentialPalette,
+    tile_storage: &TileStorage,
+    tile_colors: &mut Query<&mut TileColor>,
+) {
+    let (min, max) = layer
+        .values()
+        .filter(|value| value.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(*value), max.max(*value))
+        });
+
+    color_tiles_by_value(
+        gradient,
+        min,
+        max,
+        |tile_pos| {
+            layer
+                .checked_get(tile_pos)
+                .copied()
+                .filter(|value| value.is_finite())
+        },
+        tile_storage,
+        tile_colors,
+    );
+}
+
+fn for_each_tile(map_size: &TilemapSize, mut f: impl FnMut(TilePos)) {
+    for y in 0..map_size.y {
+        for x in 0..map_size.x {
+            f(TilePos { x, y });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::{schedule::Schedule, system::ResMut, world::World};
+    use bevy::prelude::{Entity, Resource};
+
+    #[derive(Resource)]
+    struct Tiles(TileStorage);
+
+    #[test]
+    fn palettes_color_categories_and_values() {
+        let palette = CategoricalPalette::TolBright;
+        assert_eq!(palette.color(1).0, TOL_BRIGHT[1]);
+        assert_eq!(palette.color(8).0, palette.color(1).0);
+
+        let gradient = SequentialPalette::Viridis;
+        assert_eq!(gradient.color(-1.0).0, VIRIDIS[0]);
+        assert_eq!(gradient.color(2.0).0, VIRIDIS[9]);
+        assert_eq!(
+            gradient.color_in_range(15.0, 10.0, 20.0).0,
+            gradient.color(0.5).0
+        );
+        assert_eq!(
+            gradient.color_in_range(3.0, 5.0, 5.0).0,
+            gradient.color(0.0).0
+        );
+
+        let mut world = World::new();
+        let mut storage = TileStorage::empty(TilemapSize::new(3, 1));
+        let tiles = [0, 1, 2].map(|x| {
+            let tile = world.spawn(TileColor::default()).id();
+            storage.set(&TilePos::new(x, 0), tile);
+            tile
+        });
+        world.insert_resource(Tiles(storage));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            |tiles: ResMut<Tiles>, mut tile_colors: Query<&mut TileColor>| {
+                color_tiles_by_category(
+                    CategoricalPalette::OkabeIto,
+                    |tile_pos| (tile_pos.x > 0).then_some(tile_pos.x),
+                    &tiles.0,
+                    &mut tile_colors,
+                );
+            },
+        );
+        schedule.run(&mut world);
+
+        let color = |world: &World, tile: Entity| world.get::<TileColor>(tile).unwrap().0;
+        assert_eq!(color(&world, tiles[0]), TileColor::default().0);
+        assert_eq!(color(&world, tiles[1]), OKABE_ITO[1]);
+        assert_eq!(color(&world, tiles[2]), OKABE_ITO[2]);
+    }
+
+    #[test]
+    fn scalar_layers_are_stretched_over_the_gradient() {
+        let mut world = World::new();
+        let size = TilemapSize::new(3, 1);
+        let mut storage = TileStorage::empty(size);
+        let tiles = [0, 1, 2].map(|x| {
+            let tile = world.spawn(TileColor::default()).id();
+            storage.set(&TilePos::new(x, 0), tile);
+            tile
+        });
+        world.insert_resource(Tiles(storage));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            move |tiles: ResMut<Tiles>, mut tile_colors: Query<&mut TileColor>| {
+                // The unreachable tile neither widens the range nor gets a color.
+                let layer = TileDataLayer::from_fn(size, |tile_pos| {
+                    [2.0, 4.0, f32::INFINITY][tile_pos.x as usize]
+                });
+                visualize_scalar_layer(
+                    &layer,
+                    SequentialPalette::Cividis,
+                    &tiles.0,
+                    &mut tile_colors,
+                );
+            },
+        );
+        schedule.run(&mut world);
+
+        let color = |tile: Entity| world.get::<TileColor>(tile).unwrap().0;
+        assert_eq!(color(tiles[0]), CIVIDIS[0]);
+        assert_eq!(color(tiles[1]), CIVIDIS[9]);
+        assert_eq!(color(tiles[2]), TileColor::default().0);
+    }
+}
@@ -0,0 +1,345 @@
+//! Helpers for finding paths between tiles, and the cost of reaching tiles from several sources.
+
+use crate::tiles::TilePos;
+use bevy::math::FloatOrd;
+use bevy::platform::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Describes how differences in elevation between two neighboring tiles affect the cost of moving
+/// between them.
+///
+/// This is useful for isometric maps with cliffs and slopes, where climbing should be more
+/// expensive than walking on flat ground, and some transitions should not be possible at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElevationCost {
+    /// Cost of moving between two tiles of equal height.
+    pub base_cost: f32,
+    /// Additional cost per unit of height gained when moving upwards.
+    pub climb_cost: f32,
+    /// Additional cost per unit of height lost when moving downwards.
+    pub descent_cost: f32,
+    /// The largest height gain allowed in a single step. Steeper transitions are rejected.
+    pub max_climb: Option<f32>,
+    /// The largest height loss allowed in a single step. Steeper transitions are rejected.
+    pub max_drop: Option<f32>,
+}
+
+impl Default for ElevationCost {
+    fn default() -> Self {
+        Self {
+            base_cost: 1.0,
+            climb_cost: 0.0,
+            descent_cost: 0.0,
+            max_climb: None,
+            max_drop: None,
+        }
+    }
+}
+
+impl ElevationCost {
+    /// Returns the cost of stepping from a tile at height `from` onto a tile at height `to`.
+    ///
+    /// Returns `None` if the height difference exceeds [`max_climb`](Self::max_climb) or
+    /// [`max_drop`](Self::max_drop).
+    pub fn step_cost(&self, from: f32, to: f32) -> Option<f32> {
+        let delta = to - from;
+        if delta > 0.0 {
+            if self.max_climb.is_some_and(|max_climb| delta > max_climb) {
+                return None;
+            }
+            Some(self.base_cost + delta * self.climb_cost)
+        } else {
+            if self.max_drop.is_some_and(|max_drop| -delta > max_drop) {
+                return None;
+            }
+            Some(self.base_cost - delta * self.descent_cost)
+        }
+    }
+}
+
+/// Finds the cheapest path from `start` to `goal` using Dijkstra's algorithm.
+///
+/// `neighbors` returns the tiles reachable in a single step from a tile, for example by using
+/// [`Neighbors::get_square_neighboring_positions`](crate::helpers::square_grid::neighbors::Neighbors::get_square_neighboring_positions)
+/// or [`HexNeighbors::get_neighboring_positions`](crate::helpers::hex_grid::neighbors::HexNeighbors::get_neighboring_positions).
+///
+/// `cost` returns the cost of stepping from the first tile onto the second one, or `None` if the
+/// step is not allowed. Costs must not be negative. [`ElevationCost::step_cost`] can be used to
+/// derive costs from tile heights.
+///
+/// Returns the path (including both `start` and `goal`) together with its total cost, or `None`
+/// if `goal` can not be reached.
+pub fn find_path<N, I, C>(
+    start: TilePos,
+    goal: TilePos,
+    neighbors: N,
+    cost: C,
+) -> Option<(Vec<TilePos>, f32)>
+where
+    N: FnMut(&TilePos) -> I,
+    I: IntoIterator<Item = TilePos>,
+    C: FnMut(&TilePos, &TilePos) -> Option<f32>,
+{
+    let mut reached = false;
+    let visits = traverse([(start, 0.0)], neighbors, cost, None, |tile_pos| {
+        reached = tile_pos == goal;
+        !reached
+    });
+    if !reached {
+        return None;
+    }
+
+    let mut path = vec![goal];
+    let mut tile_pos = goal;
+    while let Some(previous) = visits[&tile_pos].previous {
+        path.push(previous);
+        tile_pos = previous;
+    }
+    path.reverse();
+    Some((path, visits[&goal].cost))
+}
+
+/// The cost of reaching every tile from the nearest of several sources, as computed by
+/// [`distance_field`].
+#[derive(Clone, Debug, Default)]
+pub struct DistanceField {
+    visits: HashMap<TilePos, Visit>,
+}
+
+impl DistanceField {
+    /// Returns the cost of reaching `tile_pos` from the cheapest source, or `None` if it can not
+    /// be reached.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<f32> {
+        self.visits.get(tile_pos).map(|visit| visit.cost)
+    }
+
+    /// Returns the index of the source `tile_pos` is reached from most cheaply, in the order the
+    /// sources were given.
+    pub fn source(&self, tile_pos: &TilePos) -> Option<usize> {
+        self.visits.get(tile_pos).map(|visit| visit.source)
+    }
+
+    /// Returns the neighbor of `tile_pos` one step closer to its cheapest source, i.e. the tile
+    /// to move to when following the field downhill. Returns `None` for the sources themselves
+    /// and for tiles which can not be reached.
+    pub fn next_step(&self, tile_pos: &TilePos) -> Option<TilePos> {
+        self.visits.get(tile_pos)?.previous
+    }
+
+    /// Iterates over the reached tiles and their costs, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, f32)> + '_ {
+        self.visits
+            .iter()
+            .map(|(tile_pos, visit)| (*tile_pos, visit.cost))
+    }
+
+    /// Returns the number of reached tiles.
+    pub fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+}
+
+/// Computes the cost of reaching every tile from the nearest of several `sources`, e.g. to build
+/// threat maps or desirability fields for AI.
+///
+/// Each source is given with the cost it starts at, which acts as its weight: a source starting
+/// at a lower (possibly negative) cost claims tiles further away from the other sources. Tiles
+/// are reached through `neighbors` and `cost` exactly like in [`find_path`], so the field agrees
+/// with the paths it finds. With a `cost` of `Some(1.0)` for every step, the field holds step
+/// counts, like a breadth-first search.
+///
+/// Tiles which cost more than `max_cost` to reach are left out, which bounds the work done on
+/// large maps.
+pub fn distance_field<S, N, I, C>(
+    sources: S,
+    neighbors: N,
+    cost: C,
+    max_cost: Option<f32>,
+) -> DistanceField
+where
+    S: IntoIterator<Item = (TilePos, f32)>,
+    N: FnMut(&TilePos) -> I,
+    I: IntoIterator<Item = TilePos>,
+    C: FnMut(&TilePos, &TilePos) -> Option<f32>,
+{
+    DistanceField {
+        visits: traverse(sources, neighbors, cost, max_cost, |_| true),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Visit {
+    cost: f32,
+    previous: Option<TilePos>,
+    source: usize,
+}
+
+/// Runs Dijkstra's algorithm from the `seeds`, each starting at its own cost, and returns the
+/// cheapest known visit of every tile reached.
+///
+/// `settle` is called with each tile once its cost is final, in order of cost; the search stops
+/// as soon as it returns `false`. Only the visits of settled tiles are guaranteed to be final.
+fn traverse<S, N, I, C>(
+    seeds: S,
+    mut neighbors: N,
+    mut cost: C,
+    max_cost: Option<f32>,
+    mut settle: impl FnMut(TilePos) -> bool,
+) -> HashMap<TilePos, Visit>
+where
+    S: IntoIterator<Item = (TilePos, f32)>,
+    N: FnMut(&TilePos) -> I,
+    I: IntoIterator<Item = TilePos>,
+    C: FnMut(&TilePos, &TilePos) -> Option<f32>,
+{
+    let within_max_cost = |cost: f32| max_cost.is_none_or(|max_cost| cost <= max_cost);
+    let mut visits = HashMap::<TilePos, Visit>::default();
+    let mut frontier = BinaryHeap::new();
+
+    for (source, (tile_pos, start_cost)) in seeds.into_iter().enumerate() {
+        if within_max_cost(start_cost)
+            && visits
+                .get(&tile_pos)
+                .is_none_or(|visit| start_cost < visit.cost)
+        {
+            visits.insert(
+                tile_pos,
+                Visit {
+                    cost: start_cost,
+                    previous: None,
+                    source,
+                },
+            );
+            frontier.push(Reverse((FloatOrd(start_cost), tile_pos)));
+        }
+    }
+
+    while let Some(Reverse((FloatOrd(current_cost), current))) = frontier.pop() {
+        // Skip stale entries which were superseded by a cheaper route.
+        if current_cost > visits[&current].cost {
+            continue;
+        }
+        if !settle(current) {
+            break;
+        }
+
+        let source = visits[&current].source;
+        for next in neighbors(&current) {
+            let Some(step_cost) = cost(&current, &next) else {
+                continue;
+            };
+            let next_cost = current_cost + step_cost;
+            if within_max_cost(next_cost)
+                && visits.get(&next).is_none_or(|visit| next_cost < visit.cost)
+            {
+                visits.insert(
+                    next,
+                    Visit {
+                        cost: next_cost,
+                        previous: Some(current),
+                        source,
+                    },
+                );
+                frontier.push(Reverse((FloatOrd(next_cost), next)));
+            }
+        }
+    }
+
+    visits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::square_grid::neighbors::Neighbors;
+    use crate::map::TilemapSize;
+
+    #[test]
+    fn step_cost_respects_max_climb() {
+        let elevation_cost = ElevationCost {
+            climb_cost: 2.0,
+            max_climb: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(elevation_cost.step_cost(0.0, 0.5), Some(2.0));
+        assert_eq!(elevation_cost.step_cost(0.0, 1.5), None);
+        assert_eq!(elevation_cost.step_cost(1.5, 0.0), Some(1.0));
+    }
+
+    #[test]
+    fn path_avoids_cliffs() {
+        let map_size = TilemapSize { x: 3, y: 3 };
+        // A cliff runs through the middle column, except for the top row.
+        let height = |tile_pos: &TilePos| {
+            if tile_pos.x == 1 && tile_pos.y < 2 {
+                5.0
+            } else {
+                0.0
+            }
+        };
+        let elevation_cost = ElevationCost {
+            max_climb: Some(1.0),
+            ..Default::default()
+        };
+
+        let (path, cost) = find_path(
+            TilePos::new(0, 0),
+            TilePos::new(2, 0),
+            |tile_pos| {
+                Neighbors::get_square_neighboring_positions(tile_pos, &map_size, false)
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>()
+            },
+            |from, to| elevation_cost.step_cost(height(from), height(to)),
+        )
+        .unwrap();
+
+        assert_eq!(cost, 6.0);
+        assert!(path.iter().all(|tile_pos| height(tile_pos) == 0.0));
+    }
+
+    #[test]
+    fn weighted_sources_split_the_field() {
+        let map_size = TilemapSize { x: 7, y: 1 };
+        let neighbors = |tile_pos: &TilePos| {
+            Neighbors::get_square_neighboring_positions(tile_pos, &map_size, false)
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        // Swamp on the fifth tile costs three times as much to enter.
+        let cost = |_: &TilePos, to: &TilePos| Some(if to.x == 4 { 3.0 } else { 1.0 });
+
+        // The source on the left is weighted to reach further than the one on the right.
+        let field = distance_field(
+            [(TilePos::new(0, 0), -1.0), (TilePos::new(6, 0), 0.0)],
+            neighbors,
+            cost,
+            None,
+        );
+        let costs = (0..7)
+            .map(|x| field.get(&TilePos::new(x, 0)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(costs, vec![-1.0, 0.0, 1.0, 2.0, 4.0, 1.0, 0.0]);
+        assert_eq!(field.source(&TilePos::new(3, 0)), Some(0));
+        assert_eq!(field.source(&TilePos::new(4, 0)), Some(1));
+        assert_eq!(
+            field.next_step(&TilePos::new(4, 0)),
+            Some(TilePos::new(5, 0))
+        );
+
+        // The field agrees with the cost of the paths found between the same tiles.
+        let (_, path_cost) =
+            find_path(TilePos::new(6, 0), TilePos::new(2, 0), neighbors, cost).unwrap();
+        let field = distance_field([(TilePos::new(6, 0), 0.0)], neighbors, cost, Some(3.0));
+        assert_eq!(path_cost, 6.0);
+        assert_eq!(field.get(&TilePos::new(2, 0)), None);
+        assert_eq!(field.len(), 2);
+    }
+}
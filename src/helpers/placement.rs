@@ -0,0 +1,168 @@
+//! Checks whether a multi-tile object (a building, a large prop) fits into a tilemap, so the check
+//! shown by a placement UI and the placement itself agree on the cells the object covers.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::placement::*;
+//! let mut storage = TileStorage::empty(TilemapSize::new(8, 8));
+//! storage.set(&TilePos::new(3, 2), Entity::PLACEHOLDER);
+//!
+//! let house = TileFootprint::Rect(TilemapSize::new(2, 2));
+//! let is_empty = |_: &TilePos, tile: Option<Entity>| tile.is_none();
+//! assert_eq!(
+//!     can_place(&storage, TilePos::new(2, 1), &house, is_empty),
+//!     Err(vec![BlockedCell::Rejected(TilePos::new(3, 2), Some(Entity::PLACEHOLDER))])
+//! );
+//! // On success, the covered positions are returned, ready to be filled.
+//! let covered = can_place(&storage, TilePos::new(0, 0), &house, is_empty).unwrap();
+//! assert_eq!(covered.len(), 4);
+//! ```
+
+use bevy::prelude::Entity;
+
+use crate::map::TilemapSize;
+use crate::tiles::{TileDataLayer, TilePos, TileStorage};
+
+/// The cells covered by a multi-tile object, relative to the position it is placed at.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub enum TileFootprint {
+    /// Every cell of a rectangle of the given size.
+    Rect(TilemapSize),
+    /// The given cells, for objects which are not rectangular.
+    Cells(Vec<TilePos>),
+}
+
+impl TileFootprint {
+    /// The footprint of the cells of a layer which hold a value, e.g. a stamp built with the
+    /// [`tilemap!`](crate::tilemap) macro.
+    pub fn from_layer<T: Send + Sync + 'static>(layer: &TileDataLayer<Option<T>>) -> Self {
+        Self::Cells(
+            layer
+                .iter()
+                .filter(|(_, value)| value.is_some())
+                .map(|(tile_pos, _)| tile_pos)
+                .collect(),
+        )
+    }
+
+    /// Iterates over the cells of the footprint, relative to its origin.
+    pub fn cells(&self) -> impl Iterator<Item = TilePos> + '_ {
+        let rect = match self {
+            Self::Rect(size) => Some(*size),
+            Self::Cells(_) => None,
+        };
+        let cells = match self {
+            Self::Rect(_) => &[][..],
+            Self::Cells(cells) => cells,
+        };
+        rect.into_iter()
+            .flat_map(|size| (0..size.y).flat_map(move |y| (0..size.x).map(move |x| (x, y))))
+            .map(|(x, y)| TilePos::new(x, y))
+            .chain(cells.iter().copied())
+    }
+}
+
+/// A cell of a [`TileFootprint`] which prevents it from being placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockedCell {
+    /// The cell at this offset in the footprint lies outside of the tilemap.
+    OutOfBounds(TilePos),
+    /// The predicate rejected the cell at this position, which holds the given tile.
+    Rejected(TilePos, Option<Entity>),
+}
+
+/// Checks whether `footprint` can be placed with its origin at `origin`.
+///
+/// Every cell must lie within the tilemap and be accepted by `predicate`, which is given the
+/// position of the cell and the tile in it, if any. Use `|_, tile| tile.is_none()` to only allow
+/// empty cells.
+///
+/// Returns the positions covered by the footprint, or all of the cells which block it.
+pub fn can_place(
+    storage: &TileStorage,
+    origin: TilePos,
+    footprint: &TileFootprint,
+    mut predicate: impl FnMut(&TilePos, Option<Entity>) -> bool,
+) -> Result<Vec<TilePos>, Vec<BlockedCell>> {
+    let mut covered = Vec::new();
+    let mut blocked = Vec::new();
+    for offset in footprint.cells() {
+        let tile_pos = origin
+            .x
+            .checked_add(offset.x)
+            .zip(origin.y.checked_add(offset.y))
+            .map(|(x, y)| TilePos::new(x, y))
+            .filter(|tile_pos| tile_pos.within_map_bounds(&storage.size));
+        let Some(tile_pos) = tile_pos else {
+            blocked.push(BlockedCell::OutOfBounds(offset));
+            continue;
+        };
+
+        let tile = storage.get(&tile_pos);
+        if predicate(&tile_pos, tile) {
+            covered.push(tile_pos);
+        } else {
+            blocked.push(BlockedCell::Rejected(tile_pos, tile));
+        }
+    }
+
+    if blocked.is_empty() {
+        Ok(covered)
+    } else {
+        Err(blocked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap;
+
+    #[test]
+    fn every_blocking_cell_is_reported() {
+        let mut storage = TileStorage::empty(TilemapSize::new(4, 4));
+        storage.set(&TilePos::new(1, 0), Entity::PLACEHOLDER);
+
+        let footprint = TileFootprint::from_layer(&tilemap!(
+            "
+            ##
+            #.
+            ",
+            { '#' => 0 }
+        ));
+        let blocked = can_place(&storage, TilePos::new(1, 3), &footprint, |_, tile| {
+            tile.is_none()
+        })
+        .unwrap_err();
+        // The top row of the art is at `y = 1`, above the map.
+        assert_eq!(
+            blocked,
+            vec![
+                BlockedCell::OutOfBounds(TilePos::new(0, 1)),
+                BlockedCell::OutOfBounds(TilePos::new(1, 1)),
+            ]
+        );
+
+        let blocked = can_place(&storage, TilePos::new(1, 0), &footprint, |_, tile| {
+            tile.is_none()
+        })
+        .unwrap_err();
+        assert_eq!(
+            blocked,
+            vec![BlockedCell::Rejected(
+                TilePos::new(1, 0),
+                Some(Entity::PLACEHOLDER)
+            )]
+        );
+
+        let covered = can_place(&storage, TilePos::new(2, 2), &footprint, |_, tile| {
+            tile.is_none()
+        })
+        .unwrap();
+        assert_eq!(
+            covered,
+            vec![TilePos::new(2, 2), TilePos::new(2, 3), TilePos::new(3, 3)]
+        );
+    }
+}
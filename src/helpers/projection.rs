@@ -1,11 +1,15 @@
 use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::hex_grid::neighbors::HEX_OFFSETS;
 use crate::helpers::hex_grid::offset::{ColEvenPos, ColOddPos, RowEvenPos, RowOddPos};
+use crate::helpers::selection::unclamped_tile_coords;
+use crate::helpers::square_grid::SquarePos;
 use crate::helpers::square_grid::diamond::DiamondPos;
+use crate::helpers::square_grid::neighbors::SquareDirection;
 use crate::helpers::square_grid::staggered::StaggeredPos;
-use crate::map::{HexCoordSystem, IsoCoordSystem};
-use crate::tiles::TilePos;
+use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapTopology};
+use crate::tiles::{SignedTilePos, TilePos};
 use crate::{TilemapAnchor, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
-use bevy::math::Vec2;
+use bevy::math::{Rect, Vec2};
 
 impl TilePos {
     /// Get the center of this tile in world space.
@@ -23,6 +27,43 @@ impl TilePos {
         offset + self.center_in_world_unanchored(grid_size, map_type)
     }
 
+    /// Returns the corners of this tile in world space, counter-clockwise.
+    ///
+    /// Square and isometric tiles have four corners, hexagonal tiles have six.
+    pub fn corners_in_world(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+    ) -> Vec<Vec2> {
+        let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+        self.corners_in_world_unanchored(grid_size, map_type)
+            .into_iter()
+            .map(|corner| corner + offset)
+            .collect()
+    }
+
+    /// Returns the axis-aligned bounding rectangle of this tile's cell in world space, i.e. of
+    /// its [corners](Self::corners_in_world).
+    pub fn world_rect(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+    ) -> Rect {
+        let corners = self.corners_in_world(map_size, grid_size, tile_size, map_type, anchor);
+        let (min, max) = corners
+            .iter()
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), corner| {
+                (min.min(*corner), max.max(*corner))
+            });
+        Rect::from_corners(min, max)
+    }
+
     pub(crate) fn center_in_world_unanchored(
         &self,
         grid_size: &TilemapGridSize,
@@ -47,27 +88,92 @@ impl TilePos {
         }
     }
 
+    /// Returns the corners of this tile in world space, ignoring the tilemap's anchor.
+    ///
+    /// Square and isometric tiles have four corners, hexagonal tiles have six.
+    pub(crate) fn corners_in_world_unanchored(
+        &self,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Vec<Vec2> {
+        const SQUARE_CORNERS: [SquareDirection; 4] = [
+            SquareDirection::NorthEast,
+            SquareDirection::NorthWest,
+            SquareDirection::SouthWest,
+            SquareDirection::SouthEast,
+        ];
+
+        match map_type {
+            TilemapType::Square => {
+                let square_pos = SquarePos::from(self);
+                SQUARE_CORNERS
+                    .iter()
+                    .map(|direction| square_pos.corner_in_world(*direction, grid_size))
+                    .collect()
+            }
+            TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+                let diamond_pos = DiamondPos::from(self);
+                SQUARE_CORNERS
+                    .iter()
+                    .map(|direction| diamond_pos.corner_in_world(*direction, grid_size))
+                    .collect()
+            }
+            TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+                let staggered_pos = StaggeredPos::from(self);
+                SQUARE_CORNERS
+                    .iter()
+                    .map(|direction| staggered_pos.corner_in_world(*direction, grid_size))
+                    .collect()
+            }
+            TilemapType::Hexagon(hex_coord_sys) => {
+                let axial_pos = AxialPos::from_tile_pos_given_coord_system(self, *hex_coord_sys);
+                let center = Vec2::new(axial_pos.q as f32, axial_pos.r as f32);
+                // A hex corner lies between two adjacent neighbors, at a third of the way to the
+                // sum of their offsets.
+                (0..6)
+                    .map(|ix| {
+                        let a = HEX_OFFSETS[ix];
+                        let b = HEX_OFFSETS[(ix + 1) % 6];
+                        let corner =
+                            center + Vec2::new((a.q + b.q) as f32, (a.r + b.r) as f32) / 3.0;
+                        match hex_coord_sys {
+                            HexCoordSystem::Row
+                            | HexCoordSystem::RowEven
+                            | HexCoordSystem::RowOdd => AxialPos::project_row(corner, grid_size),
+                            HexCoordSystem::Column
+                            | HexCoordSystem::ColumnEven
+                            | HexCoordSystem::ColumnOdd => AxialPos::project_col(corner, grid_size),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
     /// Try converting a pair of `i32` numbers into a `TilePos`.
     ///
     /// Returns `None` if either one of `x` or `y` is negative, or lies out of the bounds of
     /// `map_size`.
     pub fn from_i32_pair(x: i32, y: i32, map_size: &TilemapSize) -> Option<TilePos> {
-        if x < 0 || y < 0 {
-            None
-        } else {
-            let tile_pos = TilePos {
-                x: x as u32,
-                y: y as u32,
-            };
-
-            if tile_pos.within_map_bounds(map_size) {
-                Some(tile_pos)
-            } else {
-                None
-            }
-        }
+        SignedTilePos::new(x, y).as_tile_pos_given_map_size(map_size)
     }
 
+    /// Returns the tile containing the given world position, or `None` if it does not lie on the
+    /// tilemap.
+    ///
+    /// This is the inverse of [`center_in_world`](Self::center_in_world): for every tile position
+    /// `p` on the map, and every [`TilemapType`], [`TilemapAnchor`], grid size and tile size,
+    ///
+    /// ```text
+    /// TilePos::from_world_pos(&p.center_in_world(..), ..) == Some(p)
+    /// ```
+    ///
+    /// More generally, every point strictly inside the cell of `p` maps to `p`, up to `f32`
+    /// precision. A point exactly on the border between cells maps to one of the cells sharing
+    /// it, with halves rounded towards larger grid coordinates for all map types.
+    ///
+    /// `world_pos` has to be in the local space of the tilemap; transform world positions with
+    /// the inverse of the tilemap's [`GlobalTransform`](bevy::prelude::GlobalTransform) first.
     pub fn from_world_pos(
         world_pos: &Vec2,
         map_size: &TilemapSize,
@@ -111,4 +217,247 @@ impl TilePos {
             },
         }
     }
+
+    /// Like [`from_world_pos`](Self::from_world_pos), but on a map with the given
+    /// [`TilemapTopology`]: points past a wrapping edge, e.g. on a copy of the map drawn next to
+    /// it, map to the tile they show.
+    pub fn from_world_pos_wrapped(
+        world_pos: &Vec2,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+        topology: &TilemapTopology,
+    ) -> Option<TilePos> {
+        let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+        let coords = unclamped_tile_coords(&(world_pos - offset), grid_size, map_type);
+        topology.wrap(coords, map_size)
+    }
+}
+
+/// Snaps a world position to the center of the tile containing it.
+///
+/// Useful for placement cursors and drag-and-drop which must land exactly on the grid.
+///
+/// Returns `None` if `world_pos` does not lie on the tilemap.
+pub fn snap_world_pos_to_tile_center(
+    world_pos: &Vec2,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Option<Vec2> {
+    TilePos::from_world_pos(world_pos, map_size, grid_size, tile_size, map_type, anchor)
+        .map(|tile_pos| tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor))
+}
+
+/// Snaps a world position to the nearest corner of the tile containing it.
+///
+/// Returns `None` if `world_pos` does not lie on the tilemap.
+pub fn snap_world_pos_to_tile_corner(
+    world_pos: &Vec2,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Option<Vec2> {
+    let tile_pos =
+        TilePos::from_world_pos(world_pos, map_size, grid_size, tile_size, map_type, anchor)?;
+    tile_pos
+        .corners_in_world(map_size, grid_size, tile_size, map_type, anchor)
+        .into_iter()
+        .min_by(|a, b| {
+            a.distance_squared(*world_pos)
+                .total_cmp(&b.distance_squared(*world_pos))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Affine2;
+    use proptest::prelude::*;
+
+    fn map_type() -> impl Strategy<Value = TilemapType> {
+        prop_oneof![
+            Just(TilemapType::Square),
+            Just(TilemapType::Isometric(IsoCoordSystem::Diamond)),
+            Just(TilemapType::Isometric(IsoCoordSystem::Staggered)),
+            Just(TilemapType::Hexagon(HexCoordSystem::Row)),
+            Just(TilemapType::Hexagon(HexCoordSystem::RowEven)),
+            Just(TilemapType::Hexagon(HexCoordSystem::RowOdd)),
+            Just(TilemapType::Hexagon(HexCoordSystem::Column)),
+            Just(TilemapType::Hexagon(HexCoordSystem::ColumnEven)),
+            Just(TilemapType::Hexagon(HexCoordSystem::ColumnOdd)),
+        ]
+    }
+
+    fn anchor() -> impl Strategy<Value = TilemapAnchor> {
+        prop_oneof![
+            Just(TilemapAnchor::None),
+            Just(TilemapAnchor::Center),
+            Just(TilemapAnchor::BottomLeft),
+            Just(TilemapAnchor::BottomCenter),
+            Just(TilemapAnchor::BottomRight),
+            Just(TilemapAnchor::CenterLeft),
+            Just(TilemapAnchor::CenterRight),
+            Just(TilemapAnchor::TopLeft),
+            Just(TilemapAnchor::TopCenter),
+            Just(TilemapAnchor::TopRight),
+            (-1.0f32..1.0, -1.0f32..1.0).prop_map(|(x, y)| TilemapAnchor::Custom(Vec2::new(x, y))),
+        ]
+    }
+
+    /// A tilemap with a tile on it, and a transform from the local space of the tilemap.
+    #[derive(Debug)]
+    struct Case {
+        map_size: TilemapSize,
+        grid_size: TilemapGridSize,
+        tile_size: TilemapTileSize,
+        map_type: TilemapType,
+        anchor: TilemapAnchor,
+        transform: Affine2,
+        tile_pos: TilePos,
+    }
+
+    impl Case {
+        fn center(&self) -> Vec2 {
+            self.tile_pos.center_in_world(
+                &self.map_size,
+                &self.grid_size,
+                &self.tile_size,
+                &self.map_type,
+                &self.anchor,
+            )
+        }
+
+        /// Transforms `local_pos` to world space and back, then looks up the tile there.
+        fn tile_at(&self, local_pos: Vec2) -> Option<TilePos> {
+            let world_pos = self.transform.transform_point2(local_pos);
+            let local_pos = self.transform.inverse().transform_point2(world_pos);
+            TilePos::from_world_pos(
+                &local_pos,
+                &self.map_size,
+                &self.grid_size,
+                &self.tile_size,
+                &self.map_type,
+                &self.anchor,
+            )
+        }
+    }
+
+    fn case() -> impl Strategy<Value = Case> {
+        (
+            (1u32..256, 1u32..256),
+            (0.1f32..1000.0, 0.1f32..1000.0),
+            (0.25f32..4.0, 0.25f32..4.0),
+            map_type(),
+            anchor(),
+            (-1000.0f32..1000.0, -1000.0f32..1000.0),
+            -std::f32::consts::PI..std::f32::consts::PI,
+            (0.25f32..4.0, 0.25f32..4.0),
+            (0u32..256, 0u32..256),
+        )
+            .prop_map(
+                |(
+                    map_size,
+                    grid_size,
+                    tile_ratio,
+                    map_type,
+                    anchor,
+                    translation,
+                    angle,
+                    scale,
+                    tile_pos,
+                )| {
+                    Case {
+                        map_size: TilemapSize::new(map_size.0, map_size.1),
+                        grid_size: TilemapGridSize::new(grid_size.0, grid_size.1),
+                        tile_size: TilemapTileSize::new(
+                            grid_size.0 * tile_ratio.0,
+                            grid_size.1 * tile_ratio.1,
+                        ),
+                        map_type,
+                        anchor,
+                        transform: Affine2::from_scale_angle_translation(
+                            scale.into(),
+                            angle,
+                            translation.into(),
+                        ),
+                        tile_pos: TilePos::new(tile_pos.0 % map_size.0, tile_pos.1 % map_size.1),
+                    }
+                },
+            )
+    }
+
+    #[test]
+    fn hex_borders_round_towards_larger_coordinates() {
+        use crate::helpers::hex_grid::cube::{CubePos, FractionalCubePos};
+
+        // Halves used to be rounded away from zero, so borders resolved differently on either
+        // side of the origin.
+        assert_eq!(
+            FractionalCubePos::new(0.5, 0.0, -0.5).round(),
+            CubePos::new(1, 0, -1)
+        );
+        assert_eq!(
+            FractionalCubePos::new(-0.5, 0.0, 0.5).round(),
+            CubePos::new(0, 0, 0)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn tile_centers_round_trip(case in case()) {
+            prop_assert_eq!(case.tile_at(case.center()), Some(case.tile_pos));
+        }
+
+        #[test]
+        fn points_inside_a_cell_map_to_its_tile(
+            case in case(),
+            corner in 0usize..6,
+            (a, b) in (0.0f32..1.0, 0.0f32..1.0),
+        ) {
+            // A point in the triangle between the center and two adjacent corners of the cell,
+            // pulled slightly towards the center to stay clear of `f32` rounding at the border.
+            let center = case.center();
+            let offset = center - case.tile_pos.center_in_world_unanchored(&case.grid_size, &case.map_type);
+            let corners = case
+                .tile_pos
+                .corners_in_world_unanchored(&case.grid_size, &case.map_type);
+            let i = corner % corners.len();
+            let j = (i + 1) % corners.len();
+            let (a, b) = (a, b * (1.0 - a));
+            let point = center
+                + 0.999 * (a * (corners[i] + offset - center) + b * (corners[j] + offset - center));
+            prop_assert_eq!(case.tile_at(point), Some(case.tile_pos));
+        }
+
+        #[test]
+        fn corners_wind_around_the_center(case in case()) {
+            let corners = case.tile_pos.corners_in_world(
+                &case.map_size,
+                &case.grid_size,
+                &case.tile_size,
+                &case.map_type,
+                &case.anchor,
+            );
+            let center = case.center();
+            for (i, corner) in corners.iter().enumerate() {
+                let next = corners[(i + 1) % corners.len()];
+                prop_assert!((*corner - center).perp_dot(next - center) > 0.0);
+            }
+            let rect = case.tile_pos.world_rect(
+                &case.map_size,
+                &case.grid_size,
+                &case.tile_size,
+                &case.map_type,
+                &case.anchor,
+            );
+            prop_assert!(rect.contains(center));
+        }
+    }
 }
@@ -0,0 +1,452 @@
+//! Converts vector shapes, e.g. roads and zones drawn in a level editor, into the tiles they
+//! cover, on any map type.
+//!
+//! Points are given in the tilemap's local space (see [`TilePos::from_world_pos`]). Tiles
+//! outside of the tilemap are never returned.
+
+use bevy::math::{IVec2, Vec2};
+use bevy::platform::collections::HashSet;
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::line::tile_line_coords;
+use crate::helpers::selection::unclamped_tile_coords;
+use crate::map::{TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::TilePos;
+
+/// Which tiles along the outline of a polygon are covered by it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EdgeRule {
+    /// The tiles the outline passes through are covered too, so the tiles enclose the whole
+    /// polygon.
+    #[default]
+    Inclusive,
+    /// Only the tiles whose center lies strictly inside of the polygon are covered, so the tiles
+    /// lie within the polygon.
+    Exclusive,
+}
+
+/// Which tiles are covered by a shape, for [`tiles_in_polygon`] and [`tiles_in_ellipse`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TileCoverage {
+    /// The tiles whose center lies inside of the shape or on its outline.
+    #[default]
+    Center,
+    /// The tiles which overlap the shape, even by a little. Tiles which only touch the outline
+    /// are not covered.
+    Overlap,
+}
+
+/// Returns the tiles on the polyline through `points`, in order and without repeats.
+///
+/// Each segment is drawn like [`tile_line`](crate::helpers::line::tile_line), between the tiles
+/// which contain its ends.
+pub fn rasterize_polyline(
+    points: &[Vec2],
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Vec<TilePos> {
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let coords = points
+        .iter()
+        .map(|point| unclamped_tile_coords(&(*point - offset), grid_size, map_type))
+        .collect::<Vec<_>>();
+
+    let mut seen = HashSet::new();
+    let mut tiles = Vec::new();
+    let mut push = |coords: IVec2| {
+        if let Some(tile_pos) = TilePos::from_i32_pair(coords.x, coords.y, map_size)
+            && seen.insert(tile_pos)
+        {
+            tiles.push(tile_pos);
+        }
+    };
+    if let [single] = coords[..] {
+        push(single);
+    }
+    for segment in coords.windows(2) {
+        for coords in tile_line_coords(segment[0], segment[1], map_type) {
+            push(coords);
+        }
+    }
+    tiles
+}
+
+/// Returns the tiles covered by the polygon with the given vertices, which is closed between
+/// the last and the first point. Self-intersecting polygons are filled with the even-odd rule.
+///
+/// A tile is covered if its center lies inside of the polygon; whether tiles on the outline are
+/// covered too depends on the `edge_rule`. The tiles are returned row by row.
+pub fn rasterize_polygon(
+    points: &[Vec2],
+    edge_rule: EdgeRule,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Vec<TilePos> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let (min, max) = candidate_range(
+        points.iter().copied(),
+        offset,
+        map_size,
+        grid_size,
+        map_type,
+    );
+
+    let outline = match edge_rule {
+        EdgeRule::Inclusive => {
+            let mut closed = points.to_vec();
+            closed.push(points[0]);
+            rasterize_polyline(&closed, map_size, grid_size, tile_size, map_type, anchor)
+                .into_iter()
+                .collect()
+        }
+        EdgeRule::Exclusive => HashSet::new(),
+    };
+    // Centers closer to the outline than this count as lying on it.
+    let epsilon = grid_size.x.min(grid_size.y) * 1e-4;
+
+    tiles_in_range(min, max, |tile_pos| {
+        outline.contains(&tile_pos) || {
+            let center = offset + tile_pos.center_in_world_unanchored(grid_size, map_type);
+            match point_in_polygon(center, points, epsilon) {
+                PointLocation::Inside => true,
+                PointLocation::OnOutline => edge_rule == EdgeRule::Inclusive,
+                PointLocation::Outside => false,
+            }
+        }
+    })
+}
+
+/// Returns the tiles covered by the polygon with the given vertices, which is closed between
+/// the last and the first point. Self-intersecting polygons are filled with the even-odd rule.
+///
+/// Unlike [`rasterize_polygon`], which also covers the tiles its outline is drawn through, the
+/// tiles are picked by their area: see [`TileCoverage`]. The tiles are returned row by row.
+pub fn tiles_in_polygon(
+    points: &[Vec2],
+    coverage: TileCoverage,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Vec<TilePos> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let (min, max) = candidate_range(
+        points.iter().copied(),
+        offset,
+        map_size,
+        grid_size,
+        map_type,
+    );
+    let epsilon = grid_size.x.min(grid_size.y) * 1e-4;
+
+    tiles_in_range(min, max, |tile_pos| {
+        let center = offset + tile_pos.center_in_world_unanchored(grid_size, map_type);
+        if !matches!(
+            point_in_polygon(center, points, epsilon),
+            PointLocation::Outside
+        ) {
+            return true;
+        }
+        if coverage == TileCoverage::Center {
+            return false;
+        }
+        let corners = tile_pos
+            .corners_in_world_unanchored(grid_size, map_type)
+            .into_iter()
+            .map(|corner| offset + corner)
+            .collect::<Vec<_>>();
+        let inside = |point: &Vec2, polygon: &[Vec2]| {
+            matches!(
+                point_in_polygon(*point, polygon, epsilon),
+                PointLocation::Inside
+            )
+        };
+        corners.iter().any(|corner| inside(corner, points))
+            || points.iter().any(|point| inside(point, &corners))
+            || edges(&corners).any(|(a, b)| edges(points).any(|(c, d)| segments_cross(a, b, c, d)))
+    })
+}
+
+/// Returns the tiles covered by the axis-aligned ellipse around `center` with the given `radii`,
+/// picked by their area as described by [`TileCoverage`]. The tiles are returned row by row.
+#[allow(clippy::too_many_arguments)]
+pub fn tiles_in_ellipse(
+    center: Vec2,
+    radii: Vec2,
+    coverage: TileCoverage,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Vec<TilePos> {
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let bounds = [
+        center - radii,
+        center + radii,
+        center + Vec2::new(radii.x, -radii.y),
+        center + Vec2::new(-radii.x, radii.y),
+    ];
+    let (min, max) = candidate_range(bounds, offset, map_size, grid_size, map_type);
+    // Positions are mapped so that the ellipse becomes the unit circle.
+    let radii = radii.abs().max(Vec2::splat(f32::EPSILON));
+    let to_unit = |point: Vec2| (point - center) / radii;
+    let epsilon = 1e-4;
+
+    tiles_in_range(min, max, |tile_pos| {
+        let tile_center = offset + tile_pos.center_in_world_unanchored(grid_size, map_type);
+        if to_unit(tile_center).length() <= 1.0 + epsilon {
+            return true;
+        }
+        if coverage == TileCoverage::Center {
+            return false;
+        }
+        let corners = tile_pos
+            .corners_in_world_unanchored(grid_size, map_type)
+            .into_iter()
+            .map(|corner| to_unit(offset + corner))
+            .collect::<Vec<_>>();
+        matches!(
+            point_in_polygon(Vec2::ZERO, &corners, epsilon),
+            PointLocation::Inside
+        ) || edges(&corners).any(|(a, b)| {
+            let edge = b - a;
+            let t = (-a).dot(edge) / edge.length_squared().max(f32::EPSILON);
+            (a + edge * t.clamp(0.0, 1.0)).length() < 1.0 - epsilon
+        })
+    })
+}
+
+/// Returns the tiles covered by the circle around `center` with the given `radius`. See
+/// [`tiles_in_ellipse`].
+#[allow(clippy::too_many_arguments)]
+pub fn tiles_in_circle(
+    center: Vec2,
+    radius: f32,
+    coverage: TileCoverage,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Vec<TilePos> {
+    tiles_in_ellipse(
+        center,
+        Vec2::splat(radius),
+        coverage,
+        map_size,
+        grid_size,
+        tile_size,
+        map_type,
+        anchor,
+    )
+}
+
+/// Finds a range of tile coordinates which surely contains every tile near the given points.
+/// Hexagonal and staggered coordinates are not linear in world space, so a margin is added.
+fn candidate_range(
+    points: impl IntoIterator<Item = Vec2>,
+    offset: Vec2,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+) -> (IVec2, IVec2) {
+    let coords = points
+        .into_iter()
+        .map(|point| unclamped_tile_coords(&(point - offset), grid_size, map_type));
+    let (min, max) = coords.fold((IVec2::MAX, IVec2::MIN), |(min, max), coords| {
+        (min.min(coords), max.max(coords))
+    });
+    let margin = IVec2::splat(2);
+    let min = (min - margin).max(IVec2::ZERO);
+    let max = (max + margin).min(IVec2::new(map_size.x as i32 - 1, map_size.y as i32 - 1));
+    (min, max)
+}
+
+/// Returns the tiles between `min` and `max` which are `covered`, row by row.
+fn tiles_in_range(
+    min: IVec2,
+    max: IVec2,
+    mut covered: impl FnMut(TilePos) -> bool,
+) -> Vec<TilePos> {
+    let mut tiles = Vec::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let tile_pos = TilePos::new(x as u32, y as u32);
+            if covered(tile_pos) {
+                tiles.push(tile_pos);
+            }
+        }
+    }
+    tiles
+}
+
+/// Returns the edges of the polygon with the given vertices.
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, a)| (*a, points[(index + 1) % points.len()]))
+}
+
+/// Returns whether the segments `a`-`b` and `c`-`d` cross each other, rather than only touching.
+fn segments_cross(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, point: Vec2| (q - p).perp_dot(point - p);
+    side(c, d, a) * side(c, d, b) < 0.0 && side(a, b, c) * side(a, b, d) < 0.0
+}
+
+enum PointLocation {
+    Inside,
+    OnOutline,
+    Outside,
+}
+
+fn point_in_polygon(point: Vec2, points: &[Vec2], epsilon: f32) -> PointLocation {
+    let mut inside = false;
+    for (index, a) in points.iter().enumerate() {
+        let b = points[(index + 1) % points.len()];
+        let edge = b - *a;
+        let t = (point - *a).dot(edge) / edge.length_squared().max(f32::EPSILON);
+        if point.distance(*a + edge * t.clamp(0.0, 1.0)) <= epsilon {
+            return PointLocation::OnOutline;
+        }
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    if inside {
+        PointLocation::Inside
+    } else {
+        PointLocation::Outside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_rules_pick_the_outline() {
+        let map_size = TilemapSize::new(8, 8);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let rasterize = |points: &[Vec2], edge_rule| {
+            rasterize_polygon(
+                points,
+                edge_rule,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &TilemapType::Square,
+                &TilemapAnchor::None,
+            )
+        };
+
+        // A square whose sides run through the centers of the tiles 1 to 3.
+        let square = [
+            Vec2::new(16.0, 16.0),
+            Vec2::new(48.0, 16.0),
+            Vec2::new(48.0, 48.0),
+            Vec2::new(16.0, 48.0),
+        ];
+        assert_eq!(rasterize(&square, EdgeRule::Inclusive).len(), 9);
+        assert_eq!(
+            rasterize(&square, EdgeRule::Exclusive),
+            vec![TilePos::new(2, 2)]
+        );
+
+        // A road leaving the map keeps the tiles inside of it.
+        let road = rasterize_polyline(
+            &[
+                Vec2::new(-40.0, 0.0),
+                Vec2::new(40.0, 0.0),
+                Vec2::new(40.0, 32.0),
+            ],
+            &map_size,
+            &grid_size,
+            &tile_size,
+            &TilemapType::Square,
+            &TilemapAnchor::None,
+        );
+        assert_eq!(
+            road,
+            vec![
+                TilePos::new(0, 0),
+                TilePos::new(1, 0),
+                TilePos::new(2, 0),
+                TilePos::new(3, 0),
+                TilePos::new(3, 1),
+                TilePos::new(3, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn coverage_picks_centers_or_overlaps() {
+        let map_size = TilemapSize::new(8, 8);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let in_circle = |radius, coverage| {
+            tiles_in_circle(
+                Vec2::new(64.0, 64.0),
+                radius,
+                coverage,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &TilemapType::Square,
+                &TilemapAnchor::None,
+            )
+        };
+        // The circle reaches the centers of the four tiles next to the one it is centered on,
+        // and overlaps every tile of the 3x3 block around it.
+        assert_eq!(in_circle(16.0, TileCoverage::Center).len(), 5);
+        assert_eq!(in_circle(16.0, TileCoverage::Overlap).len(), 9);
+
+        // A thin triangle passing between the centers only overlaps tiles.
+        let sliver = [
+            Vec2::new(4.0, 4.0),
+            Vec2::new(60.0, 12.0),
+            Vec2::new(60.0, 10.0),
+        ];
+        let in_polygon = |coverage| {
+            tiles_in_polygon(
+                &sliver,
+                coverage,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &TilemapType::Square,
+                &TilemapAnchor::None,
+            )
+        };
+        assert!(in_polygon(TileCoverage::Center).is_empty());
+        assert_eq!(
+            in_polygon(TileCoverage::Overlap),
+            vec![
+                TilePos::new(0, 0),
+                TilePos::new(1, 0),
+                TilePos::new(2, 0),
+                TilePos::new(3, 0),
+                TilePos::new(2, 1),
+                TilePos::new(3, 1),
+                TilePos::new(4, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Casts rays through the tiles of a tilemap, e.g. for line of sight or bullets hitting walls,
+//! without a physics engine.
+//!
+//! Square and isometric maps are traversed with a DDA (digital differential analyzer), hexagonal
+//! maps by stepping through the edge of the current hex the ray leaves it by. Either way, every
+//! tile the ray passes through is visited once, in order, together with the point where the ray
+//! enters it.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::raycast::cast_ray;
+//! const WALL: TileTextureIndex = TileTextureIndex(3);
+//!
+//! /// Returns where a shot from `origin` towards `direction` hits a wall.
+//! fn shoot(
+//!     origin: Vec2,
+//!     direction: Vec2,
+//!     tilemap: (&TilemapSize, &TilemapGridSize, &TilemapTileSize, &TilemapType, &TilemapAnchor),
+//!     tile_storage: &TileStorage,
+//!     tiles: &Query<&TileTextureIndex>,
+//! ) -> Option<Vec2> {
+//!     let (map_size, grid_size, tile_size, map_type, anchor) = tilemap;
+//!     cast_ray(origin, direction, 500.0, map_size, grid_size, tile_size, map_type, anchor)
+//!         .find(|(tile_pos, _)| {
+//!             tile_storage
+//!                 .get(tile_pos)
+//!                 .and_then(|tile_entity| tiles.get(tile_entity).ok())
+//!                 .is_some_and(|texture_index| *texture_index == WALL)
+//!         })
+//!         .map(|(_, hit_point)| hit_point)
+//! }
+//! ```
+
+use bevy::math::{IVec2, Mat2, Rect, Vec2};
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::hex_grid::axial::{AxialPos, COL_BASIS, ROW_BASIS};
+use crate::helpers::hex_grid::neighbors::HEX_OFFSETS;
+use crate::helpers::square_grid::diamond::{DiamondPos, INV_DIAMOND_BASIS};
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::{
+    HexCoordSystem, IsoCoordSystem, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType,
+};
+use crate::tiles::TilePos;
+
+/// Returns the tiles on the map which the ray from `origin` in `direction` passes through within
+/// `max_distance`, in order, each with the point where the ray enters it. The first tile is
+/// entered at `origin`, or where the ray enters the map if `origin` lies outside of it.
+///
+/// Positions are in the local space of the tilemap (see [`TilePos::from_world_pos`]), and
+/// `max_distance` may be [`f32::INFINITY`] to follow the ray until it leaves the map.
+#[allow(clippy::too_many_arguments)]
+pub fn cast_ray(
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> impl Iterator<Item = (TilePos, Vec2)> + use<> {
+    let direction = direction.normalize_or_zero();
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let (map_size, map_type) = (*map_size, *map_type);
+
+    // The ray is clipped to the map, so rays leaving it stop, and rays starting outside of it
+    // skip to where they enter it.
+    let mut traversal = map_bounds(&map_size, grid_size, &map_type)
+        .filter(|_| direction != Vec2::ZERO)
+        .and_then(|bounds| clip_ray(origin - offset, direction, bounds))
+        .map(|(start, end)| (start.max(0.0), end.min(max_distance)))
+        .filter(|(start, end)| start <= end)
+        .map(|(start, end)| {
+            Traversal::new(
+                origin - offset + direction * start,
+                direction,
+                start,
+                end,
+                grid_size,
+                &map_type,
+            )
+        });
+
+    std::iter::from_fn(move || {
+        let traversal = traversal.as_mut()?;
+        loop {
+            let (coords, distance) = traversal.next()?;
+            if let Some(tile_pos) = tile_pos_of(coords, &map_type, &map_size) {
+                return Some((tile_pos, origin + direction * distance));
+            }
+        }
+    })
+}
+
+/// Returns the tile at the coordinates of a traversal, if it lies on the map.
+fn tile_pos_of(coords: IVec2, map_type: &TilemapType, map_size: &TilemapSize) -> Option<TilePos> {
+    match map_type {
+        TilemapType::Hexagon(hex_coord_sys) => AxialPos::new(coords.x, coords.y)
+            .as_tile_pos_given_coord_system_and_map_size(*hex_coord_sys, map_size),
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            StaggeredPos::from(DiamondPos::new(coords.x, coords.y)).as_tile_pos(map_size)
+        }
+        _ => TilePos::from_i32_pair(coords.x, coords.y, map_size),
+    }
+}
+
+/// Returns a rectangle around every tile of the map, ignoring the anchor.
+fn map_bounds(
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+) -> Option<Rect> {
+    if map_size.x == 0 || map_size.y == 0 {
+        return None;
+    }
+    let (x, y) = (map_size.x - 1, map_size.y - 1);
+    let bounds = [(0, 0), (x, 0), (0, y), (x, y)]
+        .into_iter()
+        .flat_map(|(x, y)| TilePos::new(x, y).corners_in_world_unanchored(grid_size, map_type))
+        .fold(Rect::EMPTY, |bounds, corner| bounds.union_point(corner));
+    // Hexagonal and staggered rows and columns zigzag, so the corner tiles need not be the
+    // outermost ones.
+    Some(bounds.inflate(grid_size.x.max(grid_size.y)))
+}
+
+/// Returns the distances along the ray at which it enters and leaves `bounds`, if it hits them.
+fn clip_ray(origin: Vec2, direction: Vec2, bounds: Rect) -> Option<(f32, f32)> {
+    let inverse = direction.recip();
+    let a = (bounds.min - origin) * inverse;
+    let b = (bounds.max - origin) * inverse;
+    // Axes the ray runs parallel to give NaN, which `min` and `max` ignore.
+    let start = a.x.min(b.x).max(a.y.min(b.y));
+    let end = a.x.max(b.x).min(a.y.max(b.y));
+    (start <= end && end >= 0.0).then_some((start, end))
+}
+
+/// The state of a ray passing through the cells of a grid.
+enum Traversal {
+    /// Square and isometric grids, whose cells are squares after a linear transform.
+    Grid {
+        cell: IVec2,
+        step: IVec2,
+        /// The distance along the ray at which it crosses the next cell border on each axis.
+        next_border: Vec2,
+        /// The distance along the ray between two cell borders on each axis.
+        border_spacing: Vec2,
+        distance: f32,
+        end: f32,
+    },
+    /// Hexagonal grids, in a space where the hexes are regular and neighbors one unit apart.
+    Hex {
+        cell: AxialPos,
+        basis: Mat2,
+        /// The start of the ray and the distance it travels per unit of world distance.
+        start: Vec2,
+        velocity: Vec2,
+        start_distance: f32,
+        distance: f32,
+        end: f32,
+    },
+}
+
+impl Traversal {
+    fn new(
+        start: Vec2,
+        direction: Vec2,
+        distance: f32,
+        end: f32,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Self {
+        let scale = Mat2::from_diagonal(Vec2::ONE / Vec2::from(grid_size));
+        match map_type {
+            TilemapType::Hexagon(hex_coord_sys) => {
+                let (cell, basis, to_hex_space) = match hex_coord_sys {
+                    HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => (
+                        AxialPos::from_world_pos_row(&start, grid_size),
+                        ROW_BASIS,
+                        Mat2::from_diagonal(Vec2::new(1.0, 1.0 / ROW_BASIS.y_axis.y)) * scale,
+                    ),
+                    HexCoordSystem::Column
+                    | HexCoordSystem::ColumnEven
+                    | HexCoordSystem::ColumnOdd => (
+                        AxialPos::from_world_pos_col(&start, grid_size),
+                        COL_BASIS,
+                        Mat2::from_diagonal(Vec2::new(1.0 / COL_BASIS.x_axis.x, 1.0)) * scale,
+                    ),
+                };
+                Traversal::Hex {
+                    cell,
+                    basis,
+                    start: to_hex_space * start,
+                    velocity: to_hex_space * direction,
+                    start_distance: distance,
+                    distance,
+                    end,
+                }
+            }
+            _ => {
+                let to_grid_space = match map_type {
+                    TilemapType::Square => scale,
+                    _ => INV_DIAMOND_BASIS * scale,
+                };
+                // Cells span from their coordinates to the next ones in this space.
+                let start = to_grid_space * start + 0.5;
+                let velocity = to_grid_space * direction;
+                let cell = start.floor().as_ivec2();
+                let step = velocity.signum().as_ivec2();
+                let border = |start: f32, cell: i32, velocity: f32| {
+                    if velocity > 0.0 {
+                        (cell as f32 + 1.0 - start) / velocity
+                    } else if velocity < 0.0 {
+                        (start - cell as f32) / -velocity
+                    } else {
+                        f32::INFINITY
+                    }
+                };
+                Traversal::Grid {
+                    cell,
+                    step: IVec2::new(
+                        if velocity.x == 0.0 { 0 } else { step.x },
+                        if velocity.y == 0.0 { 0 } else { step.y },
+                    ),
+                    next_border: distance
+                        + Vec2::new(
+                            border(start.x, cell.x, velocity.x),
+                            border(start.y, cell.y, velocity.y),
+                        ),
+                    border_spacing: velocity.abs().recip(),
+                    distance,
+                    end,
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Traversal {
+    /// The coordinates of a cell, and the distance along the ray at which it is entered.
+    type Item = (IVec2, f32);
+
+    fn next(&mut self) -> Option<(IVec2, f32)> {
+        match self {
+            Traversal::Grid {
+                cell,
+                step,
+                next_border,
+                border_spacing,
+                distance,
+                end,
+            } => {
+                if *distance > *end {
+                    return None;
+                }
+                let item = (*cell, *distance);
+                if next_border.x < next_border.y {
+                    cell.x += step.x;
+                    *distance = next_border.x;
+                    next_border.x += border_spacing.x;
+                } else {
+                    cell.y += step.y;
+                    *distance = next_border.y;
+                    next_border.y += border_spacing.y;
+                }
+                Some(item)
+            }
+            Traversal::Hex {
+                cell,
+                basis,
+                start,
+                velocity,
+                start_distance,
+                distance,
+                end,
+            } => {
+                if *distance > *end {
+                    return None;
+                }
+                let item = (IVec2::new(cell.q, cell.r), *distance);
+                // The ray leaves the hex through the first of the borders towards the neighbors
+                // it moves towards, which lie halfway to the neighbors.
+                let center = *basis * Vec2::new(cell.q as f32, cell.r as f32);
+                let relative_start = *start - center;
+                let exit = HEX_OFFSETS
+                    .iter()
+                    .filter_map(|offset| {
+                        let normal = *basis * Vec2::new(offset.q as f32, offset.r as f32);
+                        let speed = velocity.dot(normal);
+                        (speed > 0.0).then(|| ((0.5 - relative_start.dot(normal)) / speed, *offset))
+                    })
+                    .min_by(|(a, _), (b, _)| a.total_cmp(b));
+                match exit {
+                    Some((exit_distance, offset)) => {
+                        *cell = *cell + offset;
+                        *distance = (*start_distance + exit_distance).max(*distance);
+                    }
+                    None => *distance = f32::INFINITY,
+                }
+                Some(item)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_visit_the_tiles_they_pass_through() {
+        let map_size = TilemapSize::new(12, 12);
+        let grid_size = TilemapGridSize::new(16.0, 12.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let anchor = TilemapAnchor::Center;
+        for map_type in [
+            TilemapType::Square,
+            TilemapType::Isometric(IsoCoordSystem::Diamond),
+            TilemapType::Isometric(IsoCoordSystem::Staggered),
+            TilemapType::Hexagon(HexCoordSystem::Row),
+            TilemapType::Hexagon(HexCoordSystem::RowOdd),
+            TilemapType::Hexagon(HexCoordSystem::ColumnEven),
+        ] {
+            let center = |tile_pos: TilePos| {
+                tile_pos.center_in_world(&map_size, &grid_size, &tile_size, &map_type, &anchor)
+            };
+            let tile_at = |point: Vec2| {
+                TilePos::from_world_pos(
+                    &point, &map_size, &grid_size, &tile_size, &map_type, &anchor,
+                )
+            };
+            let origin = center(TilePos::new(2, 3)) + Vec2::new(1.0, -2.0);
+            let direction = center(TilePos::new(9, 7)) - origin;
+            let hits = cast_ray(
+                origin,
+                direction,
+                direction.length(),
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &map_type,
+                &anchor,
+            )
+            .collect::<Vec<_>>();
+            assert_eq!(hits[0], (TilePos::new(2, 3), origin), "{map_type:?}");
+            assert_eq!(hits.last().unwrap().0, TilePos::new(9, 7), "{map_type:?}");
+            // The ray is inside of each tile between the point where it enters it and the point
+            // where it enters the next one.
+            for pair in hits.windows(2) {
+                let midpoint = (pair[0].1 + pair[1].1) / 2.0;
+                assert_eq!(tile_at(midpoint), Some(pair[0].0), "{map_type:?}");
+            }
+
+            // Rays from outside of the map start where they enter it.
+            let outside = origin - direction * 10.0;
+            let (first, entry) = cast_ray(
+                outside,
+                direction,
+                f32::INFINITY,
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &map_type,
+                &anchor,
+            )
+            .next()
+            .unwrap();
+            assert_eq!(
+                tile_at(entry + direction.normalize()),
+                Some(first),
+                "{map_type:?}"
+            );
+        }
+    }
+}
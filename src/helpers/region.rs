@@ -0,0 +1,158 @@
+//! Helpers for regions of tiles, e.g. to place the names or icons of territories over a map.
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::hex_grid::neighbors::HexNeighbors;
+use crate::helpers::square_grid::neighbors::Neighbors;
+use crate::map::{IsoCoordSystem, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::TilePos;
+use bevy::math::Vec2;
+use bevy::platform::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
+
+/// Returns the centroid of a region, i.e. the average of the world space centers of its tiles.
+///
+/// Returns `None` if `tiles` is empty. Note that the centroid of a non-convex region (e.g. a
+/// crescent) may lie outside of it; use [`region_label_position`] to place labels.
+pub fn region_centroid(
+    tiles: &[TilePos],
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Option<Vec2> {
+    if tiles.is_empty() {
+        return None;
+    }
+    let sum: Vec2 = tiles
+        .iter()
+        .map(|tile_pos| tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor))
+        .sum();
+    Some(sum / tiles.len() as f32)
+}
+
+/// Returns the world space position where a label for a region is best placed.
+///
+/// This approximates the region's pole of inaccessibility: the center of the tile which is
+/// furthest away (in steps between neighboring tiles) from the region's border. Ties are broken
+/// in favor of the tile closest to the [centroid](region_centroid). Tiles on the edge of the map
+/// count as lying on the border.
+///
+/// Returns `None` if `tiles` is empty.
+pub fn region_label_position(
+    tiles: &[TilePos],
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> Option<Vec2> {
+    let centroid = region_centroid(tiles, map_size, grid_size, tile_size, map_type, anchor)?;
+    let region: HashSet<TilePos> = tiles.iter().copied().collect();
+
+    // Breadth first search inwards, starting from all tiles on the border.
+    let mut depths: HashMap<TilePos, u32> = HashMap::default();
+    let mut queue = VecDeque::new();
+    for tile_pos in region.iter() {
+        let neighbors = region_neighbors(tile_pos, map_size, map_type);
+        if neighbors.len() < neighbor_count(map_type)
+            || neighbors.iter().any(|neighbor| !region.contains(neighbor))
+        {
+            depths.insert(*tile_pos, 0);
+            queue.push_back(*tile_pos);
+        }
+    }
+    while let Some(tile_pos) = queue.pop_front() {
+        let depth = depths[&tile_pos];
+        for neighbor in region_neighbors(&tile_pos, map_size, map_type) {
+            if region.contains(&neighbor) && !depths.contains_key(&neighbor) {
+                depths.insert(neighbor, depth + 1);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    depths
+        .into_iter()
+        .map(|(tile_pos, depth)| {
+            let center = tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+            (depth, center.distance_squared(centroid), center)
+        })
+        .max_by(|(depth_a, distance_a, _), (depth_b, distance_b, _)| {
+            depth_a.cmp(depth_b).then(distance_b.total_cmp(distance_a))
+        })
+        .map(|(_, _, center)| center)
+}
+
+/// The number of tiles sharing an edge with a tile, for the given map type.
+fn neighbor_count(map_type: &TilemapType) -> usize {
+    match map_type {
+        TilemapType::Hexagon(_) => 6,
+        TilemapType::Square | TilemapType::Isometric(_) => 4,
+    }
+}
+
+/// The tiles sharing an edge with `tile_pos`, which lie on the map.
+pub(crate) fn region_neighbors(
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> Vec<TilePos> {
+    match map_type {
+        TilemapType::Hexagon(hex_coord_sys) => {
+            HexNeighbors::get_neighboring_positions(tile_pos, map_size, hex_coord_sys)
+                .iter()
+                .copied()
+                .collect()
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            Neighbors::get_staggered_neighboring_positions(tile_pos, map_size, false)
+                .iter()
+                .copied()
+                .collect()
+        }
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            Neighbors::get_square_neighboring_positions(tile_pos, map_size, false)
+                .iter()
+                .copied()
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_is_placed_inside_non_convex_region() {
+        let map_size = TilemapSize { x: 10, y: 10 };
+        let grid_size = TilemapGridSize { x: 1.0, y: 1.0 };
+        let tile_size = TilemapTileSize { x: 1.0, y: 1.0 };
+        let map_type = TilemapType::Square;
+        let anchor = TilemapAnchor::None;
+
+        // An "L", whose centroid lies outside of it.
+        let mut tiles = Vec::new();
+        for y in 1..9 {
+            for x in 1..3 {
+                tiles.push(TilePos { x, y });
+            }
+        }
+        for y in 1..3 {
+            for x in 3..9 {
+                tiles.push(TilePos { x, y });
+            }
+        }
+
+        let label = region_label_position(
+            &tiles, &map_size, &grid_size, &tile_size, &map_type, &anchor,
+        )
+        .unwrap();
+        let label_tile = TilePos::from_world_pos(
+            &label, &map_size, &grid_size, &tile_size, &map_type, &anchor,
+        )
+        .unwrap();
+        assert!(tiles.contains(&label_tile));
+    }
+}
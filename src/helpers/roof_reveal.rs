@@ -0,0 +1,378 @@
+//! Reveals the roofs which tracked entities are under, e.g. the roof of the building the player
+//! entered, by fading the roof tiles out and back in once the player left.
+//!
+//! A roof is a connected region of [`TileOccluder`] tiles. Add the [`RoofRevealPlugin`], a
+//! [`RoofReveal`] to the tilemap holding the roofs and a [`RoofRevealTracker`] to the entities
+//! which reveal them. The whole roof over a tracked entity fades out, by animating the alpha of
+//! the [`TileColor`] of its tiles, and a [`RoofRevealChanged`] message is sent when a roof is
+//! revealed or hidden again.
+//!
+//! Unlike [`TilemapOcclusionReveal`](crate::map::TilemapOcclusionReveal), which fades occluders
+//! around a point in the shader, it follows the shape of the roof, whatever its size.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::roof_reveal::*;
+//! fn spawn_player(mut commands: Commands, roofs: Entity) {
+//!     commands.entity(roofs).insert(RoofReveal::default());
+//!     commands.spawn((
+//!         Sprite::default(),
+//!         Transform::from_xyz(64.0, 32.0, 2.0),
+//!         RoofRevealTracker::new(roofs),
+//!     ));
+//! }
+//!
+//! fn report_roofs(mut changes: MessageReader<RoofRevealChanged>) {
+//!     for change in changes.read() {
+//!         info!("{} roof tiles revealed: {}", change.tiles.len(), change.revealed);
+//!     }
+//! }
+//! ```
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::region::region_neighbors;
+use crate::map::{TilemapGridSize, TilemapId, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::{TileColor, TileOccluder, TilePos, TileStorage};
+
+/// Adds the systems which reveal roofs.
+pub struct RoofRevealPlugin;
+
+impl Plugin for RoofRevealPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<RoofRevealChanged>()
+            .add_systems(Update, (reveal_roofs, fade_roofs).chain());
+    }
+}
+
+/// Reveals the roofs of a tilemap which [`RoofRevealTracker`]s are under.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Clone, Debug)]
+pub struct RoofReveal {
+    /// The alpha the tiles of revealed roofs are faded to, relative to their own: `0.0` hides
+    /// them, `0.3` leaves a hint of the roof.
+    pub revealed_alpha: f32,
+    /// How long fading a roof out or back in takes, in seconds.
+    pub fade_duration: f32,
+    /// The revealed roofs, by their first tile.
+    revealed: HashMap<TilePos, Vec<TilePos>>,
+}
+
+impl Default for RoofReveal {
+    fn default() -> Self {
+        Self {
+            revealed_alpha: 0.0,
+            fade_duration: 0.25,
+            revealed: HashMap::default(),
+        }
+    }
+}
+
+impl RoofReveal {
+    /// Returns `true` if the tile at `tile_pos` belongs to a revealed roof.
+    pub fn is_revealed(&self, tile_pos: &TilePos) -> bool {
+        self.revealed.values().any(|tiles| tiles.contains(tile_pos))
+    }
+}
+
+/// Reveals the roof of a tilemap with a [`RoofReveal`] over the entity.
+///
+/// The tile the entity is on is its [`TilePos`], if it has one, e.g. on a tile based map, and is
+/// found from its [`GlobalTransform`] otherwise.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoofRevealTracker {
+    /// The tilemap whose roofs are revealed.
+    pub tilemap: Entity,
+}
+
+impl RoofRevealTracker {
+    pub fn new(tilemap: Entity) -> Self {
+        Self { tilemap }
+    }
+}
+
+/// Sent when a roof is revealed, or hidden again once no tracked entity is under it.
+#[derive(Message, Clone, Debug, PartialEq, Eq)]
+pub struct RoofRevealChanged {
+    /// The tilemap of the roof.
+    pub tilemap: Entity,
+    /// The tiles of the roof.
+    pub tiles: Vec<TilePos>,
+    /// Whether the roof was revealed or hidden.
+    pub revealed: bool,
+}
+
+/// Added to the tiles of roofs while they are faded, to restore their alpha afterwards.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct RoofFade {
+    /// The alpha of the tile's color before it was faded.
+    pub original_alpha: f32,
+    /// How far the tile is faded out, from `0.0` to `1.0`.
+    pub progress: f32,
+    /// Whether the tile is fading out rather than back in.
+    pub revealed: bool,
+}
+
+/// Returns the connected region of occluders containing `start`, sorted by position.
+fn roof_at(
+    start: TilePos,
+    tile_storage: &TileStorage,
+    map_type: &TilemapType,
+    occluders: &Query<(), With<TileOccluder>>,
+) -> Vec<TilePos> {
+    let is_occluder = |tile_pos: &TilePos| {
+        tile_storage
+            .checked_get(tile_pos)
+            .is_some_and(|tile_entity| occluders.contains(tile_entity))
+    };
+    if !is_occluder(&start) {
+        return Vec::new();
+    }
+
+    let mut roof = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(tile_pos) = stack.pop() {
+        for neighbor in region_neighbors(&tile_pos, &tile_storage.size, map_type) {
+            if is_occluder(&neighbor) && roof.insert(neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+    let mut roof: Vec<TilePos> = roof.into_iter().collect();
+    roof.sort_by_key(|tile_pos| (tile_pos.y, tile_pos.x));
+    roof
+}
+
+#[allow(clippy::type_complexity)]
+fn reveal_roofs(
+    mut commands: Commands,
+    mut tilemaps: Query<(
+        Entity,
+        &mut RoofReveal,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &GlobalTransform,
+    )>,
+    trackers: Query<(
+        &RoofRevealTracker,
+        Option<&TilePos>,
+        Option<&GlobalTransform>,
+    )>,
+    occluders: Query<(), With<TileOccluder>>,
+    mut fades: Query<&mut RoofFade>,
+    colors: Query<&TileColor>,
+    mut changes: MessageWriter<RoofRevealChanged>,
+) {
+    for (
+        tilemap,
+        mut reveal,
+        tile_storage,
+        map_size,
+        grid_size,
+        tile_size,
+        map_type,
+        anchor,
+        map_transform,
+    ) in tilemaps.iter_mut()
+    {
+        let mut revealed: HashMap<TilePos, Vec<TilePos>> = HashMap::default();
+        for (tracker, tile_pos, transform) in trackers.iter() {
+            if tracker.tilemap != tilemap {
+                continue;
+            }
+            let tile_pos = tile_pos.copied().or_else(|| {
+                // Tile helpers work in the local space of the tilemap.
+                let local_pos = map_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(transform?.translation())
+                    .truncate();
+                TilePos::from_world_pos(
+                    &local_pos, map_size, grid_size, tile_size, map_type, anchor,
+                )
+            });
+            let Some(tile_pos) = tile_pos else {
+                continue;
+            };
+            if revealed.values().any(|roof| roof.contains(&tile_pos)) {
+                continue;
+            }
+            let roof = roof_at(tile_pos, tile_storage, map_type, &occluders);
+            if let Some(first) = roof.first() {
+                revealed.insert(*first, roof);
+            }
+        }
+
+        if revealed.keys().collect::<HashSet<_>>() == reveal.revealed.keys().collect() {
+            continue;
+        }
+
+        for (first, tiles) in reveal.revealed.iter() {
+            if revealed.contains_key(first) {
+                continue;
+            }
+            for tile_entity in tiles
+                .iter()
+                .filter_map(|tile_pos| tile_storage.checked_get(tile_pos))
+            {
+                if let Ok(mut fade) = fades.get_mut(tile_entity) {
+                    fade.revealed = false;
+                }
+            }
+            changes.write(RoofRevealChanged {
+                tilemap,
+                tiles: tiles.clone(),
+                revealed: false,
+            });
+        }
+
+        for (first, tiles) in revealed.iter() {
+            if reveal.revealed.contains_key(first) {
+                continue;
+            }
+            for tile_entity in tiles
+                .iter()
+                .filter_map(|tile_pos| tile_storage.checked_get(tile_pos))
+            {
+                if let Ok(mut fade) = fades.get_mut(tile_entity) {
+                    fade.revealed = true;
+                } else if let Ok(color) = colors.get(tile_entity) {
+                    commands.entity(tile_entity).insert(RoofFade {
+                        original_alpha: color.0.alpha(),
+                        progress: 0.0,
+                        revealed: true,
+                    });
+                }
+            }
+            changes.write(RoofRevealChanged {
+                tilemap,
+                tiles: tiles.clone(),
+                revealed: true,
+            });
+        }
+
+        reveal.revealed = revealed;
+    }
+}
+
+fn fade_roofs(
+    mut commands: Commands,
+    time: Res<Time>,
+    reveals: Query<&RoofReveal>,
+    mut tiles: Query<(Entity, &TilemapId, &mut TileColor, &mut RoofFade)>,
+) {
+    for (tile_entity, tilemap_id, mut color, mut fade) in tiles.iter_mut() {
+        let Ok(reveal) = reveals.get(tilemap_id.0) else {
+            continue;
+        };
+        let step = if reveal.fade_duration > 0.0 {
+            time.delta_secs() / reveal.fade_duration
+        } else {
+            1.0
+        };
+        fade.progress = if fade.revealed {
+            (fade.progress + step).min(1.0)
+        } else {
+            (fade.progress - step).max(0.0)
+        };
+
+        let alpha = fade.original_alpha * (1.0 + (reveal.revealed_alpha - 1.0) * fade.progress);
+        if color.0.alpha() != alpha {
+            color.0.set_alpha(alpha);
+        }
+        if !fade.revealed && fade.progress <= 0.0 {
+            commands.entity(tile_entity).remove::<RoofFade>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileBundle;
+    use std::time::Duration;
+
+    #[test]
+    fn roofs_fade_out_and_back_in() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Messages<RoofRevealChanged>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems((reveal_roofs, fade_roofs).chain());
+
+        // A roof over the two tiles on the right of a row of three.
+        let map_size = TilemapSize::new(3, 1);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        let tiles = [0, 1, 2].map(|x| {
+            let tile_pos = TilePos::new(x, 0);
+            let mut tile = world.spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(tilemap),
+                ..Default::default()
+            });
+            if x > 0 {
+                tile.insert(TileOccluder);
+            }
+            let tile_entity = tile.id();
+            tile_storage.set(&tile_pos, tile_entity);
+            tile_entity
+        });
+        world.entity_mut(tilemap).insert((
+            RoofReveal {
+                fade_duration: 1.0,
+                ..Default::default()
+            },
+            tile_storage,
+            map_size,
+            TilemapGridSize::new(16.0, 16.0),
+            TilemapTileSize::new(16.0, 16.0),
+            TilemapType::Square,
+            TilemapAnchor::None,
+            GlobalTransform::IDENTITY,
+        ));
+        let player = world
+            .spawn((RoofRevealTracker::new(tilemap), TilePos::new(2, 0)))
+            .id();
+        let alpha = |world: &World, tile: Entity| world.get::<TileColor>(tile).unwrap().0.alpha();
+        let mut step = |world: &mut World| {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(600));
+            schedule.run(world);
+            world
+                .resource_mut::<Messages<RoofRevealChanged>>()
+                .drain()
+                .collect::<Vec<_>>()
+        };
+
+        let changes = step(&mut world);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].revealed);
+        assert_eq!(
+            changes[0].tiles,
+            vec![TilePos::new(1, 0), TilePos::new(2, 0)]
+        );
+        step(&mut world);
+        step(&mut world);
+        assert_eq!(alpha(&world, tiles[0]), 1.0);
+        assert_eq!(alpha(&world, tiles[1]), 0.0);
+        assert_eq!(alpha(&world, tiles[2]), 0.0);
+
+        // Leaving the building fades the roof back in.
+        *world.get_mut::<TilePos>(player).unwrap() = TilePos::new(0, 0);
+        let changes = step(&mut world);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].revealed);
+        step(&mut world);
+        assert_eq!(alpha(&world, tiles[1]), 1.0);
+        assert!(world.get::<RoofFade>(tiles[1]).is_none());
+    }
+}
@@ -1 +1,274 @@
+//! Helpers for selecting tiles, e.g. with a drag rectangle in an editor.
 
+use crate::anchor::TilemapAnchor;
+use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::hex_grid::offset::{ColEvenPos, ColOddPos, RowEvenPos, RowOddPos};
+use crate::helpers::square_grid::diamond::DiamondPos;
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::{
+    HexCoordSystem, IsoCoordSystem, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType,
+};
+use crate::tiles::TilePos;
+use bevy::math::{IVec2, Rect, Vec2};
+
+/// A rectangular region of tiles, defined by an `origin` in [`TilePos`], and a `size` in tiles
+/// ([`TilemapSize`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRect {
+    pub origin: TilePos,
+    pub size: TilemapSize,
+}
+
+impl TileRect {
+    pub const fn new(origin: TilePos, size: TilemapSize) -> Self {
+        Self { origin, size }
+    }
+
+    /// Creates the smallest rectangle containing both `a` and `b`.
+    pub fn from_corners(a: TilePos, b: TilePos) -> Self {
+        let origin = TilePos::new(a.x.min(b.x), a.y.min(b.y));
+        Self {
+            origin,
+            // Rectangles spanning every `u32` coordinate are one tile short, as their size does
+            // not fit.
+            size: TilemapSize {
+                x: (a.x.max(b.x) - origin.x).saturating_add(1),
+                y: (a.y.max(b.y) - origin.y).saturating_add(1),
+            },
+        }
+    }
+
+    /// Returns `true` if the rectangle contains no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.size.x == 0 || self.size.y == 0
+    }
+
+    /// Returns `true` if `tile_pos` lies within the rectangle.
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        tile_pos.x >= self.origin.x
+            && tile_pos.y >= self.origin.y
+            && tile_pos.x - self.origin.x < self.size.x
+            && tile_pos.y - self.origin.y < self.size.y
+    }
+
+    /// Returns an iterator over all tile positions in the rectangle, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = TilePos> + use<> {
+        let TileRect { origin, size } = *self;
+        (0..size.y)
+            .flat_map(move |y| (0..size.x).map(move |x| TilePos::new(origin.x + x, origin.y + y)))
+    }
+}
+
+/// The tiles covered by a selection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TileSelection {
+    /// On square maps, a world space rectangle always covers a rectangle of tiles.
+    Rect(TileRect),
+    /// On hexagonal and isometric maps, the covered tiles do not form a [`TileRect`].
+    Tiles(Vec<TilePos>),
+}
+
+impl TileSelection {
+    /// Returns `true` if no tiles are selected.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TileSelection::Rect(rect) => rect.is_empty(),
+            TileSelection::Tiles(tiles) => tiles.is_empty(),
+        }
+    }
+
+    /// Returns `true` if `tile_pos` is selected.
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        match self {
+            TileSelection::Rect(rect) => rect.contains(tile_pos),
+            TileSelection::Tiles(tiles) => tiles.contains(tile_pos),
+        }
+    }
+
+    /// Returns an iterator over all selected tile positions.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = TilePos> + '_> {
+        match self {
+            TileSelection::Rect(rect) => Box::new(rect.iter()),
+            TileSelection::Tiles(tiles) => Box::new(tiles.iter().copied()),
+        }
+    }
+}
+
+/// Returns the tiles covered by dragging a rectangle from `start` to `end`, both given in the
+/// tilemap's local space (see [`TilePos::from_world_pos`]).
+///
+/// A tile is covered if its center lies within the rectangle. Tiles outside of the tilemap are
+/// never selected.
+pub fn select_tiles_in_world_rect(
+    start: &Vec2,
+    end: &Vec2,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+) -> TileSelection {
+    let rect = Rect::from_corners(*start, *end);
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+
+    // Find a range of tile coordinates which surely contains every covered tile. Hexagonal and
+    // staggered coordinates are not linear in world space, so a margin is added.
+    let corners = [
+        rect.min,
+        rect.max,
+        Vec2::new(rect.min.x, rect.max.y),
+        Vec2::new(rect.max.x, rect.min.y),
+    ]
+    .map(|corner| unclamped_tile_coords(&(corner - offset), grid_size, map_type));
+    let margin = IVec2::splat(2);
+    let min = (corners.iter().fold(IVec2::MAX, |min, c| min.min(*c)) - margin).max(IVec2::ZERO);
+    let max = (corners.iter().fold(IVec2::MIN, |max, c| max.max(*c)) + margin)
+        .min(IVec2::new(map_size.x as i32 - 1, map_size.y as i32 - 1));
+
+    let mut tiles = Vec::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let tile_pos = TilePos::new(x as u32, y as u32);
+            let center = tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+            if rect.contains(center) {
+                tiles.push(tile_pos);
+            }
+        }
+    }
+
+    if *map_type == TilemapType::Square {
+        let rect = match (tiles.first(), tiles.last()) {
+            (Some(first), Some(last)) => TileRect::from_corners(*first, *last),
+            _ => TileRect::default(),
+        };
+        TileSelection::Rect(rect)
+    } else {
+        TileSelection::Tiles(tiles)
+    }
+}
+
+/// Returns the coordinates of the tile containing `pos` (relative to the tilemap's unanchored
+/// origin), even if they lie outside of the tilemap.
+pub(crate) fn unclamped_tile_coords(
+    pos: &Vec2,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+) -> IVec2 {
+    match map_type {
+        TilemapType::Square => ((*pos / Vec2::from(grid_size)) + 0.5).floor().as_ivec2(),
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let (q, r) = match hex_coord_sys {
+                HexCoordSystem::RowEven => {
+                    let p = RowEvenPos::from_world_pos(pos, grid_size);
+                    (p.q, p.r)
+                }
+                HexCoordSystem::RowOdd => {
+                    let p = RowOddPos::from_world_pos(pos, grid_size);
+                    (p.q, p.r)
+                }
+                HexCoordSystem::ColumnEven => {
+                    let p = ColEvenPos::from_world_pos(pos, grid_size);
+                    (p.q, p.r)
+                }
+                HexCoordSystem::ColumnOdd => {
+                    let p = ColOddPos::from_world_pos(pos, grid_size);
+                    (p.q, p.r)
+                }
+                HexCoordSystem::Row => {
+                    let p = AxialPos::from_world_pos_row(pos, grid_size);
+                    (p.q, p.r)
+                }
+                HexCoordSystem::Column => {
+                    let p = AxialPos::from_world_pos_col(pos, grid_size);
+                    (p.q, p.r)
+                }
+            };
+            IVec2::new(q, r)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            let p = DiamondPos::from_world_pos(pos, grid_size);
+            IVec2::new(p.x, p.y)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let p = StaggeredPos::from_world_pos(pos, grid_size);
+            IVec2::new(p.x, p.y)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_rects_span_their_corners() {
+        let rect = TileRect::from_corners(TilePos::new(5, 2), TilePos::new(1, 7));
+        assert_eq!(
+            rect,
+            TileRect::new(TilePos::new(1, 2), TilemapSize::new(5, 6))
+        );
+        assert!(rect.contains(&TilePos::new(5, 7)));
+        assert!(!rect.contains(&TilePos::new(0, 7)));
+        assert_eq!(rect.iter().count(), 30);
+        assert_eq!(rect.iter().next(), Some(TilePos::new(1, 2)));
+
+        let whole = TileRect::from_corners(TilePos::new(0, 0), TilePos::new(u32::MAX, 0));
+        assert_eq!(whole.size, TilemapSize::new(u32::MAX, 1));
+    }
+
+    #[test]
+    fn rectangles_select_the_tiles_whose_center_they_cover() {
+        let map_size = TilemapSize::new(10, 10);
+        let grid_size = TilemapGridSize::new(32.0, 28.0);
+        let tile_size = TilemapTileSize::new(32.0, 28.0);
+
+        // On square maps, the selection is a rectangle of tiles.
+        let selection = select_tiles_in_world_rect(
+            &Vec2::new(100.0, 20.0),
+            &Vec2::new(20.0, 70.0),
+            &map_size,
+            &grid_size,
+            &tile_size,
+            &TilemapType::Square,
+            &TilemapAnchor::None,
+        );
+        assert_eq!(
+            selection,
+            TileSelection::Rect(TileRect::new(TilePos::new(1, 1), TilemapSize::new(3, 2)))
+        );
+
+        let (start, end) = (Vec2::new(-70.0, 50.0), Vec2::new(40.0, -30.0));
+        let rect = Rect::from_corners(start, end);
+        let anchor = TilemapAnchor::Center;
+        for map_type in [
+            TilemapType::Square,
+            TilemapType::Hexagon(HexCoordSystem::RowOdd),
+            TilemapType::Hexagon(HexCoordSystem::Column),
+            TilemapType::Isometric(IsoCoordSystem::Diamond),
+            TilemapType::Isometric(IsoCoordSystem::Staggered),
+        ] {
+            let selection = select_tiles_in_world_rect(
+                &start, &end, &map_size, &grid_size, &tile_size, &map_type, &anchor,
+            );
+            assert_eq!(
+                matches!(selection, TileSelection::Rect(_)),
+                map_type == TilemapType::Square
+            );
+
+            let expected = (0..map_size.y)
+                .flat_map(|y| (0..map_size.x).map(move |x| TilePos::new(x, y)))
+                .filter(|tile_pos| {
+                    rect.contains(
+                        tile_pos
+                            .center_in_world(&map_size, &grid_size, &tile_size, &map_type, &anchor),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let mut selected = selection.iter().collect::<Vec<_>>();
+            selected.sort_by_key(|tile_pos| (tile_pos.y, tile_pos.x));
+            assert!(!expected.is_empty(), "{map_type:?}");
+            assert_eq!(selected, expected, "{map_type:?}");
+        }
+    }
+}
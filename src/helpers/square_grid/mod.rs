@@ -8,6 +8,7 @@ use crate::helpers::square_grid::staggered::StaggeredPos;
 use crate::tiles::TilePos;
 use crate::{TilemapGridSize, TilemapSize};
 use bevy::math::Vec2;
+use std::iter::FusedIterator;
 use std::ops::{Add, Mul, Sub};
 
 /// Position for tiles arranged in a square coordinate system.
@@ -176,8 +177,116 @@ impl SquarePos {
     pub fn offset(&self, direction: &SquareDirection) -> SquarePos {
         *self + SQUARE_OFFSETS[*direction as usize]
     }
+
+    /// Returns the positions at a chessboard distance of `radius` from `self`, i.e. the border of
+    /// the square of side `2 * radius + 1` around it. The ring starts at its south-west corner
+    /// and goes around counter-clockwise.
+    ///
+    /// If `radius` is zero, `self` is the only position.
+    #[inline]
+    pub fn ring(&self, radius: u32) -> SquareRingIter {
+        SquareRingIter {
+            origin: *self,
+            radius,
+            index: 0,
+        }
+    }
+
+    /// Returns the positions at a chessboard distance of at most `radius` from `self`, ring by
+    /// ring outward, starting with `self`.
+    #[inline]
+    pub fn spiral(&self, radius: u32) -> SquareSpiralIter {
+        SquareSpiralIter {
+            ring: self.ring(0),
+            radius,
+        }
+    }
 }
 
+/// Iterator over the positions of a square ring, created by [`SquarePos::ring`].
+#[derive(Clone, Debug)]
+pub struct SquareRingIter {
+    origin: SquarePos,
+    radius: u32,
+    index: u32,
+}
+
+impl SquareRingIter {
+    fn len_of(radius: u32) -> usize {
+        (radius as usize * 8).max(1)
+    }
+}
+
+impl Iterator for SquareRingIter {
+    type Item = SquarePos;
+
+    fn next(&mut self) -> Option<SquarePos> {
+        if self.index as usize >= Self::len_of(self.radius) {
+            return None;
+        }
+        let side_len = 2 * self.radius;
+        let index = self.index;
+        self.index += 1;
+        if side_len == 0 {
+            return Some(self.origin);
+        }
+        let r = self.radius as i32;
+        let k = (index % side_len) as i32;
+        let offset = match index / side_len {
+            0 => SquarePos::new(k - r, -r),
+            1 => SquarePos::new(r, k - r),
+            2 => SquarePos::new(r - k, r),
+            _ => SquarePos::new(-r, r - k),
+        };
+        Some(self.origin + offset)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = Self::len_of(self.radius) - self.index as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SquareRingIter {}
+
+impl FusedIterator for SquareRingIter {}
+
+/// Iterator over the positions of a filled square, created by [`SquarePos::spiral`].
+#[derive(Clone, Debug)]
+pub struct SquareSpiralIter {
+    ring: SquareRingIter,
+    radius: u32,
+}
+
+impl Iterator for SquareSpiralIter {
+    type Item = SquarePos;
+
+    fn next(&mut self) -> Option<SquarePos> {
+        loop {
+            if let Some(square_pos) = self.ring.next() {
+                return Some(square_pos);
+            }
+            if self.ring.radius >= self.radius {
+                return None;
+            }
+            self.ring = self.ring.origin.ring(self.ring.radius + 1);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let side = |radius: usize| 2 * radius + 1;
+        let ring_radius = self.ring.radius as usize;
+        let radius = (self.radius as usize).max(ring_radius);
+        let outer_rings = side(radius).pow(2) - side(ring_radius).pow(2);
+        let len = self.ring.len() + outer_rings;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SquareSpiralIter {}
+
+impl FusedIterator for SquareSpiralIter {}
+
 impl TilePos {
     /// Get the neighbor lying in the specified direction from this position, if it  fits on the map
     /// and assuming that this is a map using the standard (non-isometric) square coordinate system
@@ -1,7 +1,8 @@
 use crate::helpers::square_grid::SquarePos;
 use crate::helpers::square_grid::staggered::StaggeredPos;
-use crate::map::TilemapSize;
-use crate::prelude::{TilePos, TileStorage};
+use crate::map::{TilemapSize, TilemapTopology};
+use crate::prelude::{TilePos, TileStore};
+use bevy::math::IVec2;
 use bevy::prelude::Entity;
 use std::ops::{Add, Sub};
 
@@ -344,24 +345,37 @@ impl Neighbors<TilePos> {
         tile_pos: &TilePos,
         map_size: &TilemapSize,
         include_diagonals: bool,
+    ) -> Neighbors<TilePos> {
+        Self::get_square_neighboring_positions_wrapped(
+            tile_pos,
+            map_size,
+            include_diagonals,
+            &TilemapTopology::Bounded,
+        )
+    }
+
+    /// Returns neighboring tile positions for a tile position in a square grid, on a map with the
+    /// given [`TilemapTopology`], so tiles on a wrapping edge have neighbors across it.
+    ///
+    /// A tile position will be `None` for a particular direction, if that neighbor would lie past
+    /// a bounded edge of the map.
+    pub fn get_square_neighboring_positions_wrapped(
+        tile_pos: &TilePos,
+        map_size: &TilemapSize,
+        include_diagonals: bool,
+        topology: &TilemapTopology,
     ) -> Neighbors<TilePos> {
         let square_pos = SquarePos::from(tile_pos);
-        if include_diagonals {
-            let f =
-                |direction: SquareDirection| square_pos.offset(&direction).as_tile_pos(map_size);
-
-            Neighbors::from_directional_closure(f)
-        } else {
-            let f = |direction: SquareDirection| {
-                if direction.is_cardinal() {
-                    square_pos.offset(&direction).as_tile_pos(map_size)
-                } else {
-                    None
-                }
-            };
-
-            Neighbors::from_directional_closure(f)
-        }
+        let f = |direction: SquareDirection| {
+            if include_diagonals || direction.is_cardinal() {
+                let SquarePos { x, y } = square_pos.offset(&direction);
+                topology.wrap(IVec2::new(x, y), map_size)
+            } else {
+                None
+            }
+        };
+
+        Neighbors::from_directional_closure(f)
     }
 
     /// Returns neighboring tile positions for a tile position in a staggered square grid, which is
@@ -373,28 +387,42 @@ impl Neighbors<TilePos> {
         tile_pos: &TilePos,
         map_size: &TilemapSize,
         include_diagonals: bool,
+    ) -> Neighbors<TilePos> {
+        Self::get_staggered_neighboring_positions_wrapped(
+            tile_pos,
+            map_size,
+            include_diagonals,
+            &TilemapTopology::Bounded,
+        )
+    }
+
+    /// Returns neighboring tile positions for a tile position in a staggered square grid, on a
+    /// map with the given [`TilemapTopology`], so tiles on a wrapping edge have neighbors across
+    /// it.
+    ///
+    /// A tile position will be `None` for a particular direction, if that neighbor would lie past
+    /// a bounded edge of the map.
+    pub fn get_staggered_neighboring_positions_wrapped(
+        tile_pos: &TilePos,
+        map_size: &TilemapSize,
+        include_diagonals: bool,
+        topology: &TilemapTopology,
     ) -> Neighbors<TilePos> {
         let staggered_pos = StaggeredPos::from(tile_pos);
-        if include_diagonals {
-            let f =
-                |direction: SquareDirection| staggered_pos.offset(&direction).as_tile_pos(map_size);
-
-            Neighbors::from_directional_closure(f)
-        } else {
-            let f = |direction: SquareDirection| {
-                if direction.is_cardinal() {
-                    staggered_pos.offset(&direction).as_tile_pos(map_size)
-                } else {
-                    None
-                }
-            };
-
-            Neighbors::from_directional_closure(f)
-        }
+        let f = |direction: SquareDirection| {
+            if include_diagonals || direction.is_cardinal() {
+                let StaggeredPos { x, y } = staggered_pos.offset(&direction);
+                topology.wrap(IVec2::new(x, y), map_size)
+            } else {
+                None
+            }
+        };
+
+        Neighbors::from_directional_closure(f)
     }
 
     /// Returns the entities associated with each tile position.
-    pub fn entities(&self, tile_storage: &TileStorage) -> Neighbors<Entity> {
+    pub fn entities(&self, tile_storage: &impl TileStore) -> Neighbors<Entity> {
         let f = |tile_pos| tile_storage.get(tile_pos);
         self.and_then_ref(f)
     }
@@ -0,0 +1,240 @@
+//! Places multi-tile objects, like buildings, prefab rooms or brush stamps, onto tilemaps.
+//!
+//! A [`Stamp`] is a small grid of tiles, with empty cells where the tilemap is left as it is. It
+//! can be drawn with the [`tilemap!`](crate::tilemap) macro, copied from a region of another
+//! tilemap, or read from a tile layer of a Tiled map with the `tiled` feature. A
+//! [nine-slice](Stamp::nine_slice) stretches a stamp to any size while keeping its borders, e.g.
+//! for rooms and windows.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::helpers::stamp::*;
+//! fn build_room(mut commands: Commands, mut tilemaps: Query<(Entity, &mut TileStorage)>) {
+//!     let walls = Stamp::from_layer(&bevy_ecs_tilemap::tilemap!(
+//!         "
+//!         +-+
+//!         |.|
+//!         +-+
+//!         ",
+//!         { '+' => 0, '-' => 1, '|' => 2 }
+//!     ));
+//!     let (tilemap_entity, mut tile_storage) = tilemaps.single_mut().unwrap();
+//!     stamp_tiles(
+//!         SignedTilePos::new(4, 2),
+//!         &walls.nine_slice(TilemapSize::new(8, 5), 1),
+//!         TilemapId(tilemap_entity),
+//!         &mut commands,
+//!         &mut tile_storage,
+//!     );
+//! }
+//! ```
+
+use bevy::prelude::{ChildOf, Commands, Query};
+
+use crate::helpers::placement::TileFootprint;
+use crate::map::{TilemapId, TilemapSize};
+use crate::tiles::{
+    SignedTilePos, TileBundle, TileColor, TileDataLayer, TileFlip, TilePos, TileStore,
+    TileTextureIndex, TileVisible,
+};
+
+/// A small grid of tiles which is placed onto tilemaps as a whole with [`stamp_tiles`].
+///
+/// The [`position`](TileBundle::position) and [`tilemap_id`](TileBundle::tilemap_id) of the
+/// bundles are replaced when the tiles are spawned.
+#[derive(Clone, Debug, Default)]
+pub struct Stamp(pub TileDataLayer<Option<TileBundle>>);
+
+impl Stamp {
+    /// Returns an empty stamp of the given size.
+    pub fn new(size: TilemapSize) -> Self {
+        Self(TileDataLayer::filled(size, None))
+    }
+
+    /// Returns a stamp of the given texture indices, e.g. drawn with the
+    /// [`tilemap!`](crate::tilemap) macro.
+    pub fn from_layer(layer: &TileDataLayer<Option<TileTextureIndex>>) -> Self {
+        Self(TileDataLayer::from_fn(layer.size, |tile_pos| {
+            layer.get(&tile_pos).map(|texture_index| TileBundle {
+                texture_index,
+                ..Default::default()
+            })
+        }))
+    }
+
+    /// Copies the tiles of the region of a tilemap starting at `origin`. Cells outside of the
+    /// tilemap, or without a tile, are empty.
+    pub fn from_tilemap(
+        tile_storage: &impl TileStore,
+        origin: TilePos,
+        size: TilemapSize,
+        tiles: &Query<(&TileTextureIndex, &TileVisible, &TileFlip, &TileColor)>,
+    ) -> Self {
+        Self(TileDataLayer::from_fn(size, |tile_pos| {
+            let tile_pos =
+                origin.checked_add(SignedTilePos::from(tile_pos).0, &tile_storage.size())?;
+            let (texture_index, visible, flip, color) =
+                tiles.get(tile_storage.get(&tile_pos)?).ok()?;
+            Some(TileBundle {
+                texture_index: *texture_index,
+                visible: *visible,
+                flip: *flip,
+                color: *color,
+                ..Default::default()
+            })
+        }))
+    }
+
+    pub fn size(&self) -> TilemapSize {
+        self.0.size
+    }
+
+    /// Returns the cells of the stamp which hold a tile, to check where it fits with
+    /// [`can_place`](crate::helpers::placement::can_place).
+    pub fn footprint(&self) -> TileFootprint {
+        TileFootprint::from_layer(&self.0)
+    }
+
+    /// Stretches the stamp to `size` as a nine-slice: the corners of `border` tiles are kept, the
+    /// edges between them are repeated along the sides, and the center is repeated to fill the
+    /// middle.
+    ///
+    /// The border is limited to half of the stamp on each axis. When `size` is smaller than two
+    /// borders, the far borders are clipped.
+    pub fn nine_slice(&self, size: TilemapSize, border: u32) -> Stamp {
+        let source = self.size();
+        Stamp(TileDataLayer::from_fn(size, |tile_pos| {
+            let x = nine_slice_coord(tile_pos.x, size.x, source.x, border)?;
+            let y = nine_slice_coord(tile_pos.y, size.y, source.y, border)?;
+            *self.0.get(&TilePos::new(x, y))
+        }))
+    }
+}
+
+/// Returns the coordinate of the source tile of a nine-slice on one axis, or `None` if the middle
+/// of the source is empty.
+fn nine_slice_coord(coord: u32, len: u32, source_len: u32, border: u32) -> Option<u32> {
+    let border = border.min(source_len / 2);
+    let middle = source_len - 2 * border;
+    if coord < border {
+        Some(coord)
+    } else if coord >= len.saturating_sub(border) {
+        Some(source_len - (len - coord))
+    } else if middle > 0 {
+        Some(border + (coord - border) % middle)
+    } else {
+        None
+    }
+}
+
+/// Spawns the tiles of `stamp` with its bottom left cell at `origin`, and returns the positions
+/// of the spawned tiles.
+///
+/// Tiles which replace existing tiles despawn them, and empty cells of the stamp leave the
+/// tilemap as it is. Tiles that do not fit in the tilemap are not spawned, so the origin may lie
+/// outside of it, e.g. while dragging a stamp over the edge of the tilemap.
+pub fn stamp_tiles(
+    origin: SignedTilePos,
+    stamp: &Stamp,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut impl TileStore,
+) -> Vec<TilePos> {
+    let size = tile_storage.size();
+    let mut stamped = Vec::new();
+    for (pos, tile) in stamp.0.iter() {
+        let Some(tile) = tile else {
+            continue;
+        };
+        let Some(tile_pos) =
+            (origin + SignedTilePos::from(pos).0).as_tile_pos_given_map_size(&size)
+        else {
+            continue;
+        };
+        if let Some(previous) = tile_storage.get(&tile_pos) {
+            commands.entity(previous).despawn();
+        }
+        let tile_entity = commands
+            .spawn((
+                TileBundle {
+                    position: tile_pos,
+                    tilemap_id,
+                    ..*tile
+                },
+                ChildOf(tilemap_id.0),
+            ))
+            .id();
+        tile_storage.set(&tile_pos, tile_entity);
+        stamped.push(tile_pos);
+    }
+    stamped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileStorage;
+    use bevy::ecs::world::CommandQueue;
+    use bevy::prelude::World;
+
+    #[test]
+    fn stamps_are_clipped_and_replace_tiles() {
+        let frame = Stamp::from_layer(&crate::tilemap!(
+            "
+            789
+            4.6
+            123
+            ",
+            {
+                '1' => 1, '2' => 2, '3' => 3, '4' => 4, '6' => 6, '7' => 7, '8' => 8, '9' => 9
+            }
+        ));
+        let stretched = frame.nine_slice(TilemapSize::new(5, 4), 1);
+        let texture_indices = |stamp: &Stamp| {
+            stamp
+                .0
+                .values()
+                .map(|tile| tile.map(|tile| tile.texture_index.0))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            texture_indices(&stretched),
+            [
+                [Some(1), Some(2), Some(2), Some(2), Some(3)],
+                [Some(4), None, None, None, Some(6)],
+                [Some(4), None, None, None, Some(6)],
+                [Some(7), Some(8), Some(8), Some(8), Some(9)],
+            ]
+            .concat()
+        );
+
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut storage = TileStorage::empty(TilemapSize::new(4, 4));
+        let old_tile = commands.spawn_empty().id();
+        storage.set(&TilePos::new(0, 1), old_tile);
+
+        // Only the top right corner of the frame and its sides reach into the tilemap.
+        let stamped = stamp_tiles(
+            SignedTilePos::new(-2, -1),
+            &frame,
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        assert_eq!(stamped, vec![TilePos::new(0, 0), TilePos::new(0, 1)]);
+        queue.apply(&mut world);
+        assert!(world.get_entity(old_tile).is_err());
+        let texture_index = |tile_pos| {
+            world
+                .get::<TileTextureIndex>(storage.get(&tile_pos).unwrap())
+                .unwrap()
+                .0
+        };
+        assert_eq!(texture_index(TilePos::new(0, 0)), 6);
+        assert_eq!(texture_index(TilePos::new(0, 1)), 9);
+    }
+}
@@ -0,0 +1,136 @@
+//! Helpers for streaming the chunks of a large map in and out around a point of interest, such
+//! as the camera or the player.
+
+use bevy::math::{IVec2, Vec2};
+use bevy::platform::collections::HashSet;
+use std::sync::Arc;
+
+/// The point of interest chunks are streamed around, as seen by a [`ChunkPriority`] function.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChunkFocus {
+    /// The chunk the point of interest is in.
+    pub chunk: IVec2,
+    /// The velocity of the point of interest, in chunks per second. It is zero if the point of
+    /// interest is not moving.
+    pub velocity: Vec2,
+}
+
+/// Ranks the chunks waiting in a [`ChunkSpawnQueue`]. Chunks with a higher priority are spawned
+/// first.
+pub type ChunkPriority = Arc<dyn Fn(IVec2, &ChunkFocus) -> f32 + Send + Sync>;
+
+/// Prioritizes chunks by their distance to the focused chunk, spawning the closest ones first.
+pub fn distance_priority() -> ChunkPriority {
+    Arc::new(|chunk, focus| -chunk.as_vec2().distance(focus.chunk.as_vec2()))
+}
+
+/// Prioritizes chunks by their distance to where the point of interest will be in `lookahead`
+/// seconds, so chunks ahead of a moving camera or player are spawned before the ones behind it.
+pub fn heading_priority(lookahead: f32) -> ChunkPriority {
+    Arc::new(move |chunk, focus| {
+        let target = focus.chunk.as_vec2() + focus.velocity * lookahead;
+        -chunk.as_vec2().distance(target)
+    })
+}
+
+/// A queue of chunks waiting to be spawned, which hands out a limited number of chunks per frame
+/// in the order given by a [`ChunkPriority`] function.
+///
+/// Each chunk is only queued once until it is popped.
+#[derive(Clone)]
+pub struct ChunkSpawnQueue {
+    pending: Vec<IVec2>,
+    queued: HashSet<IVec2>,
+    priority: ChunkPriority,
+    /// The maximum number of chunks returned by [`pop_budgeted`](Self::pop_budgeted).
+    pub budget: usize,
+}
+
+impl Default for ChunkSpawnQueue {
+    fn default() -> Self {
+        Self::new(distance_priority(), 4)
+    }
+}
+
+impl ChunkSpawnQueue {
+    /// Creates an empty queue that hands out at most `budget` chunks per frame, ordered by
+    /// `priority`.
+    pub fn new(priority: ChunkPriority, budget: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            queued: HashSet::default(),
+            priority,
+            budget,
+        }
+    }
+
+    /// Replaces the function which orders the queued chunks.
+    pub fn set_priority(&mut self, priority: ChunkPriority) {
+        self.priority = priority;
+    }
+
+    /// Queues a chunk, returning `false` if it was already queued.
+    pub fn push(&mut self, chunk: IVec2) -> bool {
+        if self.queued.insert(chunk) {
+            self.pending.push(chunk);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if the chunk is waiting to be spawned.
+    pub fn contains(&self, chunk: &IVec2) -> bool {
+        self.queued.contains(chunk)
+    }
+
+    /// Keeps only the queued chunks for which `f` returns `true`, e.g. to drop chunks which went
+    /// out of range before they were spawned.
+    pub fn retain(&mut self, mut f: impl FnMut(&IVec2) -> bool) {
+        let queued = &mut self.queued;
+        self.pending.retain(|chunk| {
+            let keep = f(chunk);
+            if !keep {
+                queued.remove(chunk);
+            }
+            keep
+        });
+    }
+
+    /// Returns the number of queued chunks.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if no chunks are queued.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Removes all queued chunks.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.queued.clear();
+    }
+
+    /// Removes and returns up to [`budget`](Self::budget) chunks, highest priority first.
+    pub fn pop_budgeted(&mut self, focus: &ChunkFocus) -> Vec<IVec2> {
+        let mut ranked = self
+            .pending
+            .drain(..)
+            .map(|chunk| ((self.priority)(chunk, focus), chunk))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let count = self.budget.min(ranked.len());
+        self.pending
+            .extend(ranked.drain(count..).map(|(_, chunk)| chunk));
+        ranked
+            .into_iter()
+            .map(|(_, chunk)| {
+                self.queued.remove(&chunk);
+                chunk
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,222 @@
+//! A terrain brush which paints terrains the way the terrain brush of Tiled does, picking the
+//! tiles of the painted position and its neighbors from a set of Wang tiles.
+//!
+//! Each tile of a Wang set gives the terrain of its corners and edges as a [`WangId`]. Painting a
+//! terrain on a position gives it the tile whose corners and edges all have that terrain, and
+//! replaces the tiles around it with the ones which best fit their new corners and edges. With
+//! the `tiled` feature, brushes are made from the wang sets of Tiled tilesets with
+//! [`TiledMap::terrain_brush`](crate::tiled::TiledMap::terrain_brush).
+
+use bevy::prelude::Query;
+
+use crate::helpers::square_grid::neighbors::{Neighbors, SQUARE_DIRECTIONS, SquareDirection};
+use crate::tiles::{TilePos, TileStorage, TileTextureIndex};
+
+/// The terrains of the corners and edges of a tile, as in Tiled: top, top-right, right,
+/// bottom-right, bottom, bottom-left, left and top-left, where top is north. Terrains count from
+/// `1`, and `0` means no terrain.
+pub type WangId = [u8; 8];
+
+/// Which parts of tiles a [`TerrainBrush`] matches.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum TerrainKind {
+    /// Only the corners, which are shared by four tiles.
+    #[default]
+    Corner,
+    /// Only the edges, which are shared by two tiles.
+    Edge,
+    /// Both the corners and the edges.
+    Mixed,
+}
+
+impl TerrainKind {
+    fn matches_slot(&self, slot: usize) -> bool {
+        match self {
+            TerrainKind::Corner => !slot.is_multiple_of(2),
+            TerrainKind::Edge => slot.is_multiple_of(2),
+            TerrainKind::Mixed => true,
+        }
+    }
+}
+
+/// The slots of the [`WangId`] of a neighbor which touch the tile it neighbors.
+fn touching_slots(direction: SquareDirection) -> &'static [usize] {
+    use SquareDirection::*;
+    match direction {
+        North => &[3, 4, 5],
+        East => &[5, 6, 7],
+        South => &[7, 0, 1],
+        West => &[1, 2, 3],
+        NorthEast => &[5],
+        SouthEast => &[7],
+        SouthWest => &[1],
+        NorthWest => &[3],
+    }
+}
+
+/// Paints terrains onto a tilemap, fixing up the tiles around the painted ones.
+#[derive(Clone, Debug, Default)]
+pub struct TerrainBrush {
+    pub kind: TerrainKind,
+    tiles: Vec<(WangId, TileTextureIndex)>,
+}
+
+impl TerrainBrush {
+    /// Creates a brush from Wang tiles. When several tiles fit a position equally well, the one
+    /// which comes first is used.
+    pub fn new(
+        kind: TerrainKind,
+        tiles: impl IntoIterator<Item = (WangId, TileTextureIndex)>,
+    ) -> Self {
+        Self {
+            kind,
+            tiles: tiles.into_iter().collect(),
+        }
+    }
+
+    /// Returns the [`WangId`] of a texture, if it is one of the brush's tiles.
+    pub fn wang_id(&self, texture_index: &TileTextureIndex) -> Option<WangId> {
+        self.tiles
+            .iter()
+            .find(|(_, tile_texture_index)| tile_texture_index == texture_index)
+            .map(|(wang_id, _)| *wang_id)
+    }
+
+    /// Returns the tile which fits `wang_id` best, i.e. whose terrains match the most of its
+    /// terrains. Slots without a terrain match anything.
+    pub fn best_fit(&self, wang_id: &WangId) -> Option<TileTextureIndex> {
+        let mut best: Option<(usize, TileTextureIndex)> = None;
+        for (tile_wang_id, texture_index) in &self.tiles {
+            let score = (0..8)
+                .filter(|slot| {
+                    self.kind.matches_slot(*slot)
+                        && wang_id[*slot] != 0
+                        && wang_id[*slot] == tile_wang_id[*slot]
+                })
+                .count();
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, *texture_index));
+            }
+        }
+        best.map(|(_, texture_index)| texture_index)
+    }
+
+    /// Paints `terrain` on the tile at `tile_pos`, and replaces the tiles around it with the ones
+    /// which fit best.
+    ///
+    /// The neighbors keep the terrains of their corners and edges which do not touch the painted
+    /// tile. Neighbors whose texture is not one of the brush's tiles are treated as having no
+    /// terrain. Returns the positions of the tiles whose texture changed.
+    pub fn paint(
+        &self,
+        tile_storage: &TileStorage,
+        tiles: &mut Query<&mut TileTextureIndex>,
+        tile_pos: &TilePos,
+        terrain: u8,
+    ) -> Vec<TilePos> {
+        let mut repaints = vec![(*tile_pos, [terrain; 8])];
+        let neighbors =
+            Neighbors::get_square_neighboring_positions(tile_pos, &tile_storage.size, true);
+        for direction in SQUARE_DIRECTIONS {
+            let Some(neighbor) = neighbors.get(direction) else {
+                continue;
+            };
+            let mut wang_id = tile_storage
+                .checked_get(neighbor)
+                .and_then(|tile_entity| tiles.get(tile_entity).ok())
+                .and_then(|texture_index| self.wang_id(texture_index))
+                .unwrap_or_default();
+            for slot in touching_slots(direction) {
+                wang_id[*slot] = terrain;
+            }
+            repaints.push((*neighbor, wang_id));
+        }
+
+        let mut changed = Vec::new();
+        for (tile_pos, wang_id) in repaints {
+            let Some(texture_index) = self.best_fit(&wang_id) else {
+                continue;
+            };
+            if let Some(tile_entity) = tile_storage.checked_get(&tile_pos)
+                && let Ok(mut tile_texture_index) = tiles.get_mut(tile_entity)
+                && *tile_texture_index != texture_index
+            {
+                *tile_texture_index = texture_index;
+                changed.push(tile_pos);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapId, TilemapSize};
+    use crate::tiles::TileBundle;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::*;
+
+    /// A corner set of grass (1) and water (2), with every combination of corners. The texture
+    /// index of a tile adds 1, 2, 4 and 8 for water on its top-right, bottom-right, bottom-left
+    /// and top-left corners.
+    fn grass_and_water() -> TerrainBrush {
+        TerrainBrush::new(
+            TerrainKind::Corner,
+            (0..16u32).map(|index| {
+                let corner = |bit: u32| if index & bit != 0 { 2 } else { 1 };
+                (
+                    [0, corner(1), 0, corner(2), 0, corner(4), 0, corner(8)],
+                    TileTextureIndex(index),
+                )
+            }),
+        )
+    }
+
+    #[test]
+    fn painting_fixes_up_neighbors() {
+        let mut world = World::new();
+        let map_size = TilemapSize::new(3, 3);
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        for index in 0..map_size.count() as u32 {
+            let tile_pos = TilePos::new(index % map_size.x, index / map_size.x);
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+        world.entity_mut(tilemap).insert(tile_storage);
+
+        let changed = world
+            .run_system_once(
+                |storage: Query<&TileStorage>, mut tiles: Query<&mut TileTextureIndex>| {
+                    grass_and_water().paint(
+                        storage.single().unwrap(),
+                        &mut tiles,
+                        &TilePos::new(1, 1),
+                        2,
+                    )
+                },
+            )
+            .unwrap();
+        assert_eq!(changed.len(), 9);
+
+        let tile_storage = world.get::<TileStorage>(tilemap).unwrap();
+        let texture = |x, y| {
+            world
+                .get::<TileTextureIndex>(tile_storage.get(&TilePos::new(x, y)).unwrap())
+                .unwrap()
+                .0
+        };
+        assert_eq!(texture(1, 1), 15);
+        // The tile north of the painted one gets water on its bottom corners.
+        assert_eq!(texture(1, 2), 2 | 4);
+        // The tile south-west of it only on its top-right corner.
+        assert_eq!(texture(0, 0), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use bevy::color::{Color, Srgba};
+use bevy::log::warn;
+use bevy::math::IVec2;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::{Component, ReflectComponent, ReflectDefault};
+use bevy::reflect::{
+    DynamicEnum, DynamicList, PartialReflect, Reflect, ReflectMut, Struct, TypeRegistry,
+};
+
+use crate::tiles::TilePos;
+
+/// The value of a field of an LDtk entity.
+#[derive(Reflect, Default, Clone, Debug, PartialEq)]
+pub enum LdtkFieldValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// Strings, colors (as `#rrggbb`), enum values, file paths and entity references (as the iid
+    /// of the entity).
+    String(String),
+    /// A grid position, in tiles from the bottom left corner of the level.
+    Point(IVec2),
+    Array(Vec<LdtkFieldValue>),
+}
+
+/// The fields of an LDtk entity, by identifier.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct LdtkFields(pub HashMap<String, LdtkFieldValue>);
+
+impl LdtkFields {
+    /// Returns the value of the field with the given identifier.
+    pub fn get(&self, identifier: &str) -> Option<&LdtkFieldValue> {
+        self.0.get(identifier)
+    }
+}
+
+fn set<T: Reflect>(field: &mut dyn PartialReflect, value: T) -> bool {
+    match field.try_downcast_mut::<T>() {
+        Some(field) => {
+            *field = value;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sets a field of a reflected struct from an LDtk value, converting between the numeric, point,
+/// color and enum types where it makes sense. Returns `false` if the types do not match.
+fn apply_field(field: &mut dyn PartialReflect, value: &LdtkFieldValue) -> bool {
+    match value {
+        LdtkFieldValue::Null => false,
+        LdtkFieldValue::Bool(v) => set(field, *v),
+        LdtkFieldValue::Int(v) => {
+            set(field, *v)
+                || set(field, *v as i32)
+                || set(field, *v as u32)
+                || set(field, *v as u64)
+                || set(field, *v as usize)
+                || set(field, *v as u8)
+                || set(field, *v as u16)
+                || set(field, *v as i16)
+                || set(field, *v as f32)
+                || set(field, *v as f64)
+        }
+        LdtkFieldValue::Float(v) => set(field, *v) || set(field, *v as f32),
+        LdtkFieldValue::String(v) => {
+            set(field, v.clone())
+                || Srgba::hex(v).is_ok_and(|color| set(field, Color::from(color)))
+                // Unit variants of enums, e.g. for LDtk enum fields.
+                || field.reflect_ref().as_enum().is_ok()
+                    && field.try_apply(&DynamicEnum::new(v.clone(), ())).is_ok()
+        }
+        LdtkFieldValue::Point(v) => {
+            set(field, *v)
+                || v.cmpge(IVec2::ZERO).all() && set(field, TilePos::new(v.x as u32, v.y as u32))
+        }
+        LdtkFieldValue::Array(values) => {
+            let mut items = Vec::with_capacity(values.len());
+            for value in values {
+                let item: Box<dyn PartialReflect> = match value {
+                    LdtkFieldValue::Int(v) => Box::new(*v),
+                    LdtkFieldValue::Float(v) => Box::new(*v),
+                    LdtkFieldValue::Bool(v) => Box::new(*v),
+                    LdtkFieldValue::String(v) => Box::new(v.clone()),
+                    LdtkFieldValue::Point(v) => Box::new(*v),
+                    _ => return false,
+                };
+                items.push(item);
+            }
+            field.try_apply(&DynamicList::from_iter(items)).is_ok()
+        }
+    }
+}
+
+/// LDtk identifiers are usually capitalized, e.g. `Max_health`, while Rust fields are snake case.
+fn same_identifier(ldtk: &str, rust: &str) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| *c != '_')
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>()
+    };
+    normalize(ldtk) == normalize(rust)
+}
+
+/// Builds the component whose type is registered under the identifier of an LDtk entity, with
+/// the fields of the entity applied to it.
+///
+/// The component has to reflect `Component` and `Default`. Fields of the component without a
+/// matching LDtk field keep their default value.
+pub(crate) fn reflect_entity_component(
+    identifier: &str,
+    fields: &LdtkFields,
+    registry: &TypeRegistry,
+) -> Option<Box<dyn PartialReflect>> {
+    let registration = registry.get_with_short_type_path(identifier)?;
+    registration.data::<ReflectComponent>()?;
+    let Some(reflect_default) = registration.data::<ReflectDefault>() else {
+        warn!("The component of LDtk entity '{identifier}' does not reflect Default.");
+        return None;
+    };
+
+    let mut component = reflect_default.default();
+    if let ReflectMut::Struct(component) = component.reflect_mut() {
+        apply_fields(identifier, fields, component);
+    }
+    Some(component.into_partial_reflect())
+}
+
+fn apply_fields(identifier: &str, fields: &LdtkFields, component: &mut dyn Struct) {
+    for index in 0..component.field_len() {
+        let Some(name) = component.name_at(index).map(str::to_owned) else {
+            continue;
+        };
+        let Some(value) = fields
+            .0
+            .iter()
+            .find_map(|(key, value)| same_identifier(key, &name).then_some(value))
+        else {
+            continue;
+        };
+        let Some(field) = component.field_at_mut(index) else {
+            continue;
+        };
+        if !apply_field(field, value) {
+            warn!(
+                "Field '{name}' of LDtk entity '{identifier}' does not match the type of its value."
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Component;
+
+    use super::*;
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    enum ChestKind {
+        #[default]
+        Wood,
+        Iron,
+    }
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Chest {
+        kind: ChestKind,
+        loot: Vec<i64>,
+        max_items: u32,
+        label: String,
+        tint: Color,
+        origin: TilePos,
+    }
+
+    #[test]
+    fn fields_are_applied_by_name() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Chest>();
+        let fields = LdtkFields(HashMap::from_iter([
+            ("Max_items".to_string(), LdtkFieldValue::Int(12)),
+            ("Label".to_string(), LdtkFieldValue::String("Loot".into())),
+            ("Tint".to_string(), LdtkFieldValue::String("#ff0000".into())),
+            (
+                "Origin".to_string(),
+                LdtkFieldValue::Point(IVec2::new(2, 3)),
+            ),
+            ("Kind".to_string(), LdtkFieldValue::String("Iron".into())),
+            (
+                "Loot".to_string(),
+                LdtkFieldValue::Array(vec![LdtkFieldValue::Int(4), LdtkFieldValue::Int(7)]),
+            ),
+            ("Unused".to_string(), LdtkFieldValue::Bool(true)),
+        ]));
+
+        let component = reflect_entity_component("Chest", &fields, &registry).unwrap();
+        let chest = component.try_downcast_ref::<Chest>().unwrap();
+        assert_eq!(
+            *chest,
+            Chest {
+                kind: ChestKind::Iron,
+                loot: vec![4, 7],
+                max_items: 12,
+                label: "Loot".into(),
+                tint: Color::from(Srgba::RED),
+                origin: TilePos::new(2, 3),
+            }
+        );
+        assert!(reflect_entity_component("Door", &fields, &registry).is_none());
+    }
+}
@@ -0,0 +1,406 @@
+//! Loads projects made with the [LDtk](https://ldtk.io) level editor.
+//!
+//! Add the [`LdtkPlugin`] and spawn an [`LdtkMapHandle`] pointing at a `.ldtk` file. Once the
+//! project is loaded, the level selected by the [`LdtkMapConfig`] is spawned as children of that
+//! entity:
+//! * `Tiles` and `AutoLayer` layers, and `IntGrid` layers with auto-layer rules, become tilemaps.
+//! * `IntGrid` layers get a [`TileDataLayer<i64>`] with the value of each cell, `0` being empty.
+//!   Without auto-layer rules, the layer entity has the data but no tiles.
+//! * Every instance of an `Entities` layer becomes an entity with an [`LdtkEntity`] and its
+//!   [`LdtkFields`]. If a component whose short type path matches the identifier of the LDtk
+//!   entity is registered, and it reflects `Component` and `Default`, it is inserted too, with
+//!   its fields set from the LDtk fields of the same name.
+//!
+//! Levels saved in separate files are skipped. Only one tile of a stack of tiles, as made by
+//! overlapping auto-layer rules, is kept in the [`TileStorage`].
+
+mod fields;
+
+pub use fields::{LdtkFieldValue, LdtkFields};
+
+use std::fmt;
+use std::path::Path;
+
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::ecs::reflect::ReflectCommandExt;
+use bevy::log::warn;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::TilemapBundle;
+use crate::anchor::TilemapAnchor;
+use crate::map::{
+    TilemapGridSize, TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTileSize,
+    TilemapType,
+};
+use crate::tiles::{
+    TileBundle, TileColor, TileDataLayer, TileFlip, TilePos, TileStorage, TileTextureIndex,
+};
+
+/// Loads `.ldtk` files as [`LdtkMap`]s, and spawns the levels of [`LdtkMapHandle`]s.
+#[derive(Default)]
+pub struct LdtkPlugin;
+
+impl Plugin for LdtkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LdtkMap>()
+            .register_asset_loader(LdtkLoader)
+            .register_type::<LdtkFields>()
+            .register_type::<LdtkEntity>()
+            .add_systems(Update, spawn_ldtk_levels);
+    }
+}
+
+/// A project loaded from a `.ldtk` file.
+#[derive(TypePath, Asset)]
+pub struct LdtkMap {
+    pub project: ldtk_rust::Project,
+    /// The image of each tileset, by tileset uid.
+    pub tilesets: HashMap<i64, Handle<Image>>,
+}
+
+/// The project whose level is spawned as children of this entity.
+#[derive(Component, Default, Clone, Debug)]
+#[require(Transform, Visibility, LdtkMapConfig)]
+pub struct LdtkMapHandle(pub Handle<LdtkMap>);
+
+/// Selects the level of an [`LdtkMapHandle`] to spawn. Changing it spawns the level again.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct LdtkMapConfig {
+    pub selected_level: usize,
+}
+
+/// A layer of an LDtk level.
+#[derive(Component, Clone, Debug)]
+pub struct LdtkLayer {
+    pub identifier: String,
+    /// `IntGrid`, `Tiles`, `AutoLayer` or `Entities`.
+    pub layer_type: String,
+}
+
+/// An instance of an LDtk entity.
+///
+/// It is spawned as a child of its [`LdtkLayer`], and its [`Transform`] is the position of its
+/// pivot relative to the center of the level.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub iid: String,
+    /// The size of the entity, in pixels.
+    pub size: Vec2,
+    pub tags: Vec<String>,
+}
+
+/// An error while loading an [`LdtkMap`].
+#[derive(Debug)]
+pub enum LdtkLoaderError {
+    /// The project could not be read.
+    Io(std::io::Error),
+    /// The project could not be parsed.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for LdtkLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not load LDtk file: {err}"),
+            Self::Json(err) => write!(f, "could not parse LDtk file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LdtkLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for LdtkLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Loads `.ldtk` files, along with the images of their tilesets.
+pub struct LdtkLoader;
+
+impl AssetLoader for LdtkLoader {
+    type Asset = LdtkMap;
+    type Settings = ();
+    type Error = LdtkLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let project: ldtk_rust::Project =
+            serde_json::from_slice(&bytes).map_err(LdtkLoaderError::Json)?;
+
+        // Tileset paths are relative to the project file.
+        let project_dir = load_context
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let tilesets = project
+            .defs
+            .tilesets
+            .iter()
+            .filter_map(|tileset| {
+                let rel_path = tileset.rel_path.as_ref()?;
+                Some((tileset.uid, project_dir.join(rel_path)))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(uid, path)| (uid, load_context.load(path)))
+            .collect();
+
+        Ok(LdtkMap { project, tilesets })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+/// Converts the JSON value of an LDtk field. Points are flipped to count rows from the bottom of
+/// a grid `grid_height` cells tall.
+fn field_value(value: &serde_json::Value, grid_height: i64) -> LdtkFieldValue {
+    use serde_json::Value;
+    match value {
+        Value::Null => LdtkFieldValue::Null,
+        Value::Bool(v) => LdtkFieldValue::Bool(*v),
+        Value::Number(v) => match v.as_i64() {
+            Some(v) => LdtkFieldValue::Int(v),
+            None => LdtkFieldValue::Float(v.as_f64().unwrap_or_default()),
+        },
+        Value::String(v) => LdtkFieldValue::String(v.clone()),
+        Value::Array(values) => LdtkFieldValue::Array(
+            values
+                .iter()
+                .map(|value| field_value(value, grid_height))
+                .collect(),
+        ),
+        Value::Object(object) => {
+            if let (Some(cx), Some(cy)) = (
+                object.get("cx").and_then(Value::as_i64),
+                object.get("cy").and_then(Value::as_i64),
+            ) {
+                LdtkFieldValue::Point(IVec2::new(cx as i32, (grid_height - 1 - cy) as i32))
+            } else if let Some(iid) = object.get("entityIid").and_then(Value::as_str) {
+                LdtkFieldValue::String(iid.to_owned())
+            } else {
+                LdtkFieldValue::Null
+            }
+        }
+    }
+}
+
+/// Spawns the selected level of new [`LdtkMapHandle`]s, and spawns levels again when the project
+/// or the selection changes.
+fn spawn_ldtk_levels(
+    mut commands: Commands,
+    mut map_events: MessageReader<AssetEvent<LdtkMap>>,
+    maps: Res<Assets<LdtkMap>>,
+    type_registry: Res<AppTypeRegistry>,
+    query: Query<(Entity, Ref<LdtkMapHandle>, Ref<LdtkMapConfig>)>,
+) {
+    let changed_maps = map_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (map_entity, handle, config) in query.iter() {
+        if !handle.is_changed() && !config.is_changed() && !changed_maps.contains(&handle.0.id()) {
+            continue;
+        }
+        let Some(ldtk_map) = maps.get(&handle.0) else {
+            continue;
+        };
+        commands.entity(map_entity).despawn_related::<Children>();
+
+        let Some(level) = ldtk_map.project.levels.get(config.selected_level) else {
+            warn!("LDtk level {} does not exist.", config.selected_level);
+            continue;
+        };
+        let Some(layers) = level.layer_instances.as_ref() else {
+            warn!(
+                "Skipping LDtk level '{}' because it is saved in a separate file.",
+                level.identifier
+            );
+            continue;
+        };
+        let level_extent = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+
+        // Layers are listed from the top.
+        for (z, layer) in layers.iter().rev().enumerate() {
+            let z = z as f32;
+            let size = TilemapSize::new(layer.c_wid as u32, layer.c_hei as u32);
+            let grid = layer.grid_size as f32;
+            let offset = Vec2::new(
+                layer.px_total_offset_x as f32,
+                -layer.px_total_offset_y as f32,
+            );
+            let visibility = if layer.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+            let layer_entity = commands
+                .spawn((
+                    LdtkLayer {
+                        identifier: layer.identifier.clone(),
+                        layer_type: layer.layer_instance_type.clone(),
+                    },
+                    Transform::from_translation(offset.extend(z)),
+                    visibility,
+                    ChildOf(map_entity),
+                ))
+                .id();
+
+            if layer.layer_instance_type == "Entities" {
+                let type_registry = type_registry.read();
+                for instance in &layer.entity_instances {
+                    let fields = LdtkFields(
+                        instance
+                            .field_instances
+                            .iter()
+                            .map(|field| {
+                                let value = field
+                                    .value
+                                    .as_ref()
+                                    .map(|value| field_value(value, layer.c_hei))
+                                    .unwrap_or_default();
+                                (field.identifier.clone(), value)
+                            })
+                            .collect(),
+                    );
+                    let component = fields::reflect_entity_component(
+                        &instance.identifier,
+                        &fields,
+                        &type_registry,
+                    );
+                    // Entity positions are in pixels from the top left corner of the level.
+                    let position = Vec2::new(
+                        instance.px[0] as f32 - level_extent.x / 2.0,
+                        level_extent.y / 2.0 - instance.px[1] as f32,
+                    );
+                    let mut entity = commands.spawn((
+                        LdtkEntity {
+                            identifier: instance.identifier.clone(),
+                            iid: instance.iid.clone(),
+                            size: Vec2::new(instance.width as f32, instance.height as f32),
+                            tags: instance.tags.clone(),
+                        },
+                        fields,
+                        Transform::from_translation(position.extend(0.0)),
+                        Visibility::default(),
+                        ChildOf(layer_entity),
+                    ));
+                    if let Some(component) = component {
+                        entity.insert_reflect(component);
+                    }
+                }
+                continue;
+            }
+
+            if layer.layer_instance_type == "IntGrid" {
+                let values = &layer.int_grid_csv;
+                commands
+                    .entity(layer_entity)
+                    .insert(TileDataLayer::from_fn(size, |tile_pos| {
+                        let row = size.y - 1 - tile_pos.y;
+                        values
+                            .get((row * size.x + tile_pos.x) as usize)
+                            .copied()
+                            .unwrap_or_default()
+                    }));
+            }
+
+            let tiles = if layer.layer_instance_type == "Tiles" {
+                &layer.grid_tiles
+            } else {
+                &layer.auto_layer_tiles
+            };
+            let Some(tileset_uid) = layer.override_tileset_uid.or(layer.tileset_def_uid) else {
+                continue;
+            };
+            let (Some(tileset), Some(texture)) = (
+                ldtk_map
+                    .project
+                    .defs
+                    .tilesets
+                    .iter()
+                    .find(|tileset| tileset.uid == tileset_uid),
+                ldtk_map.tilesets.get(&tileset_uid),
+            ) else {
+                warn!(
+                    "Skipping LDtk layer '{}' because its tileset has no image.",
+                    layer.identifier
+                );
+                continue;
+            };
+            if tiles.is_empty() {
+                continue;
+            }
+
+            let mut storage = TileStorage::empty(size);
+            for tile in tiles {
+                let x = (tile.px[0] / layer.grid_size) as u32;
+                let y = (tile.px[1] / layer.grid_size) as u32;
+                let Some(y) = size.y.checked_sub(y + 1) else {
+                    continue;
+                };
+                let position = TilePos::new(x, y);
+                if !position.within_map_bounds(&size) {
+                    continue;
+                }
+                let tile_entity = commands
+                    .spawn((
+                        TileBundle {
+                            position,
+                            tilemap_id: TilemapId(layer_entity),
+                            texture_index: TileTextureIndex(tile.t as u32),
+                            flip: TileFlip {
+                                x: tile.f & 1 != 0,
+                                y: tile.f & 2 != 0,
+                                d: false,
+                            },
+                            color: TileColor(Color::WHITE.with_alpha(layer.opacity as f32)),
+                            ..Default::default()
+                        },
+                        ChildOf(layer_entity),
+                    ))
+                    .id();
+                storage.set(&position, tile_entity);
+            }
+
+            let tile_size =
+                TilemapTileSize::new(tileset.tile_grid_size as f32, tileset.tile_grid_size as f32);
+            commands.entity(layer_entity).insert(TilemapBundle {
+                grid_size: TilemapGridSize::new(grid, grid),
+                map_type: TilemapType::Square,
+                size,
+                storage,
+                texture: TilemapTexture::Single(texture.clone()),
+                tile_size,
+                spacing: TilemapSpacing::new(tileset.spacing as f32, tileset.spacing as f32),
+                anchor: TilemapAnchor::Center,
+                transform: Transform::from_translation(offset.extend(z)),
+                visibility,
+                ..Default::default()
+            });
+        }
+    }
+}
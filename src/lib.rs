@@ -19,26 +19,39 @@ use bevy::{
     ecs::schedule::IntoScheduleConfigs,
     prelude::{
         Bundle, Changed, Component, Deref, First, GlobalTransform, InheritedVisibility, Plugin,
-        Query, Reflect, ReflectComponent, SystemSet, Transform, ViewVisibility, Visibility,
+        PostUpdate, Query, Reflect, ReflectComponent, SystemSet, Transform, ViewVisibility,
+        Visibility,
     },
     render::sync_world::SyncToRenderWorld,
     time::TimeSystems,
+    transform::TransformSystems,
 };
 
 #[cfg(feature = "render")]
 use render::material::MaterialTilemapHandle;
 
 use anchor::TilemapAnchor;
+use helpers::filling::TileEntityPool;
 use map::{
-    TilemapGridSize, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize,
-    TilemapTileSize, TilemapType,
+    TilemapChunkSize, TilemapColor, TilemapColorGrading, TilemapCustomData, TilemapDepthMode,
+    TilemapGridDistortion, TilemapGridSize, TilemapLayer, TilemapLayerBlendModes,
+    TilemapLayerOrder, TilemapOcclusionReveal, TilemapPerfSettings, TilemapRenderMode,
+    TilemapSecondaryTexture, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureAtlas,
+    TilemapTexturePadding, TilemapTextureSize, TilemapTileSize, TilemapTilesets, TilemapTime,
+    TilemapTimeScale, TilemapTopology, TilemapType, TilemapUpdateMode, TilemapUpdateState,
+    TilemapWorldBounds,
 };
 use prelude::{TilemapId, TilemapRenderSettings};
+use region_of_interest::{
+    OutsideRegionOfInterest, RegionOfInterestCamera, RegionOfInterestThrottling, RegionsOfInterest,
+};
 #[cfg(feature = "render")]
 use render::material::{MaterialTilemap, StandardTilemapMaterial};
 use tiles::{
-    AnimatedTile, TileColor, TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex,
-    TileVisible,
+    AnimatedTile, AnimationGroup, AnimationGroupSpeeds, AnimationPaused, AnimationPhase,
+    LayeredTileStorage, SparseTileStorage, TileAnimationTable, TileColor, TileColorAnimation,
+    TileCustomData, TileFlip, TileFrameAnimation, TileLayers, TileOccluder, TilePos, TilePosOld,
+    TileRotation, TileShape, TileStorage, TileTextureIndex, TileVisible, TileZOffset,
 };
 
 #[cfg(all(not(feature = "atlas"), feature = "render"))]
@@ -48,12 +61,37 @@ pub mod anchor;
 /// A module that allows pre-loading of atlases into array textures.
 #[cfg(all(not(feature = "atlas"), feature = "render"))]
 mod array_texture_preload;
+/// A module which contains a builder for tilemap bundles.
+#[cfg(feature = "render")]
+pub mod builder;
+/// A module which extends `Commands` with methods for spawning tilemaps and changing their tiles.
+pub mod commands;
+/// A module which draws gizmos for debugging tilemaps.
+#[cfg(feature = "debug")]
+pub mod debug;
+/// A module which contains diagnostics for the tilemap renderer.
+#[cfg(feature = "render")]
+pub mod diagnostics;
 /// A module which provides helper functions.
 pub mod helpers;
+/// A module which loads projects made with the LDtk editor.
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
 /// A module which contains tilemap components.
 pub mod map;
+/// A module which throttles tiles outside of regions of interest.
+pub mod region_of_interest;
 #[cfg(feature = "render")]
 pub(crate) mod render;
+/// A module which saves tilemaps and spawns them again.
+#[cfg(feature = "serde")]
+pub mod save;
+/// A module which renders tilemaps headlessly for snapshot tests.
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+/// A module which loads maps made with the Tiled editor.
+#[cfg(feature = "tiled")]
+pub mod tiled;
 /// A module which contains tile components.
 pub mod tiles;
 
@@ -65,8 +103,55 @@ impl Plugin for TilemapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         #[cfg(feature = "render")]
         app.add_plugins(render::TilemapRenderingPlugin);
+        #[cfg(feature = "serde")]
+        tiles::build_tileset_manifests(app);
 
-        app.add_systems(First, update_changed_tile_positions.in_set(TilemapFirstSet));
+        app.init_resource::<AnimationGroupSpeeds>()
+            .init_resource::<RegionsOfInterest>()
+            .init_resource::<TileEntityPool>()
+            .init_resource::<TileAnimationTable>()
+            .init_resource::<TilemapLayerOrder>()
+            .add_systems(
+                First,
+                (update_changed_tile_positions, tiles::sync_animation_groups)
+                    .in_set(TilemapFirstSet),
+            );
+        app.add_systems(
+            PostUpdate,
+            (
+                map::update_tilemap_world_bounds.after(TransformSystems::Propagate),
+                map::apply_tilemap_perf_settings
+                    .before(map::update_tilemap_update_states)
+                    .before(region_of_interest::update_camera_regions_of_interest),
+                map::update_tilemap_update_states,
+                map::advance_tilemap_times,
+                map::update_dirty_chunks,
+                tiles::update_paused_animations,
+                tiles::update_removed_tile_layers,
+                tiles::update_removed_tile_custom_data,
+                tiles::update_removed_tile_occluders,
+                tiles::update_removed_tile_rotations,
+                tiles::update_removed_tile_shapes,
+                tiles::update_removed_tile_z_offsets,
+                tiles::sync_layered_tile_storages,
+                tiles::update_removed_animation_phases,
+                tiles::update_removed_frame_animations,
+                tiles::animate_tile_colors,
+                tiles::sync_translated_tile_positions,
+                tiles::rebuild_missing_tile_storages,
+                (
+                    region_of_interest::update_camera_regions_of_interest,
+                    region_of_interest::throttle_tiles_outside_regions_of_interest,
+                )
+                    .chain()
+                    .after(TransformSystems::Propagate),
+            ),
+        );
+
+        app.add_observer(map::mark_removed_tile_chunks);
+
+        #[cfg(feature = "debug")]
+        app.add_systems(PostUpdate, tiles::validate_tile_texture_indices);
 
         #[cfg(all(not(feature = "atlas"), feature = "render"))]
         {
@@ -79,20 +164,58 @@ impl Plugin for TilemapPlugin {
             .register_type::<TilemapId>()
             .register_type::<TilemapSize>()
             .register_type::<TilemapTexture>()
+            .register_type::<TilemapSecondaryTexture>()
             .register_type::<TilemapTileSize>()
             .register_type::<TilemapGridSize>()
+            .register_type::<TilemapPerfSettings>()
             .register_type::<TilemapSpacing>()
             .register_type::<TilemapTextureSize>()
+            .register_type::<TilemapTextureAtlas>()
+            .register_type::<TilemapTilesets>()
+            .register_type::<TilemapTexturePadding>()
             .register_type::<TilemapType>()
             .register_type::<TilemapAnchor>()
+            .register_type::<TilemapWorldBounds>()
+            .register_type::<TilemapUpdateMode>()
+            .register_type::<TilemapUpdateState>()
+            .register_type::<TilemapTimeScale>()
+            .register_type::<TilemapTime>()
+            .register_type::<TilemapCustomData>()
+            .register_type::<TilemapRenderSettings>()
+            .register_type::<TilemapChunkSize>()
             .register_type::<TilePos>()
             .register_type::<TileTextureIndex>()
             .register_type::<TileColor>()
             .register_type::<TileVisible>()
+            .register_type::<TileOccluder>()
+            .register_type::<TileZOffset>()
+            .register_type::<TileRotation>()
+            .register_type::<TileShape>()
             .register_type::<TileFlip>()
             .register_type::<TileStorage>()
+            .register_type::<SparseTileStorage>()
+            .register_type::<LayeredTileStorage>()
             .register_type::<TilePosOld>()
             .register_type::<AnimatedTile>()
+            .register_type::<AnimationGroup>()
+            .register_type::<AnimationPaused>()
+            .register_type::<AnimationPhase>()
+            .register_type::<TileFrameAnimation>()
+            .register_type::<TileLayers>()
+            .register_type::<TileCustomData>()
+            .register_type::<TileColorAnimation>()
+            .register_type::<TilemapLayerBlendModes>()
+            .register_type::<TilemapGridDistortion>()
+            .register_type::<TilemapColor>()
+            .register_type::<TilemapOcclusionReveal>()
+            .register_type::<TilemapLayer>()
+            .register_type::<TilemapRenderMode>()
+            .register_type::<TilemapDepthMode>()
+            .register_type::<TilemapTopology>()
+            .register_type::<TilemapColorGrading>()
+            .register_type::<RegionOfInterestCamera>()
+            .register_type::<RegionOfInterestThrottling>()
+            .register_type::<OutsideRegionOfInterest>()
             .configure_sets(First, TilemapFirstSet.after(TimeSystems));
     }
 }
@@ -176,11 +299,20 @@ pub mod prelude {
     pub use crate::anchor::TilemapAnchor;
     #[cfg(all(not(feature = "atlas"), feature = "render"))]
     pub use crate::array_texture_preload::*;
+    #[cfg(feature = "render")]
+    pub use crate::builder::TilemapBuilder;
+    pub use crate::commands::{TilemapCommands, TilemapEntityCommands};
     pub use crate::helpers;
     pub use crate::helpers::filling::*;
     pub use crate::helpers::geometry::*;
     pub use crate::helpers::transform::*;
+    #[cfg(feature = "ldtk")]
+    pub use crate::ldtk::{
+        LdtkEntity, LdtkFieldValue, LdtkFields, LdtkLayer, LdtkMap, LdtkMapConfig, LdtkMapHandle,
+        LdtkPlugin,
+    };
     pub use crate::map::*;
+    pub use crate::region_of_interest::*;
     #[cfg(feature = "render")]
     pub use crate::render::material::MaterialTilemap;
     #[cfg(feature = "render")]
@@ -191,6 +323,10 @@ pub mod prelude {
     pub use crate::render::material::MaterialTilemapPlugin;
     #[cfg(feature = "render")]
     pub use crate::render::material::StandardTilemapMaterial;
+    #[cfg(feature = "tiled")]
+    pub use crate::tiled::{
+        TiledLayer, TiledMap, TiledMapEntities, TiledMapHandle, TiledMapPlugin, TiledObject,
+    };
     pub use crate::tiles::*;
 }
 
@@ -1,17 +1,32 @@
 use bevy::{
-    asset::Assets,
+    asset::{AssetEvent, AssetId, Assets},
     camera::visibility::{VisibilityClass, add_visibility_class},
     ecs::{
         entity::{EntityMapper, MapEntities},
+        lifecycle::HookContext,
         reflect::ReflectMapEntities,
+        world::DeferredWorld,
     },
-    math::{UVec2, Vec2},
+    image::{TextureAtlasLayout, TextureFormatPixelInfo},
+    math::{IVec2, Rect, URect, UVec2, Vec2, Vec3, Vec4},
+    platform::collections::HashSet,
     prelude::{
-        Component, Deref, DerefMut, Entity, Handle, Image, Reflect, ReflectComponent, Res, ResMut,
+        Changed, Color, Commands, Component, Deref, DerefMut, DetectChanges, DetectChangesMut,
+        Entity, GlobalTransform, Handle, Has, Image, MessageReader, Mut, On, Or, Query, Ref,
+        Reflect, ReflectComponent, Remove, Res, ResMut, Resource, Time,
     },
     render::render_resource::TextureUsages,
 };
+use std::borrow::Cow;
 use std::ops::Add;
+use std::time::Duration;
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::transform::chunk_aabb;
+use crate::region_of_interest::RegionOfInterestThrottling;
+use crate::tiles::{
+    MAX_TILE_LAYERS, TileColor, TileFlip, TilePos, TilePosOld, TileTextureIndex, TileVisible,
+};
 
 /// The default chunk_size (in tiles) used per mesh.
 pub const CHUNK_SIZE_2D: UVec2 = UVec2::from_array([64, 64]);
@@ -19,7 +34,9 @@ pub const CHUNK_SIZE_2D: UVec2 = UVec2::from_array([64, 64]);
 /// Custom parameters for the render pipeline.
 ///
 /// It must be added as a component to the tilemap entity.
-#[derive(Component, Debug, Copy, Clone)]
+#[derive(Component, Reflect, Debug, Copy, Clone)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[require(VisibilityClass)]
 #[component(on_add = add_visibility_class::<TilemapRenderSettings>)]
 pub struct TilemapRenderSettings {
@@ -48,10 +65,543 @@ impl Default for TilemapRenderSettings {
     }
 }
 
+/// The dimensions of the chunks of a tilemap, in tiles, replacing the
+/// [`render_chunk_size`](TilemapRenderSettings::render_chunk_size) of its render settings.
+///
+/// Chunks are also the unit of culling: changes to the tiles of chunks which no camera sees are
+/// not extracted to the render world until the chunk comes into view. Small chunks suit tilemaps
+/// which are much larger than the screen, as only the chunks around the view are kept up to date.
+/// Changing the chunk size rebuilds every chunk of the tilemap.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Deref)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapChunkSize(pub UVec2);
+
+impl TilemapChunkSize {
+    /// Returns the chunk size of a tilemap with the given render settings and chunk size.
+    pub fn resolve(chunk_size: Option<&Self>, render_settings: &TilemapRenderSettings) -> UVec2 {
+        chunk_size.map_or(render_settings.render_chunk_size, |chunk_size| chunk_size.0)
+    }
+}
+
+/// When the chunks of a [`TilemapDirtyChunks`] become clean again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DirtyChunksMode {
+    /// Only the chunks changed in the last frame are dirty, to redo per-chunk work like colliders,
+    /// lightmaps or minimaps every frame.
+    #[default]
+    LastFrame,
+    /// Changed chunks stay dirty until they are [`take`](TilemapDirtyChunks::take)n, e.g. by an
+    /// autosave which runs every few seconds.
+    UntilTaken,
+}
+
+/// The chunks of a tilemap whose tiles changed, to redo per-chunk work only where needed.
+///
+/// It must be added as a component to the tilemap entity, and is updated in `PostUpdate`. A chunk
+/// is dirty when a tile in it was spawned or despawned, moved into or out of it, or had its
+/// texture, visibility, flip or color changed. Changes to other components can be
+/// [`mark`](Self::mark)ed by hand. All chunks are dirty after the component is added and after
+/// the chunk size changed.
+///
+/// The chunks are the render chunks of the tilemap, see [`TilemapChunkSize`], unless another size
+/// is given with [`with_chunk_size`](Self::with_chunk_size). How long they stay dirty depends on
+/// the [`DirtyChunksMode`].
+#[derive(Component, Clone, Debug, Default)]
+#[component(on_insert = TilemapDirtyChunks::on_insert)]
+pub struct TilemapDirtyChunks {
+    mode: DirtyChunksMode,
+    /// The chunk size which replaces the render chunk size, if any.
+    fixed_chunk_size: Option<UVec2>,
+    chunk_size: UVec2,
+    chunks: HashSet<UVec2>,
+    /// Chunks of the tiles changed since the last update.
+    marked: HashSet<UVec2>,
+}
+
+impl TilemapDirtyChunks {
+    pub fn new(mode: DirtyChunksMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Uses chunks of the given size, in tiles, instead of the render chunks of the tilemap.
+    pub fn with_chunk_size(mut self, chunk_size: UVec2) -> Self {
+        self.fixed_chunk_size = Some(chunk_size);
+        self
+    }
+
+    pub fn mode(&self) -> DirtyChunksMode {
+        self.mode
+    }
+
+    /// Returns the size of the chunks, in tiles.
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// Returns the chunk containing `tile_pos`.
+    pub fn chunk_of(&self, tile_pos: &TilePos) -> UVec2 {
+        UVec2::new(tile_pos.x, tile_pos.y) / self.chunk_size.max(UVec2::ONE)
+    }
+
+    /// Marks the chunk containing `tile_pos` as dirty. In the
+    /// [`LastFrame`](DirtyChunksMode::LastFrame) mode it becomes dirty at the next update.
+    pub fn mark(&mut self, tile_pos: &TilePos) {
+        let chunk = self.chunk_of(tile_pos);
+        match self.mode {
+            DirtyChunksMode::LastFrame => self.marked.insert(chunk),
+            DirtyChunksMode::UntilTaken => self.chunks.insert(chunk),
+        };
+    }
+
+    pub fn is_dirty(&self, chunk: UVec2) -> bool {
+        self.chunks.contains(&chunk)
+    }
+
+    /// Returns the dirty chunks, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = UVec2> + '_ {
+        self.chunks.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the dirty chunks, ordered by row, and marks every chunk clean.
+    pub fn take(&mut self) -> Vec<UVec2> {
+        let mut chunks = self.chunks.drain().collect::<Vec<_>>();
+        chunks.sort_by_key(|chunk| (chunk.y, chunk.x));
+        chunks
+    }
+
+    /// Adds the marked chunks to the dirty chunks, or replaces them in the
+    /// [`LastFrame`](DirtyChunksMode::LastFrame) mode. Only marks the component as changed if they
+    /// differ.
+    fn update(mut dirty_chunks: Mut<Self>, chunk_size: UVec2, map_size: &TilemapSize) {
+        let mut chunks = std::mem::take(&mut dirty_chunks.bypass_change_detection().marked);
+        if chunk_size != dirty_chunks.chunk_size {
+            chunks = Self::all_chunks(chunk_size, map_size);
+        } else if dirty_chunks.mode == DirtyChunksMode::UntilTaken {
+            if chunks.is_subset(&dirty_chunks.chunks) {
+                return;
+            }
+            chunks.extend(dirty_chunks.chunks.iter().copied());
+        }
+        if chunk_size != dirty_chunks.chunk_size || chunks != dirty_chunks.chunks {
+            dirty_chunks.chunk_size = chunk_size;
+            dirty_chunks.chunks = chunks;
+        }
+    }
+
+    /// Returns every chunk of a tilemap of the given size.
+    fn all_chunks(chunk_size: UVec2, map_size: &TilemapSize) -> HashSet<UVec2> {
+        let chunk_size = chunk_size.max(UVec2::ONE);
+        let columns = map_size.x.div_ceil(chunk_size.x);
+        (0..map_size.y.div_ceil(chunk_size.y))
+            .flat_map(|y| (0..columns).map(move |x| UVec2::new(x, y)))
+            .collect()
+    }
+
+    /// Takes the chunk size from the tilemap the component is inserted into, so tiles
+    /// [`mark`](Self::mark)ed before the next update are put in the right chunks, and marks every
+    /// chunk.
+    fn on_insert(mut world: DeferredWorld, context: HookContext) {
+        let tilemap = world.entity(context.entity);
+        let Some(map_size) = tilemap.get::<TilemapSize>().copied() else {
+            return;
+        };
+        let chunk_size = TilemapChunkSize::resolve(
+            tilemap.get::<TilemapChunkSize>(),
+            &tilemap
+                .get::<TilemapRenderSettings>()
+                .copied()
+                .unwrap_or_default(),
+        );
+        let Some(mut dirty_chunks) = world.get_mut::<TilemapDirtyChunks>(context.entity) else {
+            return;
+        };
+        let dirty_chunks = dirty_chunks.bypass_change_detection();
+        dirty_chunks.chunk_size = dirty_chunks.fixed_chunk_size.unwrap_or(chunk_size);
+        dirty_chunks.marked = Self::all_chunks(dirty_chunks.chunk_size, &map_size);
+    }
+}
+
+/// How the tiles of a tilemap are ordered against each other and against other 2d entities, e.g.
+/// sprites.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapRenderMode {
+    /// Each render chunk is drawn at once, at the depth of the tilemap, or of the chunk when
+    /// [`TilemapRenderSettings::y_sort`] is used.
+    #[default]
+    Chunks,
+    /// Each tile is drawn at a depth derived from its world space `y`, plus its
+    /// [`TileZOffset`](crate::tiles::TileZOffset), so sprites placed with
+    /// [`TilemapRenderMode::y_sort_z`] are drawn in front of the tiles above them and behind the
+    /// tiles below them, e.g. trees and walls on an isometric map.
+    ///
+    /// Tiles at the same depth are still drawn together, but maps whose rows are not level, like
+    /// isometric diamond maps, need a draw for each diagonal of every chunk.
+    YSort,
+}
+
+impl TilemapRenderMode {
+    /// Returns the depth something at `translation`, in world space, is sorted at in a tilemap
+    /// of the given size which uses [`TilemapRenderMode::YSort`].
+    ///
+    /// Use it as the `z` of sprites which should be sorted among the tiles, with the `z` of the
+    /// tilemap in `translation`. The same depth is used for chunks with
+    /// [`TilemapRenderSettings::y_sort`].
+    pub fn y_sort_z(translation: Vec3, map_size: &TilemapSize, tile_size: &TilemapTileSize) -> f32 {
+        translation.z + (1.0 - translation.y / (map_size.y as f32 * tile_size.y))
+    }
+}
+
+/// Which depth the tiles of a tilemap write to the depth buffer, for post-processing effects
+/// like fog or outlines which tell layers apart by their depth.
+///
+/// Tiles are only depth tested by default, so the depth buffer does not know about them. Opaque
+/// pixels of tiles which write depth hide what is drawn after them at a lower depth, so 2d
+/// entities which are sorted among the tiles should not be placed behind them.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapDepthMode {
+    /// No depth is written.
+    #[default]
+    None,
+    /// Every tile writes the depth of its chunk, i.e. the `z` of the tilemap.
+    PerChunk,
+    /// Every tile writes a depth derived from the world space `y` of its center, as given by
+    /// [`TilemapRenderMode::y_sort_z`], so tiles lower on the screen are nearer.
+    PerTile,
+}
+
+/// Puts a tilemap on a named or numbered layer, whose depth is given by the [`TilemapLayerOrder`].
+///
+/// Tilemaps on a layer are drawn at the `z` of the layer instead of the `z` of their
+/// [`GlobalTransform`], so the order of ground, decoration and overhead tilemaps is set in one
+/// place. The transform itself is left alone. Tilemaps on a layer which is not in the order keep
+/// their own `z`.
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapLayer {
+    Name(Cow<'static, str>),
+    Id(i32),
+}
+
+impl From<&'static str> for TilemapLayer {
+    fn from(name: &'static str) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl From<String> for TilemapLayer {
+    fn from(name: String) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl From<i32> for TilemapLayer {
+    fn from(id: i32) -> Self {
+        Self::Id(id)
+    }
+}
+
+/// The order of the [`TilemapLayer`]s, from the bottom to the top.
+///
+/// The `n`th layer is drawn at `base_z + n * z_spacing`. With the default spacing of `1.0`, layers
+/// stay apart when [`TilemapRenderSettings::y_sort`] is used.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct TilemapLayerOrder {
+    layers: Vec<TilemapLayer>,
+    pub base_z: f32,
+    pub z_spacing: f32,
+}
+
+impl Default for TilemapLayerOrder {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            base_z: 0.0,
+            z_spacing: 1.0,
+        }
+    }
+}
+
+impl TilemapLayerOrder {
+    /// Orders the given layers from the bottom to the top.
+    pub fn new<L: Into<TilemapLayer>>(layers: impl IntoIterator<Item = L>) -> Self {
+        Self {
+            layers: layers.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a layer on top of the others, or moves it there if it is already in the order.
+    pub fn push(&mut self, layer: impl Into<TilemapLayer>) {
+        let layer = layer.into();
+        self.layers.retain(|other| *other != layer);
+        self.layers.push(layer);
+    }
+
+    /// Inserts a layer right below `above`, or on top if `above` is not in the order.
+    pub fn insert_below(&mut self, layer: impl Into<TilemapLayer>, above: &TilemapLayer) {
+        let layer = layer.into();
+        self.layers.retain(|other| *other != layer);
+        let index = self
+            .layers
+            .iter()
+            .position(|other| other == above)
+            .unwrap_or(self.layers.len());
+        self.layers.insert(index, layer);
+    }
+
+    /// Returns the layers from the bottom to the top.
+    pub fn layers(&self) -> &[TilemapLayer] {
+        &self.layers
+    }
+
+    /// Returns the `z` the tilemaps on `layer` are drawn at, if the layer is in the order.
+    pub fn z(&self, layer: &TilemapLayer) -> Option<f32> {
+        self.layers
+            .iter()
+            .position(|other| other == layer)
+            .map(|index| self.base_z + index as f32 * self.z_spacing)
+    }
+}
+
+/// How an overlay layer of [`TileLayers`](crate::tiles::TileLayers) is blended onto the layers
+/// below it.
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayerBlendMode {
+    /// The layer is drawn over the layers below it, according to its alpha.
+    #[default]
+    Normal,
+    /// The layers below are darkened by multiplying them with the layer's color.
+    Multiply,
+    /// The layer's color is added to the layers below it, e.g. for glows and highlights.
+    Additive,
+}
+
+/// The [`LayerBlendMode`] of each overlay layer of the [`TileLayers`](crate::tiles::TileLayers)
+/// in a tilemap.
+///
+/// It must be added as a component to the tilemap entity. Without it, all layers use
+/// [`LayerBlendMode::Normal`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapLayerBlendModes(pub [LayerBlendMode; MAX_TILE_LAYERS]);
+
+/// Distorts the corners of the tiles in a tilemap with seeded noise, so the grid looks
+/// hand-drawn or organic.
+///
+/// The noise is defined on the corners of the tile grid, so neighboring tiles which share a corner
+/// move it together. Only the rendering is affected: picking helpers such as
+/// [`TilePos::from_world_pos`] keep mapping to the undistorted grid.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapGridDistortion {
+    /// The largest distance a corner is moved, in the local space of the tilemap.
+    pub amplitude: Vec2,
+    /// How quickly the distortion varies across the grid, in noise features per tile. At `1.0`
+    /// every corner moves independently; smaller values give smoother, wavier grids.
+    pub frequency: f32,
+    /// The seed of the noise.
+    pub seed: u32,
+}
+
+impl Default for TilemapGridDistortion {
+    fn default() -> Self {
+        Self {
+            amplitude: Vec2::ZERO,
+            frequency: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl TilemapGridDistortion {
+    /// Returns how far the shader moves the given corner of the tile grid, in the local space of
+    /// the tilemap. Corners are given in tiles, so the bottom left corner of the tile at `(x, y)`
+    /// is `(x, y)`.
+    ///
+    /// While the amplitude is less than half the grid size, the center of a tile moved by the
+    /// average of its corners stays within the tile, so [`TilePos::from_world_pos`] still picks
+    /// the tile drawn there.
+    pub fn offset(&self, corner: Vec2) -> Vec2 {
+        let scaled = corner * self.frequency;
+        let cell = scaled.floor();
+        let f = scaled - cell;
+        let u = f * f * (3.0 - 2.0 * f);
+        let i = cell.as_ivec2();
+        let lattice = |point: IVec2| self.lattice_offset(point);
+        let offset = lattice(i).lerp(lattice(i + IVec2::new(1, 0)), u.x).lerp(
+            lattice(i + IVec2::new(0, 1)).lerp(lattice(i + IVec2::new(1, 1)), u.x),
+            u.y,
+        );
+        offset * self.amplitude
+    }
+
+    /// A random offset in [-1, 1] for a point of the noise lattice, like `lattice_offset` in the
+    /// shader.
+    fn lattice_offset(&self, point: IVec2) -> Vec2 {
+        let seed = UVec2::new(self.seed, self.seed.wrapping_mul(747796405));
+        let hash = pcg2d(point.as_uvec2() ^ seed);
+        hash.as_vec2() / 4294967295.0 * 2.0 - 1.0
+    }
+}
+
+/// The PCG hash of `pcg2d` in the shader.
+fn pcg2d(input: UVec2) -> UVec2 {
+    let mut v = input
+        .wrapping_mul(UVec2::splat(1664525))
+        .wrapping_add(UVec2::splat(1013904223));
+    v.x = v.x.wrapping_add(v.y.wrapping_mul(1664525));
+    v.y = v.y.wrapping_add(v.x.wrapping_mul(1664525));
+    v ^= v >> 16;
+    v.x = v.x.wrapping_add(v.y.wrapping_mul(1664525));
+    v.y = v.y.wrapping_add(v.x.wrapping_mul(1664525));
+    v ^= v >> 16;
+    v
+}
+
+/// A color multiplied into the [`TileColor`](crate::tiles::TileColor) of every tile of a
+/// tilemap, e.g. to fade a roof layer out while the player walks under it.
+///
+/// Changing it only updates a uniform of each chunk, however many tiles the tilemap has.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapColor(pub Color);
+
+impl Default for TilemapColor {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+impl From<Color> for TilemapColor {
+    fn from(color: Color) -> Self {
+        TilemapColor(color)
+    }
+}
+
+/// Fades out the [`TileOccluder`](crate::tiles::TileOccluder) tiles of a tilemap around a point
+/// and inside a region, e.g. the roof tiles around the player, or over the room they entered.
+///
+/// Occluders are faded to [`revealed_alpha`](Self::revealed_alpha) within
+/// [`radius`](Self::radius) of the [`center`](Self::center), and back in over the next
+/// [`falloff`](Self::falloff) world units. Positions are in world space, so the center is
+/// typically copied from the player's translation every frame, which only updates a uniform.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapOcclusionReveal {
+    pub center: Vec2,
+    /// The radius of the circle around the center in which occluders are faded out fully; `0.0`
+    /// disables the circle.
+    pub radius: f32,
+    pub falloff: f32,
+    /// A region, e.g. the interior of a building, in which occluders are faded out fully.
+    pub region: Option<Rect>,
+    /// The alpha occluders are faded to: `0.0` hides them, `0.3` leaves a hint of the roof.
+    pub revealed_alpha: f32,
+}
+
+impl TilemapOcclusionReveal {
+    /// Hides occluders within `radius` of `center`.
+    pub fn circle(center: Vec2, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+            ..Default::default()
+        }
+    }
+
+    /// Hides occluders inside `region`.
+    pub fn region(region: Rect) -> Self {
+        Self {
+            region: Some(region),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for TilemapOcclusionReveal {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            radius: 0.0,
+            falloff: 0.0,
+            region: None,
+            revealed_alpha: 0.0,
+        }
+    }
+}
+
+/// Grades the final colors of the tiles in a tilemap with a lookup table, e.g. to give a single
+/// level a sepia or toxic tint without affecting the rest of the camera's view.
+///
+/// The lookup table is a 2D strip of `N` slices of `N`x`N` texels placed side by side, so the
+/// image is `N * N` texels wide and `N` texels high. Within a slice red increases from left to
+/// right and green from top to bottom, and blue increases from slice to slice. This is the layout
+/// of the strip LUTs exported by most image editors; 3D textures are not supported. The table is
+/// indexed by the sRGB encoded color, and should use an sRGB texture format.
+///
+/// Colors are blended between the nearest texels, regardless of the image's sampler. Until the
+/// image is loaded, or if its size does not match the layout, the colors are left unchanged.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapColorGrading {
+    /// The lookup table image.
+    pub lut: Handle<Image>,
+    /// How much of the graded color is used, from `0.0` (the original color) to `1.0`.
+    pub strength: f32,
+}
+
+impl TilemapColorGrading {
+    /// Grades the tilemap fully with the given lookup table.
+    pub fn new(lut: Handle<Image>) -> Self {
+        Self { lut, strength: 1.0 }
+    }
+}
+
+impl Default for TilemapColorGrading {
+    fn default() -> Self {
+        Self::new(Handle::default())
+    }
+}
+
 /// A component which stores a reference to the tilemap entity.
 #[derive(Component, Reflect, Clone, Copy, Debug, Hash, Deref, DerefMut, PartialEq, Eq)]
 #[reflect(Component, MapEntities)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[component(map_entities)]
 pub struct TilemapId(pub Entity);
 
 impl MapEntities for TilemapId {
@@ -69,6 +619,7 @@ impl Default for TilemapId {
 /// Size of the tilemap in tiles.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapSize {
     pub x: u32,
     pub y: u32,
@@ -188,6 +739,32 @@ impl TilemapTexture {
         })
     }
 
+    /// Returns the number of tile textures in the tileset, or `None` if its images are not loaded
+    /// yet.
+    ///
+    /// A single image is split into tiles of `tile_size`, with `spacing` between them.
+    pub fn tile_count(
+        &self,
+        tile_size: &TilemapTileSize,
+        spacing: &TilemapSpacing,
+        images: &Assets<Image>,
+    ) -> Option<u32> {
+        match self {
+            TilemapTexture::Single(handle) => {
+                let texture_size = images.get(handle)?.size_f32();
+                let tile_count_x = (texture_size.x / (tile_size.x + spacing.x)).floor();
+                let tile_count_y = (texture_size.y / (tile_size.y + spacing.y)).floor();
+                Some((tile_count_x * tile_count_y) as u32)
+            }
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::Vector(handles) => Some(handles.len() as u32),
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::TextureContainer(handle) => {
+                Some(images.get(handle)?.texture_descriptor.array_layer_count())
+            }
+        }
+    }
+
     /// Sets images with the `COPY_SRC` flag.
     pub fn set_images_to_copy_src(&self, images: &mut ResMut<Assets<Image>>) {
         for handle in self.image_handles() {
@@ -208,9 +785,25 @@ impl TilemapTexture {
     }
 }
 
+/// A second texture of a tilemap, such as a normal map or an emission map, which is sampled with
+/// the same UVs as its [`TilemapTexture`].
+///
+/// It must have the same layout as the `TilemapTexture`: the same kind, image sizes, tile size and
+/// spacing. Textures which are rebuilt, like the ones of a [`TilemapTextureAtlas`] or a padded
+/// atlas, are sampled as they are before they are rebuilt, so they cannot be paired with one.
+///
+/// Custom [`MaterialTilemap`](crate::render::material::MaterialTilemap) shaders read it with
+/// `sample_secondary_texture` from `bevy_ecs_tilemap::common`, e.g. to light the tilemap with a
+/// normal map. The [`StandardTilemapMaterial`](crate::render::material::StandardTilemapMaterial)
+/// can add it to the color as emission. It reads as transparent black until it has loaded.
+#[derive(Component, Reflect, Clone, Debug, Deref, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TilemapSecondaryTexture(pub TilemapTexture);
+
 /// Size of the tiles in pixels
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialOrd, PartialEq)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapTileSize {
     pub x: f32,
     pub y: f32,
@@ -278,6 +871,7 @@ impl From<Vec2> for TilemapTileSize {
 /// a grid size of 16x8.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialOrd, PartialEq)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapGridSize {
     pub x: f32,
     pub y: f32,
@@ -339,6 +933,7 @@ impl From<&Vec2> for TilemapGridSize {
 /// Defaults to 0.0
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapSpacing {
     pub x: f32,
     pub y: f32,
@@ -382,6 +977,511 @@ impl TilemapSpacing {
     }
 }
 
+/// Builds the [`TilemapTexture`] of a tilemap from an image and a [`TextureAtlasLayout`], such as
+/// the ones made by bevy's `TextureAtlasBuilder` or exported from TexturePacker.
+///
+/// The tiles of a tilemap are sampled from a grid, so once both assets have loaded, the sprites
+/// of the layout are copied into a grid of [`TilemapTileSize`] cells in layout order, and the
+/// tilemap's [`TilemapTexture`], [`TilemapSpacing`] and [`TilemapTextureSize`] are replaced to
+/// match it. [`TileTextureIndex`](crate::tiles::TileTextureIndex) `i` then shows sprite `i` of
+/// the layout. Sprites of another size than the tiles are centered in their cell, and cropped
+/// if they are larger.
+///
+/// The image must be uncompressed and have a single mip level.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct TilemapTextureAtlas {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    #[reflect(ignore)]
+    built: bool,
+}
+
+impl TilemapTextureAtlas {
+    pub fn new(image: Handle<Image>, layout: Handle<TextureAtlasLayout>) -> Self {
+        Self {
+            image,
+            layout,
+            built: false,
+        }
+    }
+
+    /// Returns a grid atlas with a cell of `tile_size` for each sprite of `layout`, copied from
+    /// `image`.
+    ///
+    /// Returns `None` if the layout is empty, or the image has no data, is compressed, or has
+    /// more than one mip level or layer.
+    pub fn build_grid(
+        image: &Image,
+        layout: &TextureAtlasLayout,
+        tile_size: &TilemapTileSize,
+    ) -> Option<Image> {
+        let sprites = layout
+            .textures
+            .iter()
+            .map(|rect| (image, *rect))
+            .collect::<Vec<_>>();
+        build_grid_from_sprites(&sprites, tile_size)
+    }
+}
+
+/// Copies each sprite, given as an image and the area of it which holds the sprite, into a cell
+/// of `cell_size` of a grid atlas, centering sprites of another size and cropping larger ones.
+///
+/// Returns `None` if there are no sprites, or if an image has no data, is compressed, has more
+/// than one mip level or layer, or has another format than the first one.
+fn build_grid_from_sprites(
+    sprites: &[(&Image, URect)],
+    cell_size: &TilemapTileSize,
+) -> Option<Image> {
+    let (first, _) = sprites.first()?;
+    let format = first.texture_descriptor.format;
+    let pixel_size = format.pixel_size().ok()?;
+    for (image, _) in sprites {
+        let descriptor = &image.texture_descriptor;
+        if descriptor.format != format
+            || descriptor.mip_level_count != 1
+            || descriptor.size.depth_or_array_layers != 1
+            || image.data.is_none()
+        {
+            return None;
+        }
+    }
+
+    let cell = UVec2::new(cell_size.x as u32, cell_size.y as u32).max(UVec2::ONE);
+    let count = sprites.len() as u32;
+    let columns = (count as f32).sqrt().ceil() as u32;
+    let size = cell * UVec2::new(columns, count.div_ceil(columns));
+    let mut grid = vec![0; (size.x * size.y) as usize * pixel_size];
+    for (index, (image, rect)) in sprites.iter().enumerate() {
+        let data = image.data.as_ref()?;
+        let image_size = image.size();
+        let index = index as u32;
+        let origin = cell * UVec2::new(index % columns, index / columns);
+        let rect = URect::from_corners(rect.min.min(image_size), rect.max.min(image_size));
+        let copied = rect.size().min(cell);
+        let from = rect.min + (rect.size() - copied) / 2;
+        let to = origin + (cell - copied) / 2;
+        for y in 0..copied.y {
+            let source = ((from.y + y) * image_size.x + from.x) as usize * pixel_size;
+            let target = ((to.y + y) * size.x + to.x) as usize * pixel_size;
+            let length = copied.x as usize * pixel_size;
+            grid[target..target + length].copy_from_slice(&data[source..source + length]);
+        }
+    }
+
+    let mut atlas = (*first).clone();
+    atlas.texture_descriptor.size.width = size.x;
+    atlas.texture_descriptor.size.height = size.y;
+    atlas.data = Some(grid);
+    Some(atlas)
+}
+
+/// Builds the grid atlases of tilemaps with a [`TilemapTextureAtlas`].
+#[allow(clippy::type_complexity)]
+pub(crate) fn build_tilemap_texture_atlases(
+    mut tilemaps: Query<(
+        &mut TilemapTextureAtlas,
+        &mut TilemapTexture,
+        &TilemapTileSize,
+        &mut TilemapSpacing,
+        Option<&mut TilemapTextureSize>,
+    )>,
+    mut images: ResMut<Assets<Image>>,
+    layouts: Option<Res<Assets<TextureAtlasLayout>>>,
+) {
+    let Some(layouts) = layouts else {
+        return;
+    };
+    for (mut atlas, mut texture, tile_size, mut spacing, texture_size) in tilemaps.iter_mut() {
+        if atlas.is_changed() {
+            atlas.bypass_change_detection().built = false;
+        }
+        if atlas.built {
+            continue;
+        }
+        let (Some(image), Some(layout)) = (images.get(&atlas.image), layouts.get(&atlas.layout))
+        else {
+            continue;
+        };
+        let Some(grid) = TilemapTextureAtlas::build_grid(image, layout, tile_size) else {
+            bevy::log::warn!(
+                "Cannot build a tilemap texture from the atlas {:?}: it must be uncompressed, with a single mip level and layer",
+                atlas.image.id()
+            );
+            atlas.bypass_change_detection().built = true;
+            continue;
+        };
+        if let Some(mut texture_size) = texture_size {
+            *texture_size = grid.size_f32().into();
+        }
+        atlas.bypass_change_detection().built = true;
+        *texture = TilemapTexture::Single(images.add(grid));
+        *spacing = TilemapSpacing::zero();
+    }
+}
+
+/// An image of a [`TilemapTilesets`], split into `columns` by `rows` tiles of `tile_size`, with
+/// `spacing` between them and around them.
+#[derive(Reflect, Clone, Debug)]
+pub struct Tileset {
+    pub image: Handle<Image>,
+    pub tile_size: TilemapTileSize,
+    pub spacing: TilemapSpacing,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Tileset {
+    pub fn new(image: Handle<Image>, tile_size: TilemapTileSize, columns: u32, rows: u32) -> Self {
+        Self {
+            image,
+            tile_size,
+            spacing: TilemapSpacing::zero(),
+            columns,
+            rows,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: TilemapSpacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Returns the area of the image which holds the tile with the given index.
+    fn tile_rect(&self, index: u32) -> URect {
+        let tile_size = UVec2::new(self.tile_size.x as u32, self.tile_size.y as u32);
+        let spacing = UVec2::new(self.spacing.x as u32, self.spacing.y as u32);
+        let min = spacing
+            + (tile_size + spacing) * UVec2::new(index % self.columns, index / self.columns);
+        URect::from_corners(min, min + tile_size)
+    }
+}
+
+/// Builds the [`TilemapTexture`] of a tilemap from several tilesets, so a single tilemap can mix
+/// tiles of all of them, and Y-sort them against each other.
+///
+/// The tiles of the tilesets are numbered one tileset after the other, like the global tile ids
+/// of Tiled: [`texture_index`](Self::texture_index) returns the
+/// [`TileTextureIndex`](crate::tiles::TileTextureIndex) of a tile of a tileset. Once the images
+/// of all tilesets have loaded, their tiles are copied into a grid of [`TilemapTileSize`] cells
+/// in that order, and the tilemap's [`TilemapTexture`], [`TilemapSpacing`] and
+/// [`TilemapTextureSize`] are replaced to match it. If a tileset has larger tiles than the
+/// tilemap, the cells grow to fit the largest of them, see [`cell_size`](Self::cell_size), and
+/// the [`TilemapTileSize`] of the tilemap is set to the size of a cell. Smaller tiles are centered
+/// in their cell.
+///
+/// The grid is built again when the tilesets change, or when one of their images is modified,
+/// e.g. by hot reloading.
+///
+/// The images must be uncompressed, share the same format and have a single mip level.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct TilemapTilesets {
+    pub tilesets: Vec<Tileset>,
+    #[reflect(ignore)]
+    built: bool,
+}
+
+impl TilemapTilesets {
+    pub fn new(tilesets: impl IntoIterator<Item = Tileset>) -> Self {
+        Self {
+            tilesets: tilesets.into_iter().collect(),
+            built: false,
+        }
+    }
+
+    /// Returns the texture index of the first tile of the tileset with the given index.
+    pub fn first_index(&self, tileset: usize) -> u32 {
+        self.tilesets
+            .iter()
+            .take(tileset)
+            .map(Tileset::tile_count)
+            .sum()
+    }
+
+    /// Returns the texture index of tile `index` of the tileset with the given index, or `None`
+    /// if there is no such tile.
+    pub fn texture_index(&self, tileset: usize, index: u32) -> Option<TileTextureIndex> {
+        (index < self.tilesets.get(tileset)?.tile_count())
+            .then(|| TileTextureIndex(self.first_index(tileset) + index))
+    }
+
+    /// Returns the tileset and the index in it of a texture index, the reverse of
+    /// [`texture_index`](Self::texture_index).
+    pub fn tileset_of(&self, texture_index: TileTextureIndex) -> Option<(usize, u32)> {
+        let mut index = texture_index.0;
+        for (tileset_index, tileset) in self.tilesets.iter().enumerate() {
+            if index < tileset.tile_count() {
+                return Some((tileset_index, index));
+            }
+            index -= tileset.tile_count();
+        }
+        None
+    }
+
+    /// Returns the size of the cells of the grid atlas for a tilemap with tiles of `tile_size`:
+    /// large enough for those and for the tiles of every tileset.
+    pub fn cell_size(&self, tile_size: &TilemapTileSize) -> TilemapTileSize {
+        self.tilesets
+            .iter()
+            .fold(*tile_size, |cell_size, tileset| TilemapTileSize {
+                x: cell_size.x.max(tileset.tile_size.x),
+                y: cell_size.y.max(tileset.tile_size.y),
+            })
+    }
+
+    /// Returns a grid atlas with a cell of [`cell_size`](Self::cell_size) for each tile of the
+    /// tilesets, or `None` if an image has not loaded yet or cannot be copied.
+    pub fn build_grid(&self, images: &Assets<Image>, tile_size: &TilemapTileSize) -> Option<Image> {
+        let mut sprites = Vec::new();
+        for tileset in &self.tilesets {
+            let image = images.get(&tileset.image)?;
+            sprites
+                .extend((0..tileset.tile_count()).map(|index| (image, tileset.tile_rect(index))));
+        }
+        build_grid_from_sprites(&sprites, &self.cell_size(tile_size))
+    }
+}
+
+/// Builds the grid atlases of tilemaps with [`TilemapTilesets`].
+#[allow(clippy::type_complexity)]
+pub(crate) fn build_tilemap_tilesets(
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    mut tilemaps: Query<(
+        &mut TilemapTilesets,
+        &mut TilemapTexture,
+        &mut TilemapTileSize,
+        &mut TilemapSpacing,
+        Option<&mut TilemapTextureSize>,
+    )>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let modified_images = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    for (mut tilesets, mut texture, mut tile_size, mut spacing, texture_size) in tilemaps.iter_mut()
+    {
+        if tilesets.is_changed()
+            || tilesets
+                .tilesets
+                .iter()
+                .any(|tileset| modified_images.contains(&tileset.image.id()))
+        {
+            tilesets.bypass_change_detection().built = false;
+        }
+        if tilesets.built
+            || tilesets
+                .tilesets
+                .iter()
+                .any(|tileset| !images.contains(&tileset.image))
+        {
+            continue;
+        }
+        tilesets.bypass_change_detection().built = true;
+        let Some(grid) = tilesets.build_grid(&images, &tile_size) else {
+            bevy::log::warn!(
+                "Cannot build a tilemap texture from the tilesets: their images must be uncompressed, share a format, and have a single mip level and layer"
+            );
+            continue;
+        };
+        if let Some(mut texture_size) = texture_size {
+            *texture_size = grid.size_f32().into();
+        }
+        tile_size.set_if_neq(tilesets.cell_size(&tile_size));
+        *texture = TilemapTexture::Single(images.add(grid));
+        *spacing = TilemapSpacing::zero();
+    }
+}
+
+/// Pads the tiles of a [`TilemapTexture::Single`] atlas by copying the pixels along their edges
+/// into gutters around them, so tightly packed tiles do not bleed into each other when the
+/// tilemap is scaled or zoomed.
+///
+/// Once the atlas has loaded it is replaced by a padded copy, and the [`TilemapSpacing`] and
+/// [`TilemapTextureSize`] of the tilemap are updated to match it. Without padding the shader keeps
+/// its samples half a texel inside each tile instead, which avoids most bleeding but slightly
+/// shrinks the edge texels. Setting `pixels` back to `0` restores the original atlas.
+///
+/// The atlas must be uncompressed and have a single mip level. If it cannot be padded, a warning
+/// is logged and `pixels` is set back to `0`.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct TilemapTexturePadding {
+    /// The number of pixels copied out from each edge of a tile.
+    pub pixels: u32,
+    #[reflect(ignore)]
+    padded: Option<PaddedAtlas>,
+}
+
+/// The atlas a [`TilemapTexturePadding`] replaced, and the padded copy it was replaced with.
+#[derive(Clone, Debug)]
+struct PaddedAtlas {
+    source: Handle<Image>,
+    source_spacing: TilemapSpacing,
+    padded: AssetId<Image>,
+    pixels: u32,
+}
+
+impl TilemapTexturePadding {
+    pub const fn new(pixels: u32) -> Self {
+        Self {
+            pixels,
+            padded: None,
+        }
+    }
+
+    /// Returns the number of pixels the edges of the tiles in the current atlas were copied into
+    /// its gutters, which is `0` until the padded atlas is in place.
+    pub fn applied_pixels(&self) -> u32 {
+        self.padded.as_ref().map_or(0, |padded| padded.pixels)
+    }
+
+    /// Returns a copy of the `atlas`, whose tiles are `tile_size` pixels large and `spacing` apart,
+    /// with `pixels` of each tile's edges copied into the gutters around it, and its spacing.
+    ///
+    /// Returns `None` if the atlas has no data, is compressed, or has more than one mip level or
+    /// layer.
+    pub fn pad_atlas(
+        atlas: &Image,
+        tile_size: &TilemapTileSize,
+        spacing: &TilemapSpacing,
+        pixels: u32,
+    ) -> Option<(Image, TilemapSpacing)> {
+        let descriptor = &atlas.texture_descriptor;
+        if descriptor.mip_level_count != 1 || descriptor.size.depth_or_array_layers != 1 {
+            return None;
+        }
+        let pixel_size = descriptor.format.pixel_size().ok()?;
+        let data = atlas.data.as_ref()?;
+
+        let size = UVec2::new(descriptor.size.width, descriptor.size.height);
+        let tile = UVec2::new(tile_size.x as u32, tile_size.y as u32);
+        let gap = UVec2::new(spacing.x as u32, spacing.y as u32);
+        // The same layout the shaders use: a gutter before each tile, including the first.
+        let tiles = ((size.as_vec2() - Vec2::from(*spacing)) / (tile + gap).as_vec2())
+            .round()
+            .as_uvec2();
+        if tiles.min_element() == 0 {
+            return None;
+        }
+
+        let new_gap = UVec2::splat(pixels * 2);
+        let new_size = new_gap + tiles * (tile + new_gap);
+        let mut new_data = vec![0; (new_size.x * new_size.y) as usize * pixel_size];
+        for row in 0..tiles.y {
+            for column in 0..tiles.x {
+                let cell = UVec2::new(column, row);
+                let from = gap + cell * (tile + gap);
+                let to = new_gap + cell * (tile + new_gap);
+                // Every texel of the tile and its padding, taken from the nearest texel of the
+                // tile.
+                for y in 0..tile.y + pixels * 2 {
+                    for x in 0..tile.x + pixels * 2 {
+                        let offset = UVec2::new(x, y).saturating_sub(UVec2::splat(pixels));
+                        let source = from + offset.min(tile - UVec2::ONE);
+                        let target = to - UVec2::splat(pixels) + UVec2::new(x, y);
+                        let source = (source.y * size.x + source.x) as usize * pixel_size;
+                        let target = (target.y * new_size.x + target.x) as usize * pixel_size;
+                        new_data[target..target + pixel_size]
+                            .copy_from_slice(data.get(source..source + pixel_size)?);
+                    }
+                }
+            }
+        }
+
+        let mut padded = atlas.clone();
+        padded.texture_descriptor.size.width = new_size.x;
+        padded.texture_descriptor.size.height = new_size.y;
+        padded.data = Some(new_data);
+        Some((
+            padded,
+            TilemapSpacing::new(new_gap.x as f32, new_gap.y as f32),
+        ))
+    }
+}
+
+/// Replaces the atlases of tilemaps with a [`TilemapTexturePadding`] by their padded copies.
+pub(crate) fn pad_tilemap_textures(
+    mut tilemaps: Query<(
+        &mut TilemapTexturePadding,
+        &mut TilemapTexture,
+        &TilemapTileSize,
+        &mut TilemapSpacing,
+        Option<&mut TilemapTextureSize>,
+    )>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (mut padding, mut texture, tile_size, mut spacing, texture_size) in tilemaps.iter_mut() {
+        #[cfg_attr(feature = "atlas", allow(clippy::infallible_destructuring_match))]
+        let handle = match &*texture {
+            TilemapTexture::Single(handle) => handle,
+            #[cfg(not(feature = "atlas"))]
+            _ => continue,
+        };
+        let current = padding
+            .padded
+            .as_ref()
+            .filter(|padded| padded.padded == handle.id());
+        if current.is_some_and(|padded| padded.pixels == padding.pixels) {
+            continue;
+        }
+        // Padding is always redone from the original atlas.
+        let (source, source_spacing) = match current {
+            Some(padded) => (padded.source.clone(), padded.source_spacing),
+            None => (handle.clone(), *spacing),
+        };
+
+        if padding.pixels == 0 {
+            if current.is_some() {
+                if let Some(mut texture_size) = texture_size
+                    && let Some(atlas) = images.get(&source)
+                {
+                    *texture_size = atlas.size_f32().into();
+                }
+                *texture = TilemapTexture::Single(source);
+                *spacing = source_spacing;
+            }
+            padding.bypass_change_detection().padded = None;
+            continue;
+        }
+
+        let Some(atlas) = images.get(&source) else {
+            continue;
+        };
+        let Some((padded, padded_spacing)) =
+            TilemapTexturePadding::pad_atlas(atlas, tile_size, &source_spacing, padding.pixels)
+        else {
+            bevy::log::warn!(
+                "Cannot pad the tilemap atlas {:?}: it must be uncompressed, with a single mip level and layer",
+                source.id()
+            );
+            padding.pixels = 0;
+            continue;
+        };
+        if let Some(mut texture_size) = texture_size {
+            *texture_size = padded.size_f32().into();
+        }
+        let padded = images.add(padded);
+        padding.padded = Some(PaddedAtlas {
+            source,
+            source_spacing,
+            padded: padded.id(),
+            pixels: padding.pixels,
+        });
+        *texture = TilemapTexture::Single(padded);
+        *spacing = padded_spacing;
+    }
+}
+
 /// Size of the atlas texture in pixels.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
 #[reflect(Component)]
@@ -442,6 +1542,7 @@ impl From<TilemapTileSize> for TilemapTextureSize {
 
 /// Different hex grid coordinate systems. You can find out more at this link: <https://www.redblobgames.com/grids/hexagons/>
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HexCoordSystem {
     RowEven,
     RowOdd,
@@ -453,6 +1554,7 @@ pub enum HexCoordSystem {
 
 /// Different isometric coordinate systems.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IsoCoordSystem {
     Diamond,
     Staggered,
@@ -461,6 +1563,7 @@ pub enum IsoCoordSystem {
 /// The type of tile to be rendered, currently we support: Square, Hex, and Isometric.
 #[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TilemapType {
     /// A tilemap with rectangular tiles.
     Square,
@@ -480,9 +1583,626 @@ impl Default for TilemapType {
     }
 }
 
+/// How the edges of a tilemap connect, e.g. for world maps which scroll seamlessly around the X
+/// axis.
+///
+/// A wrapping tilemap continues with its first column (or row) past its last one: neighbor helpers
+/// like [`Neighbors`](crate::helpers::square_grid::neighbors::Neighbors) and
+/// [`HexNeighbors`](crate::helpers::hex_grid::neighbors::HexNeighbors) find the tiles across the
+/// edge, [`TilePos::from_world_pos_wrapped`] finds the tile under any point, and the tilemap is
+/// drawn again next to each edge it wraps around, wherever a camera sees past the edge. The view
+/// of a camera should be smaller than the tilemap, as only the adjacent copies are drawn.
+///
+/// On hexagonal maps with an offset coordinate system, e.g. [`HexCoordSystem::RowEven`], the
+/// number of tiles along a wrapping axis which is offset (the rows of a `Row*` map, or the columns
+/// of a `Column*` map) must be even, so the offsets line up across the edge.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapTopology {
+    /// The tilemap ends at its edges.
+    #[default]
+    Bounded,
+    /// The left and right edges are joined, like a cylinder.
+    WrapX,
+    /// The bottom and top edges are joined, like a cylinder.
+    WrapY,
+    /// Both pairs of edges are joined, like a torus.
+    WrapBoth,
+}
+
+impl TilemapTopology {
+    pub fn wraps_x(&self) -> bool {
+        matches!(self, Self::WrapX | Self::WrapBoth)
+    }
+
+    pub fn wraps_y(&self) -> bool {
+        matches!(self, Self::WrapY | Self::WrapBoth)
+    }
+
+    /// Returns the tile at the given coordinates, which may lie past the edges of the map.
+    ///
+    /// Coordinates past a wrapping edge are wrapped around it, any number of times; `None` is
+    /// returned for coordinates past a bounded edge.
+    pub fn wrap(&self, coords: IVec2, map_size: &TilemapSize) -> Option<TilePos> {
+        let wrap_axis = |value: i32, size: u32, wraps: bool| {
+            if wraps && size > 0 {
+                Some(value.rem_euclid(size as i32) as u32)
+            } else {
+                (0..size as i32).contains(&value).then_some(value as u32)
+            }
+        };
+        Some(TilePos::new(
+            wrap_axis(coords.x, map_size.x, self.wraps_x())?,
+            wrap_axis(coords.y, map_size.y, self.wraps_y())?,
+        ))
+    }
+
+    /// Returns the offset between a tile and its copy one map width further along the X axis, in
+    /// the local space of the tilemap, if the map wraps around that axis.
+    pub fn period_x(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Option<Vec2> {
+        self.wraps_x()
+            .then(|| period(TilePos::new(map_size.x, 0), grid_size, map_type))
+    }
+
+    /// Returns the offset between a tile and its copy one map height further along the Y axis, in
+    /// the local space of the tilemap, if the map wraps around that axis.
+    pub fn period_y(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Option<Vec2> {
+        self.wraps_y()
+            .then(|| period(TilePos::new(0, map_size.y), grid_size, map_type))
+    }
+}
+
+fn period(tile_pos: TilePos, grid_size: &TilemapGridSize, map_type: &TilemapType) -> Vec2 {
+    tile_pos.center_in_world_unanchored(grid_size, map_type)
+        - TilePos::new(0, 0).center_in_world_unanchored(grid_size, map_type)
+}
+
+/// The axis-aligned bounding rectangle of a tilemap in world space, taking its anchor and
+/// [`GlobalTransform`] into account.
+///
+/// It is inserted and kept up to date automatically for every tilemap, and only written when the
+/// bounds actually change. Systems which depend on the extents of a tilemap (camera clamping,
+/// physics world bounds, minimap scaling) can use `Changed<TilemapWorldBounds>` instead of
+/// recomputing them every frame.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapWorldBounds(pub Rect);
+
+impl TilemapWorldBounds {
+    /// Calculates the world space bounds of a tilemap.
+    pub fn from_tilemap(
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+        global_transform: &GlobalTransform,
+    ) -> Self {
+        if map_size.x == 0 || map_size.y == 0 {
+            let origin = global_transform.translation().truncate();
+            return Self(Rect::from_corners(origin, origin));
+        }
+
+        let aabb = chunk_aabb(
+            UVec2::new(map_size.x - 1, map_size.y - 1),
+            grid_size,
+            tile_size,
+            map_type,
+        );
+        let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+        let min = Vec2::new(aabb.min().x, aabb.min().y) + offset;
+        let max = Vec2::new(aabb.max().x, aabb.max().y) + offset;
+
+        let mut bounds = Rect {
+            min: Vec2::splat(f32::INFINITY),
+            max: Vec2::splat(f32::NEG_INFINITY),
+        };
+        for corner in [min, Vec2::new(min.x, max.y), max, Vec2::new(max.x, min.y)] {
+            let corner = global_transform
+                .transform_point(corner.extend(0.0))
+                .truncate();
+            bounds.min = bounds.min.min(corner);
+            bounds.max = bounds.max.max(corner);
+        }
+        Self(bounds)
+    }
+}
+
+/// Updates the [`TilemapDirtyChunks`] of tilemaps with the chunks of their changed tiles.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_dirty_chunks(
+    tiles: Query<
+        (&TilemapId, Ref<TilePos>, &TilePosOld),
+        Or<(
+            Changed<TilePos>,
+            Changed<TileTextureIndex>,
+            Changed<TileVisible>,
+            Changed<TileFlip>,
+            Changed<TileColor>,
+        )>,
+    >,
+    mut tilemaps: Query<(
+        &mut TilemapDirtyChunks,
+        &TilemapSize,
+        Option<&TilemapRenderSettings>,
+        Option<&TilemapChunkSize>,
+    )>,
+) {
+    if tilemaps.is_empty() {
+        return;
+    }
+    for (tilemap_id, tile_pos, tile_pos_old) in tiles.iter() {
+        let Ok((mut dirty_chunks, ..)) = tilemaps.get_mut(tilemap_id.0) else {
+            continue;
+        };
+        let dirty_chunks = dirty_chunks.bypass_change_detection();
+        dirty_chunks.marked.insert(dirty_chunks.chunk_of(&tile_pos));
+        // A moved tile also leaves its old chunk.
+        if tile_pos.is_changed() && !tile_pos.is_added() {
+            dirty_chunks
+                .marked
+                .insert(dirty_chunks.chunk_of(&tile_pos_old.0));
+        }
+    }
+    for (dirty_chunks, map_size, render_settings, chunk_size) in tilemaps.iter_mut() {
+        let chunk_size = dirty_chunks.fixed_chunk_size.unwrap_or_else(|| {
+            TilemapChunkSize::resolve(chunk_size, &render_settings.copied().unwrap_or_default())
+        });
+        TilemapDirtyChunks::update(dirty_chunks, chunk_size, map_size);
+    }
+}
+
+/// Marks the chunks of despawned tiles, which leave nothing for [`update_dirty_chunks`] to detect.
+pub(crate) fn mark_removed_tile_chunks(
+    removed: On<Remove, TilePos>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+    mut tilemaps: Query<&mut TilemapDirtyChunks>,
+) {
+    if let Ok((tile_pos, tilemap_id)) = tiles.get(removed.entity)
+        && let Ok(mut dirty_chunks) = tilemaps.get_mut(tilemap_id.0)
+    {
+        let dirty_chunks = dirty_chunks.bypass_change_detection();
+        dirty_chunks.marked.insert(dirty_chunks.chunk_of(tile_pos));
+    }
+}
+
+/// Inserts or updates the [`TilemapWorldBounds`] of every tilemap whose extents may have changed.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_tilemap_world_bounds(
+    mut commands: Commands,
+    mut tilemaps: Query<
+        (
+            Entity,
+            &TilemapSize,
+            &TilemapGridSize,
+            &TilemapTileSize,
+            &TilemapType,
+            &TilemapAnchor,
+            &GlobalTransform,
+            Option<&mut TilemapWorldBounds>,
+        ),
+        Or<(
+            Changed<TilemapSize>,
+            Changed<TilemapGridSize>,
+            Changed<TilemapTileSize>,
+            Changed<TilemapType>,
+            Changed<TilemapAnchor>,
+            Changed<GlobalTransform>,
+        )>,
+    >,
+) {
+    for (entity, map_size, grid_size, tile_size, map_type, anchor, global_transform, bounds) in
+        tilemaps.iter_mut()
+    {
+        let new_bounds = TilemapWorldBounds::from_tilemap(
+            map_size,
+            grid_size,
+            tile_size,
+            map_type,
+            anchor,
+            global_transform,
+        );
+        match bounds {
+            Some(mut bounds) => {
+                bounds.set_if_neq(new_bounds);
+            }
+            None => {
+                commands.entity(entity).insert(new_bounds);
+            }
+        }
+    }
+}
+
+/// Controls how often changes to the tiles of a tilemap are sent to the renderer.
+///
+/// Lowering the update rate of far-away or background tilemaps reduces the cost of rebuilding
+/// their chunk meshes in large worlds. Tile changes are never lost; they are held back until the
+/// next update. GPU-driven tile animations keep playing every frame.
+///
+/// Use [`TilemapUpdateState::request_update`] to force an update on the next frame.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[require(TilemapUpdateState)]
+pub enum TilemapUpdateMode {
+    /// Changes are sent to the renderer every frame.
+    #[default]
+    EveryFrame,
+    /// Changes are sent to the renderer at most once per given interval, e.g.
+    /// `Duration::from_secs_f32(1.0 / 30.0)` for 30 Hz.
+    Interval(Duration),
+    /// Changes are only sent to the renderer when an update was requested.
+    Manual,
+}
+
+/// Tracks when the tiles of a tilemap were last sent to the renderer, according to its
+/// [`TilemapUpdateMode`].
+///
+/// A new tilemap is always updated on its first frame.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapUpdateState {
+    last_update: Duration,
+    requested: bool,
+    due: bool,
+}
+
+impl Default for TilemapUpdateState {
+    fn default() -> Self {
+        Self {
+            last_update: Duration::ZERO,
+            requested: true,
+            due: true,
+        }
+    }
+}
+
+impl TilemapUpdateState {
+    /// Sends pending tile changes to the renderer on the next frame, regardless of the
+    /// [`TilemapUpdateMode`].
+    pub fn request_update(&mut self) {
+        self.requested = true;
+    }
+
+    /// Returns `true` if tile changes are sent to the renderer in the current frame.
+    pub fn is_due(&self) -> bool {
+        self.due
+    }
+}
+
+/// Decides for each tilemap whether its tile changes are sent to the renderer this frame.
+pub(crate) fn update_tilemap_update_states(
+    time: Res<Time>,
+    mut tilemaps: Query<(&TilemapUpdateMode, &mut TilemapUpdateState)>,
+) {
+    let now = time.elapsed();
+    for (mode, mut state) in tilemaps.iter_mut() {
+        let due = state.requested
+            || match mode {
+                TilemapUpdateMode::EveryFrame => true,
+                TilemapUpdateMode::Interval(interval) => {
+                    now.saturating_sub(state.last_update) >= *interval
+                }
+                TilemapUpdateMode::Manual => false,
+            };
+        state.due = due;
+        if due {
+            state.last_update = now;
+            state.requested = false;
+        }
+    }
+}
+
+/// Scales the time which drives the shaders of a tilemap: its tile animations, and the
+/// `tilemap_data.time` that custom [`MaterialTilemap`](crate::render::material::MaterialTilemap)
+/// shaders read, e.g. to scroll water or sway grass. `0.0` freezes the tilemap, and negative
+/// scales play it backwards.
+///
+/// Tilemaps without it follow the global time. The time of the tilemap is kept in its
+/// [`TilemapTime`], so changing the scale does not make it jump.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(TilemapTime)]
+pub struct TilemapTimeScale(pub f32);
+
+impl Default for TilemapTimeScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The time the shaders of a tilemap with a [`TilemapTimeScale`] see, in seconds.
+///
+/// It starts at the global time when it is added, so animations do not restart, and wraps around
+/// with the global time's [`wrap_period`](Time::wrap_period).
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapTime {
+    elapsed_secs: f32,
+}
+
+impl TilemapTime {
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed_secs
+    }
+
+    /// Sets the time of the tilemap, e.g. to synchronize it with another tilemap.
+    pub fn set_elapsed_secs(&mut self, elapsed_secs: f32) {
+        self.elapsed_secs = elapsed_secs;
+    }
+}
+
+/// Advances the [`TilemapTime`] of each tilemap by the frame time, scaled by its
+/// [`TilemapTimeScale`].
+pub(crate) fn advance_tilemap_times(
+    time: Res<Time>,
+    mut tilemaps: Query<(&TilemapTimeScale, &mut TilemapTime)>,
+) {
+    let wrap_period = time.wrap_period().as_secs_f32();
+    for (time_scale, mut tilemap_time) in tilemaps.iter_mut() {
+        tilemap_time.elapsed_secs = if tilemap_time.is_added() {
+            time.elapsed_secs_wrapped()
+        } else {
+            (tilemap_time.elapsed_secs + time.delta_secs() * time_scale.0).rem_euclid(wrap_period)
+        };
+    }
+}
+
+/// Values for the shaders of a tilemap, which custom
+/// [`MaterialTilemap`](crate::render::material::MaterialTilemap) shaders read as
+/// `tilemap_data.custom_data`, e.g. a wind direction and strength, or the offset of a palette
+/// cycle.
+///
+/// Unlike [`TileCustomData`](crate::tiles::TileCustomData), the values are shared by all tiles, and
+/// changing them only updates a uniform of each chunk.
+///
+/// It must be added as a component to the tilemap entity.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapCustomData(pub [Vec4; 4]);
+
+/// The main performance knobs of a tilemap, set together from a preset instead of one subsystem
+/// at a time.
+///
+/// When the component is added or changed, the settings are written to the tilemap's
+/// [`TilemapRenderSettings`], [`TilemapUpdateMode`] and
+/// [`RegionOfInterestThrottling`](crate::region_of_interest::RegionOfInterestThrottling), which
+/// should then be left alone. Add it when spawning the tilemap, since tiles which were already
+/// rendered stay in the chunks of the old chunk size.
+///
+/// | Knob | Raise it when | Lower it when |
+/// |------|---------------|---------------|
+/// | [`render_chunk_size`](Self::render_chunk_size) | Draw calls dominate, tiles rarely change | Single tile edits stall the frame |
+/// | [`update_mode`](Self::update_mode) interval | Many tiles change every frame | Changes must show up immediately |
+///
+/// [`throttle_outside_regions_of_interest`](Self::throttle_outside_regions_of_interest) only pays
+/// off for maps much larger than the screen, and needs a
+/// [`RegionOfInterestCamera`](crate::region_of_interest::RegionOfInterestCamera) on the cameras.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapPerfSettings {
+    /// See [`TilemapRenderSettings::render_chunk_size`].
+    pub render_chunk_size: UVec2,
+    /// How often tile changes are sent to the renderer.
+    pub update_mode: TilemapUpdateMode,
+    /// Whether tiles outside of the [`RegionsOfInterest`](crate::region_of_interest::RegionsOfInterest)
+    /// are throttled.
+    pub throttle_outside_regions_of_interest: bool,
+}
+
+impl TilemapPerfSettings {
+    /// For weaker GPUs and CPUs: small chunks, so edits rebuild little, and tile changes sent at
+    /// 30 Hz.
+    pub const MOBILE: Self = Self {
+        render_chunk_size: UVec2::new(32, 32),
+        update_mode: TilemapUpdateMode::Interval(Duration::from_nanos(1_000_000_000 / 30)),
+        throttle_outside_regions_of_interest: false,
+    };
+
+    /// The crate defaults: chunks of [`CHUNK_SIZE_2D`], updated every frame.
+    pub const DESKTOP: Self = Self {
+        render_chunk_size: CHUNK_SIZE_2D,
+        update_mode: TilemapUpdateMode::EveryFrame,
+        throttle_outside_regions_of_interest: false,
+    };
+
+    /// For maps of millions of tiles: large chunks to keep the number of draw calls down, and
+    /// tiles outside of the regions of interest throttled.
+    pub const HUGE_MAP: Self = Self {
+        render_chunk_size: UVec2::new(128, 128),
+        update_mode: TilemapUpdateMode::EveryFrame,
+        throttle_outside_regions_of_interest: true,
+    };
+}
+
+impl Default for TilemapPerfSettings {
+    fn default() -> Self {
+        Self::DESKTOP
+    }
+}
+
+/// Applies changed [`TilemapPerfSettings`] to the components they stand for.
+pub(crate) fn apply_tilemap_perf_settings(
+    mut commands: Commands,
+    mut tilemaps: Query<
+        (
+            Entity,
+            &TilemapPerfSettings,
+            &mut TilemapRenderSettings,
+            Has<RegionOfInterestThrottling>,
+        ),
+        Changed<TilemapPerfSettings>,
+    >,
+) {
+    for (entity, settings, mut render_settings, throttled) in tilemaps.iter_mut() {
+        if render_settings.render_chunk_size != settings.render_chunk_size {
+            render_settings.render_chunk_size = settings.render_chunk_size;
+        }
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(settings.update_mode);
+        match (settings.throttle_outside_regions_of_interest, throttled) {
+            (true, false) => {
+                entity_commands.insert(RegionOfInterestThrottling::default());
+            }
+            (false, true) => {
+                entity_commands.remove::<RegionOfInterestThrottling>();
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bevy::ecs::{message::Messages, schedule::Schedule, world::World};
+
+    #[test]
+    fn wrapping_maps_join_their_edges() {
+        use crate::helpers::hex_grid::neighbors::{HexDirection, HexNeighbors};
+
+        let map_size = TilemapSize::new(6, 4);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let map_type = TilemapType::Hexagon(HexCoordSystem::RowOdd);
+
+        let wrap_x = TilemapTopology::WrapX;
+        assert_eq!(
+            wrap_x.wrap(IVec2::new(-1, 2), &map_size),
+            Some(TilePos::new(5, 2))
+        );
+        assert_eq!(wrap_x.wrap(IVec2::new(13, 4), &map_size), None);
+        assert_eq!(
+            TilemapTopology::WrapBoth.wrap(IVec2::new(13, 4), &map_size),
+            Some(TilePos::new(1, 0))
+        );
+
+        // The eastern neighbor of a tile on the eastern edge is on the western edge.
+        let neighbors = HexNeighbors::get_neighboring_positions_wrapped(
+            &TilePos::new(5, 1),
+            &map_size,
+            &HexCoordSystem::RowOdd,
+            &wrap_x,
+        );
+        assert_eq!(neighbors.get(HexDirection::Zero), Some(&TilePos::new(0, 1)));
+
+        // A point on the copy of the map to the right shows the same tile.
+        let tile_pos = TilePos::new(2, 1);
+        let center = tile_pos.center_in_world(
+            &map_size,
+            &grid_size,
+            &tile_size,
+            &map_type,
+            &TilemapAnchor::None,
+        );
+        let period = wrap_x.period_x(&map_size, &grid_size, &map_type).unwrap();
+        let from_world_pos = |topology| {
+            TilePos::from_world_pos_wrapped(
+                &(center + period),
+                &map_size,
+                &grid_size,
+                &tile_size,
+                &map_type,
+                &TilemapAnchor::None,
+                &topology,
+            )
+        };
+        assert_eq!(from_world_pos(wrap_x), Some(tile_pos));
+        assert_eq!(from_world_pos(TilemapTopology::Bounded), None);
+    }
+
+    #[test]
+    fn perf_settings_are_applied_to_the_tilemap() {
+        use bevy::ecs::system::RunSystemOnce;
+        use bevy::prelude::World;
+
+        let mut world = World::new();
+        let tilemap = world
+            .spawn((
+                TilemapRenderSettings::default(),
+                TilemapPerfSettings::HUGE_MAP,
+            ))
+            .id();
+        world.run_system_once(apply_tilemap_perf_settings).unwrap();
+        assert_eq!(
+            world
+                .get::<TilemapRenderSettings>(tilemap)
+                .unwrap()
+                .render_chunk_size,
+            UVec2::new(128, 128)
+        );
+        assert!(world.get::<RegionOfInterestThrottling>(tilemap).is_some());
+
+        world
+            .entity_mut(tilemap)
+            .insert(TilemapPerfSettings::MOBILE);
+        world.run_system_once(apply_tilemap_perf_settings).unwrap();
+        assert!(world.get::<RegionOfInterestThrottling>(tilemap).is_none());
+        assert_eq!(
+            world.get::<TilemapUpdateMode>(tilemap),
+            Some(&TilemapPerfSettings::MOBILE.update_mode)
+        );
+        assert!(world.get::<TilemapUpdateState>(tilemap).is_some());
+    }
+
+    #[test]
+    fn tilemap_times_follow_their_scale() {
+        use bevy::prelude::{Schedule, World};
+
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(advance_tilemap_times);
+        let mut step = |world: &mut World| {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(2));
+            schedule.run(world);
+        };
+
+        step(&mut world);
+        let tilemap = world.spawn(TilemapTimeScale(0.5)).id();
+        let elapsed = |world: &World| world.get::<TilemapTime>(tilemap).unwrap().elapsed_secs();
+        // The tilemap starts at the global time.
+        step(&mut world);
+        assert_eq!(elapsed(&world), 4.0);
+        step(&mut world);
+        assert_eq!(elapsed(&world), 5.0);
+
+        world.get_mut::<TilemapTimeScale>(tilemap).unwrap().0 = -1.0;
+        step(&mut world);
+        assert_eq!(elapsed(&world), 3.0);
+    }
+
+    #[test]
+    fn layers_are_spaced_in_order() {
+        let mut order = TilemapLayerOrder::new(["ground", "overhead"]);
+        order.insert_below("decoration", &"overhead".into());
+        order.push(TilemapLayer::Id(7));
+        order.base_z = 10.0;
+        assert_eq!(order.z(&"ground".into()), Some(10.0));
+        assert_eq!(order.z(&"decoration".into()), Some(11.0));
+        assert_eq!(order.z(&"overhead".into()), Some(12.0));
+        assert_eq!(order.z(&7.into()), Some(13.0));
+        assert_eq!(order.z(&"sky".into()), None);
+
+        order.push("ground");
+        assert_eq!(order.z(&"ground".into()), Some(13.0));
+    }
 
     #[test]
     fn add_tilemap_size() {
@@ -537,4 +2257,355 @@ mod tests {
         let b = Vec2 { x: 3., y: 3. };
         assert_eq!(a + b, TilemapTextureSize { x: 5., y: 5. });
     }
+
+    #[test]
+    fn padding_copies_tile_edges_into_gutters() {
+        use bevy::asset::RenderAssetUsages;
+        use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+        // Two 2x2 tiles side by side, without spacing.
+        let atlas = Image::new(
+            Extent3d {
+                width: 4,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            vec![1, 2, 5, 6, 3, 4, 7, 8],
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::default(),
+        );
+        let (padded, spacing) = TilemapTexturePadding::pad_atlas(
+            &atlas,
+            &TilemapTileSize::new(2.0, 2.0),
+            &TilemapSpacing::zero(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(spacing, TilemapSpacing::new(2.0, 2.0));
+        assert_eq!(padded.size(), UVec2::new(10, 6));
+
+        let data = padded.data.unwrap();
+        let texel = |x: usize, y: usize| data[y * 10 + x];
+        // The first tile starts after a gutter, and its corners are copied outwards.
+        assert_eq!(texel(0, 0), 0);
+        assert_eq!(texel(2, 2), 1);
+        assert_eq!(texel(1, 1), 1);
+        assert_eq!(texel(4, 1), 2);
+        assert_eq!(texel(1, 4), 3);
+        // The gutter between the tiles holds the edge of each.
+        assert_eq!(texel(4, 2), 2);
+        assert_eq!(texel(5, 2), 5);
+        assert_eq!(texel(8, 4), 8);
+        assert_eq!(texel(9, 4), 0);
+    }
+
+    #[test]
+    fn atlas_layouts_are_copied_into_a_grid() {
+        use bevy::asset::RenderAssetUsages;
+        use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+        let image = Image::new(
+            Extent3d {
+                width: 6,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            vec![1, 2, 5, 6, 7, 8, 3, 4, 9, 10, 11, 12],
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::default(),
+        );
+        // A sprite the size of a tile, and one twice as wide which gets cropped to its middle.
+        let layout = TextureAtlasLayout {
+            size: UVec2::new(6, 2),
+            textures: vec![URect::new(0, 0, 2, 2), URect::new(2, 0, 6, 2)],
+        };
+        let grid =
+            TilemapTextureAtlas::build_grid(&image, &layout, &TilemapTileSize::new(2.0, 2.0))
+                .unwrap();
+        assert_eq!(grid.size(), UVec2::new(4, 2));
+        assert_eq!(grid.data.unwrap(), vec![1, 2, 6, 7, 3, 4, 10, 11]);
+    }
+
+    #[test]
+    fn tilesets_are_numbered_and_copied_in_order() {
+        use bevy::asset::RenderAssetUsages;
+        use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+        let mut images = Assets::<Image>::default();
+        let mut image = |width, height, data| {
+            images.add(Image::new(
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                TextureFormat::R8Unorm,
+                RenderAssetUsages::default(),
+            ))
+        };
+        // Two 1x1 tiles, and a single 2x2 tile which makes every cell 2x2.
+        let small = image(2, 1, vec![1, 2]);
+        let large = image(2, 2, vec![3, 4, 5, 6]);
+        let tile_size = TilemapTileSize::new(1.0, 1.0);
+        let tilesets = TilemapTilesets::new([
+            Tileset::new(small, tile_size, 2, 1),
+            Tileset::new(large, TilemapTileSize::new(2.0, 2.0), 1, 1),
+        ]);
+
+        assert_eq!(tilesets.texture_index(1, 0), Some(TileTextureIndex(2)));
+        assert_eq!(tilesets.texture_index(0, 2), None);
+        assert_eq!(tilesets.tileset_of(TileTextureIndex(1)), Some((0, 1)));
+        assert_eq!(tilesets.tileset_of(TileTextureIndex(3)), None);
+
+        assert_eq!(
+            tilesets.cell_size(&tile_size),
+            TilemapTileSize::new(2.0, 2.0)
+        );
+        let grid = tilesets.build_grid(&images, &tile_size).unwrap();
+        assert_eq!(grid.size(), UVec2::new(4, 4));
+        #[rustfmt::skip]
+        assert_eq!(grid.data.unwrap(), vec![
+            1, 0, 2, 0,
+            0, 0, 0, 0,
+            3, 4, 0, 0,
+            5, 6, 0, 0,
+        ]);
+
+        // The tilemap grows its tiles to the cells, and rebuilds the grid when an image changes.
+        let mut world = World::new();
+        world.insert_resource(images);
+        world.init_resource::<Messages<AssetEvent<Image>>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(build_tilemap_tilesets);
+        let tilemap = world
+            .spawn((
+                tilesets.clone(),
+                TilemapTexture::default(),
+                tile_size,
+                TilemapSpacing::zero(),
+            ))
+            .id();
+        let grid_data = |world: &World| {
+            let TilemapTexture::Single(grid) = world.get::<TilemapTexture>(tilemap).unwrap() else {
+                panic!("the tilesets were not built into a single texture");
+            };
+            world
+                .resource::<Assets<Image>>()
+                .get(grid)
+                .unwrap()
+                .data
+                .clone()
+                .unwrap()
+        };
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<TilemapTileSize>(tilemap),
+            Some(&TilemapTileSize::new(2.0, 2.0))
+        );
+        assert_eq!(grid_data(&world)[..4], [1, 0, 2, 0]);
+
+        let small = &tilesets.tilesets[0].image;
+        world
+            .resource_mut::<Assets<Image>>()
+            .get_mut(small)
+            .unwrap()
+            .data = Some(vec![7, 8]);
+        world.write_message(AssetEvent::Modified { id: small.id() });
+        schedule.run(&mut world);
+        assert_eq!(grid_data(&world)[..4], [7, 0, 8, 0]);
+    }
+
+    #[test]
+    fn dirty_chunks_last_a_frame_or_until_taken() {
+        let mut world = World::new();
+        world.add_observer(mark_removed_tile_chunks);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_dirty_chunks);
+        let map_size = TilemapSize::new(8, 4);
+        let tilemap = world
+            .spawn((
+                map_size,
+                TilemapChunkSize(UVec2::new(4, 4)),
+                TilemapDirtyChunks::default(),
+            ))
+            .id();
+        let autosaved = world
+            .spawn((
+                map_size,
+                TilemapDirtyChunks::new(DirtyChunksMode::UntilTaken)
+                    .with_chunk_size(UVec2::new(2, 2)),
+            ))
+            .id();
+        let tiles = [
+            (tilemap, TilePos::new(5, 1)),
+            (autosaved, TilePos::new(5, 1)),
+        ]
+        .map(|(tilemap, position)| {
+            world
+                .spawn(crate::tiles::TileBundle {
+                    position,
+                    tilemap_id: TilemapId(tilemap),
+                    old_position: TilePosOld(position),
+                    ..Default::default()
+                })
+                .id()
+        });
+        let dirty_chunks = |world: &mut World, tilemap| {
+            let mut dirty_chunks = world.get::<TilemapDirtyChunks>(tilemap).unwrap().clone();
+            (dirty_chunks.chunk_size(), dirty_chunks.take())
+        };
+
+        // Every chunk is dirty at first, in the render chunks unless told otherwise.
+        schedule.run(&mut world);
+        assert_eq!(
+            dirty_chunks(&mut world, tilemap),
+            (UVec2::new(4, 4), vec![UVec2::new(0, 0), UVec2::new(1, 0)])
+        );
+        assert_eq!(world.get::<TilemapDirtyChunks>(autosaved).unwrap().len(), 8);
+        world
+            .get_mut::<TilemapDirtyChunks>(autosaved)
+            .unwrap()
+            .take();
+
+        // Painting a tile dirties its chunk, and moving one both of its chunks.
+        world.get_mut::<TileTextureIndex>(tiles[0]).unwrap().0 = 3;
+        world.get_mut::<TilePos>(tiles[1]).unwrap().x = 1;
+        schedule.run(&mut world);
+        assert_eq!(dirty_chunks(&mut world, tilemap).1, vec![UVec2::new(1, 0)]);
+        assert_eq!(
+            dirty_chunks(&mut world, autosaved).1,
+            vec![UVec2::new(0, 0), UVec2::new(2, 0)]
+        );
+
+        // Without changes, the chunks of the last frame become clean, but the others are kept
+        // until they are taken.
+        world.despawn(tiles[0]);
+        schedule.run(&mut world);
+        assert_eq!(dirty_chunks(&mut world, tilemap).1, vec![UVec2::new(1, 0)]);
+        schedule.run(&mut world);
+        assert!(world.get::<TilemapDirtyChunks>(tilemap).unwrap().is_empty());
+        assert_eq!(world.get::<TilemapDirtyChunks>(autosaved).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn tiles_marked_before_the_first_update_are_in_their_chunk() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_dirty_chunks);
+        let tilemap = world
+            .spawn((
+                TilemapSize::new(8, 8),
+                TilemapChunkSize(UVec2::new(4, 4)),
+                TilemapDirtyChunks::new(DirtyChunksMode::UntilTaken),
+            ))
+            .id();
+
+        let mut dirty_chunks = world.get_mut::<TilemapDirtyChunks>(tilemap).unwrap();
+        assert_eq!(dirty_chunks.chunk_size(), UVec2::new(4, 4));
+        assert_eq!(dirty_chunks.chunk_of(&TilePos::new(5, 6)), UVec2::new(1, 1));
+        dirty_chunks.take();
+        dirty_chunks.mark(&TilePos::new(5, 6));
+
+        // The first update still makes every chunk dirty, without adding chunks off the map.
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get_mut::<TilemapDirtyChunks>(tilemap).unwrap().take(),
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| UVec2::new(x, y))
+        );
+    }
+
+    #[test]
+    fn distorted_tiles_are_picked_at_their_own_position() {
+        let map_size = TilemapSize::new(12, 10);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let map_type = TilemapType::Square;
+        let anchor = TilemapAnchor::Center;
+        let distortion = TilemapGridDistortion {
+            amplitude: Vec2::new(6.0, 6.0),
+            frequency: 0.5,
+            seed: 7,
+        };
+
+        let mut moved = false;
+        for x in 0..map_size.x {
+            for y in 0..map_size.y {
+                let tile_pos = TilePos::new(x, y);
+                let corner = Vec2::new(x as f32, y as f32);
+                let offsets = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]
+                    .map(|shape| distortion.offset(corner + shape));
+                for offset in offsets {
+                    assert!(offset.abs().cmple(distortion.amplitude).all());
+                    moved |= offset != Vec2::ZERO;
+                }
+                // The corner a tile shares with its right neighbor is moved the same way.
+                assert_eq!(
+                    offsets[1],
+                    distortion.offset(Vec2::new(x as f32 + 1.0, y as f32))
+                );
+
+                let center =
+                    tile_pos.center_in_world(&map_size, &grid_size, &tile_size, &map_type, &anchor);
+                let drawn = center + offsets.iter().sum::<Vec2>() / 4.0;
+                assert_eq!(
+                    TilePos::from_world_pos(
+                        &drawn, &map_size, &grid_size, &tile_size, &map_type, &anchor
+                    ),
+                    Some(tile_pos)
+                );
+            }
+        }
+        assert!(moved);
+    }
+
+    #[test]
+    fn world_bounds_follow_the_tilemap() {
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_tilemap_world_bounds);
+        let tilemap = world
+            .spawn((
+                TilemapSize::new(4, 2),
+                TilemapGridSize::new(16.0, 16.0),
+                TilemapTileSize::new(16.0, 16.0),
+                TilemapType::Square,
+                TilemapAnchor::None,
+                GlobalTransform::from_xyz(100.0, 0.0, 0.0),
+            ))
+            .id();
+        let bounds = |world: &World| world.get::<TilemapWorldBounds>(tilemap).unwrap().0;
+
+        schedule.run(&mut world);
+        assert_eq!(bounds(&world), Rect::new(92.0, -8.0, 156.0, 24.0));
+
+        // Writing the same transform again leaves the bounds unchanged.
+        let changed = world
+            .entity(tilemap)
+            .get_ref::<TilemapWorldBounds>()
+            .unwrap()
+            .last_changed();
+        *world.get_mut::<GlobalTransform>(tilemap).unwrap() =
+            GlobalTransform::from_xyz(100.0, 0.0, 0.0);
+        schedule.run(&mut world);
+        assert_eq!(
+            world
+                .entity(tilemap)
+                .get_ref::<TilemapWorldBounds>()
+                .unwrap()
+                .last_changed(),
+            changed
+        );
+
+        // Growing the map grows the bounds, and an empty map shrinks them to its origin.
+        *world.get_mut::<TilemapSize>(tilemap).unwrap() = TilemapSize::new(4, 3);
+        schedule.run(&mut world);
+        assert_eq!(bounds(&world).max, Vec2::new(156.0, 40.0));
+        *world.get_mut::<TilemapSize>(tilemap).unwrap() = TilemapSize::new(0, u32::MAX);
+        schedule.run(&mut world);
+        assert_eq!(bounds(&world), Rect::new(100.0, 0.0, 100.0, 0.0));
+    }
 }
@@ -0,0 +1,312 @@
+//! Throttles the tiles of huge tilemaps which lie outside of the regions the game is interested
+//! in, typically the viewports of its cameras.
+//!
+//! Tiles outside of every region get the [`OutsideRegionOfInterest`] marker. The renderer and the
+//! tile animation systems filter it out by archetype, so off-screen tiles cost nothing to iterate
+//! each frame. Whole render chunks enter and leave the regions at once, and changes made to
+//! throttled tiles are sent to the renderer when their chunk enters the regions again.
+
+use bevy::{
+    camera::Camera,
+    math::{IVec2, Rect, UVec2, Vec2},
+    platform::collections::HashSet,
+    prelude::{
+        Commands, Component, DetectChangesMut, Entity, GlobalTransform, Query, Reflect,
+        ReflectComponent, RemovedComponents, Res, ResMut, Resource, With,
+    },
+};
+
+use crate::anchor::TilemapAnchor;
+use crate::helpers::selection::unclamped_tile_coords;
+use crate::map::{
+    TilemapChunkSize, TilemapGridSize, TilemapRenderSettings, TilemapSize, TilemapTileSize,
+    TilemapType,
+};
+use crate::tiles::{TilePos, TilePosOld, TileStorage};
+
+/// The world-space regions whose tiles are kept up to date on tilemaps with
+/// [`RegionOfInterestThrottling`].
+#[derive(Resource, Default, Clone, Debug)]
+pub struct RegionsOfInterest {
+    /// Regions added by the game, e.g. around the player or a minimap.
+    pub regions: Vec<Rect>,
+    /// The viewports of the cameras with a [`RegionOfInterestCamera`], updated every frame.
+    camera_regions: Vec<Rect>,
+}
+
+impl RegionsOfInterest {
+    /// Returns every region, including the ones of cameras.
+    pub fn iter(&self) -> impl Iterator<Item = &Rect> {
+        self.regions.iter().chain(self.camera_regions.iter())
+    }
+}
+
+/// Adds the viewport of a 2D camera, grown by `margin` world units on each side, to the
+/// [`RegionsOfInterest`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct RegionOfInterestCamera {
+    pub margin: f32,
+}
+
+/// Opts a tilemap into throttling its tiles outside of the [`RegionsOfInterest`].
+///
+/// Throttled tiles are not extracted for rendering and their [`TileColorAnimation`]s are paused,
+/// so what was last drawn for them stays on screen. Tiles moved to another chunk while they are
+/// throttled stay throttled until their old chunk enters the regions.
+///
+/// [`TileColorAnimation`]: crate::tiles::TileColorAnimation
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct RegionOfInterestThrottling {
+    /// The render chunks which overlap the regions, or `None` before the first update.
+    #[reflect(ignore)]
+    active_chunks: Option<HashSet<UVec2>>,
+}
+
+impl RegionOfInterestThrottling {
+    /// Returns `true` if the render chunk with the given index currently overlaps the regions of
+    /// interest.
+    pub fn is_chunk_active(&self, chunk: &UVec2) -> bool {
+        self.active_chunks
+            .as_ref()
+            .is_some_and(|chunks| chunks.contains(chunk))
+    }
+}
+
+/// Marks a tile which lies outside of the [`RegionsOfInterest`] of a tilemap with
+/// [`RegionOfInterestThrottling`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct OutsideRegionOfInterest;
+
+/// Recomputes the regions of the cameras with a [`RegionOfInterestCamera`].
+pub(crate) fn update_camera_regions_of_interest(
+    mut regions: ResMut<RegionsOfInterest>,
+    cameras: Query<(&Camera, &GlobalTransform, &RegionOfInterestCamera)>,
+) {
+    regions.camera_regions.clear();
+    for (camera, camera_transform, roi) in cameras.iter() {
+        if !camera.is_active {
+            continue;
+        }
+        let Some(size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        let mut rect = Rect::EMPTY;
+        for corner in [
+            Vec2::ZERO,
+            Vec2::new(size.x, 0.0),
+            size,
+            Vec2::new(0.0, size.y),
+        ] {
+            if let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, corner) {
+                rect = rect.union_point(world_pos);
+            }
+        }
+        if !rect.is_empty() {
+            regions.camera_regions.push(rect.inflate(roi.margin));
+        }
+    }
+}
+
+/// Returns the render chunks of a tilemap which overlap the given world-space region.
+#[allow(clippy::too_many_arguments)]
+fn chunks_in_region(
+    region: &Rect,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    anchor: &TilemapAnchor,
+    chunk_size: UVec2,
+    global_transform: &GlobalTransform,
+) -> impl Iterator<Item = UVec2> + use<> {
+    let offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+    let to_local = global_transform.affine().inverse();
+    let corners = [
+        region.min,
+        Vec2::new(region.min.x, region.max.y),
+        region.max,
+        Vec2::new(region.max.x, region.min.y),
+    ]
+    .map(|corner| {
+        let local = to_local.transform_point3(corner.extend(0.0)).truncate();
+        unclamped_tile_coords(&(local - offset), grid_size, map_type)
+    });
+
+    // Tiles overhang their grid cell, and hexagonal and staggered coordinates are not linear in
+    // world space, so a margin is added.
+    let margin = IVec2::splat(2);
+    let min = corners.iter().fold(IVec2::MAX, |min, c| min.min(*c)) - margin;
+    let max = corners.iter().fold(IVec2::MIN, |max, c| max.max(*c)) + margin;
+    let last = IVec2::new(map_size.x as i32 - 1, map_size.y as i32 - 1);
+    let (min, max) = if min.cmpgt(last).any() || max.cmplt(IVec2::ZERO).any() {
+        (UVec2::ONE, UVec2::ZERO)
+    } else {
+        let chunk_size = chunk_size.max(UVec2::ONE);
+        (
+            min.max(IVec2::ZERO).as_uvec2() / chunk_size,
+            max.min(last).as_uvec2() / chunk_size,
+        )
+    };
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| UVec2::new(x, y)))
+}
+
+/// Returns the tile entities of a render chunk.
+fn chunk_tiles<'a>(
+    chunk: UVec2,
+    chunk_size: UVec2,
+    storage: &'a TileStorage,
+) -> impl Iterator<Item = Entity> + 'a {
+    let chunk_size = chunk_size.max(UVec2::ONE);
+    let min = chunk * chunk_size;
+    let max = (min + chunk_size).min(UVec2::new(storage.size.x, storage.size.y));
+    (min.y..max.y)
+        .flat_map(move |y| (min.x..max.x).map(move |x| TilePos::new(x, y)))
+        .filter_map(|tile_pos| storage.get(&tile_pos))
+}
+
+/// Sends a tile which was throttled to the renderer again.
+fn wake_tile(
+    entity: Entity,
+    commands: &mut Commands,
+    tiles: &mut Query<(&mut TilePos, &mut TilePosOld), With<OutsideRegionOfInterest>>,
+) {
+    if let Ok((mut tile_pos, mut tile_pos_old)) = tiles.get_mut(entity) {
+        // The tile may have moved while it was throttled, so the renderer is made to drop it from
+        // wherever it is stored by giving it an old position which never matches.
+        tile_pos.set_changed();
+        tile_pos_old.0 = TilePos::new(u32::MAX, u32::MAX);
+        commands.entity(entity).remove::<OutsideRegionOfInterest>();
+    }
+}
+
+/// Marks the tiles of throttled tilemaps which left the [`RegionsOfInterest`], and wakes the
+/// ones which entered them.
+#[allow(clippy::type_complexity)]
+pub(crate) fn throttle_tiles_outside_regions_of_interest(
+    mut commands: Commands,
+    regions: Res<RegionsOfInterest>,
+    mut tilemaps: Query<(
+        &mut RegionOfInterestThrottling,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &TilemapRenderSettings,
+        Option<&TilemapChunkSize>,
+        &GlobalTransform,
+    )>,
+    mut removed: RemovedComponents<RegionOfInterestThrottling>,
+    storages: Query<&TileStorage>,
+    mut tiles: Query<(&mut TilePos, &mut TilePosOld), With<OutsideRegionOfInterest>>,
+) {
+    for tilemap in removed.read() {
+        if let Ok(storage) = storages.get(tilemap) {
+            for entity in storage.iter().flatten() {
+                wake_tile(*entity, &mut commands, &mut tiles);
+            }
+        }
+    }
+
+    for (
+        mut throttling,
+        storage,
+        map_size,
+        grid_size,
+        tile_size,
+        map_type,
+        anchor,
+        render_settings,
+        chunk_size,
+        global_transform,
+    ) in tilemaps.iter_mut()
+    {
+        let chunk_size = TilemapChunkSize::resolve(chunk_size, render_settings);
+        let active_chunks = regions
+            .iter()
+            .flat_map(|region| {
+                chunks_in_region(
+                    region,
+                    map_size,
+                    grid_size,
+                    tile_size,
+                    map_type,
+                    anchor,
+                    chunk_size,
+                    global_transform,
+                )
+            })
+            .collect::<HashSet<_>>();
+
+        match &throttling.active_chunks {
+            None => {
+                for (ix, entity) in storage.iter().enumerate() {
+                    let Some(entity) = entity else {
+                        continue;
+                    };
+                    let tile_pos =
+                        UVec2::new(ix as u32 % storage.size.x, ix as u32 / storage.size.x);
+                    if !active_chunks.contains(&(tile_pos / chunk_size.max(UVec2::ONE))) {
+                        commands.entity(*entity).insert(OutsideRegionOfInterest);
+                    }
+                }
+            }
+            Some(previous) if *previous == active_chunks => continue,
+            Some(previous) => {
+                for chunk in previous.difference(&active_chunks) {
+                    for entity in chunk_tiles(*chunk, chunk_size, storage) {
+                        commands.entity(entity).insert(OutsideRegionOfInterest);
+                    }
+                }
+                for chunk in active_chunks.difference(previous) {
+                    for entity in chunk_tiles(*chunk, chunk_size, storage) {
+                        wake_tile(entity, &mut commands, &mut tiles);
+                    }
+                }
+            }
+        }
+        throttling.active_chunks = Some(active_chunks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_in_region_cover_the_region() {
+        let map_size = TilemapSize::new(64, 64);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tile_size = TilemapTileSize::new(16.0, 16.0);
+        let chunks = chunks_in_region(
+            &Rect::new(100.0, 100.0, 300.0, 150.0),
+            &map_size,
+            &grid_size,
+            &tile_size,
+            &TilemapType::Square,
+            &TilemapAnchor::None,
+            UVec2::splat(8),
+            &GlobalTransform::default(),
+        )
+        .collect::<Vec<_>>();
+        // Tiles 4..=21 by 4..=11, with the margin, lie in chunks 0..=2 by 0..=1.
+        assert_eq!(chunks.len(), 6);
+        assert!(chunks.contains(&UVec2::new(2, 1)));
+
+        let outside = chunks_in_region(
+            &Rect::new(-500.0, -500.0, -400.0, -400.0),
+            &map_size,
+            &grid_size,
+            &tile_size,
+            &TilemapType::Square,
+            &TilemapAnchor::None,
+            UVec2::splat(8),
+            &GlobalTransform::default(),
+        );
+        assert_eq!(outside.count(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        render_resource::{
+            Extent3d, TextureDataOrder, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages, TextureView, TextureViewDescriptor,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::tiles::TileAnimationTable;
+
+/// The number of texels in a row of the animation table texture.
+const TABLE_WIDTH: u32 = 1024;
+
+impl ExtractResource for TileAnimationTable {
+    type Source = TileAnimationTable;
+
+    fn extract_resource(source: &Self::Source) -> Self {
+        source.clone()
+    }
+}
+
+/// The [`TileAnimationTable`] on the GPU, as an `Rg32Float` texture holding its texels in rows of
+/// [`TABLE_WIDTH`].
+#[derive(Resource)]
+pub struct GpuTileAnimationTable {
+    pub texture_view: TextureView,
+}
+
+impl FromWorld for GpuTileAnimationTable {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let render_queue = world.resource::<RenderQueue>();
+        Self {
+            texture_view: create_table_texture(render_device, render_queue, &[]),
+        }
+    }
+}
+
+fn create_table_texture(
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+    texels: &[[f32; 2]],
+) -> TextureView {
+    // An empty table still needs a texel to be bound.
+    let len = texels.len().max(1) as u32;
+    let width = len.min(TABLE_WIDTH);
+    let height = len.div_ceil(width);
+    let mut data = Vec::with_capacity((width * height) as usize * 8);
+    for index in 0..(width * height) as usize {
+        let texel = texels.get(index).copied().unwrap_or_default();
+        data.extend(texel.iter().flat_map(|value| value.to_le_bytes()));
+    }
+
+    let texture = render_device.create_texture_with_data(
+        render_queue,
+        &TextureDescriptor {
+            label: Some("tile_animation_table"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rg32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &data,
+    );
+    texture.create_view(&TextureViewDescriptor::default())
+}
+
+/// Uploads the [`TileAnimationTable`] again whenever it was changed in the main world.
+pub fn prepare_tile_animation_table(
+    table: Option<Res<TileAnimationTable>>,
+    mut gpu_table: ResMut<GpuTileAnimationTable>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    if let Some(table) = table
+        && table.is_changed()
+    {
+        gpu_table.texture_view = create_table_texture(&render_device, &render_queue, &table.texels);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    platform::collections::HashMap,
+    prelude::{ResMut, Resource},
+    render::{
+        MainWorld,
+        render_resource::{Buffer, BufferDescriptor, BufferUsages},
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+/// The smallest buffer size handed out by the [`ChunkBufferPool`], in bytes.
+const MIN_BUCKET_SIZE: u64 = 1024;
+
+/// Statistics about the reuse of chunk vertex and index buffers in the render world.
+///
+/// The counters cover the most recently rendered frame. Add the
+/// [`TilemapDiagnosticsPlugin`](crate::diagnostics::TilemapDiagnosticsPlugin) to record them as
+/// diagnostics.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkBufferPoolStats {
+    /// Number of buffers taken from the pool instead of being allocated.
+    pub reused: u32,
+    /// Number of buffers that had to be allocated because the pool had no buffer of a fitting size.
+    pub allocated: u32,
+    /// Number of buffers that were freed because their bucket in the pool was full.
+    pub evicted: u32,
+    /// Number of buffers currently waiting in the pool.
+    pub pooled_buffers: u32,
+    /// Total size of the buffers currently waiting in the pool, in bytes.
+    pub pooled_bytes: u64,
+}
+
+/// Recycles the GPU buffers of chunks that were rebuilt or despawned, so chunks that are created
+/// later can reuse them instead of allocating new ones.
+///
+/// Buffers are grouped into power-of-two sized buckets. Each bucket is a ring buffer holding at
+/// most [`max_buffers_per_bucket`](Self::max_buffers_per_bucket) buffers; once it is full the
+/// oldest buffer is freed.
+#[derive(Resource)]
+pub(crate) struct ChunkBufferPool {
+    buckets: HashMap<(BufferUsages, u64), VecDeque<Buffer>>,
+    pub max_buffers_per_bucket: usize,
+    stats: ChunkBufferPoolStats,
+}
+
+impl Default for ChunkBufferPool {
+    fn default() -> Self {
+        Self {
+            buckets: HashMap::default(),
+            max_buffers_per_bucket: 16,
+            stats: ChunkBufferPoolStats::default(),
+        }
+    }
+}
+
+impl ChunkBufferPool {
+    fn bucket_size(len: u64) -> u64 {
+        len.max(MIN_BUCKET_SIZE).next_power_of_two()
+    }
+
+    /// Returns a buffer with the given `usage` which starts with `contents`, reusing a pooled
+    /// buffer if one of a fitting size is available.
+    ///
+    /// The buffer may be larger than `contents`.
+    pub fn acquire(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        usage: BufferUsages,
+        label: &'static str,
+        contents: &[u8],
+    ) -> Buffer {
+        let usage = usage | BufferUsages::COPY_DST;
+        let size = Self::bucket_size(contents.len() as u64);
+
+        let buffer = match self
+            .buckets
+            .get_mut(&(usage, size))
+            .and_then(|bucket| bucket.pop_front())
+        {
+            Some(buffer) => {
+                self.stats.reused += 1;
+                self.stats.pooled_buffers -= 1;
+                self.stats.pooled_bytes -= size;
+                buffer
+            }
+            None => {
+                self.stats.allocated += 1;
+                device.create_buffer(&BufferDescriptor {
+                    label: Some(label),
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            }
+        };
+
+        if !contents.is_empty() {
+            queue.write_buffer(&buffer, 0, contents);
+        }
+        buffer
+    }
+
+    /// Returns a buffer that is no longer used by its chunk to the pool.
+    ///
+    /// Buffers that were not created by [`acquire`](Self::acquire) are simply dropped.
+    pub fn release(&mut self, buffer: Buffer) {
+        let size = buffer.size();
+        if size != Self::bucket_size(size) || !buffer.usage().contains(BufferUsages::COPY_DST) {
+            return;
+        }
+
+        let bucket = self.buckets.entry((buffer.usage(), size)).or_default();
+        if bucket.len() >= self.max_buffers_per_bucket {
+            bucket.pop_front();
+            self.stats.evicted += 1;
+        } else {
+            self.stats.pooled_buffers += 1;
+            self.stats.pooled_bytes += size;
+        }
+        bucket.push_back(buffer);
+    }
+}
+
+/// Copies the pool statistics of the last frame into the main world, then resets the per-frame
+/// counters.
+pub(crate) fn extract_buffer_pool_stats(
+    mut pool: ResMut<ChunkBufferPool>,
+    mut main_world: ResMut<MainWorld>,
+) {
+    if let Some(mut stats) = main_world.get_resource_mut::<ChunkBufferPoolStats>() {
+        *stats = pool.stats;
+    }
+    pool.stats.reused = 0;
+    pool.stats.allocated = 0;
+    pool.stats.evicted = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::{schedule::Schedule, world::World};
+
+    #[test]
+    fn buffers_are_pooled_in_power_of_two_buckets() {
+        assert_eq!(ChunkBufferPool::bucket_size(0), MIN_BUCKET_SIZE);
+        assert_eq!(
+            ChunkBufferPool::bucket_size(MIN_BUCKET_SIZE),
+            MIN_BUCKET_SIZE
+        );
+        assert_eq!(ChunkBufferPool::bucket_size(MIN_BUCKET_SIZE + 1), 2048);
+        assert_eq!(ChunkBufferPool::bucket_size(5000), 8192);
+    }
+
+    #[test]
+    fn stats_are_extracted_and_reset_every_frame() {
+        let mut render_world = World::new();
+        let mut main_world = MainWorld::default();
+        main_world.init_resource::<ChunkBufferPoolStats>();
+        render_world.insert_resource(main_world);
+        let stats = ChunkBufferPoolStats {
+            reused: 3,
+            allocated: 2,
+            evicted: 1,
+            pooled_buffers: 4,
+            pooled_bytes: 4096,
+        };
+        render_world.insert_resource(ChunkBufferPool {
+            stats,
+            ..Default::default()
+        });
+        let mut schedule = Schedule::default();
+        schedule.add_systems(extract_buffer_pool_stats);
+
+        schedule.run(&mut render_world);
+        let extracted = |render_world: &World| {
+            *render_world
+                .resource::<MainWorld>()
+                .resource::<ChunkBufferPoolStats>()
+        };
+        assert_eq!(extracted(&render_world), stats);
+
+        // The counters of the next frame start over, but the pooled buffers are still there.
+        schedule.run(&mut render_world);
+        assert_eq!(
+            extracted(&render_world),
+            ChunkBufferPoolStats {
+                pooled_buffers: 4,
+                pooled_bytes: 4096,
+                ..Default::default()
+            }
+        );
+    }
+}
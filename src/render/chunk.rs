@@ -1,18 +1,22 @@
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
 use bevy::{
-    asset::RenderAssetUsages,
+    asset::{AssetId, RenderAssetUsages},
     mesh::{BaseMeshPipelineKey, Indices, PrimitiveTopology},
     platform::collections::HashMap,
 };
-use bevy::{camera::primitives::Aabb, math::Mat4};
 use bevy::{
-    math::{UVec2, UVec3, UVec4, Vec2, Vec3Swizzles, Vec4, Vec4Swizzles},
-    prelude::{Component, Entity, GlobalTransform, Mesh},
+    camera::{primitives::Aabb, visibility::RenderLayers},
+    math::Mat4,
+};
+use bevy::{
+    math::{UVec2, UVec3, UVec4, Vec2, Vec3Swizzles, Vec4},
+    prelude::{Component, Entity, GlobalTransform, Image, Mesh},
     render::{
         mesh::{RenderMesh, RenderMeshBufferInfo},
-        render_resource::{BufferInitDescriptor, BufferUsages, ShaderType},
-        renderer::RenderDevice,
+        render_resource::{BufferUsages, ShaderType},
+        renderer::{RenderDevice, RenderQueue},
     },
 };
 use bevy::{
@@ -25,30 +29,48 @@ use crate::prelude::helpers::transform::{chunk_aabb, chunk_index_to_world_space}
 use crate::render::extract::ExtractedFrustum;
 use crate::{
     FrustumCulling, TilemapGridSize, TilemapTileSize,
-    map::{TilemapSize, TilemapTexture, TilemapType},
+    map::{
+        TilemapDepthMode, TilemapOcclusionReveal, TilemapRenderMode, TilemapSize, TilemapTexture,
+        TilemapTopology, TilemapType,
+    },
     tiles::TilePos,
 };
 
 use super::RenderChunkSize;
+use super::buffer_pool::ChunkBufferPool;
 
+/// Stores the render chunks of every tilemap.
+///
+/// Chunks are keyed by the render world entity of their tilemap, which is unique even when
+/// several source worlds extract tilemaps into the same render world, or when a despawned
+/// tilemap's entity index is reused.
 #[derive(Resource, Default, Clone, Debug)]
 pub struct RenderChunk2dStorage {
-    chunks: HashMap<u32, HashMap<UVec3, RenderChunk2d>>,
-    entity_to_chunk_tile: HashMap<Entity, (u32, UVec3, UVec2)>,
-    entity_to_chunk: HashMap<Entity, UVec3>,
+    chunks: HashMap<Entity, HashMap<UVec3, RenderChunk2d>>,
+    entity_to_chunk_tile: HashMap<Entity, (Entity, UVec3, UVec2)>,
 }
 
 #[derive(Default, Component, Clone, Copy, Debug)]
 pub struct ChunkId(pub UVec3);
 
+/// The color grading lookup table of the chunk drawn by a render entity, which selects its
+/// texture bind group.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkColorGradingLut(pub Option<AssetId<Image>>);
+
+/// The [`TilemapSecondaryTexture`](crate::map::TilemapSecondaryTexture) of the chunk drawn by a
+/// render entity, which selects its texture bind group.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkSecondaryTexture(pub Option<TilemapTexture>);
+
 impl RenderChunk2dStorage {
     #[allow(clippy::too_many_arguments)]
     pub fn get_or_add(
         &mut self,
         tile_entity: Entity,
         tile_pos: UVec2,
-        chunk_entity: Entity,
-        position: &UVec4,
+        tilemap_entity: Entity,
+        chunk_index: UVec3,
         chunk_size: UVec2,
         mesh_type: TilemapType,
         tile_size: TilemapTileSize,
@@ -63,59 +85,45 @@ impl RenderChunk2dStorage {
         render_size: RenderChunkSize,
         y_sort: bool,
     ) -> &mut RenderChunk2d {
-        let pos = position.xyz();
-
         self.entity_to_chunk_tile
-            .insert(tile_entity, (position.w, pos, tile_pos));
-
-        let chunk_storage = if self.chunks.contains_key(&position.w) {
-            self.chunks.get_mut(&position.w).unwrap()
-        } else {
-            let hash_map = HashMap::default();
-            self.chunks.insert(position.w, hash_map);
-            self.chunks.get_mut(&position.w).unwrap()
-        };
-
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        position.hash(&mut hasher);
+            .insert(tile_entity, (tilemap_entity, chunk_index, tile_pos));
 
-        if chunk_storage.contains_key(&pos) {
-            chunk_storage.get_mut(&pos).unwrap()
-        } else {
-            let chunk = RenderChunk2d::new(
-                hasher.finish(),
-                chunk_entity.to_bits(),
-                &pos,
-                chunk_size,
-                mesh_type,
-                tile_size,
-                spacing,
-                grid_size,
-                texture,
-                texture_size,
-                map_size,
-                transform,
-                visibility.get(),
-                **frustum_culling,
-                render_size,
-                y_sort,
-            );
-            self.entity_to_chunk.insert(chunk_entity, pos);
-            chunk_storage.insert(pos, chunk);
-            chunk_storage.get_mut(&pos).unwrap()
-        }
+        self.get_chunk_storage(tilemap_entity)
+            .entry(chunk_index)
+            .or_insert_with(|| {
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                (tilemap_entity, chunk_index).hash(&mut hasher);
+                RenderChunk2d::new(
+                    hasher.finish(),
+                    tilemap_entity.to_bits(),
+                    &chunk_index,
+                    chunk_size,
+                    mesh_type,
+                    tile_size,
+                    spacing,
+                    grid_size,
+                    texture,
+                    texture_size,
+                    map_size,
+                    transform,
+                    visibility.get(),
+                    **frustum_culling,
+                    render_size,
+                    y_sort,
+                )
+            })
     }
 
-    pub fn get(&self, position: &UVec4) -> Option<&RenderChunk2d> {
-        if let Some(chunk_storage) = self.chunks.get(&position.w) {
-            return chunk_storage.get(&position.xyz());
-        }
-        None
+    pub fn get(&self, tilemap_entity: Entity, chunk_index: &UVec3) -> Option<&RenderChunk2d> {
+        self.chunks.get(&tilemap_entity)?.get(chunk_index)
     }
 
-    pub fn get_mut(&mut self, position: &UVec4) -> &mut RenderChunk2d {
-        let chunk_storage = self.chunks.get_mut(&position.w).unwrap();
-        chunk_storage.get_mut(&position.xyz()).unwrap()
+    pub fn get_mut(
+        &mut self,
+        tilemap_entity: Entity,
+        chunk_index: &UVec3,
+    ) -> Option<&mut RenderChunk2d> {
+        self.chunks.get_mut(&tilemap_entity)?.get_mut(chunk_index)
     }
 
     pub fn remove_tile_with_entity(&mut self, entity: Entity) {
@@ -123,37 +131,42 @@ impl RenderChunk2dStorage {
             chunk.set(&tile_pos.into(), None);
         }
 
-        self.entity_to_chunk.remove(&entity);
         self.entity_to_chunk_tile.remove(&entity);
     }
 
-    pub fn get_mut_from_entity(&mut self, entity: Entity) -> Option<(&mut RenderChunk2d, UVec2)> {
-        if !self.entity_to_chunk_tile.contains_key(&entity) {
-            return None;
-        }
-
-        let (tilemap_id, chunk_pos, tile_pos) = self.entity_to_chunk_tile.get(&entity).unwrap();
-
-        let chunk_storage = self.chunks.get_mut(tilemap_id).unwrap();
-        Some((chunk_storage.get_mut(&chunk_pos.xyz()).unwrap(), *tile_pos))
+    /// Returns the index of the chunk the tile drawn by the given entity is in.
+    pub fn chunk_index_of(&self, entity: Entity) -> Option<UVec3> {
+        self.entity_to_chunk_tile
+            .get(&entity)
+            .map(|(_, chunk_index, _)| *chunk_index)
     }
 
-    pub fn get_chunk_storage(&mut self, position: &UVec4) -> &mut HashMap<UVec3, RenderChunk2d> {
-        if self.chunks.contains_key(&position.w) {
-            self.chunks.get_mut(&position.w).unwrap()
-        } else {
-            let hash_map = HashMap::default();
-            self.chunks.insert(position.w, hash_map);
-            self.chunks.get_mut(&position.w).unwrap()
-        }
+    pub fn get_mut_from_entity(&mut self, entity: Entity) -> Option<(&mut RenderChunk2d, UVec2)> {
+        let (tilemap_entity, chunk_index, tile_pos) = *self.entity_to_chunk_tile.get(&entity)?;
+        let chunk = self.get_mut(tilemap_entity, &chunk_index)?;
+        Some((chunk, tile_pos))
     }
 
-    pub fn remove(&mut self, position: &UVec4) {
-        let chunk_storage = self.get_chunk_storage(position);
-
-        let pos = position.xyz();
+    pub fn get_chunk_storage(
+        &mut self,
+        tilemap_entity: Entity,
+    ) -> &mut HashMap<UVec3, RenderChunk2d> {
+        self.chunks.entry(tilemap_entity).or_default()
+    }
 
-        chunk_storage.remove(&pos);
+    pub fn remove(
+        &mut self,
+        tilemap_entity: Entity,
+        chunk_index: &UVec3,
+        buffer_pool: &mut ChunkBufferPool,
+    ) {
+        if let Some(chunk) = self
+            .chunks
+            .get_mut(&tilemap_entity)
+            .and_then(|chunks| chunks.remove(chunk_index))
+        {
+            chunk.release_buffers(buffer_pool);
+        }
     }
 
     pub fn count(&self) -> usize {
@@ -170,17 +183,31 @@ impl RenderChunk2dStorage {
             .flat_map(|(_, x)| x.iter_mut().map(|x| x.1))
     }
 
-    pub fn remove_map(&mut self, entity: Entity) {
-        self.chunks.remove(&entity.index());
+    pub fn remove_map(&mut self, entity: Entity, buffer_pool: &mut ChunkBufferPool) {
+        if let Some(chunks) = self.chunks.remove(&entity) {
+            for chunk in chunks.into_values() {
+                chunk.release_buffers(buffer_pool);
+            }
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PackedTileData {
     pub visible: bool,
+    /// Whether the tile is a [`TileOccluder`](crate::tiles::TileOccluder).
+    pub occluder: bool,
     pub position: Vec4,
     pub texture: Vec4,
     pub color: [f32; 4],
+    /// The texture indices of the overlay layers, or `-1.0` for layers without a texture.
+    pub layers: Vec4,
+    /// The [`TileCustomData`](crate::tiles::TileCustomData) of the tile.
+    pub custom_data: Vec4,
+    /// The [`TileZOffset`](crate::tiles::TileZOffset) of the tile.
+    pub z_offset: f32,
+    /// The corners of the [`TileShape`](crate::tiles::TileShape) of the tile.
+    pub shape: [Vec2; 4],
 }
 
 #[derive(Clone, Debug)]
@@ -205,6 +232,9 @@ pub struct RenderChunk2d {
     /// The [`Aabb`] of this chunk, based on the map type, grid size, and tile size. It is not
     /// transformed by the `global_transform` or [`local_transform`]
     aabb: Aabb,
+    /// How far the chunk is moved along the Z axis, for the layers of a
+    /// [`LayeredTileStorage`](crate::tiles::LayeredTileStorage).
+    z_offset: f32,
     local_transform: Transform,
     /// The [`GlobalTransform`] of this chunk, stored as a [`Transform`].
     global_transform: Transform,
@@ -220,11 +250,54 @@ pub struct RenderChunk2d {
     pub render_mesh: Option<RenderMesh>,
     pub vertex_buffer: Option<Buffer>,
     pub index_buffer: Option<Buffer>,
+    /// The flags of each tile of the mesh, one `u32` per vertex: bit 0 is set for visible tiles
+    /// and bit 1 for occluders. Kept apart from the mesh so toggling
+    /// [`TileVisible`](crate::tiles::TileVisible) or [`TileOccluder`](crate::tiles::TileOccluder)
+    /// does not rebuild it.
+    pub visibility_buffer: Option<Buffer>,
     pub dirty_mesh: bool,
+    /// The packed vertex data of a mesh built by [`build_mesh`](Self::build_mesh) which has not
+    /// been uploaded yet.
+    mesh_vertex_data: Option<Vec<u8>>,
+    pub dirty_visibility: bool,
+    /// One bit per tile slot, set for tiles which are visible.
+    visibility_mask: Vec<u32>,
+    /// One bit per tile slot, set for tiles which are occluders.
+    occluder_mask: Vec<u32>,
+    /// The tile slot of each tile in the mesh, in mesh order.
+    mesh_slots: Vec<u32>,
     pub visible: bool,
     pub frustum_culling: bool,
     pub render_size: RenderChunkSize,
     pub y_sort: bool,
+    /// The [`LayerBlendMode`](crate::map::LayerBlendMode) of each overlay layer.
+    pub layer_blend_modes: UVec4,
+    /// The [`TilemapGridDistortion`](crate::map::TilemapGridDistortion) of the tilemap, packed as
+    /// `(amplitude.x, amplitude.y, frequency, unused)`.
+    pub distortion: Vec4,
+    pub distortion_seed: u32,
+    /// The lookup table of the tilemap's [`TilemapColorGrading`](crate::map::TilemapColorGrading).
+    pub color_grading_lut: Option<AssetId<Image>>,
+    pub color_grading_strength: f32,
+    /// The [`TilemapColor`](crate::map::TilemapColor) of the tilemap, in linear space.
+    pub color: Vec4,
+    pub occlusion_reveal: TilemapOcclusionReveal,
+    render_mode: TilemapRenderMode,
+    /// With [`TilemapRenderMode::YSort`], the range of indices of each run of tiles sorted at the
+    /// same depth, and that depth. The index buffer is ordered by depth, so runs are as long as
+    /// possible.
+    pub sort_items: Vec<(Range<u32>, f32)>,
+    dirty_sort_items: bool,
+    pub topology: TilemapTopology,
+    /// The [`TilemapTexturePadding`](crate::map::TilemapTexturePadding) applied to the atlas, in
+    /// pixels.
+    pub texture_padding: f32,
+    /// The `RenderLayers` of the tilemap. Only views sharing a layer draw the chunk.
+    pub render_layers: RenderLayers,
+    /// The [`TilemapSecondaryTexture`](crate::map::TilemapSecondaryTexture) of the tilemap.
+    pub secondary_texture: Option<TilemapTexture>,
+    /// The [`TilemapDepthMode`](crate::map::TilemapDepthMode) of the tilemap.
+    pub depth_mode: TilemapDepthMode,
 }
 
 impl RenderChunk2d {
@@ -253,8 +326,11 @@ impl RenderChunk2d {
         let transform = local_transform * global_transform;
         let transform_matrix = transform.to_matrix();
         let aabb = chunk_aabb(size_in_tiles, &grid_size, &tile_size, &map_type);
+        let tile_count = (size_in_tiles.x * size_in_tiles.y) as usize;
         Self {
             dirty_mesh: true,
+            mesh_vertex_data: None,
+            dirty_visibility: true,
             render_mesh: None,
             id,
             index: *index,
@@ -265,6 +341,7 @@ impl RenderChunk2d {
             grid_size,
             tile_size,
             aabb,
+            z_offset: 0.0,
             local_transform,
             global_transform,
             transform,
@@ -275,15 +352,34 @@ impl RenderChunk2d {
             ),
             vertex_buffer: None,
             index_buffer: None,
+            visibility_buffer: None,
+            visibility_mask: vec![0; tile_count.div_ceil(32)],
+            occluder_mask: vec![0; tile_count.div_ceil(32)],
+            mesh_slots: Vec::new(),
             spacing,
             texture_size,
             texture,
             tilemap_id,
-            tiles: vec![None; (size_in_tiles.x * size_in_tiles.y) as usize],
+            tiles: vec![None; tile_count],
             visible,
             frustum_culling,
             render_size,
             y_sort,
+            layer_blend_modes: UVec4::ZERO,
+            distortion: Vec4::ZERO,
+            distortion_seed: 0,
+            color_grading_lut: None,
+            color_grading_strength: 0.0,
+            color: Vec4::ONE,
+            occlusion_reveal: TilemapOcclusionReveal::default(),
+            render_mode: TilemapRenderMode::default(),
+            sort_items: Vec::new(),
+            dirty_sort_items: false,
+            topology: TilemapTopology::default(),
+            texture_padding: 0.0,
+            render_layers: RenderLayers::default(),
+            secondary_texture: None,
+            depth_mode: TilemapDepthMode::default(),
         }
     }
 
@@ -296,9 +392,51 @@ impl RenderChunk2d {
         &mut self.tiles[tile_pos.to_index(&self.size_in_tiles.into())]
     }
 
+    /// Sets the tile at `tile_pos`.
+    ///
+    /// Setting a tile to what it already is changes nothing, and changing nothing but the
+    /// visibility or the occluder flag of a tile only updates the flag masks, which are much
+    /// cheaper to upload than rebuilding the mesh.
     pub fn set(&mut self, tile_pos: &TilePos, tile: Option<PackedTileData>) {
-        self.dirty_mesh = true;
-        self.tiles[tile_pos.to_index(&self.size_in_tiles.into())] = tile;
+        let index = tile_pos.to_index(&self.size_in_tiles.into());
+        if self.tiles[index] == tile {
+            return;
+        }
+        let visibility_only = match (&self.tiles[index], &tile) {
+            (Some(old), Some(new)) => {
+                PackedTileData {
+                    visible: old.visible,
+                    occluder: old.occluder,
+                    ..*new
+                } == *old
+            }
+            _ => false,
+        };
+        if visibility_only {
+            self.dirty_visibility = true;
+        } else {
+            self.dirty_mesh = true;
+        }
+        self.set_flag_bits(index, tile.as_ref());
+        self.tiles[index] = tile;
+    }
+
+    fn set_flag_bits(&mut self, index: usize, tile: Option<&PackedTileData>) {
+        set_mask_bit(
+            &mut self.visibility_mask,
+            index,
+            tile.is_some_and(|tile| tile.visible),
+        );
+        set_mask_bit(
+            &mut self.occluder_mask,
+            index,
+            tile.is_some_and(|tile| tile.occluder),
+        );
+    }
+
+    /// Returns `true` if any tile of this chunk is visible.
+    pub fn has_visible_tiles(&self) -> bool {
+        self.visibility_mask.iter().any(|bits| *bits != 0)
     }
 
     pub fn get_index(&self) -> UVec3 {
@@ -309,6 +447,28 @@ impl RenderChunk2d {
         self.map_type
     }
 
+    pub fn render_mode(&self) -> TilemapRenderMode {
+        self.render_mode
+    }
+
+    pub fn set_render_mode(&mut self, render_mode: TilemapRenderMode) {
+        if self.render_mode != render_mode {
+            self.render_mode = render_mode;
+            self.dirty_sort_items = true;
+        }
+    }
+
+    /// Moves the chunk along the Z axis, relative to its tilemap.
+    pub fn set_z_offset(&mut self, z_offset: f32) {
+        if self.z_offset != z_offset {
+            self.z_offset = z_offset;
+            self.local_transform = Transform::from_translation(self.position.extend(z_offset));
+            self.transform = self.global_transform * self.local_transform;
+            self.transform_matrix = self.transform.to_matrix();
+            self.dirty_sort_items = true;
+        }
+    }
+
     pub fn get_transform(&self) -> Transform {
         self.transform
     }
@@ -321,6 +481,70 @@ impl RenderChunk2d {
         frustum.intersects_obb(&self.aabb, &self.transform_matrix)
     }
 
+    /// Returns the transforms this chunk is drawn at which intersect one of the `frustums`: its
+    /// own, and for a wrapping tilemap, those of its copies next to each edge the tilemap wraps
+    /// around. The chunk itself is only culled if `frustum_culling` is enabled.
+    pub fn visible_transforms(&self, frustums: &[&ExtractedFrustum]) -> Vec<Transform> {
+        let steps = |period: Option<Vec2>| match period {
+            Some(period) => vec![Vec2::ZERO, -period, period],
+            None => vec![Vec2::ZERO],
+        };
+        let steps_x = steps(self.topology.period_x(
+            &self.map_size,
+            &self.grid_size,
+            &self.map_type,
+        ));
+        let steps_y = steps(self.topology.period_y(
+            &self.map_size,
+            &self.grid_size,
+            &self.map_type,
+        ));
+
+        let mut transforms = Vec::new();
+        for step_x in &steps_x {
+            for step_y in &steps_y {
+                let offset = *step_x + *step_y;
+                let transform = if offset == Vec2::ZERO {
+                    self.transform
+                } else {
+                    self.global_transform
+                        * Transform::from_translation(
+                            (self.position + offset).extend(self.z_offset),
+                        )
+                };
+                let culled = (offset != Vec2::ZERO || self.frustum_culling)
+                    && !frustums
+                        .iter()
+                        .any(|frustum| frustum.intersects_obb(&self.aabb, &transform.to_matrix()));
+                if !culled {
+                    transforms.push(transform);
+                }
+            }
+        }
+        transforms
+    }
+
+    /// Whether a view with the given `frustum` and `render_layers` sees the chunk drawn at
+    /// `transform`, which is one of its [`visible_transforms`](Self::visible_transforms). Views
+    /// without `RenderLayers` only see the default layer.
+    pub fn is_seen_by(
+        &self,
+        transform: &Transform,
+        frustum: Option<&ExtractedFrustum>,
+        render_layers: Option<&RenderLayers>,
+    ) -> bool {
+        if !self
+            .render_layers
+            .intersects(render_layers.unwrap_or(&RenderLayers::default()))
+        {
+            return false;
+        }
+        if *transform == self.transform && !self.frustum_culling {
+            return true;
+        }
+        frustum.is_none_or(|frustum| frustum.intersects_obb(&self.aabb, &transform.to_matrix()))
+    }
+
     pub fn update_geometry(
         &mut self,
         global_transform: Transform,
@@ -342,7 +566,7 @@ impl RenderChunk2d {
                 &self.map_type,
             );
 
-            self.local_transform = Transform::from_translation(self.position.extend(0.0));
+            self.local_transform = Transform::from_translation(self.position.extend(self.z_offset));
             dirty_local_transform = true;
 
             self.aabb = chunk_aabb(
@@ -362,29 +586,60 @@ impl RenderChunk2d {
         if dirty_local_transform || dirty_global_transform {
             self.transform = global_transform * self.local_transform;
             self.transform_matrix = self.transform.to_matrix();
+            self.dirty_sort_items = true;
         }
     }
 
-    pub fn prepare(
-        &mut self,
-        device: &RenderDevice,
-        mesh_vertex_buffer_layouts: &mut MeshVertexBufferLayouts,
-    ) {
+    /// Returns the GPU buffers of this chunk to the `buffer_pool`.
+    pub fn release_buffers(mut self, buffer_pool: &mut ChunkBufferPool) {
+        if let Some(vertex_buffer) = self.vertex_buffer.take() {
+            buffer_pool.release(vertex_buffer);
+        }
+        if let Some(index_buffer) = self.index_buffer.take() {
+            buffer_pool.release(index_buffer);
+        }
+        if let Some(visibility_buffer) = self.visibility_buffer.take() {
+            buffer_pool.release(visibility_buffer);
+        }
+    }
+
+    /// Returns `true` if a tile of the chunk had a [`TileShape`](crate::tiles::TileShape) other
+    /// than [`Full`](crate::tiles::TileShape::Full) when its mesh was last built, so its vertices
+    /// hold the corners of the shapes and it is drawn with the `TILE_SHAPES` shader def.
+    pub fn has_shapes(&self) -> bool {
+        self.mesh.contains_attribute(crate::render::ATTRIBUTE_SHAPE)
+    }
+
+    /// Rebuilds the mesh of the chunk from its tiles if they changed, ready to be uploaded by
+    /// [`prepare`](Self::prepare).
+    ///
+    /// This only touches the chunk itself, so the meshes of many chunks can be built in parallel.
+    pub fn build_mesh(&mut self) {
         if self.dirty_mesh {
             let size = ((self.size_in_tiles.x * self.size_in_tiles.y) * 4) as usize;
             let mut positions: Vec<[f32; 4]> = Vec::with_capacity(size);
             let mut textures: Vec<[f32; 4]> = Vec::with_capacity(size);
             let mut colors: Vec<[f32; 4]> = Vec::with_capacity(size);
+            let mut layers: Vec<[f32; 4]> = Vec::with_capacity(size);
+            let mut custom_data: Vec<[f32; 4]> = Vec::with_capacity(size);
+            let mut shapes: Vec<[f32; 2]> = Vec::with_capacity(size);
+            let full_corners = crate::tiles::TileShape::Full.corners();
+            let mut shaped = false;
             let mut indices: Vec<u32> =
                 Vec::with_capacity(((self.size_in_tiles.x * self.size_in_tiles.y) * 6) as usize);
 
             let mut i = 0;
+            self.mesh_slots.clear();
 
-            // Convert tile into mesh data.
-            for tile in self.tiles.iter().filter_map(|x| x.as_ref()) {
-                if !tile.visible {
-                    continue;
-                }
+            // Convert tile into mesh data. Hidden tiles are part of the mesh too, and collapsed
+            // by the vertex shader, so showing them again does not need a new mesh.
+            for (slot, tile) in self
+                .tiles
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, tile)| Some((slot, tile.as_ref()?)))
+            {
+                self.mesh_slots.push(slot as u32);
 
                 let position: [f32; 4] = tile.position.to_array();
                 positions.extend(
@@ -405,6 +660,10 @@ impl RenderChunk2d {
                 );
 
                 colors.extend(std::iter::repeat_n(tile.color, 4));
+                layers.extend(std::iter::repeat_n(tile.layers.to_array(), 4));
+                custom_data.extend(std::iter::repeat_n(tile.custom_data.to_array(), 4));
+                shaped |= tile.shape != full_corners;
+                shapes.extend(tile.shape.map(|corner| corner.to_array()));
 
                 // flipping and rotation packed in bits
                 // bit 0 : flip_x
@@ -434,20 +693,62 @@ impl RenderChunk2d {
                 crate::render::ATTRIBUTE_COLOR,
                 VertexAttributeValues::Float32x4(colors),
             );
+            self.mesh.insert_attribute(
+                crate::render::ATTRIBUTE_LAYERS,
+                VertexAttributeValues::Float32x4(layers),
+            );
+            self.mesh.insert_attribute(
+                crate::render::ATTRIBUTE_CUSTOM_DATA,
+                VertexAttributeValues::Float32x4(custom_data),
+            );
+            // Chunks of full tiles leave the shapes out of their vertices, see `has_shapes`.
+            if shaped {
+                self.mesh.insert_attribute(
+                    crate::render::ATTRIBUTE_SHAPE,
+                    VertexAttributeValues::Float32x2(shapes),
+                );
+            } else {
+                self.mesh.remove_attribute(crate::render::ATTRIBUTE_SHAPE);
+            }
             self.mesh.insert_indices(Indices::U32(indices));
+            self.mesh_vertex_data = Some(self.mesh.create_packed_vertex_buffer_data());
+            self.dirty_mesh = false;
 
-            let vertex_buffer_data = self.mesh.create_packed_vertex_buffer_data();
-            let vertex_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
-                usage: BufferUsages::VERTEX,
-                label: Some("Mesh Vertex Buffer"),
-                contents: &vertex_buffer_data,
-            });
+            // Tiles changed through `get_mut` are not in the masks yet.
+            for index in 0..self.tiles.len() {
+                let tile = self.tiles[index];
+                self.set_flag_bits(index, tile.as_ref());
+            }
+            self.dirty_visibility = true;
+            self.dirty_sort_items = true;
+        }
+    }
 
-            let index_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
-                usage: BufferUsages::INDEX,
-                contents: self.mesh.get_index_buffer_bytes().unwrap(),
-                label: Some("Mesh Index Buffer"),
-            });
+    pub fn prepare(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        buffer_pool: &mut ChunkBufferPool,
+        mesh_vertex_buffer_layouts: &mut MeshVertexBufferLayouts,
+    ) {
+        self.build_mesh();
+
+        if let Some(vertex_buffer_data) = self.mesh_vertex_data.take() {
+            let vertex_buffer = buffer_pool.acquire(
+                device,
+                queue,
+                BufferUsages::VERTEX,
+                "Mesh Vertex Buffer",
+                &vertex_buffer_data,
+            );
+
+            let index_buffer = buffer_pool.acquire(
+                device,
+                queue,
+                BufferUsages::INDEX,
+                "Mesh Index Buffer",
+                self.mesh.get_index_buffer_bytes().unwrap(),
+            );
 
             let buffer_info = RenderMeshBufferInfo::Indexed {
                 count: self.mesh.indices().unwrap().len() as u32,
@@ -466,11 +767,112 @@ impl RenderChunk2d {
                     PrimitiveTopology::TriangleList,
                 ),
             });
-            self.vertex_buffer = Some(vertex_buffer);
-            self.index_buffer = Some(index_buffer);
-            self.dirty_mesh = false;
+            // The previous buffers are no longer referenced once they have been replaced, so
+            // they can be handed to the next chunk that needs buffers of the same size.
+            if let Some(old_vertex_buffer) = self.vertex_buffer.replace(vertex_buffer) {
+                buffer_pool.release(old_vertex_buffer);
+            }
+            if let Some(old_index_buffer) = self.index_buffer.replace(index_buffer) {
+                buffer_pool.release(old_index_buffer);
+            }
+        }
+
+        if self.dirty_sort_items {
+            let indices = self.update_sort_items();
+            if let Some(index_buffer) = &self.index_buffer
+                && !indices.is_empty()
+            {
+                queue.write_buffer(index_buffer, 0, &indices);
+            }
+        }
+
+        if self.dirty_visibility {
+            let visibility: Vec<u8> = self
+                .mesh_slots
+                .iter()
+                .flat_map(|slot| {
+                    let slot = *slot as usize;
+                    let flags = mask_bit(&self.visibility_mask, slot) as u32
+                        | ((mask_bit(&self.occluder_mask, slot) as u32) << 1);
+                    std::iter::repeat_n(flags.to_ne_bytes(), 4).flatten()
+                })
+                .collect();
+            match &self.visibility_buffer {
+                // The buffer of the current mesh is updated in place.
+                Some(buffer) if buffer.size() >= visibility.len() as u64 => {
+                    queue.write_buffer(buffer, 0, &visibility);
+                }
+                _ => {
+                    let buffer = buffer_pool.acquire(
+                        device,
+                        queue,
+                        BufferUsages::VERTEX,
+                        "Tile Visibility Buffer",
+                        &visibility,
+                    );
+                    if let Some(old_buffer) = self.visibility_buffer.replace(buffer) {
+                        buffer_pool.release(old_buffer);
+                    }
+                }
+            }
+            self.dirty_visibility = false;
         }
     }
+
+    /// Collects the runs of tiles at the same depth of a [`TilemapRenderMode::YSort`] chunk into
+    /// `sort_items`, and returns the contents of the index buffer, ordered by depth.
+    fn update_sort_items(&mut self) -> Vec<u8> {
+        self.dirty_sort_items = false;
+        self.sort_items.clear();
+        if self.render_mode != TilemapRenderMode::YSort {
+            return Vec::new();
+        }
+
+        let mut depths: Vec<(f32, u32)> = self
+            .mesh_slots
+            .iter()
+            .enumerate()
+            .map(|(mesh_index, slot)| {
+                let tile_pos =
+                    TilePos::new(slot % self.size_in_tiles.x, slot / self.size_in_tiles.x);
+                let center = tile_pos.center_in_world_unanchored(&self.grid_size, &self.map_type);
+                let translation = self.transform.transform_point(center.extend(0.0));
+                let z_offset = self.tiles[*slot as usize].map_or(0.0, |tile| tile.z_offset);
+                let depth =
+                    TilemapRenderMode::y_sort_z(translation, &self.map_size, &self.tile_size);
+                (depth + z_offset, mesh_index as u32)
+            })
+            .collect();
+        depths.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let mut indices = Vec::with_capacity(depths.len() * 6 * size_of::<u32>());
+        for (order, (depth, mesh_index)) in depths.iter().enumerate() {
+            let i = mesh_index * 4;
+            for index in [i, i + 2, i + 1, i, i + 3, i + 2] {
+                indices.extend_from_slice(&index.to_ne_bytes());
+            }
+
+            let start = order as u32 * 6;
+            match self.sort_items.last_mut() {
+                Some((range, last_depth)) if last_depth == depth => range.end = start + 6,
+                _ => self.sort_items.push((start..start + 6, *depth)),
+            }
+        }
+        indices
+    }
+}
+
+fn set_mask_bit(mask: &mut [u32], index: usize, value: bool) {
+    let bit = 1 << (index % 32);
+    if value {
+        mask[index / 32] |= bit;
+    } else {
+        mask[index / 32] &= !bit;
+    }
+}
+
+fn mask_bit(mask: &[u32], index: usize) -> bool {
+    mask[index / 32] & (1 << (index % 32)) != 0
 }
 
 // Used to transfer info to the GPU for tile building.
@@ -482,6 +884,34 @@ pub struct TilemapUniformData {
     pub spacing: Vec2,
     pub chunk_pos: Vec2,
     pub map_size: Vec2,
+    pub layer_blend_modes: UVec4,
+    pub distortion: Vec4,
+    pub distortion_seed: u32,
+    pub color_grading_strength: f32,
+    pub color: Vec4,
+    /// The [`TilemapOcclusionReveal`] circle, as `(center.x, center.y, radius, falloff)`.
+    pub occlusion_circle: Vec4,
+    /// The [`TilemapOcclusionReveal`] region, as `(min.x, min.y, max.x, max.y)`. It is empty
+    /// when its minimum is greater than its maximum.
+    pub occlusion_region: Vec4,
+    pub occlusion_revealed_alpha: f32,
+    pub texture_padding: f32,
+    /// The [`TilemapTime`](crate::map::TilemapTime) of the tilemap, or the global time.
+    pub time: f32,
+    /// The [`TilemapCustomData`](crate::map::TilemapCustomData) of the tilemap.
+    pub custom_data: [Vec4; 4],
+}
+
+fn occlusion_circle(reveal: &TilemapOcclusionReveal) -> Vec4 {
+    reveal.center.extend(reveal.radius).extend(reveal.falloff)
+}
+
+fn occlusion_region(reveal: &TilemapOcclusionReveal) -> Vec4 {
+    reveal
+        .region
+        .map_or(Vec4::new(1.0, 1.0, 0.0, 0.0), |region| {
+            region.min.extend(region.max.x).extend(region.max.y)
+        })
 }
 
 impl From<&RenderChunk2d> for TilemapUniformData {
@@ -497,6 +927,18 @@ impl From<&RenderChunk2d> for TilemapUniformData {
             spacing: chunk.spacing,
             chunk_pos: chunk_ix * chunk_size,
             map_size: map_size * tile_size,
+            layer_blend_modes: chunk.layer_blend_modes,
+            distortion: chunk.distortion,
+            distortion_seed: chunk.distortion_seed,
+            color_grading_strength: chunk.color_grading_strength,
+            color: chunk.color,
+            occlusion_circle: occlusion_circle(&chunk.occlusion_reveal),
+            occlusion_region: occlusion_region(&chunk.occlusion_reveal),
+            occlusion_revealed_alpha: chunk.occlusion_reveal.revealed_alpha,
+            texture_padding: chunk.texture_padding,
+            // Set by `prepare` every frame.
+            time: 0.0,
+            custom_data: [Vec4::ZERO; 4],
         }
     }
 }
@@ -514,6 +956,206 @@ impl From<&mut RenderChunk2d> for TilemapUniformData {
             spacing: chunk.spacing,
             chunk_pos: chunk_pos * chunk_size,
             map_size: map_size * tile_size,
+            layer_blend_modes: chunk.layer_blend_modes,
+            distortion: chunk.distortion,
+            distortion_seed: chunk.distortion_seed,
+            color_grading_strength: chunk.color_grading_strength,
+            color: chunk.color,
+            occlusion_circle: occlusion_circle(&chunk.occlusion_reveal),
+            occlusion_region: occlusion_region(&chunk.occlusion_reveal),
+            occlusion_revealed_alpha: chunk.occlusion_reveal.revealed_alpha,
+            texture_padding: chunk.texture_padding,
+            // Set by `prepare` every frame.
+            time: 0.0,
+            custom_data: [Vec4::ZERO; 4],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileShape;
+    use bevy::math::Vec3;
+
+    #[test]
+    fn toggling_visibility_keeps_the_mesh() {
+        let mut chunk = RenderChunk2d::new(
+            0,
+            0,
+            &UVec3::ZERO,
+            UVec2::new(8, 8),
+            TilemapType::Square,
+            TilemapTileSize::new(16.0, 16.0),
+            Vec2::ZERO,
+            TilemapGridSize::new(16.0, 16.0),
+            TilemapTexture::Single(Default::default()),
+            Vec2::new(16.0, 16.0),
+            TilemapSize::new(8, 8),
+            GlobalTransform::IDENTITY,
+            true,
+            true,
+            RenderChunkSize::new(UVec2::new(8, 8)),
+            false,
+        );
+        let tile = PackedTileData {
+            visible: true,
+            occluder: false,
+            position: Vec4::new(3.0, 5.0, 0.0, 0.0),
+            texture: Vec4::ZERO,
+            color: [1.0; 4],
+            layers: Vec4::splat(-1.0),
+            custom_data: Vec4::ZERO,
+            z_offset: 0.0,
+            shape: TileShape::Full.corners(),
+        };
+        let tile_pos = TilePos::new(3, 5);
+        chunk.set(&tile_pos, Some(tile));
+        assert!(chunk.has_visible_tiles());
+        chunk.build_mesh();
+        assert!(!chunk.dirty_mesh);
+
+        // Extracting a tile again without changes does not rebuild the mesh.
+        chunk.set(&tile_pos, Some(tile));
+        assert!(!chunk.dirty_mesh);
+
+        chunk.set(
+            &tile_pos,
+            Some(PackedTileData {
+                visible: false,
+                ..tile
+            }),
+        );
+        assert!(!chunk.dirty_mesh);
+        assert!(chunk.dirty_visibility);
+        assert!(!chunk.has_visible_tiles());
+
+        chunk.set(
+            &tile_pos,
+            Some(PackedTileData {
+                visible: false,
+                occluder: true,
+                ..tile
+            }),
+        );
+        assert!(!chunk.dirty_mesh);
+
+        chunk.set(
+            &tile_pos,
+            Some(PackedTileData {
+                color: [0.5; 4],
+                ..tile
+            }),
+        );
+        assert!(chunk.dirty_mesh);
+        assert!(chunk.has_visible_tiles());
+
+        // Only chunks with shaped tiles carry the shapes in their vertices.
+        assert!(!chunk.has_shapes());
+        chunk.set(
+            &tile_pos,
+            Some(PackedTileData {
+                shape: TileShape::Half.corners(),
+                ..tile
+            }),
+        );
+        chunk.build_mesh();
+        assert!(chunk.has_shapes());
+    }
+
+    #[test]
+    fn y_sorted_tiles_are_drawn_in_runs_by_depth() {
+        let mut chunk = RenderChunk2d::new(
+            0,
+            0,
+            &UVec3::ZERO,
+            UVec2::new(2, 2),
+            TilemapType::Square,
+            TilemapTileSize::new(16.0, 16.0),
+            Vec2::ZERO,
+            TilemapGridSize::new(16.0, 16.0),
+            TilemapTexture::Single(Default::default()),
+            Vec2::new(16.0, 16.0),
+            TilemapSize::new(2, 2),
+            GlobalTransform::IDENTITY,
+            true,
+            true,
+            RenderChunkSize::new(UVec2::new(2, 2)),
+            false,
+        );
+        chunk.set_render_mode(TilemapRenderMode::YSort);
+        for slot in 0..4 {
+            let tile_pos = TilePos::new(slot % 2, slot / 2);
+            chunk.set(
+                &tile_pos,
+                Some(PackedTileData {
+                    visible: true,
+                    occluder: false,
+                    position: Vec4::new(tile_pos.x as f32, tile_pos.y as f32, 0.0, 0.0),
+                    texture: Vec4::ZERO,
+                    color: [1.0; 4],
+                    layers: Vec4::splat(-1.0),
+                    custom_data: Vec4::ZERO,
+                    // The wall on the right of the bottom row is drawn over the row above it.
+                    z_offset: if slot == 1 { 0.75 } else { 0.0 },
+                    shape: TileShape::Full.corners(),
+                }),
+            );
+            chunk.mesh_slots.push(slot);
+        }
+
+        let indices = chunk.update_sort_items();
+        assert_eq!(indices.len(), 4 * 6 * size_of::<u32>());
+        // The top row is furthest back, and the bottom row is split by the wall.
+        assert_eq!(
+            chunk.sort_items,
+            vec![(0..12, 0.5), (12..18, 1.0), (18..24, 1.75)]
+        );
+    }
+
+    #[test]
+    fn per_tile_depth_matches_y_sort_z() {
+        let map_size = TilemapSize::new(4, 4);
+        let grid_size = TilemapGridSize::new(16.0, 16.0);
+        let tilemap_translation = Vec3::new(100.0, -40.0, 3.0);
+        // The second chunk of the top row, away from the origin of the world.
+        let chunk = RenderChunk2d::new(
+            0,
+            0,
+            &UVec3::new(1, 1, 0),
+            UVec2::new(2, 2),
+            TilemapType::Square,
+            TilemapTileSize::new(16.0, 24.0),
+            Vec2::ZERO,
+            grid_size,
+            TilemapTexture::Single(Default::default()),
+            Vec2::new(16.0, 24.0),
+            map_size,
+            GlobalTransform::from_translation(tilemap_translation),
+            true,
+            true,
+            RenderChunkSize::new(UVec2::new(2, 2)),
+            false,
+        );
+        let uniform = TilemapUniformData::from(&chunk);
+        let model = chunk.get_transform_matrix();
+
+        for tile_pos in [TilePos::new(0, 0), TilePos::new(1, 1)] {
+            // The corners `get_mesh` of square maps gives the shader, in world space.
+            let point = Vec2::new(tile_pos.x as f32, tile_pos.y as f32) * uniform.grid_size;
+            let corner = |offset: Vec2| model.transform_point3((point + offset).extend(0.0));
+            let center = 0.5 * (corner(-0.5 * uniform.tile_size) + corner(0.5 * uniform.tile_size));
+            let shader_depth = center.z + 1.0 - center.y / uniform.map_size.y;
+
+            let translation = tilemap_translation
+                + (Vec2::new(32.0, 32.0)
+                    + tile_pos.center_in_world_unanchored(&grid_size, &TilemapType::Square))
+                .extend(0.0);
+            let y_sort_z = TilemapRenderMode::y_sort_z(translation, &map_size, &chunk.tile_size);
+            assert!(
+                (shader_depth - y_sort_z).abs() < 1e-6,
+                "{tile_pos:?}: {shader_depth} != {y_sort_z}"
+            );
         }
     }
 }
@@ -0,0 +1,53 @@
+use bevy::{
+    prelude::{Res, ResMut, Resource},
+    render::MainWorld,
+};
+
+#[cfg(not(feature = "atlas"))]
+use super::TextureArrayCache;
+use super::chunk::RenderChunk2dStorage;
+
+/// Statistics about the chunks in the render world, to profile what rendering tilemaps costs.
+///
+/// The statistics describe the most recently rendered frame. Add the
+/// [`TilemapDiagnosticsPlugin`](crate::diagnostics::TilemapDiagnosticsPlugin) to record them as
+/// diagnostics.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderChunkStats {
+    /// Number of chunks of all tilemaps.
+    pub chunks: u32,
+    /// Number of chunks which were drawn, i.e. which were neither hidden nor culled.
+    pub visible_chunks: u32,
+    /// Number of chunks whose mesh was rebuilt because their tiles changed.
+    pub rebuilt_chunks: u32,
+    /// Number of vertices of the meshes of all chunks.
+    pub vertices: u64,
+    /// Number of layers of the texture arrays made from the tilemap textures. Always `0` with the
+    /// `atlas` feature, which uses the textures as they are.
+    pub texture_array_layers: u32,
+}
+
+/// Completes the statistics of the last frame and copies them into the main world, then resets
+/// the per-frame counters.
+pub(crate) fn extract_render_chunk_stats(
+    mut stats: ResMut<RenderChunkStats>,
+    chunk_storage: Res<RenderChunk2dStorage>,
+    #[cfg(not(feature = "atlas"))] texture_array_cache: Res<TextureArrayCache>,
+    mut main_world: ResMut<MainWorld>,
+) {
+    stats.chunks = chunk_storage.iter().count() as u32;
+    stats.vertices = chunk_storage
+        .iter()
+        .filter_map(|chunk| chunk.render_mesh.as_ref())
+        .map(|render_mesh| render_mesh.vertex_count as u64)
+        .sum();
+    #[cfg(not(feature = "atlas"))]
+    {
+        stats.texture_array_layers = texture_array_cache.array_layers();
+    }
+    if let Some(mut main_stats) = main_world.get_resource_mut::<RenderChunkStats>() {
+        *main_stats = *stats;
+    }
+    stats.visible_chunks = 0;
+    stats.rebuilt_chunks = 0;
+}
@@ -6,10 +6,11 @@ use bevy::{
         SystemParamItem,
         lifetimeless::{Read, SQuery, SRes},
     },
-    math::UVec4,
     render::{
         mesh::RenderMeshBufferInfo,
-        render_phase::{RenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_phase::{
+            PhaseItemExtraIndex, RenderCommand, RenderCommandResult, TrackedRenderPass,
+        },
         render_resource::PipelineCache,
         view::ViewUniformOffset,
     },
@@ -20,7 +21,10 @@ use crate::map::TilemapId;
 
 use super::{
     DynamicUniformIndex,
-    chunk::{ChunkId, RenderChunk2dStorage, TilemapUniformData},
+    chunk::{
+        ChunkColorGradingLut, ChunkId, ChunkSecondaryTexture, RenderChunk2dStorage,
+        TilemapUniformData,
+    },
     material::{MaterialTilemap, MaterialTilemapHandle, RenderMaterialsTilemap},
     prepare::MeshUniform,
     queue::{ImageBindGroups, TilemapViewBindGroup, TransformBindGroup},
@@ -82,20 +86,40 @@ pub struct SetTextureBindGroup<const I: usize>;
 impl<const I: usize> RenderCommand<Transparent2d> for SetTextureBindGroup<I> {
     type Param = SRes<ImageBindGroups>;
     type ViewQuery = ();
-    type ItemQuery = Read<TilemapTexture>;
+    type ItemQuery = (
+        Read<TilemapTexture>,
+        Read<ChunkColorGradingLut>,
+        Read<ChunkSecondaryTexture>,
+    );
     #[inline]
     fn render<'w>(
         _item: &Transparent2d,
         _view: (),
-        texture: Option<&'w TilemapTexture>,
+        item: Option<(
+            &'w TilemapTexture,
+            &'w ChunkColorGradingLut,
+            &'w ChunkSecondaryTexture,
+        )>,
         image_bind_groups: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let Some(texture) = texture else {
+        let Some((texture, lut, secondary)) = item else {
             return RenderCommandResult::Skip;
         };
 
-        let bind_group = image_bind_groups.into_inner().values.get(texture).unwrap();
+        // Without a bind group for the lookup table or the secondary texture, they have not been
+        // loaded yet.
+        let bind_groups = image_bind_groups.into_inner().values.get(texture).unwrap();
+        let Some(bind_group) = [
+            (lut.0, secondary.0.clone()),
+            (None, secondary.0.clone()),
+            (lut.0, None),
+            (None, None),
+        ]
+        .iter()
+        .find_map(|key| bind_groups.get(key)) else {
+            return RenderCommandResult::Skip;
+        };
         pass.set_bind_group(I, bind_group, &[]);
 
         RenderCommandResult::Success
@@ -185,7 +209,7 @@ impl RenderCommand<Transparent2d> for DrawMesh {
     type ItemQuery = (Read<ChunkId>, Read<TilemapId>);
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        item: &Transparent2d,
         _view: (),
         ids: Option<(&'w ChunkId, &'w TilemapId)>,
         chunk_storage: SystemParamItem<'w, '_, Self::Param>,
@@ -195,28 +219,42 @@ impl RenderCommand<Transparent2d> for DrawMesh {
             return RenderCommandResult::Skip;
         };
 
-        if let Some(chunk) = chunk_storage.into_inner().get(&UVec4::new(
-            chunk_id.0.x,
-            chunk_id.0.y,
-            chunk_id.0.z,
-            tilemap_id.0.index(),
-        )) && let (Some(render_mesh), Some(vertex_buffer), Some(index_buffer)) = (
-            &chunk.render_mesh,
-            &chunk.vertex_buffer,
-            &chunk.index_buffer,
-        ) {
-            if render_mesh.vertex_count == 0 {
+        if let Some(chunk) = chunk_storage.into_inner().get(tilemap_id.0, &chunk_id.0)
+            && let (
+                Some(render_mesh),
+                Some(vertex_buffer),
+                Some(index_buffer),
+                Some(visibility_buffer),
+            ) = (
+                &chunk.render_mesh,
+                &chunk.vertex_buffer,
+                &chunk.index_buffer,
+                &chunk.visibility_buffer,
+            )
+        {
+            if render_mesh.vertex_count == 0 || !chunk.has_visible_tiles() {
                 return RenderCommandResult::Skip;
             }
 
             pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            pass.set_vertex_buffer(1, visibility_buffer.slice(..));
             match &render_mesh.buffer_info {
                 RenderMeshBufferInfo::Indexed {
                     index_format,
                     count,
                 } => {
                     pass.set_index_buffer(index_buffer.slice(..), 0, *index_format);
-                    pass.draw_indexed(0..*count, 0, 0..1);
+                    // Y-sorted chunks draw one run of tiles per phase item.
+                    let indices = match item.extra_index {
+                        PhaseItemExtraIndex::DynamicOffset(index) => {
+                            let Some((indices, _)) = chunk.sort_items.get(index as usize) else {
+                                return RenderCommandResult::Skip;
+                            };
+                            indices.clone()
+                        }
+                        _ => 0..*count,
+                    };
+                    pass.draw_indexed(indices, 0, 0..1);
                 }
                 RenderMeshBufferInfo::NonIndexed => {
                     pass.draw(0..render_mesh.vertex_count, 0..1);
@@ -1,7 +1,10 @@
 use bevy::{
-    camera::primitives::{Aabb, Frustum},
+    camera::{
+        primitives::{Aabb, Frustum},
+        visibility::RenderLayers,
+    },
     math::Affine3A,
-    platform::collections::HashMap,
+    platform::collections::{HashMap, HashSet},
     prelude::*,
     render::{
         Extract,
@@ -11,21 +14,34 @@ use bevy::{
 };
 
 use crate::anchor::TilemapAnchor;
+use crate::helpers::transform::{chunk_aabb, chunk_index_to_world_space};
 use crate::prelude::TilemapGridSize;
 use crate::prelude::TilemapRenderSettings;
+use crate::region_of_interest::OutsideRegionOfInterest;
 use crate::render::DefaultSampler;
-use crate::tiles::AnimatedTile;
 use crate::tiles::TilePosOld;
+use crate::tiles::{
+    AnimatedTile, AnimationPaused, AnimationPhase, TileCustomData, TileFrameAnimation, TileLayers,
+    TileOccluder, TileRotation, TileShape, TileZOffset,
+};
 use crate::{
     FrustumCulling,
     map::{
-        TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize,
-        TilemapTileSize, TilemapType,
+        TilemapChunkSize, TilemapColor, TilemapColorGrading, TilemapDepthMode,
+        TilemapGridDistortion, TilemapId, TilemapLayer, TilemapLayerBlendModes, TilemapLayerOrder,
+        TilemapOcclusionReveal, TilemapRenderMode, TilemapSecondaryTexture, TilemapSize,
+        TilemapSpacing, TilemapTexture, TilemapTexturePadding, TilemapTextureSize, TilemapTileSize,
+        TilemapTopology, TilemapType, TilemapUpdateMode, TilemapUpdateState,
+    },
+    tiles::{
+        LayeredTileStorage, SparseTileStorage, TileColor, TileFlip, TilePos, TileStorage,
+        TileStore, TileTextureIndex, TileVisible,
     },
-    tiles::{TileColor, TileFlip, TilePos, TileTextureIndex, TileVisible},
 };
 
+use super::RenderChunkSize;
 use super::chunk::PackedTileData;
+use std::f32::consts::TAU;
 
 #[derive(Component)]
 pub struct ChangedInMainWorld;
@@ -37,6 +53,9 @@ pub struct ExtractedTile {
     pub old_position: TilePosOld,
     pub tile: PackedTileData,
     pub tilemap_id: TilemapId,
+    /// The layer of the [`LayeredTileStorage`] the tile is stored in, which selects the chunks it
+    /// is drawn in.
+    pub storage_layer: u32,
 }
 
 #[derive(Bundle)]
@@ -45,6 +64,36 @@ pub struct ExtractedTileBundle {
     changed: ChangedInMainWorld,
 }
 
+/// Marks an [`ExtractedTile`] whose tilemap was not due for an update (see
+/// [`TilemapUpdateMode`]) when it was extracted. It is held back until the tilemap is extracted
+/// again.
+#[derive(Component)]
+pub struct DeferredTile;
+
+/// Marks an extracted tilemap with a reduced update rate which is due for an update this frame,
+/// so its [`DeferredTile`]s are released.
+#[derive(Component)]
+pub struct TilemapUpdateDue;
+
+/// Marks an extracted tilemap whose chunk size changed, so its chunks are rebuilt from all of its
+/// tiles, which are extracted with it.
+#[derive(Component)]
+pub struct TilemapRechunked;
+
+/// The chunks of each tilemap whose changed tiles were not extracted because no camera saw them,
+/// and the chunk size each tilemap was last extracted with. Keyed by the main world tilemap.
+#[derive(Resource, Default)]
+pub(crate) struct CulledChunks {
+    skipped: HashMap<Entity, HashSet<UVec2>>,
+    chunk_sizes: HashMap<Entity, UVec2>,
+}
+
+#[derive(Bundle)]
+pub struct DeferredTileBundle {
+    tile: ExtractedTile,
+    deferred: DeferredTile,
+}
+
 #[derive(Bundle)]
 pub struct ExtractedTilemapBundle {
     transform: GlobalTransform,
@@ -60,8 +109,23 @@ pub struct ExtractedTilemapBundle {
     render_settings: TilemapRenderSettings,
     changed: ChangedInMainWorld,
     anchor: TilemapAnchor,
+    layer_blend_modes: TilemapLayerBlendModes,
+    grid_distortion: TilemapGridDistortion,
+    color_grading: TilemapColorGrading,
+    color: TilemapColor,
+    occlusion_reveal: TilemapOcclusionReveal,
+    render_mode: TilemapRenderMode,
+    topology: TilemapTopology,
+    texture_padding: TilemapTexturePadding,
+    render_layers: RenderLayers,
+    secondary_texture: ExtractedSecondaryTexture,
+    depth_mode: TilemapDepthMode,
 }
 
+/// The [`TilemapSecondaryTexture`] of a tilemap, if it has one.
+#[derive(Component, Clone, Debug, Default)]
+pub(crate) struct ExtractedSecondaryTexture(pub Option<TilemapTexture>);
+
 #[derive(Component)]
 pub(crate) struct ExtractedTilemapTexture {
     pub tilemap_id: TilemapId,
@@ -165,6 +229,11 @@ pub(crate) struct ExtractedTilemapTextureBundle {
     changed: ChangedInMainWorld,
 }
 
+/// The [`TilemapSecondaryTexture`] of a tilemap once its images are ready, extracted like its
+/// [`TilemapTexture`].
+#[derive(Component)]
+pub(crate) struct ExtractedSecondaryTilemapTexture(pub ExtractedTilemapTexture);
+
 #[derive(Component, Debug)]
 pub struct ExtractedFrustum {
     frustum: Frustum,
@@ -177,31 +246,211 @@ impl ExtractedFrustum {
     }
 }
 
+/// The color grading of a tilemap without a [`TilemapColorGrading`], which leaves the colors
+/// unchanged.
+fn disabled_color_grading() -> TilemapColorGrading {
+    TilemapColorGrading {
+        strength: 0.0,
+        ..Default::default()
+    }
+}
+
+/// The transform a tilemap is drawn with: its own, moved to the `z` of its [`TilemapLayer`].
+fn layered_transform(
+    transform: &GlobalTransform,
+    tilemap_entity: Entity,
+    layer_query: &Query<(Entity, &TilemapLayer)>,
+    layer_order: &TilemapLayerOrder,
+) -> GlobalTransform {
+    let Some(z) = layer_query
+        .get(tilemap_entity)
+        .ok()
+        .and_then(|(_, layer)| layer_order.z(layer))
+    else {
+        return *transform;
+    };
+    let mut affine = transform.affine();
+    affine.translation.z = z;
+    GlobalTransform::from(affine)
+}
+
+type StorageQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static TileStorage>,
+        Option<&'static SparseTileStorage>,
+        Option<&'static LayeredTileStorage>,
+    ),
+>;
+
+/// The storage the tiles of a tilemap are looked up in: its [`SparseTileStorage`] if it has one,
+/// otherwise its [`TileStorage`]. Tilemaps with a [`LayeredTileStorage`] have none, so that their
+/// tiles are never skipped as culled.
+fn tile_store<'a>(
+    storage_query: &'a StorageQuery,
+    tilemap_entity: Entity,
+) -> Option<&'a dyn TileStore> {
+    match storage_query.get(tilemap_entity).ok()? {
+        (_, _, Some(_)) => None,
+        (_, Some(sparse), None) => Some(sparse),
+        (dense, None, None) => dense.map(|dense| dense as &dyn TileStore),
+    }
+}
+
+/// The layer of the [`LayeredTileStorage`] of its tilemap a tile is stored in. Tiles of other
+/// tilemaps, or which are not stored, are drawn in the bottom layer.
+fn storage_layer(
+    storage_query: &StorageQuery,
+    tilemap_entity: Entity,
+    tile_pos: &TilePos,
+    tile_entity: Entity,
+) -> u32 {
+    storage_query
+        .get(tilemap_entity)
+        .ok()
+        .and_then(|(_, _, layered)| layered)
+        .and_then(|layered| {
+            layered
+                .stack(tile_pos)
+                .position(|entity| entity == Some(tile_entity))
+        })
+        .map_or(0, |layer| layer as u32)
+}
+
+/// Decides which chunks of a tilemap are seen by a camera, from the same transform and bounds
+/// the chunks are culled with once they are prepared.
+#[derive(Clone)]
+struct ChunkCulling {
+    chunk_size: RenderChunkSize,
+    chunk_size_in_tiles: UVec2,
+    map_size: TilemapSize,
+    grid_size: TilemapGridSize,
+    map_type: TilemapType,
+    transform: Affine3A,
+    aabb: Aabb,
+    /// Wrapping tilemaps, and tilemaps without frustum culling, draw every chunk.
+    enabled: bool,
+    /// Only cameras sharing one of these layers see the chunks.
+    render_layers: RenderLayers,
+}
+
+impl ChunkCulling {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        transform: &GlobalTransform,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        anchor: &TilemapAnchor,
+        chunk_size: UVec2,
+        frustum_culling: &FrustumCulling,
+        topology: &TilemapTopology,
+        render_layers: &RenderLayers,
+    ) -> Self {
+        let anchor_offset = anchor.as_offset(map_size, grid_size, tile_size, map_type);
+        Self {
+            chunk_size: RenderChunkSize::new(chunk_size),
+            chunk_size_in_tiles: chunk_size,
+            map_size: *map_size,
+            grid_size: *grid_size,
+            map_type: *map_type,
+            transform: transform.affine() * Affine3A::from_translation(anchor_offset.extend(0.0)),
+            aabb: chunk_aabb(chunk_size, grid_size, tile_size, map_type),
+            enabled: **frustum_culling && !topology.wraps_x() && !topology.wraps_y(),
+            render_layers: render_layers.clone(),
+        }
+    }
+
+    fn chunk(&self, tile_pos: &TilePos) -> UVec2 {
+        self.chunk_size.map_tile_to_chunk(tile_pos)
+    }
+
+    /// Whether one of the `views` which shares a render layer with the tilemap sees the chunk.
+    /// Without any camera, every chunk is seen.
+    fn is_visible(&self, chunk: UVec2, views: &[(Frustum, RenderLayers)]) -> bool {
+        if views.is_empty() {
+            return true;
+        }
+        let mut frustums = views
+            .iter()
+            .filter(|(_, render_layers)| render_layers.intersects(&self.render_layers))
+            .map(|(frustum, _)| frustum)
+            .peekable();
+        if !self.enabled {
+            return frustums.peek().is_some();
+        }
+        let position = chunk_index_to_world_space(
+            chunk,
+            self.chunk_size_in_tiles,
+            &self.grid_size,
+            &self.map_type,
+        );
+        let transform = self.transform * Affine3A::from_translation(position.extend(0.0));
+        frustums.any(|frustum| frustum.intersects_obb(&self.aabb, &transform, true, false))
+    }
+
+    /// Returns the positions of the tiles in the chunk.
+    fn tile_positions(&self, chunk: UVec2) -> impl Iterator<Item = TilePos> + use<> {
+        let start = chunk * self.chunk_size_in_tiles;
+        let end = (start + self.chunk_size_in_tiles).min(UVec2::from(self.map_size));
+        (start.y..end.y).flat_map(move |y| (start.x..end.x).map(move |x| TilePos::new(x, y)))
+    }
+}
+
+/// The components of a tile which are extracted.
+type ExtractedTileData = (
+    (Entity, &'static RenderEntity),
+    Ref<'static, TilePos>,
+    &'static TilePosOld,
+    &'static TilemapId,
+    &'static TileTextureIndex,
+    &'static TileVisible,
+    &'static TileFlip,
+    &'static TileColor,
+    Option<&'static AnimatedTile>,
+    Option<&'static AnimationPaused>,
+    Option<&'static AnimationPhase>,
+    Option<&'static TileFrameAnimation>,
+    Option<&'static TileLayers>,
+    Option<&'static TileCustomData>,
+    (
+        Has<TileOccluder>,
+        Option<&'static TileZOffset>,
+        Option<&'static TileRotation>,
+        Option<&'static TileShape>,
+    ),
+);
+
 #[allow(clippy::too_many_arguments)]
 pub fn extract(
     mut commands: Commands,
     default_image_settings: Res<DefaultSampler>,
+    mut culled_chunks: ResMut<CulledChunks>,
     changed_tiles_query: Extract<
         Query<
+            ExtractedTileData,
             (
-                &RenderEntity,
-                &TilePos,
-                &TilePosOld,
-                &TilemapId,
-                &TileTextureIndex,
-                &TileVisible,
-                &TileFlip,
-                &TileColor,
-                Option<&AnimatedTile>,
+                Or<(
+                    Changed<TilePos>,
+                    Changed<TileVisible>,
+                    Changed<TileTextureIndex>,
+                    Changed<TileFlip>,
+                    Changed<TileColor>,
+                    Changed<AnimatedTile>,
+                    Changed<AnimationPaused>,
+                    Changed<AnimationPhase>,
+                    Changed<TileFrameAnimation>,
+                    Changed<TileLayers>,
+                    Changed<TileCustomData>,
+                    Changed<TileOccluder>,
+                    Changed<TileZOffset>,
+                    Changed<TileRotation>,
+                    Changed<TileShape>,
+                )>,
+                Without<OutsideRegionOfInterest>,
             ),
-            Or<(
-                Changed<TilePos>,
-                Changed<TileVisible>,
-                Changed<TileTextureIndex>,
-                Changed<TileFlip>,
-                Changed<TileColor>,
-                Changed<AnimatedTile>,
-            )>,
         >,
     >,
     tilemap_query: Extract<
@@ -218,6 +467,20 @@ pub fn extract(
             &FrustumCulling,
             &TilemapRenderSettings,
             &TilemapAnchor,
+            Option<&TilemapLayerBlendModes>,
+            Option<&TilemapGridDistortion>,
+            (
+                Option<&TilemapColorGrading>,
+                Option<&TilemapColor>,
+                Option<&TilemapOcclusionReveal>,
+                Option<&TilemapRenderMode>,
+                Option<&TilemapTopology>,
+                Option<&TilemapTexturePadding>,
+                Option<&TilemapChunkSize>,
+                Option<&RenderLayers>,
+                Option<&TilemapSecondaryTexture>,
+                Option<&TilemapDepthMode>,
+            ),
         )>,
     >,
     changed_tilemap_query: Extract<
@@ -236,18 +499,161 @@ pub fn extract(
                 Changed<FrustumCulling>,
                 Changed<TilemapRenderSettings>,
                 Changed<TilemapAnchor>,
+                Changed<TilemapLayerBlendModes>,
+                Changed<TilemapGridDistortion>,
+                Or<(
+                    Changed<TilemapColorGrading>,
+                    Changed<TilemapColor>,
+                    Changed<TilemapLayer>,
+                    Changed<TilemapOcclusionReveal>,
+                    Changed<TilemapRenderMode>,
+                    Changed<TilemapTopology>,
+                    Changed<TilemapTexturePadding>,
+                    Changed<TilemapChunkSize>,
+                    Changed<RenderLayers>,
+                    Changed<TilemapSecondaryTexture>,
+                    Changed<TilemapDepthMode>,
+                )>,
             )>,
         >,
     >,
-    camera_query: Extract<Query<(&RenderEntity, &Frustum), With<Camera>>>,
+    update_state_query: Extract<Query<(Entity, Ref<TilemapUpdateMode>, &TilemapUpdateState)>>,
+    layer_query: Extract<Query<(Entity, &TilemapLayer)>>,
+    layer_order: Extract<Res<TilemapLayerOrder>>,
+    camera_query: Extract<Query<(&RenderEntity, &Frustum, Option<&RenderLayers>), With<Camera>>>,
+    tiles_query: Extract<Query<ExtractedTileData>>,
+    storage_query: Extract<
+        Query<(
+            Option<&TileStorage>,
+            Option<&SparseTileStorage>,
+            Option<&LayeredTileStorage>,
+        )>,
+    >,
     images: Extract<Res<Assets<Image>>>,
 ) {
     let mut extracted_tiles = Vec::new();
+    let mut deferred_tiles = Vec::new();
     let mut extracted_tilemaps = <HashMap<_, _>>::default();
     let mut extracted_tilemap_textures = Vec::new();
+
+    let views: Vec<(Frustum, RenderLayers)> = camera_query
+        .iter()
+        .map(|(_, frustum, render_layers)| (*frustum, render_layers.cloned().unwrap_or_default()))
+        .collect();
+    let mut cullings: HashMap<Entity, ChunkCulling> = HashMap::default();
+    let mut culling = |tilemap_entity: Entity| -> Option<ChunkCulling> {
+        if !cullings.contains_key(&tilemap_entity) {
+            let data = tilemap_query.get(tilemap_entity).ok()?;
+            let culling = ChunkCulling::new(
+                &layered_transform(data.1, tilemap_entity, &layer_query, &layer_order),
+                data.7,
+                data.4,
+                data.2,
+                data.5,
+                data.11,
+                TilemapChunkSize::resolve(data.14.6, data.10),
+                data.9,
+                &data.14.4.copied().unwrap_or_default(),
+                &data.14.7.cloned().unwrap_or_default(),
+            );
+            cullings.insert(tilemap_entity, culling);
+        }
+        cullings.get(&tilemap_entity).cloned()
+    };
+
+    // Tilemaps whose chunk size changed have their chunks rebuilt from all of their tiles.
+    culled_chunks
+        .chunk_sizes
+        .retain(|tilemap_entity, _| tilemap_query.contains(*tilemap_entity));
+    culled_chunks
+        .skipped
+        .retain(|tilemap_entity, _| tilemap_query.contains(*tilemap_entity));
+    let mut rechunked_tilemaps = HashSet::new();
+    for tilemap_entity in changed_tilemap_query.iter() {
+        let Ok(data) = tilemap_query.get(tilemap_entity) else {
+            continue;
+        };
+        let chunk_size = TilemapChunkSize::resolve(data.14.6, data.10);
+        if culled_chunks
+            .chunk_sizes
+            .insert(tilemap_entity, chunk_size)
+            .is_some_and(|old_chunk_size| old_chunk_size != chunk_size)
+        {
+            rechunked_tilemaps.insert(tilemap_entity);
+            culled_chunks.skipped.remove(&tilemap_entity);
+            commands.entity(data.0.id()).insert(TilemapRechunked);
+        }
+    }
+
+    let mut tiles = Vec::new();
+    for tile in changed_tiles_query.iter() {
+        let ((tile_entity, _), ref tile_pos, tile_pos_old, tilemap_id, ..) = tile;
+        if rechunked_tilemaps.contains(&tilemap_id.0) {
+            continue;
+        }
+        // Changes to tiles in chunks no camera sees are picked up from the tile storage once the
+        // chunk comes into view. Tiles which moved are always extracted, to remove them from
+        // their old slot.
+        if (tile_pos.is_added() || **tile_pos == tile_pos_old.0)
+            && tile_store(&storage_query, tilemap_id.0)
+                .is_some_and(|storage| storage.checked_get(tile_pos) == Some(tile_entity))
+            && let Some(culling) = culling(tilemap_id.0)
+        {
+            let chunk = culling.chunk(tile_pos);
+            if !culling.is_visible(chunk, &views) {
+                culled_chunks
+                    .skipped
+                    .entry(tilemap_id.0)
+                    .or_default()
+                    .insert(chunk);
+                continue;
+            }
+        }
+        tiles.push(tile);
+    }
+
+    let CulledChunks { skipped, .. } = &mut *culled_chunks;
+    for (tilemap_entity, chunks) in skipped.iter_mut() {
+        let (Some(culling), Some(storage)) = (
+            culling(*tilemap_entity),
+            tile_store(&storage_query, *tilemap_entity),
+        ) else {
+            continue;
+        };
+        chunks.retain(|chunk| {
+            if !culling.is_visible(*chunk, &views) {
+                return true;
+            }
+            tiles.extend(
+                culling
+                    .tile_positions(*chunk)
+                    .filter_map(|tile_pos| storage.checked_get(&tile_pos))
+                    .filter_map(|tile_entity| tiles_query.get(tile_entity).ok()),
+            );
+            false
+        });
+    }
+    skipped.retain(|_, chunks| !chunks.is_empty());
+
+    for tilemap_entity in &rechunked_tilemaps {
+        if let Some(storage) = tile_store(&storage_query, *tilemap_entity) {
+            tiles.extend(
+                storage
+                    .iter_some()
+                    .filter_map(|(_, tile_entity)| tiles_query.get(tile_entity).ok()),
+            );
+        } else if let Ok((_, _, Some(layered))) = storage_query.get(*tilemap_entity) {
+            tiles.extend(
+                layered
+                    .iter_some()
+                    .filter_map(|(_, _, tile_entity)| tiles_query.get(tile_entity).ok()),
+            );
+        }
+    }
+
     // Process all tiles
     for (
-        render_entity,
+        (tile_entity, render_entity),
         tile_pos,
         tile_pos_old,
         tilemap_id,
@@ -256,18 +662,53 @@ pub fn extract(
         flip,
         color,
         animated,
-    ) in changed_tiles_query.iter()
+        paused,
+        phase,
+        frame_animation,
+        layers,
+        custom_data,
+        (occluder, z_offset, rotation, shape),
+    ) in tiles
     {
         // flipping and rotation packed in bits
         // bit 0 : flip_x
         // bit 1 : flip_y
         // bit 2 : flip_d (anti diagonal)
+        // Quarter turns are folded into the flip, and other angles are stored as the fraction
+        // of a turn, in the fractional part.
+        let rotation = rotation.copied().unwrap_or_default();
+        let flip = flip.rotated(rotation.quarter_turns());
         let tile_flip_bits = flip.x as i32 | ((flip.y as i32) << 1) | ((flip.d as i32) << 2);
+        // Rounding may give a whole turn for tiny negative angles, which must not reach the bits.
+        let turn = (rotation.angle() / TAU).rem_euclid(1.0).fract();
 
         let mut position = Vec4::new(tile_pos.x as f32, tile_pos.y as f32, 0.0, 0.0);
-        let mut texture = Vec4::new(tile_texture.0 as f32, tile_flip_bits as f32, 0.0, 0.0);
-        if let Some(animation_data) = animated {
+        let mut texture = Vec4::new(
+            tile_texture.0 as f32,
+            tile_flip_bits as f32 + turn,
+            0.0,
+            0.0,
+        );
+        if let Some(paused) = paused
+            && (animated.is_some() || frame_animation.is_some())
+        {
+            // A paused animation is drawn like a static tile showing a single frame.
+            let frame = paused
+                .frame
+                .or(animated.map(|animation_data| animation_data.start))
+                .unwrap_or(tile_texture.0) as f32;
+            texture.z = frame;
+            texture.w = frame;
+        } else if let Some(frame_animation) = frame_animation {
+            // A negative end marks an animation of the `TileAnimationTable`, whose entry starts
+            // at `z`.
+            position.z = frame_animation.speed;
+            position.w = phase.map_or(0.0, |phase| phase.0);
+            texture.z = frame_animation.animation.index() as f32;
+            texture.w = -1.0;
+        } else if let Some(animation_data) = animated {
             position.z = animation_data.speed;
+            position.w = phase.map_or(0.0, |phase| phase.0);
             texture.z = animation_data.start as f32;
             texture.w = animation_data.end as f32;
         } else {
@@ -275,21 +716,71 @@ pub fn extract(
             texture.w = tile_texture.0 as f32;
         }
 
+        // The overlays of a paused range animation stay on the frame it is halted on, which the
+        // shader can not tell from its start.
+        let layers = layers.copied().unwrap_or_default();
+        let layers = match (paused, animated) {
+            (Some(paused), Some(animated)) if frame_animation.is_none() => {
+                layers.at_frame(animated, paused.frame.unwrap_or(animated.start))
+            }
+            _ => layers,
+        };
+
         let tile = PackedTileData {
             visible: visible.0,
+            occluder,
             position,
             texture,
             color: color.0.to_linear().to_f32_array(),
+            layers: Vec4::from_array(
+                layers
+                    .0
+                    .map(|layer| layer.map_or(-1.0, |texture_index| texture_index.0 as f32)),
+            ),
+            custom_data: custom_data.copied().unwrap_or_default().0,
+            z_offset: z_offset.map_or(0.0, |z_offset| z_offset.0),
+            shape: shape.copied().unwrap_or_default().corners(),
         };
 
         let data = tilemap_query.get(tilemap_id.0).unwrap();
 
+        let tile = ExtractedTile {
+            entity: render_entity.id(),
+            position: *tile_pos,
+            old_position: if rechunked_tilemaps.contains(&tilemap_id.0) {
+                // The tile is added to a new chunk, and must not be removed from it again.
+                TilePosOld(*tile_pos)
+            } else {
+                *tile_pos_old
+            },
+            tile,
+            tilemap_id: TilemapId(data.0.id()),
+            storage_layer: storage_layer(&storage_query, tilemap_id.0, &tile_pos, tile_entity),
+        };
+
+        // Tiles of tilemaps which are not due for an update are parked in the render world,
+        // without extracting their tilemap, until the tilemap is due.
+        if !rechunked_tilemaps.contains(&tilemap_id.0)
+            && update_state_query
+                .get(tilemap_id.0)
+                .is_ok_and(|(_, _, state)| !state.is_due())
+        {
+            deferred_tiles.push((
+                render_entity.id(),
+                DeferredTileBundle {
+                    tile,
+                    deferred: DeferredTile,
+                },
+            ));
+            continue;
+        }
+
         extracted_tilemaps.insert(
             data.0.id(),
             (
                 data.0.id(),
                 ExtractedTilemapBundle {
-                    transform: *data.1,
+                    transform: layered_transform(data.1, tilemap_id.0, &layer_query, &layer_order),
                     tile_size: *data.2,
                     texture_size: TilemapTextureSize::default(),
                     spacing: *data.3,
@@ -299,35 +790,75 @@ pub fn extract(
                     map_size: *data.7,
                     visibility: *data.8,
                     frustum_culling: *data.9,
-                    render_settings: *data.10,
+                    render_settings: TilemapRenderSettings {
+                        render_chunk_size: TilemapChunkSize::resolve(data.14.6, data.10),
+                        ..*data.10
+                    },
                     changed: ChangedInMainWorld,
                     anchor: *data.11,
+                    layer_blend_modes: data.12.copied().unwrap_or_default(),
+                    grid_distortion: data.13.copied().unwrap_or_default(),
+                    color_grading: data.14.0.cloned().unwrap_or_else(disabled_color_grading),
+                    color: data.14.1.copied().unwrap_or_default(),
+                    occlusion_reveal: data.14.2.copied().unwrap_or_default(),
+                    render_mode: data.14.3.copied().unwrap_or_default(),
+                    topology: data.14.4.copied().unwrap_or_default(),
+                    texture_padding: data.14.5.cloned().unwrap_or_default(),
+                    render_layers: data.14.7.cloned().unwrap_or_default(),
+                    secondary_texture: ExtractedSecondaryTexture(
+                        data.14.8.map(|texture| texture.0.clone()),
+                    ),
+                    depth_mode: data.14.9.copied().unwrap_or_default(),
                 },
             ),
         );
         extracted_tiles.push((
             render_entity.id(),
             ExtractedTileBundle {
-                tile: ExtractedTile {
-                    entity: render_entity.id(),
-                    position: *tile_pos,
-                    old_position: *tile_pos_old,
-                    tile,
-                    tilemap_id: TilemapId(data.0.id()),
-                },
+                tile,
                 changed: ChangedInMainWorld,
             },
         ));
     }
 
-    for tilemap_entity in changed_tilemap_query.iter() {
+    // Tilemaps with a reduced update rate are re-extracted whenever they are due, so their
+    // deferred tiles get prepared. So are tilemaps which were just switched to update every
+    // frame, which may still have tiles deferred from before.
+    let mut due_tilemaps = Vec::new();
+    for (tilemap_entity, mode, state) in update_state_query.iter() {
+        if (*mode != TilemapUpdateMode::EveryFrame || mode.is_changed())
+            && state.is_due()
+            && let Ok(data) = tilemap_query.get(tilemap_entity)
+        {
+            due_tilemaps.push(tilemap_entity);
+            commands.entity(data.0.id()).insert(TilemapUpdateDue);
+        }
+    }
+
+    // Every tilemap on a layer is drawn at a new depth when the order of the layers changes.
+    let relayered_tilemaps = layer_order
+        .is_changed()
+        .then(|| layer_query.iter().map(|(entity, _)| entity))
+        .into_iter()
+        .flatten();
+
+    for tilemap_entity in changed_tilemap_query
+        .iter()
+        .chain(due_tilemaps)
+        .chain(relayered_tilemaps)
+    {
         if let Ok(data) = tilemap_query.get(tilemap_entity) {
             extracted_tilemaps.insert(
                 data.0.id(),
                 (
                     data.0.id(),
                     ExtractedTilemapBundle {
-                        transform: *data.1,
+                        transform: layered_transform(
+                            data.1,
+                            tilemap_entity,
+                            &layer_query,
+                            &layer_order,
+                        ),
                         tile_size: *data.2,
                         texture_size: TilemapTextureSize::default(),
                         spacing: *data.3,
@@ -337,9 +868,25 @@ pub fn extract(
                         map_size: *data.7,
                         visibility: *data.8,
                         frustum_culling: *data.9,
-                        render_settings: *data.10,
+                        render_settings: TilemapRenderSettings {
+                            render_chunk_size: TilemapChunkSize::resolve(data.14.6, data.10),
+                            ..*data.10
+                        },
                         changed: ChangedInMainWorld,
                         anchor: *data.11,
+                        layer_blend_modes: data.12.copied().unwrap_or_default(),
+                        grid_distortion: data.13.copied().unwrap_or_default(),
+                        color_grading: data.14.0.cloned().unwrap_or_else(disabled_color_grading),
+                        color: data.14.1.copied().unwrap_or_default(),
+                        occlusion_reveal: data.14.2.copied().unwrap_or_default(),
+                        render_mode: data.14.3.copied().unwrap_or_default(),
+                        topology: data.14.4.copied().unwrap_or_default(),
+                        texture_padding: data.14.5.cloned().unwrap_or_default(),
+                        render_layers: data.14.7.cloned().unwrap_or_default(),
+                        secondary_texture: ExtractedSecondaryTexture(
+                            data.14.8.map(|texture| texture.0.clone()),
+                        ),
+                        depth_mode: data.14.9.copied().unwrap_or_default(),
                     },
                 ),
             );
@@ -349,9 +896,25 @@ pub fn extract(
     let extracted_tilemaps: Vec<_> = extracted_tilemaps.drain().map(|(_, val)| val).collect();
 
     // Extracts tilemap textures.
-    for (render_entity, _, tile_size, tile_spacing, _, _, texture, _, _, _, _, _) in
+    let mut extracted_secondary_textures = Vec::new();
+    for (render_entity, _, tile_size, tile_spacing, _, _, texture, _, _, _, _, _, _, _, data) in
         tilemap_query.iter()
     {
+        if let Some(secondary_texture) = data.8
+            && secondary_texture.verify_ready(&images)
+        {
+            extracted_secondary_textures.push((
+                render_entity.id(),
+                ExtractedSecondaryTilemapTexture(ExtractedTilemapTexture::new(
+                    render_entity.id(),
+                    secondary_texture.0.clone(),
+                    *tile_size,
+                    *tile_spacing,
+                    default_image_settings.0.min_filter.into(),
+                    &images,
+                )),
+            ));
+        }
         if texture.verify_ready(&images) {
             extracted_tilemap_textures.push((
                 render_entity.id(),
@@ -370,19 +933,80 @@ pub fn extract(
         }
     }
 
-    for (render_entity, frustum) in camera_query.iter() {
+    for (render_entity, frustum, _) in camera_query.iter() {
         commands
             .entity(render_entity.id())
             .insert(ExtractedFrustum { frustum: *frustum });
     }
 
     commands.insert_batch(extracted_tiles);
+    commands.insert_batch(deferred_tiles);
     commands.insert_batch(extracted_tilemaps);
     commands.insert_batch(extracted_tilemap_textures);
+    commands.insert_batch(extracted_secondary_textures);
 }
 
-pub fn remove_changed(mut commands: Commands, query: Query<Entity, With<ChangedInMainWorld>>) {
+pub fn remove_changed(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        Or<(
+            With<ChangedInMainWorld>,
+            With<TilemapUpdateDue>,
+            With<TilemapRechunked>,
+        )>,
+    >,
+) {
     for entity in &query {
-        commands.entity(entity).remove::<ChangedInMainWorld>();
+        commands
+            .entity(entity)
+            .remove::<(ChangedInMainWorld, TilemapUpdateDue, TilemapRechunked)>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_outside_the_view_are_culled() {
+        // A camera seeing from -100 to 100 on both axes, and chunks of 8 by 8 tiles of 16 pixels.
+        let frustum = Frustum::from_clip_from_world(&Mat4::orthographic_rh(
+            -100.0, 100.0, -100.0, 100.0, -1000.0, 1000.0,
+        ));
+        let views = [(frustum, RenderLayers::default())];
+        let culling = |frustum_culling: bool| {
+            ChunkCulling::new(
+                &GlobalTransform::IDENTITY,
+                &TilemapSize::new(20, 20),
+                &TilemapGridSize::new(16.0, 16.0),
+                &TilemapTileSize::new(16.0, 16.0),
+                &TilemapType::Square,
+                &TilemapAnchor::None,
+                UVec2::new(8, 8),
+                &FrustumCulling(frustum_culling),
+                &TilemapTopology::default(),
+                &RenderLayers::default(),
+            )
+        };
+
+        let culling_enabled = culling(true);
+        assert_eq!(
+            culling_enabled.chunk(&TilePos::new(17, 3)),
+            UVec2::new(2, 0)
+        );
+        assert!(culling_enabled.is_visible(UVec2::ZERO, &views));
+        assert!(!culling_enabled.is_visible(UVec2::new(2, 0), &views));
+        // Without cameras, or without frustum culling, every chunk is seen.
+        assert!(culling_enabled.is_visible(UVec2::new(2, 0), &[]));
+        assert!(culling(false).is_visible(UVec2::new(2, 0), &views));
+        // Cameras on other render layers see none of the chunks.
+        let other_views = [(frustum, RenderLayers::layer(1))];
+        assert!(!culling_enabled.is_visible(UVec2::ZERO, &other_views));
+        assert!(!culling(false).is_visible(UVec2::ZERO, &other_views));
+
+        // Chunks on the edge of the tilemap only hold the tiles inside it.
+        assert_eq!(culling_enabled.tile_positions(UVec2::new(2, 2)).count(), 16);
+        assert_eq!(culling_enabled.tile_positions(UVec2::ZERO).count(), 64);
     }
 }
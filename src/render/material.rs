@@ -1,8 +1,9 @@
-use crate::prelude::{TilemapId, TilemapRenderSettings};
+use crate::prelude::{TilemapId, TilemapRenderMode, TilemapRenderSettings};
 
 #[cfg(not(feature = "atlas"))]
 use bevy::render::renderer::RenderQueue;
 use bevy::{
+    camera::visibility::RenderLayers,
     core_pipeline::core_2d::Transparent2d,
     ecs::system::{StaticSystemParam, SystemParamItem},
     math::FloatOrd,
@@ -20,10 +21,11 @@ use bevy::{
         render_resource::{
             AsBindGroup, AsBindGroupError, BindGroup, BindGroupEntry, BindGroupLayout,
             BindingResource, OwnedBindingResource, PipelineCache, RenderPipelineDescriptor,
-            SpecializedRenderPipeline, SpecializedRenderPipelines,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, TextureViewDescriptor,
+            TextureViewDimension,
         },
         renderer::RenderDevice,
-        texture::GpuImage,
+        texture::{FallbackImage, FallbackImageZero, GpuImage},
         view::{ExtractedView, RenderVisibleEntities, ViewUniforms},
     },
 };
@@ -34,6 +36,7 @@ use super::{
     ModifiedImageIds,
     chunk::{ChunkId, RenderChunk2dStorage},
     draw::DrawTilemapMaterial,
+    extract::ExtractedFrustum,
     pipeline::{TilemapPipeline, TilemapPipelineKey},
     prepare,
     queue::{ImageBindGroups, TilemapViewBindGroup},
@@ -42,6 +45,43 @@ use super::{
 #[cfg(not(feature = "atlas"))]
 pub(crate) use super::TextureArrayCache;
 
+/// A material for tilemaps, which can replace the vertex and fragment shaders of the built-in
+/// tilemap pipeline. Its bindings are bound to `@group(3)`.
+///
+/// Custom shaders build on the built-in ones through these WGSL imports, rather than copying them:
+///
+/// - `bevy_ecs_tilemap::vertex_output::MeshVertexOutput`, which the vertex shader passes to the
+///   fragment shader. It holds the texture index, color and
+///   [`TileCustomData`](crate::tiles::TileCustomData) of the tile, among others.
+/// - `bevy_ecs_tilemap::vertex::process_vertex`, the built-in vertex shader. A custom vertex
+///   shader can change its output, e.g. to sway tiles.
+/// - `bevy_ecs_tilemap::common::process_fragment`, the built-in fragment shader, and the pieces it
+///   is made of: `tile_color` returns the color of the tile without discarding transparent
+///   fragments, `tile_position`, `tile_texture_index` and `tile_uv` return the position of the
+///   tile on the tilemap, the texture index it shows and the UV within its texture, and
+///   `sample_secondary_texture` samples the
+///   [`TilemapSecondaryTexture`](crate::map::TilemapSecondaryTexture).
+/// - `bevy_ecs_tilemap::common::tilemap_data`, the uniform of the chunk. Its `time` is the time
+///   of the tilemap, which follows its [`TilemapTimeScale`](crate::map::TilemapTimeScale), and
+///   its `custom_data` holds the [`TilemapCustomData`](crate::map::TilemapCustomData).
+///
+/// ```wgsl
+/// #import bevy_ecs_tilemap::common::{VertexInput, tilemap_data}
+/// #import bevy_ecs_tilemap::vertex::process_vertex
+/// #import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
+/// #import bevy_sprite::mesh2d_view_bindings::view
+///
+/// // Sways the tiles from side to side, each row of a chunk a little later than the one below it.
+/// // The first value of the `TilemapCustomData` is the strength of the wind.
+/// @vertex
+/// fn vertex(vertex_input: VertexInput) -> MeshVertexOutput {
+///     var out = process_vertex(vertex_input);
+///     let phase = tilemap_data.time * 2.0 + vertex_input.position.y * 0.5;
+///     let sway = sin(phase) * tilemap_data.custom_data[0].x;
+///     out.position += view.clip_from_world * vec4<f32>(sway, 0.0, 0.0, 0.0);
+///     return out;
+/// }
+/// ```
 pub trait MaterialTilemap: AsBindGroup + Asset + Clone + Sized {
     /// Returns this material's vertex shader. If [`ShaderRef::Default`] is returned, the default mesh vertex shader
     /// will be used.
@@ -142,6 +182,7 @@ where
 {
     fn build(&self, app: &mut App) {
         app.init_asset::<M>()
+            .register_type::<MaterialTilemapHandle<M>>()
             .add_plugins(ExtractComponentPlugin::<MaterialTilemapHandle<M>>::extract_visible());
     }
 
@@ -416,7 +457,13 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
         Query<(Entity, &ChunkId, &Transform, &TilemapId)>,
         Query<&MaterialTilemapHandle<M>>,
     ),
-    mut views: Query<(&ExtractedView, &Msaa, &RenderVisibleEntities)>,
+    mut views: Query<(
+        &ExtractedView,
+        &Msaa,
+        &RenderVisibleEntities,
+        Option<&ExtractedFrustum>,
+        Option<&RenderLayers>,
+    )>,
     render_materials: Res<RenderMaterialsTilemap<M>>,
     #[cfg(not(feature = "atlas"))] (mut texture_array_cache, render_queue): (
         ResMut<TextureArrayCache>,
@@ -437,7 +484,7 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
         return;
     }
 
-    for (view, msaa, visible_entities) in views.iter_mut() {
+    for (view, msaa, visible_entities, frustum, render_layers) in views.iter_mut() {
         let Some(transparent_phase) = transparent_render_phases.get_mut(&view.retained_view_entity)
         else {
             continue;
@@ -452,7 +499,7 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
             if !visible_entities
                 .get::<TilemapRenderSettings>()
                 .iter()
-                .any(|(entity, _main_entity)| entity.index() == tilemap_id.0.index())
+                .any(|(entity, _main_entity)| *entity == tilemap_id.0)
             {
                 continue;
             }
@@ -464,12 +511,13 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                 continue;
             };
 
-            if let Some(chunk) = chunk_storage.get(&UVec4::new(
-                chunk_id.0.x,
-                chunk_id.0.y,
-                chunk_id.0.z,
-                tilemap_id.0.index(),
-            )) {
+            if let Some(chunk) = chunk_storage.get(tilemap_id.0, &chunk_id.0) {
+                // Chunks are culled against every camera when they are prepared, and each view
+                // only draws the ones it sees itself.
+                if !chunk.is_seen_by(transform, frustum, render_layers) {
+                    continue;
+                }
+
                 #[cfg(not(feature = "atlas"))]
                 if !texture_array_cache.contains(&chunk.texture) {
                     continue;
@@ -484,6 +532,8 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                     msaa: msaa.samples(),
                     map_type: chunk.get_map_type(),
                     hdr: view.hdr,
+                    depth_mode: chunk.depth_mode,
+                    shapes: chunk.has_shapes(),
                 };
 
                 let pipeline_id = material_pipelines.specialize(
@@ -494,11 +544,30 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                         bind_group_data: material.key.clone(),
                     },
                 );
+                if chunk.render_mode() == TilemapRenderMode::YSort {
+                    // Each run of tiles is drawn by its own phase item, which `DrawMesh` finds
+                    // through its extra index.
+                    for (index, (_, z)) in chunk.sort_items.iter().enumerate() {
+                        transparent_phase.add(Transparent2d {
+                            entity: (entity, tilemap_id.0.into()),
+                            draw_function: draw_tilemap,
+                            pipeline: pipeline_id,
+                            sort_key: FloatOrd(*z),
+                            batch_range: 0..1,
+                            extra_index: PhaseItemExtraIndex::DynamicOffset(index as u32),
+                            extracted_index: usize::MAX,
+                            indexed: false,
+                        });
+                    }
+                    continue;
+                }
+
                 let z = if chunk.y_sort {
-                    transform.translation.z
-                        + (1.0
-                            - (transform.translation.y
-                                / (chunk.map_size.y as f32 * chunk.tile_size.y)))
+                    TilemapRenderMode::y_sort_z(
+                        transform.translation,
+                        &chunk.map_size,
+                        &chunk.tile_size,
+                    )
                 } else {
                     transform.translation.z
                 };
@@ -535,6 +604,7 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
     mut views: Query<(Entity, &RenderVisibleEntities)>,
     render_materials: Res<RenderMaterialsTilemap<M>>,
     modified_image_ids: Res<ModifiedImageIds>,
+    (fallback_image, fallback_image_zero): (Res<FallbackImage>, Res<FallbackImageZero>),
     #[cfg(not(feature = "atlas"))] (mut texture_array_cache, render_queue): (
         ResMut<TextureArrayCache>,
         Res<RenderQueue>,
@@ -577,7 +647,7 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
                 if !visible_entities
                     .get::<TilemapRenderSettings>()
                     .iter()
-                    .any(|(entity, _main_entity)| entity.index() == tilemap_id.0.index())
+                    .any(|(entity, _main_entity)| *entity == tilemap_id.0)
                 {
                     continue;
                 }
@@ -589,12 +659,7 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
                     continue;
                 };
 
-                if let Some(chunk) = chunk_storage.get(&UVec4::new(
-                    chunk_id.0.x,
-                    chunk_id.0.y,
-                    chunk_id.0.z,
-                    tilemap_id.0.index(),
-                )) {
+                if let Some(chunk) = chunk_storage.get(tilemap_id.0, &chunk_id.0) {
                     #[cfg(not(feature = "atlas"))]
                     if !texture_array_cache.contains(&chunk.texture) {
                         continue;
@@ -605,11 +670,43 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
                         continue;
                     }
 
+                    // Chunks are drawn without color grading until the lookup table is loaded.
+                    let lut = chunk
+                        .color_grading_lut
+                        .and_then(|id| gpu_images.get(id).map(|image| (id, image)));
+                    // Likewise, the secondary texture reads as transparent black until it is
+                    // loaded.
+                    let secondary = chunk.secondary_texture.as_ref().and_then(|texture| {
+                        #[cfg(not(feature = "atlas"))]
+                        let gpu_image = texture_array_cache
+                            .contains(texture)
+                            .then(|| texture_array_cache.get(texture));
+                        #[cfg(feature = "atlas")]
+                        let gpu_image = gpu_images.get(texture.image_handle());
+                        gpu_image.map(|image| (texture, image))
+                    });
                     let create_bind_group = || {
                         #[cfg(not(feature = "atlas"))]
                         let gpu_image = texture_array_cache.get(&chunk.texture);
                         #[cfg(feature = "atlas")]
                         let gpu_image = gpu_images.get(chunk.texture.image_handle()).unwrap();
+                        let lut_view = match lut {
+                            Some((_, lut_image)) => &lut_image.texture_view,
+                            None => &fallback_image.d2.texture_view,
+                        };
+                        let secondary_view =
+                            match secondary {
+                                Some((_, secondary_image)) => secondary_image.texture_view.clone(),
+                                None => fallback_image_zero.texture.create_view(
+                                    &TextureViewDescriptor {
+                                        #[cfg(not(feature = "atlas"))]
+                                        dimension: Some(TextureViewDimension::D2Array),
+                                        #[cfg(feature = "atlas")]
+                                        dimension: Some(TextureViewDimension::D2),
+                                        ..Default::default()
+                                    },
+                                ),
+                            };
                         render_device.create_bind_group(
                             Some("sprite_material_bind_group"),
                             &tilemap_pipeline.material_layout,
@@ -622,18 +719,37 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
                                     binding: 1,
                                     resource: BindingResource::Sampler(&gpu_image.sampler),
                                 },
+                                BindGroupEntry {
+                                    binding: 2,
+                                    resource: BindingResource::TextureView(lut_view),
+                                },
+                                BindGroupEntry {
+                                    binding: 3,
+                                    resource: BindingResource::TextureView(&secondary_view),
+                                },
                             ],
                         )
                     };
-                    if modified_image_ids.is_texture_modified(&chunk.texture) {
-                        image_bind_groups
-                            .values
-                            .insert(chunk.texture.clone(), create_bind_group());
+                    let key = (
+                        lut.map(|(id, _)| id),
+                        secondary.map(|(texture, _)| texture.clone()),
+                    );
+                    let modified = modified_image_ids.is_texture_modified(&chunk.texture)
+                        || key
+                            .0
+                            .is_some_and(|id| modified_image_ids.is_image_modified(id))
+                        || key
+                            .1
+                            .as_ref()
+                            .is_some_and(|texture| modified_image_ids.is_texture_modified(texture));
+                    let bind_groups = image_bind_groups
+                        .values
+                        .entry(chunk.texture.clone())
+                        .or_default();
+                    if modified {
+                        bind_groups.insert(key, create_bind_group());
                     } else {
-                        image_bind_groups
-                            .values
-                            .entry(chunk.texture.clone())
-                            .or_insert_with(create_bind_group);
+                        bind_groups.entry(key).or_insert_with(create_bind_group);
                     }
                 }
             }
@@ -641,7 +757,46 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
     }
 }
 
-#[derive(AsBindGroup, Debug, Clone, Default, TypePath, Asset)]
-pub struct StandardTilemapMaterial {}
+#[derive(AsBindGroup, Debug, Clone, TypePath, Asset)]
+#[bind_group_data(StandardTilemapMaterialKey)]
+pub struct StandardTilemapMaterial {
+    /// Multiplied with the [`TilemapSecondaryTexture`](crate::map::TilemapSecondaryTexture),
+    /// which is added to the color of the tiles as emission, weighted by its alpha.
+    ///
+    /// Black, the default, disables emission. Emission brightens tiles beyond their texture, so
+    /// 2D lighting which darkens the scene leaves emissive parts of tiles lit.
+    #[uniform(0)]
+    pub emissive: LinearRgba,
+}
 
-impl MaterialTilemap for StandardTilemapMaterial {}
+impl Default for StandardTilemapMaterial {
+    fn default() -> Self {
+        Self {
+            emissive: LinearRgba::BLACK,
+        }
+    }
+}
+
+/// The pipeline key of a [`StandardTilemapMaterial`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StandardTilemapMaterialKey {
+    emissive: bool,
+}
+
+impl From<&StandardTilemapMaterial> for StandardTilemapMaterialKey {
+    fn from(material: &StandardTilemapMaterial) -> Self {
+        Self {
+            emissive: material.emissive != LinearRgba::BLACK,
+        }
+    }
+}
+
+impl MaterialTilemap for StandardTilemapMaterial {
+    fn specialize(descriptor: &mut RenderPipelineDescriptor, key: MaterialTilemapKey<Self>) {
+        if key.bind_group_data.emissive
+            && let Some(fragment) = descriptor.fragment.as_mut()
+        {
+            fragment.shader_defs.push("TILEMAP_EMISSIVE".into());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{
+    platform::collections::HashMap,
+    render::{
+        render_resource::{
+            AddressMode, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BindingResource,
+            BindingType, ColorTargetState, ColorWrites, CommandEncoder, FilterMode, LoadOp,
+            MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
+            PipelineLayoutDescriptor, PrimitiveState, RawFragmentState,
+            RawRenderPipelineDescriptor, RawVertexState, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipeline, Sampler, SamplerBindingType, SamplerDescriptor,
+            ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, Texture,
+            TextureAspect, TextureFormat, TextureFormatFeatureFlags, TextureSampleType,
+            TextureUsages, TextureViewDescriptor, TextureViewDimension,
+        },
+        renderer::RenderDevice,
+    },
+};
+
+/// Returns the number of mip levels in a full mip chain for a texture of the given size.
+pub(crate) fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Returns `true` if mip levels of textures with the given format can be generated by a
+/// [`MipmapGenerator`], i.e. the format can be both filtered and rendered to.
+pub(crate) fn can_generate_mipmaps(render_device: &RenderDevice, format: TextureFormat) -> bool {
+    let features = format.guaranteed_format_features(render_device.features());
+    features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT)
+        && features
+            .flags
+            .contains(TextureFormatFeatureFlags::FILTERABLE)
+}
+
+/// Fills the mip chain of a texture by repeatedly downsampling each level into the next one.
+#[derive(Debug, Clone)]
+pub(crate) struct MipmapGenerator {
+    shader: ShaderModule,
+    layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    sampler: Sampler,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new(render_device: &RenderDevice) -> Self {
+        let shader = render_device.create_and_validate_shader_module(ShaderModuleDescriptor {
+            label: Some("tilemap_mipmap_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/mipmap.wgsl").into()),
+        });
+
+        let layout = render_device.create_bind_group_layout(
+            "tilemap_mipmap_layout",
+            &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        );
+
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("tilemap_mipmap_pipeline_layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("tilemap_mipmap_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            shader,
+            layout,
+            pipeline_layout,
+            sampler,
+            pipelines: HashMap::default(),
+        }
+    }
+
+    fn pipeline(&mut self, render_device: &RenderDevice, format: TextureFormat) -> RenderPipeline {
+        self.pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                render_device.create_render_pipeline(&RawRenderPipelineDescriptor {
+                    label: Some("tilemap_mipmap_pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: RawVertexState {
+                        module: &self.shader,
+                        entry_point: Some("vertex"),
+                        compilation_options: PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    fragment: Some(RawFragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fragment"),
+                        compilation_options: PipelineCompilationOptions::default(),
+                        targets: &[Some(ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Records the commands which generate mip levels `1..` of the given array layers from
+    /// their mip level `0`.
+    ///
+    /// The texture must have been created with [`TextureUsages::RENDER_ATTACHMENT`] and
+    /// [`TextureUsages::TEXTURE_BINDING`].
+    pub fn generate(
+        &mut self,
+        render_device: &RenderDevice,
+        command_encoder: &mut CommandEncoder,
+        texture: &Texture,
+        layers: u32,
+    ) {
+        let format = texture.format();
+        let pipeline = self.pipeline(render_device, format);
+
+        for layer in 0..layers {
+            let views = (0..texture.mip_level_count())
+                .map(|mip_level| {
+                    texture.create_view(&TextureViewDescriptor {
+                        label: Some("tilemap_mipmap_view"),
+                        format: Some(format),
+                        dimension: Some(TextureViewDimension::D2),
+                        aspect: TextureAspect::All,
+                        base_mip_level: mip_level,
+                        mip_level_count: Some(1),
+                        base_array_layer: layer,
+                        array_layer_count: Some(1),
+                        usage: None,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for pair in views.windows(2) {
+                let [source, target] = pair else {
+                    continue;
+                };
+                let bind_group = render_device.create_bind_group(
+                    "tilemap_mipmap_bind_group",
+                    &self.layout,
+                    &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(source),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                );
+
+                let mut pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("tilemap_mipmap_pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: target,
+                        depth_slice: None,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &*bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+}
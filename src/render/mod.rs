@@ -25,9 +25,10 @@ use extract::remove_changed;
 
 use crate::{
     TilemapFirstSet,
-    tiles::{TilePos, TileStorage},
+    tiles::{LayeredTileStorage, SparseTileStorage, TileAnimationTable, TilePos, TileStorage},
 };
 use crate::{
+    map::TilemapSecondaryTexture,
     prelude::TilemapTexture,
     render::{
         material::{MaterialTilemapPlugin, StandardTilemapMaterial},
@@ -35,26 +36,37 @@ use crate::{
     },
 };
 
+pub use self::buffer_pool::ChunkBufferPoolStats;
+pub use self::chunk_stats::RenderChunkStats;
 use self::{
+    animation_table::GpuTileAnimationTable,
+    buffer_pool::ChunkBufferPool,
     chunk::RenderChunk2dStorage,
     draw::DrawTilemap,
     pipeline::{TILEMAP_SHADER_FRAGMENT, TILEMAP_SHADER_VERTEX, TilemapPipeline},
     queue::ImageBindGroups,
 };
 
+mod animation_table;
+mod buffer_pool;
 mod chunk;
+mod chunk_stats;
 mod draw;
 mod extract;
 pub mod material;
 mod pipeline;
 pub(crate) mod prepare;
 mod queue;
+mod shader_data;
+mod storage_layers;
 
+#[cfg(not(feature = "atlas"))]
+mod mipmap;
 #[cfg(not(feature = "atlas"))]
 mod texture_array_cache;
 
 #[cfg(not(feature = "atlas"))]
-use self::extract::ExtractedTilemapTexture;
+use self::extract::{ExtractedSecondaryTilemapTexture, ExtractedTilemapTexture};
 #[cfg(not(feature = "atlas"))]
 pub(crate) use self::texture_array_cache::TextureArrayCache;
 
@@ -106,16 +118,31 @@ pub const STAGGERED_ISO: Handle<Shader> = uuid_handle!("da349823-a307-44a5-ab78-
 pub const SQUARE: Handle<Shader> = uuid_handle!("6db56afb-a562-4e3c-b459-486a6d5c12ae");
 pub const TILEMAP_VERTEX_OUTPUT: Handle<Shader> =
     uuid_handle!("49b568da-6c5a-4936-a3c8-d5dd6b894f92");
+pub const VERTEX: Handle<Shader> = uuid_handle!("3f0c6a1e-8d52-4b7e-9a61-2c4e5d7f8b90");
 
 impl Plugin for TilemapRenderingPlugin {
     fn build(&self, app: &mut App) {
         #[cfg(not(feature = "atlas"))]
         app.add_systems(Update, set_texture_to_copy_src);
 
+        app.add_systems(
+            Update,
+            (
+                (
+                    crate::map::build_tilemap_texture_atlases,
+                    crate::map::build_tilemap_tilesets,
+                ),
+                crate::map::pad_tilemap_textures,
+            )
+                .chain(),
+        );
+
         app.add_systems(First, clear_removed.in_set(TilemapFirstSet));
 
         app.add_observer(on_remove_tile);
-        app.add_observer(on_remove_tilemap);
+        app.add_observer(on_remove_tilemap::<TileStorage>);
+        app.add_observer(on_remove_tilemap::<SparseTileStorage>);
+        app.add_observer(on_remove_tilemap::<LayeredTileStorage>);
 
         app.add_plugins(ExtractComponentPlugin::<RemovedTileEntity>::default());
         app.add_plugins(ExtractComponentPlugin::<RemovedMapEntity>::default());
@@ -131,6 +158,8 @@ impl Plugin for TilemapRenderingPlugin {
             .unwrap();
 
         app.init_resource::<ModifiedImageIds>()
+            .init_resource::<ChunkBufferPoolStats>()
+            .init_resource::<RenderChunkStats>()
             .add_systems(Update, collect_modified_image_asset_messages);
     }
 
@@ -211,6 +240,8 @@ impl Plugin for TilemapRenderingPlugin {
             Shader::from_wgsl
         );
 
+        load_internal_asset!(app, VERTEX, "shaders/vertex.wgsl", Shader::from_wgsl);
+
         load_internal_asset!(
             app,
             TILEMAP_SHADER_VERTEX,
@@ -244,16 +275,38 @@ impl Plugin for TilemapRenderingPlugin {
         render_app
             .insert_resource(DefaultSampler(sampler))
             .insert_resource(RenderChunk2dStorage::default())
+            .init_resource::<ChunkBufferPool>()
+            .init_resource::<RenderChunkStats>()
+            .init_resource::<extract::CulledChunks>()
+            .init_resource::<shader_data::ExtractedTilemapShaderData>()
+            .init_resource::<storage_layers::ExtractedLayerZOffsets>()
             .add_systems(
                 ExtractSchedule,
-                (extract::extract, extract_resource::<ModifiedImageIds>),
+                (
+                    extract::extract,
+                    shader_data::extract_tilemap_shader_data,
+                    storage_layers::extract_layer_z_offsets,
+                    extract_resource::<ModifiedImageIds>,
+                    extract_resource::<TileAnimationTable>,
+                    buffer_pool::extract_buffer_pool_stats,
+                    chunk_stats::extract_render_chunk_stats,
+                ),
             )
             .add_systems(
                 Render,
-                (prepare::prepare_removal, prepare::prepare)
+                (
+                    prepare::prepare_removal,
+                    prepare::prepare_deferred_tiles,
+                    prepare::prepare,
+                )
                     .chain()
                     .in_set(RenderSystems::PrepareAssets),
             )
+            .add_systems(
+                Render,
+                animation_table::prepare_tile_animation_table
+                    .in_set(RenderSystems::PrepareResources),
+            )
             .add_systems(
                 Render,
                 queue::queue_transform_bind_group.in_set(RenderSystems::PrepareBindGroups),
@@ -263,6 +316,7 @@ impl Plugin for TilemapRenderingPlugin {
             .init_resource::<SpecializedRenderPipelines<TilemapPipeline>>()
             .init_resource::<MeshUniformResource>()
             .init_resource::<TilemapUniformResource>()
+            .init_resource::<GpuTileAnimationTable>()
             .init_resource::<ModifiedImageIds>();
 
         render_app.add_render_command::<Transparent2d, DrawTilemap>();
@@ -272,11 +326,15 @@ impl Plugin for TilemapRenderingPlugin {
 pub fn set_texture_to_copy_src(
     mut images: ResMut<Assets<Image>>,
     texture_query: Query<&TilemapTexture>,
+    secondary_texture_query: Query<&TilemapSecondaryTexture>,
 ) {
     // quick and dirty, run this for all textures anytime a texture component is created.
     for texture in texture_query.iter() {
         texture.set_images_to_copy_src(&mut images)
     }
+    for texture in secondary_texture_query.iter() {
+        texture.set_images_to_copy_src(&mut images)
+    }
 }
 
 /// Stores the index of a uniform inside of [`ComponentUniforms`].
@@ -299,6 +357,12 @@ pub const ATTRIBUTE_TEXTURE: MeshVertexAttribute =
     MeshVertexAttribute::new("Texture", 222922753, VertexFormat::Float32x4);
 pub const ATTRIBUTE_COLOR: MeshVertexAttribute =
     MeshVertexAttribute::new("Color", 231497124, VertexFormat::Float32x4);
+pub const ATTRIBUTE_LAYERS: MeshVertexAttribute =
+    MeshVertexAttribute::new("Layers", 238472165, VertexFormat::Float32x4);
+pub const ATTRIBUTE_CUSTOM_DATA: MeshVertexAttribute =
+    MeshVertexAttribute::new("CustomData", 243915836, VertexFormat::Float32x4);
+pub const ATTRIBUTE_SHAPE: MeshVertexAttribute =
+    MeshVertexAttribute::new("Shape", 250643817, VertexFormat::Float32x2);
 
 #[derive(Component, ExtractComponent, Clone)]
 
@@ -317,8 +381,8 @@ fn on_remove_tile(
     }
 }
 
-fn on_remove_tilemap(
-    removed: On<Remove, TileStorage>,
+fn on_remove_tilemap<S: Component>(
+    removed: On<Remove, S>,
     mut commands: Commands,
     query: Query<&RenderEntity>,
 ) {
@@ -346,11 +410,15 @@ fn prepare_textures(
     render_device: Res<RenderDevice>,
     mut texture_array_cache: ResMut<TextureArrayCache>,
     extracted_tilemap_textures: Query<&ExtractedTilemapTexture>,
+    extracted_secondary_textures: Query<&ExtractedSecondaryTilemapTexture>,
     render_images: Res<bevy::render::render_asset::RenderAssets<GpuImage>>,
 ) {
     for extracted_texture in extracted_tilemap_textures.iter() {
         texture_array_cache.add_extracted_texture(extracted_texture);
     }
+    for extracted_texture in extracted_secondary_textures.iter() {
+        texture_array_cache.add_extracted_texture(&extracted_texture.0);
+    }
 
     texture_array_cache.prepare(&render_device, &render_images);
 }
@@ -367,6 +435,10 @@ impl ModifiedImageIds {
             .iter()
             .any(|&image| self.0.contains(&image.id()))
     }
+
+    pub fn is_image_modified(&self, image: AssetId<Image>) -> bool {
+        self.0.contains(&image)
+    }
 }
 
 /// A system to collect the asset events of modified images for one frame.
@@ -13,14 +13,15 @@ use bevy::{
             MultisampleState, PolygonMode, PrimitiveState, PrimitiveTopology,
             RenderPipelineDescriptor, SamplerBindingType, ShaderStages, ShaderType,
             SpecializedRenderPipeline, StencilFaceState, StencilState, TextureFormat,
-            TextureSampleType, TextureViewDimension, VertexFormat, VertexState, VertexStepMode,
+            TextureSampleType, TextureViewDimension, VertexAttribute, VertexFormat, VertexState,
+            VertexStepMode,
         },
         renderer::RenderDevice,
         view::{ViewTarget, ViewUniform},
     },
 };
 
-use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapType};
+use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapDepthMode, TilemapType};
 
 use super::{chunk::TilemapUniformData, prepare::MeshUniform};
 
@@ -92,6 +93,17 @@ impl FromWorld for TilemapPipeline {
                     },
                     count: None,
                 },
+                // The `TileAnimationTable`.
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
             ],
         );
 
@@ -115,6 +127,28 @@ impl FromWorld for TilemapPipeline {
                     ty: BindingType::Sampler(SamplerBindingType::Filtering),
                     count: None,
                 },
+                // The color grading lookup table.
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                // The `TilemapSecondaryTexture`, laid out like the tilemap texture.
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                },
             ],
         );
 
@@ -138,6 +172,28 @@ impl FromWorld for TilemapPipeline {
                     ty: BindingType::Sampler(SamplerBindingType::Filtering),
                     count: None,
                 },
+                // The color grading lookup table.
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                // The `TilemapSecondaryTexture`, laid out like the tilemap texture.
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
             ],
         );
 
@@ -153,6 +209,9 @@ pub struct TilemapPipelineKey {
     pub msaa: u32,
     pub map_type: TilemapType,
     pub hdr: bool,
+    pub depth_mode: TilemapDepthMode,
+    /// Whether the vertices of the chunk hold the corners of [`TileShape`](crate::tiles::TileShape)s.
+    pub shapes: bool,
 }
 
 impl SpecializedRenderPipeline for TilemapPipeline {
@@ -181,24 +240,51 @@ impl SpecializedRenderPipeline for TilemapPipeline {
         };
         shader_defs.push(mesh_string.into());
 
-        let formats = vec![
+        if key.depth_mode == TilemapDepthMode::PerTile {
+            shader_defs.push("TILEMAP_DEPTH_PER_TILE".into());
+        }
+
+        if key.shapes {
+            shader_defs.push("TILE_SHAPES".into());
+        }
+
+        let mut formats = vec![
             // Position
             VertexFormat::Float32x4,
             // Uv
             VertexFormat::Float32x4,
             // Color
             VertexFormat::Float32x4,
+            // Overlay layers
+            VertexFormat::Float32x4,
+            // Custom data
+            VertexFormat::Float32x4,
         ];
+        if key.shapes {
+            // Shape
+            formats.push(VertexFormat::Float32x2);
+        }
 
         let vertex_layout =
             VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats);
+        // Tile flags (visibility and occluders), which live in their own buffer so they can be
+        // updated without the mesh.
+        let visibility_layout = VertexBufferLayout {
+            array_stride: VertexFormat::Uint32.size(),
+            step_mode: VertexStepMode::Vertex,
+            attributes: vec![VertexAttribute {
+                format: VertexFormat::Uint32,
+                offset: 0,
+                shader_location: 6,
+            }],
+        };
 
         RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: TILEMAP_SHADER_VERTEX,
                 entry_point: Some("vertex".into()),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![vertex_layout],
+                buffers: vec![vertex_layout, visibility_layout],
             },
             fragment: Some(FragmentState {
                 shader: TILEMAP_SHADER_FRAGMENT,
@@ -241,7 +327,7 @@ impl SpecializedRenderPipeline for TilemapPipeline {
             },
             depth_stencil: Some(DepthStencilState {
                 format: CORE_2D_DEPTH_FORMAT,
-                depth_write_enabled: false,
+                depth_write_enabled: key.depth_mode != TilemapDepthMode::None,
                 depth_compare: CompareFunction::GreaterEqual,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
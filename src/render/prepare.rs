@@ -2,14 +2,19 @@ use std::marker::PhantomData;
 
 use crate::anchor::TilemapAnchor;
 use crate::map::{
-    TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize, TilemapTileSize,
+    TilemapColor, TilemapColorGrading, TilemapDepthMode, TilemapGridDistortion, TilemapId,
+    TilemapLayerBlendModes, TilemapOcclusionReveal, TilemapRenderMode, TilemapSize, TilemapSpacing,
+    TilemapTexture, TilemapTexturePadding, TilemapTextureSize, TilemapTileSize, TilemapTopology,
     TilemapType,
 };
 use crate::prelude::TilemapRenderSettings;
 use crate::render::extract::ExtractedFrustum;
 use crate::{FrustumCulling, prelude::TilemapGridSize, render::RenderChunkSize};
-use bevy::prelude::{InheritedVisibility, Resource, Transform, With};
+use bevy::camera::visibility::RenderLayers;
+use bevy::ecs::query::Has;
+use bevy::prelude::{ColorToComponents, InheritedVisibility, Resource, Time, Transform, With};
 use bevy::render::sync_world::TemporaryRenderEntity;
+use bevy::tasks::{ComputeTaskPool, ParallelSliceMut};
 use bevy::{log::trace, mesh::MeshVertexBufferLayouts};
 use bevy::{
     math::{Mat4, UVec4},
@@ -20,11 +25,20 @@ use bevy::{
     },
 };
 
-use super::extract::ChangedInMainWorld;
+use super::buffer_pool::ChunkBufferPool;
+use super::chunk_stats::RenderChunkStats;
+use super::extract::{ChangedInMainWorld, DeferredTile, TilemapUpdateDue};
+use super::shader_data::ExtractedTilemapShaderData;
+use super::storage_layers::ExtractedLayerZOffsets;
 use super::{
     DynamicUniformIndex,
-    chunk::{ChunkId, PackedTileData, RenderChunk2dStorage, TilemapUniformData},
-    extract::{ExtractedTile, ExtractedTilemapTexture},
+    chunk::{
+        ChunkColorGradingLut, ChunkId, ChunkSecondaryTexture, PackedTileData, RenderChunk2dStorage,
+        TilemapUniformData,
+    },
+    extract::{
+        ExtractedSecondaryTexture, ExtractedTile, ExtractedTilemapTexture, TilemapRechunked,
+    },
 };
 use super::{RemovedMapEntity, RemovedTileEntity};
 
@@ -43,8 +57,15 @@ pub struct MeshUniform {
 pub(crate) fn prepare(
     mut commands: Commands,
     mut chunk_storage: ResMut<RenderChunk2dStorage>,
+    mut buffer_pool: ResMut<ChunkBufferPool>,
+    mut stats: ResMut<RenderChunkStats>,
     mut mesh_uniforms: ResMut<MeshUniformResource>,
     mut tilemap_uniforms: ResMut<TilemapUniformResource>,
+    (tilemap_shader_data, layer_z_offsets): (
+        Res<ExtractedTilemapShaderData>,
+        Res<ExtractedLayerZOffsets>,
+    ),
+    time: Res<Time>,
     extracted_tiles: Query<&ExtractedTile, With<ChangedInMainWorld>>,
     extracted_tilemaps: Query<
         (
@@ -61,18 +82,42 @@ pub(crate) fn prepare(
             &FrustumCulling,
             &TilemapRenderSettings,
             &TilemapAnchor,
+            (
+                &TilemapLayerBlendModes,
+                &TilemapGridDistortion,
+                &TilemapColorGrading,
+                &TilemapColor,
+                &TilemapOcclusionReveal,
+                &TilemapRenderMode,
+                &TilemapTopology,
+                &TilemapTexturePadding,
+                &RenderLayers,
+                &ExtractedSecondaryTexture,
+                &TilemapDepthMode,
+            ),
         ),
         With<ChangedInMainWorld>,
     >,
     extracted_tilemap_textures: Query<&ExtractedTilemapTexture, With<ChangedInMainWorld>>,
     extracted_frustum_query: Query<&ExtractedFrustum>,
+    rechunked_tilemaps: Query<Entity, With<TilemapRechunked>>,
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     mut mesh_vertex_buffer_layouts: ResMut<MeshVertexBufferLayouts>,
 ) {
+    // The chunks of tilemaps whose chunk size changed are built again from all of their tiles,
+    // which were extracted along with them.
+    for tilemap_entity in rechunked_tilemaps.iter() {
+        chunk_storage.remove_map(tilemap_entity, &mut buffer_pool);
+    }
+
     for tile in extracted_tiles.iter() {
-        // First if the tile position has changed remove the tile from the old location.
-        if tile.position != tile.old_position.0 {
+        // First if the tile position or layer has changed remove the tile from the old location.
+        if tile.position != tile.old_position.0
+            || chunk_storage
+                .chunk_index_of(tile.entity)
+                .is_some_and(|chunk_id| chunk_id.z != tile.storage_layer)
+        {
             chunk_storage.remove_tile_with_entity(tile.entity);
         }
 
@@ -90,23 +135,19 @@ pub(crate) fn prepare(
             frustum_culling,
             tilemap_render_settings,
             _,
+            _,
         ) = extracted_tilemaps.get(tile.tilemap_id.0).unwrap();
         let chunk_size = RenderChunkSize(tilemap_render_settings.render_chunk_size);
         let chunk_index = chunk_size.map_tile_to_chunk(&tile.position);
-
-        let chunk_data = UVec4::new(
-            chunk_index.x,
-            chunk_index.y,
-            transform.translation().z as u32,
-            tile.tilemap_id.0.index(),
-        );
+        // Every layer of a layered tile storage is drawn in chunks of its own.
+        let chunk_id = chunk_index.extend(tile.storage_layer);
 
         let in_chunk_tile_index = chunk_size.map_tile_to_chunk_tile(&tile.position, &chunk_index);
         let chunk = chunk_storage.get_or_add(
             tile.entity,
             in_chunk_tile_index,
             tile.tilemap_id.0,
-            &chunk_data,
+            chunk_id,
             *chunk_size,
             *mesh_type,
             *tile_size,
@@ -149,9 +190,22 @@ pub(crate) fn prepare(
         frustum_culling,
         _,
         anchor,
+        (
+            layer_blend_modes,
+            grid_distortion,
+            color_grading,
+            color,
+            occlusion_reveal,
+            render_mode,
+            topology,
+            texture_padding,
+            render_layers,
+            secondary_texture,
+            depth_mode,
+        ),
     ) in extracted_tilemaps.iter()
     {
-        let chunks = chunk_storage.get_chunk_storage(&UVec4::new(0, 0, 0, entity.index()));
+        let chunks = chunk_storage.get_chunk_storage(entity);
         for chunk in chunks.values_mut() {
             chunk.texture = texture.clone();
             chunk.map_size = *map_size;
@@ -159,6 +213,24 @@ pub(crate) fn prepare(
             chunk.spacing = (*spacing).into();
             chunk.visible = visibility.get();
             chunk.frustum_culling = **frustum_culling;
+            chunk.layer_blend_modes =
+                UVec4::from_array(layer_blend_modes.0.map(|mode| mode as u32));
+            chunk.distortion = grid_distortion
+                .amplitude
+                .extend(grid_distortion.frequency)
+                .extend(0.0);
+            chunk.distortion_seed = grid_distortion.seed;
+            chunk.color_grading_lut =
+                (color_grading.strength > 0.0).then(|| color_grading.lut.id());
+            chunk.color_grading_strength = color_grading.strength;
+            chunk.color = color.0.to_linear().to_vec4();
+            chunk.occlusion_reveal = *occlusion_reveal;
+            chunk.set_render_mode(*render_mode);
+            chunk.topology = *topology;
+            chunk.texture_padding = texture_padding.applied_pixels() as f32;
+            chunk.render_layers = render_layers.clone();
+            chunk.secondary_texture = secondary_texture.0.clone();
+            chunk.depth_mode = *depth_mode;
             let anchor_offset: Vec2 = anchor.as_offset(map_size, grid_size, tile_size, map_type);
             // The following code that merely adds a vector would be faster and
             // work in most usecases.
@@ -176,10 +248,19 @@ pub(crate) fn prepare(
         }
     }
 
+    for (tilemap_entity, z_offsets) in &layer_z_offsets.0 {
+        for chunk in chunk_storage
+            .get_chunk_storage(*tilemap_entity)
+            .values_mut()
+        {
+            let layer = chunk.get_index().z as usize;
+            chunk.set_z_offset(z_offsets.get(layer).copied().unwrap_or(0.0));
+        }
+    }
+
     for tilemap in extracted_tilemap_textures.iter() {
         let texture_size: Vec2 = tilemap.texture_size.into();
-        let chunks =
-            chunk_storage.get_chunk_storage(&UVec4::new(0, 0, 0, tilemap.tilemap_id.0.index()));
+        let chunks = chunk_storage.get_chunk_storage(tilemap.tilemap_id.0);
         for chunk in chunks.values_mut() {
             chunk.texture_size = texture_size;
         }
@@ -188,43 +269,84 @@ pub(crate) fn prepare(
     mesh_uniforms.0.clear();
     tilemap_uniforms.0.clear();
 
-    for chunk in chunk_storage.iter_mut() {
-        if !chunk.visible {
-            trace!("Visibility culled chunk: {:?}", chunk.get_index());
-            continue;
-        }
+    let frustums: Vec<&ExtractedFrustum> = extracted_frustum_query.iter().collect();
+    let mut visible_chunks = chunk_storage
+        .iter_mut()
+        .filter_map(|chunk| {
+            if !chunk.visible {
+                trace!("Visibility culled chunk: {:?}", chunk.get_index());
+                return None;
+            }
 
-        if chunk.frustum_culling
-            && !extracted_frustum_query
-                .iter()
-                .any(|frustum| chunk.intersects_frustum(frustum))
-        {
-            trace!("Frustum culled chunk: {:?}", chunk.get_index());
-            continue;
-        }
+            let transforms = chunk.visible_transforms(&frustums);
+            if transforms.is_empty() {
+                trace!("Frustum culled chunk: {:?}", chunk.get_index());
+                return None;
+            }
+            Some((chunk, transforms))
+        })
+        .collect::<Vec<_>>();
 
-        chunk.prepare(&render_device, &mut mesh_vertex_buffer_layouts);
-
-        let chunk_uniform: TilemapUniformData = chunk.into();
-
-        commands.spawn((
-            chunk.texture.clone(),
-            chunk.get_transform(),
-            ChunkId(chunk.get_index()),
-            chunk.get_map_type(),
-            TilemapId(Entity::from_bits(chunk.tilemap_id)),
-            DynamicUniformIndex::<MeshUniform> {
-                index: mesh_uniforms.0.push(&MeshUniform {
-                    transform: chunk.get_transform_matrix(),
-                }),
-                marker: PhantomData,
-            },
-            DynamicUniformIndex::<TilemapUniformData> {
-                index: tilemap_uniforms.0.push(&chunk_uniform),
-                marker: PhantomData,
-            },
-            TemporaryRenderEntity,
-        ));
+    // Building the meshes of the chunks whose tiles changed is the expensive part when many
+    // tiles change at once, so it is spread over the compute task pool. Only uploading them to
+    // the GPU is left to the loop below.
+    let mut dirty_chunks = visible_chunks
+        .iter_mut()
+        .filter(|(chunk, _)| chunk.dirty_mesh)
+        .map(|(chunk, _)| &mut **chunk)
+        .collect::<Vec<_>>();
+    stats.rebuilt_chunks += dirty_chunks.len() as u32;
+    if dirty_chunks.len() > 1 {
+        dirty_chunks.par_splat_map_mut(ComputeTaskPool::get(), None, |_, chunks| {
+            for chunk in chunks {
+                chunk.build_mesh();
+            }
+        });
+    }
+
+    stats.visible_chunks += visible_chunks.len() as u32;
+    for (chunk, transforms) in visible_chunks {
+        chunk.prepare(
+            &render_device,
+            &render_queue,
+            &mut buffer_pool,
+            &mut mesh_vertex_buffer_layouts,
+        );
+
+        let mut chunk_uniform: TilemapUniformData = chunk.into();
+        let shader_data = tilemap_shader_data
+            .0
+            .get(&Entity::from_bits(chunk.tilemap_id))
+            .copied()
+            .unwrap_or_default();
+        chunk_uniform.time = shader_data
+            .time
+            .unwrap_or_else(|| time.elapsed_secs_wrapped());
+        chunk_uniform.custom_data = shader_data.custom_data;
+
+        // Wrapping tilemaps draw the same chunk again at each of its visible copies.
+        for transform in transforms {
+            commands.spawn((
+                chunk.texture.clone(),
+                transform,
+                ChunkId(chunk.get_index()),
+                ChunkColorGradingLut(chunk.color_grading_lut),
+                ChunkSecondaryTexture(chunk.secondary_texture.clone()),
+                chunk.get_map_type(),
+                TilemapId(Entity::from_bits(chunk.tilemap_id)),
+                DynamicUniformIndex::<MeshUniform> {
+                    index: mesh_uniforms.0.push(&MeshUniform {
+                        transform: transform.to_matrix(),
+                    }),
+                    marker: PhantomData,
+                },
+                DynamicUniformIndex::<TilemapUniformData> {
+                    index: tilemap_uniforms.0.push(&chunk_uniform),
+                    marker: PhantomData,
+                },
+                TemporaryRenderEntity,
+            ));
+        }
     }
 
     mesh_uniforms.0.write_buffer(&render_device, &render_queue);
@@ -235,6 +357,7 @@ pub(crate) fn prepare(
 
 pub fn prepare_removal(
     mut chunk_storage: ResMut<RenderChunk2dStorage>,
+    mut buffer_pool: ResMut<ChunkBufferPool>,
     removed_tiles: Query<&RemovedTileEntity>,
     removed_maps: Query<&RemovedMapEntity>,
 ) {
@@ -243,6 +366,91 @@ pub fn prepare_removal(
     }
 
     for removed_map in removed_maps.iter() {
-        chunk_storage.remove_map(removed_map.0.id());
+        chunk_storage.remove_map(removed_map.0.id(), &mut buffer_pool);
+    }
+}
+
+/// Releases the deferred tiles of every tilemap which is due for an update this frame, so they
+/// are picked up by [`prepare`].
+///
+/// Deferred tiles which were extracted again this frame, without being deferred, were extracted
+/// while their tilemap was due, and are released too.
+pub fn prepare_deferred_tiles(
+    mut commands: Commands,
+    mut chunk_storage: ResMut<RenderChunk2dStorage>,
+    deferred_tiles: Query<(Entity, &ExtractedTile, Has<ChangedInMainWorld>), With<DeferredTile>>,
+    due_tilemaps: Query<(), With<TilemapUpdateDue>>,
+) {
+    for (entity, tile, extracted_again) in deferred_tiles.iter() {
+        if !extracted_again && !due_tilemaps.contains(tile.tilemap_id.0) {
+            continue;
+        }
+
+        // The tile may have moved several times while it was deferred, so its old position is
+        // not reliable. Remove it from wherever it is stored before it is added again.
+        chunk_storage.remove_tile_with_entity(tile.entity);
+        commands
+            .entity(entity)
+            .remove::<DeferredTile>()
+            .insert(ChangedInMainWorld);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{TilePos, TilePosOld};
+    use bevy::ecs::schedule::Schedule;
+    use bevy::math::Vec4;
+    use bevy::prelude::World;
+
+    fn extracted_tile(tilemap: Entity) -> ExtractedTile {
+        ExtractedTile {
+            entity: Entity::PLACEHOLDER,
+            position: TilePos::new(1, 1),
+            old_position: TilePosOld(TilePos::new(1, 1)),
+            tile: PackedTileData {
+                visible: true,
+                occluder: false,
+                position: Vec4::ZERO,
+                texture: Vec4::ZERO,
+                color: [1.0; 4],
+                layers: Vec4::splat(-1.0),
+                custom_data: Vec4::ZERO,
+                z_offset: 0.0,
+                shape: [Vec2::ZERO; 4],
+            },
+            tilemap_id: TilemapId(tilemap),
+            storage_layer: 0,
+        }
+    }
+
+    #[test]
+    fn deferred_tiles_are_held_back_until_their_tilemap_is_due() {
+        let mut world = World::new();
+        world.init_resource::<RenderChunk2dStorage>();
+        let tilemap = world.spawn_empty().id();
+        let held = world.spawn((extracted_tile(tilemap), DeferredTile)).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(prepare_deferred_tiles);
+
+        schedule.run(&mut world);
+        assert!(world.entity(held).contains::<DeferredTile>());
+        assert!(!world.entity(held).contains::<ChangedInMainWorld>());
+
+        // A deferred tile extracted again was extracted while its tilemap was due, e.g. after the
+        // tilemap was switched to update every frame, and is not held back any longer.
+        let extracted_again = world
+            .spawn((extracted_tile(tilemap), DeferredTile, ChangedInMainWorld))
+            .id();
+        schedule.run(&mut world);
+        assert!(!world.entity(extracted_again).contains::<DeferredTile>());
+        assert!(world.entity(held).contains::<DeferredTile>());
+
+        // Once the tilemap is due, its deferred tiles are flushed to `prepare`.
+        world.entity_mut(tilemap).insert(TilemapUpdateDue);
+        schedule.run(&mut world);
+        assert!(!world.entity(held).contains::<DeferredTile>());
+        assert!(world.entity(held).contains::<ChangedInMainWorld>());
     }
 }
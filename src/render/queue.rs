@@ -2,12 +2,13 @@ use bevy::{
     platform::collections::HashMap,
     prelude::*,
     render::{
-        render_resource::{BindGroup, BindGroupEntry},
+        render_resource::{BindGroup, BindGroupEntry, BindingResource},
         renderer::RenderDevice,
     },
 };
 
 use super::{
+    animation_table::GpuTileAnimationTable,
     pipeline::TilemapPipeline,
     prepare::{MeshUniformResource, TilemapUniformResource},
 };
@@ -24,6 +25,7 @@ pub fn queue_transform_bind_group(
     render_device: Res<RenderDevice>,
     transform_uniforms: Res<MeshUniformResource>,
     tilemap_uniforms: Res<TilemapUniformResource>,
+    animation_table: Res<GpuTileAnimationTable>,
 ) {
     if let (Some(binding1), Some(binding2)) =
         (transform_uniforms.0.binding(), tilemap_uniforms.0.binding())
@@ -41,6 +43,10 @@ pub fn queue_transform_bind_group(
                         binding: 1,
                         resource: binding2,
                     },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&animation_table.texture_view),
+                    },
                 ],
             ),
         });
@@ -52,7 +58,13 @@ pub struct TilemapViewBindGroup {
     pub value: BindGroup,
 }
 
+/// The texture bind groups of the tilemaps, by texture and then by color grading lookup table and
+/// secondary texture.
 #[derive(Default, Resource)]
 pub struct ImageBindGroups {
-    pub values: HashMap<TilemapTexture, BindGroup>,
+    pub values: HashMap<TilemapTexture, HashMap<ImageBindGroupKey, BindGroup>>,
 }
+
+/// The color grading lookup table and secondary texture of a texture bind group. Either is `None`
+/// while it is not loaded.
+pub type ImageBindGroupKey = (Option<AssetId<Image>>, Option<TilemapTexture>);
@@ -0,0 +1,47 @@
+use bevy::{
+    math::Vec4,
+    platform::collections::HashMap,
+    prelude::{Entity, Or, Query, ResMut, Resource, With},
+    render::{Extract, sync_world::RenderEntity},
+};
+
+use crate::map::{TilemapCustomData, TilemapTime};
+
+/// The [`TilemapTime`] and [`TilemapCustomData`] of the tilemaps which have either, keyed by the
+/// render world tilemap. They change from frame to frame, so unlike the other tilemap components
+/// they are extracted every frame.
+#[derive(Resource, Default)]
+pub(crate) struct ExtractedTilemapShaderData(pub HashMap<Entity, TilemapShaderData>);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TilemapShaderData {
+    /// The time of the tilemap, or `None` to follow the global time.
+    pub time: Option<f32>,
+    pub custom_data: [Vec4; 4],
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn extract_tilemap_shader_data(
+    mut extracted: ResMut<ExtractedTilemapShaderData>,
+    tilemaps: Extract<
+        Query<
+            (
+                &RenderEntity,
+                Option<&TilemapTime>,
+                Option<&TilemapCustomData>,
+            ),
+            Or<(With<TilemapTime>, With<TilemapCustomData>)>,
+        >,
+    >,
+) {
+    extracted.0.clear();
+    for (render_entity, time, custom_data) in tilemaps.iter() {
+        extracted.0.insert(
+            render_entity.id(),
+            TilemapShaderData {
+                time: time.map(TilemapTime::elapsed_secs),
+                custom_data: custom_data.map_or([Vec4::ZERO; 4], |custom_data| custom_data.0),
+            },
+        );
+    }
+}
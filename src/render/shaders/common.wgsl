@@ -15,15 +15,50 @@ struct TilemapData {
     spacing: vec2<f32>,
     chunk_pos: vec2<f32>,
     map_size: vec2<f32>,
+    // The blend mode of each overlay layer: 0 = normal, 1 = multiply, 2 = additive.
+    layer_blend_modes: vec4<u32>,
+    // The grid distortion, as (amplitude.x, amplitude.y, frequency, unused).
+    distortion: vec4<f32>,
+    distortion_seed: u32,
+    // How much of the color graded by `color_grading_lut` is used, 0 to disable grading.
+    color_grading_strength: f32,
+    // The `TilemapColor`, multiplied into every tile's color.
+    color: vec4<f32>,
+    // The `TilemapOcclusionReveal`, as a circle of (center.x, center.y, radius, falloff) and a
+    // region of (min.x, min.y, max.x, max.y), both in world space.
+    occlusion_circle: vec4<f32>,
+    occlusion_region: vec4<f32>,
+    occlusion_revealed_alpha: f32,
+    // The pixels of each tile's edges copied into the gutters of the atlas by
+    // `TilemapTexturePadding`.
+    texture_padding: f32,
+    // The `TilemapTime` of the tilemap in seconds, scaled by its `TilemapTimeScale`, or the global
+    // time for tilemaps without one.
+    time: f32,
+    // The `TilemapCustomData` of the tilemap.
+    custom_data: array<vec4<f32>, 4>,
 };
 @group(1) @binding(1)
 var<uniform> tilemap_data: TilemapData;
 
+// The `TileAnimationTable`. Each animation starts with a texel of (frame count, total duration),
+// followed by a texel of (texture index, end time) for each frame.
+@group(1) @binding(2)
+var animation_table: texture_2d<f32>;
+
 struct VertexInput {
     @builtin(vertex_index) v_index: u32,
     @location(0) uv: vec4<f32>,
     @location(1) position: vec4<f32>,
     @location(2) color: vec4<f32>,
+    @location(3) layers: vec4<f32>,
+    @location(4) custom_data: vec4<f32>,
+    #ifdef TILE_SHAPES
+    // The corner of the `TileShape` of the tile, as a fraction of the quad of the tile.
+    @location(5) shape: vec2<f32>,
+    #endif
+    // Bit 0 is set for visible tiles, and bit 1 for occluders.
+    @location(6) flags: u32,
 }
 
 #ifdef ATLAS
@@ -37,14 +72,169 @@ var sprite_texture: texture_2d_array<f32>;
 @group(2) @binding(1)
 var sprite_sampler: sampler;
 
+// A strip of N slices of NxN texels, indexed by (red, green) within a slice and blue across them.
+@group(2) @binding(2)
+var color_grading_lut: texture_2d<f32>;
+
+// The `TilemapSecondaryTexture`, with the same layout as `sprite_texture`. Transparent black when
+// the tilemap has none.
+#ifdef ATLAS
+@group(2) @binding(3)
+var secondary_texture: texture_2d<f32>;
+#else
+@group(2) @binding(3)
+var secondary_texture: texture_2d_array<f32>;
+#endif
+
 #import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
 
-fn process_fragment(in: MeshVertexOutput) -> vec4<f32> {
-    #ifdef ATLAS
-    let half_texture_pixel_size_u = 0.5 / tilemap_data.texture_size.x;
-    let half_texture_pixel_size_v = 0.5 / tilemap_data.texture_size.y;
-    let half_tile_pixel_size_u = 0.5 / tilemap_data.tile_size.x;
-    let half_tile_pixel_size_v = 0.5 / tilemap_data.tile_size.y;
+// A 2D integer hash, see "Hash Functions for GPU Rendering" (Jarzynski and Olano, 2020).
+fn pcg2d(input: vec2<u32>) -> vec2<u32> {
+    var v = input * 1664525u + 1013904223u;
+    v.x += v.y * 1664525u;
+    v.y += v.x * 1664525u;
+    v = v ^ (v >> vec2<u32>(16u));
+    v.x += v.y * 1664525u;
+    v.y += v.x * 1664525u;
+    v = v ^ (v >> vec2<u32>(16u));
+    return v;
+}
+
+// A random offset in [-1, 1] for a point of the noise lattice.
+fn lattice_offset(point: vec2<i32>) -> vec2<f32> {
+    let seed = tilemap_data.distortion_seed;
+    let hash = pcg2d(bitcast<vec2<u32>>(point) ^ vec2<u32>(seed, seed * 747796405u));
+    return vec2<f32>(hash) / 4294967295.0 * 2.0 - 1.0;
+}
+
+// The distortion of a corner of the tile grid, given in tile coordinates. It is smooth value noise,
+// so corners shared by neighboring tiles are moved together.
+fn grid_distortion(corner: vec2<f32>) -> vec2<f32> {
+    let scaled = corner * tilemap_data.distortion.z;
+    let cell = floor(scaled);
+    let f = scaled - cell;
+    let u = f * f * (3.0 - 2.0 * f);
+    let i = vec2<i32>(cell);
+    let offset = mix(
+        mix(lattice_offset(i), lattice_offset(i + vec2<i32>(1, 0)), u.x),
+        mix(lattice_offset(i + vec2<i32>(0, 1)), lattice_offset(i + vec2<i32>(1, 1)), u.x),
+        u.y
+    );
+    return offset * tilemap_data.distortion.xy;
+}
+
+#ifdef ATLAS
+// Returns the atlas UV of a point in the tile with the given texture index, from its local UV.
+fn atlas_uv(texture_index: i32, local_uv: vec2<f32>) -> vec2<f32> {
+    let index = u32(texture_index);
+    let columns: u32 = u32(round((tilemap_data.texture_size.x - tilemap_data.spacing.x) / (tilemap_data.tile_size.x + tilemap_data.spacing.x)));
+    let sprite_sheet_x: f32 = tilemap_data.spacing.x + f32(index % columns) * (tilemap_data.tile_size.x + tilemap_data.spacing.x);
+    let sprite_sheet_y: f32 = tilemap_data.spacing.y + f32(index / columns) * (tilemap_data.tile_size.y + tilemap_data.spacing.y);
+    return (vec2<f32>(sprite_sheet_x, sprite_sheet_y) + local_uv * tilemap_data.tile_size) / tilemap_data.texture_size;
+}
+#endif
+
+// Blends an overlay layer onto the layers below it.
+fn blend_layer(base: vec4<f32>, layer: vec4<f32>, mode: u32) -> vec4<f32> {
+    switch mode {
+        // Multiply
+        case 1u: {
+            return vec4<f32>(mix(base.rgb, base.rgb * layer.rgb, layer.a), base.a);
+        }
+        // Additive
+        case 2u: {
+            return vec4<f32>(base.rgb + layer.rgb * layer.a, base.a);
+        }
+        // Normal
+        default: {
+            let alpha = layer.a + base.a * (1.0 - layer.a);
+            if (alpha <= 0.0) {
+                return vec4<f32>(0.0);
+            }
+            return vec4<f32>((layer.rgb * layer.a + base.rgb * base.a * (1.0 - layer.a)) / alpha, alpha);
+        }
+    }
+}
+
+// Samples the overlay layers of a tile and blends them onto its base color.
+fn blend_layers(base: vec4<f32>, in: MeshVertexOutput, uv_offset: vec2<f32>) -> vec4<f32> {
+    // The layers are sampled in non-uniform control flow, so the derivatives are taken up front.
+    // All layers cover the same area of the texture as the base tile.
+    let uv_dx = dpdx(in.uv.xy);
+    let uv_dy = dpdy(in.uv.xy);
+
+    var color = base;
+    for (var i = 0; i < 4; i++) {
+        let texture_index = in.layers[i];
+        if (texture_index < 0) {
+            continue;
+        }
+        #ifdef ATLAS
+        let layer = textureSampleGrad(sprite_texture, sprite_sampler, atlas_uv(texture_index, in.uv.zw) + uv_offset, uv_dx, uv_dy);
+        #else
+        let layer = textureSampleGrad(sprite_texture, sprite_sampler, in.uv.xy, texture_index, uv_dx, uv_dy);
+        #endif
+        color = blend_layer(color, layer, tilemap_data.layer_blend_modes[i]);
+    }
+    return color;
+}
+
+fn color_grading_texel(r: u32, g: u32, b: u32, size: u32) -> vec3<f32> {
+    return textureLoad(color_grading_lut, vec2<u32>(b * size + r, g), 0).rgb;
+}
+
+// Grades a color with the lookup table, blending the eight nearest texels. The texels are loaded
+// rather than sampled, so the result does not depend on the sampler of the lookup table image.
+fn apply_color_grading(color: vec4<f32>) -> vec4<f32> {
+    let strength = tilemap_data.color_grading_strength;
+    let dimensions = textureDimensions(color_grading_lut);
+    let size = dimensions.y;
+    // The fallback texture bound until the lookup table is loaded does not match the layout.
+    if (strength <= 0.0 || size < 2u || dimensions.x != size * size) {
+        return color;
+    }
+
+    // The table is indexed by the sRGB encoded color.
+    let encoded = pow(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+    let scaled = encoded * f32(size - 1u);
+    let lo = min(vec3<u32>(floor(scaled)), vec3<u32>(size - 1u));
+    let hi = min(lo + vec3<u32>(1u), vec3<u32>(size - 1u));
+    let t = scaled - vec3<f32>(lo);
+
+    let c00 = mix(
+        color_grading_texel(lo.r, lo.g, lo.b, size),
+        color_grading_texel(hi.r, lo.g, lo.b, size),
+        t.r,
+    );
+    let c10 = mix(
+        color_grading_texel(lo.r, hi.g, lo.b, size),
+        color_grading_texel(hi.r, hi.g, lo.b, size),
+        t.r,
+    );
+    let c01 = mix(
+        color_grading_texel(lo.r, lo.g, hi.b, size),
+        color_grading_texel(hi.r, lo.g, hi.b, size),
+        t.r,
+    );
+    let c11 = mix(
+        color_grading_texel(lo.r, hi.g, hi.b, size),
+        color_grading_texel(hi.r, hi.g, hi.b, size),
+        t.r,
+    );
+    let graded = mix(mix(c00, c10, t.g), mix(c01, c11, t.g), t.b);
+    return vec4<f32>(mix(color.rgb, graded, strength), color.a);
+}
+
+#ifdef ATLAS
+// Returns the offset which keeps the atlas UV of a fragment within its tile.
+fn atlas_uv_offset(in: MeshVertexOutput) -> vec2<f32> {
+    // Samples within half a pixel of the sides of a tile reach into its neighbors, unless the
+    // gutters around it hold copies of its edges.
+    let inset = max(0.5 - tilemap_data.texture_padding, 0.0);
+    let half_texture_pixel_size_u = inset / tilemap_data.texture_size.x;
+    let half_texture_pixel_size_v = inset / tilemap_data.texture_size.y;
+    let half_tile_pixel_size_u = inset / tilemap_data.tile_size.x;
+    let half_tile_pixel_size_v = inset / tilemap_data.tile_size.y;
 
     // Offset the UV 1/2 pixel from the sides of the tile, so that the sampler doesn't bleed onto
     // adjacent tiles at the edges.
@@ -59,17 +249,54 @@ fn process_fragment(in: MeshVertexOutput) -> vec4<f32> {
     } else if (in.uv.w > (1.0 - half_tile_pixel_size_v)) {
         uv_offset.y = - half_texture_pixel_size_v;
     }
+    return uv_offset;
+}
+#endif
 
-    let color = textureSample(sprite_texture, sprite_sampler, in.uv.xy + uv_offset) * in.color;
-    if (color.a < 0.001) {
-        discard;
-    }
-    return color;
+// Samples the `TilemapSecondaryTexture` at the same point as the base tile.
+fn sample_secondary_texture(in: MeshVertexOutput) -> vec4<f32> {
+    #ifdef ATLAS
+    return textureSample(secondary_texture, sprite_sampler, in.uv.xy + atlas_uv_offset(in));
     #else
-    let color = textureSample(sprite_texture, sprite_sampler, in.uv.xy, in.tile_id) * in.color;
+    return textureSample(secondary_texture, sprite_sampler, in.uv.xy, in.tile_id);
+    #endif
+}
+
+// The position of the tile on the tilemap.
+fn tile_position(in: MeshVertexOutput) -> vec2<u32> {
+    return vec2<u32>(tilemap_data.chunk_pos) + in.storage_position;
+}
+
+// The texture index the tile shows, after animation.
+fn tile_texture_index(in: MeshVertexOutput) -> i32 {
+    return in.tile_id;
+}
+
+// The UV of the fragment within the texture of its tile, from (0, 0) at the top left to (1, 1) at
+// the bottom right.
+fn tile_uv(in: MeshVertexOutput) -> vec2<f32> {
+    return in.uv.zw;
+}
+
+// The color of the tile at the fragment: its texture with the overlay layers blended on,
+// multiplied by its color and graded. Unlike `process_fragment`, it does not discard transparent
+// fragments.
+fn tile_color(in: MeshVertexOutput) -> vec4<f32> {
+    #ifdef ATLAS
+    let uv_offset = atlas_uv_offset(in);
+    let base = textureSample(sprite_texture, sprite_sampler, in.uv.xy + uv_offset);
+    #else
+    let uv_offset = vec2<f32>(0.0);
+    let base = textureSample(sprite_texture, sprite_sampler, in.uv.xy, in.tile_id);
+    #endif
+    return apply_color_grading(blend_layers(base, in, uv_offset) * in.color);
+}
+
+// The fragment of the built-in fragment shader.
+fn process_fragment(in: MeshVertexOutput) -> vec4<f32> {
+    let color = tile_color(in);
     if (color.a < 0.001) {
         discard;
     }
     return color;
-    #endif
-}
\ No newline at end of file
+}
@@ -1,7 +1,21 @@
 #import bevy_ecs_tilemap::common::process_fragment
 #import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
 
+#ifdef TILEMAP_EMISSIVE
+#import bevy_ecs_tilemap::common::sample_secondary_texture
+
+// The `emissive` color of the `StandardTilemapMaterial`.
+@group(3) @binding(0)
+var<uniform> emissive: vec4<f32>;
+#endif
+
 @fragment
 fn fragment(in: MeshVertexOutput) -> @location(0) vec4<f32> {
+    #ifdef TILEMAP_EMISSIVE
+    let color = process_fragment(in);
+    let emission = sample_secondary_texture(in);
+    return vec4<f32>(color.rgb + emission.rgb * emission.a * emissive.rgb, color.a);
+    #else
     return process_fragment(in);
+    #endif
 }
\ No newline at end of file
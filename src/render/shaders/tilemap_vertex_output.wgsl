@@ -1,9 +1,19 @@
 #define_import_path bevy_ecs_tilemap::vertex_output
 
+// What the vertex shader of a tilemap passes to its fragment shader.
 struct MeshVertexOutput {
     @builtin(position) position: vec4<f32>,
+    // The UV in the tilemap texture in x and y, and the UV within the tile in z and w.
     @location(0) uv: vec4<f32>,
+    // The `TileColor` of the tile, multiplied by the `TilemapColor`.
     @location(1) color: vec4<f32>,
+    // The texture index the tile shows, after animation.
     @location(2) @interpolate(flat) tile_id: i32,
+    // The position of the tile within its chunk. See `common::tile_position` for the position on
+    // the tilemap.
     @location(3) storage_position: vec2<u32>,
+    // The texture indices of the overlay layers, or -1 for layers without a texture.
+    @location(4) @interpolate(flat) layers: vec4<i32>,
+    // The `TileCustomData` of the tile.
+    @location(5) @interpolate(flat) custom_data: vec4<f32>,
 }
@@ -0,0 +1,242 @@
+#define_import_path bevy_ecs_tilemap::vertex
+
+#import bevy_ecs_tilemap::common::{VertexInput, tilemap_data, mesh, grid_distortion, animation_table}
+#import bevy_ecs_tilemap::mesh_output::MeshOutput
+#import bevy_sprite::mesh2d_view_bindings::view
+#import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
+
+#ifdef SQUARE
+    #import bevy_ecs_tilemap::square::get_mesh
+#endif
+
+#ifdef ISO_DIAMOND
+    #import bevy_ecs_tilemap::diamond_iso::get_mesh
+#endif
+
+#ifdef ISO_STAGGERED
+    #import bevy_ecs_tilemap::staggered_iso::get_mesh
+#endif
+
+#ifdef COLUMN_EVEN_HEX
+    #import bevy_ecs_tilemap::column_even_hex::get_mesh
+#endif
+
+#ifdef COLUMN_HEX
+    #import bevy_ecs_tilemap::column_hex::get_mesh
+#endif
+
+#ifdef COLUMN_ODD_HEX
+    #import bevy_ecs_tilemap::column_odd_hex::get_mesh
+#endif
+
+#ifdef ROW_EVEN_HEX
+    #import bevy_ecs_tilemap::row_even_hex::get_mesh
+#endif
+
+#ifdef ROW_HEX
+    #import bevy_ecs_tilemap::row_hex::get_mesh
+#endif
+
+#ifdef ROW_ODD_HEX
+    #import bevy_ecs_tilemap::row_odd_hex::get_mesh
+#endif
+
+fn animation_table_texel(index: u32) -> vec2<f32> {
+    let width = textureDimensions(animation_table).x;
+    return textureLoad(animation_table, vec2<u32>(index % width, index / width), 0).xy;
+}
+
+// The alpha of an occluder at the given world position, faded out by the `TilemapOcclusionReveal`.
+fn occluder_alpha(world_position: vec2<f32>) -> f32 {
+    let circle = tilemap_data.occlusion_circle;
+    let distance = length(world_position - circle.xy);
+    var reveal = clamp((circle.z + circle.w - distance) / max(circle.w, 1e-4), 0.0, 1.0);
+    let region = tilemap_data.occlusion_region;
+    if (all(world_position >= region.xy) && all(world_position <= region.zw)) {
+        reveal = 1.0;
+    }
+    return mix(1.0, tilemap_data.occlusion_revealed_alpha, reveal);
+}
+
+// Places a vertex of a tile and fills in what the fragment shader needs, as the built-in vertex
+// shader does. Custom vertex shaders can call it and change its output, e.g. to move tiles.
+fn process_vertex(vertex_input: VertexInput) -> MeshVertexOutput {
+    var out: MeshVertexOutput;
+    let animation_speed = vertex_input.position.z;
+    // The `AnimationPhase` of the tile, as a fraction of its loop.
+    let animation_phase = vertex_input.position.w;
+
+    let tile_position = vec3(vertex_input.position.xy, 0.0);
+    var mesh_data: MeshOutput = get_mesh(vertex_input.v_index, tile_position);
+    var full_corners = array<vec2<f32>, 4>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 0.0)
+    );
+    #ifdef TILE_SHAPES
+    // The `TileShape` places the vertex within the quad of the tile. The corners of full tiles
+    // are left as they are, so they match the ones of their neighbors exactly.
+    let shape = vertex_input.shape;
+    if (any(shape != full_corners[vertex_input.v_index % 4u])) {
+        let bottom_left = get_mesh(0u, tile_position).world_position;
+        mesh_data.world_position = bottom_left
+            + (get_mesh(3u, tile_position).world_position - bottom_left) * shape.x
+            + (get_mesh(1u, tile_position).world_position - bottom_left) * shape.y;
+    }
+    #else
+    let shape = full_corners[vertex_input.v_index % 4u];
+    #endif
+    // The center of the tile, halfway between its bottom left and top right corners.
+    let center = 0.5 * (get_mesh(0u, tile_position).world_position + get_mesh(2u, tile_position).world_position);
+
+    // A `TileRotation::Angle`, stored as the fraction of a turn next to the flip bits, rotates
+    // the tile around its center.
+    let turn = fract(vertex_input.uv.y);
+    if (turn > 0.0) {
+        let angle = turn * 6.283185307;
+        let offset = mesh_data.world_position.xy - center.xy;
+        let rotated = vec2<f32>(
+            offset.x * cos(angle) - offset.y * sin(angle),
+            offset.x * sin(angle) + offset.y * cos(angle)
+        );
+        mesh_data.world_position = vec4<f32>(center.xy + rotated, mesh_data.world_position.zw);
+    }
+
+    #ifdef TILEMAP_DEPTH_PER_TILE
+    // The depth of `TilemapRenderMode::y_sort_z` at the center of the tile, for
+    // `TilemapDepthMode::PerTile`. `get_mesh` moves the corners by the transform of the chunk, so
+    // like the translation `y_sort_z` takes, the center is in world space.
+    mesh_data.world_position.z = center.z + 1.0 - center.y / tilemap_data.map_size.y;
+    #endif
+
+    if (any(tilemap_data.distortion.xy != vec2<f32>(0.0))) {
+        // The point of the vertex on the grid, which is a corner of the grid for the corners of
+        // full tiles. Picking is unaffected, as it uses the undistorted grid.
+        let corner = tilemap_data.chunk_pos + vertex_input.position.xy + shape;
+        mesh_data.world_position += mesh.model * vec4<f32>(grid_distortion(corner), 0.0, 0.0);
+    }
+
+    var texture_index: u32;
+    // How many frames a range animation is past its start, by which its overlay layers advance.
+    var layer_frame_offset = 0;
+    if (vertex_input.uv.w < 0.0) {
+        // An animation of the `TileAnimationTable`, starting at the texel in `uv.z`.
+        let start = u32(vertex_input.uv.z);
+        let header = animation_table_texel(start);
+        let duration = header.y;
+        let time = fract(tilemap_data.time * animation_speed / duration + animation_phase) * duration;
+        texture_index = u32(animation_table_texel(start + 1u).x);
+        for (var frame = 1u; frame <= u32(header.x); frame++) {
+            let texel = animation_table_texel(start + frame);
+            texture_index = u32(texel.x);
+            if (time < texel.y) {
+                break;
+            }
+        }
+    } else {
+        let frames: f32 = f32(vertex_input.uv.w - vertex_input.uv.z);
+
+        var current_animation_frame = fract(tilemap_data.time * animation_speed + animation_phase) * frames;
+
+        current_animation_frame = clamp(f32(vertex_input.uv.z) + current_animation_frame, f32(vertex_input.uv.z), f32(vertex_input.uv.w));
+
+        texture_index = u32(current_animation_frame);
+        layer_frame_offset = i32(texture_index) - i32(vertex_input.uv.z);
+    }
+
+    #ifdef ATLAS
+    // Get the top-left corner of the current frame in the texture, accounting for padding around the whole texture
+    // as well as spacing between the tiles.
+    let columns: u32 = u32(round((tilemap_data.texture_size.x - tilemap_data.spacing.x) / (tilemap_data.tile_size.x + tilemap_data.spacing.x)));
+    let sprite_sheet_x: f32 = tilemap_data.spacing.x + floor(f32(texture_index % columns)) * (tilemap_data.tile_size.x + tilemap_data.spacing.x);
+    let sprite_sheet_y: f32 = tilemap_data.spacing.y + floor(f32(texture_index / columns)) * (tilemap_data.tile_size.y + tilemap_data.spacing.y);
+
+    let start_u: f32 = sprite_sheet_x / tilemap_data.texture_size.x;
+    let end_u: f32 = (sprite_sheet_x + tilemap_data.tile_size.x) / tilemap_data.texture_size.x;
+    let start_v: f32 = sprite_sheet_y / tilemap_data.texture_size.y;
+    let end_v: f32 = (sprite_sheet_y + tilemap_data.tile_size.y) / tilemap_data.texture_size.y;
+    #else
+    let start_u: f32 = 0.0;
+    let end_u: f32 = 1.0;
+    let start_v: f32 = 0.0;
+    let end_v: f32 = 1.0;
+    #endif
+
+    var atlas_uvs: array<vec4<f32>, 4>;
+
+    var x1: array<vec4<f32>, 8> = array<vec4<f32>, 8>(
+        // The x and y are the texture UV, and the z and w and the local tile UV
+        vec4<f32>(start_u, end_v, 0.0, 1.0),       // no flip/rotation
+        vec4<f32>(end_u, end_v, 1.0, 1.0),         // flip x
+        vec4<f32>(start_u, start_v, 0.0, 0.0),     // flip y
+        vec4<f32>(end_u, start_v, 1.0, 0.0),       // flip x y
+        vec4<f32>(end_u, start_v, 1.0, 0.0),       // flip     d
+        vec4<f32>(end_u, end_v, 1.0, 1.0),         // flip x   d
+        vec4<f32>(start_u, start_v, 0.0, 0.0),     // flip y   d
+        vec4<f32>(start_u, end_v, 0.0, 1.0)
+    );
+
+    var x2: array<vec4<f32>, 8> = array<vec4<f32>, 8>(
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+        vec4<f32>(end_u, start_v, 1.0, 0.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(end_u, end_v, 1.0, 1.0),
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(end_u, start_v, 1.0, 0.0),
+        vec4<f32>(end_u, end_v, 1.0, 1.0)
+    );
+
+    var x3: array<vec4<f32>, 8> = array<vec4<f32>, 8>(
+        vec4<f32>(end_u, start_v, 1.0, 0.0),
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+        vec4<f32>(end_u, end_v, 1.0, 1.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+        vec4<f32>(end_u, end_v, 1.0, 1.0),
+        vec4<f32>(end_u, start_v, 1.0, 0.0)
+    );
+
+    var x4: array<vec4<f32>, 8> = array<vec4<f32>, 8>(
+        vec4<f32>(end_u, end_v, 1.0, 1.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(end_u, start_v, 1.0, 0.0),
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+        vec4<f32>(end_u, end_v, 1.0, 1.0),
+        vec4<f32>(end_u, start_v, 1.0, 0.0),
+        vec4<f32>(start_u, end_v, 0.0, 1.0),
+        vec4<f32>(start_u, start_v, 0.0, 0.0),
+    );
+
+    atlas_uvs = array<vec4<f32>, 4>(
+        x1[u32(vertex_input.uv.y)],
+        x2[u32(vertex_input.uv.y)],
+        x3[u32(vertex_input.uv.y)],
+        x4[u32(vertex_input.uv.y)]
+    );
+
+    // The UV at the point of the `TileShape`, between the UVs of the corners of the quad.
+    out.uv = mix(
+        mix(atlas_uvs[0], atlas_uvs[3], shape.x),
+        mix(atlas_uvs[1], atlas_uvs[2], shape.x),
+        shape.y
+    );
+    out.tile_id = i32(texture_index);
+    // out.uv = out.uv + 1e-5;
+    out.position = view.clip_from_world * mesh_data.world_position;
+    out.color = vertex_input.color * tilemap_data.color;
+    out.storage_position = vec2<u32>(vertex_input.position.xy);
+    let layers = vec4<i32>(vertex_input.layers);
+    out.layers = select(layers, layers + layer_frame_offset, layers >= vec4<i32>(0));
+    out.custom_data = vertex_input.custom_data;
+    if ((vertex_input.flags & 2u) != 0u) {
+        out.color.a *= occluder_alpha(mesh_data.world_position.xy);
+    }
+    if ((vertex_input.flags & 1u) == 0u) {
+        // Collapse hidden tiles to a point outside of the view, so nothing is rasterized.
+        out.position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+    }
+    return out;
+}
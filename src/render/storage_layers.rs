@@ -0,0 +1,25 @@
+use bevy::{
+    platform::collections::HashMap,
+    prelude::{Entity, Query, ResMut, Resource},
+    render::{Extract, sync_world::RenderEntity},
+};
+
+use crate::tiles::LayeredTileStorage;
+
+/// The Z offsets of the layers of the tilemaps with a [`LayeredTileStorage`], keyed by the render
+/// world tilemap. There are only a few per tilemap, so they are extracted every frame rather than
+/// tracking which of them changed.
+#[derive(Resource, Default)]
+pub(crate) struct ExtractedLayerZOffsets(pub HashMap<Entity, Vec<f32>>);
+
+pub(crate) fn extract_layer_z_offsets(
+    mut extracted: ResMut<ExtractedLayerZOffsets>,
+    tilemaps: Extract<Query<(&RenderEntity, &LayeredTileStorage)>>,
+) {
+    extracted.0.clear();
+    for (render_entity, storage) in tilemaps.iter() {
+        extracted
+            .0
+            .insert(render_entity.id(), storage.z_offsets().to_vec());
+    }
+}
@@ -19,6 +19,7 @@ use bevy::{
 };
 
 use super::ModifiedImageIds;
+use super::mipmap::{MipmapGenerator, can_generate_mipmaps, mip_level_count};
 
 #[derive(Resource, Default, Debug, Clone)]
 pub struct TextureArrayCache {
@@ -37,6 +38,7 @@ pub struct TextureArrayCache {
     prepare_queue: HashSet<TilemapTexture>,
     queue_queue: HashSet<TilemapTexture>,
     bad_flag_queue: HashSet<TilemapTexture>,
+    mipmap_generator: Option<MipmapGenerator>,
 }
 
 impl TextureArrayCache {
@@ -135,6 +137,14 @@ impl TextureArrayCache {
         self.textures.contains_key(texture)
     }
 
+    /// Returns the number of layers of all prepared texture arrays.
+    pub fn array_layers(&self) -> u32 {
+        self.textures
+            .values()
+            .map(|gpu_image| gpu_image.texture.depth_or_array_layers())
+            .sum()
+    }
+
     /// Prepares each texture array texture
     pub fn prepare(
         &mut self,
@@ -163,6 +173,16 @@ impl TextureArrayCache {
                         *count
                     };
 
+                    // Zoomed out tilemaps alias badly without mipmaps, so the mip chain is filled
+                    // in `queue`, either from the mips of the source images or by rendering it.
+                    let mut usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+                    let mip_level_count = if can_generate_mipmaps(render_device, *format) {
+                        usage |= TextureUsages::RENDER_ATTACHMENT;
+                        mip_level_count(tile_size.x as u32, tile_size.y as u32)
+                    } else {
+                        1
+                    };
+
                     let gpu_texture = render_device.create_texture(&TextureDescriptor {
                         label: Some("texture_array"),
                         size: Extent3d {
@@ -170,11 +190,11 @@ impl TextureArrayCache {
                             height: tile_size.y as u32,
                             depth_or_array_layers: count,
                         },
-                        mip_level_count: 1,
+                        mip_level_count,
                         sample_count: 1,
                         dimension: TextureDimension::D2,
                         format: *format,
-                        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                        usage,
                         view_formats: &[],
                     });
 
@@ -294,6 +314,19 @@ impl TextureArrayCache {
                         );
                     }
 
+                    // Tiles in an atlas generally do not line up with the texels of its smaller
+                    // mip levels, so the mip chain is always generated.
+                    if array_gpu_image.mip_level_count > 1 {
+                        self.mipmap_generator
+                            .get_or_insert_with(|| MipmapGenerator::new(render_device))
+                            .generate(
+                                render_device,
+                                &mut command_encoder,
+                                &array_gpu_image.texture,
+                                count,
+                            );
+                    }
+
                     let command_buffer = command_encoder.finish();
                     render_queue.submit(vec![command_buffer]);
                 }
@@ -317,26 +350,47 @@ impl TextureArrayCache {
                             label: Some("create_texture_array_from_handles_vec"),
                         });
 
+                    // Copy the mip chains of the source images if all of them have one, otherwise
+                    // only copy the base level and generate the rest.
+                    let mip_level_count = array_gpu_image.mip_level_count;
+                    let copy_mips = gpu_images
+                        .iter()
+                        .all(|gpu_image| gpu_image.mip_level_count >= mip_level_count);
+                    let copied_mip_levels = if copy_mips { mip_level_count } else { 1 };
+
                     for i in 0..count {
-                        command_encoder.copy_texture_to_texture(
-                            TexelCopyTextureInfo {
-                                texture: &gpu_images[i as usize].texture,
-                                mip_level: 0,
-                                origin: Origin3d { x: 0, y: 0, z: 0 },
-                                aspect: TextureAspect::All,
-                            },
-                            TexelCopyTextureInfo {
-                                texture: &array_gpu_image.texture,
-                                mip_level: 0,
-                                origin: Origin3d { x: 0, y: 0, z: i },
-                                aspect: TextureAspect::All,
-                            },
-                            Extent3d {
-                                width: tile_size.x as u32,
-                                height: tile_size.y as u32,
-                                depth_or_array_layers: 1,
-                            },
-                        );
+                        for mip_level in 0..copied_mip_levels {
+                            command_encoder.copy_texture_to_texture(
+                                TexelCopyTextureInfo {
+                                    texture: &gpu_images[i as usize].texture,
+                                    mip_level,
+                                    origin: Origin3d { x: 0, y: 0, z: 0 },
+                                    aspect: TextureAspect::All,
+                                },
+                                TexelCopyTextureInfo {
+                                    texture: &array_gpu_image.texture,
+                                    mip_level,
+                                    origin: Origin3d { x: 0, y: 0, z: i },
+                                    aspect: TextureAspect::All,
+                                },
+                                Extent3d {
+                                    width: (tile_size.x as u32 >> mip_level).max(1),
+                                    height: (tile_size.y as u32 >> mip_level).max(1),
+                                    depth_or_array_layers: 1,
+                                },
+                            );
+                        }
+                    }
+
+                    if copied_mip_levels < mip_level_count {
+                        self.mipmap_generator
+                            .get_or_insert_with(|| MipmapGenerator::new(render_device))
+                            .generate(
+                                render_device,
+                                &mut command_encoder,
+                                &array_gpu_image.texture,
+                                count,
+                            );
                     }
 
                     let command_buffer = command_encoder.finish();
@@ -0,0 +1,1360 @@
+//! Saves whole tilemaps, and spawns them again from their saves.
+//!
+//! A [`TilemapSave`] holds the settings of a tilemap, the paths of its textures and all of its
+//! tiles. Besides the components every tile has, the reflected components of the tiles are saved
+//! too, if their type is registered with `ReflectComponent`. Saves are written in a compact binary
+//! format with [`TilemapSave::to_bytes`], where runs of identical tiles take up the space of one,
+//! or in any serde format such as RON.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::save::TilemapSave;
+//! fn save_and_reload(world: &mut World, tilemap: Entity) -> Option<Entity> {
+//!     let bytes = TilemapSave::capture(world, tilemap)?.to_bytes();
+//!     world.entity_mut(tilemap).despawn();
+//!     let save = TilemapSave::from_bytes(&bytes).ok()?;
+//!     Some(save.spawn(world))
+//! }
+//! ```
+//!
+//! Spawned tiles are new entities: the [`TileStorage`] and [`TilemapId`]s of the spawned tilemap
+//! point at them. Maps with more than [`MAX_LOADED_TILES`] positions are spawned with a
+//! [`SparseTileStorage`] instead, which only takes up memory for the saved tiles, and tilemaps
+//! saved from a [`LayeredTileStorage`] are spawned with one, holding the same layers.
+//!
+//! Saved components which are registered with `ReflectMapEntities` are pointed at the spawned
+//! entities too, when they hold the saved tilemap or one of its tiles. Other entities in them are
+//! kept as they were.
+
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::asset::ron;
+use bevy::ecs::entity::{EntityHashMap, EntityMapper};
+use bevy::ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities};
+use bevy::log::warn;
+use bevy::math::Vec2;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use serde::de::DeserializeSeed;
+
+use crate::anchor::TilemapAnchor;
+use crate::map::{
+    HexCoordSystem, IsoCoordSystem, TilemapGridSize, TilemapId, TilemapSize, TilemapSpacing,
+    TilemapTexture, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{
+    LayeredTileStorage, SparseTileStorage, TileBundle, TileColor, TileFlip, TilePos, TilePosOld,
+    TileStorage, TileStore, TileTextureIndex, TileVisible, tile_store,
+};
+
+const MAGIC: &[u8; 4] = b"BETM";
+const VERSION: u8 = 1;
+
+/// The most tiles [`TilemapSave::from_bytes`] reads, which fill a map of 4096 by 4096 tiles.
+pub const MAX_LOADED_TILES: u64 = 1 << 24;
+
+/// The textures of a saved tilemap, as asset paths. Textures without a path are saved as empty
+/// paths, and spawned with a default handle.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SavedTexture {
+    Single(String),
+    #[cfg(not(feature = "atlas"))]
+    Vector(Vec<String>),
+    #[cfg(not(feature = "atlas"))]
+    TextureContainer(String),
+}
+
+impl Default for SavedTexture {
+    fn default() -> Self {
+        SavedTexture::Single(String::new())
+    }
+}
+
+impl SavedTexture {
+    fn from_texture(texture: &TilemapTexture) -> Self {
+        let path = |handle: &Handle<Image>| {
+            handle
+                .path()
+                .map(|path| path.to_string())
+                .unwrap_or_default()
+        };
+        match texture {
+            TilemapTexture::Single(handle) => SavedTexture::Single(path(handle)),
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::Vector(handles) => {
+                SavedTexture::Vector(handles.iter().map(path).collect())
+            }
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::TextureContainer(handle) => {
+                SavedTexture::TextureContainer(path(handle))
+            }
+        }
+    }
+
+    fn to_texture(&self, asset_server: Option<&AssetServer>) -> TilemapTexture {
+        let load = |path: &String| match asset_server {
+            Some(asset_server) if !path.is_empty() => asset_server.load(path.clone()),
+            _ => Handle::default(),
+        };
+        match self {
+            SavedTexture::Single(path) => TilemapTexture::Single(load(path)),
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::Vector(paths) => TilemapTexture::Vector(paths.iter().map(load).collect()),
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::TextureContainer(path) => TilemapTexture::TextureContainer(load(path)),
+        }
+    }
+
+    fn paths(&self) -> Vec<&String> {
+        match self {
+            SavedTexture::Single(path) => vec![path],
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::Vector(paths) => paths.iter().collect(),
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::TextureContainer(path) => vec![path],
+        }
+    }
+}
+
+/// A reflected component of a saved tile, serialized as RON.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SavedComponent {
+    /// The index of the component's type in [`TilemapSave::component_types`].
+    pub type_index: u32,
+    pub ron: String,
+}
+
+/// A saved tile.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SavedTile {
+    pub position: TilePos,
+    /// The layer of the [`LayeredTileStorage`] the tile was saved from, or `0`.
+    #[serde(default)]
+    pub layer: u32,
+    pub texture_index: TileTextureIndex,
+    pub visible: TileVisible,
+    pub flip: TileFlip,
+    pub color: TileColor,
+    pub components: Vec<SavedComponent>,
+}
+
+/// A saved tilemap, made by [`TilemapSave::capture`] and spawned by [`TilemapSave::spawn`].
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TilemapSave {
+    pub size: TilemapSize,
+    pub map_type: TilemapType,
+    pub grid_size: TilemapGridSize,
+    pub tile_size: TilemapTileSize,
+    pub spacing: TilemapSpacing,
+    pub anchor: TilemapAnchor,
+    pub texture: SavedTexture,
+    /// The type paths of the reflected components of the tiles.
+    pub component_types: Vec<String>,
+    /// The Z offsets of the layers of a tilemap saved from a [`LayeredTileStorage`], from the
+    /// bottom up. Empty for other tilemaps, whose tiles are all in layer `0`.
+    #[serde(default)]
+    pub layer_z_offsets: Vec<f32>,
+    /// The tiles, ordered by layer and then by row. Positions without a tile are left out.
+    pub tiles: Vec<SavedTile>,
+    /// The tilemap the save was captured from, which saved components may hold.
+    #[serde(default)]
+    pub source_tilemap: Option<Entity>,
+    /// The tiles held by saved components, with the position and layer they were saved at.
+    #[serde(default)]
+    pub referenced_tiles: Vec<(TilePos, u32, Entity)>,
+}
+
+/// Records the entities held by a component, leaving it unchanged.
+#[derive(Default)]
+struct EntityCollector(Vec<Entity>);
+
+impl EntityMapper for EntityCollector {
+    fn get_mapped(&mut self, source: Entity) -> Entity {
+        self.0.push(source);
+        source
+    }
+
+    fn set_mapped(&mut self, _source: Entity, _target: Entity) {}
+}
+
+/// Components which every saved tile has, or which only make sense for the spawned entity.
+fn is_builtin(type_id: TypeId) -> bool {
+    [
+        TypeId::of::<TilePos>(),
+        TypeId::of::<TilePosOld>(),
+        TypeId::of::<TilemapId>(),
+        TypeId::of::<TileTextureIndex>(),
+        TypeId::of::<TileVisible>(),
+        TypeId::of::<TileFlip>(),
+        TypeId::of::<TileColor>(),
+        TypeId::of::<ChildOf>(),
+    ]
+    .contains(&type_id)
+}
+
+impl TilemapSave {
+    /// Saves a tilemap and its tiles.
+    ///
+    /// The tiles are taken from the storage found by [`tile_store`], or from every layer of the
+    /// [`LayeredTileStorage`] of the tilemap, along with the Z offsets of the layers. Returns
+    /// `None` if `tilemap` has no tile storage or is missing one of the settings of a tilemap.
+    pub fn capture(world: &World, tilemap: Entity) -> Option<Self> {
+        let entity = world.get_entity(tilemap).ok()?;
+        let mut save = TilemapSave {
+            size: *entity.get::<TilemapSize>()?,
+            map_type: *entity.get::<TilemapType>()?,
+            grid_size: *entity.get::<TilemapGridSize>()?,
+            tile_size: *entity.get::<TilemapTileSize>()?,
+            spacing: entity.get::<TilemapSpacing>().copied().unwrap_or_default(),
+            anchor: entity.get::<TilemapAnchor>().copied().unwrap_or_default(),
+            texture: entity
+                .get::<TilemapTexture>()
+                .map(SavedTexture::from_texture)
+                .unwrap_or_default(),
+            component_types: Vec::new(),
+            layer_z_offsets: Vec::new(),
+            tiles: Vec::new(),
+            source_tilemap: Some(tilemap),
+            referenced_tiles: Vec::new(),
+        };
+
+        let registry = world.get_resource::<AppTypeRegistry>().map(|r| r.read());
+        let mut tiles = match entity.get::<LayeredTileStorage>() {
+            Some(layered) => {
+                save.layer_z_offsets = layered.z_offsets().to_vec();
+                layered
+                    .iter_some()
+                    .map(|(layer, position, tile_entity)| (layer as u32, position, tile_entity))
+                    .collect::<Vec<_>>()
+            }
+            None => tile_store(world, tilemap)?
+                .iter_some()
+                .map(|(position, tile_entity)| (0, position, tile_entity))
+                .collect(),
+        };
+        // Saves list the tiles row by row, but sparse storages iterate in no particular order.
+        tiles.sort_unstable_by_key(|(layer, position, _)| (*layer, position.y, position.x));
+        for (layer, position, tile_entity) in tiles {
+            let Ok(tile) = world.get_entity(tile_entity) else {
+                continue;
+            };
+            let components = match &registry {
+                Some(registry) => save.capture_components(world, tile, registry),
+                None => Vec::new(),
+            };
+            save.tiles.push(SavedTile {
+                position,
+                layer,
+                texture_index: tile.get().copied().unwrap_or_default(),
+                visible: tile.get().copied().unwrap_or_default(),
+                flip: tile.get().copied().unwrap_or_default(),
+                color: tile.get().copied().unwrap_or_default(),
+                components,
+            });
+        }
+        Some(save)
+    }
+
+    fn capture_components(
+        &mut self,
+        world: &World,
+        tile: EntityRef,
+        registry: &TypeRegistry,
+    ) -> Vec<SavedComponent> {
+        let mut components = Vec::new();
+        for component_id in tile.archetype().components() {
+            let Some(type_id) = world
+                .components()
+                .get_info(*component_id)
+                .and_then(|info| info.type_id())
+            else {
+                continue;
+            };
+            if is_builtin(type_id) {
+                continue;
+            }
+            let Some(registration) = registry.get(type_id) else {
+                continue;
+            };
+            let Some(value) = registration
+                .data::<ReflectComponent>()
+                .and_then(|reflect_component| reflect_component.reflect(tile))
+            else {
+                continue;
+            };
+            if let Some(map_entities) = registration.data::<ReflectMapEntities>() {
+                self.collect_referenced_tiles(world, value.as_partial_reflect(), map_entities);
+            }
+            let serializer = TypedReflectSerializer::new(value.as_partial_reflect(), registry);
+            let ron = match ron::to_string(&serializer) {
+                Ok(ron) => ron,
+                Err(err) => {
+                    warn!(
+                        "Skipping component {} of a saved tile: {err}",
+                        registration.type_info().type_path()
+                    );
+                    continue;
+                }
+            };
+            let type_path = registration.type_info().type_path();
+            let type_index = match self.component_types.iter().position(|t| t == type_path) {
+                Some(index) => index,
+                None => {
+                    self.component_types.push(type_path.to_string());
+                    self.component_types.len() - 1
+                }
+            };
+            components.push(SavedComponent {
+                type_index: type_index as u32,
+                ron,
+            });
+        }
+        components
+    }
+
+    /// Adds the tiles of the saved tilemap which `value` holds to the
+    /// [`referenced_tiles`](Self::referenced_tiles).
+    fn collect_referenced_tiles(
+        &mut self,
+        world: &World,
+        value: &dyn PartialReflect,
+        map_entities: &ReflectMapEntities,
+    ) {
+        let mut collector = EntityCollector::default();
+        map_entities.map_entities(value.to_dynamic().as_mut(), &mut collector);
+        for entity in collector.0 {
+            let Ok(tile) = world.get_entity(entity) else {
+                continue;
+            };
+            let (Some(tilemap_id), Some(position)) =
+                (tile.get::<TilemapId>(), tile.get::<TilePos>())
+            else {
+                continue;
+            };
+            if Some(tilemap_id.0) != self.source_tilemap {
+                continue;
+            }
+            let layer = world
+                .get::<LayeredTileStorage>(tilemap_id.0)
+                .and_then(|layered| {
+                    (0..layered.layer_count())
+                        .find(|layer| layered.checked_get(position, *layer) == Some(entity))
+                })
+                .unwrap_or(0) as u32;
+            if !self.referenced_tiles.contains(&(*position, layer, entity)) {
+                self.referenced_tiles.push((*position, layer, entity));
+            }
+        }
+    }
+
+    /// Spawns the saved tilemap and its tiles, which are children of the tilemap, and returns the
+    /// tilemap.
+    ///
+    /// Textures are loaded through the `AssetServer`, if there is one. Components whose type is
+    /// not registered, or whose RON can not be read, are left out with a warning.
+    ///
+    /// The tiles are kept in a [`TileStorage`], unless the map has more than
+    /// [`MAX_LOADED_TILES`] positions: then they are kept in a [`SparseTileStorage`], next to an
+    /// empty [`TileStorage`], so that a huge map with few tiles does not fill the memory. Saves
+    /// with [`layer_z_offsets`](Self::layer_z_offsets) are spawned with a
+    /// [`LayeredTileStorage`] instead, and tiles in layers it does not have are left out.
+    pub fn spawn(&self, world: &mut World) -> Entity {
+        let texture = self.texture.to_texture(world.get_resource::<AssetServer>());
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = SpawnedStorage::new(self);
+        for tile in &self.tiles {
+            let tile_entity = world
+                .spawn((
+                    TileBundle {
+                        position: tile.position,
+                        texture_index: tile.texture_index,
+                        tilemap_id: TilemapId(tilemap),
+                        visible: tile.visible,
+                        flip: tile.flip,
+                        color: tile.color,
+                        old_position: TilePosOld(tile.position),
+                        ..Default::default()
+                    },
+                    ChildOf(tilemap),
+                ))
+                .id();
+            tile_storage.checked_set(&tile.position, tile.layer, tile_entity);
+        }
+
+        // Components are inserted once every tile is spawned, so they can be pointed at them.
+        let mut entity_map = EntityHashMap::default();
+        if let Some(source_tilemap) = self.source_tilemap {
+            entity_map.insert(source_tilemap, tilemap);
+        }
+        for (position, layer, entity) in &self.referenced_tiles {
+            if let Some(tile_entity) = tile_storage.checked_get(position, *layer) {
+                entity_map.insert(*entity, tile_entity);
+            }
+        }
+        for tile in self.tiles.iter().filter(|tile| !tile.components.is_empty()) {
+            if let Some(tile_entity) = tile_storage.checked_get(&tile.position, tile.layer) {
+                self.spawn_components(world, tile_entity, &tile.components, &mut entity_map);
+            }
+        }
+
+        let tile_storage = tile_storage.insert_into(&mut world.entity_mut(tilemap));
+        #[cfg(feature = "render")]
+        world.entity_mut(tilemap).insert(crate::TilemapBundle {
+            grid_size: self.grid_size,
+            map_type: self.map_type,
+            size: self.size,
+            spacing: self.spacing,
+            storage: tile_storage,
+            texture,
+            tile_size: self.tile_size,
+            anchor: self.anchor,
+            ..Default::default()
+        });
+        #[cfg(not(feature = "render"))]
+        world.entity_mut(tilemap).insert((
+            crate::StandardTilemapBundle {
+                grid_size: self.grid_size,
+                map_type: self.map_type,
+                size: self.size,
+                spacing: self.spacing,
+                storage: tile_storage,
+                texture,
+                tile_size: self.tile_size,
+                ..Default::default()
+            },
+            self.anchor,
+        ));
+        tilemap
+    }
+
+    fn spawn_components(
+        &self,
+        world: &mut World,
+        tile_entity: Entity,
+        saved: &[SavedComponent],
+        entity_map: &mut EntityHashMap<Entity>,
+    ) {
+        let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+            return;
+        };
+        let registry = registry.read();
+        for component in saved {
+            let Some(type_path) = self.component_types.get(component.type_index as usize) else {
+                continue;
+            };
+            let Some((registration, reflect_component)) = registry
+                .get_with_type_path(type_path)
+                .and_then(|registration| {
+                    Some((registration, registration.data::<ReflectComponent>()?))
+                })
+            else {
+                warn!("Skipping component {type_path} of a saved tile: it is not registered");
+                continue;
+            };
+            let value = ron::Deserializer::from_str(&component.ron)
+                .map_err(|err| err.to_string())
+                .and_then(|mut deserializer| {
+                    TypedReflectDeserializer::new(registration, &registry)
+                        .deserialize(&mut deserializer)
+                        .map_err(|err| err.to_string())
+                });
+            match value {
+                Ok(mut value) => {
+                    if let Some(map_entities) = registration.data::<ReflectMapEntities>() {
+                        map_entities.map_entities(value.as_partial_reflect_mut(), entity_map);
+                    }
+                    reflect_component.insert(
+                        &mut world.entity_mut(tile_entity),
+                        value.as_partial_reflect(),
+                        &registry,
+                    );
+                }
+                Err(err) => warn!("Skipping component {type_path} of a saved tile: {err}"),
+            }
+        }
+    }
+
+    /// Writes the save in a compact binary format.
+    ///
+    /// Tiles are written layer by layer and row by row, including the empty positions, and runs of
+    /// identical tiles without reflected components are written once, so large areas of the same
+    /// tile take up almost no space.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u8(VERSION);
+        writer.u32(self.size.x);
+        writer.u32(self.size.y);
+        let (map_type, coord_system) = match self.map_type {
+            TilemapType::Square => (0, 0),
+            TilemapType::Hexagon(hex_coord_system) => (1, hex_coord_system as u8),
+            TilemapType::Isometric(iso_coord_system) => (2, iso_coord_system as u8),
+        };
+        writer.u8(map_type);
+        writer.u8(coord_system);
+        writer.vec2(self.grid_size.into());
+        writer.vec2(self.tile_size.into());
+        writer.vec2(Vec2::new(self.spacing.x, self.spacing.y));
+        let (anchor, custom) = anchor_tag(&self.anchor);
+        writer.u8(anchor);
+        writer.vec2(custom);
+        let texture_kind = match &self.texture {
+            SavedTexture::Single(_) => 0,
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::Vector(_) => 1,
+            #[cfg(not(feature = "atlas"))]
+            SavedTexture::TextureContainer(_) => 2,
+        };
+        writer.u8(texture_kind);
+        let paths = self.texture.paths();
+        writer.u32(paths.len() as u32);
+        for path in paths {
+            writer.str(path);
+        }
+        writer.u32(self.component_types.len() as u32);
+        for type_path in &self.component_types {
+            writer.str(type_path);
+        }
+        writer.u32(self.layer_z_offsets.len() as u32);
+        for z_offset in &self.layer_z_offsets {
+            writer.f32(*z_offset);
+        }
+
+        // Of several tiles at one position, the last one is saved.
+        let layer_count = layer_count(&self.layer_z_offsets);
+        let tiles = self
+            .tiles
+            .iter()
+            .filter(|tile| tile.layer < layer_count && tile.position.within_map_bounds(&self.size))
+            .map(|tile| ((tile.layer, tile_index(&tile.position, &self.size)), tile))
+            .collect::<BTreeMap<_, _>>();
+        for layer in 0..layer_count {
+            let mut index = 0;
+            let mut runs = tiles.range((layer, 0)..=(layer, u64::MAX)).peekable();
+            while let Some((&(_, start), tile)) = runs.next() {
+                write_run(&mut writer, None, start - index);
+                let record = TileRecord::from_tile(tile);
+                let mut end = start + 1;
+                while runs
+                    .next_if(|((_, next), tile)| {
+                        *next == end && TileRecord::from_tile(tile) == record
+                    })
+                    .is_some()
+                {
+                    end += 1;
+                }
+                write_run(&mut writer, Some(&record), end - start);
+                index = end;
+            }
+            write_run(&mut writer, None, tile_count(&self.size) - index);
+        }
+
+        // Tiles with reflected components are written after the grid, as they break up runs.
+        let with_components = tiles
+            .values()
+            .filter(|tile| !tile.components.is_empty())
+            .collect::<Vec<_>>();
+        writer.u32(with_components.len() as u32);
+        for SavedTile {
+            position,
+            layer,
+            components,
+            ..
+        } in with_components
+        {
+            writer.u32(position.x);
+            writer.u32(position.y);
+            writer.u32(*layer);
+            writer.u32(components.len() as u32);
+            for component in components {
+                writer.u32(component.type_index);
+                writer.str(&component.ron);
+            }
+        }
+
+        // The entities the saved components may hold, to point them at the spawned ones.
+        writer.entity(self.source_tilemap);
+        writer.u32(self.referenced_tiles.len() as u32);
+        for (position, layer, entity) in &self.referenced_tiles {
+            writer.u32(position.x);
+            writer.u32(position.y);
+            writer.u32(*layer);
+            writer.entity(Some(*entity));
+        }
+        writer.bytes
+    }
+
+    /// Reads a save written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Saves with more than [`MAX_LOADED_TILES`] tiles are rejected, so that a damaged or
+    /// malicious file, whose runs can cover billions of positions in a few bytes, can not make
+    /// it run out of memory. For the same reason, [`spawn`](Self::spawn) only allocates every
+    /// position of maps with at most that many positions, and layered saves whose layers have
+    /// more positions than that together are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TilemapSaveError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err(TilemapSaveError::NotASave);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(TilemapSaveError::UnsupportedVersion(version));
+        }
+        let size = TilemapSize::new(reader.u32()?, reader.u32()?);
+        let map_type = match (reader.u8()?, reader.u8()?) {
+            (0, _) => TilemapType::Square,
+            (1, coord_system) => TilemapType::Hexagon(match coord_system {
+                0 => HexCoordSystem::RowEven,
+                1 => HexCoordSystem::RowOdd,
+                2 => HexCoordSystem::ColumnEven,
+                3 => HexCoordSystem::ColumnOdd,
+                4 => HexCoordSystem::Row,
+                5 => HexCoordSystem::Column,
+                _ => return Err(TilemapSaveError::Corrupt),
+            }),
+            (2, coord_system) => TilemapType::Isometric(match coord_system {
+                0 => IsoCoordSystem::Diamond,
+                1 => IsoCoordSystem::Staggered,
+                _ => return Err(TilemapSaveError::Corrupt),
+            }),
+            _ => return Err(TilemapSaveError::Corrupt),
+        };
+        let grid_size = reader.vec2()?.into();
+        let tile_size = reader.vec2()?.into();
+        let spacing = reader.vec2()?;
+        let spacing = TilemapSpacing::new(spacing.x, spacing.y);
+        let anchor = anchor_from_tag(reader.u8()?, reader.vec2()?)?;
+        let texture_kind = reader.u8()?;
+        let paths = (0..reader.u32()?)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let texture = match (texture_kind, paths.as_slice()) {
+            (0, [path]) => SavedTexture::Single(path.clone()),
+            #[cfg(not(feature = "atlas"))]
+            (1, _) => SavedTexture::Vector(paths),
+            #[cfg(not(feature = "atlas"))]
+            (2, [path]) => SavedTexture::TextureContainer(path.clone()),
+            _ => return Err(TilemapSaveError::Corrupt),
+        };
+        let component_types = (0..reader.u32()?)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let layer_z_offsets = (0..reader.u32()?)
+            .map(|_| reader.f32())
+            .collect::<Result<Vec<_>, _>>()?;
+        let layer_count = layer_count(&layer_z_offsets);
+        // Every layer of a `LayeredTileStorage` holds all positions.
+        if !layer_z_offsets.is_empty()
+            && tile_count(&size).saturating_mul(layer_count as u64) > MAX_LOADED_TILES
+        {
+            return Err(TilemapSaveError::TooManyTiles);
+        }
+
+        let mut tiles = Vec::new();
+        for layer in 0..layer_count {
+            let mut index = 0;
+            while index < tile_count(&size) {
+                let run = reader.u32()? as u64;
+                let record = TileRecord::read(&mut reader)?;
+                if run == 0 || index + run > tile_count(&size) {
+                    return Err(TilemapSaveError::Corrupt);
+                }
+                if let Some(record) = record {
+                    if tiles.len() as u64 + run > MAX_LOADED_TILES {
+                        return Err(TilemapSaveError::TooManyTiles);
+                    }
+                    for index in index..index + run {
+                        let position = TilePos::new(
+                            (index % size.x as u64) as u32,
+                            (index / size.x as u64) as u32,
+                        );
+                        tiles.push(record.to_tile(position, layer));
+                    }
+                }
+                index += run;
+            }
+        }
+        for _ in 0..reader.u32()? {
+            let position = TilePos::new(reader.u32()?, reader.u32()?);
+            let layer = reader.u32()?;
+            let tile = tiles
+                .binary_search_by_key(&(layer, position.y, position.x), |tile| {
+                    (tile.layer, tile.position.y, tile.position.x)
+                })
+                .map_err(|_| TilemapSaveError::Corrupt)?;
+            for _ in 0..reader.u32()? {
+                let type_index = reader.u32()?;
+                let ron = reader.string()?;
+                tiles[tile]
+                    .components
+                    .push(SavedComponent { type_index, ron });
+            }
+        }
+        let source_tilemap = reader.entity()?;
+        let referenced_tiles = (0..reader.u32()?)
+            .map(|_| {
+                let position = TilePos::new(reader.u32()?, reader.u32()?);
+                let layer = reader.u32()?;
+                let entity = reader.entity()?.ok_or(TilemapSaveError::Corrupt)?;
+                Ok((position, layer, entity))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TilemapSave {
+            size,
+            map_type,
+            grid_size,
+            tile_size,
+            spacing,
+            anchor,
+            texture,
+            component_types,
+            layer_z_offsets,
+            tiles,
+            source_tilemap,
+            referenced_tiles,
+        })
+    }
+}
+
+/// The storage a save is spawned into.
+enum SpawnedStorage {
+    Dense(TileStorage),
+    Sparse(SparseTileStorage),
+    Layered(LayeredTileStorage),
+}
+
+impl SpawnedStorage {
+    fn new(save: &TilemapSave) -> Self {
+        if !save.layer_z_offsets.is_empty() {
+            let mut layered = LayeredTileStorage::empty(save.size, save.layer_z_offsets.len());
+            for (layer, z_offset) in save.layer_z_offsets.iter().enumerate() {
+                layered.set_z_offset(layer, *z_offset);
+            }
+            SpawnedStorage::Layered(layered)
+        } else if tile_count(&save.size) > MAX_LOADED_TILES {
+            SpawnedStorage::Sparse(SparseTileStorage::empty(save.size))
+        } else {
+            SpawnedStorage::Dense(TileStorage::empty(save.size))
+        }
+    }
+
+    fn checked_get(&self, tile_pos: &TilePos, layer: u32) -> Option<Entity> {
+        match self {
+            SpawnedStorage::Layered(layered) => layered.checked_get(tile_pos, layer as usize),
+            SpawnedStorage::Dense(storage) if layer == 0 => storage.checked_get(tile_pos),
+            SpawnedStorage::Sparse(storage) if layer == 0 => storage.checked_get(tile_pos),
+            _ => None,
+        }
+    }
+
+    fn checked_set(&mut self, tile_pos: &TilePos, layer: u32, tile_entity: Entity) {
+        match self {
+            // The tiles are new, so they are drawn in their layer without being moved there.
+            SpawnedStorage::Layered(layered) if (layer as usize) < layered.layer_count() => {
+                layered
+                    .layer_mut(layer as usize)
+                    .checked_set(tile_pos, tile_entity);
+            }
+            SpawnedStorage::Dense(storage) if layer == 0 => {
+                storage.checked_set(tile_pos, tile_entity);
+            }
+            SpawnedStorage::Sparse(storage) if layer == 0 => {
+                storage.checked_set(tile_pos, tile_entity);
+            }
+            _ => {}
+        }
+    }
+
+    /// Inserts the storages other than the [`TileStorage`] into `tilemap`, and returns the
+    /// [`TileStorage`] for its bundle.
+    fn insert_into(self, tilemap: &mut EntityWorldMut) -> TileStorage {
+        match self {
+            SpawnedStorage::Dense(storage) => storage,
+            SpawnedStorage::Sparse(storage) => {
+                tilemap.insert(storage);
+                TileStorage::default()
+            }
+            SpawnedStorage::Layered(layered) => {
+                tilemap.insert(layered);
+                TileStorage::default()
+            }
+        }
+    }
+}
+
+fn anchor_tag(anchor: &TilemapAnchor) -> (u8, Vec2) {
+    match anchor {
+        TilemapAnchor::None => (0, Vec2::ZERO),
+        TilemapAnchor::TopLeft => (1, Vec2::ZERO),
+        TilemapAnchor::TopCenter => (2, Vec2::ZERO),
+        TilemapAnchor::TopRight => (3, Vec2::ZERO),
+        TilemapAnchor::CenterLeft => (4, Vec2::ZERO),
+        TilemapAnchor::Center => (5, Vec2::ZERO),
+        TilemapAnchor::CenterRight => (6, Vec2::ZERO),
+        TilemapAnchor::BottomLeft => (7, Vec2::ZERO),
+        TilemapAnchor::BottomCenter => (8, Vec2::ZERO),
+        TilemapAnchor::BottomRight => (9, Vec2::ZERO),
+        TilemapAnchor::Custom(custom) => (10, *custom),
+    }
+}
+
+fn anchor_from_tag(tag: u8, custom: Vec2) -> Result<TilemapAnchor, TilemapSaveError> {
+    Ok(match tag {
+        0 => TilemapAnchor::None,
+        1 => TilemapAnchor::TopLeft,
+        2 => TilemapAnchor::TopCenter,
+        3 => TilemapAnchor::TopRight,
+        4 => TilemapAnchor::CenterLeft,
+        5 => TilemapAnchor::Center,
+        6 => TilemapAnchor::CenterRight,
+        7 => TilemapAnchor::BottomLeft,
+        8 => TilemapAnchor::BottomCenter,
+        9 => TilemapAnchor::BottomRight,
+        10 => TilemapAnchor::Custom(custom),
+        _ => return Err(TilemapSaveError::Corrupt),
+    })
+}
+
+/// The components every tile has, as written to the binary format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TileRecord {
+    texture_index: u32,
+    flags: u8,
+    color: [f32; 4],
+}
+
+impl TileRecord {
+    const PRESENT: u8 = 1;
+    const VISIBLE: u8 = 2;
+    const FLIP_X: u8 = 4;
+    const FLIP_Y: u8 = 8;
+    const FLIP_D: u8 = 16;
+
+    fn from_tile(tile: &SavedTile) -> Self {
+        let mut flags = Self::PRESENT;
+        for (set, flag) in [
+            (tile.visible.0, Self::VISIBLE),
+            (tile.flip.x, Self::FLIP_X),
+            (tile.flip.y, Self::FLIP_Y),
+            (tile.flip.d, Self::FLIP_D),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        Self {
+            texture_index: tile.texture_index.0,
+            flags,
+            color: tile.color.0.to_linear().to_f32_array(),
+        }
+    }
+
+    fn to_tile(self, position: TilePos, layer: u32) -> SavedTile {
+        let [r, g, b, a] = self.color;
+        SavedTile {
+            position,
+            layer,
+            texture_index: TileTextureIndex(self.texture_index),
+            visible: TileVisible(self.flags & Self::VISIBLE != 0),
+            flip: TileFlip {
+                x: self.flags & Self::FLIP_X != 0,
+                y: self.flags & Self::FLIP_Y != 0,
+                d: self.flags & Self::FLIP_D != 0,
+            },
+            color: TileColor(Color::linear_rgba(r, g, b, a)),
+            components: Vec::new(),
+        }
+    }
+
+    /// Writes the flags, and the rest of the record if there is a tile.
+    fn write(record: Option<&Self>, writer: &mut Writer) {
+        let Some(record) = record else {
+            writer.u8(0);
+            return;
+        };
+        writer.u8(record.flags);
+        writer.u32(record.texture_index);
+        for channel in record.color {
+            writer.f32(channel);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Option<Self>, TilemapSaveError> {
+        let flags = reader.u8()?;
+        if flags & Self::PRESENT == 0 {
+            return Ok(None);
+        }
+        let texture_index = reader.u32()?;
+        let mut color = [0.0; 4];
+        for channel in &mut color {
+            *channel = reader.f32()?;
+        }
+        Ok(Some(Self {
+            texture_index,
+            flags,
+            color,
+        }))
+    }
+}
+
+/// The number of positions of a tilemap, which may not fit into a `u32` or `usize`.
+fn tile_count(size: &TilemapSize) -> u64 {
+    size.x as u64 * size.y as u64
+}
+
+/// The number of layers a save has, which is one for tilemaps without a [`LayeredTileStorage`].
+fn layer_count(layer_z_offsets: &[f32]) -> u32 {
+    layer_z_offsets.len().max(1) as u32
+}
+
+fn tile_index(tile_pos: &TilePos, size: &TilemapSize) -> u64 {
+    tile_pos.y as u64 * size.x as u64 + tile_pos.x as u64
+}
+
+/// Writes `len` positions holding `record`, split into runs which fit into a `u32`.
+fn write_run(writer: &mut Writer, record: Option<&TileRecord>, mut len: u64) {
+    while len > 0 {
+        let run = len.min(u32::MAX as u64) as u32;
+        writer.u32(run);
+        TileRecord::write(record, writer);
+        len -= run as u64;
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn entity(&mut self, value: Option<Entity>) {
+        self.u8(value.is_some() as u8);
+        let bits = value.map_or(0, Entity::to_bits);
+        self.bytes.extend_from_slice(&bits.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TilemapSaveError> {
+        if self.bytes.len() < len {
+            return Err(TilemapSaveError::Corrupt);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, TilemapSaveError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, TilemapSaveError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, TilemapSaveError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, TilemapSaveError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn entity(&mut self) -> Result<Option<Entity>, TilemapSaveError> {
+        let present = self.u8()? != 0;
+        let bits = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        if !present {
+            return Ok(None);
+        }
+        Entity::try_from_bits(bits)
+            .map(Some)
+            .ok_or(TilemapSaveError::Corrupt)
+    }
+
+    fn string(&mut self) -> Result<String, TilemapSaveError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| TilemapSaveError::Corrupt)
+    }
+}
+
+/// An error while reading a [`TilemapSave`] with [`TilemapSave::from_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TilemapSaveError {
+    /// The bytes do not start like a save.
+    NotASave,
+    /// The save was written by a newer version of the format.
+    UnsupportedVersion(u8),
+    /// The save is truncated or otherwise damaged.
+    Corrupt,
+    /// The save holds more than [`MAX_LOADED_TILES`] tiles.
+    TooManyTiles,
+}
+
+impl fmt::Display for TilemapSaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotASave => write!(f, "not a tilemap save"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported tilemap save version {version}")
+            }
+            Self::Corrupt => write!(f, "the tilemap save is damaged"),
+            Self::TooManyTiles => write!(f, "the tilemap save holds too many tiles"),
+        }
+    }
+}
+
+impl std::error::Error for TilemapSaveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::entity::MapEntities;
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Ore {
+        amount: u32,
+    }
+
+    #[test]
+    fn tilemaps_survive_saving() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Ore>();
+
+        let size = TilemapSize::new(64, 64);
+        let tilemap = world
+            .spawn((
+                size,
+                TilemapType::Hexagon(HexCoordSystem::ColumnOdd),
+                TilemapGridSize::new(16.0, 14.0),
+                TilemapTileSize::new(16.0, 16.0),
+                TilemapAnchor::Custom(Vec2::new(0.25, -0.5)),
+            ))
+            .id();
+        let mut tile_storage = TileStorage::empty(size);
+        for y in 0..size.y {
+            // The top row is left empty.
+            for x in 0..size.x.min(if y == size.y - 1 { 0 } else { size.x }) {
+                let position = TilePos::new(x, y);
+                let mut tile = world.spawn(TileBundle {
+                    position,
+                    tilemap_id: TilemapId(tilemap),
+                    texture_index: TileTextureIndex(y / 8),
+                    ..Default::default()
+                });
+                if position == TilePos::new(5, 6) {
+                    tile.insert((
+                        Ore { amount: 40 },
+                        TileFlip {
+                            x: true,
+                            y: false,
+                            d: true,
+                        },
+                        TileColor(Color::srgb(0.5, 0.25, 1.0)),
+                    ));
+                }
+                tile_storage.set(&position, tile.id());
+            }
+        }
+        world.entity_mut(tilemap).insert(tile_storage);
+
+        let save = TilemapSave::capture(&world, tilemap).unwrap();
+        assert_eq!(save.tiles.len(), 64 * 63);
+        assert_eq!(save.component_types.len(), 1);
+        let bytes = save.to_bytes();
+        // Runs of identical tiles take up the space of one.
+        assert!(bytes.len() < 1024, "{} bytes", bytes.len());
+        let loaded = TilemapSave::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.tiles.len(), save.tiles.len());
+        assert_eq!(loaded.anchor, save.anchor);
+        assert_eq!(loaded.map_type, save.map_type);
+        assert_eq!(
+            TilemapSave::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(TilemapSaveError::Corrupt)
+        );
+
+        let spawned = loaded.spawn(&mut world);
+        let tile_storage = world.get::<TileStorage>(spawned).unwrap();
+        assert_eq!(tile_storage.get(&TilePos::new(0, 63)), None);
+        let ore = tile_storage.get(&TilePos::new(5, 6)).unwrap();
+        let plain = tile_storage.get(&TilePos::new(5, 17)).unwrap();
+        assert_eq!(world.get::<Ore>(ore), Some(&Ore { amount: 40 }));
+        assert!(world.get::<TileFlip>(ore).unwrap().d);
+        assert_eq!(world.get::<TilemapId>(ore), Some(&TilemapId(spawned)));
+        assert_eq!(
+            world.get::<TileTextureIndex>(plain),
+            Some(&TileTextureIndex(2))
+        );
+        assert_eq!(world.get::<Ore>(plain), None);
+        assert_eq!(
+            world.get::<TilemapAnchor>(spawned),
+            Some(&TilemapAnchor::Custom(Vec2::new(0.25, -0.5)))
+        );
+    }
+
+    #[test]
+    fn sparse_tilemaps_are_captured_row_by_row() {
+        let mut world = World::new();
+        let size = TilemapSize::new(1000, 1000);
+        let tilemap = world
+            .spawn((
+                size,
+                TilemapType::Square,
+                TilemapGridSize::new(16.0, 16.0),
+                TilemapTileSize::new(16.0, 16.0),
+                TileStorage::default(),
+            ))
+            .id();
+        let mut storage = crate::tiles::SparseTileStorage::empty(size);
+        for position in [
+            TilePos::new(7, 900),
+            TilePos::new(3, 2),
+            TilePos::new(1, 900),
+        ] {
+            let tile = world.spawn(TileBundle {
+                position,
+                tilemap_id: TilemapId(tilemap),
+                ..Default::default()
+            });
+            storage.set(&position, tile.id());
+        }
+        world.entity_mut(tilemap).insert(storage);
+
+        let save = TilemapSave::capture(&world, tilemap).unwrap();
+        assert_eq!(
+            save.tiles
+                .iter()
+                .map(|tile| tile.position)
+                .collect::<Vec<_>>(),
+            [
+                TilePos::new(3, 2),
+                TilePos::new(1, 900),
+                TilePos::new(7, 900)
+            ]
+        );
+    }
+
+    #[test]
+    fn huge_saves_are_read_without_overflowing() {
+        let tile = SavedTile {
+            position: TilePos::new(123_456, 1 << 19),
+            layer: 0,
+            texture_index: TileTextureIndex(3),
+            visible: TileVisible(true),
+            flip: TileFlip::default(),
+            color: TileColor::default(),
+            components: Vec::new(),
+        };
+        // A sparse map larger than `u32::MAX` positions round trips.
+        let save = TilemapSave {
+            size: TilemapSize::new(1 << 20, 1 << 20),
+            tiles: vec![tile.clone()],
+            ..Default::default()
+        };
+        let loaded = TilemapSave::from_bytes(&save.to_bytes()).unwrap();
+        assert_eq!(loaded.tiles.len(), 1);
+        assert_eq!(loaded.tiles[0].position, tile.position);
+
+        // Spawning it only takes up memory for its one tile.
+        let mut world = World::new();
+        let spawned = loaded.spawn(&mut world);
+        assert!(world.get::<SparseTileStorage>(spawned).is_some());
+        let spawned_tile = tile_store(&world, spawned)
+            .unwrap()
+            .get(&tile.position)
+            .unwrap();
+        assert_eq!(
+            world.get::<TileTextureIndex>(spawned_tile),
+            Some(&TileTextureIndex(3))
+        );
+        assert_eq!(
+            world.get::<TilemapId>(spawned_tile),
+            Some(&TilemapId(spawned))
+        );
+
+        // A few bytes claiming billions of tiles are rejected instead of filling the memory.
+        // An empty save ends with the number of tiles with components, and the saved entities.
+        let header_len = TilemapSave::default().to_bytes().len() - 17;
+        let mut writer = Writer::default();
+        writer
+            .bytes
+            .extend_from_slice(&save.to_bytes()[..header_len]);
+        writer.bytes[5..13].fill(0xff);
+        write_run(
+            &mut writer,
+            Some(&TileRecord::from_tile(&tile)),
+            u32::MAX as u64,
+        );
+        assert_eq!(
+            TilemapSave::from_bytes(&writer.bytes).err(),
+            Some(TilemapSaveError::TooManyTiles)
+        );
+    }
+
+    /// A tile which leads to another tile, e.g. a teleporter.
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component, MapEntities)]
+    struct Portal {
+        target: Entity,
+        tilemap: Entity,
+        other: Entity,
+    }
+
+    impl MapEntities for Portal {
+        fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+            self.target = entity_mapper.get_mapped(self.target);
+            self.tilemap = entity_mapper.get_mapped(self.tilemap);
+            self.other = entity_mapper.get_mapped(self.other);
+        }
+    }
+
+    #[test]
+    fn entities_in_components_point_at_the_spawned_tiles() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Portal>();
+
+        let size = TilemapSize::new(4, 4);
+        let tilemap = world
+            .spawn((
+                size,
+                TilemapType::Square,
+                TilemapGridSize::new(16.0, 16.0),
+                TilemapTileSize::new(16.0, 16.0),
+            ))
+            .id();
+        let other = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(size);
+        let mut spawn_tile = |world: &mut World, position: TilePos| {
+            let tile = world
+                .spawn(TileBundle {
+                    position,
+                    tilemap_id: TilemapId(tilemap),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&position, tile);
+            tile
+        };
+        let target = spawn_tile(&mut world, TilePos::new(3, 2));
+        let portal = spawn_tile(&mut world, TilePos::new(0, 1));
+        world.entity_mut(portal).insert(Portal {
+            target,
+            tilemap,
+            other,
+        });
+        world.entity_mut(tilemap).insert(tile_storage);
+
+        let bytes = TilemapSave::capture(&world, tilemap).unwrap().to_bytes();
+        let spawned = TilemapSave::from_bytes(&bytes).unwrap().spawn(&mut world);
+        let tile_storage = world.get::<TileStorage>(spawned).unwrap();
+        let spawned_portal = tile_storage.get(&TilePos::new(0, 1)).unwrap();
+        assert_eq!(
+            world.get::<Portal>(spawned_portal),
+            Some(&Portal {
+                target: tile_storage.get(&TilePos::new(3, 2)).unwrap(),
+                tilemap: spawned,
+                other,
+            })
+        );
+    }
+
+    #[test]
+    fn layered_tilemaps_keep_their_layers() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Portal>();
+
+        let size = TilemapSize::new(4, 4);
+        let tilemap = world
+            .spawn((
+                size,
+                TilemapType::Square,
+                TilemapGridSize::new(16.0, 16.0),
+                TilemapTileSize::new(16.0, 16.0),
+            ))
+            .id();
+        let mut layered = LayeredTileStorage::empty(size, 2);
+        layered.push_layer(5.0);
+        let tile_pos = TilePos::new(1, 2);
+        let mut stack = Vec::new();
+        for layer in 0..3 {
+            let tile = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap),
+                    texture_index: TileTextureIndex(layer),
+                    ..Default::default()
+                })
+                .id();
+            layered.set(&tile_pos, layer as usize, tile);
+            stack.push(tile);
+        }
+        // The portal on top leads to the tile below it, not to the ground.
+        world.entity_mut(stack[2]).insert(Portal {
+            target: stack[1],
+            tilemap,
+            other: tilemap,
+        });
+        world.entity_mut(tilemap).insert(layered);
+
+        let bytes = TilemapSave::capture(&world, tilemap).unwrap().to_bytes();
+        let loaded = TilemapSave::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.layer_z_offsets, [0.0, 1.0, 5.0]);
+        let spawned = loaded.spawn(&mut world);
+        let layered = world.get::<LayeredTileStorage>(spawned).unwrap();
+        assert_eq!(layered.z_offsets(), [0.0, 1.0, 5.0]);
+        let spawned_stack = layered.stack(&tile_pos).flatten().collect::<Vec<_>>();
+        assert_eq!(spawned_stack.len(), 3);
+        for (layer, tile) in spawned_stack.iter().enumerate() {
+            assert_eq!(
+                world.get::<TileTextureIndex>(*tile),
+                Some(&TileTextureIndex(layer as u32))
+            );
+        }
+        assert_eq!(
+            world.get::<Portal>(spawned_stack[2]).unwrap().target,
+            spawned_stack[1]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Renders tilemaps headlessly and compares the result with golden images, so changes to the
+//! renderer, or to code which spawns tilemaps, can be snapshot-tested.
+//!
+//! ```no_run
+//! # use bevy::prelude::*;
+//! # use bevy_ecs_tilemap::prelude::*;
+//! # use bevy_ecs_tilemap::snapshot::*;
+//! let mut app = snapshot_app();
+//! let camera = spawn_snapshot_camera(app.world_mut(), UVec2::new(64, 64));
+//! // Spawn the tilemap to test into `app.world_mut()`...
+//! let image = capture(&mut app, camera, 10).expect("the frame was not read back");
+//! image.assert_matches_golden("tests/goldens/my_map.pam", 0);
+//! ```
+//!
+//! Golden images are stored as uncompressed RGBA [PAM](https://netpbm.sourceforge.net/doc/pam.html)
+//! files. Run the tests with the `UPDATE_GOLDENS` environment variable set to write the images
+//! which are missing or do not match.
+//!
+//! Bevy can only render with one app per process, so all snapshots of a test binary have to share
+//! one app, e.g. in a test target with `harness = false`, despawning each tilemap and camera after
+//! its capture. The crate's own `tests/golden.rs` is set up like this.
+
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::app::{App, PluginGroupBuilder, PluginsState};
+use bevy::camera::{Camera, Camera2d, RenderTarget};
+use bevy::image::ImagePlugin;
+use bevy::math::UVec2;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::{
+    Assets, Component, DefaultPlugins, Entity, Handle, Image, On, PluginGroup, Query, ResMut,
+    Resource, World,
+};
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::render_resource::{TextureFormat, TextureUsages};
+use bevy::tasks::tick_global_task_pools_on_main_thread;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::{ExitCondition, WindowPlugin};
+
+use crate::TilemapPlugin;
+
+/// The time each update of a [`snapshot_app`] advances by, so animations are reproducible.
+pub const SNAPSHOT_FRAME_TIME: Duration = Duration::from_millis(100);
+
+const ROW_ALIGNMENT: usize = 256;
+
+/// An RGBA image, with rows from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotImage {
+    pub size: UVec2,
+    /// The pixels, four bytes each.
+    pub data: Vec<u8>,
+}
+
+/// How much two [`SnapshotImage`]s differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// The number of pixels with a channel which differs by more than the tolerance.
+    pub mismatched_pixels: usize,
+    /// The largest difference of a channel, over all pixels.
+    pub max_channel_delta: u8,
+}
+
+impl SnapshotImage {
+    /// Converts the bytes of a texture read back from the GPU, whose rows are padded to 256 bytes.
+    pub fn from_readback(size: UVec2, data: &[u8]) -> Self {
+        let row_len = size.x as usize * 4;
+        let padded_row_len = row_len.div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT;
+        let data = data
+            .chunks(padded_row_len)
+            .take(size.y as usize)
+            .flat_map(|row| &row[..row_len.min(row.len())])
+            .copied()
+            .collect();
+        Self { size, data }
+    }
+
+    /// Compares with another image, ignoring channels which differ by `tolerance` or less.
+    ///
+    /// Images of different sizes differ in every pixel.
+    pub fn diff(&self, other: &SnapshotImage, tolerance: u8) -> SnapshotDiff {
+        if self.size != other.size || self.data.len() != other.data.len() {
+            return SnapshotDiff {
+                mismatched_pixels: (self.size.x * self.size.y).max(other.size.x * other.size.y)
+                    as usize,
+                max_channel_delta: u8::MAX,
+            };
+        }
+        let mut diff = SnapshotDiff::default();
+        for (a, b) in self.data.chunks(4).zip(other.data.chunks(4)) {
+            let delta = a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+            diff.max_channel_delta = diff.max_channel_delta.max(delta);
+            if delta > tolerance {
+                diff.mismatched_pixels += 1;
+            }
+        }
+        diff
+    }
+
+    /// Encodes the image as a PAM file.
+    pub fn to_pam(&self) -> Vec<u8> {
+        let mut pam = format!(
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            self.size.x, self.size.y
+        )
+        .into_bytes();
+        pam.extend_from_slice(&self.data);
+        pam
+    }
+
+    /// Decodes an RGBA PAM file, as written by [`to_pam`](Self::to_pam).
+    pub fn from_pam(pam: &[u8]) -> Option<Self> {
+        const END: &[u8] = b"ENDHDR\n";
+        let header_len = pam.windows(END.len()).position(|w| w == END)? + END.len();
+        let header = std::str::from_utf8(&pam[..header_len]).ok()?;
+        let mut size = UVec2::ZERO;
+        for line in header.lines() {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("WIDTH"), Some(width)) => size.x = width.parse().ok()?,
+                (Some("HEIGHT"), Some(height)) => size.y = height.parse().ok()?,
+                (Some("DEPTH"), Some(depth)) if depth != "4" => return None,
+                (Some("MAXVAL"), Some(max)) if max != "255" => return None,
+                _ => {}
+            }
+        }
+        let data = pam[header_len..].to_vec();
+        (data.len() == (size.x * size.y * 4) as usize).then_some(Self { size, data })
+    }
+
+    /// Panics if the image differs from the golden image at `path` by more than `tolerance` in a
+    /// channel of any pixel.
+    ///
+    /// With the `UPDATE_GOLDENS` environment variable set, a missing or differing golden image is
+    /// written instead.
+    pub fn assert_matches_golden(&self, path: impl AsRef<Path>, tolerance: u8) {
+        let path = path.as_ref();
+        let update = std::env::var_os("UPDATE_GOLDENS").is_some();
+        let golden = std::fs::read(path)
+            .ok()
+            .and_then(|pam| SnapshotImage::from_pam(&pam));
+        let diff = golden.as_ref().map(|golden| self.diff(golden, tolerance));
+        if diff.is_some_and(|diff| diff.mismatched_pixels == 0) {
+            return;
+        }
+        if update {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).expect("could not create the golden directory");
+            }
+            std::fs::write(path, self.to_pam()).expect("could not write the golden image");
+            return;
+        }
+
+        // Keep the image next to the golden, to look at what changed.
+        let actual_path = path.with_extension("actual.pam");
+        let _ = std::fs::write(&actual_path, self.to_pam());
+        match diff {
+            None => panic!(
+                "golden image {} is missing; run with UPDATE_GOLDENS=1 to write it",
+                path.display()
+            ),
+            Some(diff) => panic!(
+                "{} pixels differ from golden image {} (max channel delta {}), see {}",
+                diff.mismatched_pixels,
+                path.display(),
+                diff.max_channel_delta,
+                actual_path.display()
+            ),
+        }
+    }
+}
+
+/// The images read back from the [`spawn_snapshot_camera`] targets, by camera.
+#[derive(Resource, Default)]
+struct SnapshotCaptures(HashMap<Entity, SnapshotImage>);
+
+/// The image a camera spawned by [`spawn_snapshot_camera`] renders into.
+#[derive(Component, Clone, Debug)]
+pub struct SnapshotTarget {
+    pub image: Handle<Image>,
+    pub size: UVec2,
+}
+
+/// The camera whose target a readback entity reads.
+#[derive(Component)]
+struct SnapshotReadback {
+    camera: Entity,
+    size: UVec2,
+}
+
+fn store_readback(
+    readback: On<ReadbackComplete>,
+    readbacks: Query<&SnapshotReadback>,
+    mut captures: ResMut<SnapshotCaptures>,
+) {
+    if let Ok(target) = readbacks.get(readback.entity) {
+        captures.0.insert(
+            target.camera,
+            SnapshotImage::from_readback(target.size, &readback.data),
+        );
+    }
+}
+
+/// Creates an app which renders without a window, with the [`TilemapPlugin`], and whose time
+/// advances by [`SNAPSHOT_FRAME_TIME`] per update.
+///
+/// Assets are loaded from the `assets` folder of the crate running the app.
+pub fn snapshot_app() -> App {
+    snapshot_app_with(DefaultPlugins.build())
+}
+
+/// Creates an app like [`snapshot_app`], from the given plugins instead of the
+/// [`DefaultPlugins`].
+///
+/// Tests built with the `bevy_winit` feature of Bevy need this to disable the `WinitPlugin`,
+/// which panics outside of the main thread, e.g.
+/// `snapshot_app_with(DefaultPlugins.build().disable::<WinitPlugin>())`.
+pub fn snapshot_app_with(plugins: PluginGroupBuilder) -> App {
+    let mut app = App::new();
+    app.add_plugins(
+        plugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+                ..Default::default()
+            })
+            .set(ImagePlugin::default_nearest()),
+    )
+    .add_plugins(TilemapPlugin)
+    .init_resource::<SnapshotCaptures>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(SNAPSHOT_FRAME_TIME))
+    .add_observer(store_readback);
+
+    // `App::run` would finish the plugins, which the render app needs before its first update.
+    while app.plugins_state() == PluginsState::Adding {
+        tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Spawns a 2D camera which renders into an image of the given size, centered on the origin.
+pub fn spawn_snapshot_camera(world: &mut World, size: UVec2) -> Entity {
+    let mut image = Image::new_target_texture(size.x, size.y, TextureFormat::Rgba8UnormSrgb);
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let target = world.resource_mut::<Assets<Image>>().add(image);
+
+    world
+        .spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::from(target.clone()),
+                ..Default::default()
+            },
+            SnapshotTarget {
+                image: target,
+                size,
+            },
+        ))
+        .id()
+}
+
+/// Updates the app `frames` times, so assets can load and animations advance, then reads back
+/// the next frame rendered by `camera`, which has to be spawned by [`spawn_snapshot_camera`].
+///
+/// Returns `None` if the frame was not read back within 10 more updates.
+pub fn capture(app: &mut App, camera: Entity, frames: u32) -> Option<SnapshotImage> {
+    for _ in 0..frames {
+        app.update();
+    }
+    let world = app.world_mut();
+    let target = world.get::<SnapshotTarget>(camera)?.clone();
+    let readback = world
+        .spawn((
+            Readback::texture(target.image),
+            SnapshotReadback {
+                camera,
+                size: target.size,
+            },
+        ))
+        .id();
+
+    let mut image = None;
+    for _ in 0..10 {
+        app.update();
+        image = app
+            .world_mut()
+            .resource_mut::<SnapshotCaptures>()
+            .0
+            .remove(&camera);
+        if image.is_some() {
+            break;
+        }
+    }
+    app.world_mut().despawn(readback);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readback_padding_is_stripped_and_pam_round_trips() {
+        let size = UVec2::new(3, 2);
+        let mut padded = vec![0u8; ROW_ALIGNMENT * 2];
+        padded[..12].fill(1);
+        padded[ROW_ALIGNMENT..ROW_ALIGNMENT + 12].fill(2);
+        let image = SnapshotImage::from_readback(size, &padded);
+        assert_eq!(image.data.len(), 24);
+        assert!(image.data[12..].iter().all(|b| *b == 2));
+
+        let decoded = SnapshotImage::from_pam(&image.to_pam()).unwrap();
+        assert_eq!(decoded, image);
+
+        let mut changed = image.clone();
+        changed.data[0] = 5;
+        let diff = image.diff(&changed, 3);
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_channel_delta, 4);
+        assert_eq!(image.diff(&changed, 4).mismatched_pixels, 0);
+    }
+}
@@ -0,0 +1,601 @@
+//! Loads maps made with the [Tiled](https://www.mapeditor.org/) editor.
+//!
+//! Add the [`TiledMapPlugin`] and spawn a [`TiledMapHandle`] pointing at a `.tmx` file. Once the
+//! map is loaded, one tilemap is spawned per tile layer and tileset as children of that entity,
+//! and one [`TiledObject`] per object. The map is spawned again whenever the asset changes.
+//!
+//! Supported are:
+//! * embedded and external (`.tsx`) tilesets, made of a single image or a collection of images,
+//! * finite tile layers, also inside of group layers,
+//! * object layers,
+//! * orthogonal, isometric, staggered and hexagonal maps,
+//! * flipped and rotated tiles, and animations over consecutive tiles of a tileset,
+//! * wang sets, as [`TerrainBrush`]es made with [`TiledMap::terrain_brush`],
+//! * tile layers of prefab maps, as [`Stamp`]s made with [`TiledMap::stamp`].
+//!
+//! Infinite tile layers and image layers are skipped. Tilesets made of a collection of images are
+//! skipped with the `atlas` feature.
+
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ::tiled::{
+    DefaultResourceCache, Layer, LayerType, Loader, Orientation, ResourceReader, StaggerAxis,
+    StaggerIndex, TileLayer, Tileset, WangSetType,
+};
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, ReadAssetBytesError, io::Reader};
+use bevy::log::{info, warn};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::TilemapBundle;
+use crate::anchor::TilemapAnchor;
+use crate::helpers::stamp::Stamp;
+use crate::helpers::terrain_brush::{TerrainBrush, TerrainKind};
+use crate::map::{
+    HexCoordSystem, IsoCoordSystem, TilemapGridSize, TilemapId, TilemapRenderSettings, TilemapSize,
+    TilemapSpacing, TilemapTexture, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{
+    AnimatedTile, TileBundle, TileColor, TileDataLayer, TileFlip, TilePos, TileStorage,
+    TileTextureIndex,
+};
+
+/// Loads `.tmx` files as [`TiledMap`]s, and spawns the maps of [`TiledMapHandle`]s.
+#[derive(Default)]
+pub struct TiledMapPlugin;
+
+impl Plugin for TiledMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TiledMap>()
+            .register_asset_loader(TiledMapLoader)
+            .add_systems(Update, spawn_tiled_maps);
+    }
+}
+
+/// A map loaded from a `.tmx` file.
+#[derive(TypePath, Asset)]
+pub struct TiledMap {
+    pub map: ::tiled::Map,
+    /// The texture of each tileset, by tileset index.
+    pub tilemap_textures: HashMap<usize, TilemapTexture>,
+    /// The index of each tile image within the texture of tilesets made of a collection of images.
+    #[cfg(not(feature = "atlas"))]
+    pub tile_image_offsets: HashMap<(usize, ::tiled::TileId), u32>,
+}
+
+impl TiledMap {
+    /// Returns the texture index of a tile of a tileset, if its image was loaded.
+    pub fn texture_index(&self, tileset_index: usize, tile_id: ::tiled::TileId) -> Option<u32> {
+        match self.tilemap_textures.get(&tileset_index)? {
+            TilemapTexture::Single(_) => Some(tile_id),
+            #[cfg(not(feature = "atlas"))]
+            _ => self
+                .tile_image_offsets
+                .get(&(tileset_index, tile_id))
+                .copied(),
+        }
+    }
+
+    /// Returns the [`TilemapType`] matching the orientation of the map.
+    ///
+    /// Tiled numbers rows from the top while tilemaps number them from the bottom, so which rows
+    /// of a hexagonal map are shoved depends on its height.
+    pub fn tilemap_type(&self) -> TilemapType {
+        match self.map.orientation {
+            Orientation::Orthogonal => TilemapType::Square,
+            Orientation::Isometric => TilemapType::Isometric(IsoCoordSystem::Diamond),
+            Orientation::Staggered => TilemapType::Isometric(IsoCoordSystem::Staggered),
+            Orientation::Hexagonal => match self.map.stagger_axis {
+                StaggerAxis::X => match self.map.stagger_index {
+                    // Tiled shoves columns down, which shoves the other columns up.
+                    StaggerIndex::Odd => TilemapType::Hexagon(HexCoordSystem::ColumnEven),
+                    StaggerIndex::Even => TilemapType::Hexagon(HexCoordSystem::ColumnOdd),
+                },
+                StaggerAxis::Y => {
+                    let odd_rows = (self.map.stagger_index == StaggerIndex::Odd)
+                        == !self.map.height.is_multiple_of(2);
+                    TilemapType::Hexagon(if odd_rows {
+                        HexCoordSystem::RowOdd
+                    } else {
+                        HexCoordSystem::RowEven
+                    })
+                }
+            },
+        }
+    }
+
+    /// Returns the [`AnimatedTile`] of a tile, if the tile has an animation which can be played
+    /// back.
+    ///
+    /// The frames have to be consecutive images of the tileset texture. Frames of different
+    /// durations are played back at their average duration.
+    pub fn animation(
+        &self,
+        tileset_index: usize,
+        tileset: &Tileset,
+        tile_id: ::tiled::TileId,
+    ) -> Option<AnimatedTile> {
+        let tile = tileset.get_tile(tile_id)?;
+        let frames = tile.animation.as_ref()?;
+        let indices = frames
+            .iter()
+            .map(|frame| self.texture_index(tileset_index, frame.tile_id))
+            .collect::<Option<Vec<_>>>()?;
+        let start = *indices.first()?;
+        if indices
+            .iter()
+            .enumerate()
+            .any(|(i, index)| *index != start + i as u32)
+        {
+            warn!(
+                "Skipping the animation of tile {tile_id} of tileset '{}' because its frames are not consecutive.",
+                tileset.name
+            );
+            return None;
+        }
+        let duration_ms = frames.iter().map(|frame| frame.duration).sum::<u32>();
+        Some(AnimatedTile {
+            start,
+            end: start + indices.len() as u32,
+            speed: if duration_ms > 0 {
+                1000.0 / duration_ms as f32
+            } else {
+                0.0
+            },
+        })
+    }
+}
+
+impl TiledMap {
+    /// Returns a [`TerrainBrush`] for the wang set of the given name in a tileset, made of the
+    /// tiles of the set whose images were loaded.
+    ///
+    /// Tiled numbers terrains from `1`, in the order of the colors of the wang set.
+    pub fn terrain_brush(&self, tileset_index: usize, wang_set_name: &str) -> Option<TerrainBrush> {
+        let tileset = self.map.tilesets().get(tileset_index)?;
+        let wang_set = tileset
+            .wang_sets
+            .iter()
+            .find(|wang_set| wang_set.name == wang_set_name)?;
+        let kind = match wang_set.wang_set_type {
+            WangSetType::Corner => TerrainKind::Corner,
+            WangSetType::Edge => TerrainKind::Edge,
+            WangSetType::Mixed => TerrainKind::Mixed,
+        };
+        // Ties are broken by the order of the tiles, so they are sorted by id.
+        let mut wang_tiles = wang_set.wang_tiles.iter().collect::<Vec<_>>();
+        wang_tiles.sort_by_key(|(tile_id, _)| **tile_id);
+        Some(TerrainBrush::new(
+            kind,
+            wang_tiles.into_iter().filter_map(|(tile_id, wang_tile)| {
+                let texture_index = self.texture_index(tileset_index, *tile_id)?;
+                Some((wang_tile.wang_id.0, TileTextureIndex(texture_index)))
+            }),
+        ))
+    }
+
+    /// Returns a [`Stamp`] of the tiles of the tile layer of the given name, e.g. of a prefab
+    /// room drawn as a small map, which is as large as the map.
+    ///
+    /// A tilemap only has a single texture, so only the tiles of the given tileset are part of the
+    /// stamp. Animations are left out.
+    pub fn stamp(&self, layer_name: &str, tileset_index: usize) -> Option<Stamp> {
+        let mut layers = Vec::new();
+        flatten_layers(self.map.layers(), &mut layers);
+        let layer = layers.iter().find(|layer| layer.name == layer_name)?;
+        let LayerType::Tiles(TileLayer::Finite(layer_data)) = layer.layer_type() else {
+            return None;
+        };
+        let map_size = TilemapSize::new(self.map.width, self.map.height);
+        Some(Stamp(TileDataLayer::from_fn(map_size, |tile_pos| {
+            // Tiled numbers rows from the top.
+            let (tiled_x, tiled_y) = (tile_pos.x as i32, (map_size.y - 1 - tile_pos.y) as i32);
+            let layer_tile = layer_data.get_tile(tiled_x, tiled_y)?;
+            let tile_data = layer_data.get_tile_data(tiled_x, tiled_y)?;
+            if layer_tile.tileset_index() != tileset_index {
+                return None;
+            }
+            let texture_index = self.texture_index(tileset_index, layer_tile.id())?;
+            Some(TileBundle {
+                texture_index: TileTextureIndex(texture_index),
+                flip: TileFlip {
+                    x: tile_data.flip_h,
+                    y: tile_data.flip_v,
+                    d: tile_data.flip_d,
+                },
+                color: TileColor(Color::WHITE.with_alpha(layer.opacity)),
+                ..Default::default()
+            })
+        })))
+    }
+}
+
+/// The map to spawn as children of this entity.
+///
+/// The [`TilemapRenderSettings`] of this entity are used for every layer.
+#[derive(Component, Default, Clone, Debug)]
+#[require(Transform, Visibility, TilemapRenderSettings, TiledMapEntities)]
+pub struct TiledMapHandle(pub Handle<TiledMap>);
+
+/// The layers and objects spawned for a [`TiledMapHandle`], which are despawned when the map is
+/// spawned again.
+#[derive(Component, Default, Clone, Debug)]
+pub struct TiledMapEntities(pub Vec<Entity>);
+
+/// A tilemap spawned for a tile layer of a [`TiledMap`].
+#[derive(Component, Clone, Debug)]
+pub struct TiledLayer {
+    /// The id of the layer in Tiled.
+    pub id: u32,
+    pub name: String,
+    /// The index of the tileset whose tiles the tilemap holds.
+    pub tileset_index: usize,
+}
+
+/// An object of an object layer of a [`TiledMap`].
+///
+/// The [`Transform`] of the object is its position in Tiled relative to the [`TiledMapHandle`].
+/// Positions are only converted exactly for orthogonal maps.
+#[derive(Component, Clone, Debug)]
+pub struct TiledObject {
+    /// The id of the object in Tiled.
+    pub id: u32,
+    pub name: String,
+    /// The class of the object.
+    pub user_type: String,
+    /// The id of the object layer in Tiled.
+    pub layer_id: u32,
+}
+
+/// An error while loading a [`TiledMap`].
+#[derive(Debug)]
+pub enum TiledMapLoaderError {
+    /// The map could not be read.
+    Io(std::io::Error),
+    /// A tileset or template referenced by the map could not be read.
+    ReadAssetBytes(ReadAssetBytesError),
+    /// The map could not be parsed.
+    Tiled(::tiled::Error),
+}
+
+impl fmt::Display for TiledMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not load Tiled file: {err}"),
+            Self::ReadAssetBytes(err) => write!(f, "could not load Tiled dependency: {err}"),
+            Self::Tiled(err) => write!(f, "could not parse Tiled file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TiledMapLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::ReadAssetBytes(err) => Some(err),
+            Self::Tiled(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for TiledMapLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ReadAssetBytesError> for TiledMapLoaderError {
+    fn from(err: ReadAssetBytesError) -> Self {
+        Self::ReadAssetBytes(err)
+    }
+}
+
+/// Serves the files read by the asset loader to the Tiled parser, which reads synchronously, and
+/// remembers the first file which has not been read yet.
+struct PrefetchedReader<'a> {
+    files: &'a HashMap<PathBuf, Arc<[u8]>>,
+    missing: &'a Mutex<Option<PathBuf>>,
+}
+
+impl ResourceReader for PrefetchedReader<'_> {
+    type Resource = Cursor<Arc<[u8]>>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> Result<Self::Resource, Self::Error> {
+        match self.files.get(path) {
+            Some(bytes) => Ok(Cursor::new(bytes.clone())),
+            None => {
+                let mut missing = self.missing.lock().unwrap();
+                missing.get_or_insert_with(|| path.to_path_buf());
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{} has not been read yet", path.display()),
+                ))
+            }
+        }
+    }
+}
+
+/// Loads `.tmx` files, along with the tilesets and templates they reference.
+pub struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = TiledMap;
+    type Settings = ();
+    type Error = TiledMapLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let map_path = load_context.path().to_path_buf();
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut files = HashMap::default();
+        files.insert(map_path.clone(), Arc::from(bytes));
+
+        // The parser is run again each time it needs a file which has not been read yet, e.g. an
+        // external tileset.
+        let map = loop {
+            let missing = Mutex::new(None);
+            let result = Loader::with_cache_and_reader(
+                DefaultResourceCache::new(),
+                PrefetchedReader {
+                    files: &files,
+                    missing: &missing,
+                },
+            )
+            .load_tmx_map(&map_path);
+            let missing = missing.into_inner().unwrap();
+            let path = match (result, missing) {
+                (Ok(map), _) => break map,
+                (Err(err), None) => return Err(TiledMapLoaderError::Tiled(err)),
+                (Err(err), Some(path)) if files.contains_key(&path) => {
+                    return Err(TiledMapLoaderError::Tiled(err));
+                }
+                (Err(_), Some(path)) => path,
+            };
+            let bytes = load_context
+                .read_asset_bytes(AssetPath::from(path.clone()))
+                .await?;
+            files.insert(path, Arc::from(bytes));
+        };
+
+        let mut tilemap_textures = HashMap::default();
+        #[cfg(not(feature = "atlas"))]
+        let mut tile_image_offsets = HashMap::default();
+
+        // Image paths are relative to the assets folder already, as the parser resolves them
+        // relative to the map or tileset which references them.
+        for (tileset_index, tileset) in map.tilesets().iter().enumerate() {
+            let tilemap_texture = match &tileset.image {
+                Some(image) => TilemapTexture::Single(load_context.load(image.source.clone())),
+                #[cfg(feature = "atlas")]
+                None => {
+                    info!(
+                        "Skipping image collection tileset '{}' which is incompatible with the atlas feature",
+                        tileset.name
+                    );
+                    continue;
+                }
+                #[cfg(not(feature = "atlas"))]
+                None => {
+                    let mut tile_images = Vec::new();
+                    for (tile_id, tile) in tileset.tiles() {
+                        if let Some(image) = &tile.image {
+                            tile_image_offsets
+                                .insert((tileset_index, tile_id), tile_images.len() as u32);
+                            tile_images.push(load_context.load(image.source.clone()));
+                        }
+                    }
+                    TilemapTexture::Vector(tile_images)
+                }
+            };
+            tilemap_textures.insert(tileset_index, tilemap_texture);
+        }
+
+        Ok(TiledMap {
+            map,
+            tilemap_textures,
+            #[cfg(not(feature = "atlas"))]
+            tile_image_offsets,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+/// Appends the layers, and the layers inside of group layers, in drawing order.
+fn flatten_layers<'map>(layers: impl Iterator<Item = Layer<'map>>, out: &mut Vec<Layer<'map>>) {
+    for layer in layers {
+        if let LayerType::Group(group) = layer.layer_type() {
+            flatten_layers(group.layers(), out);
+        } else {
+            out.push(layer);
+        }
+    }
+}
+
+/// Spawns the maps of new [`TiledMapHandle`]s, and spawns maps again when they change.
+#[allow(clippy::type_complexity)]
+fn spawn_tiled_maps(
+    mut commands: Commands,
+    mut map_events: MessageReader<AssetEvent<TiledMap>>,
+    maps: Res<Assets<TiledMap>>,
+    mut map_query: Query<(
+        Entity,
+        Ref<TiledMapHandle>,
+        &mut TiledMapEntities,
+        &TilemapRenderSettings,
+    )>,
+) {
+    let changed_maps = map_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (map_entity, handle, mut spawned, render_settings) in map_query.iter_mut() {
+        if !handle.is_changed() && !changed_maps.contains(&handle.0.id()) {
+            continue;
+        }
+        let Some(tiled_map) = maps.get(&handle.0) else {
+            continue;
+        };
+
+        for entity in spawned.0.drain(..) {
+            commands.entity(entity).try_despawn();
+        }
+
+        let map = &tiled_map.map;
+        let map_size = TilemapSize::new(map.width, map.height);
+        let grid_size = TilemapGridSize::new(map.tile_width as f32, map.tile_height as f32);
+        let map_type = tiled_map.tilemap_type();
+        let map_extent = Vec2::new(
+            (map.width * map.tile_width) as f32,
+            (map.height * map.tile_height) as f32,
+        );
+
+        let mut layers = Vec::new();
+        flatten_layers(map.layers(), &mut layers);
+
+        for (layer_index, layer) in layers.iter().enumerate() {
+            let z = layer_index as f32;
+            let offset = Vec2::new(layer.offset_x, -layer.offset_y);
+            let visibility = if layer.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+
+            match layer.layer_type() {
+                LayerType::Tiles(TileLayer::Finite(layer_data)) => {
+                    // A tilemap only has a single texture, so a layer is split into one tilemap
+                    // per tileset it uses.
+                    let mut tilemaps = HashMap::<usize, (Entity, TileStorage)>::default();
+                    for x in 0..map.width {
+                        for y in 0..map.height {
+                            // Tiled numbers rows from the top.
+                            let (tiled_x, tiled_y) = (x as i32, (map.height - 1 - y) as i32);
+                            let Some(layer_tile) = layer_data.get_tile(tiled_x, tiled_y) else {
+                                continue;
+                            };
+                            let Some(tile_data) = layer_data.get_tile_data(tiled_x, tiled_y) else {
+                                continue;
+                            };
+                            let tileset_index = layer_tile.tileset_index();
+                            let Some(texture_index) =
+                                tiled_map.texture_index(tileset_index, layer_tile.id())
+                            else {
+                                continue;
+                            };
+
+                            let (tilemap_entity, storage) =
+                                tilemaps.entry(tileset_index).or_insert_with(|| {
+                                    (commands.spawn_empty().id(), TileStorage::empty(map_size))
+                                });
+                            let tile_pos = TilePos::new(x, y);
+                            let mut tile = commands.spawn((
+                                TileBundle {
+                                    position: tile_pos,
+                                    tilemap_id: TilemapId(*tilemap_entity),
+                                    texture_index: TileTextureIndex(texture_index),
+                                    flip: TileFlip {
+                                        x: tile_data.flip_h,
+                                        y: tile_data.flip_v,
+                                        d: tile_data.flip_d,
+                                    },
+                                    color: TileColor(Color::WHITE.with_alpha(layer.opacity)),
+                                    ..Default::default()
+                                },
+                                ChildOf(*tilemap_entity),
+                            ));
+                            if let Some(animation) = tiled_map.animation(
+                                tileset_index,
+                                layer_tile.get_tileset(),
+                                layer_tile.id(),
+                            ) {
+                                tile.insert(animation);
+                            }
+                            storage.set(&tile_pos, tile.id());
+                        }
+                    }
+
+                    for (tileset_index, (tilemap_entity, storage)) in tilemaps {
+                        let tileset = &map.tilesets()[tileset_index];
+                        commands.entity(tilemap_entity).insert((
+                            TilemapBundle {
+                                grid_size,
+                                size: map_size,
+                                storage,
+                                texture: tiled_map.tilemap_textures[&tileset_index].clone(),
+                                tile_size: TilemapTileSize::new(
+                                    tileset.tile_width as f32,
+                                    tileset.tile_height as f32,
+                                ),
+                                spacing: TilemapSpacing::new(
+                                    tileset.spacing as f32,
+                                    tileset.spacing as f32,
+                                ),
+                                anchor: TilemapAnchor::Center,
+                                transform: Transform::from_translation(offset.extend(z)),
+                                map_type,
+                                render_settings: *render_settings,
+                                visibility,
+                                ..Default::default()
+                            },
+                            TiledLayer {
+                                id: layer.id(),
+                                name: layer.name.clone(),
+                                tileset_index,
+                            },
+                            ChildOf(map_entity),
+                        ));
+                        spawned.0.push(tilemap_entity);
+                    }
+                }
+                LayerType::Objects(object_layer) => {
+                    for object in object_layer.objects() {
+                        // Object positions are in pixels from the top left corner of the map.
+                        let position =
+                            Vec2::new(object.x - map_extent.x / 2.0, map_extent.y / 2.0 - object.y)
+                                + offset;
+                        let entity = commands
+                            .spawn((
+                                TiledObject {
+                                    id: object.id(),
+                                    name: object.name.clone(),
+                                    user_type: object.user_type.clone(),
+                                    layer_id: layer.id(),
+                                },
+                                Transform::from_translation(position.extend(z)).with_rotation(
+                                    Quat::from_rotation_z(-object.rotation.to_radians()),
+                                ),
+                                if object.visible {
+                                    visibility
+                                } else {
+                                    Visibility::Hidden
+                                },
+                                ChildOf(map_entity),
+                            ))
+                            .id();
+                        spawned.0.push(entity);
+                    }
+                }
+                _ => info!(
+                    "Skipping layer '{}' because only finite tile layers and object layers are supported.",
+                    layer.name
+                ),
+            }
+        }
+    }
+}
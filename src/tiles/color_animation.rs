@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use bevy::color::Mix;
+use bevy::prelude::*;
+
+use super::TileColor;
+use crate::region_of_interest::OutsideRegionOfInterest;
+
+/// How a [`TileColorAnimation`] continues once it reaches the end of its gradient.
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorAnimationRepeat {
+    /// Plays the gradient once and holds its last color.
+    #[default]
+    Once,
+    /// Restarts the gradient from the beginning.
+    Loop,
+    /// Plays the gradient forwards, then backwards, and so on.
+    PingPong,
+}
+
+/// Animates the [`TileColor`] of a tile along a gradient, e.g. for pulsing highlights, damage
+/// flashes or fade-ins.
+///
+/// The color is evaluated once per frame, and only written when it changes, so finished
+/// animations cost nothing to render. Remove the component to stop the animation; the tile keeps
+/// the color it had last.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileColorAnimation {
+    /// The stops of the gradient, as positions in `0.0..=1.0` and the colors at those positions,
+    /// sorted by position.
+    pub gradient: Vec<(f32, Color)>,
+    /// The time it takes to play the gradient once.
+    pub duration: Duration,
+    pub repeat: ColorAnimationRepeat,
+    /// The time the animation has been playing for.
+    pub elapsed: Duration,
+}
+
+impl TileColorAnimation {
+    /// Creates an animation through the given gradient stops. See
+    /// [`gradient`](Self::gradient).
+    pub fn new(
+        gradient: Vec<(f32, Color)>,
+        duration: Duration,
+        repeat: ColorAnimationRepeat,
+    ) -> Self {
+        Self {
+            gradient,
+            duration,
+            repeat,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Creates an animation which fades back and forth between two colors, with `period` being
+    /// the time it takes to fade in one direction.
+    pub fn pulse(from: Color, to: Color, period: Duration) -> Self {
+        Self::new(
+            vec![(0.0, from), (1.0, to)],
+            period,
+            ColorAnimationRepeat::PingPong,
+        )
+    }
+
+    /// Creates an animation which starts at `flash` and fades back to `color`.
+    pub fn flash(flash: Color, color: Color, duration: Duration) -> Self {
+        Self::new(
+            vec![(0.0, flash), (1.0, color)],
+            duration,
+            ColorAnimationRepeat::Once,
+        )
+    }
+
+    /// Creates an animation which fades `color` in from fully transparent.
+    pub fn fade_in(color: Color, duration: Duration) -> Self {
+        Self::new(
+            vec![(0.0, color.with_alpha(0.0)), (1.0, color)],
+            duration,
+            ColorAnimationRepeat::Once,
+        )
+    }
+
+    /// Returns `true` if the animation reached its end and no longer changes the color.
+    pub fn is_finished(&self) -> bool {
+        self.repeat == ColorAnimationRepeat::Once && self.elapsed >= self.duration
+    }
+
+    /// Returns the position in the gradient after the animation has been playing for
+    /// [`elapsed`](Self::elapsed).
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        match self.repeat {
+            ColorAnimationRepeat::Once => t.min(1.0),
+            ColorAnimationRepeat::Loop => t.fract(),
+            ColorAnimationRepeat::PingPong => 1.0 - (t % 2.0 - 1.0).abs(),
+        }
+    }
+
+    /// Returns the color of the gradient at the given position.
+    pub fn sample(&self, position: f32) -> Color {
+        let Some(&(first_position, first_color)) = self.gradient.first() else {
+            return Color::WHITE;
+        };
+        if position <= first_position {
+            return first_color;
+        }
+        for window in self.gradient.windows(2) {
+            let [(start, start_color), (end, end_color)] = *window else {
+                continue;
+            };
+            if position <= end {
+                let t = if end > start {
+                    (position - start) / (end - start)
+                } else {
+                    1.0
+                };
+                return start_color.mix(&end_color, t);
+            }
+        }
+        self.gradient
+            .last()
+            .map_or(first_color, |&(_, color)| color)
+    }
+}
+
+/// Writes the [`TileColor`] of every [`TileColorAnimation`], then advances it. Tiles outside of
+/// the regions of interest are skipped.
+pub(crate) fn animate_tile_colors(
+    time: Res<Time>,
+    mut query: Query<(&mut TileColorAnimation, &mut TileColor), Without<OutsideRegionOfInterest>>,
+) {
+    for (mut animation, mut tile_color) in query.iter_mut() {
+        // Animations which are played once stop the frame after they reached their end, once
+        // their last color was shown. They are only evaluated again if they were changed, e.g.
+        // restarted.
+        if animation.is_finished()
+            && animation.elapsed > animation.duration
+            && !animation.is_changed()
+        {
+            continue;
+        }
+        // The color is sampled before advancing, so the first color of the gradient is shown.
+        let color = animation.sample(animation.progress());
+        if tile_color.0 != color {
+            tile_color.0 = color;
+        }
+        let elapsed = animation.elapsed + time.delta();
+        animation.elapsed = if animation.repeat == ColorAnimationRepeat::Once
+            && animation.elapsed < animation.duration
+        {
+            // Stopping at the end makes sure the last color is shown for a frame.
+            elapsed.min(animation.duration)
+        } else {
+            elapsed
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn animations_start_at_their_first_color() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let flash = TileColorAnimation::flash(
+            Color::srgb(1.0, 0.0, 0.0),
+            Color::srgb(0.0, 0.0, 1.0),
+            Duration::from_secs(1),
+        );
+        let tile = world.spawn((flash.clone(), TileColor::default())).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(animate_tile_colors);
+
+        let mut colors = Vec::new();
+        for _ in 0..3 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(500));
+            schedule.run(&mut world);
+            colors.push(world.get::<TileColor>(tile).unwrap().0);
+        }
+        assert_eq!(
+            colors,
+            [flash.sample(0.0), flash.sample(0.5), flash.sample(1.0)]
+        );
+        assert_eq!(colors[2], Color::srgb(0.0, 0.0, 1.0));
+        assert!(world.get::<TileColorAnimation>(tile).unwrap().is_finished());
+
+        // Finished animations leave the color alone.
+        world.get_mut::<TileColor>(tile).unwrap().0 = Color::WHITE;
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<TileColor>(tile).unwrap().0, Color::WHITE);
+    }
+}
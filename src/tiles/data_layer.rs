@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::map::TilemapSize;
+
+use super::TilePos;
+
+/// Dense per-tile data (e.g. movement costs, light levels or generation noise) for a tilemap of a
+/// given size.
+///
+/// Unlike storing the data as a component on every tile entity, a layer can be read and written
+/// without touching the ECS, which makes it a good fit for algorithms that sweep the whole map.
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct TileDataLayer<T: Send + Sync + 'static> {
+    data: Vec<T>,
+    pub size: TilemapSize,
+}
+
+impl<T: Clone + Send + Sync + 'static> TileDataLayer<T> {
+    /// Creates a new layer with every tile set to `value`.
+    pub fn filled(size: TilemapSize, value: T) -> Self {
+        Self {
+            data: vec![value; size.count()],
+            size,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TileDataLayer<T> {
+    /// Creates a new layer, computing the value of every tile with `f`.
+    pub fn from_fn(size: TilemapSize, mut f: impl FnMut(TilePos) -> T) -> Self {
+        let mut data = Vec::with_capacity(size.count());
+        for y in 0..size.y {
+            for x in 0..size.x {
+                data.push(f(TilePos { x, y }));
+            }
+        }
+        Self { data, size }
+    }
+
+    /// Gets the value of the given tile position.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the layer.
+    pub fn get(&self, tile_pos: &TilePos) -> &T {
+        &self.data[tile_pos.to_index(&self.size)]
+    }
+
+    /// Gets the value of the given tile position, if it lies within the extents of the layer.
+    pub fn checked_get(&self, tile_pos: &TilePos) -> Option<&T> {
+        if tile_pos.within_map_bounds(&self.size) {
+            Some(&self.data[tile_pos.to_index(&self.size)])
+        } else {
+            None
+        }
+    }
+
+    /// Gets a mutable reference to the value of the given tile position.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the layer.
+    pub fn get_mut(&mut self, tile_pos: &TilePos) -> &mut T {
+        &mut self.data[tile_pos.to_index(&self.size)]
+    }
+
+    /// Sets the value of the given tile position.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the layer.
+    pub fn set(&mut self, tile_pos: &TilePos, value: T) {
+        self.data[tile_pos.to_index(&self.size)] = value;
+    }
+
+    /// Returns an iterator over all tile positions in the layer, together with their values.
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> {
+        let width = self.size.x;
+        self.data.iter().enumerate().map(move |(index, value)| {
+            let index = index as u32;
+            (TilePos::new(index % width, index / width), value)
+        })
+    }
+
+    /// Returns an iterator over all values in the layer, in the same order as
+    /// [`TilePos::to_index`].
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
+
+    /// Returns a mutable iterator over all values in the layer, in the same order as
+    /// [`TilePos::to_index`].
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.data.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_store_a_value_per_tile() {
+        let size = TilemapSize::new(3, 2);
+        let mut layer = TileDataLayer::from_fn(size, |tile_pos| tile_pos.x + 10 * tile_pos.y);
+        assert_eq!(*layer.get(&TilePos::new(2, 1)), 12);
+        assert_eq!(layer.checked_get(&TilePos::new(3, 0)), None);
+
+        layer.set(&TilePos::new(0, 1), 7);
+        *layer.get_mut(&TilePos::new(1, 0)) += 5;
+        assert_eq!(
+            layer.values().copied().collect::<Vec<_>>(),
+            [0, 6, 2, 7, 11, 12]
+        );
+        // Positions are yielded in the order of the values.
+        assert!(
+            layer
+                .iter()
+                .all(|(tile_pos, value)| layer.get(&tile_pos) == value)
+        );
+        assert_eq!(layer.iter().last(), Some((TilePos::new(2, 1), &12)));
+
+        layer.values_mut().for_each(|value| *value = 0);
+        assert_eq!(layer, TileDataLayer::filled(size, 0));
+    }
+}
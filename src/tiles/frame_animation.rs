@@ -0,0 +1,186 @@
+use bevy::prelude::{
+    Component, DetectChangesMut, Query, Reflect, ReflectComponent, RemovedComponents, Resource,
+};
+
+use super::{AnimationPhase, TileTextureIndex};
+
+/// A frame of an animation in the [`TileAnimationTable`].
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationFrame {
+    /// The frame index in the tilemap atlas/array.
+    pub texture_index: u32,
+    /// How long the frame is shown, in seconds at a speed of `1.0`.
+    pub duration: f32,
+}
+
+impl AnimationFrame {
+    pub const fn new(texture_index: u32, duration: f32) -> Self {
+        Self {
+            texture_index,
+            duration,
+        }
+    }
+}
+
+/// Identifies an animation added to the [`TileAnimationTable`].
+#[derive(Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileAnimationId(u32);
+
+impl TileAnimationId {
+    /// The index of the first texel of the animation in the table uploaded to the GPU.
+    pub(crate) fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The animations played by [`TileFrameAnimation`]s, which are made of arbitrary frames of the
+/// tilemap atlas/array, each shown for its own duration.
+///
+/// The table is uploaded to the GPU whenever it changes, and shared by all tilemaps. Animations
+/// cannot be removed from it; it is meant to be filled once, e.g. when the tileset is loaded.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct TileAnimationTable {
+    /// For each animation, a header of `[frame count, total duration]`, followed by
+    /// `[texture index, end time]` for each frame.
+    pub(crate) texels: Vec<[f32; 2]>,
+}
+
+impl TileAnimationTable {
+    /// Adds an animation, which loops through the given frames in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no frames, or if the frames do not last for a positive duration.
+    pub fn add(&mut self, frames: impl IntoIterator<Item = AnimationFrame>) -> TileAnimationId {
+        let id = TileAnimationId(self.texels.len() as u32);
+        self.texels.push([0.0, 0.0]);
+        let mut end_time = 0.0;
+        for frame in frames {
+            end_time += frame.duration.max(0.0);
+            self.texels.push([frame.texture_index as f32, end_time]);
+        }
+        let frame_count = self.texels.len() - id.0 as usize - 1;
+        assert!(
+            frame_count > 0 && end_time > 0.0,
+            "tile animations need at least one frame and a positive duration"
+        );
+        self.texels[id.0 as usize] = [frame_count as f32, end_time];
+        id
+    }
+
+    /// Returns the frames of the given animation.
+    pub fn frames(&self, id: TileAnimationId) -> impl Iterator<Item = AnimationFrame> + '_ {
+        let [frame_count, _] = self.texels[id.0 as usize];
+        let start = id.0 as usize + 1;
+        let mut start_time = 0.0;
+        self.texels[start..start + frame_count as usize].iter().map(
+            move |[texture_index, end_time]| {
+                let duration = end_time - start_time;
+                start_time = *end_time;
+                AnimationFrame::new(*texture_index as u32, duration)
+            },
+        )
+    }
+
+    /// Returns how long one loop of the given animation lasts, in seconds at a speed of `1.0`.
+    pub fn duration(&self, id: TileAnimationId) -> f32 {
+        self.texels[id.0 as usize][1]
+    }
+
+    /// Returns the frame shown by a [`TileFrameAnimation`] at the given time (in seconds, wrapped
+    /// like [`Time::elapsed_secs_wrapped`](bevy::time::Time::elapsed_secs_wrapped)), the same
+    /// way the GPU picks it.
+    pub fn frame_at(
+        &self,
+        animation: &TileFrameAnimation,
+        elapsed_secs_wrapped: f32,
+        phase: AnimationPhase,
+    ) -> u32 {
+        let id = animation.animation.0 as usize;
+        let [frame_count, duration] = self.texels[id];
+        let loops = elapsed_secs_wrapped * animation.speed / duration + phase.0;
+        let time = (loops - loops.floor()) * duration;
+        let frames = &self.texels[id + 1..id + 1 + frame_count as usize];
+        let frame = frames
+            .iter()
+            .find(|[_, end_time]| time < *end_time)
+            .or(frames.last())
+            .unwrap();
+        frame[0] as u32
+    }
+}
+
+/// Plays an animation from the [`TileAnimationTable`] on a tile, in place of its
+/// [`TileTextureIndex`](super::TileTextureIndex).
+///
+/// Unlike [`AnimatedTile`](super::AnimatedTile), the frames need not be next to each other in the
+/// tilemap atlas/array, and each frame has its own duration. If a tile has both, this component
+/// is used. [`AnimationPhase`] and [`AnimationPaused`](super::AnimationPaused) work the same for
+/// both.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileFrameAnimation {
+    pub animation: TileAnimationId,
+    /// The speed the animation plays back at; at `1.0` the frames last for their durations.
+    pub speed: f32,
+}
+
+impl TileFrameAnimation {
+    /// Plays the given animation at its normal speed.
+    pub fn new(animation: TileAnimationId) -> Self {
+        Self {
+            animation,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Makes tiles whose [`TileFrameAnimation`] was removed be extracted again, so they stop
+/// animating.
+pub(crate) fn update_removed_frame_animations(
+    mut removed: RemovedComponents<TileFrameAnimation>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_picked_by_their_durations() {
+        let mut table = TileAnimationTable::default();
+        let torch = table.add([AnimationFrame::new(7, 1.0), AnimationFrame::new(7, 1.0)]);
+        let blink = table.add([
+            AnimationFrame::new(12, 1.5),
+            AnimationFrame::new(3, 0.25),
+            AnimationFrame::new(40, 0.25),
+        ]);
+        assert_eq!(table.duration(torch), 2.0);
+        assert_eq!(table.duration(blink), 2.0);
+        assert_eq!(
+            table.frames(blink).collect::<Vec<_>>(),
+            vec![
+                AnimationFrame::new(12, 1.5),
+                AnimationFrame::new(3, 0.25),
+                AnimationFrame::new(40, 0.25),
+            ]
+        );
+
+        let blink = TileFrameAnimation::new(blink);
+        let frame_at = |secs, phase| table.frame_at(&blink, secs, AnimationPhase(phase));
+        assert_eq!(frame_at(1.4, 0.0), 12);
+        assert_eq!(frame_at(1.6, 0.0), 3);
+        assert_eq!(frame_at(1.9, 0.0), 40);
+        assert_eq!(frame_at(3.6, 0.0), 3);
+        assert_eq!(frame_at(0.6, 0.5), 3);
+    }
+}
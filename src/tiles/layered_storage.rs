@@ -0,0 +1,308 @@
+use std::ops::DerefMut;
+
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+
+use crate::map::TilemapSize;
+
+use super::{TilePos, TileStorage, TileStore, TileTextureIndex};
+
+/// Stores several tile entities per position, one in each layer, e.g. for ground, decoration and
+/// overlay tiles, so a simple multi-layer map does not need a tilemap entity per layer.
+///
+/// Each layer is a [`TileStorage`], and the renderer looks up which one holds a tile when the tile
+/// changes. It draws every layer in chunks of its own, moved along the Z axis by the
+/// [`z_offset`](Self::z_offset) of the layer, so higher layers are drawn over lower ones.
+///
+/// A tilemap with a `LayeredTileStorage` is rendered from it, so the empty default `TileStorage`
+/// of the tilemap bundles can be left in place. Its [`TilemapSize`], texture, grid and anchor are
+/// shared by all of its layers.
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component, MapEntities)]
+#[component(map_entities)]
+pub struct LayeredTileStorage {
+    size: TilemapSize,
+    layers: Vec<TileStorage>,
+    z_offsets: Vec<f32>,
+    /// Tiles [`set`](Self::set) or [`remove`](Self::remove)d since the renderer last looked at
+    /// them, which may have moved to another layer.
+    #[reflect(ignore)]
+    moved: Vec<Entity>,
+}
+
+impl MapEntities for LayeredTileStorage {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for layer in &mut self.layers {
+            layer.map_entities(entity_mapper);
+        }
+        for entity in &mut self.moved {
+            *entity = entity_mapper.get_mapped(*entity);
+        }
+    }
+}
+
+impl LayeredTileStorage {
+    /// Creates a new tile storage with `layer_count` empty layers.
+    ///
+    /// Layer `i` is drawn `i` units above the tilemap, the way separate tilemaps are usually
+    /// stacked.
+    pub fn empty(size: TilemapSize, layer_count: usize) -> Self {
+        Self {
+            size,
+            layers: vec![TileStorage::empty(size); layer_count],
+            z_offsets: (0..layer_count).map(|layer| layer as f32).collect(),
+            moved: Vec::new(),
+        }
+    }
+
+    /// Returns the size of the tilemap the layers cover.
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// Returns the number of layers.
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns the tile storage of the given layer.
+    ///
+    /// Panics if `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn layer(&self, layer: usize) -> &TileStorage {
+        &self.layers[layer]
+    }
+
+    /// Returns the tile storage of the given layer, to fill it with the helpers which take a
+    /// [`TileStore`](super::TileStore).
+    ///
+    /// Tiles spawned into it are drawn in the layer. To move a tile which is already drawn from
+    /// another layer, use [`set`](Self::set) or [`layer_store`](Self::layer_store), which make the
+    /// renderer look the tile up again.
+    ///
+    /// Panics if `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn layer_mut(&mut self, layer: usize) -> &mut TileStorage {
+        &mut self.layers[layer]
+    }
+
+    /// Returns the given layer as a [`TileStore`] which goes through [`set`](Self::set) and
+    /// [`remove`](Self::remove), to change tiles which are already drawn with the helpers which
+    /// take one.
+    ///
+    /// Panics if `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn layer_store(&mut self, layer: usize) -> LayerStore<&mut Self> {
+        LayerStore::new(self, layer)
+    }
+
+    /// Returns an iterator over the layers, from the bottom up.
+    pub fn layers(&self) -> impl Iterator<Item = &TileStorage> {
+        self.layers.iter()
+    }
+
+    /// Gets the tile entity for the given tile position in the given layer, if there is one.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map,
+    /// or `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn get(&self, tile_pos: &TilePos, layer: usize) -> Option<Entity> {
+        self.layers[layer].get(tile_pos)
+    }
+
+    /// Gets the tile entity for the given tile position in the given layer, if the position and
+    /// the layer exist and there is an entity stored there.
+    pub fn checked_get(&self, tile_pos: &TilePos, layer: usize) -> Option<Entity> {
+        self.layers.get(layer)?.checked_get(tile_pos)
+    }
+
+    /// Sets the tile entity for the given tile position in the given layer, replacing any entity
+    /// already there.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map,
+    /// or `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn set(&mut self, tile_pos: &TilePos, layer: usize, tile_entity: Entity) {
+        self.layers[layer].set(tile_pos, tile_entity);
+        self.moved.push(tile_entity);
+    }
+
+    /// Removes the entity stored at the given tile position in the given layer, and returns it.
+    ///
+    /// A removed tile which is not despawned is drawn in the bottom layer, until it is set again.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map,
+    /// or `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn remove(&mut self, tile_pos: &TilePos, layer: usize) -> Option<Entity> {
+        let tile_entity = self.layers[layer].remove(tile_pos);
+        self.moved.extend(tile_entity);
+        tile_entity
+    }
+
+    /// Returns the tiles stacked at the given tile position, from the bottom layer up, with
+    /// `None` for the layers without a tile there.
+    pub fn stack(&self, tile_pos: &TilePos) -> impl Iterator<Item = Option<Entity>> + '_ {
+        let tile_pos = *tile_pos;
+        self.layers
+            .iter()
+            .map(move |layer| layer.checked_get(&tile_pos))
+    }
+
+    /// Returns an iterator over the stored entities, with their layer and position.
+    pub fn iter_some(&self) -> impl Iterator<Item = (usize, TilePos, Entity)> + '_ {
+        self.layers.iter().enumerate().flat_map(|(layer, storage)| {
+            storage
+                .iter_some()
+                .map(move |(tile_pos, entity)| (layer, tile_pos, entity))
+        })
+    }
+
+    /// Returns how far the tiles of the given layer are moved along the Z axis, or `0.0` if
+    /// there is no such layer.
+    pub fn z_offset(&self, layer: usize) -> f32 {
+        self.z_offsets.get(layer).copied().unwrap_or(0.0)
+    }
+
+    /// Sets how far the tiles of the given layer are moved along the Z axis, relative to the
+    /// tilemap.
+    ///
+    /// On tilemaps using [`TilemapRenderMode::YSort`](crate::map::TilemapRenderMode::YSort), the
+    /// offset is added to the depth of the tiles like a [`TileZOffset`](super::TileZOffset).
+    ///
+    /// Panics if `layer` is not less than the [`layer_count`](Self::layer_count).
+    pub fn set_z_offset(&mut self, layer: usize, z_offset: f32) {
+        self.z_offsets[layer] = z_offset;
+    }
+
+    /// Returns the Z offsets of all layers, from the bottom up.
+    pub fn z_offsets(&self) -> &[f32] {
+        &self.z_offsets
+    }
+
+    /// Adds an empty layer on top of the others, `z_offset` units above the tilemap, and returns
+    /// its index.
+    pub fn push_layer(&mut self, z_offset: f32) -> usize {
+        self.layers.push(TileStorage::empty(self.size));
+        self.z_offsets.push(z_offset);
+        self.layers.len() - 1
+    }
+}
+
+/// One layer of a [`LayeredTileStorage`], as a [`TileStore`], made by
+/// [`LayeredTileStorage::layer_store`] or from a `Mut<LayeredTileStorage>` with
+/// [`LayerStore::new`].
+///
+/// Unlike the [`TileStorage`] of [`LayeredTileStorage::layer_mut`], it changes the layer through
+/// [`LayeredTileStorage::set`] and [`LayeredTileStorage::remove`], so tiles moved between layers
+/// are drawn in their new layer.
+pub struct LayerStore<S> {
+    storage: S,
+    layer: usize,
+}
+
+impl<S: DerefMut<Target = LayeredTileStorage>> LayerStore<S> {
+    /// Panics if `layer` is not less than the [`layer_count`](LayeredTileStorage::layer_count) of
+    /// `storage`.
+    pub fn new(storage: S, layer: usize) -> Self {
+        assert!(
+            layer < storage.layer_count(),
+            "layer {layer} of a storage with {} layers",
+            storage.layer_count()
+        );
+        Self { storage, layer }
+    }
+
+    /// Returns the index of the layer.
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+}
+
+impl<S: DerefMut<Target = LayeredTileStorage>> TileStore for LayerStore<S> {
+    fn size(&self) -> TilemapSize {
+        self.storage.size()
+    }
+
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        self.storage.get(tile_pos, self.layer)
+    }
+
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        self.storage.set(tile_pos, self.layer, tile_entity);
+    }
+
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        self.storage.remove(tile_pos, self.layer)
+    }
+
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_> {
+        TileStore::iter_some(self.storage.layer(self.layer))
+    }
+}
+
+/// Makes the tiles moved with [`LayeredTileStorage::set`] and [`LayeredTileStorage::remove`] be
+/// extracted again, so they are drawn in their new layer.
+pub(crate) fn sync_layered_tile_storages(
+    mut storages: Query<&mut LayeredTileStorage, Changed<LayeredTileStorage>>,
+    mut tiles: Query<&mut TileTextureIndex>,
+) {
+    for mut storage in storages.iter_mut() {
+        for tile_entity in storage.bypass_change_detection().moved.drain(..) {
+            if let Ok(mut texture_index) = tiles.get_mut(tile_entity) {
+                texture_index.set_changed();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_stack_tiles_at_the_same_position() {
+        let mut storage = LayeredTileStorage::empty(TilemapSize::new(4, 4), 2);
+        let ground = Entity::from_raw_u32(1).unwrap();
+        let tree = Entity::from_raw_u32(2).unwrap();
+        let tile_pos = TilePos::new(1, 2);
+        storage.set(&tile_pos, 0, ground);
+        storage.set(&tile_pos, 1, tree);
+        let overlay = storage.push_layer(5.0);
+
+        assert_eq!(
+            storage.stack(&tile_pos).collect::<Vec<_>>(),
+            [Some(ground), Some(tree), None]
+        );
+        assert_eq!(storage.z_offsets(), [0.0, 1.0, 5.0]);
+        assert_eq!(storage.layer(overlay).size, TilemapSize::new(4, 4));
+        assert_eq!(storage.checked_get(&tile_pos, 3), None);
+        assert_eq!(storage.remove(&tile_pos, 0), Some(ground));
+        assert_eq!(
+            storage.iter_some().collect::<Vec<_>>(),
+            [(1, tile_pos, tree)]
+        );
+    }
+
+    #[test]
+    fn tiles_moved_through_layer_stores_are_drawn_again() {
+        let mut storage = LayeredTileStorage::empty(TilemapSize::new(4, 4), 2);
+        let tile = Entity::from_raw_u32(1).unwrap();
+        let tile_pos = TilePos::new(3, 0);
+        storage.layer_mut(0).set(&tile_pos, tile);
+        assert!(storage.moved.is_empty());
+
+        // Moving the tile up a layer goes through `remove` and `set`, like the helpers do.
+        let moved = storage.layer_store(0).checked_remove(&tile_pos);
+        assert_eq!(moved, Some(tile));
+        storage.layer_store(1).checked_set(&tile_pos, tile);
+        assert_eq!(
+            storage.stack(&tile_pos).collect::<Vec<_>>(),
+            [None, Some(tile)]
+        );
+        assert_eq!(storage.moved, [tile, tile]);
+        assert_eq!(
+            storage.layer_store(1).iter_some().collect::<Vec<_>>(),
+            [(tile_pos, tile)]
+        );
+    }
+}
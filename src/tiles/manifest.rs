@@ -0,0 +1,373 @@
+use std::any::{Any, TypeId};
+use std::sync::Arc;
+#[cfg(feature = "serde")]
+use std::{collections::BTreeMap, fmt, sync::RwLock};
+
+#[cfg(feature = "serde")]
+use bevy::asset::{AssetLoader, LoadContext, io::Reader, ron};
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use super::TileTextureIndex;
+
+/// Stores typed properties (e.g. footstep sounds, movement costs) for the textures of a tileset,
+/// keyed by [`TileTextureIndex`].
+///
+/// This lets gameplay and audio data ride along with the tiles that use a texture, instead of
+/// living in parallel hash maps. Any `Send + Sync + 'static` type can be used as a property:
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::{TileTextureIndex, TilesetManifest};
+/// struct FootstepSound(&'static str);
+///
+/// let mut manifest = TilesetManifest::default();
+/// manifest.insert(TileTextureIndex(3), FootstepSound("gravel.ogg"));
+///
+/// let sound = manifest.get::<FootstepSound>(TileTextureIndex(3)).unwrap();
+/// assert_eq!(sound.0, "gravel.ogg");
+/// assert!(manifest.get::<FootstepSound>(TileTextureIndex(4)).is_none());
+/// ```
+///
+/// It is usually added as a component to the tilemap entity, so it can be looked up through a
+/// tile's [`TilemapId`](crate::map::TilemapId). With the `serde` feature, manifests can also be
+/// loaded as assets from `.tileset.ron` files by the `TilesetManifestLoader`, with the property
+/// types registered through `App::register_tile_property`.
+#[derive(Component, Asset, TypePath, Default, Clone)]
+pub struct TilesetManifest {
+    properties: HashMap<TypeId, HashMap<TileTextureIndex, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl std::fmt::Debug for TilesetManifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TilesetManifest")
+            .field("property_types", &self.properties.len())
+            .finish()
+    }
+}
+
+impl TilesetManifest {
+    /// Sets the property of type `T` for the given texture index.
+    ///
+    /// If the texture already had a property of type `T`, it will be replaced.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, texture_index: TileTextureIndex, value: T) {
+        self.properties
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .insert(texture_index, Arc::new(value));
+    }
+
+    /// Gets the property of type `T` for the given texture index, if it has one.
+    pub fn get<T: Send + Sync + 'static>(&self, texture_index: TileTextureIndex) -> Option<&T> {
+        self.properties
+            .get(&TypeId::of::<T>())?
+            .get(&texture_index)?
+            .downcast_ref::<T>()
+    }
+
+    /// Returns `true` if the given texture index has a property of type `T`.
+    pub fn contains<T: Send + Sync + 'static>(&self, texture_index: TileTextureIndex) -> bool {
+        self.properties
+            .get(&TypeId::of::<T>())
+            .is_some_and(|values| values.contains_key(&texture_index))
+    }
+
+    /// Removes the property of type `T` from the given texture index, returning `true` if it had
+    /// one.
+    pub fn remove<T: Send + Sync + 'static>(&mut self, texture_index: TileTextureIndex) -> bool {
+        self.properties
+            .get_mut(&TypeId::of::<T>())
+            .is_some_and(|values| values.remove(&texture_index).is_some())
+    }
+
+    /// Returns an iterator over all texture indices which have a property of type `T`, together
+    /// with the property.
+    pub fn iter<T: Send + Sync + 'static>(
+        &self,
+    ) -> impl Iterator<Item = (TileTextureIndex, &T)> + '_ {
+        self.properties
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flat_map(|values| values.iter())
+            .filter_map(|(texture_index, value)| {
+                value
+                    .downcast_ref::<T>()
+                    .map(|value| (*texture_index, value))
+            })
+    }
+
+    /// Deserializes a property of type `T` and sets it for the given texture index.
+    ///
+    /// This can be used when loading the custom properties of a tileset asset, with any
+    /// [`serde`] data format.
+    #[cfg(feature = "serde")]
+    pub fn insert_deserialized<'de, T, D>(
+        &mut self,
+        texture_index: TileTextureIndex,
+        deserializer: D,
+    ) -> Result<(), D::Error>
+    where
+        T: serde::Deserialize<'de> + Send + Sync + 'static,
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        self.insert(texture_index, value);
+        Ok(())
+    }
+}
+
+/// Deserializes a property value of a manifest file into a type registered with
+/// [`RegisterTileProperty`].
+#[cfg(feature = "serde")]
+type PropertyDeserializer =
+    fn(&ron::value::RawValue) -> Result<Arc<dyn Any + Send + Sync>, ron::de::SpannedError>;
+
+/// The property types which [`TilesetManifest`] files may hold, by the name they go by in the
+/// files.
+///
+/// The [`TilesetManifestLoader`] shares the registry, so types can be registered with
+/// [`RegisterTileProperty::register_tile_property`] before or after the plugins are added.
+#[cfg(feature = "serde")]
+#[derive(Resource, Clone, Default)]
+pub struct TilesetPropertyRegistry(Arc<RwLock<HashMap<String, (TypeId, PropertyDeserializer)>>>);
+
+#[cfg(feature = "serde")]
+impl TilesetPropertyRegistry {
+    /// Registers `T` as the type of the properties called `name` in manifest files.
+    pub fn register<T>(&self, name: impl Into<String>)
+    where
+        T: serde::de::DeserializeOwned + Send + Sync + 'static,
+    {
+        let deserialize: PropertyDeserializer = |value| {
+            // Newtypes like `struct FootstepSound(String)` can be written as their inner value.
+            let value = ron::Options::default()
+                .with_default_extension(ron::extensions::Extensions::UNWRAP_NEWTYPES)
+                .from_str::<T>(value.get_ron())?;
+            Ok(Arc::new(value))
+        };
+        self.0
+            .write()
+            .unwrap()
+            .insert(name.into(), (TypeId::of::<T>(), deserialize));
+    }
+
+    /// Returns `true` if a type is registered for the properties called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.read().unwrap().contains_key(name)
+    }
+
+    /// Parses a manifest file, which maps texture indices to their properties by name:
+    ///
+    /// ```ron
+    /// {
+    ///     3: { "footstep": "gravel.ogg", "cost": 2.0 },
+    ///     4: { "footstep": "grass.ogg" },
+    /// }
+    /// ```
+    ///
+    /// Properties whose name is not registered are skipped with a warning.
+    pub fn parse(&self, bytes: &[u8]) -> Result<TilesetManifest, TilesetManifestLoaderError> {
+        let textures = ron::de::from_bytes::<
+            BTreeMap<u32, BTreeMap<String, Box<ron::value::RawValue>>>,
+        >(bytes)
+        .map_err(TilesetManifestLoaderError::Ron)?;
+        let registry = self.0.read().unwrap();
+        let mut manifest = TilesetManifest::default();
+        for (texture_index, properties) in textures {
+            for (name, value) in properties {
+                let Some((type_id, deserialize)) = registry.get(&name) else {
+                    warn!("Skipping tile property {name}: it is not registered");
+                    continue;
+                };
+                let value =
+                    deserialize(&value).map_err(|err| TilesetManifestLoaderError::Property {
+                        name: name.clone(),
+                        texture_index,
+                        err,
+                    })?;
+                manifest
+                    .properties
+                    .entry(*type_id)
+                    .or_default()
+                    .insert(TileTextureIndex(texture_index), value);
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// Registers the types of tile properties read from [`TilesetManifest`] files.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// #[derive(serde::Deserialize)]
+/// struct FootstepSound(String);
+///
+/// fn build(app: &mut App) {
+///     app.register_tile_property::<FootstepSound>("footstep");
+/// }
+/// ```
+#[cfg(feature = "serde")]
+pub trait RegisterTileProperty {
+    /// Registers `T` as the type of the properties called `name` in manifest files.
+    fn register_tile_property<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: serde::de::DeserializeOwned + Send + Sync + 'static;
+}
+
+#[cfg(feature = "serde")]
+impl RegisterTileProperty for App {
+    fn register_tile_property<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: serde::de::DeserializeOwned + Send + Sync + 'static,
+    {
+        self.world_mut()
+            .get_resource_or_init::<TilesetPropertyRegistry>()
+            .register::<T>(name);
+        self
+    }
+}
+
+/// Loads `.tileset.ron` files as [`TilesetManifest`]s, with the property types of the
+/// [`TilesetPropertyRegistry`]. See [`TilesetPropertyRegistry::parse`] for the format.
+#[cfg(feature = "serde")]
+pub struct TilesetManifestLoader {
+    pub registry: TilesetPropertyRegistry,
+}
+
+#[cfg(feature = "serde")]
+impl AssetLoader for TilesetManifestLoader {
+    type Asset = TilesetManifest;
+    type Settings = ();
+    type Error = TilesetManifestLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        self.registry.parse(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tileset.ron"]
+    }
+}
+
+/// An error while loading a [`TilesetManifest`].
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum TilesetManifestLoaderError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file could not be parsed.
+    Ron(ron::de::SpannedError),
+    /// A property does not match the type registered for its name.
+    Property {
+        name: String,
+        texture_index: u32,
+        err: ron::de::SpannedError,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for TilesetManifestLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not load tileset manifest: {err}"),
+            Self::Ron(err) => write!(f, "could not parse tileset manifest: {err}"),
+            Self::Property {
+                name,
+                texture_index,
+                err,
+            } => write!(
+                f,
+                "could not read tile property {name} of texture {texture_index}: {err}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for TilesetManifestLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Ron(err) => Some(err),
+            Self::Property { err, .. } => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<std::io::Error> for TilesetManifestLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Adds the [`TilesetManifest`] asset and its [`TilesetManifestLoader`].
+#[cfg(feature = "serde")]
+pub(crate) fn build_tileset_manifests(app: &mut App) {
+    let registry = app
+        .world_mut()
+        .get_resource_or_init::<TilesetPropertyRegistry>()
+        .clone();
+    app.init_asset::<TilesetManifest>()
+        .register_asset_loader(TilesetManifestLoader { registry });
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct FootstepSound(String);
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct MovementCost {
+        cost: f32,
+        flying_only: bool,
+    }
+
+    #[test]
+    fn manifests_are_parsed_with_the_registered_types() {
+        let registry = TilesetPropertyRegistry::default();
+        registry.register::<FootstepSound>("footstep");
+        registry.register::<MovementCost>("movement");
+        let manifest = registry
+            .parse(
+                br#"{
+                    3: { "footstep": "gravel.ogg", "movement": (cost: 2.0, flying_only: false) },
+                    4: { "footstep": "grass.ogg", "unknown": 1 },
+                }"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            manifest.get::<FootstepSound>(TileTextureIndex(4)),
+            Some(&FootstepSound("grass.ogg".to_string()))
+        );
+        assert_eq!(
+            manifest.get::<MovementCost>(TileTextureIndex(3)),
+            Some(&MovementCost {
+                cost: 2.0,
+                flying_only: false
+            })
+        );
+        assert!(!manifest.contains::<MovementCost>(TileTextureIndex(4)));
+
+        let err = registry
+            .parse(br#"{ 5: { "movement": "fast" } }"#)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TilesetManifestLoaderError::Property {
+                texture_index: 5,
+                ..
+            }
+        ));
+    }
+}
@@ -1,14 +1,39 @@
+mod color_animation;
+mod data_layer;
+mod frame_animation;
+mod layered_storage;
+mod manifest;
+mod region;
+mod sparse_storage;
 mod storage;
+#[cfg(feature = "debug")]
+mod validation;
 
 use bevy::{
-    math::{UVec2, Vec2},
-    prelude::{Bundle, Color, Component, Reflect, ReflectComponent},
+    math::{IVec2, UVec2, Vec2, Vec4},
+    platform::collections::HashMap,
+    prelude::{
+        Bundle, Changed, Color, Commands, Component, Deref, DerefMut, DetectChanges,
+        DetectChangesMut, Entity, Query, Ref, Reflect, ReflectComponent, RemovedComponents, Res,
+        ResMut, Resource, Time, Without,
+    },
     render::sync_world::SyncToRenderWorld,
 };
+use std::ops::{Add, Sub};
+
+pub use color_animation::*;
+pub use data_layer::*;
+pub use frame_animation::*;
+pub use layered_storage::*;
+pub use manifest::*;
+pub use region::*;
+pub use sparse_storage::*;
 pub use storage::*;
+#[cfg(feature = "debug")]
+pub(crate) use validation::*;
 
 use crate::TilemapSize;
-use crate::map::TilemapId;
+use crate::map::{TilemapId, TilemapTime};
 
 /// A tile position in the tilemap grid.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -34,6 +59,39 @@ impl TilePos {
     pub fn within_map_bounds(&self, map_size: &TilemapSize) -> bool {
         self.x < map_size.x && self.y < map_size.y
     }
+
+    /// Offsets `self` by `offset`.
+    ///
+    /// Returns `None` if the result lies outside of a tilemap of the specified size, including
+    /// when it would be negative.
+    pub fn checked_add(&self, offset: IVec2, map_size: &TilemapSize) -> Option<TilePos> {
+        let x = self.x as i64 + offset.x as i64;
+        let y = self.y as i64 + offset.y as i64;
+        if x < 0 || y < 0 || x >= map_size.x as i64 || y >= map_size.y as i64 {
+            None
+        } else {
+            Some(TilePos::new(x as u32, y as u32))
+        }
+    }
+
+    /// Offsets `self` by `offset`, clamping the result to the edges of a tilemap of the
+    /// specified size.
+    ///
+    /// Panics if `map_size` is empty.
+    pub fn saturating_offset(&self, offset: IVec2, map_size: &TilemapSize) -> TilePos {
+        let x = (self.x as i64 + offset.x as i64).clamp(0, map_size.x as i64 - 1);
+        let y = (self.y as i64 + offset.y as i64).clamp(0, map_size.y as i64 - 1);
+        TilePos::new(x as u32, y as u32)
+    }
+
+    /// Offsets `self` by `offset`, wrapping around the edges of a tilemap of the specified size.
+    ///
+    /// Panics if `map_size` is empty.
+    pub fn wrapping_offset(&self, offset: IVec2, map_size: &TilemapSize) -> TilePos {
+        let x = (self.x as i64 + offset.x as i64).rem_euclid(map_size.x as i64);
+        let y = (self.y as i64 + offset.y as i64).rem_euclid(map_size.y as i64);
+        TilePos::new(x as u32, y as u32)
+    }
 }
 
 impl From<TilePos> for UVec2 {
@@ -66,6 +124,105 @@ impl From<&TilePos> for Vec2 {
     }
 }
 
+/// A signed tile position, for intermediate math which may step outside of the tilemap (offsets,
+/// pattern generation, stamping prefabs at negative origins).
+///
+/// Convert it into a [`TilePos`] once the final position is known, using
+/// [`as_tile_pos_given_map_size`](Self::as_tile_pos_given_map_size) or
+/// [`as_tile_pos_given_origin`](Self::as_tile_pos_given_origin).
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq, Deref, DerefMut)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignedTilePos(pub IVec2);
+
+impl SignedTilePos {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self(IVec2::new(x, y))
+    }
+
+    /// Checks to see if `self` lies within a tilemap of the specified size.
+    pub fn within_map_bounds(&self, map_size: &TilemapSize) -> bool {
+        self.x >= 0 && self.y >= 0 && (self.x as u32) < map_size.x && (self.y as u32) < map_size.y
+    }
+
+    /// Try converting into a [`TilePos`].
+    ///
+    /// Returns `None` if either coordinate is negative, or lies out of the bounds of `map_size`.
+    pub fn as_tile_pos_given_map_size(&self, map_size: &TilemapSize) -> Option<TilePos> {
+        if self.within_map_bounds(map_size) {
+            Some(TilePos::new(self.x as u32, self.y as u32))
+        } else {
+            None
+        }
+    }
+
+    /// Try converting into a [`TilePos`], treating `self` as relative to `origin`.
+    ///
+    /// Returns `None` if the shifted position lies out of the bounds of `map_size`.
+    pub fn as_tile_pos_given_origin(
+        &self,
+        origin: &TilePos,
+        map_size: &TilemapSize,
+    ) -> Option<TilePos> {
+        origin.checked_add(self.0, map_size)
+    }
+}
+
+impl From<TilePos> for SignedTilePos {
+    fn from(pos: TilePos) -> Self {
+        Self::new(pos.x as i32, pos.y as i32)
+    }
+}
+
+impl From<&TilePos> for SignedTilePos {
+    fn from(pos: &TilePos) -> Self {
+        Self::from(*pos)
+    }
+}
+
+impl From<IVec2> for SignedTilePos {
+    fn from(v: IVec2) -> Self {
+        Self(v)
+    }
+}
+
+impl From<SignedTilePos> for IVec2 {
+    fn from(pos: SignedTilePos) -> Self {
+        pos.0
+    }
+}
+
+impl Add<SignedTilePos> for SignedTilePos {
+    type Output = SignedTilePos;
+
+    fn add(self, rhs: SignedTilePos) -> Self::Output {
+        SignedTilePos(self.0 + rhs.0)
+    }
+}
+
+impl Add<IVec2> for SignedTilePos {
+    type Output = SignedTilePos;
+
+    fn add(self, rhs: IVec2) -> Self::Output {
+        SignedTilePos(self.0 + rhs)
+    }
+}
+
+impl Sub<SignedTilePos> for SignedTilePos {
+    type Output = SignedTilePos;
+
+    fn sub(self, rhs: SignedTilePos) -> Self::Output {
+        SignedTilePos(self.0 - rhs.0)
+    }
+}
+
+impl Sub<IVec2> for SignedTilePos {
+    type Output = SignedTilePos;
+
+    fn sub(self, rhs: IVec2) -> Self::Output {
+        SignedTilePos(self.0 - rhs)
+    }
+}
+
 /// A texture index into the atlas or texture array for a single tile. Indices in an atlas are horizontal based.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -85,6 +242,9 @@ impl From<Color> for TileColor {
 }
 
 /// Hides or shows a tile based on the boolean. Default: True
+///
+/// Hidden tiles stay in their chunk's mesh, so toggling the visibility of many tiles at once, e.g.
+/// to reveal a fog of war, only uploads a small visibility mask instead of rebuilding meshes.
 #[derive(Component, Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -96,6 +256,80 @@ impl Default for TileVisible {
     }
 }
 
+/// Marks a tile which hides what is below it, e.g. a roof, so it fades out where the tilemap's
+/// [`TilemapOcclusionReveal`](crate::map::TilemapOcclusionReveal) reveals the interior.
+///
+/// Like [`TileVisible`], adding or removing it does not rebuild the chunk's mesh.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileOccluder;
+
+/// The part of its quad a tile covers, e.g. the slopes and half blocks of platformers.
+///
+/// Shapes cut the quad of the tile rather than squeeze it, so each part of the quad shows the
+/// same part of the texture as on a full tile. Picking and culling use the full tile, and
+/// changing the shape rebuilds the mesh of the chunk. Only chunks with shaped tiles store the
+/// corners of the shapes in their vertices.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileShape {
+    #[default]
+    Full,
+    /// The bottom half of the tile.
+    Half,
+    /// The bottom quarter of the tile.
+    Quarter,
+    /// The triangle below the diagonal from the top left to the bottom right corner, i.e. a
+    /// slope rising to the left.
+    SlopeLeft,
+    /// The triangle below the diagonal from the bottom left to the top right corner, i.e. a
+    /// slope rising to the right.
+    SlopeRight,
+    /// A quad with the given bottom left, top left, top right and bottom right corners, as
+    /// fractions of the quad of the tile from `(0.0, 0.0)` at its bottom left to `(1.0, 1.0)` at
+    /// its top right. The corners must be in clockwise order, or the quad is culled.
+    Quad([Vec2; 4]),
+}
+
+impl TileShape {
+    /// Returns the bottom left, top left, top right and bottom right corners of the shape, as
+    /// fractions of the quad of the tile.
+    pub fn corners(&self) -> [Vec2; 4] {
+        match self {
+            TileShape::Full => [Vec2::ZERO, Vec2::Y, Vec2::ONE, Vec2::X],
+            TileShape::Half => [
+                Vec2::ZERO,
+                Vec2::new(0.0, 0.5),
+                Vec2::new(1.0, 0.5),
+                Vec2::X,
+            ],
+            TileShape::Quarter => [
+                Vec2::ZERO,
+                Vec2::new(0.0, 0.25),
+                Vec2::new(1.0, 0.25),
+                Vec2::X,
+            ],
+            // One of the triangles of the quad is collapsed.
+            TileShape::SlopeLeft => [Vec2::ZERO, Vec2::Y, Vec2::X, Vec2::X],
+            TileShape::SlopeRight => [Vec2::ZERO, Vec2::ZERO, Vec2::ONE, Vec2::X],
+            TileShape::Quad(corners) => *corners,
+        }
+    }
+}
+
+/// Moves a tile forward or back in the draw order of a tilemap using
+/// [`TilemapRenderMode::YSort`](crate::map::TilemapRenderMode::YSort), e.g. to keep the base of a
+/// tall wall from being drawn over by the wall above it.
+///
+/// The offset is added to the depth the tile would be sorted at; it is ignored by other render
+/// modes.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileZOffset(pub f32);
+
 /// Flips the tiles texture along the X, Y or diagonal axes
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[reflect(Component)]
@@ -108,6 +342,200 @@ pub struct TileFlip {
     pub d: bool, // anti
 }
 
+impl TileFlip {
+    /// Returns the flip which shows the texture as this one does, rotated counterclockwise by
+    /// `quarter_turns` quarter turns.
+    ///
+    /// A quarter turn clockwise is three counterclockwise, i.e. flipping along the X and the
+    /// diagonal axes, as Tiled stores it.
+    pub fn rotated(self, quarter_turns: u32) -> Self {
+        (0..quarter_turns % 4).fold(self, |flip, _| TileFlip {
+            x: flip.y,
+            y: !flip.x,
+            d: !flip.d,
+        })
+    }
+}
+
+/// Rotates the texture of a tile counterclockwise, after its [`TileFlip`].
+///
+/// Quarter turns rotate the texture within the tile, like the rotations of Tiled, and are free:
+/// they are folded into the flip of the tile. [`TileRotation::Angle`] rotates the whole tile
+/// around its center instead, e.g. the 120° steps of hexagonal tiles, so its corners may reach
+/// past its neighbors. Picking and culling use the unrotated tile.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileRotation {
+    #[default]
+    None,
+    Deg90,
+    Deg180,
+    Deg270,
+    /// An angle in radians.
+    Angle(f32),
+}
+
+impl TileRotation {
+    /// Returns the number of counterclockwise quarter turns, which is `0` for
+    /// [`TileRotation::Angle`].
+    pub fn quarter_turns(&self) -> u32 {
+        match self {
+            TileRotation::None | TileRotation::Angle(_) => 0,
+            TileRotation::Deg90 => 1,
+            TileRotation::Deg180 => 2,
+            TileRotation::Deg270 => 3,
+        }
+    }
+
+    /// Returns the angle the whole tile is rotated by, in radians, which is `0.0` for quarter
+    /// turns.
+    pub fn angle(&self) -> f32 {
+        match self {
+            TileRotation::Angle(angle) => *angle,
+            _ => 0.0,
+        }
+    }
+}
+
+/// The maximum number of overlay layers in [`TileLayers`].
+pub const MAX_TILE_LAYERS: usize = 4;
+
+/// Overlay textures drawn on top of a tile's [`TileTextureIndex`] in the same draw, e.g. decals
+/// or weather effects, instead of stacking several tilemaps.
+///
+/// Layers are blended in order, each onto the result of the ones below it, using the blend mode
+/// configured for that layer in the tilemap's
+/// [`TilemapLayerBlendModes`](crate::map::TilemapLayerBlendModes). The [`TileColor`] tints the
+/// blended result.
+///
+/// On tiles with an [`AnimatedTile`], the overlays advance with the animation: each layer shows
+/// the texture as many frames past its own index as the tile is past the
+/// [`start`](AnimatedTile::start) of its animation, see [`at_frame`](Self::at_frame). Their
+/// frames are laid out like the base animation, e.g. a burning overlay at `20..24` over a tile
+/// animated through `0..4`. Overlays of tiles with a [`TileFrameAnimation`] are not animated.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileLayers(pub [Option<TileTextureIndex>; MAX_TILE_LAYERS]);
+
+impl TileLayers {
+    /// Returns the texture of the given overlay layer, if it has one.
+    pub fn get(&self, layer: usize) -> Option<TileTextureIndex> {
+        self.0.get(layer).copied().flatten()
+    }
+
+    /// Sets or clears the texture of the given overlay layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is not less than [`MAX_TILE_LAYERS`].
+    pub fn set(&mut self, layer: usize, texture_index: Option<TileTextureIndex>) {
+        self.0[layer] = texture_index;
+    }
+
+    /// Returns the overlays shown while `animation` shows the given frame, each advanced by as
+    /// many frames as `frame` is past the start of the animation.
+    pub fn at_frame(&self, animation: &AnimatedTile, frame: u32) -> Self {
+        let frames = frame as i64 - animation.start as i64;
+        Self(self.0.map(|layer| {
+            layer.map(|texture_index| {
+                TileTextureIndex((texture_index.0 as i64 + frames).clamp(0, u32::MAX as i64) as u32)
+            })
+        }))
+    }
+}
+
+/// Makes tiles whose [`TileLayers`] were removed be extracted again, so the overlays disappear.
+pub(crate) fn update_removed_tile_layers(
+    mut removed: RemovedComponents<TileLayers>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Arbitrary per-tile data passed to the tilemap shaders, e.g. a wetness or damage level read by
+/// a custom [`MaterialTilemap`](crate::render::material::MaterialTilemap) shader.
+///
+/// The value is baked into the chunk mesh along with the other tile data, and available to the
+/// fragment shader as the `custom_data` field of `MeshVertexOutput`. Tiles without the component
+/// get zeroes.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileCustomData(pub Vec4);
+
+impl From<Vec4> for TileCustomData {
+    fn from(data: Vec4) -> Self {
+        TileCustomData(data)
+    }
+}
+
+/// Makes tiles whose [`TileCustomData`] was removed be extracted again, so the shaders see zeroes.
+pub(crate) fn update_removed_tile_custom_data(
+    mut removed: RemovedComponents<TileCustomData>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Makes tiles whose [`TileZOffset`] was removed be extracted again, so they are sorted at their
+/// own depth.
+pub(crate) fn update_removed_tile_z_offsets(
+    mut removed: RemovedComponents<TileZOffset>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Makes tiles whose [`TileShape`] was removed be extracted again, so they cover their whole quad.
+pub(crate) fn update_removed_tile_shapes(
+    mut removed: RemovedComponents<TileShape>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Makes tiles whose [`TileRotation`] was removed be extracted again, so they are drawn upright.
+pub(crate) fn update_removed_tile_rotations(
+    mut removed: RemovedComponents<TileRotation>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Makes tiles whose [`TileOccluder`] was removed be extracted again, so they stop fading.
+pub(crate) fn update_removed_tile_occluders(
+    mut removed: RemovedComponents<TileOccluder>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
 /// This an optional tile bundle with default components.
 #[derive(Bundle, Default, Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -132,6 +560,7 @@ pub struct TilePosOld(pub TilePos);
 /// tells the GPU how to animate the tile.
 /// Currently all frames must be aligned in your tilemap.
 #[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnimatedTile {
     /// The start frame index in the tilemap atlas/array (inclusive).
@@ -141,3 +570,566 @@ pub struct AnimatedTile {
     /// The speed the animation plays back at.
     pub speed: f32,
 }
+
+impl AnimatedTile {
+    /// Returns the frame shown at the given time (in seconds, wrapped like
+    /// [`Time::elapsed_secs_wrapped`]), the same way the GPU picks it.
+    pub fn frame_at(&self, elapsed_secs_wrapped: f32) -> u32 {
+        self.frame_at_phase(elapsed_secs_wrapped, AnimationPhase::default())
+    }
+
+    /// Returns the frame shown at the given time by a tile with the given [`AnimationPhase`].
+    pub fn frame_at_phase(&self, elapsed_secs_wrapped: f32, phase: AnimationPhase) -> u32 {
+        if self.end <= self.start {
+            return self.start;
+        }
+        let frames = (self.end - self.start) as f32;
+        let frame = (elapsed_secs_wrapped * self.speed + phase.0).fract().abs() * frames;
+        (self.start + frame as u32).min(self.end - 1)
+    }
+}
+
+/// Shifts the [`AnimatedTile`] of a tile along its loop, so that large animated areas like water
+/// do not pulse in unison.
+///
+/// The phase is a fraction of the whole loop: `0.5` starts the tile halfway through its frames.
+/// Tiles without the component have a phase of `0.0`.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationPhase(pub f32);
+
+impl AnimationPhase {
+    /// A pseudo-random phase for the tile at `tile_pos`, which is the same every time it is
+    /// computed for the same position and `seed`.
+    pub fn scattered(tile_pos: &TilePos, seed: u32) -> Self {
+        let hash = scatter_hash(tile_pos, seed);
+        Self((hash >> 8) as f32 / (1 << 24) as f32)
+    }
+}
+
+/// Hashes a tile position and a seed into well mixed bits, for picking pseudo-random per-tile
+/// values which are the same every time.
+pub(crate) fn scatter_hash(tile_pos: &TilePos, seed: u32) -> u32 {
+    let mut hash = tile_pos.x.wrapping_mul(0x8da6_b343)
+        ^ tile_pos.y.wrapping_mul(0xd816_3841)
+        ^ seed.wrapping_mul(0xcb1a_b31f);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// Makes tiles whose [`AnimationPhase`] was removed be extracted again, so they animate in step
+/// with the shared clock.
+pub(crate) fn update_removed_animation_phases(
+    mut removed: RemovedComponents<AnimationPhase>,
+    mut query: Query<&mut TileTextureIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut texture_index) = query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Halts an [`AnimatedTile`] on a single frame, e.g. for a stopped machine or a frozen
+/// waterfall, without removing its animation.
+///
+/// When the component is inserted without a [`frame`](Self::frame), the frame currently shown is
+/// filled in. Once the component is removed, the animation continues in step with the shared
+/// animation clock, i.e. as if it had never been paused.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationPaused {
+    /// The frame index in the tilemap atlas/array the tile is halted on.
+    pub frame: Option<u32>,
+}
+
+impl AnimationPaused {
+    /// Halts the animation on the given frame.
+    pub fn on_frame(frame: u32) -> Self {
+        Self { frame: Some(frame) }
+    }
+}
+
+/// Fills in the current frame of newly paused tiles, and makes resumed tiles animate again.
+pub(crate) fn update_paused_animations(
+    time: Res<Time>,
+    animation_table: Res<TileAnimationTable>,
+    mut paused_query: Query<
+        (
+            &mut AnimationPaused,
+            Option<&AnimatedTile>,
+            Option<&TileFrameAnimation>,
+            Option<&AnimationPhase>,
+            Option<&TilemapId>,
+        ),
+        Changed<AnimationPaused>,
+    >,
+    tilemap_times: Query<&TilemapTime>,
+    mut resumed: RemovedComponents<AnimationPaused>,
+    mut animated_query: Query<&mut TileTextureIndex, Without<AnimationPaused>>,
+) {
+    for (mut paused, animated_tile, frame_animation, phase, tilemap_id) in paused_query.iter_mut() {
+        if paused.frame.is_some() {
+            continue;
+        }
+        let phase = phase.copied().unwrap_or_default();
+        // The tile shows the frame of its tilemap's time, if it has a `TilemapTimeScale`.
+        let elapsed = tilemap_id
+            .and_then(|tilemap_id| tilemap_times.get(tilemap_id.0).ok())
+            .map_or_else(|| time.elapsed_secs_wrapped(), TilemapTime::elapsed_secs);
+        paused.frame = match (frame_animation, animated_tile) {
+            (Some(frame_animation), _) => {
+                Some(animation_table.frame_at(frame_animation, elapsed, phase))
+            }
+            (None, Some(animated_tile)) => Some(animated_tile.frame_at_phase(elapsed, phase)),
+            (None, None) => None,
+        };
+    }
+
+    for entity in resumed.read() {
+        // Touch the tile so it is extracted again.
+        if let Ok(mut texture_index) = animated_query.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Makes an [`AnimatedTile`] advance its frames in lock-step with every other tile in the same
+/// group, e.g. for a large animated waterfall which is split across several tiles.
+///
+/// The tiles of a group share the clock of the group: they are played back at the speed
+/// registered for the group in [`AnimationGroupSpeeds`], which overrides [`AnimatedTile::speed`],
+/// and with the same [`AnimationPhase`]. Changing the speed of a group keeps its clock where it
+/// is, so its tiles neither jump nor drift apart. A tile which leaves its group gets back the
+/// speed and phase it had when it joined.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationGroup(pub u32);
+
+/// The playback speed of each [`AnimationGroup`].
+///
+/// Tiles in a group which has no registered speed keep their own [`AnimatedTile::speed`], and
+/// only share the phase of the group.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct AnimationGroupSpeeds {
+    speeds: HashMap<u32, f32>,
+    /// The speed and phase of the clock of each group with a registered speed. The phase is moved
+    /// when the speed changes, to keep the clock continuous.
+    clocks: HashMap<u32, (f32, f32)>,
+}
+
+impl AnimationGroupSpeeds {
+    /// Sets the playback speed of the given group.
+    pub fn set(&mut self, group: AnimationGroup, speed: f32) {
+        self.speeds.insert(group.0, speed);
+    }
+
+    /// Gets the playback speed of the given group, if one was registered.
+    pub fn get(&self, group: AnimationGroup) -> Option<f32> {
+        self.speeds.get(&group.0).copied()
+    }
+
+    /// Removes the playback speed of the given group, returning it if one was registered.
+    pub fn remove(&mut self, group: AnimationGroup) -> Option<f32> {
+        self.speeds.remove(&group.0)
+    }
+
+    /// Moves the clocks of the groups whose speed changed to the new speed, keeping the frame
+    /// they show at `elapsed_secs_wrapped`.
+    fn update_clocks(&mut self, elapsed_secs_wrapped: f32) {
+        let Self { speeds, clocks } = self;
+        clocks.retain(|group, _| speeds.contains_key(group));
+        for (group, speed) in speeds.iter() {
+            let (clock_speed, phase) = clocks.entry(*group).or_insert((*speed, 0.0));
+            if *clock_speed != *speed {
+                *phase = (*phase + elapsed_secs_wrapped * (*clock_speed - *speed)).rem_euclid(1.0);
+                *clock_speed = *speed;
+            }
+        }
+    }
+}
+
+/// The speed and phase of a tile in an [`AnimationGroup`] from before it joined the group.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct UngroupedAnimation {
+    speed: f32,
+    phase: Option<AnimationPhase>,
+}
+
+/// Plays the [`AnimatedTile`]s of each [`AnimationGroup`] back with the clock of the group, and
+/// restores the own speed and phase of tiles which left their group.
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_animation_groups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut groups: ResMut<AnimationGroupSpeeds>,
+    mut grouped: Query<(
+        Entity,
+        Ref<AnimationGroup>,
+        &mut AnimatedTile,
+        Option<&mut AnimationPhase>,
+        Option<&UngroupedAnimation>,
+    )>,
+    mut removed: RemovedComponents<AnimationGroup>,
+    mut ungrouped: Query<(&mut AnimatedTile, &UngroupedAnimation), Without<AnimationGroup>>,
+) {
+    let groups_changed = groups.is_changed();
+    if groups_changed {
+        groups
+            .bypass_change_detection()
+            .update_clocks(time.elapsed_secs_wrapped());
+    }
+
+    for (entity, group, mut animated_tile, phase, ungrouped_animation) in grouped.iter_mut() {
+        if !(groups_changed
+            || group.is_changed()
+            || animated_tile.is_changed()
+            || ungrouped_animation.is_none())
+        {
+            continue;
+        }
+
+        let ungrouped_animation = ungrouped_animation.copied().unwrap_or_else(|| {
+            let ungrouped_animation = UngroupedAnimation {
+                speed: animated_tile.speed,
+                phase: phase.as_deref().copied(),
+            };
+            commands.entity(entity).insert(ungrouped_animation);
+            ungrouped_animation
+        });
+        let (speed, group_phase) = groups
+            .clocks
+            .get(&group.0)
+            .copied()
+            .unwrap_or((ungrouped_animation.speed, 0.0));
+        if animated_tile.speed != speed {
+            animated_tile.speed = speed;
+        }
+        match phase {
+            Some(mut phase) if phase.0 != group_phase => phase.0 = group_phase,
+            None if group_phase != 0.0 => {
+                commands.entity(entity).insert(AnimationPhase(group_phase));
+            }
+            _ => {}
+        }
+    }
+
+    for entity in removed.read() {
+        let Ok((mut animated_tile, ungrouped_animation)) = ungrouped.get_mut(entity) else {
+            continue;
+        };
+        animated_tile.speed = ungrouped_animation.speed;
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.remove::<UngroupedAnimation>();
+        match ungrouped_animation.phase {
+            Some(phase) => entity_commands.insert(phase),
+            None => entity_commands.remove::<AnimationPhase>(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_tile_pos_past_edges() {
+        let map_size = TilemapSize { x: 4, y: 3 };
+        let tile_pos = TilePos::new(0, 2);
+
+        assert_eq!(tile_pos.checked_add(IVec2::new(-1, 0), &map_size), None);
+        assert_eq!(tile_pos.checked_add(IVec2::new(0, 1), &map_size), None);
+        assert_eq!(
+            tile_pos.checked_add(IVec2::new(3, -2), &map_size),
+            Some(TilePos::new(3, 0))
+        );
+
+        assert_eq!(
+            tile_pos.saturating_offset(IVec2::new(-5, 5), &map_size),
+            TilePos::new(0, 2)
+        );
+        assert_eq!(
+            tile_pos.wrapping_offset(IVec2::new(-1, 1), &map_size),
+            TilePos::new(3, 0)
+        );
+    }
+
+    #[test]
+    fn quarter_turns_compose_with_flips() {
+        let none = TileFlip::default();
+        // Tiled stores a clockwise quarter turn as flipping along the X and diagonal axes.
+        let clockwise = TileFlip {
+            x: true,
+            y: false,
+            d: true,
+        };
+        assert_eq!(none.rotated(3), clockwise);
+        assert_eq!(clockwise.rotated(1), none);
+        assert_eq!(
+            none.rotated(2),
+            TileFlip {
+                x: true,
+                y: true,
+                d: false
+            }
+        );
+        let flipped = TileFlip {
+            x: true,
+            ..Default::default()
+        };
+        assert_eq!(flipped.rotated(4), flipped);
+    }
+
+    #[test]
+    fn phases_shift_and_scatter_animations() {
+        let water = AnimatedTile {
+            start: 4,
+            end: 8,
+            speed: 1.0,
+        };
+        assert_eq!(water.frame_at(0.1), 4);
+        assert_eq!(water.frame_at_phase(0.1, AnimationPhase(0.5)), 6);
+
+        let phases: Vec<_> = (0..64)
+            .map(|x| AnimationPhase::scattered(&TilePos::new(x, 3), 7).0)
+            .collect();
+        assert!(phases.iter().all(|phase| (0.0..1.0).contains(phase)));
+        assert!(phases.iter().any(|phase| *phase < 0.25));
+        assert!(phases.iter().any(|phase| *phase > 0.75));
+        assert_eq!(
+            AnimationPhase::scattered(&TilePos::new(5, 3), 7).0,
+            phases[5]
+        );
+    }
+
+    #[test]
+    fn tile_shapes_show_their_part_of_a_full_tile() {
+        let expected = [
+            (
+                TileShape::Full,
+                [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)],
+            ),
+            (
+                TileShape::Half,
+                [(0.0, 0.0), (0.0, 0.5), (1.0, 0.5), (1.0, 0.0)],
+            ),
+            (
+                TileShape::Quarter,
+                [(0.0, 0.0), (0.0, 0.25), (1.0, 0.25), (1.0, 0.0)],
+            ),
+            (
+                TileShape::SlopeLeft,
+                [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 0.0)],
+            ),
+            (
+                TileShape::SlopeRight,
+                [(0.0, 0.0), (0.0, 0.0), (1.0, 1.0), (1.0, 0.0)],
+            ),
+            (
+                TileShape::Quad([Vec2::ZERO, Vec2::new(0.2, 0.6), Vec2::ONE, Vec2::X]),
+                [(0.0, 0.0), (0.2, 0.6), (1.0, 1.0), (1.0, 0.0)],
+            ),
+        ];
+        // The UVs of the corners of a full tile in the atlas, flipped on X, as the vertex
+        // shader gets them.
+        let full_uvs = [
+            Vec2::new(0.5, 0.75),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(0.25, 0.5),
+            Vec2::new(0.25, 0.75),
+        ];
+        let full_uv_at = |point: Vec2| Vec2::new(0.5 - 0.25 * point.x, 0.75 - 0.25 * point.y);
+        // The interpolation of the vertex shader.
+        let uv_at = |corner: Vec2| {
+            full_uvs[0]
+                .lerp(full_uvs[3], corner.x)
+                .lerp(full_uvs[1].lerp(full_uvs[2], corner.x), corner.y)
+        };
+
+        for (shape, corners) in expected {
+            assert_eq!(shape.corners(), corners.map(Vec2::from), "{shape:?}");
+            for corner in shape.corners() {
+                assert!(
+                    uv_at(corner).abs_diff_eq(full_uv_at(corner), 1e-6),
+                    "{shape:?} at {corner}"
+                );
+            }
+        }
+        assert_eq!(TileShape::Full.corners().map(uv_at), full_uvs);
+    }
+
+    #[test]
+    fn animation_groups_share_a_clock() {
+        use bevy::ecs::{schedule::Schedule, world::World};
+        use std::time::Duration;
+
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<AnimationGroupSpeeds>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_animation_groups);
+        let animation = |speed| AnimatedTile {
+            start: 0,
+            end: 10,
+            speed,
+        };
+        let group = AnimationGroup(1);
+        let phased = world
+            .spawn((animation(1.0), AnimationPhase(0.3), group))
+            .id();
+        let fast = world.spawn((animation(2.0), group)).id();
+        world.resource_mut::<AnimationGroupSpeeds>().set(group, 0.5);
+        schedule.run(&mut world);
+
+        let frame = |world: &World, tile| {
+            let elapsed = world.resource::<Time>().elapsed_secs_wrapped();
+            let phase = world
+                .get::<AnimationPhase>(tile)
+                .copied()
+                .unwrap_or_default();
+            world
+                .get::<AnimatedTile>(tile)
+                .unwrap()
+                .frame_at_phase(elapsed, phase)
+        };
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(2.2));
+        assert_eq!(world.get::<AnimatedTile>(fast).unwrap().speed, 0.5);
+        assert_eq!(frame(&world, phased), frame(&world, fast));
+        let before = frame(&world, fast);
+
+        // Speeding the group up keeps the frame it is on.
+        world.resource_mut::<AnimationGroupSpeeds>().set(group, 1.5);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AnimatedTile>(phased).unwrap().speed, 1.5);
+        assert_eq!(frame(&world, phased), before);
+        assert_eq!(frame(&world, fast), before);
+
+        // Tiles leaving the group play back as they did before.
+        world.entity_mut(phased).remove::<AnimationGroup>();
+        world.entity_mut(fast).remove::<AnimationGroup>();
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AnimatedTile>(phased).unwrap().speed, 1.0);
+        assert_eq!(
+            world.get::<AnimationPhase>(phased),
+            Some(&AnimationPhase(0.3))
+        );
+        assert_eq!(world.get::<AnimatedTile>(fast).unwrap().speed, 2.0);
+        assert_eq!(world.get::<AnimationPhase>(fast), None);
+    }
+
+    #[test]
+    fn overlays_advance_with_their_animation() {
+        let animation = AnimatedTile {
+            start: 4,
+            end: 8,
+            speed: 1.0,
+        };
+        let mut layers = TileLayers::default();
+        layers.set(0, Some(TileTextureIndex(20)));
+        layers.set(2, Some(TileTextureIndex(0)));
+
+        let frame = animation.frame_at(2.5);
+        assert_eq!(frame, 6);
+        let advanced = layers.at_frame(&animation, frame);
+        assert_eq!(advanced.get(0), Some(TileTextureIndex(22)));
+        assert_eq!(advanced.get(1), None);
+        assert_eq!(advanced.get(2), Some(TileTextureIndex(2)));
+        assert_eq!(layers.at_frame(&animation, animation.start), layers);
+        // Frames before the start of the animation can not move overlays past the first texture.
+        assert_eq!(
+            layers.at_frame(&animation, 1).get(2),
+            Some(TileTextureIndex(0))
+        );
+    }
+
+    #[test]
+    fn signed_positions_step_outside_the_map() {
+        let map_size = TilemapSize::new(4, 3);
+        let origin = TilePos::new(1, 2);
+        let stamp = SignedTilePos::new(-2, 0);
+
+        let moved = SignedTilePos::from(origin) + stamp;
+        assert_eq!(moved, SignedTilePos::new(-1, 2));
+        assert!(!moved.within_map_bounds(&map_size));
+        assert_eq!(moved.as_tile_pos_given_map_size(&map_size), None);
+        assert_eq!(
+            (moved - IVec2::new(-3, 1)).as_tile_pos_given_map_size(&map_size),
+            Some(TilePos::new(2, 1))
+        );
+        assert_eq!(
+            SignedTilePos::new(4, 0).as_tile_pos_given_map_size(&map_size),
+            None
+        );
+
+        assert_eq!(
+            SignedTilePos::new(2, -1).as_tile_pos_given_origin(&origin, &map_size),
+            Some(TilePos::new(3, 1))
+        );
+        assert_eq!(stamp.as_tile_pos_given_origin(&origin, &map_size), None);
+        assert_eq!(TilePos::from_i32_pair(-1, 0, &map_size), None);
+        assert_eq!(
+            IVec2::from(stamp + SignedTilePos::new(2, 1)),
+            IVec2::new(0, 1)
+        );
+    }
+
+    #[test]
+    fn paused_animations_hold_their_current_frame() {
+        use bevy::ecs::{schedule::Schedule, world::World};
+        use std::time::Duration;
+
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<TileAnimationTable>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_paused_animations);
+        let animated = AnimatedTile {
+            start: 0,
+            end: 4,
+            speed: 1.0,
+        };
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(0.5));
+        let tile = world
+            .spawn((TileTextureIndex(0), animated, AnimationPaused::default()))
+            .id();
+        let halted = world
+            .spawn((TileTextureIndex(0), animated, AnimationPaused::on_frame(3)))
+            .id();
+
+        // Pausing without a frame fills in the one shown now, halfway through the loop.
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<AnimationPaused>(tile),
+            Some(&AnimationPaused::on_frame(2))
+        );
+        assert_eq!(
+            world.get::<AnimationPaused>(halted),
+            Some(&AnimationPaused::on_frame(3))
+        );
+
+        // Resuming the tile makes it be extracted again.
+        let last_changed = |world: &World, tile: Entity| {
+            world
+                .entity(tile)
+                .get_ref::<TileTextureIndex>()
+                .unwrap()
+                .last_changed()
+        };
+        let before = [tile, halted].map(|tile| last_changed(&world, tile));
+        world.entity_mut(tile).remove::<AnimationPaused>();
+        schedule.run(&mut world);
+        assert_ne!(last_changed(&world, tile), before[0]);
+        assert_eq!(last_changed(&world, halted), before[1]);
+    }
+}
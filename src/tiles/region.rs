@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+
+use crate::helpers::selection::TileRect;
+use crate::map::{TilemapId, TilemapSize};
+
+use super::{SignedTilePos, TilePos, TilePosOld, TileStorage};
+
+/// Tiles taken out of a rectangle of a tilemap with [`TileStorage::copy_region`] or
+/// [`TileStorage::cut_region`], to be put back with [`TileStorage::paste_region`].
+///
+/// The region owns detached tile entities, which hold every component of the tiles except their
+/// [`TilePos`], [`TilemapId`] and [`ChildOf`]. It does not change when the tilemap it was taken
+/// from is edited, so it can serve as the clipboard of a level editor or as an undo step, and it
+/// can be pasted any number of times, into any tilemap. Use [`TileRegion::despawn`] to free the
+/// tiles once the region is no longer needed.
+#[derive(Debug, Default)]
+pub struct TileRegion {
+    pub size: TilemapSize,
+    /// The detached tiles and their positions relative to the bottom left of the region.
+    tiles: Vec<(TilePos, Entity)>,
+}
+
+impl TileRegion {
+    /// Returns the detached tiles and their positions relative to the bottom left of the region.
+    pub fn tiles(&self) -> &[(TilePos, Entity)] {
+        &self.tiles
+    }
+
+    /// Returns `true` if the region holds no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Despawns the detached tiles of the region.
+    pub fn despawn(self, commands: &mut Commands) {
+        for (_, tile_entity) in self.tiles {
+            commands.entity(tile_entity).despawn();
+        }
+    }
+}
+
+impl TileStorage {
+    /// Returns the tiles stored in `rect` which lie within the storage, with their positions
+    /// relative to its origin.
+    fn region_tiles(&self, rect: TileRect) -> impl Iterator<Item = (TilePos, TilePos)> + '_ {
+        rect.iter()
+            .filter(|tile_pos| tile_pos.within_map_bounds(&self.size))
+            .filter(|tile_pos| self.get(tile_pos).is_some())
+            .map(move |tile_pos| {
+                let offset = TilePos::new(tile_pos.x - rect.origin.x, tile_pos.y - rect.origin.y);
+                (tile_pos, offset)
+            })
+    }
+
+    /// Copies the tiles in `rect` into a [`TileRegion`], cloning all of their components which
+    /// implement `Clone` or `Reflect`. The tilemap is left as it is.
+    ///
+    /// The parts of `rect` outside of the storage are empty in the region.
+    pub fn copy_region(&self, rect: TileRect, commands: &mut Commands) -> TileRegion {
+        let tiles = self
+            .region_tiles(rect)
+            .filter_map(|(tile_pos, offset)| {
+                let clone = commands
+                    .entity(self.get(&tile_pos)?)
+                    .clone_and_spawn_with_opt_out(|builder| {
+                        builder.deny::<(TilePos, TilePosOld, TilemapId, ChildOf)>();
+                    })
+                    .id();
+                Some((offset, clone))
+            })
+            .collect();
+        TileRegion {
+            size: rect.size,
+            tiles,
+        }
+    }
+
+    /// Moves the tiles in `rect` out of the tilemap into a [`TileRegion`], leaving their slots
+    /// empty.
+    ///
+    /// Unlike [`copy_region`](Self::copy_region) this keeps the tile entities themselves, only
+    /// detaching them from the tilemap.
+    pub fn cut_region(&mut self, rect: TileRect, commands: &mut Commands) -> TileRegion {
+        let positions = self.region_tiles(rect).collect::<Vec<_>>();
+        let tiles = positions
+            .into_iter()
+            .filter_map(|(tile_pos, offset)| {
+                let tile_entity = self.remove(&tile_pos)?;
+                commands
+                    .entity(tile_entity)
+                    .remove::<(TilePos, TilePosOld, TilemapId, ChildOf)>();
+                Some((offset, tile_entity))
+            })
+            .collect();
+        TileRegion {
+            size: rect.size,
+            tiles,
+        }
+    }
+
+    /// Spawns clones of the tiles of `region` with its bottom left at `origin`, and returns the
+    /// positions of the spawned tiles.
+    ///
+    /// Tiles which replace existing tiles despawn them, and empty cells of the region leave the
+    /// tilemap as it is, so to restore a region exactly, e.g. when undoing an edit,
+    /// [`clear_region`](Self::clear_region) its rectangle first. Tiles that do not fit in the
+    /// tilemap are not spawned, so the origin may lie outside of it. The region itself is left
+    /// untouched and can be pasted again.
+    pub fn paste_region(
+        &mut self,
+        origin: SignedTilePos,
+        region: &TileRegion,
+        tilemap_id: TilemapId,
+        commands: &mut Commands,
+    ) -> Vec<TilePos> {
+        let mut pasted = Vec::new();
+        for (offset, tile) in &region.tiles {
+            let Some(tile_pos) =
+                (origin + SignedTilePos::from(offset).0).as_tile_pos_given_map_size(&self.size)
+            else {
+                continue;
+            };
+            if let Some(previous) = self.get(&tile_pos) {
+                commands.entity(previous).despawn();
+            }
+            let tile_entity = commands
+                .entity(*tile)
+                .clone_and_spawn()
+                .insert((
+                    tile_pos,
+                    TilePosOld(tile_pos),
+                    tilemap_id,
+                    ChildOf(tilemap_id.0),
+                ))
+                .id();
+            self.set(&tile_pos, tile_entity);
+            pasted.push(tile_pos);
+        }
+        pasted
+    }
+
+    /// Despawns the tiles in `rect`, leaving their slots empty. The parts of `rect` outside of
+    /// the storage are ignored.
+    pub fn clear_region(&mut self, rect: TileRect, commands: &mut Commands) {
+        for tile_pos in rect.iter() {
+            if let Some(tile_entity) = self.checked_remove(&tile_pos) {
+                commands.entity(tile_entity).despawn();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{TileBundle, TileTextureIndex};
+    use bevy::ecs::world::CommandQueue;
+
+    #[derive(Component, Clone, Debug, PartialEq)]
+    struct Door(u32);
+
+    #[test]
+    fn regions_are_copied_pasted_and_cleared() {
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let mut storage = TileStorage::empty(TilemapSize::new(4, 4));
+        for (x, y) in [(0, 0), (1, 0), (1, 1)] {
+            let tile_pos = TilePos::new(x, y);
+            let tile_entity = world
+                .spawn((
+                    TileBundle {
+                        position: tile_pos,
+                        tilemap_id,
+                        texture_index: TileTextureIndex(x + 2 * y),
+                        ..Default::default()
+                    },
+                    Door(x),
+                    ChildOf(tilemap_id.0),
+                ))
+                .id();
+            storage.set(&tile_pos, tile_entity);
+        }
+        let rect = TileRect::new(TilePos::new(0, 0), TilemapSize::new(2, 2));
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let region = storage.copy_region(rect, &mut commands);
+        // Pasted over the edge, only the left column of the region fits.
+        let pasted =
+            storage.paste_region(SignedTilePos::new(3, 2), &region, tilemap_id, &mut commands);
+        queue.apply(&mut world);
+        assert_eq!(region.tiles().len(), 3);
+        assert_eq!(pasted, vec![TilePos::new(3, 2)]);
+        let pasted_tile = storage.get(&TilePos::new(3, 2)).unwrap();
+        assert_ne!(pasted_tile, storage.get(&TilePos::new(0, 0)).unwrap());
+        assert_eq!(world.get::<Door>(pasted_tile), Some(&Door(0)));
+        assert_eq!(world.get::<TilePos>(pasted_tile), Some(&TilePos::new(3, 2)));
+        assert_eq!(
+            world.get::<ChildOf>(pasted_tile).map(ChildOf::parent),
+            Some(tilemap_id.0)
+        );
+        let (_, detached) = region.tiles()[0];
+        assert!(world.get::<TilePos>(detached).is_none());
+
+        // Cutting moves the tiles out, and clearing despawns them.
+        let moved_tile = storage.get(&TilePos::new(1, 1)).unwrap();
+        let mut commands = Commands::new(&mut queue, &world);
+        let cut = storage.cut_region(
+            TileRect::new(TilePos::new(1, 1), TilemapSize::new(1, 1)),
+            &mut commands,
+        );
+        storage.clear_region(rect, &mut commands);
+        region.despawn(&mut commands);
+        queue.apply(&mut world);
+        assert_eq!(cut.tiles(), &[(TilePos::new(0, 0), moved_tile)]);
+        assert!(world.get::<TilemapId>(moved_tile).is_none());
+        assert_eq!(
+            storage.positions().collect::<Vec<_>>(),
+            [TilePos::new(3, 2)]
+        );
+        assert!(world.get_entity(detached).is_err());
+        assert_eq!(
+            world.get::<TileTextureIndex>(pasted_tile),
+            Some(&TileTextureIndex(0))
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    platform::collections::HashMap,
+    prelude::*,
+};
+
+use crate::map::TilemapSize;
+
+use super::{TilePos, TileStore};
+
+/// Stores tile entities in a hash map, for tilemaps where most positions hold no tile, e.g.
+/// scattered decals or huge worlds which are only filled around the player.
+///
+/// Unlike [`TileStorage`](super::TileStorage), its memory grows with the number of tiles rather
+/// than with the size of the tilemap. It implements the [`TileStore`] trait. A tilemap with
+/// a `SparseTileStorage` is rendered and edited through it, see [`tile_store`](super::tile_store),
+/// so the empty default `TileStorage` of the tilemap bundles can be left in place:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// fn spawn_decals(mut commands: Commands) {
+///     let size = TilemapSize::new(100_000, 100_000);
+///     let tilemap = commands.spawn_empty().id();
+///     let mut storage = SparseTileStorage::empty(size);
+///     for x in [12, 5_000, 90_000] {
+///         let tile_pos = TilePos::new(x, x / 2);
+///         let tile = commands
+///             .spawn((
+///                 TileBundle {
+///                     position: tile_pos,
+///                     tilemap_id: TilemapId(tilemap),
+///                     ..Default::default()
+///                 },
+///                 ChildOf(tilemap),
+///             ))
+///             .id();
+///         storage.set(&tile_pos, tile);
+///     }
+///     commands.entity(tilemap).insert((
+///         TilemapBundle {
+///             size,
+///             ..Default::default()
+///         },
+///         storage,
+///     ));
+/// }
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component, MapEntities)]
+#[component(map_entities)]
+pub struct SparseTileStorage {
+    tiles: HashMap<TilePos, Entity>,
+    pub size: TilemapSize,
+}
+
+impl MapEntities for SparseTileStorage {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for entity in self.tiles.values_mut() {
+            *entity = entity_mapper.get_mapped(*entity);
+        }
+    }
+}
+
+impl SparseTileStorage {
+    /// Creates a new tile storage that is empty.
+    pub fn empty(size: TilemapSize) -> Self {
+        Self {
+            tiles: HashMap::default(),
+            size,
+        }
+    }
+
+    /// Returns the number of stored tiles.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Gets the tile entity for the given tile position, if an entity is associated with it.
+    /// Positions outside of the tilemap have no tile.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        self.tiles.get(tile_pos).copied()
+    }
+
+    /// Sets a tile entity for the given tile position, replacing any entity already there.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map.
+    pub fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        assert!(
+            tile_pos.within_map_bounds(&self.size),
+            "{tile_pos:?} lies outside of a sparse tile storage of size {:?}",
+            self.size
+        );
+        self.tiles.insert(*tile_pos, tile_entity);
+    }
+
+    /// Sets a tile entity for the given tile position, if the tile position lies within the
+    /// underlying tile map's extents.
+    pub fn checked_set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        if tile_pos.within_map_bounds(&self.size) {
+            self.tiles.insert(*tile_pos, tile_entity);
+        }
+    }
+
+    /// Removes any stored `Entity` at the given tile position, and returns it.
+    pub fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        self.tiles.remove(tile_pos)
+    }
+
+    /// Returns an iterator over the stored entities and their positions, in no particular order.
+    pub fn iter_some(&self) -> impl Iterator<Item = (TilePos, Entity)> + '_ {
+        self.tiles
+            .iter()
+            .map(|(tile_pos, entity)| (*tile_pos, *entity))
+    }
+
+    /// Removes all stored `Entity`s, returning them in an iterator.
+    pub fn drain(&mut self) -> impl Iterator<Item = Entity> + use<'_> {
+        self.tiles.drain().map(|(_, entity)| entity)
+    }
+}
+
+impl TileStore for SparseTileStorage {
+    fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        SparseTileStorage::get(self, tile_pos)
+    }
+
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        SparseTileStorage::set(self, tile_pos, tile_entity);
+    }
+
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        SparseTileStorage::remove(self, tile_pos)
+    }
+
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_> {
+        Box::new(SparseTileStorage::iter_some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::filling::fill_tilemap_rect;
+    use crate::helpers::square_grid::neighbors::Neighbors;
+    use crate::map::TilemapId;
+    use bevy::ecs::world::CommandQueue;
+
+    #[test]
+    fn sparse_storages_work_with_the_helpers() {
+        let mut world = World::new();
+        let tilemap_id = TilemapId(world.spawn_empty().id());
+        let size = TilemapSize::new(1 << 20, 1 << 20);
+        let mut storage = SparseTileStorage::empty(size);
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let origin = TilePos::new(500_000, 300_000);
+        fill_tilemap_rect(
+            crate::tiles::TileTextureIndex(1),
+            origin,
+            TilemapSize::new(2, 1),
+            tilemap_id,
+            &mut commands,
+            &mut storage,
+        );
+        queue.apply(&mut world);
+
+        assert_eq!(storage.len(), 2);
+        let neighbors = Neighbors::get_square_neighboring_positions(&origin, &size, false);
+        let east = storage.get(&TilePos::new(origin.x + 1, origin.y));
+        assert!(east.is_some());
+        let entities = neighbors.entities(&storage);
+        assert_eq!((entities.east, entities.west), (east, None));
+        assert_eq!(storage.checked_get(&TilePos::new(1 << 20, 0)), None);
+        assert_eq!(
+            world.get::<TilePos>(storage.remove(&origin).unwrap()),
+            Some(&origin)
+        );
+        assert_eq!(storage.iter_some().count(), 1);
+    }
+}
@@ -3,20 +3,26 @@ use bevy::{
         entity::{EntityMapper, MapEntities},
         reflect::ReflectMapEntities,
     },
+    math::I64Vec2,
     prelude::*,
+    tasks::{ComputeTaskPool, ParallelSlice, ParallelSliceMut, TaskPool},
 };
 
-use crate::map::TilemapSize;
+use crate::map::{TilemapId, TilemapSize};
 
-use super::TilePos;
+use super::{LayerStore, LayeredTileStorage, SparseTileStorage, TileBundle, TilePos, TilePosOld};
 
 /// Used to store tile entities for fast look up.
 /// Tile entities are stored in a grid. The grid is always filled with None.
 #[derive(Component, Reflect, Default, Debug, Clone)]
 #[reflect(Component, MapEntities)]
+#[component(map_entities)]
 pub struct TileStorage {
     tiles: Vec<Option<Entity>>,
     pub size: TilemapSize,
+    /// Set by [`TileStorage::translate_all`] until the [`TilePos`] of the moved tiles are updated.
+    #[reflect(ignore)]
+    positions_stale: bool,
 }
 
 impl MapEntities for TileStorage {
@@ -33,9 +39,51 @@ impl TileStorage {
         Self {
             tiles: vec![None; size.count()],
             size,
+            positions_stale: false,
         }
     }
 
+    /// Creates a tile storage holding a tile for each position for which `f` returns a bundle.
+    ///
+    /// The tiles are spawned as children of the tilemap in a single batch, which is much faster
+    /// than spawning them one by one on maps with millions of tiles. The `position`,
+    /// `old_position` and `tilemap_id` of the returned bundles are filled in. See
+    /// [`TilemapCommands::spawn_tilemap_filled`](crate::commands::TilemapCommands::spawn_tilemap_filled)
+    /// to spawn the tilemap as well.
+    pub fn filled(
+        size: TilemapSize,
+        tilemap_id: TilemapId,
+        world: &mut World,
+        mut f: impl FnMut(TilePos) -> Option<TileBundle>,
+    ) -> Self {
+        let mut positions = Vec::new();
+        let mut tiles = Vec::new();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let tile_pos = TilePos::new(x, y);
+                let Some(tile) = f(tile_pos) else {
+                    continue;
+                };
+                positions.push(tile_pos);
+                tiles.push((
+                    TileBundle {
+                        position: tile_pos,
+                        old_position: TilePosOld(tile_pos),
+                        tilemap_id,
+                        ..tile
+                    },
+                    ChildOf(tilemap_id.0),
+                ));
+            }
+        }
+
+        let mut storage = Self::empty(size);
+        for (tile_pos, tile_entity) in positions.iter().zip(world.spawn_batch(tiles)) {
+            storage.set(tile_pos, tile_entity);
+        }
+        storage
+    }
+
     /// Gets a tile entity for the given tile position, if an entity is associated with that tile
     /// position.
     ///
@@ -86,6 +134,75 @@ impl TileStorage {
         self.tiles.iter_mut()
     }
 
+    /// Returns an iterator over every position in the grid, with the entity stored there.
+    pub fn iter_with_pos(&self) -> impl Iterator<Item = (TilePos, Option<Entity>)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (self.index_to_pos(index), *entity))
+    }
+
+    /// Returns an iterator over the stored entities and their positions, skipping empty slots.
+    pub fn iter_some(&self) -> impl Iterator<Item = (TilePos, Entity)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity)| Some((self.index_to_pos(index), (*entity)?)))
+    }
+
+    /// Returns an iterator over the positions which have an entity stored.
+    pub fn positions(&self) -> impl Iterator<Item = TilePos> + '_ {
+        self.iter_some().map(|(tile_pos, _)| tile_pos)
+    }
+
+    /// Calls `f` with every stored entity and its position, in parallel on the
+    /// [`ComputeTaskPool`].
+    pub fn par_for_each(&self, f: impl Fn(TilePos, Entity) + Send + Sync) {
+        self.par_map(f);
+    }
+
+    /// Maps every stored entity and its position with `f`, in parallel on the
+    /// [`ComputeTaskPool`], and returns the results in the order of [`iter_some`](Self::iter_some).
+    pub fn par_map<T: Send + 'static>(
+        &self,
+        f: impl Fn(TilePos, Entity) -> T + Send + Sync,
+    ) -> Vec<T> {
+        let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = self.tiles.len().div_ceil(task_pool.thread_num()).max(1);
+        self.tiles
+            .par_chunk_map(task_pool, batch_size, |batch, tiles| {
+                tiles
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, entity)| {
+                        let tile_pos = self.index_to_pos(batch * batch_size + index);
+                        Some(f(tile_pos, (*entity)?))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Calls `f` with batches of consecutive slots and the index of the first slot of each
+    /// batch, in parallel on the [`ComputeTaskPool`].
+    pub(crate) fn par_batches_mut(
+        &mut self,
+        f: impl Fn(usize, &mut [Option<Entity>]) + Send + Sync,
+    ) {
+        let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = self.tiles.len().div_ceil(task_pool.thread_num()).max(1);
+        self.tiles
+            .par_chunk_map_mut(task_pool, batch_size, |batch, tiles| {
+                f(batch * batch_size, tiles)
+            });
+    }
+
+    fn index_to_pos(&self, index: usize) -> TilePos {
+        TilePos::new(index as u32 % self.size.x, index as u32 / self.size.x)
+    }
+
     /// Removes any stored `Entity` at the given tile position, leaving `None` in its place and
     /// returning the `Entity`.
     ///
@@ -119,4 +236,400 @@ impl TileStorage {
     pub fn drain(&mut self) -> impl Iterator<Item = Entity> + use<'_> {
         self.tiles.iter_mut().filter_map(|opt| opt.take())
     }
+
+    /// Shifts every stored entity by `offset` slots, and returns the entities which fell off the
+    /// edge of the storage.
+    ///
+    /// This lets the storage act as a viewport which scrolls over a larger world: shift it, then
+    /// reuse or despawn the returned tiles and fill the slots which were uncovered. The
+    /// [`TilePos`] of the tiles which stayed in the storage are updated to match in
+    /// [`PostUpdate`], while the returned tiles keep their old position.
+    pub fn translate_all(&mut self, offset: IVec2) -> Vec<Entity> {
+        // Positions are shifted in `i64`, so offsets near the limits of `i32` can not overflow.
+        let size = I64Vec2::new(self.size.x as i64, self.size.y as i64);
+        let offset = offset.as_i64vec2();
+        let mut tiles = vec![None; self.tiles.len()];
+        let mut fallen_off = Vec::new();
+        for (index, entity) in self.tiles.iter_mut().enumerate() {
+            let Some(entity) = entity.take() else {
+                continue;
+            };
+            let pos = I64Vec2::new(index as i64 % size.x, index as i64 / size.x) + offset;
+            if pos.cmpge(I64Vec2::ZERO).all() && pos.cmplt(size).all() {
+                tiles[(pos.y * size.x + pos.x) as usize] = Some(entity);
+            } else {
+                fallen_off.push(entity);
+            }
+        }
+        self.tiles = tiles;
+        self.positions_stale |= offset != I64Vec2::ZERO;
+        fallen_off
+    }
+}
+
+/// Looks up the tile entities of a tilemap by their position.
+///
+/// Implemented by the dense [`TileStorage`], which has a slot for every position of the tilemap,
+/// and by the [`SparseTileStorage`], which only stores the positions holding a tile. The renderer
+/// and the helpers which take a `TileStore`, like the neighbor and filling helpers, work with
+/// either.
+pub trait TileStore {
+    /// Returns the size of the tilemap the storage covers.
+    fn size(&self) -> TilemapSize;
+
+    /// Gets the tile entity for the given tile position, if an entity is associated with it.
+    ///
+    /// May panic if the given `tile_pos` doesn't lie within the extents of the underlying tile
+    /// map.
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity>;
+
+    /// Sets the tile entity for the given tile position, replacing any entity already there.
+    ///
+    /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map.
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity);
+
+    /// Removes the entity stored at the given tile position, and returns it.
+    ///
+    /// May panic if the given `tile_pos` doesn't lie within the extents of the underlying tile
+    /// map.
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity>;
+
+    /// Returns an iterator over the stored entities and their positions.
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_>;
+
+    /// Gets the tile entity for the given tile position, or `None` if the position doesn't lie
+    /// within the extents of the underlying tile map.
+    fn checked_get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        tile_pos
+            .within_map_bounds(&self.size())
+            .then(|| self.get(tile_pos))
+            .flatten()
+    }
+
+    /// Sets the tile entity for the given tile position, if the position lies within the extents
+    /// of the underlying tile map.
+    fn checked_set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        if tile_pos.within_map_bounds(&self.size()) {
+            self.set(tile_pos, tile_entity);
+        }
+    }
+
+    /// Removes the entity stored at the given tile position, if the position lies within the
+    /// extents of the underlying tile map.
+    fn checked_remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        tile_pos
+            .within_map_bounds(&self.size())
+            .then(|| self.remove(tile_pos))
+            .flatten()
+    }
+}
+
+impl TileStore for TileStorage {
+    fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        TileStorage::get(self, tile_pos)
+    }
+
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        TileStorage::set(self, tile_pos, tile_entity);
+    }
+
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        TileStorage::remove(self, tile_pos)
+    }
+
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_> {
+        Box::new(TileStorage::iter_some(self))
+    }
+}
+
+/// Lets the helpers be called with a storage borrowed from a query, e.g. `&mut storage` for a
+/// `Mut<TileStorage>`, which deref coercion does not reach through a generic parameter.
+impl<S: TileStore + ?Sized> TileStore for &mut S {
+    fn size(&self) -> TilemapSize {
+        (**self).size()
+    }
+
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        (**self).get(tile_pos)
+    }
+
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        (**self).set(tile_pos, tile_entity);
+    }
+
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        (**self).remove(tile_pos)
+    }
+
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_> {
+        (**self).iter_some()
+    }
+}
+
+impl<S: TileStore> TileStore for Mut<'_, S> {
+    fn size(&self) -> TilemapSize {
+        (**self).size()
+    }
+
+    fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        (**self).get(tile_pos)
+    }
+
+    fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
+        (**self).set(tile_pos, tile_entity);
+    }
+
+    fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        (**self).remove(tile_pos)
+    }
+
+    fn iter_some(&self) -> Box<dyn Iterator<Item = (TilePos, Entity)> + '_> {
+        (**self).iter_some()
+    }
+}
+
+/// Returns the storage the tiles of `tilemap` are kept in: its [`SparseTileStorage`] or the
+/// bottom layer of its [`LayeredTileStorage`] if it has one, otherwise its [`TileStorage`].
+///
+/// The [tile commands](crate::commands), [`stamp_tiles`](crate::helpers::stamp::stamp_tiles) and
+/// `TilemapSave::capture` go through this, so they work with every kind of storage.
+pub fn tile_store(world: &World, tilemap: Entity) -> Option<&dyn TileStore> {
+    let entity = world.get_entity(tilemap).ok()?;
+    if let Some(layered) = entity.get::<LayeredTileStorage>() {
+        return layered
+            .layers()
+            .next()
+            .map(|storage| storage as &dyn TileStore);
+    }
+    if let Some(sparse) = entity.get::<SparseTileStorage>() {
+        return Some(sparse);
+    }
+    entity
+        .get::<TileStorage>()
+        .map(|storage| storage as &dyn TileStore)
+}
+
+/// Returns the storage the tiles of `tilemap` are kept in, like [`tile_store`], to change it.
+///
+/// The bottom layer of a [`LayeredTileStorage`] is changed through a [`LayerStore`], so tiles
+/// moved into or out of it are drawn in their new layer.
+pub fn tile_store_mut(world: &mut World, tilemap: Entity) -> Option<Box<dyn TileStore + '_>> {
+    let entity = world.get_entity_mut(tilemap).ok()?;
+    if entity.contains::<LayeredTileStorage>() {
+        let layered = entity.into_mut::<LayeredTileStorage>()?;
+        return (layered.layer_count() > 0)
+            .then(|| Box::new(LayerStore::new(layered, 0)) as Box<dyn TileStore>);
+    }
+    if entity.contains::<SparseTileStorage>() {
+        return entity
+            .into_mut::<SparseTileStorage>()
+            .map(|sparse| Box::new(sparse) as Box<dyn TileStore>);
+    }
+    entity
+        .into_mut::<TileStorage>()
+        .map(|storage| Box::new(storage) as Box<dyn TileStore>)
+}
+
+/// Moves the tiles of storages shifted with [`TileStorage::translate_all`] to their new slot.
+pub(crate) fn sync_translated_tile_positions(
+    mut storages: Query<&mut TileStorage, Changed<TileStorage>>,
+    mut tiles: Query<&mut TilePos>,
+) {
+    for mut storage in storages.iter_mut() {
+        if !storage.positions_stale {
+            continue;
+        }
+        storage.bypass_change_detection().positions_stale = false;
+        for (index, entity) in storage.tiles.iter().enumerate() {
+            let Some(entity) = entity else {
+                continue;
+            };
+            if let Ok(mut tile_pos) = tiles.get_mut(*entity) {
+                tile_pos.set_if_neq(TilePos::new(
+                    index as u32 % storage.size.x,
+                    index as u32 / storage.size.x,
+                ));
+            }
+        }
+    }
+}
+
+/// Gives tilemaps spawned without a [`TileStorage`], e.g. from a scene which left it out, one
+/// built from the [`TilePos`] and [`TilemapId`] of their tiles. Tilemaps with a
+/// [`SparseTileStorage`] or a [`LayeredTileStorage`] are left as they are.
+pub(crate) fn rebuild_missing_tile_storages(
+    mut commands: Commands,
+    tilemaps: Query<
+        (Entity, &TilemapSize),
+        (
+            Added<TilemapSize>,
+            Without<TileStorage>,
+            Without<SparseTileStorage>,
+            Without<LayeredTileStorage>,
+        ),
+    >,
+    tiles: Query<(Entity, &TilePos, &TilemapId)>,
+) {
+    if tilemaps.is_empty() {
+        return;
+    }
+    let mut storages = tilemaps
+        .iter()
+        .map(|(tilemap_entity, size)| (tilemap_entity, TileStorage::empty(*size)))
+        .collect::<bevy::platform::collections::HashMap<_, _>>();
+    for (tile_entity, tile_pos, tilemap_id) in tiles.iter() {
+        if let Some(storage) = storages.get_mut(&tilemap_id.0) {
+            storage.checked_set(tile_pos, tile_entity);
+        }
+    }
+    for (tilemap_entity, storage) in storages {
+        commands.entity(tilemap_entity).insert(storage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::entity::EntityHashMap;
+    use bevy::ecs::relationship::RelationshipHookMode;
+
+    /// Copies entities from one world to another through reflection, remapping their entities,
+    /// the way scenes are spawned.
+    fn copy_reflected(from: &World, to: &mut World, entities: &[Entity]) -> EntityHashMap<Entity> {
+        let registry = from.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let mut entity_map = entities
+            .iter()
+            .map(|entity| (*entity, to.spawn_empty().id()))
+            .collect::<EntityHashMap<_>>();
+        for entity in entities {
+            let source = from.entity(*entity);
+            for component_id in source.archetype().components() {
+                let Some(reflect_component) = from
+                    .components()
+                    .get_info(*component_id)
+                    .and_then(|info| registry.get(info.type_id()?))
+                    .and_then(|registration| registration.data::<ReflectComponent>())
+                else {
+                    continue;
+                };
+                let value = reflect_component.reflect(source).unwrap().to_dynamic();
+                let target = entity_map[entity];
+                reflect_component.apply_or_insert_mapped(
+                    &mut to.entity_mut(target),
+                    value.as_partial_reflect(),
+                    &registry,
+                    &mut entity_map,
+                    RelationshipHookMode::Run,
+                );
+            }
+        }
+        entity_map
+    }
+
+    #[test]
+    fn tilemaps_survive_reflection_with_remapped_entities() {
+        let mut from = World::new();
+        from.init_resource::<AppTypeRegistry>();
+        {
+            let mut registry = from.resource::<AppTypeRegistry>().write();
+            registry.register::<TileStorage>();
+            registry.register::<TilemapSize>();
+            registry.register::<TilemapId>();
+            registry.register::<TilePos>();
+        }
+        let size = TilemapSize::new(2, 2);
+        let tilemap = from.spawn(size).id();
+        let mut storage = TileStorage::empty(size);
+        let tiles = [TilePos::new(0, 0), TilePos::new(1, 1)].map(|tile_pos| {
+            let tile = from.spawn((tile_pos, TilemapId(tilemap))).id();
+            storage.set(&tile_pos, tile);
+            tile
+        });
+        from.entity_mut(tilemap).insert(storage);
+
+        let mut to = World::new();
+        // Occupy the source entities' ids, so that unmapped entities would be noticed.
+        for _ in 0..8 {
+            to.spawn_empty();
+        }
+        let entity_map = copy_reflected(&from, &mut to, &[tilemap, tiles[0], tiles[1]]);
+        let storage = to.get::<TileStorage>(entity_map[&tilemap]).unwrap();
+        assert_eq!(
+            storage.get(&TilePos::new(1, 1)),
+            Some(entity_map[&tiles[1]])
+        );
+        assert_eq!(
+            to.get::<TilemapId>(entity_map[&tiles[0]]),
+            Some(&TilemapId(entity_map[&tilemap]))
+        );
+
+        // A tilemap loaded without its storage gets it back from its tiles.
+        let mut schedule = Schedule::default();
+        schedule.add_systems(rebuild_missing_tile_storages);
+        from.entity_mut(tilemap).remove::<TileStorage>();
+        let entity_map = copy_reflected(&from, &mut to, &[tilemap, tiles[0], tiles[1]]);
+        schedule.run(&mut to);
+        let storage = to.get::<TileStorage>(entity_map[&tilemap]).unwrap();
+        assert_eq!(
+            storage.get(&TilePos::new(0, 0)),
+            Some(entity_map[&tiles[0]])
+        );
+        assert_eq!(storage.iter_some().count(), 2);
+    }
+
+    #[test]
+    fn translate_all_shifts_entities_and_returns_the_ones_off_the_edge() {
+        let mut storage = TileStorage::empty(TilemapSize::new(3, 2));
+        let a = Entity::from_raw_u32(1).unwrap();
+        let b = Entity::from_raw_u32(2).unwrap();
+        storage.set(&TilePos::new(0, 0), a);
+        storage.set(&TilePos::new(2, 1), b);
+
+        let fallen_off = storage.translate_all(IVec2::new(1, 0));
+        assert_eq!(fallen_off, vec![b]);
+        assert_eq!(storage.get(&TilePos::new(1, 0)), Some(a));
+        assert_eq!(storage.get(&TilePos::new(0, 0)), None);
+        assert_eq!(storage.iter().flatten().count(), 1);
+
+        // Offsets past the edge of `i32` push every tile off instead of overflowing.
+        let fallen_off = storage.translate_all(IVec2::new(i32::MAX, 0));
+        assert_eq!(fallen_off, vec![a]);
+        storage.set(&TilePos::new(0, 1), b);
+        assert_eq!(storage.translate_all(IVec2::new(0, i32::MIN)), vec![b]);
+        assert_eq!(storage.iter().flatten().count(), 0);
+    }
+
+    #[test]
+    fn iterators_yield_positions() {
+        let mut storage = TileStorage::empty(TilemapSize::new(40, 30));
+        let entities = (1..=100)
+            .map(|i| {
+                (
+                    TilePos::new(i % 40, i / 7),
+                    Entity::from_raw_u32(i).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (tile_pos, entity) in &entities {
+            storage.set(tile_pos, *entity);
+        }
+
+        assert_eq!(storage.iter_with_pos().count(), 40 * 30);
+        assert_eq!(
+            storage.iter_with_pos().nth(41),
+            Some((TilePos::new(1, 1), storage.get(&TilePos::new(1, 1))))
+        );
+        let some = storage.iter_some().collect::<Vec<_>>();
+        assert!(
+            some.iter()
+                .all(|(tile_pos, entity)| storage.get(tile_pos) == Some(*entity))
+        );
+        assert_eq!(storage.positions().count(), some.len());
+        assert_eq!(storage.par_map(|tile_pos, entity| (tile_pos, entity)), some);
+    }
 }
@@ -0,0 +1,81 @@
+use bevy::ecs::entity::EntityHashSet;
+use bevy::log::warn;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use super::{AnimatedTile, TilePos, TileTextureIndex};
+use crate::map::{TilemapId, TilemapSpacing, TilemapTexture, TilemapTileSize};
+
+/// Warns about tiles whose [`TileTextureIndex`] or [`AnimatedTile`] frames lie past the end of
+/// their tilemap's tileset, which would otherwise render garbage.
+///
+/// Tiles are checked when they are spawned or their texture changes. Tiles of tilemaps whose
+/// images are still loading are checked once the images are loaded.
+#[allow(clippy::type_complexity)]
+pub(crate) fn validate_tile_texture_indices(
+    changed_tiles: Query<
+        (
+            Entity,
+            &TilePos,
+            &TileTextureIndex,
+            &TilemapId,
+            Option<&AnimatedTile>,
+        ),
+        Or<(Changed<TileTextureIndex>, Changed<AnimatedTile>)>,
+    >,
+    all_tiles: Query<(
+        Entity,
+        &TilePos,
+        &TileTextureIndex,
+        &TilemapId,
+        Option<&AnimatedTile>,
+    )>,
+    tilemaps: Query<(&TilemapTexture, &TilemapTileSize, &TilemapSpacing)>,
+    images: Option<Res<Assets<Image>>>,
+    mut pending_tilemaps: Local<EntityHashSet>,
+) {
+    let Some(images) = images else {
+        return;
+    };
+    let mut tile_counts = HashMap::<Entity, Option<u32>>::default();
+    let mut tile_count = |tilemap: Entity| {
+        *tile_counts.entry(tilemap).or_insert_with(|| {
+            let (texture, tile_size, spacing) = tilemaps.get(tilemap).ok()?;
+            texture.tile_count(tile_size, spacing, &images)
+        })
+    };
+
+    let mut loaded_tilemaps = EntityHashSet::default();
+    pending_tilemaps.retain(|tilemap| {
+        if tilemaps.contains(*tilemap) && tile_count(*tilemap).is_none() {
+            return true;
+        }
+        loaded_tilemaps.insert(*tilemap);
+        false
+    });
+    let tiles = changed_tiles.iter().chain(
+        all_tiles
+            .iter()
+            .filter(|(.., tilemap_id, _)| loaded_tilemaps.contains(&tilemap_id.0)),
+    );
+
+    for (entity, tile_pos, texture_index, tilemap_id, animated) in tiles {
+        let Some(count) = tile_count(tilemap_id.0) else {
+            if tilemaps.contains(tilemap_id.0) {
+                pending_tilemaps.insert(tilemap_id.0);
+            }
+            continue;
+        };
+        let highest_index = match animated {
+            Some(animated) => animated.end.saturating_sub(1).max(animated.start),
+            None => texture_index.0,
+        };
+        if highest_index >= count {
+            warn!(
+                "Tile {entity} at {tile_pos:?} of tilemap {} uses texture index {highest_index}, \
+                but the tileset only has {count} tiles.",
+                tilemap_id.0
+            );
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Renders small tilemaps headlessly and compares them with the golden images in
+//! `tests/goldens`. Without a GPU adapter, e.g. on CI runners without a software renderer, the
+//! test is skipped.
+//!
+//! After an intended change to the rendering, regenerate the golden images with
+//!
+//! ```text
+//! UPDATE_GOLDENS=1 cargo test --features snapshot --test golden
+//! ```
+//!
+//! which rewrites the images in `tests/goldens/*.pam` that are missing or no longer match. Look
+//! over the changed images, e.g. with ImageMagick's `display`, before committing them.
+//!
+//! Bevy renders with only one app per process, so this runs without the test harness and
+//! renders every snapshot in turn with the same app.
+
+use bevy::prelude::*;
+use bevy::winit::WinitPlugin;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_ecs_tilemap::snapshot::{
+    SnapshotImage, capture, snapshot_app_with, spawn_snapshot_camera,
+};
+
+const TARGET_SIZE: UVec2 = UVec2::new(256, 256);
+
+struct MapCase {
+    texture: &'static str,
+    tile_size: TilemapTileSize,
+    grid_size: TilemapGridSize,
+    map_type: TilemapType,
+}
+
+const SQUARE: MapCase = MapCase {
+    texture: "bw-tile-square.png",
+    tile_size: TilemapTileSize { x: 50.0, y: 50.0 },
+    grid_size: TilemapGridSize { x: 50.0, y: 50.0 },
+    map_type: TilemapType::Square,
+};
+
+/// Renders the tilemap spawned by `spawn_tilemap` after `frames` updates, then despawns it and
+/// its tiles.
+fn snapshot(
+    app: &mut App,
+    frames: u32,
+    spawn_tilemap: impl FnOnce(&mut World) -> Entity,
+) -> SnapshotImage {
+    let world = app.world_mut();
+    // Animations depend on the elapsed time, so every snapshot starts at zero.
+    world.insert_resource(Time::<Real>::default());
+    world.insert_resource(Time::<Virtual>::default());
+    world.insert_resource(Time::<()>::default());
+    let camera = spawn_snapshot_camera(world, TARGET_SIZE);
+    let tilemap_entity = spawn_tilemap(world);
+
+    let image = capture(app, camera, frames).expect("the frame was not read back");
+    let world = app.world_mut();
+    let tiles = world
+        .query::<(Entity, &TilemapId)>()
+        .iter(world)
+        .filter(|(_, tilemap_id)| tilemap_id.0 == tilemap_entity)
+        .map(|(tile_entity, _)| tile_entity)
+        .collect::<Vec<_>>();
+    for entity in tiles.into_iter().chain([tilemap_entity, camera]) {
+        world.despawn(entity);
+    }
+    image
+}
+
+/// Renders a 3x3 tilemap, calling `customize_tile` on each tile entity, after `frames` updates.
+fn render(
+    app: &mut App,
+    case: &MapCase,
+    anchor: TilemapAnchor,
+    frames: u32,
+    customize_tile: impl Fn(TilePos, &mut EntityWorldMut),
+) -> SnapshotImage {
+    snapshot(app, frames, |world| {
+        spawn_tilemap(world, case, anchor, customize_tile)
+    })
+}
+
+fn spawn_tilemap(
+    world: &mut World,
+    case: &MapCase,
+    anchor: TilemapAnchor,
+    customize_tile: impl Fn(TilePos, &mut EntityWorldMut),
+) -> Entity {
+    let texture = world.resource::<AssetServer>().load(case.texture);
+    let size = TilemapSize::new(3, 3);
+    let tilemap_entity = world.spawn_empty().id();
+    let mut storage = TileStorage::empty(size);
+    for x in 0..size.x {
+        for y in 0..size.y {
+            let tile_pos = TilePos::new(x, y);
+            let mut tile = world.spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(tilemap_entity),
+                ..Default::default()
+            });
+            customize_tile(tile_pos, &mut tile);
+            storage.set(&tile_pos, tile.id());
+        }
+    }
+    world.entity_mut(tilemap_entity).insert(TilemapBundle {
+        grid_size: case.grid_size,
+        map_type: case.map_type,
+        size,
+        storage,
+        texture: TilemapTexture::Single(texture),
+        tile_size: case.tile_size,
+        anchor,
+        ..Default::default()
+    });
+    tilemap_entity
+}
+
+fn golden(name: &str) -> String {
+    format!("{}/tests/goldens/{name}.pam", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn map_types(app: &mut App) {
+    let cases = [
+        ("square", SQUARE),
+        (
+            "isometric_diamond",
+            MapCase {
+                texture: "bw-tile-iso.png",
+                tile_size: TilemapTileSize { x: 100.0, y: 50.0 },
+                grid_size: TilemapGridSize { x: 100.0, y: 50.0 },
+                map_type: TilemapType::Isometric(IsoCoordSystem::Diamond),
+            },
+        ),
+        (
+            "isometric_staggered",
+            MapCase {
+                texture: "bw-tile-iso.png",
+                tile_size: TilemapTileSize { x: 100.0, y: 50.0 },
+                grid_size: TilemapGridSize { x: 100.0, y: 50.0 },
+                map_type: TilemapType::Isometric(IsoCoordSystem::Staggered),
+            },
+        ),
+        (
+            "hex_row",
+            MapCase {
+                texture: "bw-tile-hex-row.png",
+                tile_size: TilemapTileSize { x: 50.0, y: 58.0 },
+                grid_size: TilemapGridSize { x: 50.0, y: 58.0 },
+                map_type: TilemapType::Hexagon(HexCoordSystem::Row),
+            },
+        ),
+        (
+            "hex_column_odd",
+            MapCase {
+                texture: "bw-tile-hex-col.png",
+                tile_size: TilemapTileSize { x: 58.0, y: 50.0 },
+                grid_size: TilemapGridSize { x: 58.0, y: 50.0 },
+                map_type: TilemapType::Hexagon(HexCoordSystem::ColumnOdd),
+            },
+        ),
+    ];
+    for (name, case) in cases {
+        render(app, &case, TilemapAnchor::Center, 20, |_, _| {})
+            .assert_matches_golden(golden(&format!("map_type_{name}")), 1);
+    }
+}
+
+fn anchors(app: &mut App) {
+    for (name, anchor) in [
+        ("center", TilemapAnchor::Center),
+        ("bottom_left", TilemapAnchor::BottomLeft),
+        ("top_right", TilemapAnchor::TopRight),
+        ("custom", TilemapAnchor::Custom(Vec2::new(0.25, -0.5))),
+    ] {
+        render(app, &SQUARE, anchor, 20, |_, _| {})
+            .assert_matches_golden(golden(&format!("anchor_{name}")), 1);
+    }
+}
+
+fn flips(app: &mut App) {
+    let case = MapCase {
+        texture: "tiles.png",
+        tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+        grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+        map_type: TilemapType::Square,
+    };
+    // Each tile of the 3x3 map gets a different combination of flips.
+    render(app, &case, TilemapAnchor::Center, 20, |tile_pos, tile| {
+        let flags = tile_pos.y * 3 + tile_pos.x;
+        tile.insert((
+            TileTextureIndex(flags % 6),
+            TileFlip {
+                x: flags & 1 != 0,
+                y: flags & 2 != 0,
+                d: flags & 4 != 0,
+            },
+        ));
+    })
+    .assert_matches_golden(golden("flips"), 1);
+}
+
+fn animation_frames(app: &mut App) {
+    let case = MapCase {
+        texture: "tiles.png",
+        tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+        grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+        map_type: TilemapType::Square,
+    };
+    // Each update advances the time by 100ms and the animation loops through its four frames each
+    // second, so it shows a different frame after each number of updates.
+    for frames in [21, 24, 26] {
+        render(app, &case, TilemapAnchor::Center, frames, |_, tile| {
+            tile.insert(AnimatedTile {
+                start: 0,
+                end: 4,
+                speed: 1.0,
+            });
+        })
+        .assert_matches_golden(golden(&format!("animation_{frames}")), 1);
+    }
+}
+
+fn layered_storage(app: &mut App) {
+    // The ground fills the bottom layer, and the middle tile is covered by the top layer.
+    snapshot(app, 20, |world| {
+        let texture = world.resource::<AssetServer>().load("tiles.png");
+        let size = TilemapSize::new(3, 3);
+        let tilemap_entity = world.spawn_empty().id();
+        let mut storage = LayeredTileStorage::empty(size, 2);
+        let mut spawn_tile = |tile_pos: TilePos, layer: usize, texture_index: u32| {
+            let tile = world
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: TileTextureIndex(texture_index),
+                    ..Default::default()
+                })
+                .id();
+            storage.set(&tile_pos, layer, tile);
+        };
+        // Spawned first, so it would be overwritten if both layers shared a chunk.
+        spawn_tile(TilePos::new(1, 1), 1, 3);
+        for x in 0..size.x {
+            for y in 0..size.y {
+                spawn_tile(TilePos::new(x, y), 0, 0);
+            }
+        }
+        world.entity_mut(tilemap_entity).insert((
+            TilemapBundle {
+                grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+                size,
+                texture: TilemapTexture::Single(texture),
+                tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+                anchor: TilemapAnchor::Center,
+                ..Default::default()
+            },
+            storage,
+        ));
+        tilemap_entity
+    })
+    .assert_matches_golden(golden("layered_storage"), 1);
+}
+
+/// Returns whether wgpu finds an adapter to render with, which the render plugin panics without.
+fn gpu_available() -> bool {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+    bevy::tasks::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_ok()
+}
+
+fn main() {
+    if !gpu_available() {
+        eprintln!("skipping the golden images, as no GPU adapter is available");
+        return;
+    }
+    let mut app = snapshot_app_with(DefaultPlugins.build().disable::<WinitPlugin>());
+    map_types(&mut app);
+    anchors(&mut app);
+    flips(&mut app);
+    animation_frames(&mut app);
+    layered_storage(&mut app);
+}